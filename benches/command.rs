@@ -0,0 +1,76 @@
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rredis::{
+    Backend, BulkString, Command, CommandExecutor, RespArray, RespDecodeV2, RespEncode, RespFrame,
+};
+use std::hint::black_box;
+
+/// Encode a command the way a client sends it.
+fn request(args: &[&str]) -> Vec<u8> {
+    let args: Vec<RespFrame> = args.iter().map(|a| BulkString::new(*a).into()).collect();
+    RespFrame::from(RespArray::new(args)).encode()
+}
+
+/// Run every request of the buffer through parse, dispatch, execute and encode.
+fn serve(buf: &mut BytesMut, backend: &Backend) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    while !buf.is_empty() {
+        let frame = RespFrame::decode(buf)?;
+        let reply = match Command::try_from(frame) {
+            Ok(cmd) => cmd.execute(backend),
+            Err(e) => e.into(),
+        };
+        out.extend_from_slice(&reply.encode());
+    }
+    Ok(out)
+}
+
+fn bench_requests(c: &mut Criterion, name: &str, backend: &Backend, data: &[u8]) {
+    let buf = BytesMut::from(data);
+    c.bench_function(name, |b| {
+        b.iter_batched(
+            || buf.clone(),
+            |mut buf| serve(black_box(&mut buf), backend),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let backend = Backend::new();
+    backend.set("hit".to_string(), b"value".to_vec());
+    for i in 0..100 {
+        backend
+            .hset(
+                "hash".to_string(),
+                format!("field{}", i),
+                BulkString::new(format!("value{}", i)).into(),
+            )
+            .unwrap();
+    }
+
+    bench_requests(c, "get_hit", &backend, &request(&["get", "hit"]));
+    bench_requests(c, "get_miss", &backend, &request(&["get", "miss"]));
+    bench_requests(c, "set", &backend, &request(&["set", "key", "value"]));
+    bench_requests(
+        c,
+        "hgetall_100_fields",
+        &backend,
+        &request(&["hgetall", "hash"]),
+    );
+
+    let pipeline: Vec<u8> = (0..100)
+        .flat_map(|i| {
+            let key = format!("key{}", i % 10);
+            if i % 2 == 0 {
+                request(&["set", &key, "value"])
+            } else {
+                request(&["get", &key])
+            }
+        })
+        .collect();
+    bench_requests(c, "pipeline_100_get_set", &backend, &pipeline);
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+use std::process::Command;
+
+// Embed the commit the server is built from, reported by --version and INFO server.
+fn main() {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    // outside of a git checkout, e.g. a crate downloaded from a registry.
+    let sha = git(&["rev-parse", "--short=8", "HEAD"]).unwrap_or_else(|| "00000000".to_string());
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    println!("cargo:rustc-env=RREDIS_GIT_SHA={}", sha);
+    println!("cargo:rustc-env=RREDIS_GIT_DIRTY={}", u8::from(dirty));
+    for path in [".git/HEAD", ".git/index"] {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+}
@@ -0,0 +1,10 @@
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use rredis::{RespDecode, RespFrame};
+
+fuzz_target!(|data: &[u8]| {
+    let mut buf = BytesMut::from(data);
+    let _ = <RespFrame as RespDecode>::decode(&mut buf);
+});
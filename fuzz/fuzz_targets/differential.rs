@@ -0,0 +1,20 @@
+#![no_main]
+
+//! v1 and v2 must agree on valid frames: whatever v1 decodes, v2 decodes
+//! the encoding of it back to the same frame.
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use rredis::{RespDecode, RespDecodeV2, RespEncode, RespFrame};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(frame) = <RespFrame as RespDecode>::decode(&mut BytesMut::from(data)) else {
+        return;
+    };
+    let encoded = frame.encode();
+    let mut buf = BytesMut::from(&encoded[..]);
+    let decoded = <RespFrame as RespDecodeV2>::decode(&mut buf)
+        .unwrap_or_else(|e| panic!("v2 refuses \"{}\": {}", encoded.escape_ascii(), e));
+    assert!(buf.is_empty(), "v2 left {:?}", buf);
+    assert_eq!(decoded.encode(), encoded);
+});
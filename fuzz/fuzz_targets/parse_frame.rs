@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rredis::{parse_frame, parse_frame_length};
+
+fuzz_target!(|data: &[u8]| {
+    let mut input = data;
+    if parse_frame(&mut input).is_ok() {
+        // the length of a frame which parses is the bytes parsing consumed.
+        assert_eq!(parse_frame_length(data), Ok(data.len() - input.len()));
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rredis::parse_frame_length;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(len) = parse_frame_length(data) {
+        assert!(len <= data.len(), "length {} beyond the input", len);
+    }
+});
@@ -0,0 +1,181 @@
+//! `#[derive(ToResp, FromResp)]`, re-exported by `rredis` next to the traits
+//! they implement, which document the layout of the frames.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    ext::IdentExt, parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, Generics,
+    Index, LitByteStr, LitStr, Member,
+};
+
+#[proc_macro_derive(ToResp, attributes(resp))]
+pub fn derive_to_resp(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_resp(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromResp, attributes(resp))]
+pub fn derive_from_resp(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_resp(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// A field and its `#[resp(...)]` options.
+struct Field {
+    member: Member,
+    /// The key of the field in a map, its name unless renamed.
+    key: String,
+    /// Converted through serde and JSON rather than its own `ToResp` or `FromResp`.
+    json: bool,
+}
+
+fn expand_to_resp(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let (named, fields) = fields(input)?;
+    let values: Vec<_> = fields
+        .iter()
+        .map(|field| {
+            let member = &field.member;
+            if field.json {
+                quote!(::rredis::convert::json_to_resp(&self.#member))
+            } else {
+                quote!(::rredis::ToResp::to_resp(&self.#member))
+            }
+        })
+        .collect();
+    let body = if named {
+        let keys = fields.iter().map(|field| &field.key);
+        quote! {
+            let mut map = ::rredis::RespMap::new();
+            #(map.insert(::rredis::BulkString::new(#keys), #values);)*
+            map.into()
+        }
+    } else if let [value] = values.as_slice() {
+        value.clone()
+    } else {
+        quote!(::rredis::RespArray::new(::std::vec![#(#values),*]).into())
+    };
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, quote!(::rredis::ToResp));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rredis::ToResp for #name #ty_generics #where_clause {
+            fn to_resp(&self) -> ::rredis::RespFrame {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_from_resp(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let (named, fields) = fields(input)?;
+    let vars: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("field_{}", i))
+        .collect();
+    let values = fields.iter().zip(&vars).map(|(field, var)| {
+        let key = &field.key;
+        if field.json {
+            quote!(::rredis::convert::json_field(#var, #key)?)
+        } else {
+            quote!(::rredis::convert::field(#var, #key)?)
+        }
+    });
+    let members = fields.iter().map(|field| &field.member);
+    let body = if named {
+        let keys = fields
+            .iter()
+            .map(|field| LitByteStr::new(field.key.as_bytes(), input.ident.span()));
+        quote! {
+            #(let mut #vars = ::std::option::Option::None;)*
+            for (key, value) in ::rredis::convert::entries(frame)? {
+                match ::rredis::convert::key(&key) {
+                    #(::std::option::Option::Some(#keys) => #vars = ::std::option::Option::Some(value),)*
+                    _ => {}
+                }
+            }
+        }
+    } else if let [var] = vars.as_slice() {
+        quote!(let #var = ::std::option::Option::Some(frame);)
+    } else {
+        let len = fields.len();
+        quote! {
+            let mut items = ::rredis::convert::tuple(frame, #len)?.into_iter();
+            #(let #vars = items.next();)*
+        }
+    };
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, quote!(::rredis::FromResp));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rredis::FromResp for #name #ty_generics #where_clause {
+            fn from_resp(
+                frame: ::rredis::RespFrame,
+            ) -> ::std::result::Result<Self, ::rredis::err::RespError> {
+                #body
+                ::std::result::Result::Ok(Self { #(#members: #values),* })
+            }
+        }
+    })
+}
+
+/// The fields of a struct, and whether they are named.
+fn fields(input: &DeriveInput) -> syn::Result<(bool, Vec<Field>)> {
+    let fields = match &input.data {
+        Data::Struct(data) if !matches!(data.fields, Fields::Unit) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "only structs with fields can be converted to and from frames",
+            ))
+        }
+    };
+    let named = matches!(fields, Fields::Named(_));
+    let fields = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let (member, key) = match &field.ident {
+                Some(ident) => (Member::Named(ident.clone()), ident.unraw().to_string()),
+                None => (Member::Unnamed(Index::from(i)), i.to_string()),
+            };
+            let mut res = Field {
+                member,
+                key,
+                json: false,
+            };
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("resp"))
+            {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        res.key = meta.value()?.parse::<LitStr>()?.value();
+                    } else if meta.path.is_ident("json") {
+                        res.json = true;
+                    } else {
+                        return Err(meta.error("expected `rename = \"...\"` or `json`"));
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(res)
+        })
+        .collect::<syn::Result<_>>()?;
+    Ok((named, fields))
+}
+
+/// The generics with every type parameter bound by the derived trait.
+fn with_bound(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
@@ -0,0 +1,437 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    f64::consts::LN_2,
+    hash::{Hash, Hasher},
+    mem::size_of,
+};
+
+use dashmap::mapref::entry::Entry;
+use thiserror::Error;
+
+use crate::{Backend, KeyType, WrongType};
+
+/// Error rate of the filters created by BF.ADD and BF.MADD on a missing key.
+pub const DEFAULT_ERROR_RATE: f64 = 0.01;
+/// Capacity of the filters created by BF.ADD and BF.MADD on a missing key.
+pub const DEFAULT_CAPACITY: u64 = 100;
+/// Growth factor of the capacity of each new layer of a scaling filter.
+pub const DEFAULT_EXPANSION: u32 = 2;
+/// Each new layer has a tighter error rate, so the error rate of the whole filter stays bounded.
+const TIGHTENING_RATIO: f64 = 0.5;
+
+/// A scalable Bloom filter, like the ones of RedisBloom.
+///
+/// Items are added to the last layer; once it holds `capacity` items a new layer
+/// `expansion` times bigger is added, unless the filter is non-scaling.
+/// An item may exist if any layer may contain it.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    layers: Vec<Layer>,
+    error_rate: f64,
+    capacity: u64,
+    /// `None` for a non-scaling filter.
+    expansion: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+struct Layer {
+    bits: Vec<u64>,
+    num_bits: u64,
+    hashes: u32,
+    capacity: u64,
+    items: u64,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("non scaling filter is full")]
+pub struct FilterFull;
+
+/// A chunk of BF.LOADCHUNK which doesn't come from [`BloomFilter::chunks`].
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("received bad data")]
+pub struct BadChunk;
+
+impl BloomFilter {
+    /// A filter holding `capacity` items with a false positive rate of at most `error_rate`,
+    /// which must be in `(0, 1)`.
+    pub fn new(error_rate: f64, capacity: u64, expansion: Option<u32>) -> Self {
+        Self {
+            layers: vec![Layer::new(error_rate, capacity)],
+            error_rate,
+            capacity,
+            expansion,
+        }
+    }
+
+    /// Add the item, returns whether it was not already (probably) present.
+    pub fn add(&mut self, item: &[u8]) -> Result<bool, FilterFull> {
+        let (h1, h2) = item_hashes(item);
+        if self.layers.iter().any(|layer| layer.contains(h1, h2)) {
+            return Ok(false);
+        }
+        let last = self.layers.last().expect("a filter has at least one layer");
+        if last.items >= last.capacity {
+            let expansion = self.expansion.ok_or(FilterFull)?;
+            let n = self.layers.len() as i32;
+            let capacity = last.capacity.saturating_mul(expansion as u64);
+            let error_rate = self.error_rate * TIGHTENING_RATIO.powi(n);
+            self.layers.push(Layer::new(error_rate, capacity));
+        }
+        self.layers
+            .last_mut()
+            .expect("a filter has at least one layer")
+            .insert(h1, h2);
+        Ok(true)
+    }
+
+    pub fn contains(&self, item: &[u8]) -> bool {
+        let (h1, h2) = item_hashes(item);
+        self.layers.iter().any(|layer| layer.contains(h1, h2))
+    }
+
+    /// Number of items added to the filter.
+    pub fn len(&self) -> u64 {
+        self.layers.iter().map(|layer| layer.items).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        self.error_rate
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Bytes used by the bit arrays of the layers.
+    pub fn size(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|layer| layer.bits.len() * size_of::<u64>())
+            .sum()
+    }
+
+    /// The chunks of BF.SCANDUMP, from which BF.LOADCHUNK rebuilds the filter, with
+    /// their iterators: first the parameters of the filter and the items of each layer,
+    /// from which the layers are sized, then the bits of each layer.
+    pub fn chunks(&self) -> Vec<(u64, Vec<u8>)> {
+        let mut header = Vec::with_capacity(20 + 8 * self.layers.len());
+        header.extend_from_slice(&self.error_rate.to_le_bytes());
+        header.extend_from_slice(&self.capacity.to_le_bytes());
+        header.extend_from_slice(&self.expansion.unwrap_or(0).to_le_bytes());
+        for layer in &self.layers {
+            header.extend_from_slice(&layer.items.to_le_bytes());
+        }
+        let mut chunks = vec![(1, header)];
+        for (i, layer) in self.layers.iter().enumerate() {
+            let bits = layer
+                .bits
+                .iter()
+                .flat_map(|word| word.to_le_bytes())
+                .collect();
+            chunks.push((i as u64 + 2, bits));
+        }
+        chunks
+    }
+
+    /// An empty filter with the layers described by the first chunk of [`BloomFilter::chunks`],
+    /// which may not use more than `max_size` bytes.
+    fn from_header(header: &[u8], max_size: usize) -> Result<Self, BadChunk> {
+        if header.len() < 28 || !(header.len() - 20).is_multiple_of(8) {
+            return Err(BadChunk);
+        }
+        let u64_at = |i: usize| u64::from_le_bytes(header[i..i + 8].try_into().unwrap());
+        let error_rate = f64::from_bits(u64_at(0));
+        let capacity = u64_at(8);
+        let expansion = u32::from_le_bytes(header[16..20].try_into().unwrap());
+        let items: Vec<u64> = (20..header.len()).step_by(8).map(u64_at).collect();
+        if !(error_rate > 0.0 && error_rate < 1.0) || capacity == 0 {
+            return Err(BadChunk);
+        }
+        // the layers are sized like the filter sized them when it grew, checked before
+        // any is allocated.
+        let expansion = (expansion > 0).then_some(expansion);
+        let mut sizes = Vec::with_capacity(items.len());
+        let mut size = 0usize;
+        for n in 0..items.len() {
+            let (layer_error_rate, layer_capacity) = match (n, expansion) {
+                (0, _) => (error_rate, capacity),
+                (_, Some(expansion)) => (
+                    error_rate * TIGHTENING_RATIO.powi(n as i32),
+                    capacity.saturating_mul((expansion as u64).saturating_pow(n as u32)),
+                ),
+                (_, None) => return Err(BadChunk),
+            };
+            let (num_bits, _) = Layer::dimensions(layer_error_rate, layer_capacity);
+            size = size.saturating_add(num_bits.div_ceil(64) as usize * size_of::<u64>());
+            if size > max_size {
+                return Err(BadChunk);
+            }
+            sizes.push((layer_error_rate, layer_capacity));
+        }
+        let mut filter = Self {
+            layers: Vec::with_capacity(sizes.len()),
+            error_rate,
+            capacity,
+            expansion,
+        };
+        for (n, (layer_error_rate, layer_capacity)) in sizes.into_iter().enumerate() {
+            filter
+                .layers
+                .push(Layer::new(layer_error_rate, layer_capacity));
+            let layer_items = &items[n];
+            let layer = filter.layers.last_mut().expect("a layer was pushed");
+            // only the last layer isn't full.
+            let full = *layer_items == layer.capacity;
+            if *layer_items > layer.capacity || (n + 1 < items.len() && !full) {
+                return Err(BadChunk);
+            }
+            layer.items = *layer_items;
+        }
+        Ok(filter)
+    }
+
+    /// Fill a layer with the bits of its chunk.
+    fn load_layer(&mut self, layer: usize, bits: &[u8]) -> Result<(), BadChunk> {
+        let layer = self.layers.get_mut(layer).ok_or(BadChunk)?;
+        if bits.len() != layer.bits.len() * size_of::<u64>() {
+            return Err(BadChunk);
+        }
+        for (word, bytes) in layer.bits.iter_mut().zip(bits.chunks_exact(8)) {
+            *word = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Ok(())
+    }
+}
+
+impl Layer {
+    fn new(error_rate: f64, capacity: u64) -> Self {
+        let (num_bits, hashes) = Self::dimensions(error_rate, capacity);
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            hashes,
+            capacity,
+            items: 0,
+        }
+    }
+
+    /// The optimal number of bits and of hash functions for the error rate.
+    fn dimensions(error_rate: f64, capacity: u64) -> (u64, u32) {
+        let bits_per_item = -error_rate.ln() / (LN_2 * LN_2);
+        let num_bits = ((capacity as f64 * bits_per_item).ceil() as u64).max(64);
+        let hashes = ((bits_per_item * LN_2).ceil() as u32).max(1);
+        (num_bits, hashes)
+    }
+
+    /// The bits of an item, derived from two hashes as `h1 + i * h2`.
+    fn positions(&self, h1: u64, h2: u64) -> impl Iterator<Item = u64> + '_ {
+        (0..self.hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+
+    fn contains(&self, h1: u64, h2: u64) -> bool {
+        self.positions(h1, h2)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    fn insert(&mut self, h1: u64, h2: u64) {
+        let positions: Vec<u64> = self.positions(h1, h2).collect();
+        for bit in positions {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self.items += 1;
+    }
+}
+
+fn item_hashes(item: &[u8]) -> (u64, u64) {
+    let mut hasher = DefaultHasher::new();
+    item.hash(&mut hasher);
+    let h1 = hasher.finish();
+    h1.hash(&mut hasher);
+    // an odd step visits distinct bits for every hash function.
+    (h1, hasher.finish() | 1)
+}
+
+impl Backend {
+    /// Create an empty filter at `key`, returns false if the key already exists.
+    pub fn bf_reserve(&self, key: &str, filter: BloomFilter) -> Result<bool, WrongType> {
+        self.check_type(key, KeyType::Bloom)?;
+        Ok(match self.bloom.entry(key.to_string()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(filter);
+                self.key_changed(key);
+                true
+            }
+        })
+    }
+
+    /// Add the items to the filter at `key`, created with the default parameters if missing.
+    /// For each item returns whether it was newly added, or an error if the filter is full.
+    pub fn bf_add(
+        &self,
+        key: &str,
+        items: &[Vec<u8>],
+    ) -> Result<Vec<Result<bool, FilterFull>>, WrongType> {
+        self.check_type(key, KeyType::Bloom)?;
+        let mut filter = self.bloom.entry(key.to_string()).or_insert_with(|| {
+            BloomFilter::new(
+                DEFAULT_ERROR_RATE,
+                DEFAULT_CAPACITY,
+                Some(DEFAULT_EXPANSION),
+            )
+        });
+        let res: Vec<_> = items.iter().map(|item| filter.add(item)).collect();
+        drop(filter);
+        if res.iter().any(|r| matches!(r, Ok(true))) {
+            self.key_changed(key);
+        }
+        Ok(res)
+    }
+
+    /// The chunk of the filter at `key` following the one of `iterator`, 0 to start,
+    /// like BF.SCANDUMP. `None` once every chunk was returned, or if the key is missing.
+    pub fn bf_scandump(&self, key: &str, iterator: u64) -> Option<(u64, Vec<u8>)> {
+        let filter = self.bloom.get(key)?;
+        filter
+            .chunks()
+            .into_iter()
+            .find(|(chunk, _)| *chunk > iterator)
+    }
+
+    /// Load a chunk of [`BloomFilter::chunks`] into the filter at `key`, like BF.LOADCHUNK:
+    /// the first chunk replaces the filter with an empty one, the others fill its layers.
+    pub fn bf_loadchunk(
+        &self,
+        key: &str,
+        iterator: u64,
+        data: &[u8],
+    ) -> Result<Result<(), BadChunk>, WrongType> {
+        self.check_type(key, KeyType::Bloom)?;
+        Ok(self.load_chunk(key, iterator, data))
+    }
+
+    fn load_chunk(&self, key: &str, iterator: u64, data: &[u8]) -> Result<(), BadChunk> {
+        match iterator {
+            0 => return Err(BadChunk),
+            1 => {
+                let filter = BloomFilter::from_header(data, self.encoding.max_string_size)?;
+                self.bloom.insert(key.to_string(), filter);
+            }
+            n => {
+                let mut filter = self.bloom.get_mut(key).ok_or(BadChunk)?;
+                filter.load_layer((n - 2) as usize, data)?;
+            }
+        }
+        self.key_changed(key);
+        Ok(())
+    }
+
+    /// Whether each item may have been added to the filter at `key`.
+    pub fn bf_exists(&self, key: &str, items: &[Vec<u8>]) -> Vec<bool> {
+        match self.bloom.get(key) {
+            Some(filter) => items.iter().map(|item| filter.contains(item)).collect(),
+            None => vec![false; items.len()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(i: u64) -> Vec<u8> {
+        format!("item:{}", i).into_bytes()
+    }
+
+    #[test]
+    fn test_bloom_filter_has_no_false_negatives() {
+        let mut filter = BloomFilter::new(0.01, 1000, None);
+        for i in 0..1000 {
+            filter.add(&item(i)).unwrap();
+        }
+        assert!((0..1000).all(|i| filter.contains(&item(i))));
+        assert_eq!(filter.add(&item(0)), Ok(false));
+
+        let false_positives = (1000..11000).filter(|i| filter.contains(&item(*i))).count();
+        assert!(false_positives < 200, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_bloom_filter_scaling() {
+        let mut filter = BloomFilter::new(0.01, 10, None);
+        let added = (0..20).filter_map(|i| filter.add(&item(i)).ok()).count();
+        assert!((10..20).contains(&added));
+        assert_eq!(filter.add(&item(100)), Err(FilterFull));
+
+        let mut filter = BloomFilter::new(0.01, 10, Some(2));
+        for i in 0..100 {
+            filter.add(&item(i)).unwrap();
+        }
+        assert!((0..100).all(|i| filter.contains(&item(i))));
+        assert!(filter.layers.len() > 1);
+        assert!(filter.len() > 90);
+    }
+
+    #[test]
+    fn test_bloom_filter_chunks() {
+        let backend = Backend::new();
+        backend
+            .bf_reserve("bf", BloomFilter::new(0.01, 10, Some(2)))
+            .unwrap();
+        let items: Vec<_> = (0..50).map(item).collect();
+        backend.bf_add("bf", &items).unwrap();
+
+        let mut iterator = 0;
+        while let Some((next, data)) = backend.bf_scandump("bf", iterator) {
+            backend.bf_loadchunk("copy", next, &data).unwrap().unwrap();
+            iterator = next;
+        }
+        let copy = backend.bloom.get("copy").unwrap().clone();
+        assert_eq!(copy.chunks(), backend.bloom.get("bf").unwrap().chunks());
+        assert_eq!(backend.bf_exists("copy", &items), vec![true; 50]);
+        assert_eq!(copy.len(), backend.bloom.get("bf").unwrap().len());
+
+        assert_eq!(backend.bf_loadchunk("x", 1, b"short"), Ok(Err(BadChunk)));
+        // layers too large for the bulk strings of their bits are refused unallocated.
+        let mut huge = backend.bloom.get("bf").unwrap().chunks().remove(0).1;
+        huge.extend_from_slice(&[0xff; 8 * 70]);
+        assert_eq!(backend.bf_loadchunk("x", 1, &huge), Ok(Err(BadChunk)));
+        assert_eq!(backend.bf_loadchunk("x", 2, &[0; 8]), Ok(Err(BadChunk)));
+        // the bits of a layer must fill it exactly.
+        assert_eq!(backend.bf_loadchunk("copy", 2, &[0; 7]), Ok(Err(BadChunk)));
+        assert_eq!(
+            backend.bf_loadchunk("copy", 100, &[0; 8]),
+            Ok(Err(BadChunk))
+        );
+    }
+
+    #[test]
+    fn test_bf_backend() {
+        let backend = Backend::new();
+        assert_eq!(backend.bf_exists("bf", &[item(1)]), vec![false]);
+        assert_eq!(
+            backend.bf_add("bf", &[item(1), item(2), item(1)]),
+            Ok(vec![Ok(true), Ok(true), Ok(false)])
+        );
+        assert_eq!(
+            backend.bf_exists("bf", &[item(1), item(3)]),
+            vec![true, false]
+        );
+        assert_eq!(
+            backend.bf_reserve("bf", BloomFilter::new(0.1, 10, None)),
+            Ok(false)
+        );
+        assert_eq!(
+            backend.bf_reserve("other", BloomFilter::new(0.1, 10, None)),
+            Ok(true)
+        );
+        assert_eq!(
+            backend.bloom.get("bf").unwrap().capacity(),
+            DEFAULT_CAPACITY
+        );
+    }
+}
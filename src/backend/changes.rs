@@ -0,0 +1,144 @@
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use crate::Backend;
+
+/// Number of changes a watcher may fall behind before missing some.
+const CHANGE_CAPACITY: usize = 4096;
+
+/// What happened to a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The key was written, its value created or modified.
+    Set,
+    /// The key was removed, or its last element was.
+    Del,
+    /// Fields of the hash at the key reached their deadline and were removed.
+    Expire,
+}
+
+/// A mutation of the keyspace, reported by [`Backend::watch_changes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChange {
+    pub kind: ChangeKind,
+    pub key: String,
+}
+
+/// The stream of the changes of a backend, for in-process watchers.
+#[derive(Debug)]
+pub struct ChangeFeed {
+    tx: Sender<KeyChange>,
+}
+
+impl Default for ChangeFeed {
+    fn default() -> Self {
+        Self {
+            tx: broadcast::channel(CHANGE_CAPACITY).0,
+        }
+    }
+}
+
+impl Backend {
+    /// Subscribe to the changes of the keyspace, from now on.
+    ///
+    /// A watcher falling more than 4096 changes behind misses the oldest ones and
+    /// is told so by a `Lagged` error. Changes are sent after the mutation is done,
+    /// concurrent mutations of a key may be reported in any order.
+    pub fn watch_changes(&self) -> Receiver<KeyChange> {
+        self.changes.tx.subscribe()
+    }
+
+    /// Report that the key was written or removed, to the clients caching it, the
+    /// transactions watching it and the watchers.
+    pub(crate) fn key_changed(&self, key: &str) {
+        self.invalidate(key);
+        self.touch_watched(key);
+        // whether the key still exists is only looked up if someone is watching.
+        if self.changes.tx.receiver_count() > 0 {
+            let kind = if self.exists(key) {
+                ChangeKind::Set
+            } else {
+                ChangeKind::Del
+            };
+            self.publish_change(kind, key);
+        }
+    }
+
+    /// Report that fields of the hash at the key expired.
+    pub(crate) fn key_expired(&self, key: &str) {
+        self.invalidate(key);
+        self.touch_watched(key);
+        if self.changes.tx.receiver_count() > 0 {
+            self.publish_change(ChangeKind::Expire, key);
+        }
+    }
+
+    fn publish_change(&self, kind: ChangeKind, key: &str) {
+        // there may be no watcher left since the check, the change is then dropped.
+        let _ = self.changes.tx.send(KeyChange {
+            kind,
+            key: key.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    use crate::{BulkString, ExpireCondition, ListEnd, RespFrame};
+
+    use super::*;
+
+    fn change(kind: ChangeKind, key: &str) -> KeyChange {
+        KeyChange {
+            kind,
+            key: key.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_watch_changes() {
+        let backend = Backend::new();
+        backend.set("before".to_string(), b"v".to_vec());
+        let mut rx = backend.watch_changes();
+
+        backend.set("s".to_string(), b"v".to_vec());
+        backend
+            .push("l", vec![BulkString::new("x")], ListEnd::Tail)
+            .unwrap();
+        backend.pop("l", 1, ListEnd::Head);
+        backend.remove_key("s");
+        backend.rename("before", "after");
+        assert_eq!(rx.try_recv(), Ok(change(ChangeKind::Set, "s")));
+        assert_eq!(rx.try_recv(), Ok(change(ChangeKind::Set, "l")));
+        assert_eq!(rx.try_recv(), Ok(change(ChangeKind::Del, "l")));
+        assert_eq!(rx.try_recv(), Ok(change(ChangeKind::Del, "s")));
+        assert_eq!(rx.try_recv(), Ok(change(ChangeKind::Del, "before")));
+        assert_eq!(rx.try_recv(), Ok(change(ChangeKind::Set, "after")));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        // reads and no-op writes are not changes.
+        backend.get("after");
+        backend.remove_key("missing");
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_watch_expired_fields() {
+        let backend = Backend::new();
+        backend
+            .hset("h".to_string(), "f".to_string(), RespFrame::Integer(1))
+            .unwrap();
+        backend.hexpire(
+            "h",
+            &["f".to_string()],
+            Instant::now() + Duration::from_millis(1),
+            ExpireCondition::Always,
+        );
+        let mut rx = backend.watch_changes();
+        backend.expire_fields("h", Instant::now() + Duration::from_secs(1));
+        assert_eq!(rx.try_recv(), Ok(change(ChangeKind::Expire, "h")));
+    }
+}
@@ -0,0 +1,145 @@
+use std::io::{self, Write};
+
+use crate::{RespFrame, SimpleString};
+
+/// Bytes of commands covered by each checksum of a snapshot file, the last block
+/// of a truncated file is the one which can't be verified.
+pub(crate) const CHECKSUM_BLOCK_SIZE: usize = 64 * 1024;
+
+/// The checksum frames are `+CRC64 <16 hex digits>\r\n` simple strings.
+const CHECKSUM_PREFIX: &str = "CRC64 ";
+
+/// CRC-64/Jones, the checksum of RDB files, with the reflected polynomial.
+const CRC64_POLY: u64 = 0x95ac_9329_ac4b_c9b5;
+
+const CRC64_TABLE: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ CRC64_POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub(crate) fn crc64(mut crc: u64, data: &[u8]) -> u64 {
+    for &byte in data {
+        crc = CRC64_TABLE[((crc ^ byte as u64) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+/// Writes RESP encoded commands in blocks of about [`CHECKSUM_BLOCK_SIZE`] bytes,
+/// each followed by a frame holding the CRC64 of its commands.
+///
+/// The file starts with the checksum of an empty block, so a loader knows from the
+/// first bytes that the last block must be followed by its checksum too: a file
+/// truncated at a command boundary is still detected.
+pub(crate) struct BlockWriter<W> {
+    inner: W,
+    crc: u64,
+    len: usize,
+}
+
+impl<W: Write> BlockWriter<W> {
+    pub(crate) fn new(inner: W) -> io::Result<Self> {
+        let mut writer = Self {
+            inner,
+            crc: 0,
+            len: 0,
+        };
+        writer.write_checksum()?;
+        Ok(writer)
+    }
+
+    pub(crate) fn write_command(&mut self, command: &[u8]) -> io::Result<()> {
+        self.inner.write_all(command)?;
+        self.crc = crc64(self.crc, command);
+        self.len += command.len();
+        if self.len >= CHECKSUM_BLOCK_SIZE {
+            self.write_checksum()?;
+        }
+        Ok(())
+    }
+
+    /// Write the checksum of the last block, returns the writer once flushed.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        if self.len > 0 {
+            self.write_checksum()?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_checksum(&mut self) -> io::Result<()> {
+        self.inner.write_all(&checksum_frame(self.crc))?;
+        self.crc = 0;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+/// The encoded frame holding a checksum.
+pub(crate) fn checksum_frame(crc: u64) -> Vec<u8> {
+    format!("+{}{:016x}\r\n", CHECKSUM_PREFIX, crc).into_bytes()
+}
+
+/// The checksum held by a checksum frame, `None` for any other frame.
+pub(crate) fn checksum_of(frame: &RespFrame) -> Option<u64> {
+    let RespFrame::SimpleString(SimpleString(s)) = frame else {
+        return None;
+    };
+    let hex = s.strip_prefix(CHECKSUM_PREFIX)?;
+    u64::from_str_radix(hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use crate::{BulkString, RespArray, RespDecode, RespEncode};
+
+    use super::*;
+
+    #[test]
+    fn test_crc64_jones() {
+        assert_eq!(crc64(0, b"123456789"), 0xe9c6_d914_c4b8_d9ca);
+    }
+
+    #[test]
+    fn test_block_writer() -> io::Result<()> {
+        let command = RespArray::new(vec![
+            BulkString::new(vec![b'x'; CHECKSUM_BLOCK_SIZE / 2]).into()
+        ])
+        .encode();
+        let mut writer = BlockWriter::new(Vec::new())?;
+        for _ in 0..3 {
+            writer.write_command(&command)?;
+        }
+        let mut buf = BytesMut::from(&writer.finish()?[..]);
+
+        // the empty block, then blocks of two commands and one.
+        let mut frames = Vec::new();
+        while !buf.is_empty() {
+            let frame = RespFrame::decode(&mut buf).unwrap();
+            frames.push(checksum_of(&frame));
+        }
+        let one = crc64(0, &command);
+        let two = crc64(one, &command);
+        assert_eq!(
+            frames,
+            vec![Some(0), None, None, Some(two), None, Some(one)]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use std::{fmt::Write, time::Instant};
+
+use dashmap::DashMap;
+
+use crate::Backend;
+
+/// The connections of the server, by client id.
+#[derive(Debug, Default)]
+pub struct ClientRegistry {
+    clients: DashMap<u64, ConnectedClient>,
+}
+
+/// What the server knows about a connection, reported by CLIENT INFO.
+#[derive(Debug, Clone)]
+pub struct ConnectedClient {
+    pub id: u64,
+    /// Address of the client, `ip:port`.
+    pub addr: String,
+    pub name: String,
+    pub created: Instant,
+    /// When the client last sent a command.
+    pub last_interaction: Instant,
+    /// Shard channels the client is subscribed to.
+    pub shard_subscriptions: usize,
+    pub tracking: bool,
+    /// Version of the protocol spoken by the connection.
+    pub resp: u8,
+    pub lib_name: String,
+}
+
+impl ConnectedClient {
+    pub fn new(id: u64, addr: impl Into<String>) -> Self {
+        let now = Instant::now();
+        Self {
+            id,
+            addr: addr.into(),
+            name: String::new(),
+            created: now,
+            last_interaction: now,
+            shard_subscriptions: 0,
+            tracking: false,
+            resp: 2,
+            lib_name: String::new(),
+        }
+    }
+
+    /// The single-line description of CLIENT INFO and CLIENT LIST, newline terminated.
+    pub fn describe(&self) -> String {
+        let mut flags = String::new();
+        if self.shard_subscriptions > 0 {
+            flags.push('P');
+        }
+        if self.tracking {
+            flags.push('t');
+        }
+        if flags.is_empty() {
+            flags.push('N');
+        }
+        let mut line = String::new();
+        let _ = writeln!(
+            line,
+            "id={} addr={} name={} age={} idle={} flags={} db=0 sub=0 psub=0 ssub={} resp={} lib-name={}",
+            self.id,
+            self.addr,
+            self.name,
+            self.created.elapsed().as_secs(),
+            self.last_interaction.elapsed().as_secs(),
+            flags,
+            self.shard_subscriptions,
+            self.resp,
+            self.lib_name,
+        );
+        line
+    }
+}
+
+impl Backend {
+    /// Add a connection to the registry, replacing any client with the same id.
+    pub fn register_client(&self, client: ConnectedClient) {
+        self.clients.clients.insert(client.id, client);
+    }
+
+    pub fn unregister_client(&self, id: u64) {
+        self.clients.clients.remove(&id);
+    }
+
+    /// Update the registered state of a connection, if it is registered.
+    pub fn update_client(&self, id: u64, f: impl FnOnce(&mut ConnectedClient)) {
+        if let Some(mut client) = self.clients.clients.get_mut(&id) {
+            f(&mut client);
+        }
+    }
+
+    pub fn client_info(&self, id: u64) -> Option<ConnectedClient> {
+        self.clients.clients.get(&id).map(|client| client.clone())
+    }
+
+    /// Number of connected clients.
+    pub fn clients_len(&self) -> usize {
+        self.clients.clients.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_registry() {
+        let backend = Backend::new();
+        backend.register_client(ConnectedClient::new(7, "127.0.0.1:5000"));
+        assert_eq!(backend.clients_len(), 1);
+        assert_eq!(
+            backend.client_info(7).unwrap().describe(),
+            "id=7 addr=127.0.0.1:5000 name= age=0 idle=0 flags=N db=0 sub=0 psub=0 ssub=0 resp=2 lib-name=\n"
+        );
+
+        backend.update_client(7, |client| {
+            client.shard_subscriptions = 2;
+            client.tracking = true;
+        });
+        let line = backend.client_info(7).unwrap().describe();
+        assert!(line.contains(" flags=Pt "));
+        assert!(line.contains(" ssub=2 "));
+
+        backend.unregister_client(7);
+        assert!(backend.client_info(7).is_none());
+        assert_eq!(backend.clients_len(), 0);
+    }
+}
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use super::{
+    intern::{self, StringValue},
+    tier::SpilledValue,
+};
+use crate::Backend;
+
+/// A string value as stored in the backend.
+#[derive(Debug)]
+pub enum StoredString {
+    /// The value itself, possibly shared with the keys holding the same value.
+    Plain(StringValue),
+    /// An LZ4 block of the value, prefixed with its length.
+    Compressed(Arc<[u8]>),
+    /// The value, or its LZ4 block, written to the disk tier.
+    Spilled(SpilledValue),
+}
+
+impl Default for StoredString {
+    fn default() -> Self {
+        StoredString::Plain(StringValue::default())
+    }
+}
+
+impl StoredString {
+    /// Length of the value, whatever its stored form.
+    pub fn len(&self) -> usize {
+        match self {
+            StoredString::Plain(value) => value.len(),
+            StoredString::Compressed(block) => {
+                let (len, _) =
+                    lz4_flex::block::uncompressed_size(block).expect("stored LZ4 blocks are valid");
+                len
+            }
+            StoredString::Spilled(spilled) => spilled.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes of memory held by the stored form of the value, 0 once spilled.
+    /// A plain value counts the room reserved for it to grow.
+    pub fn allocated_size(&self) -> usize {
+        match self {
+            StoredString::Plain(value) => value.capacity(),
+            StoredString::Compressed(block) => block.len(),
+            StoredString::Spilled(_) => 0,
+        }
+    }
+
+    /// The value for a reply: a handle on a plain value, or a decompressed copy.
+    pub fn to_bytes(&self) -> Bytes {
+        match self {
+            StoredString::Plain(value) => intern::share(value),
+            StoredString::Compressed(block) => Bytes::from(decompress(block)),
+            StoredString::Spilled(spilled) => spilled.load().to_bytes(),
+        }
+    }
+
+    /// Take ownership of the value to modify it.
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            StoredString::Plain(value) => intern::unshare(value),
+            StoredString::Compressed(block) => decompress(&block),
+            StoredString::Spilled(spilled) => spilled.load().into_vec(),
+        }
+    }
+
+    /// Whether both are the same stored value, not just equal values.
+    pub(crate) fn same_as(&self, other: &StoredString) -> bool {
+        match (self, other) {
+            (StoredString::Plain(a), StoredString::Plain(b)) => Arc::ptr_eq(a, b),
+            (StoredString::Compressed(a), StoredString::Compressed(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+fn decompress(block: &[u8]) -> Vec<u8> {
+    lz4_flex::decompress_size_prepended(block).expect("stored LZ4 blocks are valid")
+}
+
+impl Backend {
+    /// The stored form of a value written by SET: compressed if it is large enough
+    /// and compresses, else shared with identical values.
+    pub(crate) fn store_string(&self, value: Vec<u8>) -> StoredString {
+        self.compress(&value)
+            .unwrap_or_else(|| StoredString::Plain(self.values.intern(value)))
+    }
+
+    /// Like [`Backend::store_string`] for values rewritten in place,
+    /// which only share small integers.
+    pub(crate) fn restore_string(&self, value: Vec<u8>) -> StoredString {
+        self.compress(&value)
+            .unwrap_or_else(|| StoredString::Plain(self.values.intern_integer(value)))
+    }
+
+    fn compress(&self, value: &[u8]) -> Option<StoredString> {
+        let threshold = self.encoding.string_compress_threshold?;
+        if value.len() < threshold {
+            return None;
+        }
+        let block = lz4_flex::compress_prepend_size(value);
+        // incompressible values are kept as is, saving the decompression on reads.
+        (block.len() < value.len()).then(|| StoredString::Compressed(block.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::EncodingConfig;
+
+    use super::*;
+
+    fn backend(threshold: Option<usize>) -> Backend {
+        Backend::with_encoding(EncodingConfig {
+            string_compress_threshold: threshold,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_large_values_are_compressed() {
+        let backend = backend(Some(64));
+        let value = b"abcd".repeat(100);
+        backend.set("big".to_string(), value.clone());
+        backend.set("small".to_string(), b"abcd".to_vec());
+        assert!(matches!(
+            *backend.map.get("big").unwrap(),
+            StoredString::Compressed(_)
+        ));
+        assert!(backend.map.get("big").unwrap().allocated_size() < value.len());
+        assert_eq!(backend.map.get("big").unwrap().len(), value.len());
+        assert!(matches!(
+            *backend.map.get("small").unwrap(),
+            StoredString::Plain(_)
+        ));
+        assert_eq!(backend.get("big"), Some(Bytes::from(value.clone())));
+
+        backend
+            .update("big", |v| v.as_mut().unwrap().extend_from_slice(b"abcd"))
+            .unwrap();
+        assert_eq!(backend.get("big").map(|v| v.len()), Some(value.len() + 4));
+        let stored = backend.map.get("big").map(|v| v.allocated_size()).unwrap();
+        assert_eq!(backend.memory_usage("big", 0), Some("big".len() + stored));
+    }
+
+    #[test]
+    fn test_compression_is_disabled_by_default() {
+        let backend = backend(None);
+        backend.set("big".to_string(), b"abcd".repeat(100));
+        assert!(matches!(
+            *backend.map.get("big").unwrap(),
+            StoredString::Plain(_)
+        ));
+    }
+}
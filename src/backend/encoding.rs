@@ -0,0 +1,199 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{Backend, StoredString};
+
+/// How a value is represented in memory, as reported by OBJECT ENCODING.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Int,
+    Embstr,
+    Raw,
+    Listpack,
+    Intset,
+    Hashtable,
+    Skiplist,
+    Quicklist,
+}
+
+/// Internals of a value, as reported by DEBUG OBJECT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectInternals {
+    /// Address of the value in memory.
+    pub address: usize,
+    /// Number of keys and pools holding the value, strings can be shared.
+    pub refcount: usize,
+    pub encoding: Encoding,
+    /// Length of the commands recreating the key, 0 if it can't be exported.
+    pub serialized_len: usize,
+    /// Time since the value was last accessed, only tracked by the disk tier.
+    pub idle: Option<Duration>,
+}
+
+/// Longest string Redis embeds in its object header.
+const EMBSTR_MAX_LEN: usize = 44;
+
+impl Encoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Int => "int",
+            Encoding::Embstr => "embstr",
+            Encoding::Raw => "raw",
+            Encoding::Listpack => "listpack",
+            Encoding::Intset => "intset",
+            Encoding::Hashtable => "hashtable",
+            Encoding::Skiplist => "skiplist",
+            Encoding::Quicklist => "quicklist",
+        }
+    }
+
+    /// Strings are always stored as bytes, they get the encoding Redis would pick
+    /// so that clients relying on it behave the same.
+    fn of_string(value: &[u8]) -> Self {
+        let is_int = std::str::from_utf8(value)
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .is_some_and(|n| n.to_string().as_bytes() == value);
+        if is_int {
+            Encoding::Int
+        } else if value.len() <= EMBSTR_MAX_LEN {
+            Encoding::Embstr
+        } else {
+            Encoding::Raw
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Backend {
+    /// The encoding of the value at `key`, `None` if the key doesn't exist.
+    /// Values of module types like Bloom filters are reported as raw, like Redis does.
+    pub fn object_encoding(&self, key: &str) -> Option<Encoding> {
+        self.expire_fields(key, std::time::Instant::now());
+        if let Some(value) = self.map.get(key) {
+            Some(match &*value {
+                StoredString::Plain(value) => Encoding::of_string(value),
+                StoredString::Compressed(_) | StoredString::Spilled(_) => Encoding::Raw,
+            })
+        } else if let Some(hash) = self.hmap.get(key) {
+            Some(hash.encoding())
+        } else if let Some(set) = self.set.get(key) {
+            Some(set.encoding())
+        } else if self.zset.contains_key(key) {
+            Some(Encoding::Skiplist)
+        } else if self.list.contains_key(key) {
+            Some(Encoding::Quicklist)
+        } else {
+            self.exists(key).then_some(Encoding::Raw)
+        }
+    }
+
+    /// The internals of the value at `key`, `None` if the key doesn't exist.
+    pub fn object_internals(&self, key: &str) -> Option<ObjectInternals> {
+        let encoding = self.object_encoding(key)?;
+        fn address<T>(value: &T) -> usize {
+            value as *const T as usize
+        }
+        let (address, refcount) = if let Some(value) = self.map.get(key) {
+            match &*value {
+                StoredString::Plain(value) => {
+                    (Arc::as_ptr(value) as usize, Arc::strong_count(value))
+                }
+                stored => (address(stored), 1),
+            }
+        } else if let Some(value) = self.hmap.get(key) {
+            (address(&*value), 1)
+        } else if let Some(value) = self.set.get(key) {
+            (address(&*value), 1)
+        } else if let Some(value) = self.zset.get(key) {
+            (address(&*value), 1)
+        } else if let Some(value) = self.list.get(key) {
+            (address(&*value), 1)
+        } else if let Some(value) = self.bloom.get(key) {
+            (address(&*value), 1)
+        } else if let Some(value) = self.timeseries.get(key) {
+            (address(&*value), 1)
+        } else {
+            #[cfg(feature = "json")]
+            let value = self.json.get(key).map(|value| address(&*value));
+            #[cfg(not(feature = "json"))]
+            let value = None;
+            (value?, 1)
+        };
+        Some(ObjectInternals {
+            address,
+            refcount,
+            encoding,
+            serialized_len: self.serialized_len(key).unwrap_or(0),
+            idle: self.last_access(key).map(|at| Instant::now() - at),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{BulkString, EncodingConfig};
+
+    use super::*;
+
+    #[test]
+    fn test_object_encoding() {
+        let backend = Backend::with_encoding(EncodingConfig {
+            hash_max_listpack_entries: 2,
+            hash_max_listpack_value: 8,
+            ..Default::default()
+        });
+        backend.set("int".to_string(), b"-12".to_vec());
+        backend.set("str".to_string(), b"012".to_vec());
+        backend.set("raw".to_string(), vec![b'x'; EMBSTR_MAX_LEN + 1]);
+        assert_eq!(backend.object_encoding("int"), Some(Encoding::Int));
+        assert_eq!(backend.object_encoding("str"), Some(Encoding::Embstr));
+        assert_eq!(backend.object_encoding("raw"), Some(Encoding::Raw));
+        assert_eq!(backend.object_encoding("missing"), None);
+
+        let hset = |field: &str, value: &str| {
+            backend.hset(
+                "h".to_string(),
+                field.to_string(),
+                BulkString::new(value).into(),
+            )
+        };
+        hset("a", "1").unwrap();
+        hset("b", "2").unwrap();
+        assert_eq!(backend.object_encoding("h"), Some(Encoding::Listpack));
+        hset("c", "3").unwrap();
+        assert_eq!(backend.object_encoding("h"), Some(Encoding::Hashtable));
+        hset("d", "a long value").unwrap();
+        // the order of the fields survives the conversion.
+        let fields: Vec<String> = backend.hgetall("h").unwrap().into_keys().collect();
+        assert_eq!(fields, vec!["a", "b", "c", "d"]);
+
+        backend
+            .hset(
+                "h2".to_string(),
+                "f".to_string(),
+                BulkString::new("a long value").into(),
+            )
+            .unwrap();
+        assert_eq!(backend.object_encoding("h2"), Some(Encoding::Hashtable));
+
+        backend
+            .sadd("s".to_string(), HashSet::from([BulkString::new("1")]))
+            .unwrap();
+        assert_eq!(backend.object_encoding("s"), Some(Encoding::Intset));
+        backend
+            .sadd("s".to_string(), HashSet::from([BulkString::new("a")]))
+            .unwrap();
+        assert_eq!(backend.object_encoding("s"), Some(Encoding::Listpack));
+    }
+}
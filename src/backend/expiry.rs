@@ -0,0 +1,217 @@
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::RandomState, BinaryHeap, HashMap},
+    hash::{BuildHasher, Hasher},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::Backend;
+
+/// Longest interval between two runs of [`Backend::active_expire`].
+pub(crate) const ACTIVE_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+/// Most keys expired by a run of [`Backend::active_expire`], to bound its pause.
+const ACTIVE_EXPIRE_BATCH: usize = 1000;
+
+/// Deadlines of the keys with expiring fields, earliest first.
+///
+/// An entry is a hint: the key is checked when its deadline passes, and entries
+/// left behind by a deadline which was removed or moved expire nothing.
+/// Every key with deadlines has an entry no later than its earliest deadline,
+/// and only deadlines earlier than the one of its entries add an entry.
+#[derive(Debug, Default)]
+pub struct ExpiryQueue {
+    inner: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    heap: BinaryHeap<Reverse<(Instant, String)>>,
+    /// The earliest entry of each key in the heap.
+    earliest: HashMap<String, Instant>,
+}
+
+impl ExpiryQueue {
+    pub(crate) fn schedule(&self, key: &str, deadline: Instant) {
+        let mut entries = self.lock();
+        if entries.earliest.get(key).is_some_and(|e| *e <= deadline) {
+            return;
+        }
+        entries.earliest.insert(key.to_string(), deadline);
+        entries.heap.push(Reverse((deadline, key.to_string())));
+    }
+
+    /// Remove the entries due at `now`, at most `limit` distinct keys.
+    fn pop_due(&self, now: Instant, limit: usize) -> Vec<String> {
+        let mut entries = self.lock();
+        let mut keys: Vec<String> = Vec::new();
+        while keys.len() < limit {
+            match entries.heap.peek() {
+                Some(Reverse((deadline, _))) if *deadline <= now => {
+                    let Reverse((deadline, key)) =
+                        entries.heap.pop().expect("the entry was peeked");
+                    if entries.earliest.get(&key) == Some(&deadline) {
+                        entries.earliest.remove(&key);
+                    }
+                    if !keys.contains(&key) {
+                        keys.push(key);
+                    }
+                }
+                _ => break,
+            }
+        }
+        keys
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.lock()
+            .heap
+            .peek()
+            .map(|Reverse((deadline, _))| *deadline)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        // the entries are consistent between two statements, a panic can't corrupt them.
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Backend {
+    /// Remove the expired fields of the hashes whose deadline has passed,
+    /// returns how many were removed. Fields are also removed lazily when their hash
+    /// is accessed, this frees the memory of the hashes which are not.
+    ///
+    /// At most [`ACTIVE_EXPIRE_BATCH`] hashes are visited, [`Backend::next_expiry`]
+    /// tells if some are left.
+    pub fn active_expire(&self, now: Instant) -> usize {
+        let keys = self.expiry_queue.pop_due(now, ACTIVE_EXPIRE_BATCH);
+        let mut expired_keys = 0;
+        let expired = keys
+            .iter()
+            .map(|key| {
+                let expired = self.expire_fields(key, now);
+                // the popped entry may have been the one covering the next deadline.
+                if let Some(deadline) = self.hmap.get(key).and_then(|h| h.next_deadline()) {
+                    self.expiry_queue.schedule(key, deadline);
+                }
+                expired_keys += usize::from(expired > 0);
+                expired
+            })
+            .sum();
+        self.stats.record_expire_run(keys.len(), expired_keys);
+        expired
+    }
+
+    /// The earliest moment [`Backend::active_expire`] may have something to expire.
+    pub fn next_expiry(&self) -> Option<Instant> {
+        self.expiry_queue.next_deadline()
+    }
+
+    /// How long the active expiration task should sleep after a run at `now`:
+    /// until the next deadline, at most [`ACTIVE_EXPIRE_INTERVAL`], plus some jitter
+    /// so that runs don't line up with periodic client load.
+    pub(crate) fn active_expire_delay(&self, now: Instant) -> Duration {
+        let until_next = match self.next_expiry() {
+            Some(deadline) => deadline.saturating_duration_since(now),
+            None => ACTIVE_EXPIRE_INTERVAL,
+        };
+        if until_next.is_zero() {
+            // a full batch left due keys behind.
+            return Duration::ZERO;
+        }
+        until_next.min(ACTIVE_EXPIRE_INTERVAL) + jitter(ACTIVE_EXPIRE_INTERVAL / 10)
+    }
+}
+
+/// A random duration below `max`.
+fn jitter(max: Duration) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+    let nanos = max.as_nanos() as u64;
+    if nanos == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_nanos(random % nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, ExpireCondition};
+
+    use super::*;
+
+    #[test]
+    fn test_expiry_queue_pops_due_keys_in_batches() {
+        let queue = ExpiryQueue::default();
+        let now = Instant::now();
+        queue.schedule("late", now + Duration::from_secs(10));
+        for key in ["a", "b", "a", "c"] {
+            queue.schedule(key, now);
+        }
+        // both entries of "a" are popped, it is visited once.
+        assert_eq!(queue.pop_due(now, 2), vec!["a", "b"]);
+        assert_eq!(queue.pop_due(now, 10), vec!["c"]);
+        assert!(queue.pop_due(now, 10).is_empty());
+        assert_eq!(queue.next_deadline(), Some(now + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_expiry_queue_skips_later_deadlines() {
+        let queue = ExpiryQueue::default();
+        let now = Instant::now();
+        for i in 0..100 {
+            queue.schedule("k", now + Duration::from_secs(10 + i));
+        }
+        assert_eq!(queue.len(), 1);
+        // an earlier deadline still needs its own entry.
+        queue.schedule("k", now + Duration::from_secs(5));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pop_due(now + Duration::from_secs(5), 10), vec!["k"]);
+        queue.schedule("k", now + Duration::from_secs(7));
+        assert_eq!(queue.next_deadline(), Some(now + Duration::from_secs(7)));
+    }
+
+    #[test]
+    fn test_active_expire_follows_the_next_deadline() {
+        let backend = Backend::new();
+        let now = Instant::now();
+        for field in ["a", "b"] {
+            backend
+                .hset(
+                    "h".to_string(),
+                    field.to_string(),
+                    BulkString::new(field).into(),
+                )
+                .unwrap();
+        }
+        let soon = now + Duration::from_secs(1);
+        let later = now + Duration::from_secs(2);
+        backend.hexpire("h", &["a".to_string()], soon, ExpireCondition::Always);
+        backend.hexpire("h", &["b".to_string()], later, ExpireCondition::Always);
+        // the deadline of "a" is moved after the one of "b", its entry is now stale.
+        backend.hexpire(
+            "h",
+            &["a".to_string()],
+            later + Duration::from_secs(1),
+            ExpireCondition::Always,
+        );
+
+        assert_eq!(backend.next_expiry(), Some(soon));
+        assert_eq!(backend.active_expire(soon), 0);
+        assert_eq!(backend.active_expire(later), 1);
+        assert!(backend.next_expiry().is_some_and(|d| d > later));
+        assert_eq!(backend.active_expire(later + Duration::from_secs(1)), 1);
+        assert_eq!(backend.next_expiry(), None);
+        assert!(backend.hgetall("h").is_none());
+
+        let delay = backend.active_expire_delay(now);
+        assert!(delay >= ACTIVE_EXPIRE_INTERVAL && delay < ACTIVE_EXPIRE_INTERVAL * 11 / 10);
+    }
+}
@@ -0,0 +1,296 @@
+use std::{
+    io::{self, Write},
+    time::Instant,
+};
+
+use crate::{Backend, BulkString, FieldType, RespArray, RespEncode, RespFrame};
+
+use super::iter::keys;
+
+/// What exporting the keyspace did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExportStats {
+    /// Keys written.
+    pub keys: usize,
+}
+
+impl Backend {
+    /// Write the keyspace as the RESP encoded commands recreating it, which
+    /// [`Backend::import_resp`] or any client can replay.
+    ///
+    /// Keys are exported one at a time: a key modified during the export is written
+    /// as it was when reached. Hash fields keep their remaining time to live.
+    pub fn export_resp(&self, mut writer: impl Write) -> io::Result<ExportStats> {
+        let stats = self.export_commands(|command| writer.write_all(command))?;
+        writer.flush()?;
+        Ok(stats)
+    }
+
+    /// Pass the RESP encoded commands recreating the keyspace to `write`, one at a time.
+    pub(crate) fn export_commands(
+        &self,
+        mut write_command: impl FnMut(&[u8]) -> io::Result<()>,
+    ) -> io::Result<ExportStats> {
+        let mut stats = ExportStats::default();
+        let mut write = |commands: Option<Vec<RespArray>>| -> io::Result<()> {
+            if let Some(commands) = commands {
+                for command in commands {
+                    write_command(&command.encode())?;
+                }
+                stats.keys += 1;
+            }
+            Ok(())
+        };
+
+        for key in keys(&self.map) {
+            write(self.string_commands(&key))?;
+        }
+        let now = Instant::now();
+        for key in keys(&self.hmap) {
+            write(self.hash_commands(&key, now))?;
+        }
+        for key in keys(&self.set) {
+            write(self.set_commands(&key))?;
+        }
+        for key in keys(&self.zset) {
+            write(self.zset_commands(&key))?;
+        }
+        for key in keys(&self.list) {
+            write(self.list_commands(&key))?;
+        }
+        for key in keys(&self.bloom) {
+            write(self.bloom_commands(&key))?;
+        }
+        #[cfg(feature = "json")]
+        for key in keys(&self.json) {
+            write(self.json_commands(&key))?;
+        }
+        // rules are created once every series is filled, so that the samples
+        // aren't compacted into their destination a second time.
+        let mut rules = Vec::new();
+        for key in keys(&self.timeseries) {
+            write(self.series_commands(&key, &mut rules))?;
+        }
+        for rule in rules {
+            write_command(&rule.encode())?;
+        }
+        // indexes are derived from the hashes, which are already written.
+        for name in keys(&self.indexes) {
+            let Some(index) = self.indexes.get(&name) else {
+                continue;
+            };
+            let mut args = vec![bulk("ft.create"), bulk(&name), bulk("on"), bulk("hash")];
+            if !index.prefixes().is_empty() {
+                args.push(bulk("prefix"));
+                args.push(bulk(&index.prefixes().len().to_string()));
+                args.extend(index.prefixes().iter().map(|prefix| bulk(prefix)));
+            }
+            args.push(bulk("schema"));
+            for (field, field_type) in index.schema() {
+                args.push(bulk(field));
+                args.push(bulk(match field_type {
+                    FieldType::Tag => "tag",
+                    FieldType::Numeric => "numeric",
+                }));
+            }
+            drop(index);
+            write_command(&RespArray::new(args).encode())?;
+        }
+        Ok(stats)
+    }
+
+    /// Length of the RESP encoded commands recreating the key, `None` if the key
+    /// doesn't exist.
+    pub(crate) fn serialized_len(&self, key: &str) -> Option<usize> {
+        let mut rules = Vec::new();
+        let commands = self.key_commands(key, &mut rules)?;
+        Some(
+            commands
+                .into_iter()
+                .chain(rules)
+                .map(|command| command.encode().len())
+                .sum(),
+        )
+    }
+
+    /// The value of the key serialized for [`Backend::restore`], like DUMP: the RESP
+    /// encoded commands recreating it. The compaction rules of a series are left out,
+    /// their destination may not exist where the key is restored.
+    pub fn dump(&self, key: &str) -> Option<Vec<u8>> {
+        let commands = self.key_commands(key, &mut Vec::new())?;
+        let mut payload = Vec::new();
+        for command in commands {
+            payload.extend_from_slice(&command.encode());
+        }
+        Some(payload)
+    }
+
+    fn key_commands(&self, key: &str, rules: &mut Vec<RespArray>) -> Option<Vec<RespArray>> {
+        self.string_commands(key)
+            .or_else(|| self.hash_commands(key, Instant::now()))
+            .or_else(|| self.set_commands(key))
+            .or_else(|| self.zset_commands(key))
+            .or_else(|| self.list_commands(key))
+            .or_else(|| self.bloom_commands(key))
+            .or_else(|| self.json_commands(key))
+            .or_else(|| self.series_commands(key, rules))
+    }
+
+    fn string_commands(&self, key: &str) -> Option<Vec<RespArray>> {
+        let value = self.get(key)?;
+        Some(vec![command(&[b"set", key.as_bytes(), &value])])
+    }
+
+    /// Fields expired at `now` are left out, the others keep their remaining time to live.
+    fn hash_commands(&self, key: &str, now: Instant) -> Option<Vec<RespArray>> {
+        let hash = self.hmap.get(key)?;
+        let mut commands = Vec::new();
+        for (field, value) in hash.iter() {
+            let deadline = hash.deadline(field);
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                continue;
+            }
+            commands.push(RespArray::new(vec![
+                bulk("hset"),
+                bulk(key),
+                bulk(field),
+                value.clone(),
+            ]));
+            if let Some(deadline) = deadline {
+                let ttl = (deadline - now).as_millis().max(1).to_string();
+                commands.push(command(&[
+                    b"hpexpire",
+                    key.as_bytes(),
+                    ttl.as_bytes(),
+                    b"fields",
+                    b"1",
+                    field.as_bytes(),
+                ]));
+            }
+        }
+        Some(commands)
+    }
+
+    fn set_commands(&self, key: &str) -> Option<Vec<RespArray>> {
+        let set = self.set.get(key)?;
+        let mut args = vec![bulk("sadd"), bulk(key)];
+        args.extend(set.iter().map(RespFrame::from));
+        Some(vec![RespArray::new(args)])
+    }
+
+    fn zset_commands(&self, key: &str) -> Option<Vec<RespArray>> {
+        let zset = self.zset.get(key)?;
+        let mut args = vec![bulk("zadd"), bulk(key)];
+        for (member, score) in zset.iter() {
+            args.push(bulk(&score.to_string()));
+            args.push(member.into());
+        }
+        Some(vec![RespArray::new(args)])
+    }
+
+    fn list_commands(&self, key: &str) -> Option<Vec<RespArray>> {
+        let list = self.list.get(key)?;
+        let mut args = vec![bulk("rpush"), bulk(key)];
+        args.extend(list.iter_from(0).cloned().map(RespFrame::from));
+        Some(vec![RespArray::new(args)])
+    }
+
+    /// The chunks of BF.SCANDUMP, loaded back in order by BF.LOADCHUNK.
+    fn bloom_commands(&self, key: &str) -> Option<Vec<RespArray>> {
+        let chunks = self.bloom.get(key)?.chunks();
+        let commands = chunks
+            .into_iter()
+            .map(|(iterator, data)| {
+                let iterator = iterator.to_string();
+                command(&[b"bf.loadchunk", key.as_bytes(), iterator.as_bytes(), &data])
+            })
+            .collect();
+        Some(commands)
+    }
+
+    #[cfg(feature = "json")]
+    fn json_commands(&self, key: &str) -> Option<Vec<RespArray>> {
+        let doc = self.json.get(key)?.to_string();
+        Some(vec![command(&[
+            b"json.set",
+            key.as_bytes(),
+            b"$",
+            doc.as_bytes(),
+        ])])
+    }
+
+    #[cfg(not(feature = "json"))]
+    fn json_commands(&self, _key: &str) -> Option<Vec<RespArray>> {
+        None
+    }
+
+    /// The commands creating and filling the series, its compaction rules are
+    /// pushed to `rules` to be created once every series is filled.
+    fn series_commands(&self, key: &str, rules: &mut Vec<RespArray>) -> Option<Vec<RespArray>> {
+        let series = self.timeseries.get(key)?;
+        let retention = series.retention_ms().to_string();
+        let mut create = vec![
+            bulk("ts.create"),
+            bulk(key),
+            bulk("retention"),
+            bulk(&retention),
+        ];
+        if !series.labels().is_empty() {
+            create.push(bulk("labels"));
+            for (label, value) in series.labels() {
+                create.push(bulk(label));
+                create.push(bulk(value));
+            }
+        }
+        let mut commands = vec![RespArray::new(create)];
+        for (timestamp, value) in series.range(0, u64::MAX) {
+            let (timestamp, value) = (timestamp.to_string(), value.to_string());
+            commands.push(command(&[
+                b"ts.add",
+                key.as_bytes(),
+                timestamp.as_bytes(),
+                value.as_bytes(),
+            ]));
+        }
+        for (dest, aggregation, bucket_ms) in series.rules() {
+            let bucket_ms = bucket_ms.to_string();
+            rules.push(command(&[
+                b"ts.createrule",
+                key.as_bytes(),
+                dest.as_bytes(),
+                b"aggregation",
+                aggregation.as_str().as_bytes(),
+                bucket_ms.as_bytes(),
+            ]));
+        }
+        Some(commands)
+    }
+
+    /// Remove every key [`Backend::export_resp`] writes.
+    pub(crate) fn clear_exported(&self) {
+        self.map.clear();
+        self.hmap.clear();
+        self.hexpires.clear();
+        self.key_deadlines.clear();
+        self.set.clear();
+        self.zset.clear();
+        self.list.clear();
+        self.bloom.clear();
+        self.timeseries.clear();
+        self.indexes.clear();
+        #[cfg(feature = "json")]
+        self.json.clear();
+    }
+}
+
+fn bulk(s: &str) -> RespFrame {
+    BulkString::new(s).into()
+}
+
+fn command(args: &[&[u8]]) -> RespArray {
+    RespArray::new(
+        args.iter()
+            .map(|arg| BulkString::new(*arg).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+}
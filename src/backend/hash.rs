@@ -0,0 +1,473 @@
+use std::{collections::HashMap, time::Instant};
+
+use dashmap::mapref::entry::Entry;
+use indexmap::IndexMap;
+
+use crate::{Backend, EncodingConfig, RespFrame};
+
+use super::{memory::frame_len, Encoding};
+
+/// A hash value: fields in insertion order, some of them expiring at a deadline.
+///
+/// Setting a field with [`HashValue::insert`] clears its deadline like HSET does,
+/// modifying it in place keeps it like HINCRBY does.
+#[derive(Debug, Clone, Default)]
+pub struct HashValue {
+    fields: HashFields,
+    deadlines: HashMap<String, Instant>,
+}
+
+#[derive(Debug, Clone)]
+enum HashFields {
+    /// Small hashes are a flat list scanned linearly, cheaper than a hash table.
+    Listpack(Vec<(String, RespFrame)>),
+    Hashtable(IndexMap<String, RespFrame>),
+}
+
+/// When HEXPIRE may set the deadline of a field.
+/// A field without a deadline is treated as never expiring by GT and LT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    Always,
+    /// Only fields without a deadline.
+    Nx,
+    /// Only fields with a deadline.
+    Xx,
+    /// Only if the new deadline is later than the current one.
+    Gt,
+    /// Only if the new deadline is earlier than the current one.
+    Lt,
+}
+
+impl Default for HashFields {
+    fn default() -> Self {
+        HashFields::Listpack(Vec::new())
+    }
+}
+
+impl HashValue {
+    pub fn insert(&mut self, field: String, value: RespFrame) -> Option<RespFrame> {
+        self.deadlines.remove(&field);
+        match &mut self.fields {
+            HashFields::Listpack(entries) => match entries.iter_mut().find(|(f, _)| *f == field) {
+                Some((_, slot)) => Some(std::mem::replace(slot, value)),
+                None => {
+                    entries.push((field, value));
+                    None
+                }
+            },
+            HashFields::Hashtable(map) => map.insert(field, value),
+        }
+    }
+
+    pub fn get(&self, field: &str) -> Option<&RespFrame> {
+        match &self.fields {
+            HashFields::Listpack(entries) => {
+                entries.iter().find(|(f, _)| f == field).map(|(_, v)| v)
+            }
+            HashFields::Hashtable(map) => map.get(field),
+        }
+    }
+
+    pub fn get_mut(&mut self, field: &str) -> Option<&mut RespFrame> {
+        match &mut self.fields {
+            HashFields::Listpack(entries) => {
+                entries.iter_mut().find(|(f, _)| f == field).map(|(_, v)| v)
+            }
+            HashFields::Hashtable(map) => map.get_mut(field),
+        }
+    }
+
+    pub fn contains_key(&self, field: &str) -> bool {
+        self.get(field).is_some()
+    }
+
+    /// Remove the field, keeping the remaining fields in insertion order.
+    pub fn shift_remove(&mut self, field: &str) -> Option<RespFrame> {
+        self.deadlines.remove(field);
+        match &mut self.fields {
+            HashFields::Listpack(entries) => {
+                let i = entries.iter().position(|(f, _)| f == field)?;
+                Some(entries.remove(i).1)
+            }
+            HashFields::Hashtable(map) => map.shift_remove(field),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.fields {
+            HashFields::Listpack(entries) => entries.len(),
+            HashFields::Hashtable(map) => map.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The fields and their values, in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &RespFrame)> {
+        let (listpack, hashtable) = match &self.fields {
+            HashFields::Listpack(entries) => (Some(entries.iter().map(|(f, v)| (f, v))), None),
+            HashFields::Hashtable(map) => (None, Some(map.iter())),
+        };
+        listpack
+            .into_iter()
+            .flatten()
+            .chain(hashtable.into_iter().flatten())
+    }
+
+    pub fn to_map(&self) -> IndexMap<String, RespFrame> {
+        self.iter().map(|(f, v)| (f.clone(), v.clone())).collect()
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        match self.fields {
+            HashFields::Listpack(_) => Encoding::Listpack,
+            HashFields::Hashtable(_) => Encoding::Hashtable,
+        }
+    }
+
+    /// Convert a listpack which outgrew the limits of `config` to a hash table.
+    pub(crate) fn fit_encoding(&mut self, config: &EncodingConfig) {
+        let HashFields::Listpack(entries) = &mut self.fields else {
+            return;
+        };
+        let too_long = |(f, v): &(String, RespFrame)| {
+            f.len() > config.hash_max_listpack_value
+                || frame_len(v) > config.hash_max_listpack_value
+        };
+        if entries.len() > config.hash_max_listpack_entries || entries.iter().any(too_long) {
+            self.fields = HashFields::Hashtable(std::mem::take(entries).into_iter().collect());
+        }
+    }
+
+    /// Convert a hash table which shrank back within the limits of `config` to a listpack,
+    /// and release spare capacity. Returns whether the encoding changed.
+    pub(crate) fn compact(&mut self, config: &EncodingConfig) -> bool {
+        self.deadlines.shrink_to_fit();
+        let fits = |map: &IndexMap<String, RespFrame>| {
+            map.len() <= config.hash_max_listpack_entries
+                && map.iter().all(|(f, v)| {
+                    f.len() <= config.hash_max_listpack_value
+                        && frame_len(v) <= config.hash_max_listpack_value
+                })
+        };
+        match &mut self.fields {
+            HashFields::Hashtable(map) if fits(map) => {
+                self.fields = HashFields::Listpack(std::mem::take(map).into_iter().collect());
+                true
+            }
+            HashFields::Hashtable(map) => {
+                map.shrink_to_fit();
+                false
+            }
+            HashFields::Listpack(entries) => {
+                entries.shrink_to_fit();
+                false
+            }
+        }
+    }
+
+    pub fn deadline(&self, field: &str) -> Option<Instant> {
+        self.deadlines.get(field).copied()
+    }
+
+    pub fn has_deadlines(&self) -> bool {
+        !self.deadlines.is_empty()
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.deadlines.values().min().copied()
+    }
+
+    /// When the hash expires as a whole: at its last deadline, if every field has one.
+    pub fn expires_at(&self) -> Option<Instant> {
+        if self.deadlines.len() < self.len() {
+            return None;
+        }
+        self.deadlines.values().max().copied()
+    }
+
+    /// Remove the fields whose deadline has passed, returns how many were removed.
+    pub fn purge_expired(&mut self, now: Instant) -> usize {
+        let expired: Vec<String> = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(field, _)| field.clone())
+            .collect();
+        for field in &expired {
+            self.shift_remove(field);
+        }
+        expired.len()
+    }
+}
+
+impl Backend {
+    /// Set the deadline of the fields of the hash at `key`. For each field, like HEXPIRE, returns
+    /// -2 if it doesn't exist, 0 if the condition isn't met, 1 if the deadline was set,
+    /// or 2 if the field was deleted because the deadline has already passed.
+    pub fn hexpire(
+        &self,
+        key: &str,
+        fields: &[String],
+        deadline: Instant,
+        condition: ExpireCondition,
+    ) -> Vec<i64> {
+        let now = Instant::now();
+        self.expire_fields(key, now);
+        let Some(mut hash) = self.hmap.get_mut(key) else {
+            return vec![-2; fields.len()];
+        };
+        let res: Vec<i64> = fields
+            .iter()
+            .map(|field| {
+                if !hash.contains_key(field) {
+                    return -2;
+                }
+                let allowed = match (condition, hash.deadline(field)) {
+                    (ExpireCondition::Always, _) => true,
+                    (ExpireCondition::Nx, current) => current.is_none(),
+                    (ExpireCondition::Xx, current) => current.is_some(),
+                    (ExpireCondition::Gt, current) => current.is_some_and(|c| deadline > c),
+                    (ExpireCondition::Lt, current) => current.is_none_or(|c| deadline < c),
+                };
+                if !allowed {
+                    0
+                } else if deadline <= now {
+                    hash.shift_remove(field);
+                    2
+                } else {
+                    hash.deadlines.insert(field.clone(), deadline);
+                    1
+                }
+            })
+            .collect();
+        self.index_deadlines(key, &hash);
+        if let Some(deadline) = hash.next_deadline() {
+            self.expiry_queue.schedule(key, deadline);
+        }
+        let empty = hash.is_empty();
+        drop(hash);
+        if empty {
+            self.hmap.remove_if(key, |_, hash| hash.is_empty());
+        }
+        if res.contains(&2) {
+            self.reindex(key);
+        }
+        if res.iter().any(|r| *r > 0) {
+            self.key_changed(key);
+        }
+        res
+    }
+
+    /// Remaining time to live of the fields in milliseconds, like HPTTL:
+    /// -2 if the field doesn't exist, -1 if it has no deadline.
+    pub fn hpttl(&self, key: &str, fields: &[String]) -> Vec<i64> {
+        let now = Instant::now();
+        self.expire_fields(key, now);
+        let Some(hash) = self.hmap.get(key) else {
+            return vec![-2; fields.len()];
+        };
+        fields
+            .iter()
+            .map(|field| {
+                if !hash.contains_key(field) {
+                    return -2;
+                }
+                match hash.deadline(field) {
+                    Some(deadline) => deadline.saturating_duration_since(now).as_millis() as i64,
+                    None => -1,
+                }
+            })
+            .collect()
+    }
+
+    /// Remove the deadline of the fields, like HPERSIST:
+    /// -2 if the field doesn't exist, -1 if it has no deadline, 1 if it was removed.
+    pub fn hpersist(&self, key: &str, fields: &[String]) -> Vec<i64> {
+        self.expire_fields(key, Instant::now());
+        let Some(mut hash) = self.hmap.get_mut(key) else {
+            return vec![-2; fields.len()];
+        };
+        let res = fields
+            .iter()
+            .map(|field| {
+                if !hash.contains_key(field) {
+                    return -2;
+                }
+                match hash.deadlines.remove(field) {
+                    Some(_) => 1,
+                    None => -1,
+                }
+            })
+            .collect();
+        self.index_deadlines(key, &hash);
+        res
+    }
+
+    /// Remove the expired fields of the hash at `key`, returns how many were removed.
+    pub(crate) fn expire_fields(&self, key: &str, now: Instant) -> usize {
+        if !self.hexpires.contains(key) {
+            return 0;
+        }
+        // the index is only updated while the hash is locked,
+        // so it can't miss a deadline set concurrently.
+        let expired = match self.hmap.entry(key.to_string()) {
+            Entry::Occupied(mut entry) => {
+                let expired = entry.get_mut().purge_expired(now);
+                self.index_deadlines(key, entry.get());
+                let removed = entry.get().is_empty();
+                if removed {
+                    entry.remove();
+                }
+                self.stats.record_expired(expired, removed);
+                expired
+            }
+            Entry::Vacant(_) => {
+                self.hexpires.remove(key);
+                0
+            }
+        };
+        if expired > 0 {
+            self.reindex(key);
+            self.key_expired(key);
+        }
+        expired
+    }
+
+    /// Keep the index of the hashes with deadlines and the deadline of the key
+    /// up to date, `hash` must be locked.
+    pub(crate) fn index_deadlines(&self, key: &str, hash: &HashValue) {
+        if hash.has_deadlines() {
+            self.hexpires.insert(key.to_string());
+        } else {
+            self.hexpires.remove(key);
+        }
+        self.key_deadlines.set(key, hash.expires_at());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::BulkString;
+
+    use super::*;
+
+    fn backend_with_hash() -> Backend {
+        let backend = Backend::new();
+        for field in ["a", "b", "c"] {
+            backend
+                .hset(
+                    "h".to_string(),
+                    field.to_string(),
+                    BulkString::new(field).into(),
+                )
+                .unwrap();
+        }
+        backend
+    }
+
+    fn fields(names: &[&str]) -> Vec<String> {
+        names.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn test_hexpire_conditions() {
+        let backend = backend_with_hash();
+        let later = Instant::now() + Duration::from_secs(100);
+        let fs = fields(&["a", "missing"]);
+
+        assert_eq!(
+            backend.hexpire("h", &fs, later, ExpireCondition::Xx),
+            vec![0, -2]
+        );
+        assert_eq!(
+            backend.hexpire("h", &fs, later, ExpireCondition::Gt),
+            vec![0, -2]
+        );
+        assert_eq!(
+            backend.hexpire("h", &fs, later, ExpireCondition::Nx),
+            vec![1, -2]
+        );
+        assert_eq!(
+            backend.hexpire("h", &fs, later, ExpireCondition::Nx),
+            vec![0, -2]
+        );
+        let sooner = later - Duration::from_secs(50);
+        assert_eq!(
+            backend.hexpire("h", &fs, sooner, ExpireCondition::Gt),
+            vec![0, -2]
+        );
+        assert_eq!(
+            backend.hexpire("h", &fs, sooner, ExpireCondition::Lt),
+            vec![1, -2]
+        );
+
+        let ttl = backend.hpttl("h", &fields(&["a", "b", "missing"]));
+        assert!(ttl[0] > 40_000 && ttl[0] <= 50_000);
+        assert_eq!(&ttl[1..], &[-1, -2]);
+        assert_eq!(
+            backend.hexpire("missing", &fs, later, ExpireCondition::Always),
+            vec![-2, -2]
+        );
+    }
+
+    #[test]
+    fn test_hexpire_in_the_past_deletes_fields() {
+        let backend = backend_with_hash();
+        let now = Instant::now();
+        let fs = fields(&["a", "b", "c"]);
+        assert_eq!(
+            backend.hexpire("h", &fs, now, ExpireCondition::Always),
+            vec![2, 2, 2]
+        );
+        // the hash is removed with its last field.
+        assert!(backend.hgetall("h").is_none());
+    }
+
+    #[test]
+    fn test_hpersist_and_hset_clear_deadlines() {
+        let backend = backend_with_hash();
+        let later = Instant::now() + Duration::from_secs(100);
+        backend.hexpire("h", &fields(&["a", "b"]), later, ExpireCondition::Always);
+
+        assert_eq!(
+            backend.hpersist("h", &fields(&["a", "c", "x"])),
+            vec![1, -1, -2]
+        );
+        backend
+            .hset(
+                "h".to_string(),
+                "b".to_string(),
+                BulkString::new("new").into(),
+            )
+            .unwrap();
+        assert_eq!(backend.hpttl("h", &fields(&["a", "b"])), vec![-1, -1]);
+        assert!(!backend.hexpires.contains("h"));
+    }
+
+    #[test]
+    fn test_expired_fields_are_removed() {
+        let backend = backend_with_hash();
+        let deadline = Instant::now() + Duration::from_millis(10);
+        backend.hexpire("h", &fields(&["a"]), deadline, ExpireCondition::Always);
+        assert!(backend.hget("h", "a").is_some());
+
+        // lazily, when the hash is accessed.
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(backend.hget("h", "a").is_none());
+        assert_eq!(backend.hgetall("h").unwrap().len(), 2);
+
+        // actively, for hashes which are not accessed.
+        let deadline = Instant::now() + Duration::from_millis(10);
+        backend.hexpire("h", &fields(&["b", "c"]), deadline, ExpireCondition::Always);
+        assert_eq!(backend.active_expire(Instant::now()), 0);
+        assert_eq!(backend.active_expire(deadline), 2);
+        assert!(!backend.hmap.contains_key("h"));
+        assert!(backend.hexpires.is_empty());
+    }
+}
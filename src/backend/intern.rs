@@ -0,0 +1,192 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+};
+
+use bytes::Bytes;
+
+/// A string value, shared by the keys holding identical values.
+pub type StringValue = Arc<Vec<u8>>;
+
+/// Integers below this are preallocated once, like Redis' shared integers.
+pub const SHARED_INTEGERS: usize = 10_000;
+/// Values at least this long are shared with an identical value already stored.
+pub const SHARED_VALUE_MIN_LEN: usize = 1024;
+/// The table of shared values is never pruned below this number of entries.
+const MIN_PRUNE_LEN: usize = 1024;
+
+lazy_static::lazy_static! {
+    static ref INTEGERS: Vec<StringValue> = (0..SHARED_INTEGERS)
+        .map(|n| Arc::new(n.to_string().into_bytes()))
+        .collect();
+}
+
+/// Deduplicates the string values written to the backend.
+///
+/// Small integers come from a table shared by every backend; large values are
+/// looked up by hash among the values still stored, which are only weakly
+/// referenced so that the pool never keeps a value alive.
+#[derive(Debug, Default)]
+pub struct ValuePool {
+    large: Mutex<LargeValues>,
+    integer_hits: AtomicU64,
+    value_hits: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct LargeValues {
+    by_hash: HashMap<u64, Vec<Weak<Vec<u8>>>>,
+    /// Length of `by_hash` at which the dead entries are pruned.
+    prune_at: usize,
+}
+
+/// How often writes reused a shared value, reported by INFO memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharingStats {
+    pub integer_hits: u64,
+    pub value_hits: u64,
+}
+
+impl ValuePool {
+    /// The value to store for `value`: a shared one if possible.
+    pub fn intern(&self, value: Vec<u8>) -> StringValue {
+        if value.len() < SHARED_VALUE_MIN_LEN {
+            return self.intern_integer(value);
+        }
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        // a poisoned pool only misses sharing opportunities.
+        let mut large = self.large.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = large.by_hash.entry(hash).or_default();
+        bucket.retain(|shared| shared.strong_count() > 0);
+        if let Some(shared) = bucket
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|shared| **shared == value)
+        {
+            self.value_hits.fetch_add(1, Ordering::Relaxed);
+            return shared;
+        }
+        let value = Arc::new(value);
+        bucket.push(Arc::downgrade(&value));
+        large.prune();
+        value
+    }
+
+    /// Like [`ValuePool::intern`], but only shares small integers, which is cheap enough
+    /// for values rewritten in place like the ones of INCR or APPEND.
+    pub fn intern_integer(&self, value: Vec<u8>) -> StringValue {
+        match shared_integer(&value) {
+            Some(shared) => {
+                self.integer_hits.fetch_add(1, Ordering::Relaxed);
+                shared
+            }
+            None => Arc::new(value),
+        }
+    }
+
+    pub fn stats(&self) -> SharingStats {
+        SharingStats {
+            integer_hits: self.integer_hits.load(Ordering::Relaxed),
+            value_hits: self.value_hits.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl LargeValues {
+    /// Drop the hashes of values no longer stored once the table doubled.
+    fn prune(&mut self) {
+        if self.by_hash.len() < self.prune_at.max(MIN_PRUNE_LEN) {
+            return;
+        }
+        self.by_hash.retain(|_, bucket| {
+            bucket.retain(|shared| shared.strong_count() > 0);
+            !bucket.is_empty()
+        });
+        self.prune_at = self.by_hash.len() * 2;
+    }
+}
+
+/// The shared value of an integer in `0..SHARED_INTEGERS` written without leading zeros.
+fn shared_integer(value: &[u8]) -> Option<StringValue> {
+    if value.is_empty() || value.len() > 5 || (value.len() > 1 && value[0] == b'0') {
+        return None;
+    }
+    if !value.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let n: usize = std::str::from_utf8(value).ok()?.parse().ok()?;
+    INTEGERS.get(n).cloned()
+}
+
+/// A handle on a stored value for a reply, sharing its payload.
+pub(crate) fn share(value: &StringValue) -> Bytes {
+    Bytes::from_owner(Shared(Arc::clone(value)))
+}
+
+/// Gives [`Bytes`] access to the payload of a shared value.
+struct Shared(StringValue);
+
+impl AsRef<[u8]> for Shared {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Take ownership of a stored value, copying it only if it is shared.
+pub(crate) fn unshare(value: StringValue) -> Vec<u8> {
+    Arc::try_unwrap(value).unwrap_or_else(|shared| Vec::clone(&shared))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_integers_are_shared() {
+        let pool = ValuePool::default();
+        let a = pool.intern(b"42".to_vec());
+        let b = pool.intern_integer(b"42".to_vec());
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(shared_integer(b"042").is_none());
+        assert!(shared_integer(b"10000").is_none());
+        assert!(shared_integer(b"-1").is_none());
+        assert_eq!(pool.stats().integer_hits, 2);
+    }
+
+    #[test]
+    fn test_large_values_are_shared_while_stored() {
+        let pool = ValuePool::default();
+        let big = vec![b'x'; SHARED_VALUE_MIN_LEN];
+        let a = pool.intern(big.clone());
+        let b = pool.intern(big.clone());
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(pool.stats().value_hits, 1);
+
+        drop((a, b));
+        let c = pool.intern(big);
+        assert_eq!(Arc::strong_count(&c), 1);
+        assert_eq!(pool.stats().value_hits, 1);
+
+        let mut other = vec![b'x'; SHARED_VALUE_MIN_LEN];
+        other[0] = b'y';
+        assert!(!Arc::ptr_eq(&c, &pool.intern(other)));
+    }
+
+    #[test]
+    fn test_share_keeps_value_alive() {
+        let value = Arc::new(b"hello".to_vec());
+        let shared = share(&value);
+        assert_eq!(shared.as_ptr(), value.as_ptr());
+        assert_eq!(Arc::strong_count(&value), 2);
+        // rewriting the value copies it while a reply still holds it.
+        assert_eq!(unshare(value), b"hello");
+        assert_eq!(&shared[..], b"hello");
+    }
+}
@@ -0,0 +1,172 @@
+use bytes::Bytes;
+use dashmap::DashMap;
+use indexmap::IndexMap;
+
+use crate::{Backend, BulkString, RespFrame};
+
+/// Bulk access to the keyspace, for maintenance tasks and applications embedding the backend.
+///
+/// The iterators yield owned copies and lock nothing between two items, so the backend
+/// may be modified while iterating. They snapshot the keys of a type when reaching it,
+/// then copy each value when yielding it: keys added meanwhile may be missed, and
+/// keys removed meanwhile are skipped.
+impl Backend {
+    /// The number of keys, whatever the type of their value.
+    pub fn len(&self) -> usize {
+        let len = self.map.len()
+            + self.hmap.len()
+            + self.set.len()
+            + self.zset.len()
+            + self.list.len()
+            + self.bloom.len()
+            + self.timeseries.len();
+        #[cfg(feature = "json")]
+        let len = len + self.json.len();
+        len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every key, whatever the type of its value.
+    pub fn keys_iter(&self) -> impl Iterator<Item = String> + '_ {
+        let keys = lazy_keys(&self.map)
+            .chain(lazy_keys(&self.hmap))
+            .chain(lazy_keys(&self.set))
+            .chain(lazy_keys(&self.zset))
+            .chain(lazy_keys(&self.list))
+            .chain(lazy_keys(&self.bloom))
+            .chain(lazy_keys(&self.timeseries));
+        #[cfg(feature = "json")]
+        let keys = keys.chain(lazy_keys(&self.json));
+        keys
+    }
+
+    /// The strings and their values.
+    pub fn strings_iter(&self) -> impl Iterator<Item = (String, Bytes)> + '_ {
+        lazy_keys(&self.map).filter_map(|key| self.get(&key).map(|value| (key, value)))
+    }
+
+    /// The hashes and their fields, in insertion order.
+    pub fn hashes_iter(&self) -> impl Iterator<Item = (String, IndexMap<String, RespFrame>)> + '_ {
+        lazy_keys(&self.hmap).filter_map(|key| self.hgetall(&key).map(|hash| (key, hash)))
+    }
+
+    /// The sets and their members.
+    pub fn sets_iter(&self) -> impl Iterator<Item = (String, Vec<BulkString>)> + '_ {
+        lazy_keys(&self.set).filter_map(|key| self.smembers(&key).map(|set| (key, set)))
+    }
+
+    /// The sorted sets and their members with their scores, by increasing score.
+    pub fn sorted_sets_iter(&self) -> impl Iterator<Item = (String, Vec<(BulkString, f64)>)> + '_ {
+        lazy_keys(&self.zset).filter_map(|key| {
+            let members = self.zset.get(&key)?.iter().collect();
+            Some((key, members))
+        })
+    }
+
+    /// The lists and their elements, from head to tail.
+    pub fn lists_iter(&self) -> impl Iterator<Item = (String, Vec<BulkString>)> + '_ {
+        lazy_keys(&self.list).filter_map(|key| {
+            let elements = self.list.get(&key)?.iter_from(0).cloned().collect();
+            Some((key, elements))
+        })
+    }
+}
+
+/// The keys of a map, collected so that no shard stays locked while they are used.
+pub(super) fn keys<V>(map: &DashMap<String, V>) -> Vec<String> {
+    map.iter().map(|entry| entry.key().clone()).collect()
+}
+
+/// The keys of a map, collected once the iterator is first advanced.
+fn lazy_keys<V>(map: &DashMap<String, V>) -> impl Iterator<Item = String> + '_ {
+    std::iter::once_with(|| keys(map)).flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::ListEnd;
+
+    use super::*;
+
+    #[test]
+    fn test_iterators() {
+        let backend = Backend::new();
+        assert!(backend.is_empty());
+        backend.set("s".to_string(), b"v".to_vec());
+        backend
+            .hset("h".to_string(), "f".to_string(), RespFrame::Integer(1))
+            .unwrap();
+        backend
+            .sadd("set".to_string(), HashSet::from([BulkString::new("m")]))
+            .unwrap();
+        backend
+            .zadd(
+                "z",
+                vec![(2.0, BulkString::new("b")), (1.0, BulkString::new("a"))],
+            )
+            .unwrap();
+        backend
+            .push(
+                "l",
+                vec![BulkString::new("x"), BulkString::new("y")],
+                ListEnd::Tail,
+            )
+            .unwrap();
+        assert_eq!(backend.len(), 5);
+
+        let keys: HashSet<String> = backend.keys_iter().collect();
+        assert_eq!(
+            keys,
+            HashSet::from(["s", "h", "set", "z", "l"].map(String::from))
+        );
+        assert_eq!(
+            backend.strings_iter().collect::<Vec<_>>(),
+            vec![("s".to_string(), Bytes::from("v"))]
+        );
+        let (key, hash) = backend.hashes_iter().next().unwrap();
+        assert_eq!(
+            (key.as_str(), hash["f"].clone()),
+            ("h", RespFrame::Integer(1))
+        );
+        assert_eq!(
+            backend.sets_iter().collect::<Vec<_>>(),
+            vec![("set".to_string(), vec![BulkString::new("m")])]
+        );
+        assert_eq!(
+            backend.sorted_sets_iter().collect::<Vec<_>>(),
+            vec![(
+                "z".to_string(),
+                vec![(BulkString::new("a"), 1.0), (BulkString::new("b"), 2.0)]
+            )]
+        );
+        assert_eq!(
+            backend.lists_iter().collect::<Vec<_>>(),
+            vec![(
+                "l".to_string(),
+                vec![BulkString::new("x"), BulkString::new("y")]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_modify_while_iterating() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.set(i.to_string(), b"v".to_vec());
+        }
+        // no shard is locked between two items, writing to the same map can't deadlock.
+        let mut seen = 0;
+        for (key, _) in backend.strings_iter() {
+            backend.set(key.clone(), b"w".to_vec());
+            backend.remove_key(&(key.parse::<i32>().unwrap() + 1).to_string());
+            seen += 1;
+        }
+        assert!(seen < 100);
+        assert!(backend.strings_iter().all(|(_, value)| value == "w"));
+    }
+}
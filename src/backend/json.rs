@@ -0,0 +1,25 @@
+use serde_json::Value;
+
+use crate::{Backend, KeyType, WrongType};
+
+use super::update_entry;
+
+impl Backend {
+    pub fn json_get(&self, key: &str) -> Option<Value> {
+        self.json.get(key).map(|v| v.value().clone())
+    }
+
+    /// Atomically read and modify the JSON document at `key`, like [`Backend::update`].
+    pub fn json_update<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut Option<Value>) -> T,
+    ) -> Result<T, WrongType> {
+        self.check_type(key, KeyType::Json)?;
+        let (res, modified) = update_entry(self.json.entry(key.to_string()), f);
+        if modified {
+            self.key_changed(key);
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,422 @@
+use std::{sync::Mutex, time::Instant};
+
+use dashmap::DashMap;
+use thiserror::Error;
+
+use crate::Backend;
+
+/// The type of the value held by a key. A key holds a single value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    String,
+    Hash,
+    Set,
+    ZSet,
+    List,
+    Bloom,
+    TimeSeries,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+/// A write of a value of one type to a key holding a value of another type.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+pub struct WrongType;
+
+/// The figures of the database in INFO keyspace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyspaceStats {
+    /// Keys, whatever the type of their value.
+    pub keys: usize,
+    /// Keys with a deadline.
+    pub expires: usize,
+    /// Average remaining time to live of the keys with a deadline, in milliseconds.
+    pub avg_ttl: u64,
+    /// Hashes having fields with a deadline.
+    pub subexpiry: usize,
+}
+
+/// The deadlines of the keys which expire as a whole, with their sum, so that
+/// INFO keyspace reads how many keys expire and their average time to live in O(1).
+#[derive(Debug)]
+pub(crate) struct KeyDeadlines {
+    deadlines: DashMap<String, Instant>,
+    /// The sum of the deadlines, in milliseconds after `epoch`.
+    total: Mutex<u128>,
+    epoch: Instant,
+}
+
+impl Default for KeyDeadlines {
+    fn default() -> Self {
+        Self {
+            deadlines: DashMap::new(),
+            total: Mutex::new(0),
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl KeyDeadlines {
+    pub(crate) fn get(&self, key: &str) -> Option<Instant> {
+        self.deadlines.get(key).map(|deadline| *deadline)
+    }
+
+    /// Set the deadline of `key`, `None` clearing it. Returns the previous one.
+    pub(crate) fn set(&self, key: &str, deadline: Option<Instant>) -> Option<Instant> {
+        let previous = match deadline {
+            Some(deadline) => self.deadlines.insert(key.to_string(), deadline),
+            None => self.deadlines.remove(key).map(|(_, deadline)| deadline),
+        };
+        if previous != deadline {
+            let mut total = self.lock();
+            if let Some(previous) = previous {
+                *total -= self.offset(previous);
+            }
+            if let Some(deadline) = deadline {
+                *total += self.offset(deadline);
+            }
+        }
+        previous
+    }
+
+    pub(crate) fn clear(&self) {
+        self.deadlines.clear();
+        *self.lock() = 0;
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.deadlines.len()
+    }
+
+    /// The average remaining time to live of the keys at `now`, in milliseconds.
+    /// Deadlines which passed but whose keys weren't removed yet lower it.
+    fn avg_ttl(&self, now: Instant) -> u64 {
+        let count = self.len() as u128;
+        if count == 0 {
+            return 0;
+        }
+        let elapsed = now.saturating_duration_since(self.epoch).as_millis();
+        (*self.lock() / count).saturating_sub(elapsed) as u64
+    }
+
+    fn offset(&self, deadline: Instant) -> u128 {
+        deadline.saturating_duration_since(self.epoch).as_millis()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, u128> {
+        // the sum is updated in a single statement, a panic can't corrupt it.
+        self.total.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Operations on keys whatever the type of their value.
+///
+/// Like every command touching several keys, they are only atomic when run
+/// under the [`Backend::lock_keys`] guard of their keys, which the server takes.
+impl Backend {
+    pub fn exists(&self, key: &str) -> bool {
+        self.key_type(key).is_some()
+    }
+
+    /// The type of the value at `key`, `None` if it doesn't exist.
+    pub fn key_type(&self, key: &str) -> Option<KeyType> {
+        let kind = if self.map.contains_key(key) {
+            KeyType::String
+        } else if self.hmap.contains_key(key) {
+            KeyType::Hash
+        } else if self.set.contains_key(key) {
+            KeyType::Set
+        } else if self.zset.contains_key(key) {
+            KeyType::ZSet
+        } else if self.list.contains_key(key) {
+            KeyType::List
+        } else if self.bloom.contains_key(key) {
+            KeyType::Bloom
+        } else if self.timeseries.contains_key(key) {
+            KeyType::TimeSeries
+        } else {
+            #[cfg(feature = "json")]
+            if self.json.contains_key(key) {
+                return Some(KeyType::Json);
+            }
+            return None;
+        };
+        Some(kind)
+    }
+
+    /// Fails if `key` holds a value of another type than `kind`. The write paths check
+    /// it before creating a value, without holding any map entry, and the server runs
+    /// them under the lock of the key: a key never holds values of two types.
+    pub(crate) fn check_type(&self, key: &str, kind: KeyType) -> Result<(), WrongType> {
+        match self.key_type(key) {
+            Some(held) if held != kind => Err(WrongType),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove the value of another type than `kind` at `key`, before a command which
+    /// replaces its destination whatever it holds, like SET, writes a `kind` value.
+    pub(crate) fn replace_type(&self, key: &str, kind: KeyType) {
+        if self.key_type(key).is_some_and(|held| held != kind) {
+            self.remove_key(key);
+        }
+    }
+
+    /// Remaining time to live of the key in milliseconds, -2 if it doesn't exist and
+    /// -1 if it has no deadline, like PTTL. Only hash fields expire, never whole keys.
+    pub fn pttl(&self, key: &str) -> i64 {
+        if !self.exists(key) {
+            return -2;
+        }
+        match self.key_deadline(key) {
+            Some(deadline) => deadline
+                .saturating_duration_since(Instant::now())
+                .as_millis() as i64,
+            None => -1,
+        }
+    }
+
+    /// When the key expires as a whole: a hash does once every field has a deadline.
+    fn key_deadline(&self, key: &str) -> Option<Instant> {
+        self.key_deadlines.get(key)
+    }
+
+    /// Remove the key, returns whether it existed.
+    pub fn remove_key(&self, key: &str) -> bool {
+        let mut removed = self.map.remove(key).is_some();
+        if self.hmap.remove(key).is_some() {
+            self.hexpires.remove(key);
+            self.key_deadlines.set(key, None);
+            self.reindex(key);
+            removed = true;
+        }
+        removed |= self.set.remove(key).is_some();
+        removed |= self.zset.remove(key).is_some();
+        removed |= self.list.remove(key).is_some();
+        removed |= self.bloom.remove(key).is_some();
+        removed |= self.timeseries.remove(key).is_some();
+        #[cfg(feature = "json")]
+        {
+            removed |= self.json.remove(key).is_some();
+        }
+        if removed {
+            self.key_changed(key);
+        }
+        removed
+    }
+
+    /// Move the value at `src` to `dst`, replacing its value, like RENAME.
+    /// Returns false if `src` doesn't exist.
+    pub fn rename(&self, src: &str, dst: &str) -> bool {
+        if !self.exists(src) {
+            return false;
+        }
+        if src == dst {
+            return true;
+        }
+        self.remove_key(dst);
+        if move_value(&self.hmap, src, dst) {
+            // the deadlines of the fields move with the hash.
+            if self.hexpires.remove(src).is_some() {
+                self.hexpires.insert(dst.to_string());
+                let deadline = self.key_deadlines.set(src, None);
+                self.key_deadlines.set(dst, deadline);
+                if let Some(deadline) = self.hmap.get(dst).and_then(|h| h.next_deadline()) {
+                    self.expiry_queue.schedule(dst, deadline);
+                }
+            }
+            self.reindex(src);
+            self.reindex(dst);
+        }
+        let _ = move_value(&self.map, src, dst)
+            || move_value(&self.set, src, dst)
+            || move_value(&self.zset, src, dst)
+            || move_value(&self.list, src, dst)
+            || move_value(&self.bloom, src, dst)
+            || move_value(&self.timeseries, src, dst);
+        #[cfg(feature = "json")]
+        move_value(&self.json, src, dst);
+        self.key_changed(src);
+        self.key_changed(dst);
+        true
+    }
+
+    /// The number of keys, and of the keys which expire, read from counters kept
+    /// up to date by the writes.
+    pub fn keyspace_stats(&self) -> KeyspaceStats {
+        KeyspaceStats {
+            keys: self.len(),
+            expires: self.key_deadlines.len(),
+            avg_ttl: self.key_deadlines.avg_ttl(Instant::now()),
+            subexpiry: self.hexpires.len(),
+        }
+    }
+
+    /// Call `f` once on every key, whatever the type of its value. The keys of a shard
+    /// are visited with the shard read-locked, so `f` must not modify the backend.
+    pub(crate) fn for_each_key(&self, mut f: impl FnMut(&str)) {
+        visit_keys(&self.map, &mut f);
+        visit_keys(&self.hmap, &mut f);
+        visit_keys(&self.set, &mut f);
+        visit_keys(&self.zset, &mut f);
+        visit_keys(&self.list, &mut f);
+        visit_keys(&self.bloom, &mut f);
+        visit_keys(&self.timeseries, &mut f);
+        #[cfg(feature = "json")]
+        visit_keys(&self.json, &mut f);
+    }
+}
+
+fn visit_keys<V>(map: &DashMap<String, V>, f: &mut impl FnMut(&str)) {
+    for entry in map.iter() {
+        f(entry.key());
+    }
+}
+
+fn move_value<V>(map: &DashMap<String, V>, src: &str, dst: &str) -> bool {
+    match map.remove(src) {
+        Some((_, value)) => {
+            map.insert(dst.to_string(), value);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        time::{Duration, Instant},
+    };
+
+    use crate::{BulkString, ExpireCondition, ListEnd};
+
+    use super::*;
+
+    #[test]
+    fn test_keyspace_stats() {
+        let backend = Backend::new();
+        assert_eq!(backend.keyspace_stats(), KeyspaceStats::default());
+
+        backend.set("s".to_string(), b"v".to_vec());
+        backend
+            .sadd("set".to_string(), HashSet::from([BulkString::new("m")]))
+            .unwrap();
+        for field in ["a", "b"] {
+            backend
+                .hset(
+                    "h".to_string(),
+                    field.to_string(),
+                    BulkString::new("v").into(),
+                )
+                .unwrap();
+        }
+        let later = Instant::now() + Duration::from_secs(100);
+        backend.hexpire("h", &["a".to_string()], later, ExpireCondition::Always);
+        let stats = backend.keyspace_stats();
+        assert_eq!((stats.keys, stats.expires, stats.subexpiry), (3, 0, 1));
+
+        // the hash expires as a whole once every field has a deadline, at the last one.
+        let last = later + Duration::from_secs(100);
+        backend.hexpire("h", &["b".to_string()], last, ExpireCondition::Always);
+        let stats = backend.keyspace_stats();
+        assert_eq!((stats.keys, stats.expires, stats.subexpiry), (3, 1, 1));
+        assert!((199_000..=200_000).contains(&stats.avg_ttl));
+        assert!(backend.pttl("h") > 199_000);
+        backend.rename("h", "h2");
+        assert_eq!(backend.keyspace_stats().expires, 1);
+        assert!(backend.pttl("h2") > 199_000);
+
+        // a field without a deadline keeps the hash.
+        backend
+            .hset(
+                "h2".to_string(),
+                "c".to_string(),
+                BulkString::new("v").into(),
+            )
+            .unwrap();
+        let stats = backend.keyspace_stats();
+        assert_eq!((stats.expires, stats.avg_ttl), (0, 0));
+        assert_eq!(backend.pttl("h2"), -1);
+        backend.hupdate("h2", "c", Option::take).unwrap();
+        assert_eq!(backend.keyspace_stats().expires, 1);
+
+        backend.remove_key("h2");
+        backend.rename("s", "s2");
+        assert_eq!(
+            backend.keyspace_stats(),
+            KeyspaceStats {
+                keys: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_one_type_per_key() {
+        let backend = Backend::new();
+        backend.set("s".to_string(), b"v".to_vec());
+        let members = HashSet::from([BulkString::new("m")]);
+        assert_eq!(
+            backend.sadd("s".to_string(), members.clone()),
+            Err(WrongType)
+        );
+        assert_eq!(
+            backend.push("s", vec![BulkString::new("e")], ListEnd::Tail),
+            Err(WrongType)
+        );
+        assert_eq!(
+            backend.zadd("s", vec![(1.0, BulkString::new("m"))]),
+            Err(WrongType)
+        );
+        assert_eq!(backend.key_type("s"), Some(KeyType::String));
+
+        backend.sadd("set".to_string(), members).unwrap();
+        assert_eq!(backend.update("set", |_| ()), Err(WrongType));
+        assert_eq!(
+            backend.smove("set", "s", BulkString::new("m")),
+            Err(WrongType)
+        );
+        assert!(backend.is_member("set".to_string(), BulkString::new("m")));
+
+        // SET replaces a value of any type.
+        backend.set("set".to_string(), b"v".to_vec());
+        assert_eq!(backend.key_type("set"), Some(KeyType::String));
+        assert!(backend.smembers("set").is_none());
+        assert_eq!(backend.keyspace_stats().keys, 2);
+    }
+
+    #[test]
+    fn test_rename_moves_any_type() {
+        let backend = Backend::new();
+        backend.set("s".to_string(), b"v".to_vec());
+        backend
+            .sadd("dst".to_string(), HashSet::from([BulkString::new("m")]))
+            .unwrap();
+        assert!(backend.rename("s", "dst"));
+        assert!(!backend.exists("s"));
+        assert_eq!(backend.get("dst"), Some("v".into()));
+        // the previous value of the destination is replaced.
+        assert!(backend.smembers("dst").is_none());
+
+        backend
+            .hset(
+                "h".to_string(),
+                "f".to_string(),
+                BulkString::new("v").into(),
+            )
+            .unwrap();
+        let later = Instant::now() + Duration::from_secs(100);
+        backend.hexpire("h", &["f".to_string()], later, ExpireCondition::Always);
+        assert!(backend.rename("h", "h2"));
+        assert_eq!(backend.hget("h2", "f"), Some(BulkString::new("v").into()));
+        assert!(backend.hexpires.contains("h2") && !backend.hexpires.contains("h"));
+
+        assert!(!backend.rename("missing", "x"));
+        assert!(backend.rename("h2", "h2"));
+        assert!(backend.remove_key("h2"));
+        assert!(!backend.exists("h2"));
+    }
+}
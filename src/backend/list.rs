@@ -0,0 +1,430 @@
+use std::{collections::VecDeque, mem::size_of};
+
+use crate::{Backend, BulkString, KeyType, WrongType};
+
+/// Most elements stored in a node of a [`QuickList`].
+const NODE_SIZE: usize = 128;
+
+/// A list stored as a deque of bounded nodes, like the Redis quicklist:
+/// pushes and pops at both ends are O(1), and inserting or removing in the
+/// middle only shifts the elements of one node.
+#[derive(Debug, Clone, Default)]
+pub struct QuickList {
+    nodes: VecDeque<VecDeque<BulkString>>,
+    len: usize,
+}
+
+impl QuickList {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push_front(&mut self, value: BulkString) {
+        match self.nodes.front_mut() {
+            Some(node) if node.len() < NODE_SIZE => node.push_front(value),
+            _ => self.nodes.push_front(VecDeque::from([value])),
+        }
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, value: BulkString) {
+        match self.nodes.back_mut() {
+            Some(node) if node.len() < NODE_SIZE => node.push_back(value),
+            _ => self.nodes.push_back(VecDeque::from([value])),
+        }
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<BulkString> {
+        let node = self.nodes.front_mut()?;
+        let value = node.pop_front();
+        if node.is_empty() {
+            self.nodes.pop_front();
+        }
+        self.len -= 1;
+        value
+    }
+
+    pub fn pop_back(&mut self) -> Option<BulkString> {
+        let node = self.nodes.back_mut()?;
+        let value = node.pop_back();
+        if node.is_empty() {
+            self.nodes.pop_back();
+        }
+        self.len -= 1;
+        value
+    }
+
+    /// The node holding the element at `index`, and its offset in the node.
+    fn locate(&self, mut index: usize) -> Option<(usize, usize)> {
+        for (i, node) in self.nodes.iter().enumerate() {
+            if index < node.len() {
+                return Some((i, index));
+            }
+            index -= node.len();
+        }
+        None
+    }
+
+    pub fn get(&self, index: usize) -> Option<&BulkString> {
+        let (node, offset) = self.locate(index)?;
+        self.nodes[node].get(offset)
+    }
+
+    /// The elements from `start`, in order.
+    pub fn iter_from(&self, start: usize) -> impl Iterator<Item = &BulkString> {
+        let (first, offset) = self.locate(start).unwrap_or((self.nodes.len(), 0));
+        self.nodes
+            .iter()
+            .skip(first)
+            .enumerate()
+            .flat_map(move |(i, node)| node.iter().skip(if i == 0 { offset } else { 0 }))
+    }
+
+    /// Insert `value` next to the first occurrence of `pivot`,
+    /// returns false if there is none.
+    pub fn insert(&mut self, pivot: &BulkString, value: BulkString, before: bool) -> bool {
+        let found = self
+            .nodes
+            .iter()
+            .enumerate()
+            .find_map(|(i, node)| node.iter().position(|v| v == pivot).map(|j| (i, j)));
+        let Some((i, j)) = found else {
+            return false;
+        };
+        let node = &mut self.nodes[i];
+        node.insert(if before { j } else { j + 1 }, value);
+        if node.len() > NODE_SIZE {
+            // split the node rather than letting it grow.
+            let half = node.split_off(node.len() / 2);
+            self.nodes.insert(i + 1, half);
+        }
+        self.len += 1;
+        true
+    }
+
+    /// Remove the occurrences of `value` like LREM: the first `count` ones from the head
+    /// if positive, from the tail if negative, all of them if 0. Returns how many were removed.
+    pub fn remove(&mut self, value: &BulkString, count: i64) -> usize {
+        let limit = if count == 0 {
+            usize::MAX
+        } else {
+            count.unsigned_abs() as usize
+        };
+        let mut removed = 0;
+        let from_tail = count < 0;
+        let indexes: Vec<usize> = if from_tail {
+            (0..self.nodes.len()).rev().collect()
+        } else {
+            (0..self.nodes.len()).collect()
+        };
+        for i in indexes {
+            let node = &mut self.nodes[i];
+            let mut kept = VecDeque::with_capacity(node.len());
+            let values: Vec<BulkString> = if from_tail {
+                node.drain(..).rev().collect()
+            } else {
+                node.drain(..).collect()
+            };
+            for v in values {
+                if removed < limit && v == *value {
+                    removed += 1;
+                } else if from_tail {
+                    kept.push_front(v);
+                } else {
+                    kept.push_back(v);
+                }
+            }
+            *node = kept;
+            if removed >= limit {
+                break;
+            }
+        }
+        self.nodes.retain(|node| !node.is_empty());
+        self.len -= removed;
+        removed
+    }
+
+    /// Estimated bytes used by each node: its elements and their slots.
+    pub fn node_sizes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.nodes.iter().map(|node| {
+            let values: usize = node.iter().map(|v| v.as_ref().len()).sum();
+            size_of::<VecDeque<BulkString>>() + node.capacity() * size_of::<BulkString>() + values
+        })
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Merge the nodes left partly empty by pops and removals into full nodes.
+    /// Returns whether there were such nodes.
+    pub(crate) fn compact(&mut self) -> bool {
+        if self.nodes.len() <= self.len.div_ceil(NODE_SIZE) {
+            return false;
+        }
+        let mut nodes: VecDeque<VecDeque<BulkString>> = VecDeque::new();
+        for value in self.nodes.drain(..).flatten() {
+            match nodes.back_mut() {
+                Some(node) if node.len() < NODE_SIZE => node.push_back(value),
+                _ => nodes.push_back(VecDeque::from([value])),
+            }
+        }
+        for node in nodes.iter_mut() {
+            node.shrink_to_fit();
+        }
+        self.nodes = nodes;
+        true
+    }
+}
+
+/// Which end of a list a command works on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Head,
+    Tail,
+}
+
+impl Backend {
+    /// Push the values one after the other, returns the length of the list.
+    pub fn push(
+        &self,
+        key: &str,
+        values: Vec<BulkString>,
+        end: ListEnd,
+    ) -> Result<usize, WrongType> {
+        self.check_type(key, KeyType::List)?;
+        let mut list = self.list.entry(key.to_string()).or_default();
+        for value in values {
+            match end {
+                ListEnd::Head => list.push_front(value),
+                ListEnd::Tail => list.push_back(value),
+            }
+        }
+        let len = list.len();
+        drop(list);
+        self.key_changed(key);
+        Ok(len)
+    }
+
+    /// Pop up to `count` values, `None` if the list doesn't exist.
+    pub fn pop(&self, key: &str, count: usize, end: ListEnd) -> Option<Vec<BulkString>> {
+        let mut list = self.list.get_mut(key)?;
+        let popped: Vec<BulkString> = (0..count)
+            .map_while(|_| match end {
+                ListEnd::Head => list.pop_front(),
+                ListEnd::Tail => list.pop_back(),
+            })
+            .collect();
+        let empty = list.is_empty();
+        drop(list);
+        if empty {
+            self.list.remove_if(key, |_, list| list.is_empty());
+        }
+        if !popped.is_empty() {
+            self.key_changed(key);
+        }
+        Some(popped)
+    }
+
+    /// Pop up to `count` values from the first non-empty list of `keys`, like LMPOP.
+    /// Returns its key and the values, `None` if every list is empty.
+    pub fn lmpop(
+        &self,
+        keys: &[String],
+        count: usize,
+        end: ListEnd,
+    ) -> Result<Option<(String, Vec<BulkString>)>, WrongType> {
+        for key in keys {
+            self.check_type(key, KeyType::List)?;
+            if let Some(values) = self.pop(key, count, end) {
+                return Ok(Some((key.clone(), values)));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn llen(&self, key: &str) -> usize {
+        self.list.get(key).map_or(0, |list| list.len())
+    }
+
+    /// The element at `index`, negative indexes counting from the tail.
+    pub fn lindex(&self, key: &str, index: i64) -> Option<BulkString> {
+        let list = self.list.get(key)?;
+        let index = if index < 0 {
+            usize::try_from(list.len() as i64 + index).ok()?
+        } else {
+            index as usize
+        };
+        list.get(index).cloned()
+    }
+
+    /// The elements in `start..=stop`, negative indexes counting from the tail like LRANGE.
+    pub fn lrange(&self, key: &str, start: i64, stop: i64) -> Vec<BulkString> {
+        let Some(list) = self.list.get(key) else {
+            return vec![];
+        };
+        let len = list.len() as i64;
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let stop = if stop < 0 {
+            len + stop
+        } else {
+            stop.min(len - 1)
+        };
+        if start > stop || start >= len {
+            return vec![];
+        }
+        list.iter_from(start as usize)
+            .take((stop - start + 1) as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Insert `value` next to `pivot` like LINSERT: returns the new length,
+    /// -1 if `pivot` isn't in the list, 0 if the list doesn't exist.
+    pub fn linsert(&self, key: &str, pivot: &BulkString, value: BulkString, before: bool) -> i64 {
+        let Some(mut list) = self.list.get_mut(key) else {
+            return 0;
+        };
+        if !list.insert(pivot, value, before) {
+            return -1;
+        }
+        let len = list.len();
+        drop(list);
+        self.key_changed(key);
+        len as i64
+    }
+
+    /// Remove occurrences of `value` like LREM, returns how many were removed.
+    pub fn lrem(&self, key: &str, count: i64, value: &BulkString) -> usize {
+        let Some(mut list) = self.list.get_mut(key) else {
+            return 0;
+        };
+        let removed = list.remove(value, count);
+        let empty = list.is_empty();
+        drop(list);
+        if empty {
+            self.list.remove_if(key, |_, list| list.is_empty());
+        }
+        if removed > 0 {
+            self.key_changed(key);
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(list: &QuickList) -> Vec<String> {
+        list.iter_from(0)
+            .map(|v| String::from_utf8_lossy(v.as_ref()).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_quicklist_nodes_stay_bounded() {
+        let mut list = QuickList::default();
+        for i in 0..NODE_SIZE * 2 {
+            list.push_back(BulkString::new(i.to_string()));
+        }
+        list.push_front(BulkString::new("head"));
+        assert_eq!(list.node_count(), 3);
+        assert_eq!(list.get(1), Some(&BulkString::new("0")));
+
+        // inserting into a full node splits it.
+        assert!(list.insert(&BulkString::new("5"), BulkString::new("x"), true));
+        assert_eq!(list.node_count(), 4);
+        assert_eq!(list.len(), NODE_SIZE * 2 + 2);
+        assert_eq!(list.get(6), Some(&BulkString::new("x")));
+        assert_eq!(list.iter_from(5).take(3).count(), 3);
+        assert!(!list.insert(&BulkString::new("missing"), BulkString::new("y"), false));
+
+        assert_eq!(list.pop_front(), Some(BulkString::new("head")));
+        assert_eq!(list.pop_back(), Some(BulkString::new("255")));
+        assert_eq!(list.node_sizes().count(), list.node_count());
+    }
+
+    #[test]
+    fn test_quicklist_remove() {
+        let mut list = QuickList::default();
+        for v in ["a", "b", "a", "c", "a"] {
+            list.push_back(BulkString::new(v));
+        }
+        assert_eq!(list.remove(&BulkString::new("a"), -2), 2);
+        assert_eq!(values(&list), vec!["a", "b", "c"]);
+        assert_eq!(list.remove(&BulkString::new("a"), 0), 1);
+        assert_eq!(values(&list), vec!["b", "c"]);
+        assert_eq!(list.len(), 2);
+    }
+
+    #[test]
+    fn test_list_commands() {
+        let backend = Backend::new();
+        let push = |end, vs: &[&str]| {
+            backend
+                .push("l", vs.iter().map(|v| BulkString::new(*v)).collect(), end)
+                .unwrap()
+        };
+        assert_eq!(push(ListEnd::Tail, &["b", "c"]), 2);
+        assert_eq!(push(ListEnd::Head, &["a", "z"]), 4);
+        assert_eq!(
+            backend.lrange("l", 1, -1),
+            ["a", "b", "c"].map(BulkString::new)
+        );
+        assert_eq!(backend.lindex("l", -1), Some(BulkString::new("c")));
+        assert_eq!(backend.lindex("l", 4), None);
+        assert_eq!(
+            backend.linsert("l", &BulkString::new("b"), BulkString::new("x"), false),
+            5
+        );
+        assert_eq!(
+            backend.pop("l", 2, ListEnd::Tail),
+            Some(vec![BulkString::new("c"), BulkString::new("x")])
+        );
+        assert_eq!(
+            backend.pop("l", 10, ListEnd::Head).map(|v| v.len()),
+            Some(3)
+        );
+        assert!(!backend.list.contains_key("l"));
+        assert_eq!(backend.pop("l", 1, ListEnd::Head), None);
+    }
+
+    #[test]
+    fn test_lmpop() {
+        let backend = Backend::new();
+        let keys = ["a", "b", "c"].map(String::from);
+        let values = vec![BulkString::new("x"), BulkString::new("y")];
+        backend.push("b", values, ListEnd::Tail).unwrap();
+        backend
+            .push("c", vec![BulkString::new("z")], ListEnd::Tail)
+            .unwrap();
+        assert_eq!(
+            backend.lmpop(&keys, 1, ListEnd::Tail),
+            Ok(Some(("b".to_string(), vec![BulkString::new("y")])))
+        );
+        assert_eq!(
+            backend.lmpop(&keys, 5, ListEnd::Head),
+            Ok(Some(("b".to_string(), vec![BulkString::new("x")])))
+        );
+        assert!(!backend.list.contains_key("b"));
+        assert_eq!(
+            backend.lmpop(&keys, 5, ListEnd::Head),
+            Ok(Some(("c".to_string(), vec![BulkString::new("z")])))
+        );
+        assert_eq!(backend.lmpop(&keys, 1, ListEnd::Head), Ok(None));
+
+        // the keys are checked in order, up to the first non-empty list.
+        backend.set("a".to_string(), b"v".to_vec());
+        assert_eq!(backend.lmpop(&keys, 1, ListEnd::Head), Err(WrongType));
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    cell::Cell,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use crate::Backend;
+
+/// Number of locks the keyspace is striped over.
+const KEY_LOCK_STRIPES: usize = 1024;
+
+/// Locks serializing the commands touching several keys with every other command
+/// on these keys, so that multi-step commands like RENAME or SMOVE are linearizable.
+///
+/// Commands on a single key are atomic thanks to the maps' entry guards,
+/// they only need a shared lock to not observe a multi-key command half-done.
+#[derive(Debug)]
+pub struct KeyLocks {
+    stripes: Vec<RwLock<()>>,
+}
+
+/// Held while a command runs, releases its key locks when dropped.
+#[derive(Debug, Default)]
+pub struct KeyGuard<'a> {
+    _shared: Vec<RwLockReadGuard<'a, ()>>,
+    _exclusive: Vec<RwLockWriteGuard<'a, ()>>,
+}
+
+/// Held while a transaction runs its commands on the thread which locked their keys.
+#[derive(Debug)]
+pub struct TransactionGuard<'a> {
+    _keys: KeyGuard<'a>,
+}
+
+thread_local! {
+    /// Whether the thread holds a [`TransactionGuard`], which already covers the keys
+    /// of the commands it runs: locking them again would deadlock.
+    static IN_TRANSACTION: Cell<bool> = const { Cell::new(false) };
+}
+
+impl Default for KeyLocks {
+    fn default() -> Self {
+        Self {
+            stripes: (0..KEY_LOCK_STRIPES).map(|_| RwLock::new(())).collect(),
+        }
+    }
+}
+
+impl KeyLocks {
+    /// The stripes of the keys, sorted and deduplicated so that locking them in order
+    /// can't deadlock with another command locking an overlapping set of keys.
+    fn stripes<'k>(&self, keys: impl IntoIterator<Item = &'k str>) -> Vec<usize> {
+        let mut stripes: Vec<usize> = keys
+            .into_iter()
+            .map(|key| {
+                let mut hasher = DefaultHasher::new();
+                key.hash(&mut hasher);
+                hasher.finish() as usize % self.stripes.len()
+            })
+            .collect();
+        stripes.sort_unstable();
+        stripes.dedup();
+        stripes
+    }
+}
+
+impl Backend {
+    /// Lock the keys of a command for the lifetime of the guard:
+    /// exclusively if the command touches several keys, shared otherwise.
+    pub fn lock_keys<'k>(&self, keys: impl IntoIterator<Item = &'k str>) -> KeyGuard<'_> {
+        if IN_TRANSACTION.get() {
+            return KeyGuard::default();
+        }
+        let mut keys: Vec<&str> = keys.into_iter().collect();
+        keys.sort_unstable();
+        keys.dedup();
+        // distinct keys may share a stripe, which must still be locked exclusively.
+        let exclusive = keys.len() > 1;
+        let stripes = self.key_locks.stripes(keys);
+        // a poisoned lock protects no data, the panicking command didn't break anything.
+        let locks = stripes.iter().map(|i| &self.key_locks.stripes[*i]);
+        if exclusive {
+            KeyGuard {
+                _exclusive: locks
+                    .map(|lock| lock.write().unwrap_or_else(|e| e.into_inner()))
+                    .collect(),
+                ..Default::default()
+            }
+        } else {
+            KeyGuard {
+                _shared: locks
+                    .map(|lock| lock.read().unwrap_or_else(|e| e.into_inner()))
+                    .collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Lock every key exclusively, for the commands replacing the whole keyspace.
+    pub fn lock_all(&self) -> KeyGuard<'_> {
+        if IN_TRANSACTION.get() {
+            return KeyGuard::default();
+        }
+        KeyGuard {
+            _exclusive: self
+                .key_locks
+                .stripes
+                .iter()
+                .map(|lock| lock.write().unwrap_or_else(|e| e.into_inner()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Lock the keys of a transaction exclusively, or every key if `all`, until the
+    /// guard is dropped. The commands the thread runs meanwhile don't lock their keys.
+    pub fn lock_transaction<'k>(
+        &self,
+        keys: impl IntoIterator<Item = &'k str>,
+        all: bool,
+    ) -> TransactionGuard<'_> {
+        let keys = if all {
+            self.lock_all()
+        } else {
+            let stripes = self.key_locks.stripes(keys);
+            KeyGuard {
+                _exclusive: stripes
+                    .iter()
+                    .map(|i| {
+                        self.key_locks.stripes[*i]
+                            .write()
+                            .unwrap_or_else(|e| e.into_inner())
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        };
+        IN_TRANSACTION.set(true);
+        TransactionGuard { _keys: keys }
+    }
+}
+
+impl Drop for TransactionGuard<'_> {
+    fn drop(&mut self) {
+        IN_TRANSACTION.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_multi_key_commands_are_exclusive() {
+        let backend = Backend::new();
+        let shared = backend.lock_keys(["a"]);
+        // single-key commands don't block each other.
+        let other = backend.lock_keys(["a"]);
+        drop(other);
+
+        let (tx, rx) = mpsc::channel();
+        let handle = {
+            let backend = backend.clone();
+            thread::spawn(move || {
+                let _guard = backend.lock_keys(["b", "a"]);
+                tx.send(()).unwrap();
+            })
+        };
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        drop(shared);
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_lock_all_waits_for_every_key() {
+        let backend = Backend::new();
+        let shared = backend.lock_keys(["a"]);
+
+        let (tx, rx) = mpsc::channel();
+        let handle = {
+            let backend = backend.clone();
+            thread::spawn(move || {
+                let _guard = backend.lock_all();
+                tx.send(()).unwrap();
+            })
+        };
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        drop(shared);
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_transaction_locks_keys_once() {
+        let backend = Backend::new();
+        let guard = backend.lock_transaction(["a"], false);
+        // the commands of the transaction don't wait for the guard of their thread.
+        drop(backend.lock_keys(["a", "b"]));
+        drop(backend.lock_all());
+
+        let (tx, rx) = mpsc::channel();
+        let handle = {
+            let backend = backend.clone();
+            thread::spawn(move || {
+                let _guard = backend.lock_keys(["a"]);
+                tx.send(()).unwrap();
+            })
+        };
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        drop(guard);
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    cmp::Reverse,
+    mem::size_of,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{Backend, BulkString, RespFrame, SharingStats, TimeSeries};
+
+/// Values larger than this are reported as big keys.
+const BIG_VALUE_BYTES: usize = 1024 * 1024;
+/// Containers with more elements than this are reported as big keys.
+const BIG_CONTAINER_LEN: usize = 100_000;
+/// Tracked keys per tracking client above which the tracking table is considered bloated.
+const TRACKED_KEYS_PER_CLIENT: usize = 100_000;
+/// Allocator fragmentation ratio above which it is reported.
+const HIGH_FRAGMENTATION_RATIO: f64 = 1.4;
+/// Below this amount of allocated memory fragmentation is not meaningful.
+const MIN_FRAGMENTATION_BYTES: usize = 10 * 1024 * 1024;
+/// How long a measure of [`Backend::used_memory`] is reused.
+const USED_MEMORY_TTL: Duration = Duration::from_millis(100);
+
+/// Approximate memory usage of the server, used by `MEMORY DOCTOR` and `INFO memory`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryStats {
+    pub keys: usize,
+    /// Estimated bytes used by keys and values.
+    pub dataset_bytes: usize,
+    /// Keys whose value is bigger than [`BIG_VALUE_BYTES`] or has more than
+    /// [`BIG_CONTAINER_LEN`] elements, with their estimated size.
+    pub big_keys: Vec<(String, usize)>,
+    pub shard_channels: usize,
+    pub shard_subscribers: usize,
+    pub tracking_clients: usize,
+    pub tracked_keys: usize,
+    pub allocator: Option<AllocatorStats>,
+    pub sharing: SharingStats,
+}
+
+/// Statistics reported by the global allocator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocatorStats {
+    /// Bytes allocated by the application.
+    pub allocated: usize,
+    /// Bytes in physically resident pages mapped by the allocator.
+    pub resident: usize,
+}
+
+impl AllocatorStats {
+    pub fn fragmentation_ratio(&self) -> f64 {
+        if self.allocated == 0 {
+            return 0.0;
+        }
+        self.resident as f64 / self.allocated as f64
+    }
+
+    #[cfg(feature = "jemalloc")]
+    fn current() -> Option<Self> {
+        use tikv_jemalloc_ctl::{epoch, stats};
+
+        // stats are cached by jemalloc until the epoch is advanced.
+        epoch::advance().ok()?;
+        Some(Self {
+            allocated: stats::allocated::read().ok()?,
+            resident: stats::resident::read().ok()?,
+        })
+    }
+
+    #[cfg(not(feature = "jemalloc"))]
+    fn current() -> Option<Self> {
+        None
+    }
+}
+
+/// The last measure of the used memory, with when it was taken.
+#[derive(Debug, Default)]
+pub(crate) struct UsedMemory(Mutex<Option<(Instant, usize)>>);
+
+impl Backend {
+    /// Bytes used by the server: the ones allocated, as reported by the allocator, or the
+    /// estimated size of the dataset when the allocator has no stats. A measure is reused
+    /// for [`USED_MEMORY_TTL`], so that checking every write against a limit is cheap.
+    pub fn used_memory(&self) -> usize {
+        let mut last = self.used_memory.0.lock().unwrap();
+        match *last {
+            Some((at, bytes)) if at.elapsed() < USED_MEMORY_TTL => bytes,
+            _ => {
+                let bytes = match AllocatorStats::current() {
+                    Some(allocator) => allocator.allocated,
+                    None => self.memory_stats().dataset_bytes,
+                };
+                *last = Some((Instant::now(), bytes));
+                bytes
+            }
+        }
+    }
+
+    /// Walk the keyspace and collect memory statistics.
+    pub fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+        let mut account = |key: &str, size: usize, len: usize| {
+            let size = key.len() + size;
+            stats.keys += 1;
+            stats.dataset_bytes += size;
+            if size > BIG_VALUE_BYTES || len > BIG_CONTAINER_LEN {
+                stats.big_keys.push((key.to_string(), size));
+            }
+        };
+
+        for entry in self.map.iter() {
+            account(entry.key(), entry.value().allocated_size(), 1);
+        }
+        for entry in self.hmap.iter() {
+            let size = entry
+                .iter()
+                .map(|(field, value)| field.len() + frame_size(value))
+                .sum();
+            account(entry.key(), size, entry.len());
+        }
+        for entry in self.set.iter() {
+            let size = entry.iter().map(|member| bulk_size(&member)).sum();
+            account(entry.key(), size, entry.len());
+        }
+        for entry in self.zset.iter() {
+            let size = entry
+                .iter()
+                .map(|(member, _)| zset_entry_size(&member))
+                .sum();
+            account(entry.key(), size, entry.len());
+        }
+        for entry in self.list.iter() {
+            account(entry.key(), entry.node_sizes().sum(), entry.len());
+        }
+        for entry in self.bloom.iter() {
+            account(entry.key(), entry.size(), entry.len() as usize);
+        }
+        for entry in self.timeseries.iter() {
+            account(entry.key(), series_size(&entry), entry.len());
+        }
+        stats.big_keys.sort_by_key(|(_, size)| Reverse(*size));
+
+        stats.shard_channels = self.shard_channels.len();
+        stats.shard_subscribers = self.shard_channels.iter().map(|c| c.len()).sum();
+        stats.tracking_clients = self.tracking.clients_len();
+        stats.tracked_keys = self.tracking.keys_len();
+        stats.allocator = AllocatorStats::current();
+        stats.sharing = self.values.stats();
+        stats
+    }
+
+    /// Estimated bytes used by the key and its value, `None` if the key doesn't exist.
+    ///
+    /// Only `samples` elements of a container are measured and the result is
+    /// extrapolated to the whole container, 0 means measuring every element.
+    pub fn memory_usage(&self, key: &str, samples: usize) -> Option<usize> {
+        let size = if let Some(value) = self.map.get(key) {
+            value.allocated_size()
+        } else if let Some(hmap) = self.hmap.get(key) {
+            sampled_size(
+                hmap.iter()
+                    .map(|(field, value)| field.len() + frame_size(value)),
+                hmap.len(),
+                samples,
+            )
+        } else if let Some(set) = self.set.get(key) {
+            sampled_size(
+                set.iter().map(|member| bulk_size(&member)),
+                set.len(),
+                samples,
+            )
+        } else if let Some(zset) = self.zset.get(key) {
+            sampled_size(
+                zset.iter().map(|(member, _)| zset_entry_size(&member)),
+                zset.len(),
+                samples,
+            )
+        } else if let Some(list) = self.list.get(key) {
+            // lists are sampled by nodes, which account for their own overhead.
+            sampled_size(list.node_sizes(), list.node_count(), samples)
+        } else if let Some(filter) = self.bloom.get(key) {
+            // the bit arrays are measured exactly, there is nothing to sample.
+            filter.size()
+        } else if let Some(series) = self.timeseries.get(key) {
+            series_size(&series)
+        } else {
+            return None;
+        };
+        Some(key.len() + size)
+    }
+
+    /// Human-readable advice about the memory usage of the server.
+    pub fn memory_doctor(&self) -> String {
+        memory_report(&self.memory_stats())
+    }
+}
+
+fn memory_report(stats: &MemoryStats) -> String {
+    if stats.keys == 0 && stats.shard_subscribers == 0 && stats.tracking_clients == 0 {
+        return "Hi Sam, this instance is empty or is using very little memory, \
+                my issues detector can't be used in these conditions."
+            .to_string();
+    }
+
+    let mut issues = Vec::new();
+    if !stats.big_keys.is_empty() {
+        let keys = stats
+            .big_keys
+            .iter()
+            .take(5)
+            .map(|(key, size)| format!("'{}' (~{} bytes)", key, size))
+            .collect::<Vec<_>>()
+            .join(", ");
+        issues.push(format!(
+            "Big keys: {} key(s) have huge values or too many elements, e.g. {}. \
+             Big values slow down every command touching them and make memory usage spiky, \
+             consider splitting them into smaller keys.",
+            stats.big_keys.len(),
+            keys
+        ));
+    }
+    if stats.tracked_keys > TRACKED_KEYS_PER_CLIENT * stats.tracking_clients.max(1) {
+        issues.push(format!(
+            "Client-side caching: {} keys are remembered for {} tracking client(s). \
+             Keys read by clients are only forgotten once modified, \
+             consider using the BCAST mode for clients reading many keys.",
+            stats.tracked_keys, stats.tracking_clients
+        ));
+    }
+    if let Some(allocator) = stats.allocator {
+        let ratio = allocator.fragmentation_ratio();
+        if allocator.allocated > MIN_FRAGMENTATION_BYTES && ratio > HIGH_FRAGMENTATION_RATIO {
+            issues.push(format!(
+                "High allocator fragmentation: the ratio between resident ({} bytes) \
+                 and allocated ({} bytes) memory is {:.2}. \
+                 This is usually caused by deleting or shrinking many big values.",
+                allocator.resident, allocator.allocated, ratio
+            ));
+        }
+    }
+
+    if issues.is_empty() {
+        return "Hi Sam, I can't find any memory issue in your instance. \
+                I can only account for what occurs on this base."
+            .to_string();
+    }
+    let mut report =
+        String::from("Sam, I detected a few issues in this R-Redis instance memory implants:\n\n");
+    for issue in issues {
+        report.push_str(" * ");
+        report.push_str(&issue);
+        report.push_str("\n\n");
+    }
+    report.push_str("I'm here to keep you safe, Sam. I want to help you.\n");
+    report
+}
+
+/// Estimated number of bytes used to store the frame.
+pub(crate) fn frame_size(frame: &RespFrame) -> usize {
+    let inner = match frame {
+        RespFrame::SimpleString(s) => s.0.len(),
+        RespFrame::Error(e) => e.0.len(),
+        RespFrame::BulkString(s) => bulk_size(s),
+        RespFrame::Array(array) => array.iter().map(frame_size).sum(),
+        RespFrame::Map(map) => map.iter().map(|(k, v)| frame_size(k) + frame_size(v)).sum(),
+        RespFrame::Set(set) => set.iter().map(frame_size).sum(),
+        RespFrame::Push(push) => push.iter().map(frame_size).sum(),
+        RespFrame::Null(_)
+        | RespFrame::Integer(_)
+        | RespFrame::Boolean(_)
+        | RespFrame::Double(_) => 0,
+    };
+    size_of::<RespFrame>() + inner
+}
+
+/// Length of the frame's content, without the size of the frame itself.
+pub(crate) fn frame_len(frame: &RespFrame) -> usize {
+    frame_size(frame) - size_of::<RespFrame>()
+}
+
+/// Extrapolate the size of a container with `len` elements from its first `samples` elements.
+fn sampled_size(sizes: impl Iterator<Item = usize>, len: usize, samples: usize) -> usize {
+    if samples == 0 || samples >= len {
+        return sizes.sum();
+    }
+    let sampled: usize = sizes.take(samples).sum();
+    sampled * len / samples
+}
+
+/// A member with its score, stored once in the dictionary and once in the skiplist.
+fn zset_entry_size(member: &BulkString) -> usize {
+    2 * (bulk_size(member) + size_of::<f64>())
+}
+
+fn series_size(series: &TimeSeries) -> usize {
+    let labels: usize = series.labels().iter().map(|(l, v)| l.len() + v.len()).sum();
+    series.len() * size_of::<(u64, f64)>() + labels
+}
+
+fn bulk_size(s: &BulkString) -> usize {
+    s.as_ref().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::BloomFilter;
+
+    use super::*;
+
+    #[test]
+    fn test_memory_stats() {
+        let backend = Backend::new();
+        backend.set("small".to_string(), "v".into());
+        backend.set("big".to_string(), vec![b'x'; BIG_VALUE_BYTES]);
+        let members: HashSet<BulkString> = (0..=BIG_CONTAINER_LEN)
+            .map(|i| BulkString::new(i.to_string()))
+            .collect();
+        backend.sadd("bigset".to_string(), members).unwrap();
+
+        let stats = backend.memory_stats();
+        assert_eq!(stats.keys, 3);
+        assert!(stats.dataset_bytes > BIG_VALUE_BYTES);
+        let big_keys: Vec<&str> = stats.big_keys.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(big_keys, vec!["big", "bigset"]);
+
+        let report = backend.memory_doctor();
+        assert!(report.contains("Big keys: 2 key(s)"), "{}", report);
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let backend = Backend::new();
+        assert_eq!(backend.memory_usage("missing", 5), None);
+
+        backend.set("k".to_string(), "v".into());
+        assert_eq!(backend.memory_usage("k", 5), Some(2));
+
+        let members: HashSet<BulkString> = (0..100)
+            .map(|i| BulkString::new(format!("{:03}", i)))
+            .collect();
+        backend.sadd("set".to_string(), members).unwrap();
+        // all members have the same size, so sampling is exact.
+        assert_eq!(backend.memory_usage("set", 5), Some(3 + 300));
+        assert_eq!(backend.memory_usage("set", 0), Some(3 + 300));
+
+        // 100 items at 1% need about 960 bits.
+        backend
+            .bf_reserve("bf", BloomFilter::new(0.01, 100, None))
+            .unwrap();
+        assert_eq!(backend.memory_usage("bf", 5), Some(2 + 120));
+    }
+
+    #[test]
+    fn test_memory_doctor_no_issues() {
+        let backend = Backend::new();
+        assert!(backend.memory_doctor().contains("instance is empty"));
+
+        backend.set("k".to_string(), "v".into());
+        assert!(backend
+            .memory_doctor()
+            .contains("can't find any memory issue"));
+    }
+
+    #[test]
+    fn test_memory_report_fragmentation() {
+        let stats = MemoryStats {
+            keys: 1,
+            allocator: Some(AllocatorStats {
+                allocated: 100 * 1024 * 1024,
+                resident: 200 * 1024 * 1024,
+            }),
+            ..Default::default()
+        };
+        assert!(memory_report(&stats).contains("ratio between resident"));
+    }
+}
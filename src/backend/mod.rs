@@ -1,17 +1,127 @@
-use std::{collections::HashSet, ops::Deref, sync::Arc};
+mod bloom;
+mod changes;
+mod checksum;
+mod clients;
+mod compress;
+mod encoding;
+mod expiry;
+mod export;
+mod hash;
+mod intern;
+mod iter;
+#[cfg(feature = "json")]
+mod json;
+mod keyspace;
+mod list;
+mod locks;
+mod memory;
+mod optimize;
+mod pubsub;
+mod push;
+mod rdb;
+mod sample;
+mod scan;
+mod search;
+mod set;
+mod slowlog;
+mod stats;
+mod string;
+mod tier;
+mod timeseries;
+mod tracking;
+mod watch;
+mod zset;
 
-use dashmap::{DashMap, DashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    ops::Deref,
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
 
-use crate::{BulkString, RespFrame};
+use bytes::Bytes;
+use dashmap::{mapref::entry::Entry, DashMap, DashSet};
+use indexmap::IndexMap;
+
+use crate::{BulkString, Cluster, EncodingConfig, RespFrame, RespNull, TieringConfig};
+
+pub(crate) use self::{
+    checksum::{checksum_frame, checksum_of, crc64, BlockWriter},
+    string::grow_string,
+    tier::SPILL_INTERVAL,
+};
+use self::{keyspace::KeyDeadlines, memory::UsedMemory, tier::Tier};
+
+pub use self::{
+    bloom::{
+        BadChunk, BloomFilter, FilterFull, DEFAULT_CAPACITY, DEFAULT_ERROR_RATE, DEFAULT_EXPANSION,
+    },
+    changes::{ChangeFeed, ChangeKind, KeyChange},
+    clients::{ClientRegistry, ConnectedClient},
+    compress::StoredString,
+    encoding::{Encoding, ObjectInternals},
+    expiry::ExpiryQueue,
+    export::ExportStats,
+    hash::{ExpireCondition, HashValue},
+    intern::{SharingStats, StringValue, ValuePool},
+    keyspace::{KeyType, KeyspaceStats, WrongType},
+    list::{ListEnd, QuickList},
+    locks::{KeyGuard, KeyLocks, TransactionGuard},
+    memory::{AllocatorStats, MemoryStats},
+    optimize::OptimizeStats,
+    pubsub::Subscriber,
+    push::{push_queue, PushReceiver, PushSender},
+    rdb::{RdbError, RdbStats},
+    search::{FieldType, Query, SearchError, SearchIndex},
+    set::SetValue,
+    slowlog::{Slowlog, SlowlogEntry},
+    stats::{ServerStats, StatsSnapshot},
+    tier::SpilledValue,
+    timeseries::{Aggregation, TimeSeries, TimeSeriesError},
+    tracking::TrackingTable,
+    watch::WatchTable,
+    zset::{
+        parse_score, LexBound, ScoreBound, SortedSet, ZAddFlags, ZIncrError, ZRangeBy, ZRangeSpec,
+    },
+};
 
 #[derive(Debug, Clone)]
 pub struct Backend(Arc<BackendInner>);
 
 #[derive(Debug)]
 pub struct BackendInner {
-    pub(crate) map: DashMap<String, RespFrame>,
-    pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
-    pub(crate) set: DashMap<String, DashSet<BulkString>>,
+    pub(crate) map: DashMap<String, StoredString>,
+    pub(crate) values: ValuePool,
+    pub(crate) hmap: DashMap<String, HashValue>,
+    /// Keys of the hashes having fields with a deadline.
+    pub(crate) hexpires: DashSet<String>,
+    /// Deadlines of the keys which expire as a whole.
+    pub(crate) key_deadlines: KeyDeadlines,
+    pub(crate) expiry_queue: ExpiryQueue,
+    pub(crate) set: DashMap<String, SetValue>,
+    pub(crate) zset: DashMap<String, SortedSet>,
+    pub(crate) list: DashMap<String, QuickList>,
+    pub(crate) bloom: DashMap<String, BloomFilter>,
+    pub(crate) timeseries: DashMap<String, TimeSeries>,
+    /// Secondary indexes over hash fields, by name.
+    pub(crate) indexes: DashMap<String, SearchIndex>,
+    pub(crate) key_locks: KeyLocks,
+    #[cfg(feature = "json")]
+    pub(crate) json: DashMap<String, serde_json::Value>,
+    pub(crate) shard_channels: DashMap<String, HashMap<u64, PushSender>>,
+    pub(crate) tracking: TrackingTable,
+    pub(crate) watches: WatchTable,
+    pub(crate) clients: ClientRegistry,
+    pub(crate) changes: ChangeFeed,
+    pub(crate) slowlog: Slowlog,
+    pub(crate) stats: ServerStats,
+    pub(crate) used_memory: UsedMemory,
+    pub(crate) encoding: EncodingConfig,
+    /// Where cold string values are spilled, if enabled.
+    pub(crate) tier: Option<Tier>,
+    /// The cluster this node is part of, in cluster mode.
+    pub(crate) cluster: OnceLock<Arc<Cluster>>,
 }
 
 impl Deref for Backend {
@@ -31,8 +141,31 @@ impl Default for BackendInner {
     fn default() -> Self {
         Self {
             map: DashMap::new(),
+            values: ValuePool::default(),
             hmap: DashMap::new(),
+            hexpires: DashSet::new(),
+            key_deadlines: KeyDeadlines::default(),
+            expiry_queue: ExpiryQueue::default(),
             set: DashMap::new(),
+            zset: DashMap::new(),
+            list: DashMap::new(),
+            bloom: DashMap::new(),
+            timeseries: DashMap::new(),
+            indexes: DashMap::new(),
+            key_locks: KeyLocks::default(),
+            #[cfg(feature = "json")]
+            json: DashMap::new(),
+            shard_channels: DashMap::new(),
+            tracking: TrackingTable::default(),
+            watches: WatchTable::default(),
+            clients: ClientRegistry::default(),
+            changes: ChangeFeed::default(),
+            slowlog: Slowlog::default(),
+            stats: ServerStats::default(),
+            used_memory: UsedMemory::default(),
+            encoding: EncodingConfig::default(),
+            tier: None,
+            cluster: OnceLock::new(),
         }
     }
 }
@@ -42,60 +175,313 @@ impl Backend {
         Self::default()
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+    /// A backend whose small collections switch encodings at the thresholds of `encoding`.
+    pub fn with_encoding(encoding: EncodingConfig) -> Self {
+        Self(Arc::new(BackendInner {
+            encoding,
+            ..Default::default()
+        }))
+    }
+
+    /// A backend which also spills its cold string values to disk, see [`TieringConfig`].
+    pub fn with_tiering(encoding: EncodingConfig, tiering: TieringConfig) -> io::Result<Self> {
+        Ok(Self(Arc::new(BackendInner {
+            encoding,
+            tier: Some(Tier::open(tiering)?),
+            ..Default::default()
+        })))
+    }
+
+    /// Switch to cluster mode: keys are only served for the slots assigned to this node.
+    /// Returns false if cluster mode was already enabled.
+    pub(crate) fn enable_cluster(&self, cluster: Arc<Cluster>) -> bool {
+        self.cluster.set(cluster).is_ok()
     }
 
-    pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+    /// The cluster this node is part of, `None` outside of cluster mode.
+    pub fn cluster(&self) -> Option<&Arc<Cluster>> {
+        self.cluster.get()
+    }
+
+    /// A handle on the stored value: it shares the payload rather than copying it,
+    /// so the value can be written to a reply while the key is modified.
+    /// Compressed values are decompressed into a new buffer, spilled ones read back.
+    pub fn get(&self, key: &str) -> Option<Bytes> {
+        let value = self.map.get(key)?;
+        let res = if matches!(*value, StoredString::Spilled(_)) {
+            drop(value);
+            self.fault_in(key)
+        } else {
+            let res = value.to_bytes();
+            drop(value);
+            Some(res)
+        };
+        self.touch(key);
+        res
+    }
+
+    /// Set the string value of `key`, replacing its value whatever its type, like SET.
+    pub fn set(&self, key: String, value: Vec<u8>) {
+        self.replace_type(&key, KeyType::String);
+        self.map.insert(key.clone(), self.store_string(value));
+        self.touch(&key);
+        self.key_changed(&key);
+    }
+
+    /// Atomically read and modify the value at `key`.
+    ///
+    /// `f` receives the current value, if any, and may replace or take it;
+    /// a `None` left behind removes the key. The key stays locked while `f` runs,
+    /// so `f` must not access the backend itself.
+    pub fn update<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut Option<Vec<u8>>) -> T,
+    ) -> Result<T, WrongType> {
+        self.check_type(key, KeyType::String)?;
+        let (res, modified) = update_entry(self.map.entry(key.to_string()), |stored| {
+            let mut value = stored.take().map(StoredString::into_vec);
+            let res = f(&mut value);
+            *stored = value.map(|value| self.restore_string(value));
+            res
+        });
+        if modified {
+            self.touch(key);
+            self.key_changed(key);
+        }
+        Ok(res)
+    }
+
+    /// Atomically set `key` to `new` if its current value equals `expected`,
+    /// `None` meaning the key must not exist. Returns whether the value was set.
+    pub fn compare_and_set(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Vec<u8>,
+    ) -> Result<bool, WrongType> {
+        self.update(key, |value| {
+            if value.as_deref() != expected {
+                return false;
+            }
+            *value = Some(new);
+            true
+        })
     }
 
     pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+        self.expire_fields(key, Instant::now());
+        self.hmap.get(key).and_then(|v| v.get(field).cloned())
     }
 
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let hmap = self.hmap.entry(key).or_default();
+    pub fn hset(&self, key: String, field: String, value: RespFrame) -> Result<(), WrongType> {
+        self.check_type(&key, KeyType::Hash)?;
+        let mut hmap = self.hmap.entry(key.clone()).or_default();
         hmap.insert(field, value);
+        hmap.fit_encoding(&self.encoding);
+        self.index_deadlines(&key, &hmap);
+        drop(hmap);
+        self.reindex(&key);
+        self.key_changed(&key);
+        Ok(())
+    }
+
+    /// Atomically read and modify the field of the hash at `key`, like [`Backend::update`].
+    pub fn hupdate<T>(
+        &self,
+        key: &str,
+        field: &str,
+        f: impl FnOnce(&mut Option<RespFrame>) -> T,
+    ) -> Result<T, WrongType> {
+        self.check_type(key, KeyType::Hash)?;
+        self.expire_fields(key, Instant::now());
+        let mut hmap = self.hmap.entry(key.to_string()).or_default();
+        let (res, modified) = match hmap.get_mut(field) {
+            Some(slot) => {
+                let mut value = Some(std::mem::replace(slot, RespFrame::Null(RespNull)));
+                let res = f(&mut value);
+                match value {
+                    Some(value) => *slot = value,
+                    None => {
+                        // keep the remaining fields in insertion order.
+                        hmap.shift_remove(field);
+                    }
+                }
+                (res, true)
+            }
+            None => {
+                let mut value = None;
+                let res = f(&mut value);
+                let modified = value.is_some();
+                if let Some(value) = value {
+                    hmap.insert(field.to_string(), value);
+                }
+                (res, modified)
+            }
+        };
+        if modified {
+            hmap.fit_encoding(&self.encoding);
+            self.index_deadlines(key, &hmap);
+        }
+        let empty = hmap.is_empty();
+        drop(hmap);
+        if empty {
+            self.hmap.remove_if(key, |_, hmap| hmap.is_empty());
+        }
+        if modified {
+            self.reindex(key);
+            self.key_changed(key);
+        }
+        Ok(res)
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    /// All fields of the hash, in insertion order.
+    pub fn hgetall(&self, key: &str) -> Option<IndexMap<String, RespFrame>> {
+        self.expire_fields(key, Instant::now());
+        self.hmap.get(key).map(|v| v.to_map())
     }
 
-    pub fn hmget(&self, key: &str, fields: &[String]) -> DashMap<String, RespFrame> {
-        let map = DashMap::new();
+    pub fn hmget(&self, key: &str, fields: &[String]) -> IndexMap<String, RespFrame> {
+        self.expire_fields(key, Instant::now());
+        let mut map = IndexMap::new();
         if let Some(v) = self.hmap.get(key) {
             for field in fields {
                 if let Some(v) = v.get(field) {
-                    map.insert(field.clone(), v.value().clone());
+                    map.insert(field.clone(), v.clone());
                 }
             }
         }
         map
     }
 
-    pub fn sadd(&self, key: String, member: HashSet<BulkString>) -> i64 {
+    /// `count` fields picked at random with their values, like HRANDFIELD, see
+    /// [`Backend::srandmember`].
+    pub fn hrandfield(&self, key: &str, count: i64) -> Vec<(String, RespFrame)> {
+        self.expire_fields(key, Instant::now());
+        let Some(hash) = self.hmap.get(key) else {
+            return Vec::new();
+        };
+        sample::random_members(hash.iter(), count)
+            .into_iter()
+            .map(|(f, v)| (f.clone(), v.clone()))
+            .collect()
+    }
+
+    pub fn sadd(&self, key: String, member: HashSet<BulkString>) -> Result<i64, WrongType> {
+        self.check_type(&key, KeyType::Set)?;
         let mut res = 0;
-        let set = self.set.entry(key).or_default();
+        let mut set = self.set.entry(key.clone()).or_default();
         for k in member {
-            if set.insert(k) {
+            if set.insert(k, &self.encoding) {
                 res += 1
             }
         }
+        drop(set);
+        if res > 0 {
+            self.key_changed(&key);
+        }
+        Ok(res)
+    }
+
+    /// Move `member` from the set at `src` to the one at `dst`, like SMOVE.
+    /// Returns false if it is not a member of `src`.
+    pub fn smove(&self, src: &str, dst: &str, member: BulkString) -> Result<bool, WrongType> {
+        self.check_type(src, KeyType::Set)?;
+        self.check_type(dst, KeyType::Set)?;
+        let Some(mut set) = self.set.get_mut(src) else {
+            return Ok(false);
+        };
+        let removed = set.remove(&member);
+        let empty = set.is_empty();
+        drop(set);
+        if !removed {
+            return Ok(false);
+        }
+        if empty {
+            self.set.remove_if(src, |_, set| set.is_empty());
+        }
+        self.set
+            .entry(dst.to_string())
+            .or_default()
+            .insert(member, &self.encoding);
+        self.key_changed(src);
+        self.key_changed(dst);
+        Ok(true)
+    }
+
+    pub fn smembers(&self, key: &str) -> Option<Vec<BulkString>> {
+        self.set.get(key).map(|set| set.iter().collect())
+    }
+
+    /// `count` members picked uniformly at random, like SRANDMEMBER: distinct
+    /// members if `count` is positive, possibly repeated ones if it is negative.
+    pub fn srandmember(&self, key: &str, count: i64) -> Vec<BulkString> {
+        match self.set.get(key) {
+            Some(set) => sample::random_members(set.iter(), count),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn is_member(&self, key: String, member: BulkString) -> bool {
+        self.set.get(&key).is_some_and(|set| set.contains(&member))
+    }
+
+    /// Cardinality of the intersection of the sets stored at `keys`.
+    /// Counting stops once `limit` is reached, 0 means no limit.
+    pub fn sintercard(&self, keys: &[String], limit: usize) -> i64 {
+        let mut sets = Vec::with_capacity(keys.len());
+        for key in keys {
+            match self.set.get(key) {
+                Some(set) => sets.push(set),
+                // intersection with an empty set is always empty.
+                None => return 0,
+            }
+        }
+        // iterate over the smallest set and probe the others.
+        sets.sort_by_key(|set| set.len());
+        let Some((smallest, others)) = sets.split_first() else {
+            return 0;
+        };
+
+        let mut res = 0;
+        for member in smallest.iter() {
+            if others.iter().all(|set| set.contains(&member)) {
+                res += 1;
+                if limit != 0 && res >= limit as i64 {
+                    break;
+                }
+            }
+        }
         res
     }
+}
 
-    pub fn is_member(&self, key: String, member: BulkString) -> i64 {
-        if let Some(set) = self.set.get(&key) {
-            if set.contains(&member) {
-                return 1;
-            } else {
-                return 0;
+/// Run `f` on the value of the entry, writing back what it leaves behind.
+/// Returns the result of `f` and whether the entry may have been modified.
+fn update_entry<V: Default, T>(
+    entry: Entry<'_, String, V>,
+    f: impl FnOnce(&mut Option<V>) -> T,
+) -> (T, bool) {
+    match entry {
+        Entry::Occupied(mut entry) => {
+            let mut value = Some(std::mem::take(entry.get_mut()));
+            let res = f(&mut value);
+            match value {
+                Some(value) => *entry.get_mut() = value,
+                None => {
+                    entry.remove();
+                }
+            }
+            (res, true)
+        }
+        Entry::Vacant(entry) => {
+            let mut value = None;
+            let res = f(&mut value);
+            let modified = value.is_some();
+            if let Some(value) = value {
+                entry.insert(value);
             }
+            (res, modified)
         }
-        0
     }
 }
@@ -0,0 +1,113 @@
+use dashmap::DashMap;
+
+use crate::Backend;
+
+use super::iter::keys;
+
+/// What a pass of the memory optimizer did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OptimizeStats {
+    /// Containers visited.
+    pub keys: usize,
+    /// Containers converted to a more compact encoding, e.g. a hash table
+    /// which shrank back to a listpack, or a list whose partly empty nodes were merged.
+    pub reencoded: usize,
+}
+
+impl Backend {
+    /// Re-encode every container into the most compact representation it allows now,
+    /// like MEMORY PURGE. Containers only switch to bigger encodings as they grow:
+    /// without this pass, one which shrank keeps its bigger encoding.
+    ///
+    /// Each key is locked only while it is re-encoded, the pass can run beside clients.
+    /// Strings are left as is, they are stored in their compact form when written.
+    pub fn optimize_memory(&self) -> OptimizeStats {
+        let mut stats = OptimizeStats::default();
+        let encoding = &self.encoding;
+        compact_each(&self.hmap, &mut stats, |hash| hash.compact(encoding));
+        compact_each(&self.set, &mut stats, |set| set.compact(encoding));
+        compact_each(&self.list, &mut stats, |list| list.compact());
+        stats
+    }
+}
+
+fn compact_each<V>(
+    map: &DashMap<String, V>,
+    stats: &mut OptimizeStats,
+    mut compact: impl FnMut(&mut V) -> bool,
+) {
+    for key in keys(map) {
+        if let Some(mut value) = map.get_mut(&key) {
+            stats.keys += 1;
+            if compact(&mut value) {
+                stats.reencoded += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        time::{Duration, Instant},
+    };
+
+    use crate::{BulkString, Encoding, EncodingConfig, ExpireCondition, ListEnd, RespFrame};
+
+    use super::*;
+
+    #[test]
+    fn test_optimize_memory() {
+        let backend = Backend::with_encoding(EncodingConfig {
+            hash_max_listpack_entries: 2,
+            set_max_intset_entries: 2,
+            set_max_listpack_entries: 2,
+            ..Default::default()
+        });
+        for field in ["a", "b", "c"] {
+            backend
+                .hset("h".to_string(), field.to_string(), RespFrame::Integer(1))
+                .unwrap();
+        }
+        // a deadline in the past deletes the field.
+        let past = Instant::now() - Duration::from_secs(1);
+        backend.hexpire("h", &["c".to_string()], past, ExpireCondition::Always);
+        let members = ["1", "2", "x"].map(BulkString::new);
+        backend
+            .sadd("s".to_string(), HashSet::from(members.clone()))
+            .unwrap();
+        backend.smove("s", "other", members[2].clone()).unwrap();
+        let values: Vec<BulkString> = (0..300).map(|i| BulkString::new(i.to_string())).collect();
+        backend.push("l", values, ListEnd::Tail).unwrap();
+        for _ in 0..100 {
+            backend.pop("l", 1, ListEnd::Head);
+            backend.pop("l", 1, ListEnd::Tail);
+        }
+        assert_eq!(backend.object_encoding("h"), Some(Encoding::Hashtable));
+        assert_eq!(backend.object_encoding("s"), Some(Encoding::Hashtable));
+        let nodes = backend.list.get("l").unwrap().node_count();
+
+        let stats = backend.optimize_memory();
+        assert_eq!(
+            stats,
+            OptimizeStats {
+                keys: 4,
+                reencoded: 3
+            }
+        );
+        assert_eq!(backend.object_encoding("h"), Some(Encoding::Listpack));
+        assert_eq!(backend.object_encoding("s"), Some(Encoding::Intset));
+        assert!(backend.list.get("l").unwrap().node_count() < nodes);
+        // the values are unchanged.
+        let fields: Vec<String> = backend.hgetall("h").unwrap().into_keys().collect();
+        assert_eq!(fields, vec!["a", "b"]);
+        assert!(backend.is_member("s".to_string(), BulkString::new("2")));
+        let list = backend.lrange("l", 0, -1);
+        assert_eq!(list.len(), 100);
+        assert_eq!(list[0], BulkString::new("100"));
+
+        // nothing left to compact.
+        assert_eq!(backend.optimize_memory().reencoded, 0);
+    }
+}
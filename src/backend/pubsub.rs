@@ -0,0 +1,119 @@
+use crate::{Backend, BulkString, RespArray, RespFrame};
+
+use super::PushSender;
+
+/// The sending half of a connection's push queue, identified by the connection id.
+pub type Subscriber = (u64, PushSender);
+
+impl Backend {
+    /// Subscribe the connection to a shard channel.
+    pub fn ssubscribe(&self, channel: String, subscriber: Subscriber) {
+        let (id, tx) = subscriber;
+        self.shard_channels
+            .entry(channel)
+            .or_default()
+            .insert(id, tx);
+    }
+
+    /// Unsubscribe the connection from a shard channel.
+    pub fn sunsubscribe(&self, channel: &str, id: u64) {
+        self.shard_channels
+            .remove_if_mut(channel, |_, subscribers| {
+                subscribers.remove(&id);
+                subscribers.is_empty()
+            });
+    }
+
+    /// Publish a message to a shard channel, returning the number of receivers.
+    pub fn spublish(&self, channel: &str, message: BulkString) -> i64 {
+        let Some(subscribers) = self.shard_channels.get(channel) else {
+            return 0;
+        };
+        let frame: RespFrame = RespArray::new(vec![
+            BulkString::new("smessage").into(),
+            BulkString::new(channel).into(),
+            message.into(),
+        ])
+        .into();
+
+        let mut res = 0;
+        for tx in subscribers.values() {
+            if tx.send(frame.clone()).is_ok() {
+                res += 1;
+            }
+        }
+        res
+    }
+
+    /// Wait until every subscriber of the shard channel has room for a message,
+    /// see [`PushOverflow::BlockPublisher`](crate::PushOverflow::BlockPublisher).
+    pub async fn wait_for_subscribers(&self, channel: &str) {
+        let Some(subscribers) = self.shard_channels.get(channel) else {
+            return;
+        };
+        // not waiting with the shard of the map locked.
+        let subscribers: Vec<_> = subscribers.values().cloned().collect();
+        for tx in subscribers {
+            tx.wait_for_room().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use crate::{backend::push_queue, PushOverflow};
+
+    use super::*;
+
+    #[test]
+    fn test_spublish() {
+        let backend = Backend::new();
+        let (tx1, mut rx1) = push_queue(16, PushOverflow::Disconnect);
+        let (tx2, mut rx2) = push_queue(16, PushOverflow::Disconnect);
+        backend.ssubscribe("ch".to_string(), (1, tx1));
+        backend.ssubscribe("ch".to_string(), (2, tx2));
+
+        assert_eq!(backend.spublish("ch", "hello".into()), 2);
+        assert_eq!(backend.spublish("other", "hello".into()), 0);
+
+        let expected: RespFrame = RespArray::new(vec![
+            BulkString::new("smessage").into(),
+            BulkString::new("ch").into(),
+            BulkString::new("hello").into(),
+        ])
+        .into();
+        assert_eq!(rx1.try_recv().unwrap(), expected);
+        assert_eq!(rx2.try_recv().unwrap(), expected);
+
+        backend.sunsubscribe("ch", 1);
+        assert_eq!(backend.spublish("ch", "hello".into()), 1);
+        backend.sunsubscribe("ch", 2);
+        assert!(backend.shard_channels.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spublish_to_full_queues() {
+        let backend = Backend::new();
+        let (tx1, mut rx1) = push_queue(1, PushOverflow::Disconnect);
+        let (tx2, mut rx2) = push_queue(1, PushOverflow::BlockPublisher);
+        backend.ssubscribe("slow".to_string(), (1, tx1));
+        backend.ssubscribe("blocking".to_string(), (2, tx2));
+
+        // the subscriber is disconnected, and no longer receives messages.
+        assert_eq!(backend.spublish("slow", "a".into()), 1);
+        assert_eq!(backend.spublish("slow", "b".into()), 0);
+        assert_eq!(backend.spublish("slow", "c".into()), 0);
+        assert_eq!(rx1.recv().await, None);
+
+        backend.wait_for_subscribers("blocking").await;
+        assert_eq!(backend.spublish("blocking", "a".into()), 1);
+        let wait = backend.wait_for_subscribers("blocking");
+        assert!(timeout(Duration::from_millis(20), wait).await.is_err());
+        assert!(rx2.recv().await.is_some());
+        backend.wait_for_subscribers("blocking").await;
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use tokio::sync::Notify;
+
+use crate::{PushOverflow, RespFrame};
+
+/// The sending half of the queue of messages pushed to a connection, e.g. by
+/// SPUBLISH or by the invalidation of tracked keys.
+#[derive(Debug, Clone)]
+pub struct PushSender(Arc<PushQueue>);
+
+/// The receiving half, read by the connection.
+#[derive(Debug)]
+pub struct PushReceiver(Arc<PushQueue>);
+
+/// A bounded queue, so that a connection which doesn't read its messages
+/// can't make the server buffer them without limit.
+#[derive(Debug)]
+struct PushQueue {
+    state: Mutex<QueueState>,
+    /// Wakes the connection once a message is queued, or the queue is closed.
+    queued: Notify,
+    /// Wakes the publishers waiting for room.
+    drained: Notify,
+    capacity: usize,
+    overflow: PushOverflow,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    frames: VecDeque<RespFrame>,
+    /// Closed once the connection is gone, or disconnected for not reading its messages.
+    closed: bool,
+}
+
+/// Create the push queue of a connection, holding at most `capacity` messages.
+pub fn push_queue(capacity: usize, overflow: PushOverflow) -> (PushSender, PushReceiver) {
+    let queue = Arc::new(PushQueue {
+        state: Mutex::default(),
+        queued: Notify::new(),
+        drained: Notify::new(),
+        capacity,
+        overflow,
+    });
+    (PushSender(queue.clone()), PushReceiver(queue))
+}
+
+impl PushSender {
+    /// Queue the message, which fails if the connection is gone. A full queue drops
+    /// its oldest message or closes, unless publishers wait for room, see
+    /// [`PushSender::wait_for_room`].
+    pub fn send(&self, frame: RespFrame) -> Result<(), RespFrame> {
+        let mut state = self.0.lock();
+        if state.closed {
+            return Err(frame);
+        }
+        if state.frames.len() >= self.0.capacity {
+            match self.0.overflow {
+                PushOverflow::DropOldest => {
+                    state.frames.pop_front();
+                }
+                PushOverflow::Disconnect => {
+                    self.0.close(state);
+                    return Err(frame);
+                }
+                // bounded by the number of publishers, which wait before publishing again.
+                PushOverflow::BlockPublisher => {}
+            }
+        }
+        state.frames.push_back(frame);
+        drop(state);
+        self.0.queued.notify_one();
+        Ok(())
+    }
+
+    /// Wait until the queue has room for another message, or is closed.
+    pub async fn wait_for_room(&self) {
+        loop {
+            // registered before checking, so that a message read in between wakes it.
+            let drained = self.0.drained.notified();
+            {
+                let state = self.0.lock();
+                if state.closed || state.frames.len() < self.0.capacity {
+                    return;
+                }
+            }
+            drained.await;
+        }
+    }
+}
+
+impl PushReceiver {
+    /// The next message, or `None` once the queue was closed because it overflowed.
+    pub async fn recv(&mut self) -> Option<RespFrame> {
+        loop {
+            if let Some(frame) = self.try_recv() {
+                return Some(frame);
+            }
+            if self.0.lock().closed {
+                return None;
+            }
+            self.0.queued.notified().await;
+        }
+    }
+
+    /// The next message if one is queued.
+    pub fn try_recv(&mut self) -> Option<RespFrame> {
+        let frame = self.0.lock().frames.pop_front();
+        if frame.is_some() {
+            self.0.drained.notify_waiters();
+        }
+        frame
+    }
+}
+
+impl Drop for PushReceiver {
+    fn drop(&mut self) {
+        self.0.close(self.0.lock());
+    }
+}
+
+impl PushQueue {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn close(&self, mut state: MutexGuard<'_, QueueState>) {
+        state.closed = true;
+        state.frames.clear();
+        drop(state);
+        self.queued.notify_one();
+        self.drained.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::*;
+
+    #[test]
+    fn test_push_queue_drop_oldest() {
+        let (tx, mut rx) = push_queue(2, PushOverflow::DropOldest);
+        for i in 0..3 {
+            assert!(tx.send(RespFrame::Integer(i)).is_ok());
+        }
+        assert_eq!(rx.try_recv(), Some(RespFrame::Integer(1)));
+        assert_eq!(rx.try_recv(), Some(RespFrame::Integer(2)));
+        assert_eq!(rx.try_recv(), None);
+
+        drop(rx);
+        assert!(tx.send(RespFrame::Integer(3)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_push_queue_disconnect() {
+        let (tx, mut rx) = push_queue(2, PushOverflow::Disconnect);
+        assert!(tx.send(RespFrame::Integer(0)).is_ok());
+        assert!(tx.send(RespFrame::Integer(1)).is_ok());
+        assert!(tx.send(RespFrame::Integer(2)).is_err());
+        assert!(tx.send(RespFrame::Integer(3)).is_err());
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_push_queue_block_publisher() -> anyhow::Result<()> {
+        let (tx, mut rx) = push_queue(1, PushOverflow::BlockPublisher);
+        tx.wait_for_room().await;
+        assert!(tx.send(RespFrame::Integer(0)).is_ok());
+        assert!(tx.send(RespFrame::Integer(1)).is_ok());
+
+        let waiting = tokio::spawn({
+            let tx = tx.clone();
+            async move { tx.wait_for_room().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        // still full.
+        assert_eq!(rx.recv().await, Some(RespFrame::Integer(0)));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        assert_eq!(rx.recv().await, Some(RespFrame::Integer(1)));
+        timeout(Duration::from_secs(1), waiting).await??;
+        Ok(())
+    }
+}
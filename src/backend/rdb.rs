@@ -0,0 +1,770 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{Backend, BulkString, ListEnd, WrongType};
+
+use super::checksum::crc64;
+
+/// Newest RDB version understood, the one written by Redis 7.4.
+const MAX_RDB_VERSION: u32 = 12;
+
+// opcodes of the RDB format.
+const OP_SLOT_INFO: u8 = 0xF4;
+const OP_FUNCTION2: u8 = 0xF5;
+const OP_MODULE_AUX: u8 = 0xF7;
+const OP_IDLE: u8 = 0xF8;
+const OP_FREQ: u8 = 0xF9;
+const OP_AUX: u8 = 0xFA;
+const OP_RESIZEDB: u8 = 0xFB;
+const OP_EXPIRETIME_MS: u8 = 0xFC;
+const OP_EXPIRETIME: u8 = 0xFD;
+const OP_SELECTDB: u8 = 0xFE;
+const OP_EOF: u8 = 0xFF;
+
+// value types of the RDB format.
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+const TYPE_LIST_ZIPLIST: u8 = 10;
+const TYPE_SET_INTSET: u8 = 11;
+const TYPE_ZSET_ZIPLIST: u8 = 12;
+const TYPE_HASH_ZIPLIST: u8 = 13;
+const TYPE_LIST_QUICKLIST: u8 = 14;
+const TYPE_HASH_LISTPACK: u8 = 16;
+const TYPE_ZSET_LISTPACK: u8 = 17;
+const TYPE_LIST_QUICKLIST_2: u8 = 18;
+const TYPE_SET_LISTPACK: u8 = 20;
+
+/// A node of a quicklist 2 holding a single large element rather than a listpack.
+const QUICKLIST_NODE_PLAIN: u64 = 1;
+
+#[derive(Debug, Error)]
+pub enum RdbError {
+    #[error("failed to read the RDB file: {0}")]
+    Io(#[from] io::Error),
+    #[error("not an RDB file")]
+    BadMagic,
+    #[error("unsupported RDB version {0}")]
+    UnsupportedVersion(u32),
+    #[error("unsupported RDB value type {0}")]
+    UnsupportedType(u8),
+    #[error("RDB files using modules are not supported")]
+    Module,
+    #[error("only database 0 can be loaded, the file has keys in database {0}")]
+    Database(u64),
+    #[error("key or hash field is not valid UTF-8")]
+    Utf8,
+    #[error("corrupt RDB file: {0}")]
+    Corrupt(&'static str),
+    #[error("RDB checksum mismatch, expected {expected:#x}, got {actual:#x}")]
+    Checksum { expected: u64, actual: u64 },
+    #[error("corrupt RDB file: a key holds values of two types")]
+    WrongType(#[from] WrongType),
+}
+
+/// What loading an RDB file did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RdbStats {
+    /// Keys loaded.
+    pub keys: usize,
+    /// Keys skipped because they had already expired.
+    pub expired: usize,
+    /// Keys loaded without the time to live they had: the backend has no key expiration.
+    pub ttls_dropped: usize,
+}
+
+/// A value decoded from an RDB file, whatever its encoding.
+#[derive(Debug, PartialEq)]
+enum RdbValue {
+    String(Vec<u8>),
+    List(Vec<Vec<u8>>),
+    Set(Vec<Vec<u8>>),
+    ZSet(Vec<(Vec<u8>, f64)>),
+    Hash(Pairs),
+}
+
+/// Field and value pairs of a hash, or member and score strings of a zset.
+type Pairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+impl Backend {
+    /// Load the keys of a Redis RDB file, replacing the keys already present with
+    /// the same names. Keys which have expired are skipped, and the others lose
+    /// their time to live.
+    pub fn load_rdb_file(&self, path: impl AsRef<Path>) -> Result<RdbStats, RdbError> {
+        self.load_rdb(BufReader::new(File::open(path)?))
+    }
+
+    /// Like [`Backend::load_rdb_file`], from any reader.
+    pub fn load_rdb(&self, reader: impl Read) -> Result<RdbStats, RdbError> {
+        let mut rdb = RdbReader {
+            inner: reader,
+            crc: 0,
+        };
+        let mut magic = [0; 9];
+        rdb.read_exact(&mut magic)?;
+        if &magic[..5] != b"REDIS" {
+            return Err(RdbError::BadMagic);
+        }
+        let version: u32 = std::str::from_utf8(&magic[5..])
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .ok_or(RdbError::BadMagic)?;
+        if version == 0 || version > MAX_RDB_VERSION {
+            return Err(RdbError::UnsupportedVersion(version));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut stats = RdbStats::default();
+        let mut expire_at = None;
+        loop {
+            match rdb.read_u8()? {
+                OP_EOF => break,
+                OP_SELECTDB => match rdb.read_length()? {
+                    0 => {}
+                    db => return Err(RdbError::Database(db)),
+                },
+                OP_RESIZEDB => {
+                    rdb.read_length()?;
+                    rdb.read_length()?;
+                }
+                OP_AUX => {
+                    rdb.read_string()?;
+                    rdb.read_string()?;
+                }
+                OP_EXPIRETIME_MS => expire_at = Some(u64::from_le_bytes(rdb.read_array()?)),
+                OP_EXPIRETIME => {
+                    expire_at = Some(u32::from_le_bytes(rdb.read_array()?) as u64 * 1000)
+                }
+                OP_IDLE => {
+                    rdb.read_length()?;
+                }
+                OP_FREQ => {
+                    rdb.read_u8()?;
+                }
+                OP_FUNCTION2 => {
+                    rdb.read_string()?;
+                }
+                OP_SLOT_INFO => {
+                    for _ in 0..3 {
+                        rdb.read_length()?;
+                    }
+                }
+                OP_MODULE_AUX => return Err(RdbError::Module),
+                value_type => {
+                    let key = String::from_utf8(rdb.read_string()?).map_err(|_| RdbError::Utf8)?;
+                    let value = rdb.read_value(value_type)?;
+                    match expire_at.take() {
+                        Some(at) if at <= now => {
+                            stats.expired += 1;
+                            continue;
+                        }
+                        Some(_) => stats.ttls_dropped += 1,
+                        None => {}
+                    }
+                    self.remove_key(&key);
+                    self.insert_rdb_value(key, value)?;
+                    stats.keys += 1;
+                }
+            }
+        }
+
+        // files written with rdbchecksum disabled have a zero checksum.
+        if version >= 5 {
+            let actual = rdb.crc;
+            let expected = u64::from_le_bytes(rdb.read_array()?);
+            if expected != 0 && expected != actual {
+                return Err(RdbError::Checksum { expected, actual });
+            }
+        }
+        if stats.ttls_dropped > 0 {
+            warn!(
+                "{} keys loaded from the RDB file lost their time to live",
+                stats.ttls_dropped
+            );
+        }
+        Ok(stats)
+    }
+
+    fn insert_rdb_value(&self, key: String, value: RdbValue) -> Result<(), RdbError> {
+        match value {
+            RdbValue::String(value) => self.set(key, value),
+            RdbValue::List(values) => {
+                let values = values.into_iter().map(BulkString::new).collect();
+                self.push(&key, values, ListEnd::Tail)?;
+            }
+            RdbValue::Set(members) => {
+                let members: HashSet<BulkString> =
+                    members.into_iter().map(BulkString::new).collect();
+                self.sadd(key, members)?;
+            }
+            RdbValue::ZSet(members) => {
+                let members = members
+                    .into_iter()
+                    .map(|(member, score)| (score, BulkString::new(member)))
+                    .collect();
+                self.zadd(&key, members)?;
+            }
+            RdbValue::Hash(fields) => {
+                for (field, value) in fields {
+                    let field = String::from_utf8(field).map_err(|_| RdbError::Utf8)?;
+                    self.hset(key.clone(), field, BulkString::new(value).into())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads an RDB file, computing the checksum of what was read.
+struct RdbReader<R> {
+    inner: R,
+    crc: u64,
+}
+
+impl<R: Read> RdbReader<R> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), RdbError> {
+        self.inner.read_exact(buf)?;
+        self.crc = crc64(self.crc, buf);
+        Ok(())
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], RdbError> {
+        let mut buf = [0; N];
+        self.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, RdbError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    fn read_bytes(&mut self, len: u64) -> Result<Vec<u8>, RdbError> {
+        // grow the buffer as data comes, rather than trusting a corrupt length.
+        let mut buf = Vec::new();
+        (&mut self.inner).take(len).read_to_end(&mut buf)?;
+        if buf.len() as u64 != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.crc = crc64(self.crc, &buf);
+        Ok(buf)
+    }
+
+    /// A length, or the kind of a specially encoded string if the flag is set.
+    fn read_length_or_encoding(&mut self) -> Result<(u64, bool), RdbError> {
+        let first = self.read_u8()?;
+        Ok(match first >> 6 {
+            0 => ((first & 0x3f) as u64, false),
+            1 => (
+                (((first & 0x3f) as u64) << 8) | self.read_u8()? as u64,
+                false,
+            ),
+            2 => match first {
+                0x80 => (u32::from_be_bytes(self.read_array()?) as u64, false),
+                0x81 => (u64::from_be_bytes(self.read_array()?), false),
+                _ => return Err(RdbError::Corrupt("unknown length encoding")),
+            },
+            _ => ((first & 0x3f) as u64, true),
+        })
+    }
+
+    fn read_length(&mut self) -> Result<u64, RdbError> {
+        match self.read_length_or_encoding()? {
+            (len, false) => Ok(len),
+            (_, true) => Err(RdbError::Corrupt("expected a length")),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<Vec<u8>, RdbError> {
+        match self.read_length_or_encoding()? {
+            (len, false) => self.read_bytes(len),
+            (0, true) => Ok((self.read_u8()? as i8).to_string().into_bytes()),
+            (1, true) => Ok(i16::from_le_bytes(self.read_array()?)
+                .to_string()
+                .into_bytes()),
+            (2, true) => Ok(i32::from_le_bytes(self.read_array()?)
+                .to_string()
+                .into_bytes()),
+            (3, true) => {
+                let compressed_len = self.read_length()?;
+                let len = self.read_length()?;
+                let compressed = self.read_bytes(compressed_len)?;
+                lzf_decompress(&compressed, len as usize)
+            }
+            _ => Err(RdbError::Corrupt("unknown string encoding")),
+        }
+    }
+
+    /// A score of the original zset type, written as a string.
+    fn read_double_string(&mut self) -> Result<f64, RdbError> {
+        match self.read_u8()? {
+            253 => Ok(f64::NAN),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            len => parse_double(&self.read_bytes(len as u64)?),
+        }
+    }
+
+    fn read_strings(&mut self) -> Result<Vec<Vec<u8>>, RdbError> {
+        let len = self.read_length()?;
+        (0..len).map(|_| self.read_string()).collect()
+    }
+
+    fn read_value(&mut self, value_type: u8) -> Result<RdbValue, RdbError> {
+        Ok(match value_type {
+            TYPE_STRING => RdbValue::String(self.read_string()?),
+            TYPE_LIST => RdbValue::List(self.read_strings()?),
+            TYPE_SET => RdbValue::Set(self.read_strings()?),
+            TYPE_ZSET | TYPE_ZSET_2 => {
+                let len = self.read_length()?;
+                let mut members = Vec::new();
+                for _ in 0..len {
+                    let member = self.read_string()?;
+                    let score = if value_type == TYPE_ZSET {
+                        self.read_double_string()?
+                    } else {
+                        f64::from_le_bytes(self.read_array()?)
+                    };
+                    members.push((member, score));
+                }
+                RdbValue::ZSet(members)
+            }
+            TYPE_HASH => {
+                let len = self.read_length()?;
+                let mut fields = Vec::new();
+                for _ in 0..len {
+                    fields.push((self.read_string()?, self.read_string()?));
+                }
+                RdbValue::Hash(fields)
+            }
+            TYPE_LIST_ZIPLIST => RdbValue::List(ziplist(&self.read_string()?)?),
+            TYPE_SET_INTSET => RdbValue::Set(intset(&self.read_string()?)?),
+            TYPE_ZSET_ZIPLIST => RdbValue::ZSet(scored(ziplist(&self.read_string()?)?)?),
+            TYPE_HASH_ZIPLIST => RdbValue::Hash(pairs(ziplist(&self.read_string()?)?)?),
+            TYPE_LIST_QUICKLIST => {
+                let mut values = Vec::new();
+                for node in self.read_strings()? {
+                    values.extend(ziplist(&node)?);
+                }
+                RdbValue::List(values)
+            }
+            TYPE_HASH_LISTPACK => RdbValue::Hash(pairs(listpack(&self.read_string()?)?)?),
+            TYPE_ZSET_LISTPACK => RdbValue::ZSet(scored(listpack(&self.read_string()?)?)?),
+            TYPE_LIST_QUICKLIST_2 => {
+                let len = self.read_length()?;
+                let mut values = Vec::new();
+                for _ in 0..len {
+                    let container = self.read_length()?;
+                    let node = self.read_string()?;
+                    if container == QUICKLIST_NODE_PLAIN {
+                        values.push(node);
+                    } else {
+                        values.extend(listpack(&node)?);
+                    }
+                }
+                RdbValue::List(values)
+            }
+            TYPE_SET_LISTPACK => RdbValue::Set(listpack(&self.read_string()?)?),
+            other => return Err(RdbError::UnsupportedType(other)),
+        })
+    }
+}
+
+fn parse_double(s: &[u8]) -> Result<f64, RdbError> {
+    std::str::from_utf8(s)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or(RdbError::Corrupt("invalid score"))
+}
+
+/// Members followed by their score, as in the ziplist and listpack encodings of zsets.
+fn scored(elements: Vec<Vec<u8>>) -> Result<Vec<(Vec<u8>, f64)>, RdbError> {
+    pairs(elements)?
+        .into_iter()
+        .map(|(member, score)| Ok((member, parse_double(&score)?)))
+        .collect()
+}
+
+fn pairs(elements: Vec<Vec<u8>>) -> Result<Pairs, RdbError> {
+    if !elements.len().is_multiple_of(2) {
+        return Err(RdbError::Corrupt("odd number of elements in a map"));
+    }
+    let mut elements = elements.into_iter();
+    let mut res = Vec::new();
+    while let (Some(a), Some(b)) = (elements.next(), elements.next()) {
+        res.push((a, b));
+    }
+    Ok(res)
+}
+
+/// Reads the blobs embedded in string values: ziplists, listpacks and intsets.
+struct Blob<'a>(&'a [u8]);
+
+impl<'a> Blob<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], RdbError> {
+        if self.0.len() < n {
+            return Err(RdbError::Corrupt("truncated encoded value"));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, RdbError> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// A little endian signed integer of `n` bytes.
+    fn int(&mut self, n: usize) -> Result<i64, RdbError> {
+        let bytes = self.take(n)?;
+        let mut buf = [0; 8];
+        buf[..n].copy_from_slice(bytes);
+        let shift = 64 - 8 * n as u32;
+        Ok(i64::from_le_bytes(buf) << shift >> shift)
+    }
+
+    fn u32(&mut self) -> Result<u32, RdbError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+fn int_bytes(n: i64) -> Vec<u8> {
+    n.to_string().into_bytes()
+}
+
+/// The elements of a ziplist, the compact encoding of RDB versions before 10.
+fn ziplist(data: &[u8]) -> Result<Vec<Vec<u8>>, RdbError> {
+    let mut blob = Blob(data);
+    // zlbytes, zltail and zllen.
+    blob.take(10)?;
+    let mut res = Vec::new();
+    loop {
+        let prevlen = blob.u8()?;
+        if prevlen == 0xFF {
+            return Ok(res);
+        }
+        if prevlen == 0xFE {
+            blob.take(4)?;
+        }
+        let encoding = blob.u8()?;
+        let element = match encoding {
+            0x00..=0x3F => blob.take((encoding & 0x3f) as usize)?.to_vec(),
+            0x40..=0x7F => {
+                let len = (((encoding & 0x3f) as usize) << 8) | blob.u8()? as usize;
+                blob.take(len)?.to_vec()
+            }
+            0x80 => {
+                let len = u32::from_be_bytes(blob.take(4)?.try_into().unwrap());
+                blob.take(len as usize)?.to_vec()
+            }
+            0xC0 => int_bytes(blob.int(2)?),
+            0xD0 => int_bytes(blob.int(4)?),
+            0xE0 => int_bytes(blob.int(8)?),
+            0xF0 => int_bytes(blob.int(3)?),
+            0xFE => int_bytes(blob.int(1)?),
+            0xF1..=0xFD => int_bytes((encoding & 0x0f) as i64 - 1),
+            _ => return Err(RdbError::Corrupt("unknown ziplist entry encoding")),
+        };
+        res.push(element);
+    }
+}
+
+/// The elements of a listpack, the compact encoding since RDB version 10.
+fn listpack(data: &[u8]) -> Result<Vec<Vec<u8>>, RdbError> {
+    let mut blob = Blob(data);
+    // total bytes and number of elements.
+    blob.take(6)?;
+    let mut res = Vec::new();
+    loop {
+        let before = blob.0.len();
+        let encoding = blob.u8()?;
+        let element = match encoding {
+            0x00..=0x7F => int_bytes(encoding as i64),
+            0x80..=0xBF => blob.take((encoding & 0x3f) as usize)?.to_vec(),
+            0xC0..=0xDF => {
+                let n = (((encoding & 0x1f) as i64) << 8) | blob.u8()? as i64;
+                // a 13 bit two's complement integer.
+                int_bytes(if n >= 1 << 12 { n - (1 << 13) } else { n })
+            }
+            0xE0..=0xEF => {
+                let len = (((encoding & 0x0f) as usize) << 8) | blob.u8()? as usize;
+                blob.take(len)?.to_vec()
+            }
+            0xF0 => {
+                let len = blob.u32()?;
+                blob.take(len as usize)?.to_vec()
+            }
+            0xF1 => int_bytes(blob.int(2)?),
+            0xF2 => int_bytes(blob.int(3)?),
+            0xF3 => int_bytes(blob.int(4)?),
+            0xF4 => int_bytes(blob.int(8)?),
+            0xFF => return Ok(res),
+            _ => return Err(RdbError::Corrupt("unknown listpack entry encoding")),
+        };
+        // skip the back length, sized after the entry it follows.
+        let entry_len = before - blob.0.len();
+        let backlen = match entry_len {
+            0..=127 => 1,
+            128..=16_383 => 2,
+            16_384..=2_097_151 => 3,
+            2_097_152..=268_435_455 => 4,
+            _ => 5,
+        };
+        blob.take(backlen)?;
+        res.push(element);
+    }
+}
+
+/// The members of an intset.
+fn intset(data: &[u8]) -> Result<Vec<Vec<u8>>, RdbError> {
+    let mut blob = Blob(data);
+    let width = blob.u32()? as usize;
+    if !matches!(width, 2 | 4 | 8) {
+        return Err(RdbError::Corrupt("unknown intset encoding"));
+    }
+    let len = blob.u32()?;
+    (0..len).map(|_| Ok(int_bytes(blob.int(width)?))).collect()
+}
+
+/// Decompress an LZF block of strings compressed by Redis.
+fn lzf_decompress(input: &[u8], len: usize) -> Result<Vec<u8>, RdbError> {
+    let corrupt = || RdbError::Corrupt("invalid LZF data");
+    let mut out = Vec::with_capacity(len.min(input.len() * 4));
+    let mut blob = Blob(input);
+    while !blob.0.is_empty() {
+        let ctrl = blob.u8()? as usize;
+        if ctrl < 32 {
+            // a literal run of ctrl + 1 bytes.
+            out.extend_from_slice(blob.take(ctrl + 1).map_err(|_| corrupt())?);
+            continue;
+        }
+        // a back reference to data already decompressed.
+        let mut run = ctrl >> 5;
+        if run == 7 {
+            run += blob.u8()? as usize;
+        }
+        let distance = ((ctrl & 0x1f) << 8) + blob.u8()? as usize + 1;
+        let start = out.len().checked_sub(distance).ok_or_else(corrupt)?;
+        for i in 0..run + 2 {
+            out.push(out[start + i]);
+        }
+    }
+    if out.len() != len {
+        return Err(corrupt());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RespFrame;
+
+    use super::*;
+
+    /// Builds RDB files the way Redis writes them.
+    #[derive(Default)]
+    struct RdbWriter(Vec<u8>);
+
+    impl RdbWriter {
+        fn new(version: u32) -> Self {
+            Self(format!("REDIS{:04}", version).into_bytes())
+        }
+
+        fn byte(mut self, b: u8) -> Self {
+            self.0.push(b);
+            self
+        }
+
+        fn bytes(mut self, b: &[u8]) -> Self {
+            self.0.extend_from_slice(b);
+            self
+        }
+
+        fn length(self, len: usize) -> Self {
+            match len {
+                0..=63 => self.byte(len as u8),
+                64..=16_383 => self.bytes(&[0x40 | (len >> 8) as u8, len as u8]),
+                _ => self.byte(0x80).bytes(&(len as u32).to_be_bytes()),
+            }
+        }
+
+        fn string(self, s: &[u8]) -> Self {
+            self.length(s.len()).bytes(s)
+        }
+
+        fn finish(self) -> Vec<u8> {
+            let mut data = self.byte(OP_EOF).0;
+            let crc = crc64(0, &data);
+            data.extend_from_slice(&crc.to_le_bytes());
+            data
+        }
+    }
+
+    fn listpack_of(entries: &[&[u8]]) -> Vec<u8> {
+        let mut data = vec![0; 4];
+        data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for entry in entries {
+            data.push(0x80 | entry.len() as u8);
+            data.extend_from_slice(entry);
+            data.push(entry.len() as u8 + 1);
+        }
+        data.push(0xFF);
+        let total = data.len() as u32;
+        data[..4].copy_from_slice(&total.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_lzf_decompress() -> anyhow::Result<()> {
+        // the literal "a" then nine bytes copied from one byte back.
+        let data = lzf_decompress(&[0x00, b'a', 0xE0, 0x00, 0x00], 10)?;
+        assert_eq!(data, b"a".repeat(10));
+        assert!(lzf_decompress(&[0x20, 0x05], 3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_encodings() -> anyhow::Result<()> {
+        let mut data = listpack_of(&[b"a"]);
+        // insert a 7 bit uint and a negative 13 bit int before the end.
+        let end = data.len() - 1;
+        data.splice(end..end, [0x05, 0x01, 0xDF, 0xFF, 0x02]);
+        assert_eq!(
+            listpack(&data)?,
+            vec![b"a".to_vec(), b"5".to_vec(), b"-1".to_vec()]
+        );
+
+        let mut data = vec![0; 10];
+        data.extend_from_slice(&[0x00, 0x02, b'h', b'i', 0x04, 0xF3, 0x02, 0xFE, 0xF6, 0xFF]);
+        assert_eq!(
+            ziplist(&data)?,
+            vec![b"hi".to_vec(), b"2".to_vec(), b"-10".to_vec()]
+        );
+
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[0xFF, 0xFF, 0x07, 0x00]);
+        assert_eq!(intset(&data)?, vec![b"-1".to_vec(), b"7".to_vec()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_rdb() -> anyhow::Result<()> {
+        let data = RdbWriter::new(11)
+            .byte(OP_AUX)
+            .string(b"redis-ver")
+            .string(b"7.2.4")
+            .byte(OP_SELECTDB)
+            .length(0)
+            .byte(OP_RESIZEDB)
+            .length(6)
+            .length(1)
+            .byte(TYPE_STRING)
+            .string(b"s")
+            .string(b"hello")
+            // the integer 300 as a 16 bit int.
+            .byte(TYPE_STRING)
+            .string(b"n")
+            .bytes(&[0xC1, 0x2C, 0x01])
+            .byte(TYPE_STRING)
+            .string(b"lzf")
+            .bytes(&[0xC3, 5, 10, 0x00, b'a', 0xE0, 0x00, 0x00])
+            .byte(TYPE_LIST)
+            .string(b"l")
+            .length(2)
+            .string(b"a")
+            .string(b"b")
+            .byte(TYPE_HASH_LISTPACK)
+            .string(b"h")
+            .string(&listpack_of(&[b"f", b"v"]))
+            .byte(TYPE_ZSET_2)
+            .string(b"z")
+            .length(1)
+            .string(b"m")
+            .bytes(&1.5f64.to_le_bytes())
+            .byte(OP_EXPIRETIME_MS)
+            .bytes(&1u64.to_le_bytes())
+            .byte(TYPE_STRING)
+            .string(b"expired")
+            .string(b"x")
+            .byte(OP_EXPIRETIME_MS)
+            .bytes(&u64::MAX.to_le_bytes())
+            .byte(TYPE_SET)
+            .string(b"set")
+            .length(1)
+            .string(b"m")
+            .finish();
+
+        let backend = Backend::new();
+        backend.set("l".to_string(), b"replaced".to_vec());
+        let stats = backend.load_rdb(&data[..])?;
+        assert_eq!(
+            stats,
+            RdbStats {
+                keys: 7,
+                expired: 1,
+                ttls_dropped: 1
+            }
+        );
+        assert_eq!(backend.get("s"), Some("hello".into()));
+        assert_eq!(backend.get("n"), Some("300".into()));
+        assert_eq!(backend.get("lzf"), Some("a".repeat(10).into()));
+        assert_eq!(backend.get("l"), None);
+        assert_eq!(backend.lrange("l", 0, -1), ["a", "b"].map(BulkString::new));
+        assert_eq!(
+            backend.hget("h", "f"),
+            Some(RespFrame::BulkString("v".into()))
+        );
+        assert_eq!(backend.zscore("z", &BulkString::new("m")), Some(1.5));
+        assert!(!backend.exists("expired"));
+        assert!(backend.exists("set"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_rdb_errors() {
+        let backend = Backend::new();
+        let mut data = RdbWriter::new(11)
+            .byte(TYPE_STRING)
+            .string(b"k")
+            .string(b"v")
+            .finish();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(matches!(
+            backend.load_rdb(&data[..]),
+            Err(RdbError::Checksum { .. })
+        ));
+
+        let data = RdbWriter::new(11).byte(OP_SELECTDB).length(1).finish();
+        assert!(matches!(
+            backend.load_rdb(&data[..]),
+            Err(RdbError::Database(1))
+        ));
+        let data = RdbWriter::new(11).byte(15).string(b"stream").finish();
+        assert!(matches!(
+            backend.load_rdb(&data[..]),
+            Err(RdbError::UnsupportedType(15))
+        ));
+        assert!(matches!(
+            backend.load_rdb(&b"REDIS0099"[..]),
+            Err(RdbError::UnsupportedVersion(99))
+        ));
+        assert!(matches!(
+            backend.load_rdb(&b"NOTREDIS0"[..]),
+            Err(RdbError::BadMagic)
+        ));
+    }
+}
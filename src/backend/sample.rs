@@ -0,0 +1,142 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+/// Members of a collection picked uniformly at random, like SRANDMEMBER and
+/// HRANDFIELD: `count` distinct members if it is positive, `-count` members
+/// picked independently, so possibly repeated, if it is negative.
+///
+/// The members are walked once, and every member has the same chance to be
+/// picked whatever its position, so the layout of the collection doesn't bias
+/// the sample.
+pub(crate) fn random_members<T: Clone>(members: impl Iterator<Item = T>, count: i64) -> Vec<T> {
+    let mut rng = Rng::new();
+    match count {
+        0 => Vec::new(),
+        count if count > 0 => sample(members, count as usize, &mut rng),
+        count => sample_with_repetition(members, count.unsigned_abs(), &mut rng),
+    }
+}
+
+/// Reservoir sampling of `count` distinct items, in random order.
+fn sample<T>(items: impl Iterator<Item = T>, count: usize, rng: &mut Rng) -> Vec<T> {
+    let mut reservoir = Vec::with_capacity(count.min(items.size_hint().0));
+    for (i, item) in items.enumerate() {
+        if reservoir.len() < count {
+            reservoir.push(item);
+        } else if let Some(slot) = reservoir.get_mut(rng.below(i + 1)) {
+            *slot = item;
+        }
+    }
+    // items which were never replaced are still in iteration order.
+    for i in (1..reservoir.len()).rev() {
+        reservoir.swap(i, rng.below(i + 1));
+    }
+    reservoir
+}
+
+/// `count` items, each picked uniformly on its own.
+///
+/// Nothing is sized by `count` up front: the reply grows one pick at a time,
+/// so a huge count fails no allocation before the first pick.
+fn sample_with_repetition<T: Clone>(
+    items: impl Iterator<Item = T>,
+    count: u64,
+    rng: &mut Rng,
+) -> Vec<T> {
+    let items: Vec<T> = items.collect();
+    if items.is_empty() {
+        return Vec::new();
+    }
+    let mut res = Vec::new();
+    for _ in 0..count {
+        res.push(items[rng.below(items.len())].clone());
+    }
+    res
+}
+
+/// xorshift64*, seeded from the random keys of the std hasher.
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        Self(RandomState::new().build_hasher().finish() | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// An index below `n`, by multiplying rather than a modulo which favors the
+    /// low indexes.
+    fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_random_members() {
+        let items = || 0..10;
+        assert!(random_members(items(), 0).is_empty());
+
+        let picked = random_members(items(), 4);
+        assert_eq!(picked.len(), 4);
+        assert_eq!(picked.iter().collect::<HashSet<_>>().len(), 4);
+        assert!(picked.iter().all(|i| *i < 10));
+
+        let mut all = random_members(items(), 20);
+        all.sort_unstable();
+        assert_eq!(all, items().collect::<Vec<_>>());
+
+        let picked = random_members(items(), -25);
+        assert_eq!(picked.len(), 25);
+        assert!(picked.iter().all(|i| *i < 10));
+        assert_eq!(random_members(0..1, -3), vec![0, 0, 0]);
+        assert!(random_members(0..0, -3).is_empty());
+    }
+
+    #[test]
+    fn test_sampling_is_uniform() {
+        // each of the 10 items is expected 10000 times with both samplings.
+        let mut rng = Rng::new();
+        let mut distinct = [0; 10];
+        let mut repeated = [0; 10];
+        for _ in 0..20000 {
+            for i in sample(0..10, 5, &mut rng) {
+                distinct[i] += 1;
+            }
+            for i in sample_with_repetition(0..10, 5, &mut rng) {
+                repeated[i] += 1;
+            }
+        }
+        for hits in distinct.into_iter().chain(repeated) {
+            assert!(
+                (9000..11000).contains(&hits),
+                "{:?} {:?}",
+                distinct,
+                repeated
+            );
+        }
+
+        // the first item picked is as likely to be any of them.
+        let mut first = [0; 4];
+        for _ in 0..8000 {
+            first[sample(0..4, 4, &mut rng)[0]] += 1;
+        }
+        assert!(
+            first.iter().all(|hits| (1600..2400).contains(hits)),
+            "{:?}",
+            first
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use crate::{glob::glob_match, Backend};
+
+/// The smallest number of bits of a cursor, the size of the table of an empty keyspace.
+const MIN_CURSOR_BITS: u32 = 4;
+
+/// Where a key is visited by SCAN: the reversed bits of its hash.
+///
+/// Like Redis, SCAN walks a table of 2^n buckets, a key being in the bucket indexed by
+/// the low n bits of its hash, incrementing the reversed bits of its cursor. Reversing
+/// the whole hash instead gives the position of the key in this walk whatever n is:
+/// the bucket of a key is the top n bits of its position, a cursor its smallest position.
+fn scan_position(key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish().reverse_bits()
+}
+
+impl Backend {
+    /// One step of an iteration over the keyspace, like SCAN: returns the keys of the
+    /// buckets from `cursor` on, at least `count` of them unless the iteration is done,
+    /// and the cursor of the next step, 0 once done. An iteration starts with cursor 0.
+    ///
+    /// Every key present from the start to the end of an iteration is returned at least
+    /// once, however many keys are added or removed meanwhile: the table grows and shrinks
+    /// with the keyspace, and the reversed cursor stays valid for any table size.
+    /// `pattern` filters the keys returned, not the ones counted.
+    ///
+    /// There is no index of the keys by bucket: a step hashes every key.
+    pub fn scan(&self, cursor: u64, count: usize, pattern: Option<&str>) -> (u64, Vec<String>) {
+        let start = cursor.reverse_bits();
+        let mut total = 0usize;
+        let mut ahead = Vec::new();
+        self.for_each_key(|key| {
+            total += 1;
+            let position = scan_position(key);
+            if position >= start {
+                ahead.push(position);
+            }
+        });
+
+        // the table fits the keyspace, like the tables of Redis after a rehash.
+        let bits = total
+            .next_power_of_two()
+            .trailing_zeros()
+            .clamp(MIN_CURSOR_BITS, u64::BITS - 1);
+        // the step stops at the end of the bucket of the count-th key.
+        let end = if count == 0 || ahead.len() <= count {
+            None
+        } else {
+            let (_, last, _) = ahead.select_nth_unstable(count - 1);
+            let bucket = *last >> (u64::BITS - bits);
+            (bucket + 1 < 1 << bits).then(|| (bucket + 1) << (u64::BITS - bits))
+        };
+
+        let mut keys = Vec::new();
+        self.for_each_key(|key| {
+            let position = scan_position(key);
+            if position >= start
+                && end.is_none_or(|end| position < end)
+                && pattern.is_none_or(|p| glob_match(p.as_bytes(), key.as_bytes()))
+            {
+                keys.push(key.to_string());
+            }
+        });
+        // a key changing type during the step may be visited in two maps.
+        keys.sort_unstable();
+        keys.dedup();
+        (end.map_or(0, u64::reverse_bits), keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// Run a whole iteration, calling `between` with the step number between steps.
+    fn scan_all(backend: &Backend, count: usize, mut between: impl FnMut(usize)) -> Vec<String> {
+        let (mut cursor, mut keys, mut step) = (0, Vec::new(), 0);
+        loop {
+            let (next, batch) = backend.scan(cursor, count, None);
+            keys.extend(batch);
+            if next == 0 {
+                return keys;
+            }
+            step += 1;
+            between(step);
+            cursor = next;
+        }
+    }
+
+    #[test]
+    fn test_scan_returns_every_key_once() {
+        let backend = Backend::new();
+        for i in 0..1000 {
+            backend.set(format!("key:{}", i), b"v".to_vec());
+        }
+        let keys = scan_all(&backend, 10, |_| {});
+        assert_eq!(keys.len(), 1000);
+        assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 1000);
+
+        let (cursor, keys) = backend.scan(0, 10_000, Some("key:1?"));
+        assert_eq!(cursor, 0);
+        assert_eq!(keys.len(), 10);
+    }
+
+    #[test]
+    fn test_scan_while_the_keyspace_grows_and_shrinks() {
+        let backend = Backend::new();
+        for i in 0..500 {
+            backend.set(format!("stable:{}", i), b"v".to_vec());
+            backend.set(format!("removed:{}", i), b"v".to_vec());
+        }
+        // the table grows from 1024 buckets to 4096 mid-scan, then shrinks to 512.
+        let keys = scan_all(&backend, 7, |step| match step {
+            10 => {
+                for i in 0..3000 {
+                    backend.set(format!("added:{}", i), b"v".to_vec());
+                }
+            }
+            30 => {
+                for i in 0..500 {
+                    backend.remove_key(&format!("removed:{}", i));
+                }
+                for i in 0..3000 {
+                    backend.remove_key(&format!("added:{}", i));
+                }
+            }
+            _ => {}
+        });
+        let keys: HashSet<String> = keys.into_iter().collect();
+        for i in 0..500 {
+            assert!(
+                keys.contains(&format!("stable:{}", i)),
+                "stable:{} missed",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_scan_empty_keyspace() {
+        let backend = Backend::new();
+        assert_eq!(backend.scan(0, 10, None), (0, vec![]));
+    }
+}
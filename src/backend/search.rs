@@ -0,0 +1,461 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::Bound,
+};
+
+use dashmap::mapref::entry::Entry;
+use thiserror::Error;
+
+use crate::{Backend, BulkString, RespFrame};
+
+/// How the values of a hash field are indexed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    /// Matched exactly or by prefix, case-insensitively.
+    Tag,
+    /// Matched by range, values which are not numbers are not indexed.
+    Numeric,
+}
+
+/// An inverted index over some fields of the hashes whose key starts with one of `prefixes`,
+/// kept up to date as the hashes are written.
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    prefixes: Vec<String>,
+    schema: Vec<(String, FieldType)>,
+    /// Field, tag and the keys having it.
+    tags: HashMap<String, BTreeMap<String, BTreeSet<String>>>,
+    /// Field, number and the keys having it.
+    numbers: HashMap<String, BTreeMap<Number, BTreeSet<String>>>,
+    /// The indexed values of every key, to remove them when the hash changes.
+    docs: HashMap<String, Vec<(String, String)>>,
+}
+
+/// A query: clauses which must all match, none matching every indexed key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query(Vec<Clause>);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Clause {
+    field: String,
+    predicate: Predicate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Predicate {
+    Tag(String),
+    TagPrefix(String),
+    Range(Bound<f64>, Bound<f64>),
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SearchError {
+    #[error("Index already exists")]
+    IndexExists,
+    #[error("Unknown Index name")]
+    UnknownIndex,
+    #[error("Unknown field `{0}`")]
+    UnknownField(String),
+    #[error("Syntax error in query: {0}")]
+    Syntax(String),
+}
+
+/// A totally ordered f64, to key the numeric indexes.
+#[derive(Debug, Clone, Copy)]
+struct Number(f64);
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl SearchIndex {
+    /// An index of the hashes whose key starts with one of `prefixes`, every hash if empty.
+    pub fn new(prefixes: Vec<String>, schema: Vec<(String, FieldType)>) -> Self {
+        Self {
+            prefixes,
+            schema,
+            ..Default::default()
+        }
+    }
+
+    pub fn prefixes(&self) -> &[String] {
+        &self.prefixes
+    }
+
+    pub fn schema(&self) -> &[(String, FieldType)] {
+        &self.schema
+    }
+
+    fn covers(&self, key: &str) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|p| key.starts_with(p.as_str()))
+    }
+
+    fn field_type(&self, field: &str) -> Option<FieldType> {
+        self.schema
+            .iter()
+            .find(|(name, _)| name == field)
+            .map(|(_, ty)| *ty)
+    }
+
+    /// Index the values of the fields of the hash at `key`, `None` if it doesn't exist.
+    fn update(&mut self, key: &str, fields: Option<Vec<(String, String)>>) {
+        for (field, value) in self.docs.remove(key).unwrap_or_default() {
+            match self.field_type(&field) {
+                Some(FieldType::Tag) => {
+                    remove_key(self.tags.get_mut(&field), &value.to_lowercase(), key)
+                }
+                Some(FieldType::Numeric) => {
+                    if let Ok(n) = value.parse() {
+                        remove_key(self.numbers.get_mut(&field), &Number(n), key)
+                    }
+                }
+                None => {}
+            }
+        }
+        let Some(fields) = fields else {
+            return;
+        };
+        let mut indexed = Vec::new();
+        for (field, value) in fields {
+            match self.field_type(&field) {
+                Some(FieldType::Tag) => {
+                    let tags = self.tags.entry(field.clone()).or_default();
+                    tags.entry(value.to_lowercase())
+                        .or_default()
+                        .insert(key.to_string());
+                }
+                Some(FieldType::Numeric) => match value.parse::<f64>() {
+                    Ok(n) if !n.is_nan() => {
+                        let numbers = self.numbers.entry(field.clone()).or_default();
+                        numbers
+                            .entry(Number(n))
+                            .or_default()
+                            .insert(key.to_string());
+                    }
+                    _ => continue,
+                },
+                None => continue,
+            }
+            indexed.push((field, value));
+        }
+        if !indexed.is_empty() {
+            self.docs.insert(key.to_string(), indexed);
+        }
+    }
+
+    /// The keys matching the query, sorted.
+    pub fn search(&self, query: &Query) -> Result<Vec<String>, SearchError> {
+        let mut res: Option<BTreeSet<String>> = None;
+        for clause in &query.0 {
+            let keys = self.matching(clause)?;
+            res = Some(match res {
+                Some(res) => res.intersection(&keys).cloned().collect(),
+                None => keys,
+            });
+        }
+        let keys = match res {
+            Some(keys) => keys.into_iter().collect(),
+            None => {
+                let mut keys: Vec<String> = self.docs.keys().cloned().collect();
+                keys.sort();
+                keys
+            }
+        };
+        Ok(keys)
+    }
+
+    fn matching(&self, clause: &Clause) -> Result<BTreeSet<String>, SearchError> {
+        let ty = self
+            .field_type(&clause.field)
+            .ok_or_else(|| SearchError::UnknownField(clause.field.clone()))?;
+        let mut keys = BTreeSet::new();
+        match (&clause.predicate, ty) {
+            (Predicate::Tag(tag), FieldType::Tag) => {
+                let index = self.tags.get(&clause.field);
+                if let Some(matched) = index.and_then(|index| index.get(tag)) {
+                    keys.extend(matched.iter().cloned());
+                }
+            }
+            (Predicate::TagPrefix(prefix), FieldType::Tag) => {
+                if let Some(index) = self.tags.get(&clause.field) {
+                    index
+                        .range::<String, _>((Bound::Included(prefix), Bound::Unbounded))
+                        .take_while(|(tag, _)| tag.starts_with(prefix.as_str()))
+                        .for_each(|(_, matched)| keys.extend(matched.iter().cloned()));
+                }
+            }
+            (Predicate::Range(min, max), FieldType::Numeric) => {
+                if let Some(index) = self.numbers.get(&clause.field) {
+                    let bounds = (min.map(Number), max.map(Number));
+                    if range_is_valid(&bounds) {
+                        index
+                            .range(bounds)
+                            .for_each(|(_, matched)| keys.extend(matched.iter().cloned()));
+                    }
+                }
+            }
+            _ => {
+                return Err(SearchError::Syntax(format!(
+                    "field `{}` can't be queried this way",
+                    clause.field
+                )))
+            }
+        }
+        Ok(keys)
+    }
+}
+
+fn remove_key<K: Ord>(index: Option<&mut BTreeMap<K, BTreeSet<String>>>, value: &K, key: &str) {
+    let Some(index) = index else {
+        return;
+    };
+    if let Some(keys) = index.get_mut(value) {
+        keys.remove(key);
+        if keys.is_empty() {
+            index.remove(value);
+        }
+    }
+}
+
+/// `BTreeMap::range` panics on an empty or inverted range.
+fn range_is_valid((min, max): &(Bound<Number>, Bound<Number>)) -> bool {
+    match (min, max) {
+        (Bound::Included(a), Bound::Included(b)) => a <= b,
+        (Bound::Included(a) | Bound::Excluded(a), Bound::Included(b) | Bound::Excluded(b)) => a < b,
+        _ => true,
+    }
+}
+
+impl Query {
+    /// Parse a query made of clauses separated by spaces: `@field:{tag}`, `@field:{prefix*}`,
+    /// or `@field:[min max]` where a bound may be `-inf`, `+inf`, or exclusive with `(`.
+    /// `*` alone matches every indexed key.
+    pub fn parse(query: &str) -> Result<Self, SearchError> {
+        let query = query.trim();
+        if query == "*" {
+            return Ok(Query(Vec::new()));
+        }
+        let syntax = |message: &str| SearchError::Syntax(message.to_string());
+        let mut clauses = Vec::new();
+        let mut rest = query;
+        while !rest.is_empty() {
+            let r = rest
+                .strip_prefix('@')
+                .ok_or_else(|| syntax("expected `@field:`"))?;
+            let colon = r
+                .find(':')
+                .ok_or_else(|| syntax("expected `:` after the field"))?;
+            let field = r[..colon].to_string();
+            let r = &r[colon + 1..];
+            let (predicate, r) = if let Some(r) = r.strip_prefix('{') {
+                let end = r.find('}').ok_or_else(|| syntax("unterminated `{`"))?;
+                let tag = r[..end].trim().to_lowercase();
+                let predicate = match tag.strip_suffix('*') {
+                    Some(prefix) => Predicate::TagPrefix(prefix.to_string()),
+                    None => Predicate::Tag(tag),
+                };
+                (predicate, &r[end + 1..])
+            } else if let Some(r) = r.strip_prefix('[') {
+                let end = r.find(']').ok_or_else(|| syntax("unterminated `[`"))?;
+                let mut bounds = r[..end].split_whitespace();
+                let (Some(min), Some(max), None) = (bounds.next(), bounds.next(), bounds.next())
+                else {
+                    return Err(syntax("expected `[min max]`"));
+                };
+                (
+                    Predicate::Range(parse_bound(min)?, parse_bound(max)?),
+                    &r[end + 1..],
+                )
+            } else {
+                return Err(syntax("expected `{` or `[` after the field"));
+            };
+            clauses.push(Clause { field, predicate });
+            rest = r.trim_start();
+        }
+        Ok(Query(clauses))
+    }
+}
+
+fn parse_bound(bound: &str) -> Result<Bound<f64>, SearchError> {
+    let (exclusive, number) = match bound.strip_prefix('(') {
+        Some(number) => (true, number),
+        None => (false, bound),
+    };
+    let n = match number.to_ascii_lowercase().as_str() {
+        "-inf" => return Ok(Bound::Unbounded),
+        "+inf" | "inf" => return Ok(Bound::Unbounded),
+        n => n
+            .parse::<f64>()
+            .ok()
+            .filter(|n| !n.is_nan())
+            .ok_or_else(|| SearchError::Syntax(format!("invalid number `{}`", number)))?,
+    };
+    Ok(if exclusive {
+        Bound::Excluded(n)
+    } else {
+        Bound::Included(n)
+    })
+}
+
+/// The text of a hash value, for indexing.
+fn field_text(value: &RespFrame) -> Option<String> {
+    match value {
+        RespFrame::BulkString(BulkString(Some(data))) => String::from_utf8(data.to_vec()).ok(),
+        RespFrame::SimpleString(s) => Some(s.0.clone()),
+        RespFrame::Integer(i) => Some(i.to_string()),
+        RespFrame::Double(d) => Some(d.to_string()),
+        _ => None,
+    }
+}
+
+impl Backend {
+    /// Create an index and index the existing hashes it covers.
+    pub fn ft_create(&self, name: &str, mut index: SearchIndex) -> Result<(), SearchError> {
+        let Entry::Vacant(entry) = self.indexes.entry(name.to_string()) else {
+            return Err(SearchError::IndexExists);
+        };
+        // hold the new index while scanning, so concurrent writes are indexed after the scan.
+        for hash in self.hmap.iter() {
+            if index.covers(hash.key()) {
+                index.update(hash.key(), Some(indexed_fields(&hash)));
+            }
+        }
+        entry.insert(index);
+        Ok(())
+    }
+
+    pub fn ft_dropindex(&self, name: &str) -> Result<(), SearchError> {
+        self.indexes
+            .remove(name)
+            .map(|_| ())
+            .ok_or(SearchError::UnknownIndex)
+    }
+
+    pub fn ft_search(&self, name: &str, query: &Query) -> Result<Vec<String>, SearchError> {
+        let index = self.indexes.get(name).ok_or(SearchError::UnknownIndex)?;
+        index.search(query)
+    }
+
+    /// Update the indexes covering the hash at `key` after it has been written.
+    /// The hash must not be locked by the caller.
+    pub(crate) fn reindex(&self, key: &str) {
+        if self.indexes.is_empty() {
+            return;
+        }
+        for mut index in self.indexes.iter_mut() {
+            if !index.covers(key) {
+                continue;
+            }
+            // read the hash while holding the index, so the last write is the one indexed.
+            let fields = self.hmap.get(key).map(|hash| indexed_fields(&hash));
+            index.update(key, fields);
+        }
+    }
+}
+
+fn indexed_fields(hash: &super::HashValue) -> Vec<(String, String)> {
+    hash.iter()
+        .filter_map(|(field, value)| Some((field.clone(), field_text(value)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hset(backend: &Backend, key: &str, field: &str, value: &str) {
+        backend
+            .hset(
+                key.to_string(),
+                field.to_string(),
+                BulkString::new(value).into(),
+            )
+            .unwrap();
+    }
+
+    fn search(backend: &Backend, query: &str) -> Vec<String> {
+        backend
+            .ft_search("idx", &Query::parse(query).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_query_parse() {
+        assert_eq!(
+            Query::parse("@city:{Paris}  @age:[(18 +inf]").unwrap(),
+            Query(vec![
+                Clause {
+                    field: "city".to_string(),
+                    predicate: Predicate::Tag("paris".to_string()),
+                },
+                Clause {
+                    field: "age".to_string(),
+                    predicate: Predicate::Range(Bound::Excluded(18.0), Bound::Unbounded),
+                },
+            ])
+        );
+        assert_eq!(Query::parse("*").unwrap(), Query(vec![]));
+        for invalid in ["city:{x}", "@city:{x", "@age:[1]", "@age:[a 2]", "@age:x"] {
+            assert!(Query::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_index_is_maintained_on_write() {
+        let backend = Backend::new();
+        hset(&backend, "user:1", "city", "Paris");
+        hset(&backend, "user:1", "age", "30");
+        hset(&backend, "other:1", "city", "Paris");
+        let schema = vec![
+            ("city".to_string(), FieldType::Tag),
+            ("age".to_string(), FieldType::Numeric),
+        ];
+        backend
+            .ft_create("idx", SearchIndex::new(vec!["user:".to_string()], schema))
+            .unwrap();
+        assert_eq!(
+            backend.ft_create("idx", SearchIndex::default()),
+            Err(SearchError::IndexExists)
+        );
+
+        hset(&backend, "user:2", "city", "Pau");
+        hset(&backend, "user:2", "age", "17");
+        assert_eq!(search(&backend, "@city:{paris}"), vec!["user:1"]);
+        assert_eq!(search(&backend, "@city:{pa*}"), vec!["user:1", "user:2"]);
+        assert_eq!(search(&backend, "@age:[18 +inf]"), vec!["user:1"]);
+        assert_eq!(search(&backend, "@age:[-inf (17]"), Vec::<String>::new());
+        assert_eq!(search(&backend, "@age:[30 10]"), Vec::<String>::new());
+        assert_eq!(search(&backend, "@city:{pa*} @age:[0 20]"), vec!["user:2"]);
+        assert_eq!(search(&backend, "*"), vec!["user:1", "user:2"]);
+
+        // the old value is unindexed.
+        hset(&backend, "user:1", "city", "Lyon");
+        assert_eq!(search(&backend, "@city:{paris}"), Vec::<String>::new());
+        backend
+            .hupdate("user:1", "city", |value| *value = None)
+            .unwrap();
+        assert_eq!(search(&backend, "@city:{lyon}"), Vec::<String>::new());
+
+        let res = backend.ft_search("idx", &Query::parse("@name:{x}").unwrap());
+        assert_eq!(res, Err(SearchError::UnknownField("name".to_string())));
+        backend.ft_dropindex("idx").unwrap();
+        assert_eq!(backend.ft_dropindex("idx"), Err(SearchError::UnknownIndex));
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashSet;
+
+use crate::{BulkString, EncodingConfig};
+
+use super::Encoding;
+
+/// A set value, in the most compact encoding its members allow.
+#[derive(Debug, Clone)]
+pub enum SetValue {
+    /// Integers only, sorted so membership is a binary search.
+    Intset(Vec<i64>),
+    /// Few short members, scanned linearly.
+    Listpack(Vec<BulkString>),
+    Hashtable(HashSet<BulkString>),
+}
+
+impl Default for SetValue {
+    fn default() -> Self {
+        SetValue::Intset(Vec::new())
+    }
+}
+
+impl SetValue {
+    /// Add a member, returns whether it was not already in the set.
+    pub fn insert(&mut self, member: BulkString, config: &EncodingConfig) -> bool {
+        let added = match self {
+            SetValue::Intset(ints) => match as_int(&member) {
+                Some(n) => match ints.binary_search(&n) {
+                    Ok(_) => false,
+                    Err(i) => {
+                        ints.insert(i, n);
+                        true
+                    }
+                },
+                None => {
+                    let mut members: Vec<BulkString> = self.iter().collect();
+                    members.push(member);
+                    *self = SetValue::Listpack(members);
+                    true
+                }
+            },
+            SetValue::Listpack(members) => {
+                if members.contains(&member) {
+                    false
+                } else {
+                    members.push(member);
+                    true
+                }
+            }
+            SetValue::Hashtable(members) => members.insert(member),
+        };
+        if added {
+            self.fit_encoding(config);
+        }
+        added
+    }
+
+    /// Remove a member, returns whether it was in the set.
+    pub fn remove(&mut self, member: &BulkString) -> bool {
+        match self {
+            SetValue::Intset(ints) => match as_int(member).map(|n| ints.binary_search(&n)) {
+                Some(Ok(i)) => {
+                    ints.remove(i);
+                    true
+                }
+                _ => false,
+            },
+            SetValue::Listpack(members) => match members.iter().position(|m| m == member) {
+                Some(i) => {
+                    members.swap_remove(i);
+                    true
+                }
+                None => false,
+            },
+            SetValue::Hashtable(members) => members.remove(member),
+        }
+    }
+
+    pub fn contains(&self, member: &BulkString) -> bool {
+        match self {
+            SetValue::Intset(ints) => {
+                as_int(member).is_some_and(|n| ints.binary_search(&n).is_ok())
+            }
+            SetValue::Listpack(members) => members.contains(member),
+            SetValue::Hashtable(members) => members.contains(member),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            SetValue::Intset(ints) => ints.len(),
+            SetValue::Listpack(members) => members.len(),
+            SetValue::Hashtable(members) => members.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BulkString> + '_ {
+        let (ints, listpack, hashtable) = match self {
+            SetValue::Intset(ints) => (Some(ints), None, None),
+            SetValue::Listpack(members) => (None, Some(members.iter()), None),
+            SetValue::Hashtable(members) => (None, None, Some(members.iter())),
+        };
+        let ints = ints
+            .into_iter()
+            .flatten()
+            .map(|n| BulkString::new(n.to_string()));
+        let members = listpack
+            .into_iter()
+            .flatten()
+            .chain(hashtable.into_iter().flatten());
+        ints.chain(members.cloned())
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        match self {
+            SetValue::Intset(_) => Encoding::Intset,
+            SetValue::Listpack(_) => Encoding::Listpack,
+            SetValue::Hashtable(_) => Encoding::Hashtable,
+        }
+    }
+
+    /// Convert a set which outgrew the limits of its encoding in `config`.
+    fn fit_encoding(&mut self, config: &EncodingConfig) {
+        let fits_listpack = || {
+            self.len() <= config.set_max_listpack_entries
+                && self
+                    .iter()
+                    .all(|m| m.as_ref().len() <= config.set_max_listpack_value)
+        };
+        let target = match &*self {
+            SetValue::Intset(ints) if ints.len() > config.set_max_intset_entries => {
+                if fits_listpack() {
+                    SetValue::Listpack(self.iter().collect())
+                } else {
+                    SetValue::Hashtable(self.iter().collect())
+                }
+            }
+            SetValue::Listpack(_) if !fits_listpack() => SetValue::Hashtable(self.iter().collect()),
+            _ => return,
+        };
+        *self = target;
+    }
+
+    /// Convert a set which shrank to the most compact encoding `config` allows,
+    /// and release spare capacity. Returns whether the encoding changed.
+    pub(crate) fn compact(&mut self, config: &EncodingConfig) -> bool {
+        let before = self.encoding();
+        let ints: Option<Vec<i64>> = match &*self {
+            SetValue::Intset(_) => None,
+            _ if self.len() <= config.set_max_intset_entries => {
+                self.iter().map(|m| as_int(&m)).collect()
+            }
+            _ => None,
+        };
+        if let Some(mut ints) = ints {
+            ints.sort_unstable();
+            *self = SetValue::Intset(ints);
+        } else if matches!(self, SetValue::Hashtable(_))
+            && self.len() <= config.set_max_listpack_entries
+            && self
+                .iter()
+                .all(|m| m.as_ref().len() <= config.set_max_listpack_value)
+        {
+            *self = SetValue::Listpack(self.iter().collect());
+        }
+        match self {
+            SetValue::Intset(ints) => ints.shrink_to_fit(),
+            SetValue::Listpack(members) => members.shrink_to_fit(),
+            SetValue::Hashtable(members) => members.shrink_to_fit(),
+        }
+        self.encoding() != before
+    }
+}
+
+/// The value of a member which can be stored in an intset: an integer
+/// written the way it would be formatted back, so "01" or "+1" are not.
+fn as_int(member: &BulkString) -> Option<i64> {
+    let n: i64 = std::str::from_utf8(member.as_ref()).ok()?.parse().ok()?;
+    (n.to_string().as_bytes() == member.as_ref()).then_some(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> EncodingConfig {
+        EncodingConfig {
+            set_max_intset_entries: 3,
+            set_max_listpack_entries: 2,
+            set_max_listpack_value: 4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_set_encoding_conversions() {
+        let mut set = SetValue::default();
+        for n in ["3", "1", "2"] {
+            assert!(set.insert(BulkString::new(n), &config()));
+        }
+        assert!(!set.insert(BulkString::new("1"), &config()));
+        assert_eq!(set.encoding(), Encoding::Intset);
+        // "01" is not stored as an integer, it would be formatted back as "1".
+        assert!(!set.contains(&BulkString::new("01")));
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            ["1", "2", "3"].map(BulkString::new)
+        );
+
+        // too many integers, and too many members for a listpack.
+        set.insert(BulkString::new("4"), &config());
+        assert_eq!(set.encoding(), Encoding::Hashtable);
+        assert!(set.remove(&BulkString::new("4")));
+        assert_eq!(set.encoding(), Encoding::Hashtable);
+
+        let mut set = SetValue::default();
+        set.insert(BulkString::new("1"), &config());
+        set.insert(BulkString::new("a"), &config());
+        assert_eq!(set.encoding(), Encoding::Listpack);
+        assert!(set.contains(&BulkString::new("1")));
+        set.insert(BulkString::new("long member"), &config());
+        assert_eq!(set.encoding(), Encoding::Hashtable);
+        assert_eq!(set.len(), 3);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Backend, BulkString, RespFrame};
+
+/// Most arguments of a command kept in its entry, like Redis.
+const MAX_ARGS: usize = 32;
+/// Longest argument kept in an entry, longer ones are truncated like Redis does.
+const MAX_ARG_LEN: usize = 128;
+
+/// A command which ran longer than the SLOWLOG threshold or its execution budget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowlogEntry {
+    pub id: u64,
+    /// Unix time the command was run at, in seconds.
+    pub timestamp: u64,
+    pub duration: Duration,
+    /// The arguments, the command name included, truncated like Redis does.
+    pub args: Vec<BulkString>,
+    pub client_addr: String,
+    /// Whether the command overran the execution budget of the server.
+    pub over_budget: bool,
+}
+
+/// The most recent slow commands, newest first.
+#[derive(Debug, Default)]
+pub struct Slowlog {
+    entries: Mutex<VecDeque<SlowlogEntry>>,
+    next_id: AtomicU64,
+}
+
+impl Backend {
+    /// Record a slow command, keeping the `max_len` most recent ones.
+    pub fn slowlog_push(
+        &self,
+        args: &[RespFrame],
+        duration: Duration,
+        client_addr: String,
+        over_budget: bool,
+        max_len: usize,
+    ) {
+        let entry = SlowlogEntry {
+            id: self.slowlog.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            duration,
+            args: truncate_args(args),
+            client_addr,
+            over_budget,
+        };
+        let mut entries = self
+            .slowlog
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        entries.push_front(entry);
+        entries.truncate(max_len);
+    }
+
+    /// The `count` most recent entries, newest first, all of them if `None`.
+    pub fn slowlog_get(&self, count: Option<usize>) -> Vec<SlowlogEntry> {
+        let entries = self
+            .slowlog
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let count = count.unwrap_or(entries.len());
+        entries.iter().take(count).cloned().collect()
+    }
+
+    pub fn slowlog_len(&self) -> usize {
+        let entries = self
+            .slowlog
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        entries.len()
+    }
+
+    pub fn slowlog_reset(&self) {
+        let mut entries = self
+            .slowlog
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        entries.clear();
+    }
+}
+
+fn truncate_args(args: &[RespFrame]) -> Vec<BulkString> {
+    let mut kept: Vec<BulkString> = args
+        .iter()
+        .take(if args.len() > MAX_ARGS {
+            MAX_ARGS - 1
+        } else {
+            MAX_ARGS
+        })
+        .map(|arg| match arg {
+            RespFrame::BulkString(arg) if arg.as_ref().len() > MAX_ARG_LEN => {
+                let mut truncated = arg.as_ref()[..MAX_ARG_LEN].to_vec();
+                let more = arg.as_ref().len() - MAX_ARG_LEN;
+                truncated.extend_from_slice(format!("... ({} more bytes)", more).as_bytes());
+                BulkString::new(truncated)
+            }
+            RespFrame::BulkString(arg) => arg.clone(),
+            other => BulkString::new(other.to_string()),
+        })
+        .collect();
+    if args.len() > MAX_ARGS {
+        let more = args.len() - kept.len();
+        kept.push(BulkString::new(format!("... ({} more arguments)", more)));
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<RespFrame> {
+        args.iter().map(|a| BulkString::new(*a).into()).collect()
+    }
+
+    #[test]
+    fn test_slowlog_keeps_the_newest_entries() {
+        let backend = Backend::new();
+        for key in ["a", "b", "c"] {
+            let command = args(&["get", key]);
+            backend.slowlog_push(&command, Duration::from_millis(20), "addr".into(), false, 2);
+        }
+        assert_eq!(backend.slowlog_len(), 2);
+        let entries = backend.slowlog_get(None);
+        assert_eq!(entries[0].id, 2);
+        assert_eq!(
+            entries[0].args,
+            vec![BulkString::new("get"), BulkString::new("c")]
+        );
+        assert_eq!(entries[1].id, 1);
+        assert_eq!(backend.slowlog_get(Some(1)).len(), 1);
+
+        backend.slowlog_reset();
+        assert_eq!(backend.slowlog_len(), 0);
+        // ids keep increasing after a reset.
+        backend.slowlog_push(&args(&["get"]), Duration::ZERO, "addr".into(), true, 2);
+        assert_eq!(backend.slowlog_get(None)[0].id, 3);
+    }
+
+    #[test]
+    fn test_slowlog_truncates_arguments() {
+        let long = "x".repeat(MAX_ARG_LEN + 10);
+        let mut command = vec!["sadd", "key", long.as_str()];
+        command.extend(std::iter::repeat_n("m", 40));
+        let args = truncate_args(&args(&command));
+        assert_eq!(args.len(), MAX_ARGS);
+        assert_eq!(
+            args[2],
+            BulkString::new(format!("{}... (10 more bytes)", "x".repeat(MAX_ARG_LEN)))
+        );
+        assert_eq!(args[31], BulkString::new("... (12 more arguments)"));
+    }
+}
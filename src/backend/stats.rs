@@ -0,0 +1,121 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::Backend;
+
+/// Weight of the last run of active expiry in [`StatsSnapshot::expired_stale_perc`].
+const STALE_PERC_WEIGHT: f64 = 0.05;
+
+/// Counters of INFO stats, collected as keys expire and connections are accepted.
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    /// Hashes removed because their last field expired.
+    expired_keys: AtomicU64,
+    /// Fields removed because they expired.
+    expired_subkeys: AtomicU64,
+    /// The bits of an `f64`, see [`StatsSnapshot::expired_stale_perc`].
+    expired_stale_perc: AtomicU64,
+    /// Connections refused because the server had too many clients.
+    rejected_connections: AtomicU64,
+}
+
+/// A snapshot of the [`ServerStats`] of a backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StatsSnapshot {
+    pub expired_keys: u64,
+    pub expired_subkeys: u64,
+    /// Share of the hashes visited by active expiry which had expired fields, in
+    /// percents, smoothed over the runs like Redis does.
+    pub expired_stale_perc: f64,
+    pub rejected_connections: u64,
+    /// Keys are never evicted, cold values are spilled to disk instead.
+    pub evicted_keys: u64,
+}
+
+impl ServerStats {
+    pub(crate) fn record_expired(&self, fields: usize, key_removed: bool) {
+        self.expired_subkeys
+            .fetch_add(fields as u64, Ordering::Relaxed);
+        if key_removed {
+            self.expired_keys.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Account for a run of active expiry which visited `sampled` hashes, of
+    /// which `expired` had expired fields.
+    pub(crate) fn record_expire_run(&self, sampled: usize, expired: usize) {
+        if sampled == 0 {
+            return;
+        }
+        let current = expired as f64 / sampled as f64 * 100.0;
+        // runs don't overlap, a lost update would only skip a sample anyway.
+        let previous = f64::from_bits(self.expired_stale_perc.load(Ordering::Relaxed));
+        let perc = current * STALE_PERC_WEIGHT + previous * (1.0 - STALE_PERC_WEIGHT);
+        self.expired_stale_perc
+            .store(perc.to_bits(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_rejected_connection(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Backend {
+    pub fn stats(&self) -> StatsSnapshot {
+        let stats = &self.stats;
+        StatsSnapshot {
+            expired_keys: stats.expired_keys.load(Ordering::Relaxed),
+            expired_subkeys: stats.expired_subkeys.load(Ordering::Relaxed),
+            expired_stale_perc: f64::from_bits(stats.expired_stale_perc.load(Ordering::Relaxed)),
+            rejected_connections: stats.rejected_connections.load(Ordering::Relaxed),
+            evicted_keys: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{BulkString, ExpireCondition};
+
+    use super::*;
+
+    #[test]
+    fn test_expiry_stats() {
+        let backend = Backend::new();
+        let now = Instant::now();
+        for key in ["h1", "h2"] {
+            for field in ["a", "b"] {
+                backend
+                    .hset(
+                        key.to_string(),
+                        field.to_string(),
+                        BulkString::new("v").into(),
+                    )
+                    .unwrap();
+            }
+        }
+        let soon = now + Duration::from_secs(1);
+        let fields = ["a".to_string(), "b".to_string()];
+        backend.hexpire("h1", &fields, soon, ExpireCondition::Always);
+        backend.hexpire("h2", &fields[..1], soon, ExpireCondition::Always);
+
+        assert_eq!(backend.active_expire(soon), 3);
+        let stats = backend.stats();
+        assert_eq!(stats.expired_keys, 1);
+        assert_eq!(stats.expired_subkeys, 3);
+        // both hashes visited had expired fields.
+        assert_eq!(stats.expired_stale_perc, 5.0);
+        assert_eq!(stats.evicted_keys, 0);
+    }
+
+    #[test]
+    fn test_expired_stale_perc_is_smoothed() {
+        let stats = ServerStats::default();
+        stats.record_expire_run(0, 0);
+        stats.record_expire_run(4, 4);
+        stats.record_expire_run(4, 0);
+        let perc = f64::from_bits(stats.expired_stale_perc.load(Ordering::Relaxed));
+        assert!((perc - 4.75).abs() < 1e-9);
+    }
+}
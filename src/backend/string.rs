@@ -0,0 +1,41 @@
+/// Growing strings reserve at most this much more than their length, like SDS_MAX_PREALLOC.
+const STRING_MAX_PREALLOC: usize = 1024 * 1024;
+
+/// Make room for a value modified in place to reach `len` bytes, greedily like the
+/// sds strings of Redis: twice `len`, or 1MB more above 1MB. A value appended to
+/// again and again is then reallocated a logarithmic number of times.
+pub(crate) fn grow_string(value: &mut Vec<u8>, len: usize) {
+    if len <= value.capacity() {
+        return;
+    }
+    let greedy = match len < STRING_MAX_PREALLOC {
+        true => len * 2,
+        false => len + STRING_MAX_PREALLOC,
+    };
+    value.reserve_exact(greedy - value.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grow_string() {
+        let mut value = b"abcd".to_vec();
+        grow_string(&mut value, 6);
+        assert_eq!(value.capacity(), 12);
+        // there is room already.
+        grow_string(&mut value, 12);
+        assert_eq!(value.capacity(), 12);
+
+        let mut reallocations = 0;
+        for _ in 0..10_000 {
+            let (len, capacity) = (value.len(), value.capacity());
+            grow_string(&mut value, len + 100);
+            value.extend_from_slice(&[b'x'; 100]);
+            reallocations += usize::from(value.capacity() != capacity);
+        }
+        assert!(reallocations < 20, "{}", reallocations);
+        assert!(value.capacity() - value.len() <= STRING_MAX_PREALLOC);
+    }
+}
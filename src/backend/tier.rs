@@ -0,0 +1,266 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use dashmap::DashMap;
+use tracing::warn;
+
+use crate::{Backend, StoredString, TieringConfig};
+
+/// How often the server checks whether values must be spilled.
+pub(crate) const SPILL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The disk tier of the string values, see [`TieringConfig`].
+#[derive(Debug)]
+pub(crate) struct Tier {
+    config: TieringConfig,
+    next_id: AtomicU64,
+    /// When each string key was last read or written, to spill the coldest first.
+    accessed: DashMap<String, Instant>,
+}
+
+/// A value written to its own file in the tier directory,
+/// which is removed when the value is dropped.
+#[derive(Debug)]
+pub struct SpilledValue {
+    path: PathBuf,
+    compressed: bool,
+    /// Length of the value, which may be compressed in the file.
+    len: usize,
+}
+
+impl Tier {
+    /// Open the tier directory, removing the values spilled by a previous process:
+    /// their keys were only in its memory.
+    pub(crate) fn open(config: TieringConfig) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        for entry in fs::read_dir(&config.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "spill") {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(Self {
+            config,
+            next_id: AtomicU64::new(0),
+            accessed: DashMap::new(),
+        })
+    }
+
+    fn spill(&self, data: &[u8], compressed: bool, len: usize) -> io::Result<SpilledValue> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let path = self.config.dir.join(format!("{}.spill", id));
+        fs::write(&path, data)?;
+        Ok(SpilledValue {
+            path,
+            compressed,
+            len,
+        })
+    }
+}
+
+impl SpilledValue {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Read the value back into memory.
+    ///
+    /// The disk tier is trusted like memory is: a value which can't be read
+    /// back is lost, and the command accessing it fails.
+    pub(crate) fn load(&self) -> StoredString {
+        let data = fs::read(&self.path).unwrap_or_else(|e| {
+            panic!(
+                "failed to read spilled value {}: {}",
+                self.path.display(),
+                e
+            )
+        });
+        if self.compressed {
+            StoredString::Compressed(data.into())
+        } else {
+            StoredString::Plain(Arc::new(data))
+        }
+    }
+}
+
+impl Drop for SpilledValue {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!(
+                "failed to remove spilled value {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+impl Backend {
+    pub fn is_tiered(&self) -> bool {
+        self.tier.is_some()
+    }
+
+    /// Record an access to the string at `key`. Must not be called while holding
+    /// an entry of `map`, which [`Backend::spill_cold`] locks before `accessed`.
+    pub(crate) fn touch(&self, key: &str) {
+        if let Some(tier) = &self.tier {
+            tier.accessed.insert(key.to_string(), Instant::now());
+        }
+    }
+
+    /// When the string at `key` was last read or written, if the disk tier tracks it.
+    pub(crate) fn last_access(&self, key: &str) -> Option<Instant> {
+        let tier = self.tier.as_ref()?;
+        tier.accessed.get(key).map(|at| *at)
+    }
+
+    /// The value at `key`, read back into memory if it was spilled.
+    pub(crate) fn fault_in(&self, key: &str) -> Option<Bytes> {
+        let mut value = self.map.get_mut(key)?;
+        if let StoredString::Spilled(spilled) = &*value {
+            *value = spilled.load();
+        }
+        Some(value.to_bytes())
+    }
+
+    /// Spill the least recently used string values to disk until the ones left in
+    /// memory fit in the configured budget. Returns how many values were spilled.
+    ///
+    /// Values are written without holding their key, and only replaced if they
+    /// were not modified in the meantime.
+    pub fn spill_cold(&self) -> usize {
+        let Some(tier) = &self.tier else {
+            return 0;
+        };
+        let mut resident = 0;
+        let mut candidates = Vec::new();
+        for entry in self.map.iter() {
+            resident += entry.allocated_size();
+            if entry.allocated_size() >= tier.config.min_value_len {
+                // keys never accessed since the tier was opened sort first.
+                let accessed = tier.accessed.get(entry.key()).map(|t| *t);
+                candidates.push((accessed, entry.key().clone()));
+            }
+        }
+        if tier.accessed.len() > 2 * self.map.len() {
+            // forget the keys which no longer hold a string.
+            let keys: HashSet<String> = self.map.iter().map(|entry| entry.key().clone()).collect();
+            tier.accessed.retain(|key, _| keys.contains(key));
+        }
+        if resident <= tier.config.max_memory {
+            return 0;
+        }
+
+        candidates.sort();
+        let mut spilled = 0;
+        for (_, key) in candidates {
+            if resident <= tier.config.max_memory {
+                break;
+            }
+            let snapshot = match self.map.get(&key).as_deref() {
+                Some(StoredString::Plain(value)) => StoredString::Plain(Arc::clone(value)),
+                Some(StoredString::Compressed(block)) => {
+                    StoredString::Compressed(Arc::clone(block))
+                }
+                _ => continue,
+            };
+            let res = match &snapshot {
+                StoredString::Plain(value) => tier.spill(value, false, snapshot.len()),
+                StoredString::Compressed(block) => tier.spill(block, true, snapshot.len()),
+                StoredString::Spilled(_) => unreachable!("spilled values are skipped"),
+            };
+            let value = match res {
+                Ok(value) => value,
+                Err(e) => {
+                    warn!("failed to spill value of {}: {}", key, e);
+                    break;
+                }
+            };
+            if let Some(mut stored) = self.map.get_mut(&key) {
+                if stored.same_as(&snapshot) {
+                    resident -= stored.allocated_size();
+                    *stored = StoredString::Spilled(value);
+                    spilled += 1;
+                }
+            }
+        }
+        spilled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::EncodingConfig;
+
+    use super::*;
+
+    fn backend(name: &str, max_memory: usize) -> anyhow::Result<(Backend, PathBuf)> {
+        let dir = std::env::temp_dir().join(format!("rredis-tier-{}-{}", name, std::process::id()));
+        let config = TieringConfig {
+            min_value_len: 4,
+            ..TieringConfig::new(dir.clone(), max_memory)
+        };
+        Ok((
+            Backend::with_tiering(EncodingConfig::default(), config)?,
+            dir,
+        ))
+    }
+
+    fn spilled(backend: &Backend, key: &str) -> bool {
+        matches!(*backend.map.get(key).unwrap(), StoredString::Spilled(_))
+    }
+
+    #[test]
+    fn test_cold_values_are_spilled_and_faulted_in() -> anyhow::Result<()> {
+        let (backend, dir) = backend("spill", 250)?;
+        for key in ["a", "b", "c"] {
+            backend.set(key.to_string(), key.repeat(100).into_bytes());
+        }
+        backend.get("a");
+        backend.set("tiny".to_string(), b"x".to_vec());
+
+        // "b" and "c" are colder than "a", one of them is enough.
+        assert_eq!(backend.spill_cold(), 1);
+        assert!(spilled(&backend, "b"));
+        assert_eq!(backend.map.get("b").unwrap().len(), 100);
+        assert!(!spilled(&backend, "a") && !spilled(&backend, "tiny"));
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+        assert_eq!(backend.spill_cold(), 0);
+
+        assert_eq!(backend.get("b"), Some(Bytes::from("b".repeat(100))));
+        assert!(!spilled(&backend, "b"));
+        assert_eq!(fs::read_dir(&dir)?.count(), 0);
+
+        assert_eq!(backend.spill_cold(), 1);
+        assert!(spilled(&backend, "c"));
+        backend
+            .update("c", |v| v.as_mut().unwrap().push(b'c'))
+            .unwrap();
+        assert_eq!(backend.get("c").map(|v| v.len()), Some(101));
+        assert_eq!(fs::read_dir(&dir)?.count(), 0);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_removed_spilled_values_delete_their_file() -> anyhow::Result<()> {
+        let (backend, dir) = backend("remove", 0)?;
+        backend.set("k".to_string(), b"value".to_vec());
+        assert_eq!(backend.spill_cold(), 1);
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+        assert!(backend.remove_key("k"));
+        assert_eq!(fs::read_dir(&dir)?.count(), 0);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::BTreeMap;
+
+use dashmap::mapref::entry::Entry;
+use thiserror::Error;
+
+use crate::{Backend, KeyType, WrongType};
+
+/// A series of timestamped samples, timestamps in milliseconds.
+#[derive(Debug, Clone, Default)]
+pub struct TimeSeries {
+    samples: BTreeMap<u64, f64>,
+    /// Samples older than this, relative to the latest sample, are dropped; 0 keeps them all.
+    retention_ms: u64,
+    labels: Vec<(String, String)>,
+    rules: Vec<CompactionRule>,
+}
+
+/// Downsampling of a series into another one, like TS.CREATERULE:
+/// each bucket of samples is aggregated into a single sample of the destination,
+/// written once a sample of a later bucket is added.
+#[derive(Debug, Clone)]
+struct CompactionRule {
+    dest: String,
+    aggregation: Aggregation,
+    bucket_ms: u64,
+    /// The start of the open bucket and its samples.
+    bucket: Option<(u64, Vec<f64>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Avg,
+    Sum,
+    Min,
+    Max,
+    Count,
+    First,
+    Last,
+    Range,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TimeSeriesError {
+    #[error(transparent)]
+    WrongType(#[from] WrongType),
+    #[error("TSDB: key already exists")]
+    KeyExists,
+    #[error("TSDB: the key does not exist")]
+    KeyNotFound,
+    #[error("TSDB: the destination key does not exist")]
+    DestNotFound,
+    #[error("TSDB: the destination key already has a src rule")]
+    DestHasRule,
+    #[error("TSDB: Timestamp is older than retention")]
+    TooOld,
+    #[error(
+        "TSDB: Error at upsert, update is not supported when DUPLICATE_POLICY is set to BLOCK mode"
+    )]
+    Duplicate,
+}
+
+impl Aggregation {
+    pub fn parse(name: &str) -> Option<Self> {
+        let aggregation = match name.to_ascii_lowercase().as_str() {
+            "avg" => Aggregation::Avg,
+            "sum" => Aggregation::Sum,
+            "min" => Aggregation::Min,
+            "max" => Aggregation::Max,
+            "count" => Aggregation::Count,
+            "first" => Aggregation::First,
+            "last" => Aggregation::Last,
+            "range" => Aggregation::Range,
+            _ => return None,
+        };
+        Some(aggregation)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Aggregation::Avg => "avg",
+            Aggregation::Sum => "sum",
+            Aggregation::Min => "min",
+            Aggregation::Max => "max",
+            Aggregation::Count => "count",
+            Aggregation::First => "first",
+            Aggregation::Last => "last",
+            Aggregation::Range => "range",
+        }
+    }
+
+    /// Aggregate the values of a bucket, in timestamp order, which must not be empty.
+    pub fn apply(&self, values: &[f64]) -> f64 {
+        let max = || values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let min = || values.iter().copied().fold(f64::INFINITY, f64::min);
+        match self {
+            Aggregation::Avg => values.iter().sum::<f64>() / values.len() as f64,
+            Aggregation::Sum => values.iter().sum(),
+            Aggregation::Min => min(),
+            Aggregation::Max => max(),
+            Aggregation::Count => values.len() as f64,
+            Aggregation::First => values[0],
+            Aggregation::Last => values[values.len() - 1],
+            Aggregation::Range => max() - min(),
+        }
+    }
+}
+
+impl TimeSeries {
+    pub fn new(retention_ms: u64, labels: Vec<(String, String)>) -> Self {
+        Self {
+            retention_ms,
+            labels,
+            ..Default::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn labels(&self) -> &[(String, String)] {
+        &self.labels
+    }
+
+    pub fn retention_ms(&self) -> u64 {
+        self.retention_ms
+    }
+
+    /// The compaction rules: destination, aggregation and bucket duration.
+    pub fn rules(&self) -> impl Iterator<Item = (&str, Aggregation, u64)> + '_ {
+        self.rules
+            .iter()
+            .map(|rule| (rule.dest.as_str(), rule.aggregation, rule.bucket_ms))
+    }
+
+    /// The samples with a timestamp in `from..=to`.
+    pub fn range(&self, from: u64, to: u64) -> impl Iterator<Item = (u64, f64)> + '_ {
+        self.samples.range(from..=to).map(|(ts, v)| (*ts, *v))
+    }
+
+    /// Add a sample, returns the samples to add to the destinations of the compaction rules.
+    fn add(
+        &mut self,
+        timestamp: u64,
+        value: f64,
+    ) -> Result<Vec<(String, u64, f64)>, TimeSeriesError> {
+        if let Some(latest) = self.samples.keys().next_back() {
+            if self.retention_ms > 0 && timestamp < latest.saturating_sub(self.retention_ms) {
+                return Err(TimeSeriesError::TooOld);
+            }
+        }
+        if self.samples.contains_key(&timestamp) {
+            return Err(TimeSeriesError::Duplicate);
+        }
+        self.samples.insert(timestamp, value);
+        if self.retention_ms > 0 {
+            let latest = *self
+                .samples
+                .keys()
+                .next_back()
+                .expect("a sample was just added");
+            let oldest = latest.saturating_sub(self.retention_ms);
+            self.samples = self.samples.split_off(&oldest);
+        }
+
+        let mut compacted = Vec::new();
+        for rule in &mut self.rules {
+            let start = timestamp - timestamp % rule.bucket_ms;
+            match &mut rule.bucket {
+                Some((current, values)) if *current == start => values.push(value),
+                // samples older than the open bucket are not compacted.
+                Some((current, _)) if *current > start => {}
+                bucket => {
+                    if let Some((current, values)) = bucket.take() {
+                        compacted.push((
+                            rule.dest.clone(),
+                            current,
+                            rule.aggregation.apply(&values),
+                        ));
+                    }
+                    *bucket = Some((start, vec![value]));
+                }
+            }
+        }
+        Ok(compacted)
+    }
+}
+
+impl Backend {
+    pub fn ts_create(&self, key: &str, series: TimeSeries) -> Result<(), TimeSeriesError> {
+        self.check_type(key, KeyType::TimeSeries)?;
+        match self.timeseries.entry(key.to_string()) {
+            Entry::Occupied(_) => Err(TimeSeriesError::KeyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(series);
+                self.key_changed(key);
+                Ok(())
+            }
+        }
+    }
+
+    /// Add a sample to the series at `key`, created with `create` if missing.
+    pub fn ts_add(
+        &self,
+        key: &str,
+        timestamp: u64,
+        value: f64,
+        create: impl FnOnce() -> TimeSeries,
+    ) -> Result<(), TimeSeriesError> {
+        self.check_type(key, KeyType::TimeSeries)?;
+        let mut series = self
+            .timeseries
+            .entry(key.to_string())
+            .or_insert_with(create);
+        let compacted = series.add(timestamp, value)?;
+        // don't hold the source while locking the destinations, they may share a shard.
+        drop(series);
+        self.key_changed(key);
+        for (dest, timestamp, value) in compacted {
+            // like RedisTimeSeries, a compacted sample can't fail its source.
+            let _ = self.ts_add(&dest, timestamp, value, TimeSeries::default);
+        }
+        Ok(())
+    }
+
+    /// Samples of the series at `key` in `from..=to`, aggregated by buckets of `bucket_ms`
+    /// starting at multiples of it if `aggregation` is set.
+    pub fn ts_range(
+        &self,
+        key: &str,
+        from: u64,
+        to: u64,
+        aggregation: Option<(Aggregation, u64)>,
+    ) -> Result<Vec<(u64, f64)>, TimeSeriesError> {
+        let series = self
+            .timeseries
+            .get(key)
+            .ok_or(TimeSeriesError::KeyNotFound)?;
+        let Some((aggregation, bucket_ms)) = aggregation else {
+            return Ok(series.range(from, to).collect());
+        };
+        let mut buckets: Vec<(u64, Vec<f64>)> = Vec::new();
+        for (timestamp, value) in series.range(from, to) {
+            let start = timestamp - timestamp % bucket_ms;
+            match buckets.last_mut() {
+                Some((current, values)) if *current == start => values.push(value),
+                _ => buckets.push((start, vec![value])),
+            }
+        }
+        Ok(buckets
+            .into_iter()
+            .map(|(start, values)| (start, aggregation.apply(&values)))
+            .collect())
+    }
+
+    /// Downsample the series at `src` into the one at `dest`, which must both exist.
+    pub fn ts_create_rule(
+        &self,
+        src: &str,
+        dest: &str,
+        aggregation: Aggregation,
+        bucket_ms: u64,
+    ) -> Result<(), TimeSeriesError> {
+        if !self.timeseries.contains_key(dest) {
+            return Err(TimeSeriesError::DestNotFound);
+        }
+        let has_rule = |series: &TimeSeries| series.rules.iter().any(|rule| rule.dest == dest);
+        if self.timeseries.iter().any(|series| has_rule(&series)) {
+            return Err(TimeSeriesError::DestHasRule);
+        }
+        let mut series = self
+            .timeseries
+            .get_mut(src)
+            .ok_or(TimeSeriesError::KeyNotFound)?;
+        series.rules.push(CompactionRule {
+            dest: dest.to_string(),
+            aggregation,
+            bucket_ms,
+            bucket: None,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ts_add_and_range() {
+        let backend = Backend::new();
+        for (ts, v) in [(10, 1.0), (20, 2.0), (30, 3.0)] {
+            backend.ts_add("ts", ts, v, TimeSeries::default).unwrap();
+        }
+        assert_eq!(
+            backend.ts_add("ts", 20, 5.0, TimeSeries::default),
+            Err(TimeSeriesError::Duplicate)
+        );
+        assert_eq!(
+            backend.ts_range("ts", 15, 30, None).unwrap(),
+            vec![(20, 2.0), (30, 3.0)]
+        );
+        assert_eq!(
+            backend
+                .ts_range("ts", 0, u64::MAX, Some((Aggregation::Sum, 20)))
+                .unwrap(),
+            vec![(0, 1.0), (20, 5.0)]
+        );
+        assert_eq!(
+            backend.ts_range("missing", 0, 1, None),
+            Err(TimeSeriesError::KeyNotFound)
+        );
+    }
+
+    #[test]
+    fn test_ts_retention() {
+        let backend = Backend::new();
+        backend
+            .ts_create("ts", TimeSeries::new(100, vec![]))
+            .unwrap();
+        backend
+            .ts_add("ts", 1000, 1.0, TimeSeries::default)
+            .unwrap();
+        assert_eq!(
+            backend.ts_add("ts", 899, 1.0, TimeSeries::default),
+            Err(TimeSeriesError::TooOld)
+        );
+        backend.ts_add("ts", 950, 2.0, TimeSeries::default).unwrap();
+        backend
+            .ts_add("ts", 1100, 3.0, TimeSeries::default)
+            .unwrap();
+        // the samples older than 1100 - 100 are dropped.
+        assert_eq!(
+            backend.ts_range("ts", 0, u64::MAX, None).unwrap(),
+            vec![(1000, 1.0), (1100, 3.0)]
+        );
+    }
+
+    #[test]
+    fn test_ts_compaction_rule() {
+        let backend = Backend::new();
+        backend.ts_create("raw", TimeSeries::default()).unwrap();
+        assert_eq!(
+            backend.ts_create_rule("raw", "avg", Aggregation::Avg, 10),
+            Err(TimeSeriesError::DestNotFound)
+        );
+        backend.ts_create("avg", TimeSeries::default()).unwrap();
+        backend
+            .ts_create_rule("raw", "avg", Aggregation::Avg, 10)
+            .unwrap();
+        assert_eq!(
+            backend.ts_create_rule("raw", "avg", Aggregation::Max, 10),
+            Err(TimeSeriesError::DestHasRule)
+        );
+
+        for (ts, v) in [(1, 1.0), (5, 3.0), (12, 10.0), (25, 7.0)] {
+            backend.ts_add("raw", ts, v, TimeSeries::default).unwrap();
+        }
+        // the bucket starting at 20 is still open.
+        assert_eq!(
+            backend.ts_range("avg", 0, u64::MAX, None).unwrap(),
+            vec![(0, 2.0), (10, 10.0)]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+
+use crate::{Backend, BulkString, RespArray, RespFrame, RespPush};
+use dashmap::DashMap;
+
+use super::PushSender;
+
+/// Server-assisted client-side caching state.
+///
+/// In the default mode the server remembers which keys each client has read
+/// and sends a single invalidation once such a key is modified.
+/// In broadcasting mode clients receive invalidations for every modified key
+/// matching one of their prefixes, without the server remembering anything.
+#[derive(Debug, Default)]
+pub struct TrackingTable {
+    keys: DashMap<String, HashSet<u64>>,
+    clients: DashMap<u64, TrackingClient>,
+}
+
+#[derive(Debug)]
+struct TrackingClient {
+    tx: PushSender,
+    bcast: bool,
+    prefixes: Vec<String>,
+}
+
+impl TrackingTable {
+    pub(crate) fn clients_len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub(crate) fn keys_len(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+impl Backend {
+    /// Enable client-side caching tracking for the connection.
+    pub fn enable_tracking(&self, id: u64, tx: PushSender, bcast: bool, prefixes: Vec<String>) {
+        let client = TrackingClient {
+            tx,
+            bcast,
+            prefixes,
+        };
+        self.tracking.clients.insert(id, client);
+    }
+
+    /// Disable tracking for the connection. Keys it has read are forgotten lazily.
+    pub fn disable_tracking(&self, id: u64) {
+        self.tracking.clients.remove(&id);
+    }
+
+    /// Remember that the connection has read the keys.
+    pub fn track_keys<'a>(&self, id: u64, keys: impl IntoIterator<Item = &'a str>) {
+        match self.tracking.clients.get(&id) {
+            Some(client) if !client.bcast => {}
+            _ => return,
+        }
+        for key in keys {
+            self.tracking
+                .keys
+                .entry(key.to_string())
+                .or_default()
+                .insert(id);
+        }
+    }
+
+    /// Notify the clients caching the key that it has been modified.
+    pub(crate) fn invalidate(&self, key: &str) {
+        let tracking = &self.tracking;
+        if tracking.clients.is_empty() {
+            return;
+        }
+
+        let mut receivers = tracking
+            .keys
+            .remove(key)
+            .map(|(_, ids)| ids)
+            .unwrap_or_default();
+        for client in tracking.clients.iter() {
+            if client.bcast
+                && (client.prefixes.is_empty()
+                    || client.prefixes.iter().any(|p| key.starts_with(p.as_str())))
+            {
+                receivers.insert(*client.key());
+            }
+        }
+
+        for id in receivers {
+            if let Some(client) = tracking.clients.get(&id) {
+                let _ = client.tx.send(invalidate_message(key));
+            }
+        }
+    }
+}
+
+fn invalidate_message(key: &str) -> RespFrame {
+    RespPush::new(vec![
+        BulkString::new("invalidate").into(),
+        RespArray::new(vec![BulkString::new(key).into()]).into(),
+    ])
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{backend::push_queue, PushOverflow};
+
+    use super::*;
+
+    #[test]
+    fn test_tracking_default_mode() {
+        let backend = Backend::new();
+        let (tx, mut rx) = push_queue(16, PushOverflow::Disconnect);
+        backend.enable_tracking(1, tx, false, vec![]);
+
+        // keys not read yet are not reported.
+        backend.set("foo".to_string(), "1".into());
+        assert!(rx.try_recv().is_none());
+
+        backend.track_keys(1, ["foo"]);
+        backend.set("foo".to_string(), "2".into());
+        assert_eq!(rx.try_recv().unwrap(), invalidate_message("foo"));
+
+        // invalidation is sent only once until the key is read again.
+        backend.set("foo".to_string(), "3".into());
+        assert!(rx.try_recv().is_none());
+
+        backend.track_keys(1, ["foo"]);
+        backend.disable_tracking(1);
+        backend.set("foo".to_string(), "4".into());
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_tracking_bcast_mode() {
+        let backend = Backend::new();
+        let (tx, mut rx) = push_queue(16, PushOverflow::Disconnect);
+        backend.enable_tracking(1, tx, true, vec!["user:".to_string()]);
+
+        backend
+            .hset(
+                "user:1".to_string(),
+                "name".to_string(),
+                BulkString::new("a").into(),
+            )
+            .unwrap();
+        assert_eq!(rx.try_recv().unwrap(), invalidate_message("user:1"));
+
+        backend.set("other".to_string(), "1".into());
+        assert!(rx.try_recv().is_none());
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use dashmap::{DashMap, DashSet};
+
+use crate::Backend;
+
+/// Keys watched by connections for their next transaction, see WATCH.
+///
+/// A connection is marked dirty once one of the keys it watches is modified,
+/// which makes its next EXEC fail.
+#[derive(Debug, Default)]
+pub struct WatchTable {
+    keys: DashMap<String, HashSet<u64>>,
+    dirty: DashSet<u64>,
+}
+
+impl Backend {
+    /// Watch the keys for the connection, until [`Backend::unwatch_keys`].
+    pub fn watch_keys<'a>(&self, id: u64, keys: impl IntoIterator<Item = &'a str>) {
+        for key in keys {
+            self.watches
+                .keys
+                .entry(key.to_string())
+                .or_default()
+                .insert(id);
+        }
+    }
+
+    /// Stop watching the keys for the connection, and forget whether they were modified.
+    pub fn unwatch_keys<'a>(&self, id: u64, keys: impl IntoIterator<Item = &'a str>) {
+        for key in keys {
+            self.watches.keys.remove_if_mut(key, |_, ids| {
+                ids.remove(&id);
+                ids.is_empty()
+            });
+        }
+        self.watches.dirty.remove(&id);
+    }
+
+    /// Whether a key the connection watches was modified since it watched it.
+    pub fn is_watch_dirty(&self, id: u64) -> bool {
+        self.watches.dirty.contains(&id)
+    }
+
+    /// Mark the connections watching the key as dirty.
+    pub(crate) fn touch_watched(&self, key: &str) {
+        if let Some(ids) = self.watches.keys.get(key) {
+            for id in ids.iter() {
+                self.watches.dirty.insert(*id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_keys() {
+        let backend = Backend::new();
+        backend.watch_keys(1, ["foo", "bar"]);
+        backend.watch_keys(2, ["bar"]);
+
+        backend.set("other".to_string(), "1".into());
+        assert!(!backend.is_watch_dirty(1));
+
+        backend.set("foo".to_string(), "1".into());
+        assert!(backend.is_watch_dirty(1));
+        assert!(!backend.is_watch_dirty(2));
+
+        backend.unwatch_keys(1, ["foo", "bar"]);
+        assert!(!backend.is_watch_dirty(1));
+        backend.set("bar".to_string(), "1".into());
+        assert!(!backend.is_watch_dirty(1));
+        assert!(backend.is_watch_dirty(2));
+
+        backend.unwatch_keys(2, ["bar"]);
+        assert!(backend.watches.keys.is_empty());
+    }
+}
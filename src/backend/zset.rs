@@ -0,0 +1,965 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hasher},
+};
+
+use dashmap::mapref::one::RefMut;
+use thiserror::Error;
+
+use crate::{Backend, BulkString, KeyType, WrongType};
+
+/// Most levels a skiplist node may have, enough for 2^64 elements with P = 1/4.
+const MAX_LEVEL: usize = 32;
+/// Index of the head node in the arena, it holds no element.
+const HEAD: usize = 0;
+
+/// The key of a sorted set and the elements popped from it.
+type Popped = (String, Vec<(BulkString, f64)>);
+
+/// A sorted set: members ordered by score, then lexicographically.
+///
+/// The scores are looked up in a hash map, the order is kept by a skiplist
+/// whose links count the elements they skip, so ranks are found in O(log n).
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    scores: HashMap<BulkString, f64>,
+    list: SkipList,
+}
+
+/// The flags of ZADD.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZAddFlags {
+    /// Only add new members.
+    pub nx: bool,
+    /// Only update existing members.
+    pub xx: bool,
+    /// Only update a score to a greater one, new members are still added.
+    pub gt: bool,
+    /// Only update a score to a lower one, new members are still added.
+    pub lt: bool,
+    /// Count the updated members along with the added ones.
+    pub ch: bool,
+}
+
+/// Why ZADD INCR failed.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ZIncrError {
+    #[error(transparent)]
+    WrongType(#[from] WrongType),
+    /// The increment added opposite infinities.
+    #[error("resulting score is not a number (NaN)")]
+    NaN,
+}
+
+/// An end of a score range, like the min and max of ZRANGEBYSCORE.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+/// An end of a lexicographical range, like the min and max of ZRANGEBYLEX.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    /// `-`, below every member.
+    Min,
+    /// `+`, above every member.
+    Max,
+    Inclusive(BulkString),
+    Exclusive(BulkString),
+}
+
+/// How a range of a sorted set is selected, see [`ZRangeSpec`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeBy {
+    /// Ranks, negative ones counting from the end.
+    Rank {
+        start: i64,
+        stop: i64,
+    },
+    Score {
+        min: ScoreBound,
+        max: ScoreBound,
+    },
+    /// Members, assuming all the elements have the same score like Redis does.
+    Lex {
+        min: LexBound,
+        max: LexBound,
+    },
+}
+
+/// A range of a sorted set, like the arguments of the unified ZRANGE syntax.
+/// It is resolved the same way for ZRANGE, ZRANGEBYSCORE and ZRANGESTORE.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZRangeSpec {
+    pub by: ZRangeBy,
+    /// Walk from the highest elements down, ranks then count from the end.
+    pub rev: bool,
+    /// Elements skipped before the range, only for scores and members, like LIMIT.
+    pub offset: usize,
+    pub count: Option<usize>,
+}
+
+/// Skiplist with its nodes in an arena, linked by index.
+#[derive(Debug, Clone)]
+struct SkipList {
+    nodes: Vec<Node>,
+    /// Slots of removed nodes, reused before growing the arena.
+    free: Vec<usize>,
+    tail: Option<usize>,
+    len: usize,
+    /// Number of levels in use.
+    level: usize,
+    rng: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: BulkString,
+    score: f64,
+    levels: Vec<Link>,
+    prev: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Link {
+    next: Option<usize>,
+    /// Number of elements between the node and `next`, `next` included.
+    span: usize,
+}
+
+impl ScoreBound {
+    /// Parse a bound like ZRANGEBYSCORE: a score, `-inf` or `+inf`,
+    /// prefixed with `(` to exclude it.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (exclusive, value) = match s.strip_prefix('(') {
+            Some(value) => (true, value),
+            None => (false, s),
+        };
+        let value = parse_score(value)?;
+        Some(Self { value, exclusive })
+    }
+
+    fn below(&self, score: f64) -> bool {
+        score < self.value || (self.exclusive && score == self.value)
+    }
+
+    fn above(&self, score: f64) -> bool {
+        score > self.value || (self.exclusive && score == self.value)
+    }
+}
+
+impl LexBound {
+    /// Parse a bound like ZRANGEBYLEX: `-`, `+`, or a member prefixed with
+    /// `[` to include it or `(` to exclude it.
+    pub fn parse(s: &[u8]) -> Option<Self> {
+        match s {
+            b"-" => Some(LexBound::Min),
+            b"+" => Some(LexBound::Max),
+            [b'[', member @ ..] => Some(LexBound::Inclusive(BulkString::new(member.to_vec()))),
+            [b'(', member @ ..] => Some(LexBound::Exclusive(BulkString::new(member.to_vec()))),
+            _ => None,
+        }
+    }
+
+    fn below(&self, member: &BulkString) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(min) => member < min,
+            LexBound::Exclusive(min) => member <= min,
+        }
+    }
+
+    fn above(&self, member: &BulkString) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(max) => member > max,
+            LexBound::Exclusive(max) => member >= max,
+        }
+    }
+}
+
+impl ZRangeSpec {
+    pub fn by_rank(start: i64, stop: i64) -> Self {
+        Self {
+            by: ZRangeBy::Rank { start, stop },
+            rev: false,
+            offset: 0,
+            count: None,
+        }
+    }
+}
+
+/// Parse a score, rejecting NaN like Redis does.
+pub fn parse_score(s: &str) -> Option<f64> {
+    let score = match s.to_ascii_lowercase().as_str() {
+        "inf" | "+inf" => f64::INFINITY,
+        "-inf" => f64::NEG_INFINITY,
+        s => s.parse().ok()?,
+    };
+    (!score.is_nan()).then_some(score)
+}
+
+impl ZAddFlags {
+    /// Whether the flags let a member scored `current` be set to `score`.
+    fn allow(&self, current: Option<f64>, score: f64) -> bool {
+        match current {
+            None => !self.xx,
+            Some(old) => !self.nx && (!self.gt || score > old) && (!self.lt || score < old),
+        }
+    }
+}
+
+impl SortedSet {
+    /// Add the member or update its score, returns whether it was added.
+    pub fn insert(&mut self, member: BulkString, score: f64) -> bool {
+        match self.scores.insert(member.clone(), score) {
+            Some(old) if old == score => false,
+            Some(old) => {
+                self.list.delete(old, &member);
+                self.list.insert(score, member);
+                false
+            }
+            None => {
+                self.list.insert(score, member);
+                true
+            }
+        }
+    }
+
+    /// Remove the member, returns whether it was in the set.
+    pub fn remove(&mut self, member: &BulkString) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => self.list.delete(score, member),
+            None => false,
+        }
+    }
+
+    pub fn score(&self, member: &BulkString) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// 0-based position of the member in score order.
+    pub fn rank(&self, member: &BulkString) -> Option<usize> {
+        let score = self.score(member)?;
+        self.list.rank(score, member)
+    }
+
+    /// The elements ranked in `start..=stop`, in score order.
+    pub fn range(&self, start: usize, stop: usize) -> Vec<(BulkString, f64)> {
+        if start > stop {
+            return vec![];
+        }
+        match self.list.by_rank(start) {
+            Some(first) => self.list.iter_from(first).take(stop - start + 1).collect(),
+            None => vec![],
+        }
+    }
+
+    /// The elements of the range, in its order.
+    pub fn select(&self, spec: &ZRangeSpec) -> Vec<(BulkString, f64)> {
+        match &spec.by {
+            ZRangeBy::Rank { start, stop } => {
+                let Some((start, stop)) = resolve_ranks(self.len(), *start, *stop) else {
+                    return vec![];
+                };
+                if !spec.rev {
+                    return self.range(start, stop);
+                }
+                let last = self.len() - 1;
+                let mut elements = self.range(last - stop, last - start);
+                elements.reverse();
+                elements
+            }
+            ZRangeBy::Score { min, max } => self.walk(
+                spec,
+                |node| min.below(node.score),
+                |node| max.above(node.score),
+            ),
+            ZRangeBy::Lex { min, max } => self.walk(
+                spec,
+                |node| min.below(&node.member),
+                |node| max.above(&node.member),
+            ),
+        }
+    }
+
+    /// The elements between the ones `before` the range and the ones `after` it,
+    /// which must be consistent with the order of the list.
+    fn walk(
+        &self,
+        spec: &ZRangeSpec,
+        before: impl Fn(&Node) -> bool,
+        after: impl Fn(&Node) -> bool,
+    ) -> Vec<(BulkString, f64)> {
+        let list = &self.list;
+        let first = match spec.rev {
+            false => list.first_not(&before),
+            true => list.last_not(&after),
+        };
+        // jump over the offset instead of walking it.
+        let first = first.and_then(|x| match spec.offset {
+            0 => Some(x),
+            offset => {
+                let rank = list.rank(list.nodes[x].score, &list.nodes[x].member)?;
+                match spec.rev {
+                    false => list.by_rank(rank + offset),
+                    true => list.by_rank(rank.checked_sub(offset)?),
+                }
+            }
+        });
+        let count = spec.count.unwrap_or(usize::MAX);
+        let mut elements = Vec::new();
+        let mut x = first;
+        while let Some(node) = x.map(|x| &list.nodes[x]) {
+            let out = match spec.rev {
+                false => after(node),
+                true => before(node),
+            };
+            if out || elements.len() >= count {
+                break;
+            }
+            elements.push((node.member.clone(), node.score));
+            x = match spec.rev {
+                false => node.levels[0].next,
+                true => node.prev,
+            };
+        }
+        elements
+    }
+
+    /// All elements, in score order.
+    pub fn iter(&self) -> impl Iterator<Item = (BulkString, f64)> + '_ {
+        self.list.nodes[HEAD].levels[0]
+            .next
+            .into_iter()
+            .flat_map(|first| self.list.iter_from(first))
+    }
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        let head = Node {
+            member: BulkString::null(),
+            score: f64::NEG_INFINITY,
+            levels: vec![
+                Link {
+                    next: None,
+                    span: 0
+                };
+                MAX_LEVEL
+            ],
+            prev: None,
+        };
+        Self {
+            nodes: vec![head],
+            free: Vec::new(),
+            tail: None,
+            len: 0,
+            level: 1,
+            rng: RandomState::new().build_hasher().finish() | 1,
+        }
+    }
+}
+
+impl SkipList {
+    /// Whether the node sorts before the element `(score, member)`.
+    fn precedes(&self, node: usize, score: f64, member: &BulkString) -> bool {
+        let node = &self.nodes[node];
+        node.score < score || (node.score == score && node.member < *member)
+    }
+
+    /// The last node before the element on each level.
+    fn predecessors(
+        &self,
+        score: f64,
+        member: &BulkString,
+    ) -> ([usize; MAX_LEVEL], [usize; MAX_LEVEL]) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i + 1 == self.level { 0 } else { rank[i + 1] };
+            while let Some(next) = self.nodes[x].levels[i].next {
+                if !self.precedes(next, score, member) {
+                    break;
+                }
+                rank[i] += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            update[i] = x;
+        }
+        (update, rank)
+    }
+
+    /// Insert an element, which must not be in the list.
+    fn insert(&mut self, score: f64, member: BulkString) {
+        let (mut update, mut rank) = self.predecessors(score, &member);
+        let level = self.random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].levels[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let node = Node {
+            member,
+            score,
+            levels: vec![
+                Link {
+                    next: None,
+                    span: 0
+                };
+                level
+            ],
+            prev: (update[0] != HEAD).then_some(update[0]),
+        };
+        let x = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = node;
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        for i in 0..level {
+            let prev = self.nodes[update[i]].levels[i];
+            let skipped = rank[0] - rank[i];
+            self.nodes[x].levels[i] = Link {
+                next: prev.next,
+                span: prev.span - skipped,
+            };
+            self.nodes[update[i]].levels[i] = Link {
+                next: Some(x),
+                span: skipped + 1,
+            };
+        }
+        for (i, prev) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[*prev].levels[i].span += 1;
+        }
+        match self.nodes[x].levels[0].next {
+            Some(next) => self.nodes[next].prev = Some(x),
+            None => self.tail = Some(x),
+        }
+        self.len += 1;
+    }
+
+    /// Remove an element, returns whether it was in the list.
+    fn delete(&mut self, score: f64, member: &BulkString) -> bool {
+        let (update, _) = self.predecessors(score, member);
+        let x = match self.nodes[update[0]].levels[0].next {
+            Some(x) if self.nodes[x].score == score && self.nodes[x].member == *member => x,
+            _ => return false,
+        };
+        for (i, prev) in update.iter().enumerate().take(self.level) {
+            let link = self.nodes[x].levels.get(i).copied();
+            let prev = &mut self.nodes[*prev].levels[i];
+            match link {
+                Some(link) if prev.next == Some(x) => {
+                    prev.span += link.span;
+                    prev.span -= 1;
+                    prev.next = link.next;
+                }
+                _ => prev.span -= 1,
+            }
+        }
+        let prev = self.nodes[x].prev;
+        match self.nodes[x].levels[0].next {
+            Some(next) => self.nodes[next].prev = prev,
+            None => self.tail = prev,
+        }
+        while self.level > 1 && self.nodes[HEAD].levels[self.level - 1].next.is_none() {
+            self.level -= 1;
+        }
+        // release the member now, the slot may stay unused for a while.
+        self.nodes[x].member = BulkString::null();
+        self.nodes[x].levels = Vec::new();
+        self.free.push(x);
+        self.len -= 1;
+        true
+    }
+
+    /// 0-based rank of an element of the list.
+    fn rank(&self, score: f64, member: &BulkString) -> Option<usize> {
+        let mut rank = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].next {
+                let node = &self.nodes[next];
+                if node.score > score || (node.score == score && node.member > *member) {
+                    break;
+                }
+                rank += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if x != HEAD && self.nodes[x].member == *member {
+                return Some(rank - 1);
+            }
+        }
+        None
+    }
+
+    /// The node at a 0-based rank.
+    fn by_rank(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].next {
+                if traversed + self.nodes[x].levels[i].span > target {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if traversed == target {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    /// The first node which is not `before` a range.
+    fn first_not(&self, before: impl Fn(&Node) -> bool) -> Option<usize> {
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].next {
+                if !before(&self.nodes[next]) {
+                    break;
+                }
+                x = next;
+            }
+        }
+        self.nodes[x].levels[0].next
+    }
+
+    /// The last node which is not `after` a range.
+    fn last_not(&self, after: impl Fn(&Node) -> bool) -> Option<usize> {
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].next {
+                if after(&self.nodes[next]) {
+                    break;
+                }
+                x = next;
+            }
+        }
+        (x != HEAD).then_some(x)
+    }
+
+    fn iter_from(&self, first: usize) -> impl Iterator<Item = (BulkString, f64)> + '_ {
+        std::iter::successors(Some(first), |x| self.nodes[*x].levels[0].next).map(|x| {
+            let node = &self.nodes[x];
+            (node.member.clone(), node.score)
+        })
+    }
+
+    /// A level for a new node: each level has a 1/4 chance to have a next one.
+    fn random_level(&mut self) -> usize {
+        let mut level = 1;
+        while level < MAX_LEVEL && self.next_random().is_multiple_of(4) {
+            level += 1;
+        }
+        level
+    }
+
+    /// xorshift64*, no need for more to pick levels.
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32
+    }
+}
+
+impl Backend {
+    /// Add the members with their scores, updating the scores of existing ones.
+    /// Returns the number of members added.
+    pub fn zadd(&self, key: &str, members: Vec<(f64, BulkString)>) -> Result<i64, WrongType> {
+        self.zadd_with(key, members, ZAddFlags::default())
+    }
+
+    /// ZADD with flags, the members they refuse are left as they are.
+    /// Returns the number of members added, or added and updated with CH.
+    pub fn zadd_with(
+        &self,
+        key: &str,
+        members: Vec<(f64, BulkString)>,
+        flags: ZAddFlags,
+    ) -> Result<i64, WrongType> {
+        let Some(mut zset) = self.zset_for_add(key, flags)? else {
+            return Ok(0);
+        };
+        let mut added = 0;
+        let mut updated = 0;
+        for (score, member) in members {
+            let current = zset.score(&member);
+            if current == Some(score) || !flags.allow(current, score) {
+                continue;
+            }
+            if zset.insert(member, score) {
+                added += 1;
+            } else {
+                updated += 1;
+            }
+        }
+        drop(zset);
+        if added + updated > 0 {
+            self.key_changed(key);
+        }
+        Ok(if flags.ch { added + updated } else { added })
+    }
+
+    /// ZADD INCR: add `incr` to the score of the member, a new one starting at 0.
+    /// Returns the new score, None when the flags refuse it.
+    pub fn zadd_incr(
+        &self,
+        key: &str,
+        incr: f64,
+        member: BulkString,
+        flags: ZAddFlags,
+    ) -> Result<Option<f64>, ZIncrError> {
+        let Some(mut zset) = self.zset_for_add(key, flags)? else {
+            return Ok(None);
+        };
+        let current = zset.score(&member);
+        let score = current.unwrap_or(0.0) + incr;
+        if score.is_nan() {
+            return Err(ZIncrError::NaN);
+        }
+        if !flags.allow(current, score) {
+            return Ok(None);
+        }
+        let changed = current != Some(score);
+        zset.insert(member, score);
+        drop(zset);
+        if changed {
+            self.key_changed(key);
+        }
+        Ok(Some(score))
+    }
+
+    /// The sorted set ZADD writes to, XX doesn't create it.
+    fn zset_for_add(
+        &self,
+        key: &str,
+        flags: ZAddFlags,
+    ) -> Result<Option<RefMut<'_, String, SortedSet>>, WrongType> {
+        self.check_type(key, KeyType::ZSet)?;
+        Ok(if flags.xx {
+            self.zset.get_mut(key)
+        } else {
+            Some(self.zset.entry(key.to_string()).or_default())
+        })
+    }
+
+    /// Remove the members, returns how many were in the set.
+    pub fn zrem(&self, key: &str, members: &[BulkString]) -> i64 {
+        let Some(mut zset) = self.zset.get_mut(key) else {
+            return 0;
+        };
+        let removed = members.iter().filter(|m| zset.remove(m)).count();
+        let empty = zset.is_empty();
+        drop(zset);
+        if empty {
+            self.zset.remove_if(key, |_, zset| zset.is_empty());
+        }
+        if removed > 0 {
+            self.key_changed(key);
+        }
+        removed as i64
+    }
+
+    /// Pop up to `count` elements from the first non-empty sorted set of `keys`, like ZMPOP:
+    /// the lowest scored first, or the highest with `max`. Returns its key and the elements,
+    /// `None` if every sorted set is empty.
+    pub fn zmpop(
+        &self,
+        keys: &[String],
+        count: usize,
+        max: bool,
+    ) -> Result<Option<Popped>, WrongType> {
+        for key in keys {
+            self.check_type(key, KeyType::ZSet)?;
+            if let Some(elements) = self.zpop(key, count, max) {
+                return Ok(Some((key.clone(), elements)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn zpop(&self, key: &str, count: usize, max: bool) -> Option<Vec<(BulkString, f64)>> {
+        let mut zset = self.zset.get_mut(key)?;
+        if count == 0 {
+            return Some(vec![]);
+        }
+        // the ranks past the end are clamped to it.
+        let stop = i64::try_from(count).map_or(i64::MAX, |count| count - 1);
+        let spec = ZRangeSpec {
+            rev: max,
+            ..ZRangeSpec::by_rank(0, stop)
+        };
+        let elements = zset.select(&spec);
+        for (member, _) in &elements {
+            zset.remove(member);
+        }
+        let empty = zset.is_empty();
+        drop(zset);
+        if empty {
+            self.zset.remove_if(key, |_, zset| zset.is_empty());
+        }
+        if !elements.is_empty() {
+            self.key_changed(key);
+        }
+        Some(elements)
+    }
+
+    pub fn zscore(&self, key: &str, member: &BulkString) -> Option<f64> {
+        self.zset.get(key).and_then(|zset| zset.score(member))
+    }
+
+    pub fn zcard(&self, key: &str) -> usize {
+        self.zset.get(key).map_or(0, |zset| zset.len())
+    }
+
+    pub fn zrank(&self, key: &str, member: &BulkString) -> Option<usize> {
+        self.zset.get(key).and_then(|zset| zset.rank(member))
+    }
+
+    /// The elements of the range of the sorted set at `key`.
+    pub fn zrange(&self, key: &str, spec: &ZRangeSpec) -> Vec<(BulkString, f64)> {
+        match self.zset.get(key) {
+            Some(zset) => zset.select(spec),
+            None => vec![],
+        }
+    }
+
+    /// Store the elements of the range of `src` as the sorted set at `dst`,
+    /// replacing it, like ZRANGESTORE. Returns the number of elements stored.
+    pub fn zrangestore(&self, dst: &str, src: &str, spec: &ZRangeSpec) -> usize {
+        let elements = self.zrange(src, spec);
+        let len = elements.len();
+        self.replace_type(dst, KeyType::ZSet);
+        let existed = match len {
+            0 => self.zset.remove(dst).is_some(),
+            _ => {
+                let mut zset = SortedSet::default();
+                for (member, score) in elements {
+                    zset.insert(member, score);
+                }
+                self.zset.insert(dst.to_string(), zset);
+                true
+            }
+        };
+        if existed {
+            self.key_changed(dst);
+        }
+        len
+    }
+}
+
+/// Resolve ranks counting from the end when negative to `start..=stop`,
+/// `None` if the range is empty.
+fn resolve_ranks(len: usize, start: i64, stop: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        return None;
+    }
+    Some((start as usize, stop as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bound(s: &str) -> ScoreBound {
+        ScoreBound::parse(s).unwrap()
+    }
+
+    fn members(elements: Vec<(BulkString, f64)>) -> Vec<String> {
+        elements
+            .into_iter()
+            .map(|(m, _)| String::from_utf8_lossy(m.as_ref()).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_skiplist_ranks_follow_updates() {
+        let mut zset = SortedSet::default();
+        // enough elements to build several levels.
+        for i in 0..1000 {
+            assert!(zset.insert(BulkString::new(format!("m{}", i)), (i % 100) as f64));
+        }
+        assert!(!zset.insert(BulkString::new("m5"), 5.0));
+        for i in (0..1000).step_by(3) {
+            assert!(zset.remove(&BulkString::new(format!("m{}", i))));
+        }
+        assert!(!zset.insert(BulkString::new("m1"), -1.0));
+
+        let expected: Vec<(BulkString, f64)> = {
+            let mut all: Vec<_> = zset.scores.iter().map(|(m, s)| (m.clone(), *s)).collect();
+            all.sort_by(|(ma, sa), (mb, sb)| sa.total_cmp(sb).then(ma.cmp(mb)));
+            all
+        };
+        assert_eq!(zset.iter().collect::<Vec<_>>(), expected);
+        for (rank, (member, _)) in expected.iter().enumerate() {
+            assert_eq!(zset.rank(member), Some(rank));
+        }
+        assert_eq!(zset.range(10, 12), expected[10..=12].to_vec());
+        assert_eq!(zset.rank(&BulkString::new("m0")), None);
+    }
+
+    #[test]
+    fn test_zrange_by_score() {
+        let backend = Backend::new();
+        let added = backend.zadd(
+            "z",
+            vec![
+                (1.0, BulkString::new("a")),
+                (2.0, BulkString::new("b")),
+                (2.0, BulkString::new("c")),
+                (3.0, BulkString::new("d")),
+            ],
+        );
+        assert_eq!(added, Ok(4));
+
+        let range = |min, max, offset, count| {
+            let spec = ZRangeSpec {
+                by: ZRangeBy::Score {
+                    min: bound(min),
+                    max: bound(max),
+                },
+                rev: false,
+                offset,
+                count,
+            };
+            members(backend.zrange("z", &spec))
+        };
+        assert_eq!(range("2", "+inf", 0, None), vec!["b", "c", "d"]);
+        assert_eq!(range("(1", "(3", 0, None), vec!["b", "c"]);
+        assert_eq!(range("-inf", "+inf", 1, Some(2)), vec!["b", "c"]);
+        assert_eq!(range("-inf", "+inf", 10, None), Vec::<String>::new());
+        assert!(range("4", "5", 0, None).is_empty());
+
+        let by_rank = ZRangeSpec::by_rank(-2, -1);
+        assert_eq!(members(backend.zrange("z", &by_rank)), vec!["c", "d"]);
+        assert_eq!(backend.zrank("z", &BulkString::new("c")), Some(2));
+        assert_eq!(
+            backend.zrem("z", &[BulkString::new("a"), BulkString::new("x")]),
+            1
+        );
+        assert_eq!(backend.zcard("z"), 3);
+        assert!(ScoreBound::parse("nan").is_none());
+    }
+
+    #[test]
+    fn test_zrange_rev_and_lex() {
+        let backend = Backend::new();
+        let elements = ["a", "b", "c", "d", "e"].map(|m| (0.0, BulkString::new(m)));
+        backend.zadd("z", elements.to_vec()).unwrap();
+        let range = |by, rev, offset, count| {
+            let spec = ZRangeSpec {
+                by,
+                rev,
+                offset,
+                count,
+            };
+            members(backend.zrange("z", &spec))
+        };
+        let lex = |min: &str, max: &str| ZRangeBy::Lex {
+            min: LexBound::parse(min.as_bytes()).unwrap(),
+            max: LexBound::parse(max.as_bytes()).unwrap(),
+        };
+        let rank = |start, stop| ZRangeBy::Rank { start, stop };
+
+        assert_eq!(range(rank(0, 1), true, 0, None), vec!["e", "d"]);
+        assert_eq!(range(rank(-2, -1), true, 0, None), vec!["b", "a"]);
+        assert_eq!(range(lex("[b", "(d"), false, 0, None), vec!["b", "c"]);
+        assert_eq!(range(lex("-", "+"), false, 1, Some(2)), vec!["b", "c"]);
+        assert_eq!(range(lex("(b", "+"), true, 0, None), vec!["e", "d", "c"]);
+        assert_eq!(range(lex("-", "[c"), true, 1, Some(1)), vec!["b"]);
+        assert_eq!(range(lex("-", "+"), true, 5, None), Vec::<String>::new());
+        assert!(range(lex("[x", "+"), false, 0, None).is_empty());
+        assert!(range(lex("-", "(a"), true, 0, None).is_empty());
+        let score = ZRangeBy::Score {
+            min: bound("-inf"),
+            max: bound("(0"),
+        };
+        assert!(range(score, true, 0, None).is_empty());
+        assert!(LexBound::parse(b"b").is_none());
+    }
+
+    #[test]
+    fn test_zrangestore() {
+        let backend = Backend::new();
+        let elements = [(1.0, "a"), (2.0, "b"), (3.0, "c")].map(|(s, m)| (s, BulkString::new(m)));
+        backend.zadd("src", elements.to_vec()).unwrap();
+        backend
+            .zadd("dst", vec![(9.0, BulkString::new("old"))])
+            .unwrap();
+
+        let spec = ZRangeSpec {
+            rev: true,
+            ..ZRangeSpec::by_rank(0, 1)
+        };
+        assert_eq!(backend.zrangestore("dst", "src", &spec), 2);
+        let all = ZRangeSpec::by_rank(0, -1);
+        assert_eq!(
+            backend.zrange("dst", &all),
+            vec![(BulkString::new("b"), 2.0), (BulkString::new("c"), 3.0)]
+        );
+        // an empty range deletes the destination.
+        assert_eq!(backend.zrangestore("dst", "missing", &all), 0);
+        assert_eq!(backend.zcard("dst"), 0);
+        assert!(!backend.zset.contains_key("dst"));
+    }
+
+    #[test]
+    fn test_zmpop() {
+        let backend = Backend::new();
+        let keys = ["a", "z"].map(String::from);
+        let elements = [(1.0, "a"), (2.0, "b"), (3.0, "c")].map(|(s, m)| (s, BulkString::new(m)));
+        backend.zadd("z", elements.to_vec()).unwrap();
+        assert_eq!(
+            backend.zmpop(&keys, 1, false),
+            Ok(Some(("z".to_string(), vec![(BulkString::new("a"), 1.0)])))
+        );
+        assert_eq!(
+            backend.zmpop(&keys, usize::MAX, true),
+            Ok(Some((
+                "z".to_string(),
+                vec![(BulkString::new("c"), 3.0), (BulkString::new("b"), 2.0)]
+            )))
+        );
+        assert!(!backend.zset.contains_key("z"));
+        assert_eq!(backend.zmpop(&keys, 1, false), Ok(None));
+
+        backend.set("a".to_string(), b"v".to_vec());
+        assert_eq!(backend.zmpop(&keys, 1, false), Err(WrongType));
+    }
+}
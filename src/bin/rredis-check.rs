@@ -0,0 +1,300 @@
+//! Validate the files loaded by the server, like redis-check-rdb and redis-check-aof:
+//! a dump.rdb written by Redis, or a file of RESP commands written by `--export`.
+//! Reports the keys the file holds, and with `--fix` cuts a truncated file of
+//! commands after its last complete command.
+//!
+//! With `--bigkeys <host:port>`, reports the largest keys of a running server instead,
+//! like `redis-cli --bigkeys`.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+};
+
+use anyhow::{anyhow, bail, Context};
+use bytes::BytesMut;
+use rredis::{
+    err::RespError, inspect_resp, lookup_command, repair_resp_file, version, Backend, BulkString,
+    ImportOptions, RespArray, RespDecodeV2, RespEncode, RespFrame,
+};
+
+/// Largest keys listed in the report.
+const TOP_KEYS: usize = 10;
+/// Keys asked for by each SCAN of `--bigkeys`.
+const SCAN_COUNT: usize = 1000;
+
+fn main() -> anyhow::Result<()> {
+    let mut fix = false;
+    let mut path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fix" => fix = true,
+            "--bigkeys" => {
+                let addr = args
+                    .next()
+                    .context("--bigkeys needs the address of a server")?;
+                return big_keys(&addr);
+            }
+            "-v" | "--version" => {
+                println!("{}", version::version_line());
+                return Ok(());
+            }
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => bail!("unknown argument: {}", arg),
+        }
+    }
+    let Some(path) = path else {
+        bail!("usage: rredis-check [--fix] <file> | rredis-check --bigkeys <host:port>");
+    };
+
+    let mut report = KeyReport::default();
+    let mut file = BufReader::new(File::open(&path)?);
+    if file.fill_buf()?.starts_with(b"REDIS") {
+        if fix {
+            bail!("--fix only repairs files of commands, not RDB files");
+        }
+        let backend = Backend::new();
+        let stats = backend
+            .load_rdb_file(&path)
+            .with_context(|| format!("{} is invalid", path))?;
+        // the keys are described by the commands recreating them.
+        inspect_resp(
+            &backend.serialize()[..],
+            ImportOptions::default(),
+            |frame, len| report.add(frame, len),
+        )?;
+        println!(
+            "{}: valid RDB file, {} keys, {} expired keys skipped",
+            path, stats.keys, stats.expired
+        );
+        report.print();
+        return Ok(());
+    }
+
+    let options = ImportOptions {
+        load_truncated: true,
+    };
+    let res = inspect_resp(file, options, |frame, len| report.add(frame, len));
+    // the keys read before an error are reported too.
+    report.print();
+    let stats = res.with_context(|| format!("{} is invalid", path))?;
+    println!(
+        "{}: {} commands, {} checksummed blocks",
+        path, stats.commands, stats.verified_blocks
+    );
+    match stats.truncated_at {
+        None => println!("{}: valid commands file", path),
+        Some(offset) if fix => {
+            repair_resp_file(&path)?;
+            println!("{}: truncated, cut after byte {}", path, offset);
+        }
+        Some(offset) => bail!(
+            "{} is truncated, the last complete command ends at byte {}, see --fix",
+            path,
+            offset
+        ),
+    }
+    Ok(())
+}
+
+/// Statistics of the keys named by the commands of a file.
+#[derive(Default)]
+struct KeyReport {
+    keys: HashMap<Vec<u8>, KeyStats>,
+    /// Commands naming no key, like FT.CREATE.
+    keyless: usize,
+}
+
+struct KeyStats {
+    /// The group of the first command naming the key, e.g. `hash`.
+    kind: &'static str,
+    commands: usize,
+    /// Bytes of the commands naming the key.
+    bytes: usize,
+}
+
+impl KeyReport {
+    fn add(&mut self, frame: &RespFrame, len: usize) {
+        let RespFrame::Array(args) = frame else {
+            return;
+        };
+        let Some(RespFrame::BulkString(name)) = args.first() else {
+            return;
+        };
+        let Some(spec) = lookup_command(name.as_ref()) else {
+            return;
+        };
+        let keys = spec.key_indexes_in(args);
+        if keys.is_empty() {
+            self.keyless += 1;
+        }
+        for i in keys {
+            let Some(RespFrame::BulkString(key)) = args.get(i) else {
+                continue;
+            };
+            let stats = self.keys.entry(key.as_ref().to_vec()).or_insert(KeyStats {
+                kind: spec.docs.group,
+                commands: 0,
+                bytes: 0,
+            });
+            stats.commands += 1;
+            stats.bytes += len;
+        }
+    }
+
+    fn print(&self) {
+        println!(
+            "{} keys, {} commands without keys",
+            self.keys.len(),
+            self.keyless
+        );
+        let mut kinds: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for stats in self.keys.values() {
+            let (keys, bytes) = kinds.entry(stats.kind).or_default();
+            *keys += 1;
+            *bytes += stats.bytes;
+        }
+        for (kind, (keys, bytes)) in kinds {
+            println!("  {:<12} {:>10} keys {:>14} bytes", kind, keys, bytes);
+        }
+
+        let mut largest: Vec<_> = self.keys.iter().collect();
+        largest.sort_unstable_by(|(a, a_stats), (b, b_stats)| {
+            b_stats.bytes.cmp(&a_stats.bytes).then_with(|| a.cmp(b))
+        });
+        if !largest.is_empty() {
+            println!("largest keys:");
+        }
+        for (key, stats) in largest.into_iter().take(TOP_KEYS) {
+            println!(
+                "  {:>14} bytes {:>8} commands  {:<12} {}",
+                stats.bytes,
+                stats.commands,
+                stats.kind,
+                String::from_utf8_lossy(key)
+            );
+        }
+    }
+}
+
+/// Report the largest keys of the server at `addr`, measured by MEMORY USAGE. The keys are
+/// listed by SCAN, so the server keeps serving its clients meanwhile: keys written during
+/// the scan may be missed, and the sizes are the ones of the moment each key is measured.
+fn big_keys(addr: &str) -> anyhow::Result<()> {
+    let mut conn = Connection::connect(addr)?;
+    let mut keys = 0usize;
+    let mut total = 0u64;
+    // the smallest of the largest keys on top, to be replaced by a larger one.
+    let mut largest = BinaryHeap::new();
+    let mut cursor = b"0".to_vec();
+    loop {
+        let count = SCAN_COUNT.to_string();
+        let reply = conn.query(&[vec![
+            b"SCAN".to_vec(),
+            cursor,
+            b"COUNT".to_vec(),
+            count.into(),
+        ]])?;
+        let (next, scanned) = scan_reply(reply.into_iter().next())?;
+        let usages: Vec<Vec<Vec<u8>>> = scanned
+            .iter()
+            .map(|key| vec![b"MEMORY".to_vec(), b"USAGE".to_vec(), key.clone()])
+            .collect();
+        for (key, usage) in scanned.into_iter().zip(conn.query(&usages)?) {
+            // a key removed since it was scanned has no usage.
+            let RespFrame::Integer(bytes) = usage else {
+                continue;
+            };
+            let bytes = bytes as u64;
+            keys += 1;
+            total += bytes;
+            largest.push(Reverse((bytes, key)));
+            if largest.len() > TOP_KEYS {
+                largest.pop();
+            }
+        }
+        if next == b"0" {
+            break;
+        }
+        cursor = next;
+    }
+
+    println!("{}: {} keys, {} bytes", addr, keys, total);
+    if !largest.is_empty() {
+        println!("largest keys:");
+    }
+    for Reverse((bytes, key)) in largest.into_sorted_vec() {
+        println!("  {:>14} bytes  {}", bytes, String::from_utf8_lossy(&key));
+    }
+    Ok(())
+}
+
+/// The cursor and the keys of a reply to SCAN.
+fn scan_reply(reply: Option<RespFrame>) -> anyhow::Result<(Vec<u8>, Vec<Vec<u8>>)> {
+    let bulk = |frame: &RespFrame| match frame {
+        RespFrame::BulkString(bulk) => (**bulk).as_ref().map(|bytes| bytes.to_vec()),
+        _ => None,
+    };
+    if let Some(RespFrame::Array(reply)) = &reply {
+        if let (Some(cursor), Some(RespFrame::Array(keys))) = (reply.first(), reply.get(1)) {
+            let cursor = bulk(cursor);
+            let keys: Option<Vec<_>> = keys.iter().map(bulk).collect();
+            if let (Some(cursor), Some(keys)) = (cursor, keys) {
+                return Ok((cursor, keys));
+            }
+        }
+    }
+    bail!("unexpected reply to SCAN: {:?}", reply)
+}
+
+/// A blocking client connection, which pipelines the commands of a query.
+struct Connection {
+    stream: TcpStream,
+    buf: BytesMut,
+}
+
+impl Connection {
+    fn connect(addr: &str) -> anyhow::Result<Self> {
+        let stream =
+            TcpStream::connect(addr).with_context(|| format!("can't connect to {}", addr))?;
+        Ok(Self {
+            stream,
+            buf: BytesMut::new(),
+        })
+    }
+
+    /// Send the commands at once, then read their replies. An error reply fails the query.
+    fn query(&mut self, commands: &[Vec<Vec<u8>>]) -> anyhow::Result<Vec<RespFrame>> {
+        let mut request = Vec::new();
+        for args in commands {
+            let args: Vec<RespFrame> = args
+                .iter()
+                .map(|a| BulkString::new(a.clone()).into())
+                .collect();
+            request.extend_from_slice(&RespFrame::from(RespArray::new(args)).encode());
+        }
+        self.stream.write_all(&request)?;
+        let mut replies = Vec::with_capacity(commands.len());
+        while replies.len() < commands.len() {
+            match RespFrame::decode(&mut self.buf) {
+                Ok(RespFrame::Error(e)) => bail!("the server replied: {:?}", e),
+                Ok(frame) => {
+                    replies.push(frame);
+                    continue;
+                }
+                Err(RespError::NotCompleted(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+            let mut chunk = [0; 16 * 1024];
+            match self.stream.read(&mut chunk)? {
+                0 => return Err(anyhow!("connection closed by the server")),
+                n => self.buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+        Ok(replies)
+    }
+}
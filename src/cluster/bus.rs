@@ -0,0 +1,286 @@
+//! The cluster bus: nodes ping each other with their own state and the nodes they
+//! know of, so that slot assignments and new nodes propagate through the cluster.
+//!
+//! Messages are RESP arrays of bulk strings, unlike the binary bus of Redis:
+//! `[kind, id, addr, bus-port, config-epoch, current-epoch, slots, gossip...]`,
+//! where `slots` is a space separated list of `start-end` ranges and each gossip
+//! entry is the `id`, `addr` and `bus-port` of a known node.
+
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::{anyhow, bail};
+use futures::SinkExt;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinSet,
+    time::timeout,
+};
+use tokio_stream::StreamExt;
+use tokio_util::codec::Framed;
+use tracing::{debug, info};
+
+use crate::{network::RespFrameCodec, BulkString, RespArray, RespFrame};
+
+use super::Cluster;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MessageKind {
+    /// A ping asking the receiver to add the sender to its cluster.
+    Meet,
+    Ping,
+    Pong,
+}
+
+/// The state of the sender of a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NodeHeader {
+    pub(crate) id: String,
+    pub(crate) addr: String,
+    pub(crate) bus_port: u16,
+    pub(crate) config_epoch: u64,
+    pub(crate) current_epoch: u64,
+    /// Ranges of the slots the sender serves, bounds included.
+    pub(crate) slots: Vec<(u16, u16)>,
+}
+
+/// A node known to the sender of a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GossipEntry {
+    pub(crate) id: String,
+    pub(crate) addr: String,
+    pub(crate) bus_port: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BusMessage {
+    pub(crate) kind: MessageKind,
+    pub(crate) sender: NodeHeader,
+    pub(crate) gossip: Vec<GossipEntry>,
+}
+
+impl MessageKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MessageKind::Meet => "MEET",
+            MessageKind::Ping => "PING",
+            MessageKind::Pong => "PONG",
+        }
+    }
+}
+
+impl From<BusMessage> for RespFrame {
+    fn from(message: BusMessage) -> Self {
+        let sender = message.sender;
+        let slots = sender
+            .slots
+            .iter()
+            .map(|(start, end)| format!("{}-{}", start, end))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut fields = vec![
+            message.kind.as_str().to_string(),
+            sender.id,
+            sender.addr,
+            sender.bus_port.to_string(),
+            sender.config_epoch.to_string(),
+            sender.current_epoch.to_string(),
+            slots,
+        ];
+        for entry in message.gossip {
+            fields.extend([entry.id, entry.addr, entry.bus_port.to_string()]);
+        }
+        let frames: Vec<RespFrame> = fields
+            .into_iter()
+            .map(|field| BulkString::new(field).into())
+            .collect();
+        RespArray::new(frames).into()
+    }
+}
+
+impl TryFrom<RespFrame> for BusMessage {
+    type Error = anyhow::Error;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        let RespFrame::Array(array) = frame else {
+            bail!("cluster bus message is not an array");
+        };
+        let fields = array
+            .iter()
+            .map(|frame| match frame {
+                RespFrame::BulkString(s) => Ok(String::from_utf8_lossy(s.as_ref()).into_owned()),
+                _ => Err(anyhow!("cluster bus message field is not a bulk string")),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if fields.len() < 7 || (fields.len() - 7) % 3 != 0 {
+            bail!("cluster bus message has {} fields", fields.len());
+        }
+        let kind = match fields[0].as_str() {
+            "MEET" => MessageKind::Meet,
+            "PING" => MessageKind::Ping,
+            "PONG" => MessageKind::Pong,
+            kind => bail!("unknown cluster bus message {}", kind),
+        };
+        let mut slots = Vec::new();
+        for range in fields[6].split(' ').filter(|range| !range.is_empty()) {
+            let (start, end) = range
+                .split_once('-')
+                .ok_or_else(|| anyhow!("invalid slot range {}", range))?;
+            slots.push((start.parse()?, end.parse()?));
+        }
+        let sender = NodeHeader {
+            id: fields[1].clone(),
+            addr: fields[2].clone(),
+            bus_port: fields[3].parse()?,
+            config_epoch: fields[4].parse()?,
+            current_epoch: fields[5].parse()?,
+            slots,
+        };
+        let gossip = fields[7..]
+            .chunks(3)
+            .map(|entry| {
+                Ok(GossipEntry {
+                    id: entry[0].clone(),
+                    addr: entry[1].clone(),
+                    bus_port: entry[2].parse()?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            kind,
+            sender,
+            gossip,
+        })
+    }
+}
+
+/// Run the cluster bus: answer the pings of the other nodes on `listener`, and
+/// keep a link pinging each known node. Dropping the future closes every link.
+pub(crate) async fn run(cluster: Arc<Cluster>, listener: TcpListener) -> anyhow::Result<()> {
+    tokio::select! {
+        res = accept_loop(cluster.clone(), listener) => res,
+        () = manage_links(cluster) => Ok(()),
+    }
+}
+
+async fn accept_loop(cluster: Arc<Cluster>, listener: TcpListener) -> anyhow::Result<()> {
+    let mut peers = JoinSet::new();
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        while peers.try_join_next().is_some() {}
+        let cluster = cluster.clone();
+        peers.spawn(async move {
+            if let Err(e) = answer_pings(cluster, stream).await {
+                debug!("Cluster bus connection from {} closed: {}", addr, e);
+            }
+        });
+    }
+}
+
+async fn answer_pings(cluster: Arc<Cluster>, stream: TcpStream) -> anyhow::Result<()> {
+    let mut framed = Framed::new(stream, RespFrameCodec::default());
+    while let Some(frame) = framed.next().await {
+        let message = BusMessage::try_from(frame?)?;
+        cluster.receive(&message, None);
+        framed
+            .send(cluster.message(MessageKind::Pong).into())
+            .await?;
+    }
+    Ok(())
+}
+
+/// Start a link to each node this node learns of, the link of a node ends once
+/// the node is forgotten.
+async fn manage_links(cluster: Arc<Cluster>) {
+    let mut linked = HashSet::new();
+    let mut links = JoinSet::new();
+    loop {
+        while let Some(res) = links.try_join_next() {
+            if let Ok(id) = res {
+                linked.remove(&id);
+            }
+        }
+        for node in cluster.nodes() {
+            if node.id == cluster.myid() || linked.contains(&node.id) {
+                continue;
+            }
+            links.spawn(ping_node(cluster.clone(), node.id.clone()));
+            linked.insert(node.id);
+        }
+        tokio::time::sleep(cluster.config().ping_interval).await;
+    }
+}
+
+/// Ping the node until it is forgotten, returns its id.
+async fn ping_node(cluster: Arc<Cluster>, id: String) -> String {
+    let interval = cluster.config().ping_interval;
+    let node_timeout = cluster.config().node_timeout;
+    loop {
+        let Some(node) = cluster.nodes().into_iter().find(|node| node.id == id) else {
+            return id;
+        };
+        let addr = node.bus_addr();
+        let res: anyhow::Result<()> = async {
+            let stream = timeout(node_timeout, TcpStream::connect(&addr)).await??;
+            let mut framed = Framed::new(stream, RespFrameCodec::default());
+            loop {
+                let Some(node) = cluster.nodes().into_iter().find(|node| node.id == id) else {
+                    return Ok(());
+                };
+                let kind = if node.handshake {
+                    MessageKind::Meet
+                } else {
+                    MessageKind::Ping
+                };
+                framed.send(cluster.message(kind).into()).await?;
+                let reply = timeout(node_timeout, framed.next())
+                    .await?
+                    .ok_or_else(|| anyhow!("connection closed"))??;
+                cluster.receive(&BusMessage::try_from(reply)?, Some(&id));
+                tokio::time::sleep(interval).await;
+            }
+        }
+        .await;
+        match res {
+            Ok(()) => return id,
+            Err(e) => info!("Cluster bus link to {} failed: {}", addr, e),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use crate::{RespDecodeV2, RespEncode};
+
+    use super::*;
+
+    #[test]
+    fn test_bus_message_round_trip() -> anyhow::Result<()> {
+        let message = BusMessage {
+            kind: MessageKind::Ping,
+            sender: NodeHeader {
+                id: "a".repeat(40),
+                addr: "127.0.0.1:7000".to_string(),
+                bus_port: 17000,
+                config_epoch: 2,
+                current_epoch: 3,
+                slots: vec![(0, 100), (200, 200)],
+            },
+            gossip: vec![GossipEntry {
+                id: "b".repeat(40),
+                addr: "127.0.0.1:7001".to_string(),
+                bus_port: 17001,
+            }],
+        };
+        let frame: RespFrame = message.clone().into();
+        let mut buf = BytesMut::from(frame.encode().as_slice());
+        let decoded = BusMessage::try_from(RespFrame::decode(&mut buf)?)?;
+        assert_eq!(decoded, message);
+
+        let frame: RespFrame = RespArray::new(vec![BulkString::new("PING").into()]).into();
+        assert!(BusMessage::try_from(frame).is_err());
+        Ok(())
+    }
+}
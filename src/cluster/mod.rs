@@ -0,0 +1,71 @@
+//! Cluster mode: hash slots, the unit Redis Cluster shards the keyspace by, the
+//! view of the cluster of this node, and the bus the nodes share it over.
+
+pub(crate) mod bus;
+mod state;
+
+pub(crate) use state::SlotRoute;
+pub use state::{Cluster, ClusterNode, SetSlot};
+
+/// Number of hash slots of the keyspace.
+pub const CLUSTER_SLOTS: u16 = 16384;
+
+/// The hash slot of a key: the CRC16 of the key modulo [`CLUSTER_SLOTS`], like Redis Cluster.
+///
+/// Only the hash tag of the key is hashed if it has one: the part between the first `{`
+/// and the next `}`, if not empty. Keys sharing a tag are in the same slot, so that
+/// multi-key commands can be used on them in cluster mode.
+pub fn key_slot(key: &[u8]) -> u16 {
+    crc16(hash_tag(key).unwrap_or(key)) % CLUSTER_SLOTS
+}
+
+fn hash_tag(key: &[u8]) -> Option<&[u8]> {
+    let start = key.iter().position(|&b| b == b'{')? + 1;
+    let len = key[start..].iter().position(|&b| b == b'}')?;
+    (len > 0).then(|| &key[start..start + len])
+}
+
+/// CRC16-CCITT (XModem), the variant used by Redis Cluster.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_slot() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        // slots reported by CLUSTER KEYSLOT.
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"bar"), 5061);
+        assert_eq!(key_slot(b""), 0);
+    }
+
+    #[test]
+    fn test_key_slot_hash_tags() {
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"user1000"));
+        assert_eq!(key_slot(b"x{foo}y{bar}"), key_slot(b"foo"));
+        assert_eq!(key_slot(b"{foo"), crc16(b"{foo") % CLUSTER_SLOTS);
+        // an empty tag hashes the whole key.
+        assert_eq!(key_slot(b"{}foo"), crc16(b"{}foo") % CLUSTER_SLOTS);
+        assert_eq!(
+            key_slot(b"foo{}{bar}"),
+            crc16(b"foo{}{bar}") % CLUSTER_SLOTS
+        );
+        assert_eq!(key_slot(b"foo{{bar}}zap"), key_slot(b"{bar"));
+        assert_eq!(key_slot(b"foo{bar}{zap}"), key_slot(b"bar"));
+    }
+}
@@ -0,0 +1,917 @@
+use std::{
+    collections::{hash_map::RandomState, BTreeMap, HashMap},
+    fmt::Write as _,
+    fs,
+    hash::{BuildHasher, Hasher},
+    io,
+    path::Path,
+    sync::RwLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use indexmap::IndexMap;
+use tracing::warn;
+
+use crate::{cmd::err::CommandError, config::ClusterConfig};
+
+use super::{
+    bus::{BusMessage, GossipEntry, MessageKind, NodeHeader},
+    CLUSTER_SLOTS,
+};
+
+/// How long a node removed with CLUSTER FORGET is ignored in gossip, like Redis.
+const FORGET_TTL: Duration = Duration::from_secs(60);
+
+/// A node of the cluster, as known by this node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterNode {
+    pub id: String,
+    /// Address clients reach the node at, `ip:port`.
+    pub addr: String,
+    pub bus_port: u16,
+    pub config_epoch: u64,
+    /// Met with CLUSTER MEET but not answered yet: its id is made up until it does.
+    pub handshake: bool,
+    /// When the node last answered a ping, `None` if it never did.
+    pub last_pong: Option<SystemTime>,
+}
+
+impl ClusterNode {
+    /// Address of the cluster bus of the node.
+    pub fn bus_addr(&self) -> String {
+        let host = self.addr.rsplit_once(':').map_or("", |(host, _)| host);
+        format!("{}:{}", host, self.bus_port)
+    }
+}
+
+/// The view of the cluster of this node: the nodes it knows of, and the node serving
+/// each hash slot. It is saved to the config file on every change, and exchanged
+/// with the other nodes over the cluster bus.
+#[derive(Debug)]
+pub struct Cluster {
+    config: ClusterConfig,
+    myself: String,
+    state: RwLock<ClusterState>,
+}
+
+#[derive(Debug)]
+struct ClusterState {
+    nodes: IndexMap<String, ClusterNode>,
+    /// The id of the node serving each slot.
+    slots: Vec<Option<String>>,
+    current_epoch: u64,
+    /// Nodes removed with CLUSTER FORGET, which gossip doesn't add back until then.
+    forgotten: HashMap<String, SystemTime>,
+    /// Slots of this node being moved to another node, by slot.
+    migrating: BTreeMap<u16, String>,
+    /// Slots being moved to this node, with the node they come from.
+    importing: BTreeMap<u16, String>,
+}
+
+/// Where the slot of a key is served.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SlotRoute {
+    Myself,
+    /// Served by this node, but its keys are being moved to the node at this address:
+    /// the keys already moved are served there.
+    Migrating(String),
+    Node(String),
+    Unassigned,
+}
+
+/// A change of the state of a slot, made with CLUSTER SETSLOT to move it between nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetSlot {
+    /// The keys of the slot are being moved from this node to this one.
+    Migrating(String),
+    /// The keys of the slot are being moved to this node from this one.
+    Importing(String),
+    /// Cancel the move of the slot.
+    Stable,
+    /// The slot is now served by this node, which ends its move.
+    Node(String),
+}
+
+impl Cluster {
+    /// Load the cluster state from the config file, or start a new cluster of one
+    /// node if there is none yet.
+    pub(crate) fn open(config: ClusterConfig) -> io::Result<Self> {
+        let (myself, state) = match fs::read_to_string(&config.config_file) {
+            Ok(content) => parse_config(&content, &config)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let myself = ClusterNode {
+                    id: random_node_id(),
+                    addr: config.announce_addr.clone(),
+                    bus_port: config.bus_port,
+                    config_epoch: 0,
+                    handshake: false,
+                    last_pong: None,
+                };
+                let mut state = ClusterState {
+                    nodes: IndexMap::new(),
+                    slots: vec![None; CLUSTER_SLOTS as usize],
+                    current_epoch: 0,
+                    forgotten: HashMap::new(),
+                    migrating: BTreeMap::new(),
+                    importing: BTreeMap::new(),
+                };
+                let id = myself.id.clone();
+                state.nodes.insert(id.clone(), myself);
+                (id, state)
+            }
+            Err(e) => return Err(e),
+        };
+        let cluster = Self {
+            config,
+            myself,
+            state: RwLock::new(state),
+        };
+        cluster.save()?;
+        Ok(cluster)
+    }
+
+    pub fn myid(&self) -> &str {
+        &self.myself
+    }
+
+    pub(crate) fn config(&self) -> &ClusterConfig {
+        &self.config
+    }
+
+    /// The nodes known to this node, itself included.
+    pub fn nodes(&self) -> Vec<ClusterNode> {
+        self.read().nodes.values().cloned().collect()
+    }
+
+    /// The id of the node serving the slot, if any.
+    pub fn slot_owner(&self, slot: u16) -> Option<String> {
+        self.read().slots.get(slot as usize).cloned().flatten()
+    }
+
+    /// Where the slot is served. A connection which sent ASKING is served the slots
+    /// being imported, to create the keys moved by MIGRATE.
+    pub(crate) fn route(&self, slot: u16, asking: bool) -> SlotRoute {
+        let state = self.read();
+        if asking && state.importing.contains_key(&slot) {
+            return SlotRoute::Myself;
+        }
+        match &state.slots[slot as usize] {
+            Some(id) if *id == self.myself => {
+                let target = state
+                    .migrating
+                    .get(&slot)
+                    .and_then(|id| state.nodes.get(id));
+                match target {
+                    Some(node) => SlotRoute::Migrating(node.addr.clone()),
+                    None => SlotRoute::Myself,
+                }
+            }
+            Some(id) => match state.nodes.get(id) {
+                Some(node) => SlotRoute::Node(node.addr.clone()),
+                None => SlotRoute::Unassigned,
+            },
+            None => SlotRoute::Unassigned,
+        }
+    }
+
+    /// Start a handshake with the node at `addr`, which joins the cluster once it answers.
+    pub(crate) fn meet(&self, addr: String, bus_port: u16) {
+        let mut state = self.write();
+        let known = state
+            .nodes
+            .values()
+            .any(|node| node.addr == addr && node.bus_port == bus_port);
+        if known {
+            return;
+        }
+        let node = ClusterNode {
+            id: random_node_id(),
+            addr,
+            bus_port,
+            config_epoch: 0,
+            handshake: true,
+            last_pong: None,
+        };
+        state.nodes.insert(node.id.clone(), node);
+    }
+
+    /// Remove the node and release its slots. Gossip won't add it back for a minute.
+    pub(crate) fn forget(&self, id: &str) -> Result<(), CommandError> {
+        if id == self.myself {
+            return Err(CommandError::InvalidArgument(
+                "I tried hard but I can't forget myself...".to_string(),
+            ));
+        }
+        {
+            let mut state = self.write();
+            if state.nodes.shift_remove(id).is_none() {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unknown node {}",
+                    id
+                )));
+            }
+            for slot in state.slots.iter_mut() {
+                if slot.as_deref() == Some(id) {
+                    *slot = None;
+                }
+            }
+            state.migrating.retain(|_, node| node != id);
+            state.importing.retain(|_, node| node != id);
+            state
+                .forgotten
+                .insert(id.to_string(), SystemTime::now() + FORGET_TTL);
+        }
+        self.save_or_warn();
+        Ok(())
+    }
+
+    /// Assign the slots to this node, they must not be served by any node yet.
+    pub(crate) fn add_slots(&self, slots: &[u16]) -> Result<(), CommandError> {
+        {
+            let mut state = self.write();
+            let mut seen = vec![false; CLUSTER_SLOTS as usize];
+            for &slot in slots {
+                if std::mem::replace(&mut seen[slot as usize], true) {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Slot {} specified multiple times",
+                        slot
+                    )));
+                }
+                if state.slots[slot as usize].is_some() {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Slot {} is already busy",
+                        slot
+                    )));
+                }
+            }
+            for &slot in slots {
+                state.slots[slot as usize] = Some(self.myself.clone());
+            }
+        }
+        self.save_or_warn();
+        Ok(())
+    }
+
+    /// Change the state of the slot to move it between nodes, like CLUSTER SETSLOT.
+    pub(crate) fn set_slot(&self, slot: u16, change: SetSlot) -> Result<(), CommandError> {
+        {
+            let mut state = self.write();
+            let state = &mut *state;
+            let known = |id: &str| {
+                if state.nodes.contains_key(id) {
+                    Ok(())
+                } else {
+                    Err(CommandError::InvalidArgument(format!(
+                        "I don't know about node {}",
+                        id
+                    )))
+                }
+            };
+            let owned = state.slots[slot as usize].as_deref() == Some(self.myself.as_str());
+            match change {
+                SetSlot::Migrating(id) => {
+                    if !owned {
+                        return Err(CommandError::InvalidArgument(format!(
+                            "I'm not the owner of hash slot {}",
+                            slot
+                        )));
+                    }
+                    known(&id)?;
+                    state.migrating.insert(slot, id);
+                }
+                SetSlot::Importing(id) => {
+                    if owned {
+                        return Err(CommandError::InvalidArgument(format!(
+                            "I'm already the owner of hash slot {}",
+                            slot
+                        )));
+                    }
+                    known(&id)?;
+                    state.importing.insert(slot, id);
+                }
+                SetSlot::Stable => {
+                    state.migrating.remove(&slot);
+                    state.importing.remove(&slot);
+                }
+                SetSlot::Node(id) => {
+                    known(&id)?;
+                    state.migrating.remove(&slot);
+                    if id == self.myself && state.importing.remove(&slot).is_some() {
+                        // a new config epoch makes the other nodes, the previous owner
+                        // included, take this node's claim over the previous one.
+                        state.current_epoch += 1;
+                        let epoch = state.current_epoch;
+                        state.nodes[&self.myself].config_epoch = epoch;
+                    }
+                    state.slots[slot as usize] = Some(id);
+                }
+            }
+        }
+        self.save_or_warn();
+        Ok(())
+    }
+
+    /// The description of the nodes of CLUSTER NODES, also the format of the config file.
+    pub fn describe_nodes(&self) -> String {
+        let state = self.read();
+        let mut out = String::new();
+        for node in state.nodes.values() {
+            let flags = if node.id == self.myself {
+                "myself,master"
+            } else if node.handshake {
+                "handshake"
+            } else {
+                "master"
+            };
+            let pong = node
+                .last_pong
+                .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |at| at.as_millis());
+            let link = if node.id == self.myself || self.is_reachable(node) {
+                "connected"
+            } else {
+                "disconnected"
+            };
+            let _ = write!(
+                out,
+                "{} {}@{} {} - 0 {} {} {}",
+                node.id, node.addr, node.bus_port, flags, pong, node.config_epoch, link
+            );
+            for (start, end) in slot_ranges(&state.slots, &node.id) {
+                if start == end {
+                    let _ = write!(out, " {}", start);
+                } else {
+                    let _ = write!(out, " {}-{}", start, end);
+                }
+            }
+            if node.id == self.myself {
+                for (slot, id) in &state.migrating {
+                    let _ = write!(out, " [{}->-{}]", slot, id);
+                }
+                for (slot, id) in &state.importing {
+                    let _ = write!(out, " [{}-<-{}]", slot, id);
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// The `field:value` lines of CLUSTER INFO.
+    pub fn info(&self) -> String {
+        let state = self.read();
+        let assigned = state.slots.iter().filter(|slot| slot.is_some()).count();
+        let size = state
+            .nodes
+            .keys()
+            .filter(|id| state.slots.iter().any(|slot| slot.as_ref() == Some(id)))
+            .count();
+        let my_epoch = state.nodes.get(&self.myself).map_or(0, |n| n.config_epoch);
+        let status = if assigned == CLUSTER_SLOTS as usize {
+            "ok"
+        } else {
+            "fail"
+        };
+        format!(
+            "cluster_enabled:1\r\ncluster_state:{}\r\ncluster_slots_assigned:{}\r\n\
+             cluster_known_nodes:{}\r\ncluster_size:{}\r\n\
+             cluster_current_epoch:{}\r\ncluster_my_epoch:{}\r\n",
+            status,
+            assigned,
+            state.nodes.len(),
+            size,
+            state.current_epoch,
+            my_epoch
+        )
+    }
+
+    fn is_reachable(&self, node: &ClusterNode) -> bool {
+        node.last_pong
+            .and_then(|at| at.elapsed().ok())
+            .is_some_and(|elapsed| elapsed < self.config.node_timeout)
+    }
+
+    /// The message this node sends on the bus: its own state, and the nodes it knows.
+    pub(crate) fn message(&self, kind: MessageKind) -> BusMessage {
+        let state = self.read();
+        let myself = &state.nodes[&self.myself];
+        let sender = NodeHeader {
+            id: myself.id.clone(),
+            addr: myself.addr.clone(),
+            bus_port: myself.bus_port,
+            config_epoch: myself.config_epoch,
+            current_epoch: state.current_epoch,
+            slots: slot_ranges(&state.slots, &self.myself),
+        };
+        let gossip = state
+            .nodes
+            .values()
+            .filter(|node| node.id != self.myself && !node.handshake)
+            .map(|node| GossipEntry {
+                id: node.id.clone(),
+                addr: node.addr.clone(),
+                bus_port: node.bus_port,
+            })
+            .collect();
+        BusMessage {
+            kind,
+            sender,
+            gossip,
+        }
+    }
+
+    /// Apply a message received on the bus. `link` is the id this node knows the
+    /// sender by when it is the reply to one of its pings.
+    pub(crate) fn receive(&self, message: &BusMessage, link: Option<&str>) {
+        let changed = {
+            let mut state = self.write();
+            let now = SystemTime::now();
+            state.forgotten.retain(|_, until| *until > now);
+            self.receive_locked(&mut state, message, link, now)
+        };
+        if changed {
+            self.save_or_warn();
+        }
+    }
+
+    fn receive_locked(
+        &self,
+        state: &mut ClusterState,
+        message: &BusMessage,
+        link: Option<&str>,
+        now: SystemTime,
+    ) -> bool {
+        let sender = &message.sender;
+        if sender.id == self.myself || state.forgotten.contains_key(&sender.id) {
+            return false;
+        }
+        let mut changed = false;
+        // the node met under a made-up id tells its real one.
+        if let Some(link) = link.filter(|link| *link != sender.id) {
+            if state.nodes.get(link).is_some_and(|node| node.handshake) {
+                state.nodes.shift_remove(link);
+                changed = true;
+            }
+        }
+        if !state.nodes.contains_key(&sender.id) {
+            // only nodes met by an administrator, or the node itself, may join.
+            if link.is_none() && message.kind != MessageKind::Meet {
+                return changed;
+            }
+            state.nodes.insert(
+                sender.id.clone(),
+                ClusterNode {
+                    id: sender.id.clone(),
+                    addr: sender.addr.clone(),
+                    bus_port: sender.bus_port,
+                    config_epoch: 0,
+                    handshake: false,
+                    last_pong: None,
+                },
+            );
+            changed = true;
+        }
+        let node = &mut state.nodes[&sender.id];
+        if node.addr != sender.addr || node.bus_port != sender.bus_port {
+            node.addr = sender.addr.clone();
+            node.bus_port = sender.bus_port;
+            changed = true;
+        }
+        if node.config_epoch != sender.config_epoch {
+            node.config_epoch = sender.config_epoch;
+            changed = true;
+        }
+        if link.is_some() {
+            node.last_pong = Some(now);
+        }
+        if sender.current_epoch > state.current_epoch {
+            state.current_epoch = sender.current_epoch;
+            changed = true;
+        }
+        changed |= self.apply_slot_claims(state, sender);
+
+        for entry in &message.gossip {
+            let known = state.nodes.contains_key(&entry.id)
+                || entry.id == self.myself
+                || state.forgotten.contains_key(&entry.id);
+            if !known {
+                state.nodes.insert(
+                    entry.id.clone(),
+                    ClusterNode {
+                        id: entry.id.clone(),
+                        addr: entry.addr.clone(),
+                        bus_port: entry.bus_port,
+                        config_epoch: 0,
+                        handshake: false,
+                        last_pong: None,
+                    },
+                );
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// The sender is the authority on the slots it serves: it takes the slots it claims
+    /// unless they are served by a node with a greater config epoch, and releases the
+    /// slots it doesn't claim anymore.
+    fn apply_slot_claims(&self, state: &mut ClusterState, sender: &NodeHeader) -> bool {
+        let mut claimed = vec![false; CLUSTER_SLOTS as usize];
+        for &(start, end) in &sender.slots {
+            for slot in start..=end.min(CLUSTER_SLOTS - 1) {
+                claimed[slot as usize] = true;
+            }
+        }
+        let mut changed = false;
+        for (slot, claimed) in claimed.into_iter().enumerate() {
+            let owner = state.slots[slot].as_deref();
+            if claimed {
+                let owner_epoch = owner
+                    .and_then(|id| state.nodes.get(id))
+                    .map(|node| node.config_epoch);
+                let takes = match owner {
+                    Some(id) if id == sender.id => false,
+                    None => true,
+                    Some(_) => owner_epoch.is_none_or(|epoch| epoch < sender.config_epoch),
+                };
+                if takes {
+                    if owner == Some(self.myself.as_str()) {
+                        state.migrating.remove(&(slot as u16));
+                    }
+                    state.slots[slot] = Some(sender.id.clone());
+                    changed = true;
+                }
+            } else if owner == Some(sender.id.as_str()) {
+                state.slots[slot] = None;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn save_or_warn(&self) {
+        if let Err(e) = self.save() {
+            warn!(
+                "failed to save the cluster config to {}: {}",
+                self.config.config_file.display(),
+                e
+            );
+        }
+    }
+
+    /// Write the config file atomically: a crash never leaves a truncated one.
+    fn save(&self) -> io::Result<()> {
+        let mut content = String::new();
+        for line in self.describe_nodes().lines() {
+            // nodes still in handshake are met again by the administrator if needed.
+            if line.split(' ').nth(2) == Some("handshake") {
+                continue;
+            }
+            content.push_str(line);
+            content.push('\n');
+        }
+        let _ = writeln!(
+            content,
+            "vars currentEpoch {} lastVoteEpoch 0",
+            self.read().current_epoch
+        );
+        write_atomically(&self.config.config_file, content.as_bytes())
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, ClusterState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, ClusterState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)
+}
+
+/// Parse a config file written by [`Cluster::save`], returns the id of this node
+/// and the state.
+fn parse_config(content: &str, config: &ClusterConfig) -> io::Result<(String, ClusterState)> {
+    let invalid = |line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid cluster config line: {}", line),
+        )
+    };
+    let mut state = ClusterState {
+        nodes: IndexMap::new(),
+        slots: vec![None; CLUSTER_SLOTS as usize],
+        current_epoch: 0,
+        forgotten: HashMap::new(),
+        migrating: BTreeMap::new(),
+        importing: BTreeMap::new(),
+    };
+    let mut myself = None;
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let fields: Vec<&str> = line.split(' ').collect();
+        if fields[0] == "vars" {
+            for pair in fields[1..].chunks(2) {
+                if let [name, value] = pair {
+                    if *name == "currentEpoch" {
+                        state.current_epoch = value.parse().map_err(|_| invalid(line))?;
+                    }
+                }
+            }
+            continue;
+        }
+        if fields.len() < 8 {
+            return Err(invalid(line));
+        }
+        let (addr, bus_port) = fields[1].split_once('@').ok_or_else(|| invalid(line))?;
+        let mut node = ClusterNode {
+            id: fields[0].to_string(),
+            addr: addr.to_string(),
+            bus_port: bus_port.parse().map_err(|_| invalid(line))?,
+            config_epoch: fields[6].parse().map_err(|_| invalid(line))?,
+            handshake: false,
+            last_pong: None,
+        };
+        if fields[2].split(',').any(|flag| flag == "myself") {
+            // the address may have changed since the file was written.
+            node.addr = config.announce_addr.clone();
+            node.bus_port = config.bus_port;
+            myself = Some(node.id.clone());
+        }
+        for range in &fields[8..] {
+            if let Some(moving) = range.strip_prefix('[').and_then(|r| r.strip_suffix(']')) {
+                let (slot, change) = if let Some((slot, id)) = moving.split_once("->-") {
+                    (slot, SetSlot::Migrating(id.to_string()))
+                } else if let Some((slot, id)) = moving.split_once("-<-") {
+                    (slot, SetSlot::Importing(id.to_string()))
+                } else {
+                    return Err(invalid(line));
+                };
+                let slot: u16 = slot.parse().map_err(|_| invalid(line))?;
+                match change {
+                    SetSlot::Migrating(id) => state.migrating.insert(slot, id),
+                    SetSlot::Importing(id) => state.importing.insert(slot, id),
+                    _ => None,
+                };
+                continue;
+            }
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            let start: u16 = start.parse().map_err(|_| invalid(line))?;
+            let end: u16 = end.parse().map_err(|_| invalid(line))?;
+            if start > end || end >= CLUSTER_SLOTS {
+                return Err(invalid(line));
+            }
+            for slot in start..=end {
+                state.slots[slot as usize] = Some(node.id.clone());
+            }
+        }
+        state.nodes.insert(node.id.clone(), node);
+    }
+    let myself = myself.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the cluster config has no node flagged myself",
+        )
+    })?;
+    Ok((myself, state))
+}
+
+/// The ranges of consecutive slots served by the node.
+fn slot_ranges(slots: &[Option<String>], id: &str) -> Vec<(u16, u16)> {
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for (slot, owner) in slots.iter().enumerate() {
+        if owner.as_deref() != Some(id) {
+            continue;
+        }
+        let slot = slot as u16;
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == slot => *end = slot,
+            _ => ranges.push((slot, slot)),
+        }
+    }
+    ranges
+}
+
+/// 40 random hex characters, like the node ids of Redis.
+fn random_node_id() -> String {
+    let mut id = String::with_capacity(48);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_nanos() as u64);
+    while id.len() < 40 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(nanos);
+        let _ = write!(id, "{:016x}", hasher.finish());
+    }
+    id.truncate(40);
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn config(name: &str) -> ClusterConfig {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("rredis-{}-{}.conf", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        ClusterConfig::new("127.0.0.1:7000", path)
+    }
+
+    fn header(id: &str, epoch: u64, slots: Vec<(u16, u16)>) -> NodeHeader {
+        NodeHeader {
+            id: id.to_string(),
+            addr: "127.0.0.1:7001".to_string(),
+            bus_port: 17001,
+            config_epoch: epoch,
+            current_epoch: epoch,
+            slots,
+        }
+    }
+
+    #[test]
+    fn test_cluster_state_is_saved_and_loaded() -> anyhow::Result<()> {
+        let config = config("saved");
+        let cluster = Cluster::open(config.clone())?;
+        assert_eq!(cluster.myid().len(), 40);
+        cluster.add_slots(&[0, 1, 2, 5])?;
+        assert_eq!(cluster.slot_owner(2).as_deref(), Some(cluster.myid()));
+        assert_eq!(cluster.route(5, false), SlotRoute::Myself);
+        assert_eq!(cluster.route(3, false), SlotRoute::Unassigned);
+
+        let reopened = Cluster::open(config.clone())?;
+        assert_eq!(reopened.myid(), cluster.myid());
+        assert_eq!(
+            reopened.describe_nodes(),
+            format!(
+                "{} 127.0.0.1:7000@17000 myself,master - 0 0 0 connected 0-2 5\n",
+                cluster.myid()
+            )
+        );
+        fs::remove_file(&config.config_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_slots_errors() -> anyhow::Result<()> {
+        let config = config("addslots");
+        let cluster = Cluster::open(config.clone())?;
+        cluster.add_slots(&[1])?;
+        let err = cluster.add_slots(&[2, 1]).unwrap_err();
+        assert_eq!(err.to_string(), "ERR Slot 1 is already busy");
+        let err = cluster.add_slots(&[3, 3]).unwrap_err();
+        assert_eq!(err.to_string(), "ERR Slot 3 specified multiple times");
+        // nothing is assigned when a slot is rejected.
+        assert_eq!(cluster.slot_owner(2), None);
+        fs::remove_file(&config.config_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_handshake_and_slot_claims() -> anyhow::Result<()> {
+        let config = config("handshake");
+        let cluster = Cluster::open(config.clone())?;
+        cluster.meet("127.0.0.1:7001".to_string(), 17001);
+        let pending = cluster.nodes()[1].clone();
+        assert!(pending.handshake);
+
+        let other = "b".repeat(40);
+        let pong = BusMessage {
+            kind: MessageKind::Pong,
+            sender: header(&other, 1, vec![(10, 12)]),
+            gossip: vec![],
+        };
+        cluster.receive(&pong, Some(&pending.id));
+        let nodes = cluster.nodes();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[1].id, other);
+        assert!(!nodes[1].handshake);
+        assert!(nodes[1].last_pong.is_some());
+        assert_eq!(
+            cluster.route(11, false),
+            SlotRoute::Node("127.0.0.1:7001".to_string())
+        );
+
+        // a node which stopped claiming a slot releases it.
+        let pong = BusMessage {
+            kind: MessageKind::Pong,
+            sender: header(&other, 1, vec![(10, 10)]),
+            gossip: vec![],
+        };
+        cluster.receive(&pong, Some(&other));
+        assert_eq!(cluster.slot_owner(11), None);
+        assert_eq!(cluster.slot_owner(10), Some(other.clone()));
+
+        // slots served by this node are only taken by a greater config epoch.
+        cluster.add_slots(&[20])?;
+        let ping = BusMessage {
+            kind: MessageKind::Ping,
+            sender: header(&other, 0, vec![(20, 20)]),
+            gossip: vec![],
+        };
+        cluster.receive(&ping, None);
+        assert_eq!(cluster.route(20, false), SlotRoute::Myself);
+
+        cluster.forget(&other)?;
+        assert_eq!(cluster.nodes().len(), 1);
+        assert_eq!(cluster.slot_owner(10), None);
+        // forgotten nodes aren't added back by gossip.
+        let ping = BusMessage {
+            kind: MessageKind::Meet,
+            sender: header(&other, 1, vec![]),
+            gossip: vec![],
+        };
+        cluster.receive(&ping, None);
+        assert_eq!(cluster.nodes().len(), 1);
+        assert!(cluster.forget(&other).is_err());
+        assert!(cluster.forget(cluster.myid()).is_err());
+        fs::remove_file(&config.config_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_slot_moves_slots() -> anyhow::Result<()> {
+        let config = config("setslot");
+        let cluster = Cluster::open(config.clone())?;
+        let other = "e".repeat(40);
+        let pong = BusMessage {
+            kind: MessageKind::Meet,
+            sender: header(&other, 0, vec![(1, 1)]),
+            gossip: vec![],
+        };
+        cluster.receive(&pong, None);
+        cluster.add_slots(&[0])?;
+
+        let err = cluster
+            .set_slot(1, SetSlot::Migrating(other.clone()))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "ERR I'm not the owner of hash slot 1");
+        let err = cluster
+            .set_slot(0, SetSlot::Importing(other.clone()))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "ERR I'm already the owner of hash slot 0");
+        assert!(cluster
+            .set_slot(0, SetSlot::Migrating("f".repeat(40)))
+            .is_err());
+
+        cluster.set_slot(0, SetSlot::Migrating(other.clone()))?;
+        cluster.set_slot(1, SetSlot::Importing(other.clone()))?;
+        assert_eq!(
+            cluster.route(0, false),
+            SlotRoute::Migrating("127.0.0.1:7001".to_string())
+        );
+        assert_eq!(
+            cluster.route(1, false),
+            SlotRoute::Node("127.0.0.1:7001".to_string())
+        );
+        assert_eq!(cluster.route(1, true), SlotRoute::Myself);
+        // the moves survive a restart.
+        let reopened = Cluster::open(config.clone())?;
+        assert_eq!(reopened.describe_nodes(), cluster.describe_nodes());
+        assert!(cluster
+            .describe_nodes()
+            .contains(&format!(" [0->-{}] [1-<-{}]", other, other)));
+
+        // taking the imported slot bumps the epoch, so that the claim wins.
+        cluster.set_slot(1, SetSlot::Node(cluster.myid().to_string()))?;
+        assert_eq!(cluster.route(1, false), SlotRoute::Myself);
+        assert_eq!(cluster.message(MessageKind::Ping).sender.config_epoch, 1);
+        cluster.set_slot(0, SetSlot::Stable)?;
+        assert_eq!(cluster.route(0, false), SlotRoute::Myself);
+        fs::remove_file(&config.config_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_senders_only_join_with_meet() -> anyhow::Result<()> {
+        let config = config("meet");
+        let cluster = Cluster::open(config.clone())?;
+        let ping = BusMessage {
+            kind: MessageKind::Ping,
+            sender: header(&"c".repeat(40), 0, vec![]),
+            gossip: vec![],
+        };
+        cluster.receive(&ping, None);
+        assert_eq!(cluster.nodes().len(), 1);
+
+        let meet = BusMessage {
+            kind: MessageKind::Meet,
+            sender: header(&"c".repeat(40), 0, vec![]),
+            gossip: vec![GossipEntry {
+                id: "d".repeat(40),
+                addr: "127.0.0.1:7002".to_string(),
+                bus_port: 17002,
+            }],
+        };
+        cluster.receive(&meet, None);
+        assert_eq!(cluster.nodes().len(), 3);
+        fs::remove_file(&config.config_file)?;
+        Ok(())
+    }
+}
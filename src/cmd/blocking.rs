@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use crate::{lookup_command, RespArray, RespFrame};
+
+use super::extract_timeout;
+
+/// A command flagged `blocking`, like BLMPOP, which waits for one of its keys to be written
+/// when it finds none of them ready. The command itself never waits: it replies null,
+/// and the connection runs it again after each write to one of its keys, until it replies
+/// something else or its timeout elapses. Inside a transaction, it replies at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockingCommand {
+    pub keys: Vec<String>,
+    /// How long to wait, `None` waits forever.
+    pub timeout: Option<Duration>,
+}
+
+impl BlockingCommand {
+    /// The keys and the timeout, the first argument, of a blocking command. `None` for
+    /// the other commands and for invalid timeouts, which the command replies to.
+    pub fn parse(frame: &RespFrame) -> Option<Self> {
+        let RespFrame::Array(RespArray(Some(args))) = frame else {
+            return None;
+        };
+        let spec = match args.first() {
+            Some(RespFrame::BulkString(name)) => lookup_command(name.as_ref())?,
+            _ => return None,
+        };
+        if !spec.is_blocking() {
+            return None;
+        }
+        let timeout = extract_timeout(args.get(1)?.clone()).ok()?;
+        Some(Self {
+            keys: spec.keys(args),
+            timeout,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::array;
+
+    #[test]
+    fn test_parse_blocking_command() {
+        let parse = |args: &[&str]| BlockingCommand::parse(&array(args).into());
+        assert_eq!(
+            parse(&["BLMPOP", "1.5", "2", "a", "b", "LEFT"]),
+            Some(BlockingCommand {
+                keys: vec!["a".to_string(), "b".to_string()],
+                timeout: Some(Duration::from_millis(1500)),
+            })
+        );
+        assert_eq!(
+            parse(&["bzmpop", "0", "1", "z", "MIN"]).map(|b| b.timeout),
+            Some(None)
+        );
+        // the command replies the error of an invalid timeout.
+        assert_eq!(parse(&["blmpop", "-1", "1", "a", "LEFT"]), None);
+        assert_eq!(parse(&["lmpop", "1", "a", "LEFT"]), None);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{Backend, BloomFilter, BulkString, RespArray, RespFrame, DEFAULT_EXPANSION};
+
+use super::{
+    extract_args, extract_integer, extract_string, BfAdd, BfExists, BfLoadChunk, BfReserve,
+    BfScanDump, CommandError, CommandExecutor, RESP_OK,
+};
+
+impl CommandExecutor for BfReserve {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let filter = BloomFilter::new(self.error_rate, self.capacity, self.expansion);
+        match backend.bf_reserve(&self.key, filter) {
+            Ok(true) => RESP_OK.clone(),
+            Ok(false) => CommandError::InvalidArgument("item exists".to_string()).into(),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for BfAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let added = match backend.bf_add(&self.key, &self.items) {
+            Ok(added) => added,
+            Err(e) => return CommandError::from(e).into(),
+        };
+        let mut res = added
+            .into_iter()
+            .map(|added| match added {
+                Ok(added) => RespFrame::Integer(added as i64),
+                Err(e) => CommandError::InvalidArgument(e.to_string()).into(),
+            })
+            .collect::<Vec<_>>();
+        if self.multi {
+            RespArray::new(res).into()
+        } else {
+            res.remove(0)
+        }
+    }
+}
+
+impl CommandExecutor for BfExists {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let exists = backend.bf_exists(&self.key, std::slice::from_ref(&self.item));
+        RespFrame::Integer(exists[0] as i64)
+    }
+}
+
+impl CommandExecutor for BfScanDump {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.bloom.contains_key(&self.key) {
+            return CommandError::InvalidArgument("not found".to_string()).into();
+        }
+        // the iterator 0 and an empty chunk once done, like RedisBloom.
+        let (iterator, data) = backend
+            .bf_scandump(&self.key, self.iterator)
+            .unwrap_or_default();
+        RespArray::new(vec![
+            RespFrame::Integer(iterator as i64),
+            BulkString::new(data).into(),
+        ])
+        .into()
+    }
+}
+
+impl CommandExecutor for BfLoadChunk {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.bf_loadchunk(&self.key, self.iterator, &self.data) {
+            Ok(Ok(())) => RESP_OK.clone(),
+            Ok(Err(e)) => CommandError::InvalidArgument(e.to_string()).into(),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BfReserve {
+    type Error = CommandError;
+
+    // bf.reserve key error_rate capacity [EXPANSION expansion] [NONSCALING]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if !(4..=7).contains(&value.len()) {
+            return Err(CommandError::WrongArity("bf.reserve".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let error_rate: f64 = extract_string(args.next().unwrap())?
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument("bad error rate".to_string()))?;
+        if !(error_rate > 0.0 && error_rate < 1.0) {
+            return Err(CommandError::InvalidArgument(
+                "(0 < error rate range < 1)".to_string(),
+            ));
+        }
+        let capacity: i64 = extract_string(args.next().unwrap())?
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument("bad capacity".to_string()))?;
+        if capacity <= 0 {
+            return Err(CommandError::InvalidArgument(
+                "(capacity should be larger than 0)".to_string(),
+            ));
+        }
+
+        let mut expansion = None;
+        let mut nonscaling = false;
+        while let Some(arg) = args.next() {
+            let arg = extract_string(arg)?;
+            if arg.eq_ignore_ascii_case("nonscaling") {
+                nonscaling = true;
+            } else if arg.eq_ignore_ascii_case("expansion") {
+                let n: i64 = args
+                    .next()
+                    .map(extract_string)
+                    .transpose()?
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| CommandError::InvalidArgument("bad expansion".to_string()))?;
+                if !(1..=u32::MAX as i64).contains(&n) {
+                    return Err(CommandError::InvalidArgument(
+                        "expansion should be greater or equal to 1".to_string(),
+                    ));
+                }
+                expansion = Some(n as u32);
+            } else {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+        }
+        if nonscaling && expansion.is_some() {
+            return Err(CommandError::InvalidArgument(
+                "Nonscaling filters cannot expand".to_string(),
+            ));
+        }
+        Ok(BfReserve {
+            key,
+            error_rate,
+            capacity: capacity as u64,
+            expansion: if nonscaling {
+                None
+            } else {
+                Some(expansion.unwrap_or(DEFAULT_EXPANSION))
+            },
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BfAdd {
+    type Error = CommandError;
+
+    // bf.add key item
+    // bf.madd key item [item ...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let multi = matches!(value.first(), Some(RespFrame::BulkString(c))
+            if c.as_ref().eq_ignore_ascii_case(b"bf.madd"));
+        let arity_ok = if multi {
+            value.len() >= 3
+        } else {
+            value.len() == 3
+        };
+        if !arity_ok {
+            let name = if multi { "bf.madd" } else { "bf.add" };
+            return Err(CommandError::WrongArity(name.to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let items = args.map(extract_bytes).collect::<Result<_, _>>()?;
+        Ok(BfAdd { key, items, multi })
+    }
+}
+
+impl TryFrom<RespArray> for BfExists {
+    type Error = CommandError;
+
+    // bf.exists key item
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() != 3 {
+            return Err(CommandError::WrongArity("bf.exists".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(BfExists {
+            key: extract_string(args.next().unwrap())?,
+            item: extract_bytes(args.next().unwrap())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BfScanDump {
+    type Error = CommandError;
+
+    // bf.scandump key iterator
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() != 3 {
+            return Err(CommandError::WrongArity("bf.scandump".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(BfScanDump {
+            key: extract_string(args.next().unwrap())?,
+            iterator: extract_iterator(args.next().unwrap())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BfLoadChunk {
+    type Error = CommandError;
+
+    // bf.loadchunk key iterator data
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() != 4 {
+            return Err(CommandError::WrongArity("bf.loadchunk".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(BfLoadChunk {
+            key: extract_string(args.next().unwrap())?,
+            iterator: extract_iterator(args.next().unwrap())?,
+            data: extract_bytes(args.next().unwrap())?,
+        })
+    }
+}
+
+fn extract_iterator(frame: RespFrame) -> Result<u64, CommandError> {
+    u64::try_from(extract_integer(frame)?)
+        .map_err(|_| CommandError::InvalidArgument("invalid iterator".to_string()))
+}
+
+/// Items are arbitrary binary data, unlike keys.
+fn extract_bytes(frame: RespFrame) -> Result<Vec<u8>, CommandError> {
+    match frame {
+        RespFrame::BulkString(s) if s.0.is_some() => Ok(s.0.unwrap().into()),
+        _ => Err(CommandError::InvalidArgument(
+            "Argument must be a bulk string".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cmd::array, SimpleError};
+
+    use super::*;
+
+    #[test]
+    fn test_bf_reserve_from_resp_array() -> anyhow::Result<()> {
+        let reserve = BfReserve::try_from(array(&["bf.reserve", "bf", "0.001", "1000"]))?;
+        assert_eq!(reserve.capacity, 1000);
+        assert_eq!(reserve.expansion, Some(DEFAULT_EXPANSION));
+
+        let reserve = BfReserve::try_from(array(&["BF.RESERVE", "bf", "0.1", "10", "NONSCALING"]))?;
+        assert_eq!(reserve.expansion, None);
+
+        for (args, err) in [
+            (
+                &["bf.reserve", "bf", "1", "10"][..],
+                "ERR (0 < error rate range < 1)",
+            ),
+            (&["bf.reserve", "bf", "x", "10"], "ERR bad error rate"),
+            (
+                &["bf.reserve", "bf", "0.1", "0"],
+                "ERR (capacity should be larger than 0)",
+            ),
+            (
+                &["bf.reserve", "bf", "0.1", "10", "expansion", "0"],
+                "ERR expansion should be greater or equal to 1",
+            ),
+            (
+                &[
+                    "bf.reserve",
+                    "bf",
+                    "0.1",
+                    "10",
+                    "expansion",
+                    "2",
+                    "nonscaling",
+                ],
+                "ERR Nonscaling filters cannot expand",
+            ),
+        ] {
+            let res = BfReserve::try_from(array(args));
+            assert_eq!(res.unwrap_err().to_string(), err);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bf_commands() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let run = |args: &[&str]| -> anyhow::Result<RespFrame> {
+            Ok(match args[0] {
+                "bf.reserve" => BfReserve::try_from(array(args))?.execute(&backend),
+                "bf.exists" => BfExists::try_from(array(args))?.execute(&backend),
+                _ => BfAdd::try_from(array(args))?.execute(&backend),
+            })
+        };
+
+        assert_eq!(
+            run(&["bf.reserve", "bf", "0.01", "2", "nonscaling"])?,
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            run(&["bf.reserve", "bf", "0.01", "2"])?,
+            SimpleError::new("ERR item exists").into()
+        );
+        assert_eq!(run(&["bf.add", "bf", "a"])?, RespFrame::Integer(1));
+        assert_eq!(run(&["bf.add", "bf", "a"])?, RespFrame::Integer(0));
+        assert_eq!(run(&["bf.exists", "bf", "a"])?, RespFrame::Integer(1));
+        assert_eq!(run(&["bf.exists", "bf", "b"])?, RespFrame::Integer(0));
+        assert_eq!(
+            run(&["bf.madd", "bf", "a", "b", "c"])?,
+            RespArray::new(vec![
+                RespFrame::Integer(0),
+                RespFrame::Integer(1),
+                SimpleError::new("ERR non scaling filter is full").into(),
+            ])
+            .into()
+        );
+        assert!(run(&["bf.add", "bf", "a", "b"]).is_err());
+        Ok(())
+    }
+}
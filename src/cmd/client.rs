@@ -0,0 +1,230 @@
+use crate::{RespArray, RespFrame};
+
+use super::{
+    err::CommandError, extract_args, extract_string, ClientTracking, ConnectionCommand, Hello,
+};
+
+/// Parse the subcommands of CLIENT.
+pub(crate) fn parse_client_command(value: RespArray) -> Result<ConnectionCommand, CommandError> {
+    if value.len() < 2 {
+        return Err(CommandError::WrongArity("client".to_string()));
+    }
+    match value[1] {
+        RespFrame::BulkString(ref sub) if sub.as_ref().eq_ignore_ascii_case(b"tracking") => {
+            Ok(ConnectionCommand::ClientTracking(value.try_into()?))
+        }
+        RespFrame::BulkString(ref sub) if sub.as_ref().eq_ignore_ascii_case(b"info") => {
+            if value.len() != 2 {
+                return Err(CommandError::WrongArity("client|info".to_string()));
+            }
+            Ok(ConnectionCommand::ClientInfo)
+        }
+        RespFrame::BulkString(ref sub) if sub.as_ref().eq_ignore_ascii_case(b"attributes") => {
+            parse_switch(value, "attributes").map(ConnectionCommand::ClientAttributes)
+        }
+        RespFrame::BulkString(ref sub) if sub.as_ref().eq_ignore_ascii_case(b"trace") => {
+            parse_switch(value, "trace").map(ConnectionCommand::ClientTrace)
+        }
+        RespFrame::BulkString(ref sub) => Err(CommandError::InvalidArgument(format!(
+            "unknown subcommand '{}'",
+            String::from_utf8_lossy(sub.as_ref())
+        ))),
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid arguments for client".to_string(),
+        )),
+    }
+}
+
+// client <subcommand> <on|off>
+fn parse_switch(value: RespArray, subcommand: &str) -> Result<bool, CommandError> {
+    if value.len() != 3 {
+        return Err(CommandError::WrongArity(format!("client|{}", subcommand)));
+    }
+    let mut args = extract_args(value, 2)?.into_iter();
+    match extract_string(args.next().unwrap())?
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+impl TryFrom<RespArray> for ClientTracking {
+    type Error = CommandError;
+
+    // client tracking <on|off> [BCAST] [PREFIX prefix [PREFIX prefix ...]]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 3 {
+            return Err(CommandError::WrongArity("client|tracking".to_string()));
+        }
+        let mut args = extract_args(value, 2)?.into_iter();
+        let on = match extract_string(args.next().unwrap())?
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "on" => true,
+            "off" => false,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+
+        let mut tracking = ClientTracking {
+            on,
+            bcast: false,
+            prefixes: Vec::new(),
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(arg)?.to_ascii_lowercase().as_str() {
+                "bcast" => tracking.bcast = true,
+                "prefix" => match args.next() {
+                    Some(prefix) => tracking.prefixes.push(extract_string(prefix)?),
+                    None => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                },
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        if !tracking.bcast && !tracking.prefixes.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "PREFIX option requires BCAST mode to be enabled".to_string(),
+            ));
+        }
+        Ok(tracking)
+    }
+}
+
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+
+    // hello [protover [SETNAME clientname]]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let mut hello = Hello {
+            protover: None,
+            setname: None,
+        };
+        let Some(protover) = args.next() else {
+            return Ok(hello);
+        };
+        hello.protover = match extract_string(protover)?.parse::<i64>() {
+            Ok(protover @ (2 | 3)) => Some(protover as u8),
+            Ok(_) => return Err(CommandError::NoProto),
+            Err(_) => {
+                return Err(CommandError::InvalidArgument(
+                    "Protocol version is not an integer or out of range".to_string(),
+                ))
+            }
+        };
+        while let Some(arg) = args.next() {
+            let option = extract_string(arg)?;
+            match (option.to_ascii_lowercase().as_str(), args.next()) {
+                ("setname", Some(name)) => hello.setname = Some(extract_string(name)?),
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Syntax error in HELLO option '{}'",
+                        option
+                    )))
+                }
+            }
+        }
+        Ok(hello)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BulkString;
+
+    use super::*;
+
+    fn client(args: &[&str]) -> Result<ConnectionCommand, CommandError> {
+        let mut frames = vec![BulkString::new("client").into()];
+        frames.extend(args.iter().map(|a| BulkString::new(*a).into()));
+        parse_client_command(RespArray::new(frames))
+    }
+
+    #[test]
+    fn test_client_tracking_from_resp_array() -> anyhow::Result<()> {
+        match client(&["TRACKING", "on"])? {
+            ConnectionCommand::ClientTracking(t) => {
+                assert!(t.on);
+                assert!(!t.bcast);
+            }
+            cmd => panic!("unexpected command: {:?}", cmd),
+        }
+
+        match client(&["tracking", "ON", "BCAST", "PREFIX", "a:", "prefix", "b:"])? {
+            ConnectionCommand::ClientTracking(t) => {
+                assert!(t.bcast);
+                assert_eq!(t.prefixes, vec!["a:", "b:"]);
+            }
+            cmd => panic!("unexpected command: {:?}", cmd),
+        }
+
+        match client(&["tracking", "off"])? {
+            ConnectionCommand::ClientTracking(t) => assert!(!t.on),
+            cmd => panic!("unexpected command: {:?}", cmd),
+        }
+
+        assert!(client(&["tracking"]).is_err());
+        assert!(client(&["tracking", "maybe"]).is_err());
+        assert!(client(&["tracking", "on", "prefix", "a:"]).is_err());
+        assert!(client(&["unknown"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_info_from_resp_array() {
+        assert!(matches!(
+            client(&["INFO"]),
+            Ok(ConnectionCommand::ClientInfo)
+        ));
+        assert!(client(&["info", "extra"]).is_err());
+    }
+
+    #[test]
+    fn test_client_attributes_from_resp_array() {
+        assert!(matches!(
+            client(&["ATTRIBUTES", "on"]),
+            Ok(ConnectionCommand::ClientAttributes(true))
+        ));
+        assert!(matches!(
+            client(&["attributes", "OFF"]),
+            Ok(ConnectionCommand::ClientAttributes(false))
+        ));
+        assert!(client(&["attributes"]).is_err());
+        assert!(client(&["attributes", "maybe"]).is_err());
+    }
+
+    #[test]
+    fn test_client_trace_from_resp_array() {
+        assert!(matches!(
+            client(&["TRACE", "on"]),
+            Ok(ConnectionCommand::ClientTrace(true))
+        ));
+        assert!(matches!(
+            client(&["trace", "off"]),
+            Ok(ConnectionCommand::ClientTrace(false))
+        ));
+        assert!(client(&["trace"]).is_err());
+        assert!(client(&["trace", "on", "extra"]).is_err());
+    }
+
+    #[test]
+    fn test_hello_from_resp_array() -> anyhow::Result<()> {
+        let hello = |args: &[&str]| {
+            let mut frames = vec![BulkString::new("hello").into()];
+            frames.extend(args.iter().map(|a| BulkString::new(*a).into()));
+            Hello::try_from(RespArray::new(frames))
+        };
+        assert_eq!(hello(&[])?.protover, None);
+        let cmd = hello(&["3", "SETNAME", "app"])?;
+        assert_eq!(cmd.protover, Some(3));
+        assert_eq!(cmd.setname.as_deref(), Some("app"));
+        assert!(matches!(hello(&["4"]), Err(CommandError::NoProto)));
+        assert!(hello(&["three"]).is_err());
+        assert!(hello(&["2", "SETNAME"]).is_err());
+        assert!(hello(&["2", "AUTH", "user", "pass"]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    cluster::SetSlot, key_slot, Backend, BulkString, RespArray, RespFrame, SimpleError,
+    CLUSTER_SLOTS,
+};
+
+use super::{
+    err::CommandError, extract_args, extract_string, ClusterCommand, CommandExecutor, RESP_OK,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ClusterSubcommand {
+    MyId,
+    Nodes,
+    Info,
+    KeySlot(String),
+    /// Add the node to the cluster, its bus port defaults to its port + 10000.
+    Meet {
+        addr: String,
+        bus_port: u16,
+    },
+    Forget(String),
+    AddSlots(Vec<u16>),
+    SetSlot(u16, SetSlot),
+    CountKeysInSlot(u16),
+    GetKeysInSlot(u16, usize),
+}
+
+impl CommandExecutor for ClusterCommand {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let Some(cluster) = backend.cluster() else {
+            return SimpleError::new("ERR This instance has cluster support disabled").into();
+        };
+        let res = match self.subcommand {
+            ClusterSubcommand::MyId => Ok(BulkString::new(cluster.myid()).into()),
+            ClusterSubcommand::Nodes => Ok(BulkString::new(cluster.describe_nodes()).into()),
+            ClusterSubcommand::Info => Ok(BulkString::new(cluster.info()).into()),
+            ClusterSubcommand::KeySlot(key) => {
+                Ok(RespFrame::Integer(key_slot(key.as_bytes()) as i64))
+            }
+            ClusterSubcommand::Meet { addr, bus_port } => {
+                cluster.meet(addr, bus_port);
+                Ok(RESP_OK.clone())
+            }
+            ClusterSubcommand::Forget(id) => cluster.forget(&id).map(|_| RESP_OK.clone()),
+            ClusterSubcommand::AddSlots(slots) => {
+                cluster.add_slots(&slots).map(|_| RESP_OK.clone())
+            }
+            ClusterSubcommand::SetSlot(slot, change) => {
+                let gives_away = matches!(&change, SetSlot::Node(id) if id != cluster.myid())
+                    && cluster.slot_owner(slot).as_deref() == Some(cluster.myid());
+                if gives_away && keys_in_slot(backend, slot).next().is_some() {
+                    Err(CommandError::InvalidArgument(format!(
+                        "Can't assign hashslot {} to a different node while I still hold keys for this hash slot.",
+                        slot
+                    )))
+                } else {
+                    cluster.set_slot(slot, change).map(|_| RESP_OK.clone())
+                }
+            }
+            ClusterSubcommand::CountKeysInSlot(slot) => Ok(RespFrame::Integer(
+                keys_in_slot(backend, slot).count() as i64,
+            )),
+            ClusterSubcommand::GetKeysInSlot(slot, count) => {
+                let keys: Vec<RespFrame> = keys_in_slot(backend, slot)
+                    .take(count)
+                    .map(|key| BulkString::new(key).into())
+                    .collect();
+                Ok(RespArray::new(keys).into())
+            }
+        };
+        res.unwrap_or_else(RespFrame::from)
+    }
+}
+
+/// The keys of the slot. Keys aren't indexed by slot, the whole keyspace is walked.
+fn keys_in_slot(backend: &Backend, slot: u16) -> impl Iterator<Item = String> + '_ {
+    backend
+        .keys_iter()
+        .filter(move |key| key_slot(key.as_bytes()) == slot)
+}
+
+fn parse_slot(slot: &str) -> Result<u16, CommandError> {
+    slot.parse::<u16>()
+        .ok()
+        .filter(|slot| *slot < CLUSTER_SLOTS)
+        .ok_or_else(|| CommandError::InvalidArgument("Invalid or out of range slot".to_string()))
+}
+
+impl TryFrom<RespArray> for ClusterCommand {
+    type Error = CommandError;
+
+    // cluster myid | cluster nodes | cluster info | cluster keyslot key
+    // | cluster meet ip port [cluster-bus-port] | cluster forget node-id
+    // | cluster addslots slot [slot ...]
+    // | cluster setslot slot (IMPORTING node-id | MIGRATING node-id | NODE node-id | STABLE)
+    // | cluster countkeysinslot slot | cluster getkeysinslot slot count
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(arg) => extract_string(arg)?,
+            None => return Err(CommandError::WrongArity("cluster".to_string())),
+        };
+        let name = subcommand.to_ascii_lowercase();
+        let args = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        let arity = || CommandError::WrongArity(format!("cluster|{}", name));
+        let subcommand = match (name.as_str(), args.as_slice()) {
+            ("myid", []) => ClusterSubcommand::MyId,
+            ("nodes", []) => ClusterSubcommand::Nodes,
+            ("info", []) => ClusterSubcommand::Info,
+            ("keyslot", [key]) => ClusterSubcommand::KeySlot(key.clone()),
+            ("meet", [ip, port]) | ("meet", [ip, port, _]) => {
+                let invalid = || {
+                    CommandError::InvalidArgument(format!(
+                        "Invalid node address specified: {}:{}",
+                        ip, port
+                    ))
+                };
+                let port: u16 = port.parse().map_err(|_| invalid())?;
+                let bus_port = match args.get(2) {
+                    Some(bus_port) => bus_port.parse().map_err(|_| invalid())?,
+                    None => port.checked_add(10000).ok_or_else(invalid)?,
+                };
+                ClusterSubcommand::Meet {
+                    addr: format!("{}:{}", ip, port),
+                    bus_port,
+                }
+            }
+            ("forget", [id]) => ClusterSubcommand::Forget(id.clone()),
+            ("addslots", slots) if !slots.is_empty() => ClusterSubcommand::AddSlots(
+                slots
+                    .iter()
+                    .map(|slot| parse_slot(slot))
+                    .collect::<Result<_, _>>()?,
+            ),
+            ("setslot", [slot, state, rest @ ..]) => {
+                let slot = parse_slot(slot)?;
+                let change = match (state.to_ascii_lowercase().as_str(), rest) {
+                    ("migrating", [id]) => SetSlot::Migrating(id.clone()),
+                    ("importing", [id]) => SetSlot::Importing(id.clone()),
+                    ("node", [id]) => SetSlot::Node(id.clone()),
+                    ("stable", []) => SetSlot::Stable,
+                    _ => return Err(CommandError::InvalidArgument(
+                        "Invalid CLUSTER SETSLOT action or number of arguments. Try CLUSTER HELP"
+                            .to_string(),
+                    )),
+                };
+                ClusterSubcommand::SetSlot(slot, change)
+            }
+            ("countkeysinslot", [slot]) => ClusterSubcommand::CountKeysInSlot(parse_slot(slot)?),
+            ("getkeysinslot", [slot, count]) => {
+                let count = count.parse().map_err(|_| {
+                    CommandError::InvalidArgument("Invalid number of keys".to_string())
+                })?;
+                ClusterSubcommand::GetKeysInSlot(parse_slot(slot)?, count)
+            }
+            (
+                "myid" | "nodes" | "info" | "keyslot" | "meet" | "forget" | "addslots" | "setslot"
+                | "countkeysinslot" | "getkeysinslot",
+                _,
+            ) => return Err(arity()),
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'",
+                    subcommand
+                )))
+            }
+        };
+        Ok(ClusterCommand { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::array;
+
+    fn cluster(args: &[&str]) -> Result<ClusterCommand, CommandError> {
+        ClusterCommand::try_from(array(args))
+    }
+
+    #[test]
+    fn test_cluster_from_resp_array() -> anyhow::Result<()> {
+        assert_eq!(
+            cluster(&["cluster", "MYID"])?.subcommand,
+            ClusterSubcommand::MyId
+        );
+        assert_eq!(
+            cluster(&["cluster", "meet", "127.0.0.1", "7001"])?.subcommand,
+            ClusterSubcommand::Meet {
+                addr: "127.0.0.1:7001".to_string(),
+                bus_port: 17001
+            }
+        );
+        assert_eq!(
+            cluster(&["cluster", "meet", "127.0.0.1", "7001", "8001"])?.subcommand,
+            ClusterSubcommand::Meet {
+                addr: "127.0.0.1:7001".to_string(),
+                bus_port: 8001
+            }
+        );
+        assert_eq!(
+            cluster(&["cluster", "addslots", "1", "16383"])?.subcommand,
+            ClusterSubcommand::AddSlots(vec![1, 16383])
+        );
+        assert_eq!(
+            cluster(&["cluster", "setslot", "7", "MIGRATING", "id"])?.subcommand,
+            ClusterSubcommand::SetSlot(7, SetSlot::Migrating("id".to_string()))
+        );
+        assert_eq!(
+            cluster(&["cluster", "setslot", "7", "stable"])?.subcommand,
+            ClusterSubcommand::SetSlot(7, SetSlot::Stable)
+        );
+        assert!(cluster(&["cluster", "setslot", "7", "stable", "id"]).is_err());
+        assert!(cluster(&["cluster", "setslot", "7", "node"]).is_err());
+        assert!(cluster(&["cluster", "getkeysinslot", "7", "-1"]).is_err());
+        assert!(cluster(&["cluster", "addslots"]).is_err());
+        assert!(cluster(&["cluster", "addslots", "16384"]).is_err());
+        assert!(cluster(&["cluster", "meet", "127.0.0.1", "port"]).is_err());
+        assert!(cluster(&["cluster", "myid", "extra"]).is_err());
+        assert!(cluster(&["cluster", "failover"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cluster_disabled() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        assert_eq!(
+            cluster(&["cluster", "myid"])?.execute(&backend),
+            SimpleError::new("ERR This instance has cluster support disabled").into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,607 @@
+use crate::{Backend, BulkString, RespArray, RespFrame, RespNull, SimpleString};
+
+use super::{
+    err::CommandError,
+    extract_args, extract_string,
+    registry::{lookup_command, CommandSpec, SubcommandSpec, COMMAND_TABLE},
+    CommandCommand, CommandExecutor,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommandSubcommand {
+    Count,
+    List,
+    /// Details of the named commands, all of them if none is named.
+    Info(Vec<String>),
+    /// Documentation of the named commands, all of them if none is named.
+    Docs(Vec<String>),
+}
+
+impl CommandExecutor for CommandCommand {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        match self.subcommand {
+            CommandSubcommand::Count => RespFrame::Integer(COMMAND_TABLE.len() as i64),
+            CommandSubcommand::List => array(
+                COMMAND_TABLE
+                    .iter()
+                    .map(|spec| BulkString::new(spec.name).into()),
+            ),
+            CommandSubcommand::Info(names) if names.is_empty() => {
+                array(COMMAND_TABLE.iter().map(info_frame))
+            }
+            CommandSubcommand::Info(names) => array(names.iter().map(|name| {
+                lookup_command(name.as_bytes())
+                    .map(info_frame)
+                    .unwrap_or(RespFrame::Null(RespNull))
+            })),
+            CommandSubcommand::Docs(names) => {
+                let specs: Vec<&CommandSpec> = if names.is_empty() {
+                    COMMAND_TABLE.iter().collect()
+                } else {
+                    // unknown commands are left out.
+                    names
+                        .iter()
+                        .filter_map(|name| lookup_command(name.as_bytes()))
+                        .collect()
+                };
+                array(
+                    specs
+                        .into_iter()
+                        .flat_map(|spec| [bulk(spec.name), docs_frame(spec)]),
+                )
+            }
+        }
+    }
+}
+
+fn array(frames: impl IntoIterator<Item = RespFrame>) -> RespFrame {
+    RespArray::new(frames.into_iter().collect::<Vec<_>>()).into()
+}
+
+fn bulk(s: impl Into<String>) -> RespFrame {
+    BulkString::new(s.into()).into()
+}
+
+/// A command like COMMAND INFO replies it: its name, arity, flags, key positions,
+/// ACL categories, tips, key specs and subcommands.
+fn info_frame(spec: &CommandSpec) -> RespFrame {
+    let subcommands = spec.subcommands.iter().map(|sub| {
+        let name = format!("{}|{}", spec.name, sub.name);
+        info_entry(name, subcommand_arity(sub), spec, array([]))
+    });
+    info_entry(spec.name.to_string(), spec.arity, spec, array(subcommands))
+}
+
+fn info_entry(name: String, arity: i64, spec: &CommandSpec, subcommands: RespFrame) -> RespFrame {
+    let flags = spec
+        .flags
+        .names()
+        .into_iter()
+        .chain(spec.movable_keys.map(|_| "movablekeys"))
+        .map(|flag| SimpleString::new(flag).into());
+    array([
+        bulk(name),
+        RespFrame::Integer(arity),
+        array(flags),
+        RespFrame::Integer(spec.first_key),
+        RespFrame::Integer(spec.last_key),
+        RespFrame::Integer(spec.key_step),
+        array([]),
+        array([]),
+        array([]),
+        subcommands,
+    ])
+}
+
+/// The arity of a subcommand, deduced from its synopsis.
+fn subcommand_arity(sub: &SubcommandSpec) -> i64 {
+    let args = parse_synopsis(sub.args);
+    let required = args.iter().filter(|arg| !arg.optional).count() as i64 + 2;
+    if args.iter().any(|arg| arg.optional || arg.multiple) {
+        -required
+    } else {
+        required
+    }
+}
+
+/// A command like COMMAND DOCS replies it, in the RESP2 flavor: a flat array of
+/// field names and values, rather than a map.
+fn docs_frame(spec: &CommandSpec) -> RespFrame {
+    let docs = &spec.docs;
+    let mut fields = vec![
+        bulk("summary"),
+        bulk(docs.summary),
+        bulk("since"),
+        bulk(docs.since),
+        bulk("group"),
+        bulk(docs.group),
+    ];
+    let args = parse_synopsis(docs.args);
+    if !args.is_empty() {
+        fields.extend([bulk("arguments"), array(args.iter().map(Arg::frame))]);
+    }
+    if spec.is_container() {
+        let subcommands = spec.subcommands.iter().flat_map(|sub| {
+            let mut fields = vec![
+                bulk("summary"),
+                bulk(sub.summary),
+                bulk("since"),
+                bulk(docs.since),
+                bulk("group"),
+                bulk(docs.group),
+            ];
+            let args = parse_synopsis(sub.args);
+            if !args.is_empty() {
+                fields.extend([bulk("arguments"), array(args.iter().map(Arg::frame))]);
+            }
+            [bulk(format!("{}|{}", spec.name, sub.name)), array(fields)]
+        });
+        fields.extend([bulk("subcommands"), array(subcommands)]);
+    }
+    array(fields)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArgType {
+    Key,
+    String,
+    Integer,
+    Double,
+    PureToken,
+    OneOf,
+    Block,
+}
+
+impl ArgType {
+    /// The type of a named argument, guessed from its name.
+    fn of(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "key" | "newkey" | "source" | "destination" | "sourcekey" | "destkey" => Self::Key,
+            "count" | "numkeys" | "numfields" | "seconds" | "milliseconds" | "index" | "start"
+            | "stop" | "offset" | "capacity" | "expansion" | "timeout" | "port"
+            | "destination-db" | "increment" | "ttl" | "cursor" | "limit" | "retentionperiod"
+            | "bucketduration" | "timestamp" | "fromtimestamp" | "totimestamp" | "slot"
+            | "bus-port" => Self::Integer,
+            "score" | "min" | "max" | "error_rate" => Self::Double,
+            _ => Self::String,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Key => "key",
+            Self::String => "string",
+            Self::Integer => "integer",
+            Self::Double => "double",
+            Self::PureToken => "pure-token",
+            Self::OneOf => "oneof",
+            Self::Block => "block",
+        }
+    }
+}
+
+/// An argument of a command, as COMMAND DOCS details it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Arg {
+    name: String,
+    kind: ArgType,
+    /// The literal preceding the value, e.g. `LIMIT`, or the literal itself for a pure token.
+    token: Option<String>,
+    optional: bool,
+    multiple: bool,
+    /// The alternatives of a oneof, the parts of a block.
+    args: Vec<Arg>,
+}
+
+impl Arg {
+    fn named(name: &str, token: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: ArgType::of(name),
+            token,
+            optional: false,
+            multiple: false,
+            args: vec![],
+        }
+    }
+
+    fn token(token: &str) -> Self {
+        let name = match token {
+            "\"\"" => "empty-string".to_string(),
+            _ => token.to_ascii_lowercase(),
+        };
+        Self {
+            name,
+            kind: ArgType::PureToken,
+            token: Some(token.to_string()),
+            optional: false,
+            multiple: false,
+            args: vec![],
+        }
+    }
+
+    fn parent(kind: ArgType, name: String, token: Option<String>, args: Vec<Arg>) -> Self {
+        Self {
+            name,
+            kind,
+            token,
+            optional: false,
+            multiple: false,
+            args,
+        }
+    }
+
+    /// Whether `other` is the same argument, repeated without its token.
+    fn repeated_by(&self, other: &Arg) -> bool {
+        self.name == other.name
+            && self.kind == other.kind
+            && self.args == other.args
+            && (other.token.is_none() || self.token == other.token)
+    }
+
+    fn frame(&self) -> RespFrame {
+        let mut fields = vec![
+            bulk("name"),
+            bulk(self.name.as_str()),
+            bulk("type"),
+            bulk(self.kind.as_str()),
+        ];
+        if !matches!(
+            self.kind,
+            ArgType::PureToken | ArgType::OneOf | ArgType::Block
+        ) {
+            fields.extend([bulk("display_text"), bulk(self.name.as_str())]);
+        }
+        if let Some(token) = &self.token {
+            fields.extend([bulk("token"), bulk(token.as_str())]);
+        }
+        let flags: Vec<RespFrame> = [("optional", self.optional), ("multiple", self.multiple)]
+            .into_iter()
+            .filter(|(_, set)| *set)
+            .map(|(flag, _)| SimpleString::new(flag).into())
+            .collect();
+        if !flags.is_empty() {
+            fields.extend([bulk("flags"), array(flags)]);
+        }
+        if !self.args.is_empty() {
+            fields.extend([bulk("arguments"), array(self.args.iter().map(Arg::frame))]);
+        }
+        array(fields)
+    }
+}
+
+/// Parse a synopsis like `<key> [NX|XX] [LIMIT <offset> <count>] <field> [<field> ...]`:
+/// `<name>` is a value, an upper case word a literal token, which introduces the
+/// value following it, `[...]` is optional, `(A|B)` a choice and `...` a repetition.
+fn parse_synopsis(synopsis: &str) -> Vec<Arg> {
+    let tokens = tokenize(synopsis);
+    let mut pos = 0;
+    let alternatives = parse_alternatives(&tokens, &mut pos);
+    alternatives.into_iter().next().unwrap_or_default()
+}
+
+fn tokenize(synopsis: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = synopsis.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '[' | ']' | '(' | ')' | '|' => 1,
+            '<' => rest.find('>').map_or(rest.len(), |end| end + 1),
+            _ => rest
+                .find(|c: char| c.is_whitespace() || "[]()|".contains(c))
+                .unwrap_or(rest.len()),
+        };
+        tokens.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    tokens
+}
+
+/// Parse sequences separated by `|`, up to a closing bracket or the end.
+fn parse_alternatives(tokens: &[&str], pos: &mut usize) -> Vec<Vec<Arg>> {
+    let mut alternatives = vec![parse_sequence(tokens, pos)];
+    while tokens.get(*pos) == Some(&"|") {
+        *pos += 1;
+        alternatives.push(parse_sequence(tokens, pos));
+    }
+    alternatives
+}
+
+fn parse_sequence(tokens: &[&str], pos: &mut usize) -> Vec<Arg> {
+    let mut items: Vec<Arg> = Vec::new();
+    let mut pending: Option<String> = None;
+    while let Some(&token) = tokens.get(*pos) {
+        match token {
+            "]" | ")" | "|" => break,
+            "[" | "(" => {
+                *pos += 1;
+                let alternatives = parse_alternatives(tokens, pos);
+                // the closing bracket.
+                *pos += 1;
+                if let Some(pending) = pending.take() {
+                    items.push(Arg::token(&pending));
+                }
+                let optional = token == "[";
+                if optional && alternatives.len() == 1 && repeats(&mut items, &alternatives[0]) {
+                    continue;
+                }
+                let mut group = collapse_alternatives(alternatives);
+                group.optional |= optional;
+                items.push(group);
+                continue;
+            }
+            "..." => {
+                if let Some(last) = items.last_mut() {
+                    last.multiple = true;
+                }
+            }
+            name if name.starts_with('<') => {
+                let name = name.trim_start_matches('<').trim_end_matches('>');
+                items.push(Arg::named(name, pending.take()));
+            }
+            literal => {
+                if let Some(pending) = pending.replace(literal.to_string()) {
+                    items.push(Arg::token(&pending));
+                }
+            }
+        }
+        *pos += 1;
+    }
+    if let Some(pending) = pending {
+        items.push(Arg::token(&pending));
+    }
+    items
+}
+
+/// Handle `<a> [<a> ...]`: an optional group repeating the items before it makes
+/// these items a repeated one.
+fn repeats(items: &mut Vec<Arg>, group: &[Arg]) -> bool {
+    let mut group = group.to_vec();
+    match group.last_mut() {
+        Some(last) if last.multiple => last.multiple = false,
+        _ => return false,
+    }
+    let Some(start) = items.len().checked_sub(group.len()) else {
+        return false;
+    };
+    let repeated = items[start..]
+        .iter()
+        .zip(&group)
+        .all(|(item, again)| item.repeated_by(again));
+    if !repeated {
+        return false;
+    }
+    let mut repeated = collapse(items.split_off(start));
+    repeated.multiple = true;
+    items.push(repeated);
+    true
+}
+
+fn collapse_alternatives(mut alternatives: Vec<Vec<Arg>>) -> Arg {
+    if alternatives.len() == 1 {
+        return collapse(alternatives.remove(0));
+    }
+    let choices: Vec<Arg> = alternatives.into_iter().map(collapse).collect();
+    let name = choices
+        .iter()
+        .map(|choice| choice.name.as_str())
+        .collect::<Vec<_>>()
+        .join("|");
+    Arg::parent(ArgType::OneOf, name, None, choices)
+}
+
+/// A sequence as a single argument: a block named after its leading token if it has
+/// one, after its parts otherwise.
+fn collapse(mut items: Vec<Arg>) -> Arg {
+    if items.len() == 1 {
+        return items.remove(0);
+    }
+    let token = match items.first() {
+        Some(first) if first.kind == ArgType::PureToken => items.remove(0).token,
+        Some(_) => items[0].token.take(),
+        None => None,
+    };
+    let name = match &token {
+        Some(token) => token.to_ascii_lowercase(),
+        None => items
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>()
+            .join("-"),
+    };
+    Arg::parent(ArgType::Block, name, token, items)
+}
+
+impl TryFrom<RespArray> for CommandCommand {
+    type Error = CommandError;
+
+    // command | command count | command list
+    // | command info [command-name ...] | command docs [command-name ...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter();
+        let Some(subcommand) = args.next() else {
+            return Ok(CommandCommand {
+                subcommand: CommandSubcommand::Info(vec![]),
+            });
+        };
+        let names: Vec<String> = args.collect();
+        let subcommand = match subcommand.to_ascii_lowercase().as_str() {
+            "count" if names.is_empty() => CommandSubcommand::Count,
+            "list" if names.is_empty() => CommandSubcommand::List,
+            "info" => CommandSubcommand::Info(names),
+            "docs" => CommandSubcommand::Docs(names),
+            name @ ("count" | "list") => {
+                return Err(CommandError::WrongArity(format!("command|{}", name)))
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'",
+                    subcommand
+                )))
+            }
+        };
+        Ok(CommandCommand { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(args: &[&str]) -> Result<CommandCommand, CommandError> {
+        CommandCommand::try_from(crate::cmd::array(args))
+    }
+
+    fn arg(name: &str, kind: ArgType) -> Arg {
+        Arg::parent(kind, name.to_string(), None, vec![])
+    }
+
+    #[test]
+    fn test_command_from_resp_array() -> anyhow::Result<()> {
+        assert_eq!(
+            command(&["command"])?.subcommand,
+            CommandSubcommand::Info(vec![])
+        );
+        assert_eq!(
+            command(&["command", "DOCS", "get", "set"])?.subcommand,
+            CommandSubcommand::Docs(vec!["get".to_string(), "set".to_string()])
+        );
+        assert_eq!(
+            command(&["command", "count"])?.subcommand,
+            CommandSubcommand::Count
+        );
+        assert!(command(&["command", "count", "get"]).is_err());
+        assert!(command(&["command", "getkeys", "get", "k"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_synopsis() {
+        let key = arg("key", ArgType::Key);
+        assert_eq!(parse_synopsis(""), vec![]);
+        assert_eq!(parse_synopsis("<key>"), vec![key.clone()]);
+
+        // a repeated argument, and a repeated group of arguments.
+        let args = parse_synopsis("<key> <field> [<field> ...]");
+        assert_eq!(args[1].name, "field");
+        assert!(args[1].multiple && !args[1].optional);
+        let args = parse_synopsis("<key> <score> <member> [<score> <member> ...]");
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[1].kind, ArgType::Block);
+        assert_eq!(args[1].name, "score-member");
+        assert!(args[1].multiple);
+        assert_eq!(args[1].args[0].kind, ArgType::Double);
+
+        // tokens introduce values, or stand alone.
+        let args = parse_synopsis("<key> [EXPANSION <expansion>] [NONSCALING]");
+        assert_eq!(args[1].token.as_deref(), Some("EXPANSION"));
+        assert_eq!(args[1].kind, ArgType::Integer);
+        assert!(args[1].optional);
+        assert_eq!(args[2].kind, ArgType::PureToken);
+        let args = parse_synopsis("<key> [LIMIT <offset> <count>]");
+        assert_eq!(args[1].kind, ArgType::Block);
+        assert_eq!(args[1].token.as_deref(), Some("LIMIT"));
+        assert_eq!(args[1].args.len(), 2);
+        let args = parse_synopsis("[KEYS <key> [<key> ...]]");
+        assert_eq!(args[0].token.as_deref(), Some("KEYS"));
+        assert!(args[0].optional && args[0].multiple);
+
+        // choices.
+        let args = parse_synopsis("<key> [NX|XX]");
+        assert_eq!(args[1].kind, ArgType::OneOf);
+        assert!(args[1].optional);
+        assert_eq!(args[1].args.len(), 2);
+        let args = parse_synopsis("(<key>|\"\")");
+        assert_eq!(args[0].kind, ArgType::OneOf);
+        assert_eq!(args[0].args, vec![key, Arg::token("\"\"")]);
+        assert_eq!(args[0].args[1].name, "empty-string");
+    }
+
+    #[test]
+    fn test_every_command_is_documented() {
+        for spec in COMMAND_TABLE {
+            let docs = &spec.docs;
+            assert!(!docs.summary.is_empty(), "{}", spec.name);
+            assert!(!docs.since.is_empty(), "{}", spec.name);
+            assert!(!docs.group.is_empty(), "{}", spec.name);
+            // the synopsis has an argument per required argument of the command.
+            let args = parse_synopsis(docs.args);
+            let required = args.iter().filter(|arg| !arg.optional).count() as i64 + 1;
+            assert!(spec.arity.abs() >= required, "{}", spec.name);
+        }
+    }
+
+    #[test]
+    fn test_command_docs() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let reply = command(&["command", "docs", "get", "unknown"])?.execute(&backend);
+        let get = array([
+            bulk("summary"),
+            bulk("Returns the string value of a key."),
+            bulk("since"),
+            bulk("1.0.0"),
+            bulk("group"),
+            bulk("string"),
+            bulk("arguments"),
+            array([array([
+                bulk("name"),
+                bulk("key"),
+                bulk("type"),
+                bulk("key"),
+                bulk("display_text"),
+                bulk("key"),
+            ])]),
+        ]);
+        assert_eq!(reply, array([bulk("get"), get]));
+
+        let RespFrame::Array(docs) = command(&["command", "docs", "memory"])?.execute(&backend)
+        else {
+            panic!("docs must be an array");
+        };
+        let RespFrame::Array(fields) = &docs[1] else {
+            panic!("docs must be an array");
+        };
+        assert_eq!(fields[6], bulk("subcommands"));
+        let RespFrame::Array(subcommands) = &fields[7] else {
+            panic!("subcommands must be an array");
+        };
+        assert_eq!(subcommands[0], bulk("memory|doctor"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_info() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let reply = command(&["command", "info", "get", "unknown"])?.execute(&backend);
+        let get = array([
+            bulk("get"),
+            RespFrame::Integer(2),
+            array([SimpleString::new("readonly").into()]),
+            RespFrame::Integer(1),
+            RespFrame::Integer(1),
+            RespFrame::Integer(1),
+            array([]),
+            array([]),
+            array([]),
+            array([]),
+        ]);
+        assert_eq!(reply, array([get, RespFrame::Null(RespNull)]));
+
+        assert_eq!(
+            command(&["command", "count"])?.execute(&backend),
+            RespFrame::Integer(COMMAND_TABLE.len() as i64)
+        );
+        assert_eq!(subcommand_arity(&MEMORY_USAGE), -3);
+        Ok(())
+    }
+
+    const MEMORY_USAGE: SubcommandSpec = SubcommandSpec {
+        name: "usage",
+        args: "<key> [SAMPLES <count>]",
+        summary: "",
+    };
+}
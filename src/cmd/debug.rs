@@ -0,0 +1,217 @@
+use std::time::Duration;
+
+use crate::{glob::glob_match, Backend, RespArray, RespFrame, SimpleError, SimpleString};
+
+use super::{
+    err::CommandError, extract_args, extract_integer, extract_string, CommandExecutor,
+    ConnectionCommand, DebugCommand, RESP_OK,
+};
+
+/// Subcommands of DEBUG which change how the replies of the connection are written,
+/// to test how clients deal with slow or fragmented replies.
+pub(crate) const CONNECTION_SUBCOMMANDS: &[&str] = &["reply-delay", "reply-fragment"];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DebugSubcommand {
+    Reload,
+    Object(String),
+    /// Whether the glob pattern matches the string. Redis runs a fuzz test of
+    /// its matcher instead, matching a given string makes tests more useful.
+    StringMatchLen {
+        pattern: String,
+        string: String,
+    },
+}
+
+impl CommandExecutor for DebugCommand {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.subcommand {
+            DebugSubcommand::Reload => match backend.reload() {
+                Ok(_) => RESP_OK.clone(),
+                Err(e) => {
+                    SimpleError::new(format!("ERR Error trying to load the snapshot: {}", e)).into()
+                }
+            },
+            DebugSubcommand::Object(key) => match backend.object_internals(&key) {
+                Some(object) => {
+                    let mut line = format!(
+                        "Value at:0x{:x} refcount:{} encoding:{} serializedlength:{}",
+                        object.address, object.refcount, object.encoding, object.serialized_len
+                    );
+                    if let Some(idle) = object.idle {
+                        line.push_str(&format!(" lru_seconds_idle:{}", idle.as_secs()));
+                    }
+                    SimpleString::new(line).into()
+                }
+                None => SimpleError::new("ERR no such key").into(),
+            },
+            DebugSubcommand::StringMatchLen { pattern, string } => {
+                RespFrame::Integer(glob_match(pattern.as_bytes(), string.as_bytes()) as i64)
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for DebugCommand {
+    type Error = CommandError;
+
+    // debug reload | debug object key | debug stringmatch-len pattern string
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(arg) => extract_string(arg)?,
+            None => return Err(CommandError::WrongArity("debug".to_string())),
+        };
+        match subcommand.to_ascii_lowercase().as_str() {
+            sub if CONNECTION_SUBCOMMANDS.contains(&sub) => Err(CommandError::InvalidCommand(
+                format!("DEBUG {} is only allowed on a client connection", sub),
+            )),
+            // the NOSAVE, NOFLUSH and MERGE options of Redis are not supported.
+            "reload" if args.next().is_none() => Ok(DebugCommand {
+                subcommand: DebugSubcommand::Reload,
+            }),
+            "reload" => Err(CommandError::InvalidArgument("syntax error".to_string())),
+            "object" => match (args.next(), args.next()) {
+                (Some(key), None) => Ok(DebugCommand {
+                    subcommand: DebugSubcommand::Object(extract_string(key)?),
+                }),
+                _ => Err(CommandError::WrongArity("debug|object".to_string())),
+            },
+            "stringmatch-len" => match (args.next(), args.next(), args.next()) {
+                (Some(pattern), Some(string), None) => Ok(DebugCommand {
+                    subcommand: DebugSubcommand::StringMatchLen {
+                        pattern: extract_string(pattern)?,
+                        string: extract_string(string)?,
+                    },
+                }),
+                _ => Err(CommandError::WrongArity(
+                    "debug|stringmatch-len".to_string(),
+                )),
+            },
+            _ => Err(CommandError::InvalidArgument(format!(
+                "unknown subcommand '{}'",
+                subcommand
+            ))),
+        }
+    }
+}
+
+/// Parse the subcommands of DEBUG handled by the connection, see [`CONNECTION_SUBCOMMANDS`].
+pub(crate) fn parse_connection_debug(value: RespArray) -> Result<ConnectionCommand, CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let subcommand = match args.next() {
+        Some(arg) => extract_string(arg)?.to_ascii_lowercase(),
+        None => return Err(CommandError::WrongArity("debug".to_string())),
+    };
+    let value = match (args.next(), args.next()) {
+        (Some(value), None) => u64::try_from(extract_integer(value)?).map_err(|_| {
+            CommandError::InvalidArgument("value is out of range, must be positive".into())
+        })?,
+        _ => return Err(CommandError::WrongArity(format!("debug|{}", subcommand))),
+    };
+    match subcommand.as_str() {
+        // debug reply-delay milliseconds
+        "reply-delay" => Ok(ConnectionCommand::DebugReplyDelay(Duration::from_millis(
+            value,
+        ))),
+        // debug reply-fragment bytes
+        "reply-fragment" => Ok(ConnectionCommand::DebugReplyFragment(value as usize)),
+        _ => Err(CommandError::InvalidArgument(format!(
+            "unknown subcommand '{}'",
+            subcommand
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::array;
+
+    use super::*;
+
+    fn debug(args: &[&str]) -> Result<DebugCommand, CommandError> {
+        DebugCommand::try_from(array(args))
+    }
+
+    #[test]
+    fn test_debug_from_resp_array() -> anyhow::Result<()> {
+        assert_eq!(
+            debug(&["debug", "RELOAD"])?.subcommand,
+            DebugSubcommand::Reload
+        );
+        assert!(debug(&["debug"]).is_err());
+        assert!(debug(&["debug", "reload", "extra"]).is_err());
+        assert!(debug(&["debug", "segfault"]).is_err());
+
+        assert_eq!(
+            debug(&["debug", "object", "k"])?.subcommand,
+            DebugSubcommand::Object("k".to_string())
+        );
+        assert!(debug(&["debug", "object"]).is_err());
+        assert!(debug(&["debug", "stringmatch-len", "*"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_connection_debug_from_resp_array() -> anyhow::Result<()> {
+        let f: RespFrame = array(&["debug", "REPLY-DELAY", "20"]).into();
+        assert!(ConnectionCommand::matches(&f));
+        assert!(!ConnectionCommand::matches(
+            &array(&["debug", "object", "k"]).into()
+        ));
+        match ConnectionCommand::try_from(f)? {
+            ConnectionCommand::DebugReplyDelay(delay) => {
+                assert_eq!(delay, Duration::from_millis(20))
+            }
+            cmd => panic!("unexpected command: {:?}", cmd),
+        }
+        match parse_connection_debug(array(&["debug", "reply-fragment", "1"]))? {
+            ConnectionCommand::DebugReplyFragment(len) => assert_eq!(len, 1),
+            cmd => panic!("unexpected command: {:?}", cmd),
+        }
+        assert!(parse_connection_debug(array(&["debug", "reply-delay", "-1"])).is_err());
+        assert!(parse_connection_debug(array(&["debug", "reply-delay"])).is_err());
+        // not executed on the backend.
+        assert!(debug(&["debug", "reply-delay", "20"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_object_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("k".to_string(), b"hello".to_vec());
+        let RespFrame::SimpleString(line) = debug(&["debug", "object", "k"])?.execute(&backend)
+        else {
+            panic!("expected a simple string");
+        };
+        assert!(line.0.starts_with("Value at:0x"));
+        // *3\r\n$3\r\nset\r\n$1\r\nk\r\n$5\r\nhello\r\n
+        assert!(line
+            .0
+            .ends_with(" refcount:1 encoding:embstr serializedlength:31"));
+
+        let res = debug(&["debug", "object", "missing"])?.execute(&backend);
+        assert_eq!(res, SimpleError::new("ERR no such key").into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_stringmatch_len_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let res = debug(&["debug", "stringmatch-len", "h?llo*", "hello world"])?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(1));
+        let res = debug(&["debug", "stringmatch-len", "h[^e]llo", "hello"])?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_reload_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("k".to_string(), b"v".to_vec());
+        let res = debug(&["debug", "reload"])?.execute(&backend);
+        assert_eq!(res, RESP_OK.clone());
+        assert_eq!(backend.get("k").as_deref(), Some(&b"v"[..]));
+        Ok(())
+    }
+}
@@ -15,7 +15,7 @@ impl TryFrom<RespArray> for Echo {
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(BulkString(Some(message)))) => Ok(Echo {
-                message: String::from_utf8(message).map_err(CommandError::Utf8Error)?,
+                message: String::from_utf8(message.into()).map_err(CommandError::Utf8Error)?,
             }),
             _ => Err(CommandError::InvalidArgument(
                 "Echo command requires a single bulk string argument".to_string(),
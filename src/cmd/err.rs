@@ -1,16 +1,183 @@
 use thiserror::Error;
 
-use crate::err::RespError;
+use crate::{err::RespError, RespFrame, SimpleError, WrongType};
 
+use super::import::RestoreError;
+
+/// Errors replied to clients.
+///
+/// The messages follow the Redis conventions: they start with an upper-case
+/// error code (`ERR`, `WRONGTYPE`, ...) which client libraries dispatch on.
 #[derive(Debug, Error)]
 pub enum CommandError {
-    #[error("Invalid command: {0}")]
+    #[error("ERR {0}")]
     InvalidCommand(String),
-    #[error("Invalid argument: {0}")]
+    #[error("ERR {0}")]
     InvalidArgument(String),
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+    /// The command doesn't exist, see [`CommandError::unknown_command`].
+    #[error("ERR unknown command '{name}', with args beginning with: {args}")]
+    UnknownCommand { name: String, args: String },
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+    #[error("NOAUTH Authentication required.")]
+    NoAuth,
+    /// A command which may grow the dataset while the server is over its max memory.
+    #[error("OOM command not allowed when used memory > 'maxmemory'.")]
+    OutOfMemory,
+    /// A client connected from another host while the server is in protected mode.
+    #[error(
+        "DENIED R-Redis is running in protected mode because protected mode is enabled, \
+         no bind address was explicitly specified, and no authentication password is \
+         required to clients. In this mode connections are only accepted from the \
+         loopback interface. If you want to connect from external computers to R-Redis \
+         you may adopt one of the following solutions: 1) Restart the server with the \
+         '--protected-mode no' option, however MAKE SURE it is not publicly accessible \
+         from internet if you do so. 2) Restart the server with the '--bind' option set \
+         to the address of the interfaces clients connect to. NOTE: You only need to do \
+         one of the above things in order for the server to start accepting connections \
+         from the outside."
+    )]
+    ProtectedMode,
+    /// The key is served by another cluster node.
+    #[error("MOVED {slot} {addr}")]
+    Moved { slot: u16, addr: String },
+    /// The key is being moved to another cluster node, which the client must
+    /// send ASKING to before retrying.
+    #[error("ASK {slot} {addr}")]
+    Ask { slot: u16, addr: String },
+    /// The keys of a command are in different slots, which cluster nodes may not share.
+    #[error("CROSSSLOT Keys in request don't hash to the same slot")]
+    CrossSlot,
+    /// The key is in a slot no cluster node serves.
+    #[error("CLUSTERDOWN Hash slot not served")]
+    ClusterDown,
+
+    #[error("NOPROTO unsupported protocol version")]
+    NoProto,
+    /// EXEC of a transaction in which a command was refused.
+    #[error("EXECABORT Transaction discarded because of previous errors.")]
+    ExecAbort,
 
-    #[error("{0}")]
+    #[error(transparent)]
+    Restore(#[from] RestoreError),
+    /// Talking to another server failed.
+    #[error("IOERR {0}")]
+    Io(String),
+
+    #[error("ERR Protocol error: {0}")]
     RespError(#[from] RespError),
-    #[error("Utf8 error: {0}")]
+    #[error("ERR invalid utf8: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
 }
+
+/// Bytes of the command name, and of its arguments, quoted in [`CommandError::UnknownCommand`].
+const UNKNOWN_COMMAND_QUOTE_LEN: usize = 128;
+
+impl CommandError {
+    /// The error of the request `args` whose command doesn't exist. The message is
+    /// the one of Redis, which some clients parse: the beginning of the arguments
+    /// is quoted, each followed by a space, e.g. `'a' 'b' `.
+    pub fn unknown_command(args: &[RespFrame]) -> Self {
+        let mut args = args.iter().map(|arg| match arg {
+            RespFrame::BulkString(arg) => arg.as_ref(),
+            _ => &[],
+        });
+        let name = args.next().unwrap_or_default();
+        let name = &name[..name.len().min(UNKNOWN_COMMAND_QUOTE_LEN)];
+        let mut quoted = Vec::new();
+        for arg in args {
+            if quoted.len() >= UNKNOWN_COMMAND_QUOTE_LEN {
+                break;
+            }
+            let len = arg.len().min(UNKNOWN_COMMAND_QUOTE_LEN - quoted.len());
+            quoted.push(b'\'');
+            quoted.extend_from_slice(&arg[..len]);
+            quoted.extend_from_slice(b"' ");
+        }
+        // the message is a simple string, which can't hold a line break.
+        let sanitize = |bytes: &[u8]| String::from_utf8_lossy(bytes).replace(['\r', '\n'], " ");
+        CommandError::UnknownCommand {
+            name: sanitize(name),
+            args: sanitize(&quoted),
+        }
+    }
+}
+
+impl From<WrongType> for CommandError {
+    fn from(_: WrongType) -> Self {
+        CommandError::WrongType
+    }
+}
+
+impl From<CommandError> for RespFrame {
+    fn from(e: CommandError) -> Self {
+        SimpleError::new(e.to_string()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BulkString;
+
+    use super::*;
+
+    #[test]
+    fn test_command_error_prefixes() {
+        assert_eq!(
+            CommandError::WrongArity("get".to_string()).to_string(),
+            "ERR wrong number of arguments for 'get' command"
+        );
+        assert_eq!(
+            CommandError::InvalidArgument("syntax error".to_string()).to_string(),
+            "ERR syntax error"
+        );
+        assert!(CommandError::WrongType
+            .to_string()
+            .starts_with("WRONGTYPE "));
+        assert!(CommandError::NoAuth.to_string().starts_with("NOAUTH "));
+        assert!(CommandError::OutOfMemory.to_string().starts_with("OOM "));
+        let denied = CommandError::ProtectedMode.to_string();
+        assert!(denied.starts_with("DENIED R-Redis is running in protected mode because "));
+        assert!(!denied.contains("  ") && !denied.contains('\n'));
+        let moved = CommandError::Moved {
+            slot: 3999,
+            addr: "127.0.0.1:6381".to_string(),
+        };
+        assert_eq!(moved.to_string(), "MOVED 3999 127.0.0.1:6381");
+        assert!(CommandError::CrossSlot
+            .to_string()
+            .starts_with("CROSSSLOT "));
+        assert!(CommandError::ClusterDown
+            .to_string()
+            .starts_with("CLUSTERDOWN "));
+
+        let args: Vec<RespFrame> = ["FOO", "a", "b\r\nc"]
+            .iter()
+            .map(|a| BulkString::new(*a).into())
+            .collect();
+        assert_eq!(
+            CommandError::unknown_command(&args).to_string(),
+            "ERR unknown command 'FOO', with args beginning with: 'a' 'b  c' "
+        );
+        assert_eq!(
+            CommandError::unknown_command(&args[..1]).to_string(),
+            "ERR unknown command 'FOO', with args beginning with: "
+        );
+        let long: Vec<RespFrame> = ["foo", &"x".repeat(100), &"y".repeat(100), "z"]
+            .iter()
+            .map(|a| BulkString::new(*a).into())
+            .collect();
+        let message = CommandError::unknown_command(&long).to_string();
+        let expected = format!("'{}' '{}' ", "x".repeat(100), "y".repeat(25));
+        assert!(message.ends_with(&expected), "{}", message);
+
+        let frame: RespFrame = CommandError::WrongType.into();
+        assert_eq!(
+            frame,
+            SimpleError::new("WRONGTYPE Operation against a key holding the wrong kind of value")
+                .into()
+        );
+    }
+}
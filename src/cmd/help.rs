@@ -0,0 +1,95 @@
+use crate::{Backend, RespArray, RespFrame, SimpleString};
+
+use super::{registry::CommandSpec, CommandExecutor, Help};
+
+/// The HELP subcommand of container commands, generated from their registry entry.
+impl CommandExecutor for Help {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        let name = self.spec.name.to_ascii_uppercase();
+        let mut lines = vec![format!(
+            "{} <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+            name
+        )];
+        for sub in self.spec.subcommands {
+            let synopsis = format!("{} {}", sub.name.to_ascii_uppercase(), sub.args);
+            lines.push(synopsis.trim_end().to_string());
+            lines.push(format!("    {}", sub.summary));
+        }
+        lines.push("HELP".to_string());
+        lines.push("    Print this help.".to_string());
+        RespArray::new(
+            lines
+                .into_iter()
+                .map(|line| SimpleString::new(line).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+}
+
+impl Help {
+    /// Recognize `<container> HELP`.
+    pub(crate) fn parse(spec: &'static CommandSpec, value: &RespArray) -> Option<Self> {
+        match value.get(1) {
+            Some(RespFrame::BulkString(sub))
+                if spec.is_container()
+                    && value.len() == 2
+                    && sub.as_ref().eq_ignore_ascii_case(b"help") =>
+            {
+                Some(Help { spec })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cmd::{array, Command},
+        lookup_command,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_help_parse() {
+        let memory = lookup_command(b"memory").unwrap();
+        assert!(Help::parse(memory, &array(&["memory", "HELP"])).is_some());
+        assert!(Help::parse(memory, &array(&["memory", "help", "x"])).is_none());
+        assert!(Help::parse(memory, &array(&["memory", "doctor"])).is_none());
+
+        let get = lookup_command(b"get").unwrap();
+        assert!(Help::parse(get, &array(&["get", "help"])).is_none());
+    }
+
+    #[test]
+    fn test_help_execute() -> anyhow::Result<()> {
+        let cmd = Command::try_from(array(&["memory", "help"]))?;
+        let RespFrame::Array(lines) = cmd.execute(&Backend::new()) else {
+            panic!("help must reply with an array");
+        };
+        let lines: Vec<String> = lines
+            .iter()
+            .map(|line| match line {
+                RespFrame::SimpleString(s) => s.as_ref().to_string(),
+                _ => panic!("help lines must be simple strings"),
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "MEMORY <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+                "DOCTOR",
+                "    Return memory problems reports.",
+                "PURGE",
+                "    Re-encode values which shrank into their most compact representation.",
+                "USAGE <key> [SAMPLES <count>]",
+                "    Return memory in bytes used by <key> and its value.",
+                "HELP",
+                "    Print this help.",
+            ]
+        );
+        Ok(())
+    }
+}
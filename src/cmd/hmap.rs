@@ -1,8 +1,11 @@
-use crate::{Backend, BulkString, RespArray, RespFrame, RespMap, RespNull};
+use std::time::{Duration, Instant};
+
+use crate::{Backend, BulkString, ExpireCondition, RespArray, RespFrame, RespMap, RespNull};
 
 use super::{
-    extract_args, validate_command, CommandError, CommandExecutor, HGet, HGetAll, HMGet, HSet,
-    RESP_OK,
+    extract_args, extract_integer, extract_random_count, extract_string, map::bytes_to_integer,
+    validate_command, CommandError, CommandExecutor, HExpire, HGet, HGetAll, HIncrBy, HMGet,
+    HPersist, HRandField, HSet, HTtl, RESP_OK,
 };
 
 impl CommandExecutor for HGet {
@@ -17,8 +20,10 @@ impl CommandExecutor for HGet {
 
 impl CommandExecutor for HSet {
     fn execute(self, backend: &Backend) -> crate::RespFrame {
-        backend.hset(self.key, self.field, self.value);
-        RESP_OK.clone()
+        match backend.hset(self.key, self.field, self.value) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => CommandError::from(e).into(),
+        }
     }
 }
 
@@ -28,7 +33,7 @@ impl CommandExecutor for HGetAll {
         let mut m = RespMap::new();
         if let Some(map) = res {
             for (k, v) in map {
-                m.insert(k, v);
+                m.insert(BulkString::new(k), v);
             }
         }
         m.into()
@@ -40,12 +45,94 @@ impl CommandExecutor for HMGet {
         let m = backend.hmget(&self.key, &self.fields);
         let mut res = RespMap::new();
         for (k, v) in m {
-            res.insert(k, v);
+            res.insert(BulkString::new(k), v);
         }
         res.into()
     }
 }
 
+impl CommandExecutor for HIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let res = backend.hupdate(&self.key, &self.field, |value| {
+            let current = match value {
+                Some(frame) => match frame {
+                    RespFrame::BulkString(BulkString(Some(bytes))) => bytes_to_integer(bytes),
+                    RespFrame::Integer(i) => Some(*i),
+                    _ => None,
+                }
+                .ok_or_else(|| {
+                    CommandError::InvalidArgument("hash value is not an integer".to_string())
+                })?,
+                None => 0,
+            };
+            let new = current.checked_add(self.increment).ok_or_else(|| {
+                CommandError::InvalidArgument("increment or decrement would overflow".to_string())
+            })?;
+            *value = Some(BulkString::new(new.to_string()).into());
+            Ok::<_, CommandError>(new)
+        });
+        match res.unwrap_or_else(|e| Err(e.into())) {
+            Ok(new) => RespFrame::Integer(new),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HExpire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let deadline = Instant::now() + Duration::from_millis(self.ttl_ms as u64);
+        integers(backend.hexpire(&self.key, &self.fields, deadline, self.condition))
+    }
+}
+
+impl CommandExecutor for HTtl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ttls = backend.hpttl(&self.key, &self.fields);
+        // like Redis, round the remaining time to the nearest second.
+        integers(
+            ttls.into_iter()
+                .map(|ms| if ms < 0 { ms } else { (ms + 500) / 1000 })
+                .collect(),
+        )
+    }
+}
+
+impl CommandExecutor for HPersist {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integers(backend.hpersist(&self.key, &self.fields))
+    }
+}
+
+impl CommandExecutor for HRandField {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let Some(count) = self.count else {
+            return match backend.hrandfield(&self.key, 1).pop() {
+                Some((field, _)) => BulkString::new(field).into(),
+                None => RespFrame::Null(RespNull),
+            };
+        };
+        let fields = backend.hrandfield(&self.key, count);
+        let mut res = Vec::with_capacity(fields.len() * (1 + self.withvalues as usize));
+        for (field, value) in fields {
+            res.push(BulkString::new(field).into());
+            if self.withvalues {
+                res.push(value);
+            }
+        }
+        RespArray::new(res).into()
+    }
+}
+
+fn integers(values: Vec<i64>) -> RespFrame {
+    RespArray::new(
+        values
+            .into_iter()
+            .map(RespFrame::Integer)
+            .collect::<Vec<_>>(),
+    )
+    .into()
+}
+
 impl TryFrom<RespArray> for HGet {
     type Error = CommandError;
 
@@ -59,8 +146,8 @@ impl TryFrom<RespArray> for HGet {
                 Some(RespFrame::BulkString(BulkString(Some(key)))),
                 Some(RespFrame::BulkString(BulkString(Some(field)))),
             ) => Ok(HGet {
-                key: String::from_utf8(key).map_err(CommandError::Utf8Error)?,
-                field: String::from_utf8(field).map_err(CommandError::Utf8Error)?,
+                key: String::from_utf8(key.into()).map_err(CommandError::Utf8Error)?,
+                field: String::from_utf8(field.into()).map_err(CommandError::Utf8Error)?,
             }),
             _ => Err(CommandError::InvalidArgument(
                 "Invalid key or field".to_string(),
@@ -81,8 +168,8 @@ impl TryFrom<RespArray> for HSet {
                 Some(RespFrame::BulkString(BulkString(Some(field)))),
                 Some(value),
             ) => Ok(HSet {
-                key: String::from_utf8(key).map_err(CommandError::Utf8Error)?,
-                field: String::from_utf8(field).map_err(CommandError::Utf8Error)?,
+                key: String::from_utf8(key.into()).map_err(CommandError::Utf8Error)?,
+                field: String::from_utf8(field.into()).map_err(CommandError::Utf8Error)?,
                 value,
             }),
             _ => Err(CommandError::InvalidArgument(
@@ -100,7 +187,7 @@ impl TryFrom<RespArray> for HGetAll {
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(BulkString(Some(key)))) => Ok(HGetAll {
-                key: String::from_utf8(key).map_err(CommandError::Utf8Error)?,
+                key: String::from_utf8(key.into()).map_err(CommandError::Utf8Error)?,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
@@ -111,9 +198,7 @@ impl TryFrom<RespArray> for HMGet {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
         if value.len() < 3 {
-            return Err(CommandError::InvalidArgument(
-                "wrong number of arguments for 'hmget' command".to_string(),
-            ));
+            return Err(CommandError::WrongArity("hmget".to_string()));
         }
 
         validate_command(&value, "hmget", value.len() - 1)?;
@@ -121,7 +206,7 @@ impl TryFrom<RespArray> for HMGet {
         let mut args = extract_args(value, 1)?.into_iter();
         let key = match args.next() {
             Some(RespFrame::BulkString(BulkString(Some(key)))) => {
-                String::from_utf8(key).map_err(CommandError::Utf8Error)?
+                String::from_utf8(key.into()).map_err(CommandError::Utf8Error)?
             }
             _ => {
                 return Err(CommandError::InvalidArgument(
@@ -139,7 +224,7 @@ impl TryFrom<RespArray> for HMGet {
             match arg {
                 RespFrame::BulkString(BulkString(Some(field))) => res
                     .fields
-                    .push(String::from_utf8(field).map_err(CommandError::Utf8Error)?),
+                    .push(String::from_utf8(field.into()).map_err(CommandError::Utf8Error)?),
                 _ => {
                     return Err(CommandError::InvalidArgument(
                         "Invalid of lack of field".to_string(),
@@ -151,11 +236,209 @@ impl TryFrom<RespArray> for HMGet {
     }
 }
 
+impl TryFrom<RespArray> for HIncrBy {
+    type Error = CommandError;
+
+    // hincrby key field increment
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "hincrby", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(field), Some(increment)) => Ok(HIncrBy {
+                key: extract_string(key)?,
+                field: extract_string(field)?,
+                increment: extract_integer(increment)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, field or increment".to_string(),
+            )),
+        }
+    }
+}
+
+/// Time to live of hash fields are bounded so that deadlines never overflow.
+const MAX_FIELD_TTL_MS: i64 = (1 << 48) - 1;
+
+impl TryFrom<RespArray> for HExpire {
+    type Error = CommandError;
+
+    // hexpire key seconds [NX | XX | GT | LT] FIELDS numfields field [field ...]
+    // hpexpire key milliseconds [NX | XX | GT | LT] FIELDS numfields field [field ...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(c)) => String::from_utf8_lossy(c.as_ref()).to_lowercase(),
+            _ => "hexpire".to_string(),
+        };
+        let unit = if name == "hpexpire" { 1 } else { 1000 };
+        if value.len() < 6 {
+            return Err(CommandError::WrongArity(name));
+        }
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next().unwrap())?;
+        let ttl = extract_integer(args.next().unwrap())?;
+        let ttl_ms = ttl
+            .checked_mul(unit)
+            .filter(|ms| (0..=MAX_FIELD_TTL_MS).contains(ms))
+            .ok_or_else(|| {
+                CommandError::InvalidArgument(format!("invalid expire time in '{}' command", name))
+            })?;
+        let condition = match args.peek() {
+            Some(RespFrame::BulkString(arg)) => {
+                match arg.as_ref().to_ascii_lowercase().as_slice() {
+                    b"nx" => Some(ExpireCondition::Nx),
+                    b"xx" => Some(ExpireCondition::Xx),
+                    b"gt" => Some(ExpireCondition::Gt),
+                    b"lt" => Some(ExpireCondition::Lt),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        if condition.is_some() {
+            args.next();
+        }
+        Ok(HExpire {
+            key,
+            ttl_ms,
+            condition: condition.unwrap_or(ExpireCondition::Always),
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HTtl {
+    type Error = CommandError;
+
+    // httl key FIELDS numfields field [field ...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 5 {
+            return Err(CommandError::WrongArity("httl".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HTtl {
+            key: extract_string(args.next().unwrap())?,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HPersist {
+    type Error = CommandError;
+
+    // hpersist key FIELDS numfields field [field ...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 5 {
+            return Err(CommandError::WrongArity("hpersist".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HPersist {
+            key: extract_string(args.next().unwrap())?,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HRandField {
+    type Error = CommandError;
+
+    // hrandfield key [count [WITHVALUES]]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if !(2..=4).contains(&value.len()) {
+            return Err(CommandError::WrongArity("hrandfield".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let count = args.next().map(extract_random_count).transpose()?;
+        let withvalues = match args.next().map(extract_string).transpose()? {
+            Some(arg) if arg.eq_ignore_ascii_case("withvalues") => true,
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            None => false,
+        };
+        Ok(HRandField {
+            key,
+            count,
+            withvalues,
+        })
+    }
+}
+
+/// Parse the `FIELDS numfields field [field ...]` block ending the field expiration commands.
+fn parse_fields(mut args: impl Iterator<Item = RespFrame>) -> Result<Vec<String>, CommandError> {
+    match args.next().map(extract_string).transpose()? {
+        Some(arg) if arg.eq_ignore_ascii_case("fields") => {}
+        _ => {
+            return Err(CommandError::InvalidArgument(
+                "Mandatory argument FIELDS is missing or not at the right position".to_string(),
+            ))
+        }
+    }
+    let numfields = match args.next() {
+        Some(arg) => extract_integer(arg)?,
+        None => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+    };
+    if numfields <= 0 {
+        return Err(CommandError::InvalidArgument(
+            "Parameter `numFields` should be greater than 0".to_string(),
+        ));
+    }
+    let fields = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+    if fields.len() != numfields as usize {
+        return Err(CommandError::InvalidArgument(
+            "The `numfields` parameter must match the number of arguments".to_string(),
+        ));
+    }
+    Ok(fields)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::BulkString;
+    use crate::{BulkString, SimpleError};
 
     use super::*;
+    use crate::cmd::array;
+
+    #[test]
+    fn test_hrandfield() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend
+            .hset(
+                "h".to_string(),
+                "f".to_string(),
+                BulkString::new("v").into(),
+            )
+            .unwrap();
+        let hrandfield = |args: &[&str]| -> Result<RespFrame, CommandError> {
+            let mut frames = vec![BulkString::new("hrandfield").into()];
+            frames.extend(args.iter().map(|a| BulkString::new(*a).into()));
+            Ok(HRandField::try_from(RespArray::new(frames))?.execute(&backend))
+        };
+        let array = |items: &[&str]| -> RespFrame {
+            RespArray::new(
+                items
+                    .iter()
+                    .map(|i| BulkString::new(*i).into())
+                    .collect::<Vec<_>>(),
+            )
+            .into()
+        };
+
+        assert_eq!(hrandfield(&["h"])?, BulkString::new("f").into());
+        assert_eq!(hrandfield(&["missing"])?, RespFrame::Null(RespNull));
+        assert_eq!(hrandfield(&["h", "5", "withvalues"])?, array(&["f", "v"]));
+        assert_eq!(hrandfield(&["h", "-2"])?, array(&["f", "f"]));
+        assert_eq!(hrandfield(&["missing", "3"])?, array(&[]));
+        assert!(hrandfield(&["h", "x"]).is_err());
+        assert!(hrandfield(&["h", "1", "values"]).is_err());
+
+        for count in [i64::MIN, -(i64::MAX / 2) - 1] {
+            let res = hrandfield(&["h", &count.to_string(), "withvalues"]);
+            assert_eq!(res.unwrap_err().to_string(), "ERR value is out of range");
+        }
+        // nothing is allocated for the picks of an empty reply.
+        assert_eq!(hrandfield(&["missing", "-999999999999"])?, array(&[]));
+        Ok(())
+    }
 
     #[test]
     fn test_hget_from_resp_array() -> anyhow::Result<()> {
@@ -197,4 +480,112 @@ mod tests {
         assert_eq!(hget.key, "key");
         Ok(())
     }
+
+    #[test]
+    fn test_execute_hincrby() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let hincrby = |args: &[&str]| -> anyhow::Result<RespFrame> {
+            Ok(HIncrBy::try_from(array(args))?.execute(&backend))
+        };
+
+        assert_eq!(hincrby(&["hincrby", "h", "f", "5"])?, RespFrame::Integer(5));
+        assert_eq!(
+            hincrby(&["hincrby", "h", "f", "-7"])?,
+            RespFrame::Integer(-2)
+        );
+        assert_eq!(backend.hget("h", "f"), Some(BulkString::new("-2").into()));
+
+        backend
+            .hset(
+                "h".to_string(),
+                "s".to_string(),
+                BulkString::new("x").into(),
+            )
+            .unwrap();
+        assert_eq!(
+            hincrby(&["hincrby", "h", "s", "1"])?,
+            SimpleError::new("ERR hash value is not an integer").into()
+        );
+        assert!(hincrby(&["hincrby", "h", "f", "x"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hexpire_from_resp_array() -> anyhow::Result<()> {
+        let hexpire =
+            |args: &[&str]| -> Result<HExpire, CommandError> { HExpire::try_from(array(args)) };
+
+        let cmd = hexpire(&["hexpire", "h", "10", "NX", "FIELDS", "2", "a", "b"])?;
+        assert_eq!(cmd.ttl_ms, 10_000);
+        assert_eq!(cmd.condition, ExpireCondition::Nx);
+        assert_eq!(cmd.fields, vec!["a", "b"]);
+
+        let cmd = hexpire(&["HPEXPIRE", "h", "10", "fields", "1", "a"])?;
+        assert_eq!(cmd.ttl_ms, 10);
+        assert_eq!(cmd.condition, ExpireCondition::Always);
+
+        assert!(hexpire(&["hexpire", "h", "-1", "FIELDS", "1", "a"]).is_err());
+        assert!(hexpire(&["hexpire", "h", "10", "FIELDS", "2", "a"]).is_err());
+        assert!(hexpire(&["hexpire", "h", "10", "FIELDS", "0", "a"]).is_err());
+        assert!(hexpire(&["hexpire", "h", "10", "XX", "1", "a"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_field_expiration() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend
+            .hset(
+                "h".to_string(),
+                "a".to_string(),
+                BulkString::new("1").into(),
+            )
+            .unwrap();
+        let run = |args: &[&str]| -> anyhow::Result<RespFrame> {
+            let cmd = crate::Command::try_from(array(args))?;
+            Ok(cmd.execute(&backend))
+        };
+        let ints = |values: &[i64]| integers(values.to_vec());
+
+        assert_eq!(
+            run(&["hexpire", "h", "100", "FIELDS", "2", "a", "b"])?,
+            ints(&[1, -2])
+        );
+        assert_eq!(run(&["httl", "h", "FIELDS", "1", "a"])?, ints(&[100]));
+        assert_eq!(run(&["hpersist", "h", "FIELDS", "1", "a"])?, ints(&[1]));
+        assert_eq!(run(&["httl", "h", "FIELDS", "1", "a"])?, ints(&[-1]));
+        assert_eq!(
+            run(&["hpexpire", "h", "0", "FIELDS", "1", "a"])?,
+            ints(&[2])
+        );
+        assert_eq!(run(&["httl", "h", "FIELDS", "1", "a"])?, ints(&[-2]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_hgetall_keeps_insertion_order() {
+        let backend = Backend::new();
+        for field in ["b", "c", "a"] {
+            backend
+                .hset(
+                    "h".to_string(),
+                    field.to_string(),
+                    BulkString::new(field).into(),
+                )
+                .unwrap();
+        }
+        let res = HGetAll {
+            key: "h".to_string(),
+        }
+        .execute(&backend);
+        let RespFrame::Map(map) = res else {
+            panic!("HGETALL must reply with a map");
+        };
+        let fields: Vec<RespFrame> = map.keys().cloned().collect();
+        let expected: Vec<RespFrame> = ["b", "c", "a"]
+            .iter()
+            .map(|f| BulkString::new(*f).into())
+            .collect();
+        assert_eq!(fields, expected);
+    }
 }
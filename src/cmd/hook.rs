@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use crate::{Backend, RespFrame};
+
+use super::{err::CommandError, registry::CommandSpec};
+
+/// A mutating command, as seen by a [`WriteHook`].
+#[derive(Debug, Clone, Copy)]
+pub struct WriteCommand<'a> {
+    /// Metadata of the command: its name, flags and key positions.
+    pub spec: &'static CommandSpec,
+    /// The keys the command touches.
+    pub keys: &'a [String],
+    /// The arguments, the command name included.
+    pub args: &'a [RespFrame],
+}
+
+/// Code run around every command flagged [`CommandFlags::WRITE`](crate::CommandFlags::WRITE),
+/// e.g. to validate writes, audit them or fan them out to replicas.
+///
+/// Hooks are registered on the [`ServerBuilder`](crate::ServerBuilder) and run in
+/// registration order, while the keys of the command are locked: the writes of a key
+/// reach the hooks in the order they are applied. Plugins are not hooked.
+pub trait WriteHook: Send + Sync + 'static {
+    /// Called before the command runs. An error rejects the command,
+    /// it is replied to the client and the following hooks are not called.
+    fn before_write(&self, _cmd: &WriteCommand, _backend: &Backend) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    /// Called once the command ran, with its reply, which may be an error.
+    fn after_write(&self, _cmd: &WriteCommand, _reply: &RespFrame, _backend: &Backend) {}
+}
+
+/// Registered write hooks.
+#[derive(Clone, Default)]
+pub struct WriteHooks(Arc<Vec<Arc<dyn WriteHook>>>);
+
+impl WriteHooks {
+    pub(crate) fn new(hooks: Vec<Arc<dyn WriteHook>>) -> Self {
+        Self(Arc::new(hooks))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Run the command between the hooks, unless one of them rejects it.
+    pub(crate) fn run(
+        &self,
+        cmd: &WriteCommand,
+        backend: &Backend,
+        execute: impl FnOnce() -> RespFrame,
+    ) -> RespFrame {
+        for hook in self.0.iter() {
+            if let Err(e) = hook.before_write(cmd, backend) {
+                return e.into();
+            }
+        }
+        let reply = execute();
+        for hook in self.0.iter() {
+            hook.after_write(cmd, &reply, backend);
+        }
+        reply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::{lookup_command, BulkString, SimpleError};
+
+    use super::*;
+
+    /// Rejects the writes to keys starting with `ro:`, logs the others.
+    #[derive(Default)]
+    struct Audit(Mutex<Vec<String>>);
+
+    impl WriteHook for Arc<Audit> {
+        fn before_write(&self, cmd: &WriteCommand, _backend: &Backend) -> Result<(), CommandError> {
+            if cmd.keys.iter().any(|key| key.starts_with("ro:")) {
+                return Err(CommandError::InvalidCommand("read-only key".to_string()));
+            }
+            Ok(())
+        }
+
+        fn after_write(&self, cmd: &WriteCommand, reply: &RespFrame, _backend: &Backend) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{} {:?} {}", cmd.spec.name, cmd.keys, reply));
+        }
+    }
+
+    #[test]
+    fn test_write_hooks_run() {
+        let audit = Arc::new(Audit::default());
+        let hooks = WriteHooks::new(vec![Arc::new(audit.clone())]);
+        let backend = Backend::new();
+        let spec = lookup_command(b"set").unwrap();
+        let args = [BulkString::new("set").into()];
+
+        let keys = ["k".to_string()];
+        let cmd = WriteCommand {
+            spec,
+            keys: &keys,
+            args: &args,
+        };
+        assert_eq!(
+            hooks.run(&cmd, &backend, || RespFrame::Integer(1)),
+            RespFrame::Integer(1)
+        );
+
+        let keys = ["ro:k".to_string()];
+        let cmd = WriteCommand {
+            spec,
+            keys: &keys,
+            args: &args,
+        };
+        let reply = hooks.run(&cmd, &backend, || unreachable!("the write was rejected"));
+        assert_eq!(reply, SimpleError::new("ERR read-only key").into());
+        assert_eq!(*audit.0.lock().unwrap(), vec![r#"set ["k"] (integer) 1"#]);
+    }
+}
@@ -0,0 +1,505 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use bytes::BytesMut;
+use thiserror::Error;
+
+use crate::{
+    backend::{checksum_frame, checksum_of, crc64},
+    err::RespError,
+    Backend, BulkString, RespArray, RespDecode, RespFrame,
+};
+
+use super::{Command, CommandExecutor};
+
+/// Size of the reads of [`Backend::import_resp`].
+const READ_SIZE: usize = 64 * 1024;
+
+/// The first bytes of the RESP frames, no frame starts with another byte.
+const FRAME_PREFIXES: &[u8] = b"+-:$*_#,%~>";
+
+/// The commands [`Backend::dump`] serializes a key with.
+const PAYLOAD_COMMANDS: &[&str] = &[
+    "set",
+    "hset",
+    "hpexpire",
+    "sadd",
+    "zadd",
+    "rpush",
+    "bf.loadchunk",
+    "json.set",
+    "ts.create",
+    "ts.add",
+];
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("failed to read the commands: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid RESP data: {0}")]
+    Protocol(String),
+    #[error("command #{index} failed: {message}")]
+    Command { index: usize, message: String },
+    #[error("checksum mismatch at byte {offset}, expected {expected:#018x}, got {actual:#018x}")]
+    Checksum {
+        offset: u64,
+        expected: u64,
+        actual: u64,
+    },
+    #[error("truncated file, the last complete command ends at byte {offset}")]
+    Truncated { offset: u64 },
+}
+
+/// How [`Backend::import_resp_with`] deals with damaged files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImportOptions {
+    /// Keep the commands before a truncated last command rather than failing, like
+    /// aof-load-truncated. So is a checksummed file missing its last checksum.
+    /// Disabled by default.
+    pub load_truncated: bool,
+}
+
+/// What importing commands did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImportStats {
+    /// Commands executed, or checked by [`check_resp`].
+    pub commands: usize,
+    /// Blocks of commands whose checksum matched, see [`Backend::save_to`].
+    pub verified_blocks: usize,
+    /// The end of the last complete command of a truncated file loaded anyway,
+    /// see [`ImportOptions::load_truncated`].
+    pub truncated_at: Option<u64>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RestoreError {
+    #[error("BUSYKEY Target key name already exists.")]
+    BusyKey,
+    #[error("ERR DUMP payload version or checksum are wrong")]
+    InvalidPayload,
+}
+
+impl Backend {
+    /// Recreate a key from a payload written by [`Backend::dump`], possibly under
+    /// another name, like RESTORE. An existing key is only replaced if `replace`.
+    pub fn restore(&self, key: &str, payload: &[u8], replace: bool) -> Result<(), RestoreError> {
+        let commands = parse_payload(key, payload).ok_or(RestoreError::InvalidPayload)?;
+        if replace {
+            self.remove_key(key);
+        } else if self.exists(key) {
+            return Err(RestoreError::BusyKey);
+        }
+        for command in commands {
+            if self.replay(command.into()).is_err() {
+                // the key must not be left half restored.
+                self.remove_key(key);
+                return Err(RestoreError::InvalidPayload);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replay RESP encoded commands, like the ones written by [`Backend::export_resp`].
+    /// Stops at the first command which fails, returns how many were executed.
+    pub fn import_resp(&self, reader: impl Read) -> Result<usize, ImportError> {
+        self.import_resp_with(reader, ImportOptions::default())
+            .map(|stats| stats.commands)
+    }
+
+    /// Replay RESP encoded commands, verifying the checksums of the files written by
+    /// [`Backend::save_to`]. The commands of a block are executed before its checksum
+    /// is read: a mismatch stops the import with these commands applied.
+    pub fn import_resp_with(
+        &self,
+        reader: impl Read,
+        options: ImportOptions,
+    ) -> Result<ImportStats, ImportError> {
+        let scan = read_commands(reader, options, |frame, index, _| {
+            self.replay(frame)
+                .map_err(|message| ImportError::Command { index, message })
+        })?;
+        Ok(scan.stats)
+    }
+
+    fn replay(&self, frame: RespFrame) -> Result<(), String> {
+        let command = Command::try_from(frame).map_err(|e| e.to_string())?;
+        match command.execute(self) {
+            RespFrame::Error(e) => Err(e.0),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Validate RESP encoded commands without executing them: the frames, the checksums,
+/// and the names and arguments of the commands.
+pub fn check_resp(reader: impl Read, options: ImportOptions) -> Result<ImportStats, ImportError> {
+    inspect_resp(reader, options, |_, _| {})
+}
+
+/// Validate RESP encoded commands like [`check_resp`], passing every command to
+/// `inspect` with the length of its encoding before it is validated.
+pub fn inspect_resp(
+    reader: impl Read,
+    options: ImportOptions,
+    mut inspect: impl FnMut(&RespFrame, usize),
+) -> Result<ImportStats, ImportError> {
+    let scan = read_commands(reader, options, |frame, index, len| {
+        inspect(&frame, len);
+        match Command::try_from(frame) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(ImportError::Command {
+                index,
+                message: e.to_string(),
+            }),
+        }
+    })?;
+    Ok(scan.stats)
+}
+
+/// Cut a truncated file of commands after its last complete command, like
+/// redis-check-aof --fix, so that it loads without [`ImportOptions::load_truncated`].
+/// The last block of a checksummed file is sealed with the checksum of the commands
+/// kept. Returns the length the file was cut to, `None` if it wasn't truncated.
+/// Other damages are not repaired, they are returned as errors.
+pub fn repair_resp_file(path: impl AsRef<Path>) -> Result<Option<u64>, ImportError> {
+    let path = path.as_ref();
+    let options = ImportOptions {
+        load_truncated: true,
+    };
+    let scan = read_commands(BufReader::new(File::open(path)?), options, |_, _, _| Ok(()))?;
+    let Some(len) = scan.stats.truncated_at else {
+        return Ok(None);
+    };
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    file.set_len(len)?;
+    if scan.checksummed && scan.verified_len < len {
+        let mut block = Vec::new();
+        file.seek(SeekFrom::Start(scan.verified_len))?;
+        (&mut file)
+            .take(len - scan.verified_len)
+            .read_to_end(&mut block)?;
+        file.write_all(&checksum_frame(crc64(0, &block)))?;
+    }
+    file.sync_all()?;
+    Ok(Some(len))
+}
+
+/// What reading a stream of commands found.
+struct Scan {
+    stats: ImportStats,
+    /// Whether the stream has checksums.
+    checksummed: bool,
+    /// The end of the last checksum frame, the bytes before it are verified.
+    verified_len: u64,
+}
+
+/// Read the commands of `reader`, passing each one to `command` with its index
+/// and the length of its encoding.
+fn read_commands(
+    mut reader: impl Read,
+    options: ImportOptions,
+    mut command: impl FnMut(RespFrame, usize, usize) -> Result<(), ImportError>,
+) -> Result<Scan, ImportError> {
+    let mut buf = BytesMut::with_capacity(READ_SIZE);
+    let mut chunk = vec![0; READ_SIZE];
+    let mut stats = ImportStats::default();
+    // of the first byte of `buf` in the stream.
+    let mut offset = 0;
+    // of the commands since the last checksum frame.
+    let mut crc = 0;
+    // whether the stream has checksums, and commands not covered by one yet.
+    let mut checksummed = false;
+    let mut unverified = false;
+    let mut verified_len = 0;
+    loop {
+        while let Some(&prefix) = buf.first() {
+            if !FRAME_PREFIXES.contains(&prefix) {
+                return Err(protocol_error(
+                    format!("unknown frame type {:?}", prefix as char),
+                    offset,
+                ));
+            }
+            let len = match RespFrame::expect_length(&buf) {
+                Ok(len) if len <= buf.len() => len,
+                Ok(_) | Err(RespError::NotCompleted(_)) => break,
+                Err(e) => return Err(protocol_error(e, offset)),
+            };
+            let mut data = buf.split_to(len);
+            let block_crc = crc64(crc, &data);
+            let frame = RespFrame::decode(&mut data).map_err(|e| protocol_error(e, offset))?;
+            match checksum_of(&frame) {
+                Some(expected) if expected != crc => {
+                    return Err(ImportError::Checksum {
+                        offset,
+                        expected,
+                        actual: crc,
+                    })
+                }
+                Some(_) => {
+                    stats.verified_blocks += checksummed as usize;
+                    checksummed = true;
+                    unverified = false;
+                    verified_len = offset + len as u64;
+                    crc = 0;
+                }
+                None => {
+                    stats.commands += 1;
+                    command(frame, stats.commands, len)?;
+                    unverified = true;
+                    crc = block_crc;
+                }
+            }
+            offset += len as u64;
+        }
+        match reader.read(&mut chunk)? {
+            0 => break,
+            n => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+
+    let truncated = !buf.is_empty() || (checksummed && unverified);
+    if truncated {
+        if !options.load_truncated {
+            return Err(ImportError::Truncated { offset });
+        }
+        stats.truncated_at = Some(offset);
+    }
+    Ok(Scan {
+        stats,
+        checksummed,
+        verified_len,
+    })
+}
+
+fn protocol_error(e: impl std::fmt::Display, offset: u64) -> ImportError {
+    ImportError::Protocol(format!("{} at byte {}", e, offset))
+}
+
+/// The commands of the payload of a single key, renamed to `key`.
+fn parse_payload(key: &str, payload: &[u8]) -> Option<Vec<RespArray>> {
+    let mut buf = BytesMut::from(payload);
+    let mut dumped_key = None;
+    let mut commands = Vec::new();
+    while !buf.is_empty() {
+        let RespFrame::Array(RespArray(Some(mut args))) =
+            <RespFrame as crate::RespDecodeV2>::decode(&mut buf).ok()?
+        else {
+            return None;
+        };
+        let (Some(RespFrame::BulkString(name)), Some(RespFrame::BulkString(arg))) =
+            (args.first(), args.get(1))
+        else {
+            return None;
+        };
+        let name = String::from_utf8_lossy(name.as_ref()).to_ascii_lowercase();
+        if !PAYLOAD_COMMANDS.contains(&name.as_str()) {
+            return None;
+        }
+        match &dumped_key {
+            Some(dumped) if dumped != arg => return None,
+            Some(_) => {}
+            None => dumped_key = Some(arg.clone()),
+        }
+        args[1] = BulkString::new(key).into();
+        commands.push(RespArray::new(args));
+    }
+    (!commands.is_empty()).then_some(commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{Aggregation, BulkString, ExpireCondition, ListEnd, TimeSeries};
+
+    use super::*;
+
+    #[test]
+    fn test_export_import_round_trip() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("s".to_string(), b"bin\r\n\0ary".to_vec());
+        backend
+            .hset(
+                "h".to_string(),
+                "f".to_string(),
+                BulkString::new("v").into(),
+            )
+            .unwrap();
+        backend
+            .hset("h".to_string(), "g".to_string(), RespFrame::Integer(7))
+            .unwrap();
+        backend.hexpire(
+            "h",
+            &["g".to_string()],
+            Instant::now() + Duration::from_secs(60),
+            ExpireCondition::Always,
+        );
+        backend
+            .sadd("set".to_string(), [BulkString::new("m")].into())
+            .unwrap();
+        backend
+            .zadd("z", vec![(f64::NEG_INFINITY, BulkString::new("a"))])
+            .unwrap();
+        backend
+            .push(
+                "l",
+                vec![BulkString::new("x"), BulkString::new("y")],
+                ListEnd::Tail,
+            )
+            .unwrap();
+        backend.ts_create("ts", TimeSeries::new(0, vec![("k".into(), "v".into())]))?;
+        backend.ts_create("ts:avg", TimeSeries::default())?;
+        backend.ts_create_rule("ts", "ts:avg", Aggregation::Avg, 10)?;
+        for (timestamp, value) in [(1, 1.0), (2, 3.0), (11, 5.0)] {
+            backend.ts_add("ts", timestamp, value, TimeSeries::default)?;
+        }
+
+        let mut dump = Vec::new();
+        let stats = backend.export_resp(&mut dump)?;
+        assert_eq!(stats.keys, 7);
+
+        let restored = Backend::new();
+        assert!(restored.import_resp(&dump[..])? > stats.keys);
+        assert_eq!(restored.get("s"), backend.get("s"));
+        assert_eq!(restored.hget("h", "g"), Some(RespFrame::Integer(7)));
+        assert!(restored.hpttl("h", &["g".to_string()])[0] > 0);
+        assert_eq!(
+            restored.zscore("z", &BulkString::new("a")),
+            Some(f64::NEG_INFINITY)
+        );
+        assert_eq!(restored.lrange("l", 0, -1), backend.lrange("l", 0, -1));
+        assert_eq!(
+            restored.ts_range("ts:avg", 0, u64::MAX, None)?,
+            backend.ts_range("ts:avg", 0, u64::MAX, None)?
+        );
+
+        let mut again = Vec::new();
+        restored.export_resp(&mut again)?;
+        assert_eq!(again.len(), dump.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_truncated() -> anyhow::Result<()> {
+        let set = b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n";
+        let mut file = set.to_vec();
+        file.extend_from_slice(b"*3\r\n$3\r\nset\r\n$1\r\nk");
+
+        let backend = Backend::new();
+        let res = backend.import_resp_with(&file[..], ImportOptions::default());
+        assert!(
+            matches!(res, Err(ImportError::Truncated { offset }) if offset == set.len() as u64)
+        );
+
+        let options = ImportOptions {
+            load_truncated: true,
+        };
+        let stats = Backend::new().import_resp_with(&file[..], options)?;
+        assert_eq!(stats.commands, 1);
+        assert_eq!(stats.truncated_at, Some(set.len() as u64));
+        let stats = Backend::new().import_resp_with(&set[..], options)?;
+        assert_eq!(stats.truncated_at, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_resp() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("s".to_string(), b"v".to_vec());
+        let path = std::env::temp_dir().join(format!("rredis-check-{}", std::process::id()));
+        backend.save_to(&path)?;
+        let snapshot = std::fs::read(&path)?;
+        std::fs::remove_file(&path)?;
+
+        let stats = check_resp(&snapshot[..], ImportOptions::default())?;
+        assert_eq!(stats.commands, 1);
+        assert_eq!(stats.verified_blocks, 1);
+
+        let res = check_resp(&b"*1\r\n$4\r\nnope\r\n"[..], ImportOptions::default());
+        assert!(matches!(res, Err(ImportError::Command { index: 1, .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_repair_resp_file() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("a".to_string(), b"1".to_vec());
+        backend.set("b".to_string(), b"2".to_vec());
+        let path = std::env::temp_dir().join(format!("rredis-repair-{}", std::process::id()));
+        backend.save_to(&path)?;
+        let snapshot = std::fs::read(&path)?;
+        assert_eq!(repair_resp_file(&path)?, None);
+
+        // the last checksum is missing, then also the end of the last key written.
+        let checksum_len = b"+CRC64 0123456789abcdef\r\n".len();
+        let end = snapshot.len() - checksum_len;
+        for (len, keys) in [(end, 2), (end - 2, 1)] {
+            std::fs::write(&path, &snapshot[..len])?;
+            let cut = repair_resp_file(&path)?.unwrap();
+            assert!(cut <= len as u64);
+
+            let restored = Backend::new();
+            restored.load_from(&path)?;
+            let kept = ["a", "b"].iter().filter(|key| restored.exists(key));
+            assert_eq!(kept.count(), keys);
+            assert_eq!(std::fs::metadata(&path)?.len(), cut + checksum_len as u64);
+        }
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_restore() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend
+            .push(
+                "l",
+                vec![BulkString::new("x"), BulkString::new("y")],
+                ListEnd::Tail,
+            )
+            .unwrap();
+        let payload = backend.dump("l").unwrap();
+        assert_eq!(backend.dump("missing"), None);
+
+        backend.restore("copy", &payload, false)?;
+        assert_eq!(backend.lrange("copy", 0, -1), backend.lrange("l", 0, -1));
+        assert_eq!(
+            backend.restore("copy", &payload, false),
+            Err(RestoreError::BusyKey)
+        );
+        backend.set("s".to_string(), b"v".to_vec());
+        backend.restore("s", &payload, true)?;
+        assert_eq!(backend.get("s"), None);
+        assert_eq!(backend.lrange("s", 0, -1), backend.lrange("l", 0, -1));
+
+        // only the commands of a dumped key, on a single key, are accepted.
+        for payload in [
+            &b"*1\r\n$5\r\nflush\r\n"[..],
+            b"*2\r\n$3\r\ndel\r\n$1\r\nk\r\n",
+            b"*3\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\n1\r\n*3\r\n$3\r\nset\r\n$1\r\nb\r\n$1\r\n1\r\n",
+            b"*3\r\n$3\r\nset\r\n$1\r\na",
+            b"",
+        ] {
+            assert_eq!(
+                backend.restore("k", payload, true),
+                Err(RestoreError::InvalidPayload)
+            );
+        }
+        assert!(!backend.exists("k"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let backend = Backend::new();
+        let res = backend.import_resp(&b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n*1\r\n$4\r\nnope\r\n"[..]);
+        assert!(matches!(res, Err(ImportError::Command { index: 2, .. })));
+        let res = backend.import_resp(&b"*2\r\n$3\r\nget\r\n"[..]);
+        assert!(matches!(res, Err(ImportError::Truncated { offset: 0 })));
+        let res = backend.import_resp(&b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n?"[..]);
+        assert!(matches!(res, Err(ImportError::Protocol(_))));
+    }
+}
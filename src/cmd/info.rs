@@ -0,0 +1,199 @@
+use std::fmt::Write;
+
+use crate::{version, Backend, BulkString, RespArray, RespFrame};
+
+use super::{extract_args, extract_string, CommandError, CommandExecutor, Info};
+
+/// Sections of INFO, in the order they are rendered.
+const SECTIONS: &[&str] = &["server", "memory", "stats", "keyspace"];
+
+impl CommandExecutor for Info {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let sections: Vec<String> = SECTIONS
+            .iter()
+            .filter(|name| self.includes(name))
+            .map(|name| match *name {
+                "server" => server_section(backend),
+                "memory" => memory_section(backend),
+                "stats" => stats_section(backend),
+                "keyspace" => keyspace_section(backend),
+                _ => unreachable!("INFO section {} is not rendered", name),
+            })
+            .collect();
+        BulkString::new(sections.join("\r\n")).into()
+    }
+}
+
+impl Info {
+    fn includes(&self, section: &str) -> bool {
+        self.sections.is_empty()
+            || self
+                .sections
+                .iter()
+                .any(|s| matches!(s.as_str(), "all" | "default" | "everything") || s == section)
+    }
+}
+
+/// A section: its title, then a `field:value` line per field.
+fn render(title: &str, fields: &[(&str, String)]) -> String {
+    let mut section = format!("# {}\r\n", title);
+    for (field, value) in fields {
+        let _ = write!(section, "{}:{}\r\n", field, value);
+    }
+    section
+}
+
+fn server_section(backend: &Backend) -> String {
+    let mode = match backend.cluster() {
+        Some(_) => "cluster",
+        None => "standalone",
+    };
+    let fields = [
+        ("rredis_version", version::VERSION.to_string()),
+        ("rredis_git_sha1", version::GIT_SHA.to_string()),
+        ("rredis_git_dirty", u8::from(version::GIT_DIRTY).to_string()),
+        ("rredis_build_features", version::features().join(",")),
+        ("redis_mode", mode.to_string()),
+        (
+            "os",
+            format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+        ),
+        ("arch_bits", usize::BITS.to_string()),
+        ("mem_allocator", version::allocator().to_string()),
+        ("process_id", std::process::id().to_string()),
+    ];
+    render("Server", &fields)
+}
+
+fn memory_section(backend: &Backend) -> String {
+    let stats = backend.memory_stats();
+    let mut fields = vec![("used_memory_dataset", stats.dataset_bytes.to_string())];
+    if let Some(allocator) = stats.allocator {
+        fields.push(("allocator_allocated", allocator.allocated.to_string()));
+        fields.push(("allocator_resident", allocator.resident.to_string()));
+        fields.push((
+            "allocator_frag_ratio",
+            format!("{:.2}", allocator.fragmentation_ratio()),
+        ));
+    }
+    fields.push((
+        "shared_integer_hits",
+        stats.sharing.integer_hits.to_string(),
+    ));
+    fields.push(("shared_value_hits", stats.sharing.value_hits.to_string()));
+    render("Memory", &fields)
+}
+
+fn stats_section(backend: &Backend) -> String {
+    let stats = backend.stats();
+    let fields = [
+        (
+            "rejected_connections",
+            stats.rejected_connections.to_string(),
+        ),
+        ("expired_keys", stats.expired_keys.to_string()),
+        ("expired_subkeys", stats.expired_subkeys.to_string()),
+        (
+            "expired_stale_perc",
+            format!("{:.2}", stats.expired_stale_perc),
+        ),
+        ("evicted_keys", stats.evicted_keys.to_string()),
+    ];
+    render("Stats", &fields)
+}
+
+/// A line per database holding keys, like `db0:keys=2,expires=0,avg_ttl=0,subexpiry=0`.
+/// There is a single database.
+fn keyspace_section(backend: &Backend) -> String {
+    let stats = backend.keyspace_stats();
+    let mut fields = vec![];
+    if stats.keys > 0 {
+        fields.push((
+            "db0",
+            format!(
+                "keys={},expires={},avg_ttl={},subexpiry={}",
+                stats.keys, stats.expires, stats.avg_ttl, stats.subexpiry
+            ),
+        ));
+    }
+    render("Keyspace", &fields)
+}
+
+impl TryFrom<RespArray> for Info {
+    type Error = CommandError;
+
+    // info [section [section ...]]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let sections = extract_args(value, 1)?
+            .into_iter()
+            .map(|arg| extract_string(arg).map(|s| s.to_ascii_lowercase()))
+            .collect::<Result<_, _>>()?;
+        Ok(Info { sections })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::array;
+
+    fn info(args: &[&str]) -> anyhow::Result<String> {
+        let backend = Backend::new();
+        backend.set("a".to_string(), b"1".to_vec());
+        backend.set("b".to_string(), b"1".to_vec());
+        match Info::try_from(array(args))?.execute(&backend) {
+            RespFrame::BulkString(s) => Ok(String::from_utf8(s.as_ref().to_vec())?),
+            frame => anyhow::bail!("unexpected reply {:?}", frame),
+        }
+    }
+
+    #[test]
+    fn test_info_memory() -> anyhow::Result<()> {
+        let memory = info(&["info", "MEMORY"])?;
+        assert!(memory.starts_with("# Memory\r\n"));
+        assert!(memory.contains("\r\nshared_integer_hits:2\r\n"));
+        assert!(info(&["info"])?.contains(&memory));
+        assert_eq!(info(&["info", "clients"])?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_stats() -> anyhow::Result<()> {
+        let stats = info(&["info", "stats"])?;
+        assert_eq!(
+            stats,
+            "# Stats\r\nrejected_connections:0\r\nexpired_keys:0\r\nexpired_subkeys:0\r\n\
+             expired_stale_perc:0.00\r\nevicted_keys:0\r\n"
+        );
+        assert!(info(&["info"])?.contains(&stats));
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_keyspace() -> anyhow::Result<()> {
+        let keyspace = info(&["info", "keyspace"])?;
+        assert_eq!(
+            keyspace,
+            "# Keyspace\r\ndb0:keys=2,expires=0,avg_ttl=0,subexpiry=0\r\n"
+        );
+        assert!(info(&["info"])?.ends_with(&keyspace));
+
+        let empty = Info::try_from(RespArray::new(vec![BulkString::new("info").into()]))?;
+        match empty.execute(&Backend::new()) {
+            RespFrame::BulkString(s) => assert!(s.as_ref().ends_with(b"# Keyspace\r\n")),
+            frame => anyhow::bail!("unexpected reply {:?}", frame),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_server() -> anyhow::Result<()> {
+        let server = info(&["info", "server"])?;
+        assert!(server.starts_with("# Server\r\n"));
+        let version = format!("\r\nrredis_version:{}\r\n", version::VERSION);
+        assert!(server.contains(&version));
+        assert!(server.contains("\r\nredis_mode:standalone\r\n"));
+        assert!(info(&["info"])?.starts_with(&server));
+        Ok(())
+    }
+}
@@ -0,0 +1,432 @@
+use serde_json::{Map, Value};
+
+use crate::{Backend, BulkString, RespArray, RespFrame};
+
+use super::{
+    extract_args, extract_string, CommandError, CommandExecutor, JsonDel, JsonGet, JsonSet, RESP_OK,
+};
+
+/// A path into a JSON document, a subset of JSONPath without wildcards, filters or slices:
+/// the root `$` followed by `.member`, `["member"]` or `[index]` segments,
+/// negative indices counting from the end of the array.
+///
+/// Like RedisJSON, paths which don't start with `$` are legacy paths: `.` is the root
+/// and the leading dot may be omitted. A JSONPath replies with the array of the values
+/// it matches, a legacy path with the value itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+    legacy: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Member(String),
+    Index(i64),
+}
+
+/// When JSON.SET may write the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonSetCondition {
+    Always,
+    /// Only if the path doesn't exist.
+    Nx,
+    /// Only if the path exists.
+    Xx,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self, CommandError> {
+        let invalid = || CommandError::InvalidArgument(format!("invalid JSON path '{}'", path));
+        let (legacy, rest) = match path.strip_prefix('$') {
+            Some(rest) => (false, rest.to_string()),
+            None if path == "." => (true, String::new()),
+            None if path.starts_with(['.', '[']) => (true, path.to_string()),
+            None => (true, format!(".{}", path)),
+        };
+
+        let mut segments = Vec::new();
+        let mut rest = rest.as_str();
+        while !rest.is_empty() {
+            if let Some(r) = rest.strip_prefix('.') {
+                let end = r.find(['.', '[']).unwrap_or(r.len());
+                if end == 0 {
+                    return Err(invalid());
+                }
+                segments.push(Segment::Member(r[..end].to_string()));
+                rest = &r[end..];
+            } else if let Some(r) = rest.strip_prefix('[') {
+                let end = r.find(']').ok_or_else(invalid)?;
+                let inner = r[..end].trim();
+                let quoted = ['"', '\'']
+                    .iter()
+                    .find_map(|q| inner.strip_prefix(*q)?.strip_suffix(*q));
+                let segment = match quoted {
+                    Some(member) => Segment::Member(member.to_string()),
+                    None => Segment::Index(inner.parse().map_err(|_| invalid())?),
+                };
+                segments.push(segment);
+                rest = &r[end + 1..];
+            } else {
+                return Err(invalid());
+            }
+        }
+        Ok(JsonPath { segments, legacy })
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn get<'a>(&self, root: &'a Value) -> Option<&'a Value> {
+        self.segments
+            .iter()
+            .try_fold(root, |value, segment| match segment {
+                Segment::Member(member) => value.as_object()?.get(member),
+                Segment::Index(index) => {
+                    let array = value.as_array()?;
+                    array.get(resolve_index(*index, array.len())?)
+                }
+            })
+    }
+
+    fn get_mut<'a>(segments: &[Segment], root: &'a mut Value) -> Option<&'a mut Value> {
+        segments
+            .iter()
+            .try_fold(root, |value, segment| match segment {
+                Segment::Member(member) => value.as_object_mut()?.get_mut(member),
+                Segment::Index(index) => {
+                    let array = value.as_array_mut()?;
+                    let index = resolve_index(*index, array.len())?;
+                    array.get_mut(index)
+                }
+            })
+    }
+
+    /// Write the value at the path. A missing member is added to its object,
+    /// but the parent of the path must exist, and array indices must be in range.
+    pub fn set(&self, root: &mut Value, new: Value) -> bool {
+        let Some((last, parent)) = self.segments.split_last() else {
+            *root = new;
+            return true;
+        };
+        let Some(parent) = Self::get_mut(parent, root) else {
+            return false;
+        };
+        match (last, parent) {
+            (Segment::Member(member), Value::Object(object)) => {
+                object.insert(member.clone(), new);
+                true
+            }
+            (Segment::Index(index), Value::Array(array)) => {
+                match resolve_index(*index, array.len()) {
+                    Some(index) => {
+                        array[index] = new;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove the value at the path, which must not be the root.
+    pub fn delete(&self, root: &mut Value) -> bool {
+        let Some((last, parent)) = self.segments.split_last() else {
+            return false;
+        };
+        match (last, Self::get_mut(parent, root)) {
+            (Segment::Member(member), Some(Value::Object(object))) => {
+                object.remove(member).is_some()
+            }
+            (Segment::Index(index), Some(Value::Array(array))) => {
+                match resolve_index(*index, array.len()) {
+                    Some(index) => {
+                        array.remove(index);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+impl CommandExecutor for JsonSet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let res = backend.json_update(&self.key, |doc| {
+            let exists = doc.as_ref().and_then(|d| self.path.get(d)).is_some();
+            match (self.condition, exists) {
+                (JsonSetCondition::Nx, true) | (JsonSetCondition::Xx, false) => return Ok(false),
+                _ => {}
+            }
+            match doc {
+                Some(doc) => Ok(self.path.set(doc, self.value)),
+                None if self.path.is_root() => {
+                    *doc = Some(self.value);
+                    Ok(true)
+                }
+                None => Err(CommandError::InvalidArgument(
+                    "new objects must be created at the root".to_string(),
+                )),
+            }
+        });
+        match res.unwrap_or_else(|e| Err(e.into())) {
+            Ok(true) => RESP_OK.clone(),
+            Ok(false) => BulkString::null().into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for JsonGet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let Some(doc) = backend.json_get(&self.key) else {
+            return BulkString::null().into();
+        };
+        let res = match self.paths.as_slice() {
+            [] => Ok(doc),
+            [path] => select(&doc, path),
+            paths => {
+                // every path is reported as a JSONPath as soon as one of them is.
+                let legacy = paths.iter().all(|(_, path)| path.legacy);
+                paths
+                    .iter()
+                    .map(|(text, path)| {
+                        let value = if legacy {
+                            select(&doc, &(text.clone(), path.clone()))?
+                        } else {
+                            Value::Array(path.get(&doc).cloned().into_iter().collect())
+                        };
+                        Ok((text.clone(), value))
+                    })
+                    .collect::<Result<Map<_, _>, CommandError>>()
+                    .map(Value::Object)
+            }
+        };
+        match res {
+            Ok(value) => BulkString::new(value.to_string()).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+/// The reply to a single path: the array of matches of a JSONPath,
+/// the value of a legacy path, which must exist.
+fn select(doc: &Value, (text, path): &(String, JsonPath)) -> Result<Value, CommandError> {
+    let value = path.get(doc).cloned();
+    if !path.legacy {
+        return Ok(Value::Array(value.into_iter().collect()));
+    }
+    value.ok_or_else(|| CommandError::InvalidArgument(format!("Path '{}' does not exist", text)))
+}
+
+impl CommandExecutor for JsonDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let deleted = backend.json_update(&self.key, |doc| match doc {
+            None => false,
+            Some(_) if self.path.is_root() => {
+                *doc = None;
+                true
+            }
+            Some(doc) => self.path.delete(doc),
+        });
+        match deleted {
+            Ok(deleted) => RespFrame::Integer(deleted as i64),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for JsonSet {
+    type Error = CommandError;
+
+    // json.set key path value [NX | XX]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if !(4..=5).contains(&value.len()) {
+            return Err(CommandError::WrongArity("json.set".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let path = JsonPath::parse(&extract_string(args.next().unwrap())?)?;
+        let value = serde_json::from_str(&extract_string(args.next().unwrap())?)
+            .map_err(|e| CommandError::InvalidArgument(e.to_string()))?;
+        let condition = match args.next().map(extract_string).transpose()? {
+            None => JsonSetCondition::Always,
+            Some(arg) if arg.eq_ignore_ascii_case("nx") => JsonSetCondition::Nx,
+            Some(arg) if arg.eq_ignore_ascii_case("xx") => JsonSetCondition::Xx,
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(JsonSet {
+            key,
+            path,
+            value,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for JsonGet {
+    type Error = CommandError;
+
+    // json.get key [path [path ...]]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 2 {
+            return Err(CommandError::WrongArity("json.get".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let paths = args
+            .map(|arg| {
+                let text = extract_string(arg)?;
+                let path = JsonPath::parse(&text)?;
+                Ok((text, path))
+            })
+            .collect::<Result<_, CommandError>>()?;
+        Ok(JsonGet { key, paths })
+    }
+}
+
+impl TryFrom<RespArray> for JsonDel {
+    type Error = CommandError;
+
+    // json.del key [path]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if !(2..=3).contains(&value.len()) {
+            return Err(CommandError::WrongArity("json.del".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let path = match args.next() {
+            Some(arg) => JsonPath::parse(&extract_string(arg)?)?,
+            None => JsonPath::parse("$")?,
+        };
+        Ok(JsonDel { key, path })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::SimpleError;
+
+    use super::*;
+    use crate::cmd::array;
+
+    fn run(backend: &Backend, args: &[&str]) -> anyhow::Result<RespFrame> {
+        let request = array(args);
+        let res = match args[0] {
+            "json.set" => JsonSet::try_from(request)?.execute(backend),
+            "json.get" => JsonGet::try_from(request)?.execute(backend),
+            _ => JsonDel::try_from(request)?.execute(backend),
+        };
+        Ok(res)
+    }
+
+    #[test]
+    fn test_json_path_parse() -> anyhow::Result<()> {
+        let path = JsonPath::parse("$.a[\"b c\"][-1].d")?;
+        assert!(!path.legacy);
+        assert_eq!(
+            path.segments,
+            vec![
+                Segment::Member("a".to_string()),
+                Segment::Member("b c".to_string()),
+                Segment::Index(-1),
+                Segment::Member("d".to_string()),
+            ]
+        );
+        assert!(JsonPath::parse("$")?.is_root());
+        assert!(JsonPath::parse(".")?.is_root());
+        assert_eq!(JsonPath::parse("a.b")?, JsonPath::parse(".a.b")?);
+        assert!(JsonPath::parse("a.b")?.legacy);
+
+        for invalid in ["$..a", "$[x]", "$[0", "$a"] {
+            assert!(JsonPath::parse(invalid).is_err(), "{}", invalid);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_path_get_set_delete() -> anyhow::Result<()> {
+        let mut doc = json!({"a": [1, {"b": 2}]});
+        assert_eq!(JsonPath::parse("$.a[-1].b")?.get(&doc), Some(&json!(2)));
+        assert_eq!(JsonPath::parse("$.a[2]")?.get(&doc), None);
+
+        assert!(JsonPath::parse("$.a[0]")?.set(&mut doc, json!("x")));
+        assert!(JsonPath::parse("$.c")?.set(&mut doc, json!(true)));
+        assert!(!JsonPath::parse("$.d.e")?.set(&mut doc, json!(1)));
+        assert!(!JsonPath::parse("$.a[5]")?.set(&mut doc, json!(1)));
+        assert_eq!(doc, json!({"a": ["x", {"b": 2}], "c": true}));
+
+        assert!(JsonPath::parse("$.a[0]")?.delete(&mut doc));
+        assert!(!JsonPath::parse("$.missing")?.delete(&mut doc));
+        assert_eq!(doc, json!({"a": [{"b": 2}], "c": true}));
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_commands() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let bulk = |s: &str| RespFrame::from(BulkString::new(s));
+
+        assert_eq!(
+            run(&backend, &["json.set", "doc", "$.a", "1"])?,
+            SimpleError::new("ERR new objects must be created at the root").into()
+        );
+        assert_eq!(
+            run(&backend, &["json.set", "doc", "$", r#"{"a":{"b":[1,2]}}"#])?,
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            run(&backend, &["json.set", "doc", "$.a", "null", "NX"])?,
+            BulkString::null().into()
+        );
+        assert_eq!(
+            run(&backend, &["json.set", "doc", "$.a.c", "\"x\"", "NX"])?,
+            RESP_OK.clone()
+        );
+
+        assert_eq!(
+            run(&backend, &["json.get", "doc", "$.a.b[1]"])?,
+            bulk("[2]")
+        );
+        assert_eq!(run(&backend, &["json.get", "doc", "$.x"])?, bulk("[]"));
+        assert_eq!(run(&backend, &["json.get", "doc", ".a.c"])?, bulk("\"x\""));
+        assert_eq!(
+            run(&backend, &["json.get", "doc", ".x"])?,
+            SimpleError::new("ERR Path '.x' does not exist").into()
+        );
+        assert_eq!(
+            run(&backend, &["json.get", "doc", "$.a.c", ".a.b"])?,
+            bulk(r#"{"$.a.c":["x"],".a.b":[[1,2]]}"#)
+        );
+        assert_eq!(
+            run(&backend, &["json.get", "missing"])?,
+            BulkString::null().into()
+        );
+
+        assert_eq!(
+            run(&backend, &["json.del", "doc", "$.a.b"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["json.get", "doc"])?,
+            bulk(r#"{"a":{"c":"x"}}"#)
+        );
+        assert_eq!(run(&backend, &["json.del", "doc"])?, RespFrame::Integer(1));
+        assert_eq!(run(&backend, &["json.del", "doc"])?, RespFrame::Integer(0));
+        assert!(backend.json_get("doc").is_none());
+
+        assert!(run(&backend, &["json.set", "doc", "$", "{oops"]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{Backend, BulkString, RespArray, RespFrame};
+
+use super::{
+    extract_args, extract_integer, extract_string, validate_command, CommandError, CommandExecutor,
+    ObjectEncoding, Rename, Scan, RESP_OK,
+};
+
+/// Keys a SCAN step returns at least, by default.
+const DEFAULT_SCAN_COUNT: usize = 10;
+
+impl CommandExecutor for Rename {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.rename(&self.src, &self.dst) {
+            RESP_OK.clone()
+        } else {
+            CommandError::InvalidArgument("no such key".to_string()).into()
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Rename {
+    type Error = CommandError;
+
+    // rename key newkey
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "rename", 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Rename {
+            src: extract_string(args.next().unwrap())?,
+            dst: extract_string(args.next().unwrap())?,
+        })
+    }
+}
+
+impl CommandExecutor for Scan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, keys) = backend.scan(self.cursor, self.count, self.pattern.as_deref());
+        let keys: Vec<RespFrame> = keys
+            .into_iter()
+            .map(|k| BulkString::new(k).into())
+            .collect();
+        RespArray::new(vec![
+            BulkString::new(cursor.to_string()).into(),
+            RespArray::new(keys).into(),
+        ])
+        .into()
+    }
+}
+
+impl TryFrom<RespArray> for Scan {
+    type Error = CommandError;
+
+    // scan cursor [MATCH pattern] [COUNT count]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let cursor = match args.next() {
+            Some(cursor) => extract_string(cursor)?
+                .parse()
+                .map_err(|_| CommandError::InvalidArgument("invalid cursor".to_string()))?,
+            None => return Err(CommandError::WrongArity("scan".to_string())),
+        };
+        let mut scan = Scan {
+            cursor,
+            pattern: None,
+            count: DEFAULT_SCAN_COUNT,
+        };
+        let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or_else(syntax_error)?;
+            match extract_string(arg)?.to_ascii_lowercase().as_str() {
+                "match" => scan.pattern = Some(extract_string(value)?),
+                "count" => match extract_integer(value)? {
+                    count if count >= 1 => scan.count = count as usize,
+                    _ => return Err(syntax_error()),
+                },
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(scan)
+    }
+}
+
+impl CommandExecutor for ObjectEncoding {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.object_encoding(&self.key) {
+            Some(encoding) => BulkString::new(encoding.as_str()).into(),
+            None => BulkString::null().into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ObjectEncoding {
+    type Error = CommandError;
+
+    // object encoding key
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(arg) => extract_string(arg)?,
+            None => return Err(CommandError::WrongArity("object".to_string())),
+        };
+        if !subcommand.eq_ignore_ascii_case("encoding") {
+            return Err(CommandError::InvalidArgument(format!(
+                "unknown subcommand '{}'",
+                subcommand
+            )));
+        }
+        match (args.next(), args.next()) {
+            (Some(key), None) => Ok(ObjectEncoding {
+                key: extract_string(key)?,
+            }),
+            _ => Err(CommandError::WrongArity("object|encoding".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, SimpleError};
+
+    use super::*;
+    use crate::cmd::array;
+
+    fn scan(args: &[&str]) -> Result<Scan, CommandError> {
+        let frames: Vec<RespFrame> = std::iter::once("scan")
+            .chain(args.iter().copied())
+            .map(|a| BulkString::new(a).into())
+            .collect();
+        Scan::try_from(RespArray::new(frames))
+    }
+
+    #[test]
+    fn test_scan_from_resp_array() -> anyhow::Result<()> {
+        let parsed = scan(&["0"])?;
+        assert_eq!((parsed.cursor, parsed.count), (0, DEFAULT_SCAN_COUNT));
+        let parsed = scan(&["12", "match", "k*", "COUNT", "100"])?;
+        assert_eq!(parsed.cursor, 12);
+        assert_eq!(parsed.pattern.as_deref(), Some("k*"));
+        assert_eq!(parsed.count, 100);
+
+        assert!(scan(&[]).is_err());
+        assert!(scan(&["-1"]).is_err());
+        assert!(scan(&["0", "count", "0"]).is_err());
+        assert!(scan(&["0", "count"]).is_err());
+        assert!(scan(&["0", "type", "string"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_scan() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("k".to_string(), b"v".to_vec());
+        backend
+            .push("l", vec![BulkString::new("x")], crate::ListEnd::Tail)
+            .unwrap();
+        let res = scan(&["0", "MATCH", "k*"])?.execute(&backend);
+        assert_eq!(
+            res,
+            RespArray::new(vec![
+                BulkString::new("0").into(),
+                RespArray::new(vec![BulkString::new("k").into()]).into(),
+            ])
+            .into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_rename() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let rename = |src: &str, dst: &str| -> anyhow::Result<RespFrame> {
+            let frames: Vec<RespFrame> = ["rename", src, dst]
+                .iter()
+                .map(|a| BulkString::new(*a).into())
+                .collect();
+            Ok(Rename::try_from(RespArray::new(frames))?.execute(&backend))
+        };
+
+        assert_eq!(
+            rename("a", "b")?,
+            SimpleError::new("ERR no such key").into()
+        );
+        backend.set("a".to_string(), b"1".to_vec());
+        assert_eq!(rename("a", "b")?, RESP_OK.clone());
+        assert_eq!(backend.get("b"), Some("1".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_object_encoding() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let object = |args: &[&str]| -> Result<ObjectEncoding, CommandError> {
+            ObjectEncoding::try_from(array(args))
+        };
+
+        backend.set("n".to_string(), b"10".to_vec());
+        let res = object(&["object", "ENCODING", "n"])?.execute(&backend);
+        assert_eq!(res, BulkString::new("int").into());
+        let res = object(&["object", "encoding", "missing"])?.execute(&backend);
+        assert_eq!(res, BulkString::null().into());
+        assert!(object(&["object", "encoding"]).is_err());
+        assert!(object(&["object", "freq", "n"]).is_err());
+        Ok(())
+    }
+}
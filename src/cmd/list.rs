@@ -0,0 +1,339 @@
+use crate::{Backend, BulkString, ListEnd, RespArray, RespFrame, RespNull};
+
+use super::{
+    extract_args, extract_integer, extract_multi_pop, extract_string, extract_timeout,
+    validate_command, CommandError, CommandExecutor, LIndex, LInsert, LLen, LMPop, LPop, LPush,
+    LRange, LRem,
+};
+
+impl CommandExecutor for LPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.push(&self.key, self.values, self.end) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for LPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let popped = backend.pop(&self.key, self.count.unwrap_or(1), self.end);
+        match (popped, self.count) {
+            (None, None) => RespFrame::Null(RespNull),
+            (None, Some(_)) => RespArray::null().into(),
+            (Some(mut values), None) => match values.pop() {
+                Some(value) => value.into(),
+                None => RespFrame::Null(RespNull),
+            },
+            (Some(values), Some(_)) => bulk_array(values),
+        }
+    }
+}
+
+impl CommandExecutor for LMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lmpop(&self.keys, self.count, self.end) {
+            Ok(Some((key, values))) => {
+                RespArray::new(vec![BulkString::new(key).into(), bulk_array(values)]).into()
+            }
+            Ok(None) => RespArray::null().into(),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for LLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.llen(&self.key) as i64)
+    }
+}
+
+impl CommandExecutor for LRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        bulk_array(backend.lrange(&self.key, self.start, self.stop))
+    }
+}
+
+impl CommandExecutor for LIndex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lindex(&self.key, self.index) {
+            Some(value) => value.into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for LInsert {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.linsert(&self.key, &self.pivot, self.value, self.before))
+    }
+}
+
+impl CommandExecutor for LRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.lrem(&self.key, self.count, &self.value) as i64)
+    }
+}
+
+fn bulk_array(values: Vec<BulkString>) -> RespFrame {
+    RespArray::new(values.into_iter().map(RespFrame::from).collect::<Vec<_>>()).into()
+}
+
+impl TryFrom<RespArray> for LPush {
+    type Error = CommandError;
+
+    // lpush|rpush key element [element ...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let end = list_end(&value);
+        if value.len() < 3 {
+            return Err(CommandError::WrongArity(command_name(end, "push")));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let values = args.map(bulk_arg).collect::<Result<_, _>>()?;
+        Ok(LPush { key, values, end })
+    }
+}
+
+impl TryFrom<RespArray> for LPop {
+    type Error = CommandError;
+
+    // lpop|rpop key [count]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let end = list_end(&value);
+        if value.len() != 2 && value.len() != 3 {
+            return Err(CommandError::WrongArity(command_name(end, "pop")));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let count = match args.next() {
+            Some(count) => Some(usize::try_from(extract_integer(count)?).map_err(|_| {
+                CommandError::InvalidArgument("value is out of range, must be positive".into())
+            })?),
+            None => None,
+        };
+        Ok(LPop { key, count, end })
+    }
+}
+
+impl TryFrom<RespArray> for LMPop {
+    type Error = CommandError;
+
+    // lmpop numkeys key [key ...] LEFT | RIGHT [COUNT count]
+    // blmpop timeout numkeys key [key ...] LEFT | RIGHT [COUNT count]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(name)) if name.as_ref().eq_ignore_ascii_case(b"blmpop") => {
+                "blmpop"
+            }
+            _ => "lmpop",
+        };
+        let blocking = name == "blmpop";
+        if value.len() < 4 + blocking as usize {
+            return Err(CommandError::WrongArity(name.to_string()));
+        }
+        let mut args = extract_args(value, 1)?;
+        if blocking {
+            // the connection waits, the command itself never does.
+            extract_timeout(args.remove(0))?;
+        }
+        let (keys, from, count) = extract_multi_pop(args)?;
+        let end = match from.as_str() {
+            "LEFT" => ListEnd::Head,
+            "RIGHT" => ListEnd::Tail,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(LMPop { keys, end, count })
+    }
+}
+
+impl TryFrom<RespArray> for LLen {
+    type Error = CommandError;
+
+    // llen key
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "llen", 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LLen {
+            key: extract_string(args.next().unwrap())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LRange {
+    type Error = CommandError;
+
+    // lrange key start stop
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "lrange", 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LRange {
+            key: extract_string(args.next().unwrap())?,
+            start: extract_integer(args.next().unwrap())?,
+            stop: extract_integer(args.next().unwrap())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LIndex {
+    type Error = CommandError;
+
+    // lindex key index
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "lindex", 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LIndex {
+            key: extract_string(args.next().unwrap())?,
+            index: extract_integer(args.next().unwrap())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LInsert {
+    type Error = CommandError;
+
+    // linsert key BEFORE|AFTER pivot element
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "linsert", 4)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let before = match extract_string(args.next().unwrap())?
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "before" => true,
+            "after" => false,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(LInsert {
+            key,
+            before,
+            pivot: bulk_arg(args.next().unwrap())?,
+            value: bulk_arg(args.next().unwrap())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LRem {
+    type Error = CommandError;
+
+    // lrem key count element
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "lrem", 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LRem {
+            key: extract_string(args.next().unwrap())?,
+            count: extract_integer(args.next().unwrap())?,
+            value: bulk_arg(args.next().unwrap())?,
+        })
+    }
+}
+
+/// The end a push or pop command works on, from the first letter of its name.
+fn list_end(value: &RespArray) -> ListEnd {
+    match value.first() {
+        Some(RespFrame::BulkString(name)) if name.as_ref()[..1].eq_ignore_ascii_case(b"r") => {
+            ListEnd::Tail
+        }
+        _ => ListEnd::Head,
+    }
+}
+
+fn command_name(end: ListEnd, op: &str) -> String {
+    match end {
+        ListEnd::Head => format!("l{}", op),
+        ListEnd::Tail => format!("r{}", op),
+    }
+}
+
+fn bulk_arg(frame: RespFrame) -> Result<BulkString, CommandError> {
+    match frame {
+        RespFrame::BulkString(value) => Ok(value),
+        _ => Err(CommandError::InvalidArgument(
+            "Argument must be a bulk string".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::array;
+
+    #[test]
+    fn test_push_pop_from_resp_array() -> anyhow::Result<()> {
+        let push = LPush::try_from(array(&["RPUSH", "l", "a", "b"]))?;
+        assert_eq!(push.end, ListEnd::Tail);
+        assert_eq!(push.values.len(), 2);
+        let res = LPush::try_from(array(&["lpush", "l"]));
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "ERR wrong number of arguments for 'lpush' command"
+        );
+        let pop = LPop::try_from(array(&["lpop", "l", "3"]))?;
+        assert_eq!((pop.end, pop.count), (ListEnd::Head, Some(3)));
+        assert!(LPop::try_from(array(&["rpop", "l", "-1"])).is_err());
+        assert!(LInsert::try_from(array(&["linsert", "l", "middle", "a", "b"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmpop_from_resp_array() -> anyhow::Result<()> {
+        let pop = LMPop::try_from(array(&["lmpop", "2", "a", "b", "right", "COUNT", "3"]))?;
+        assert_eq!(pop.keys, vec!["a", "b"]);
+        assert_eq!((pop.end, pop.count), (ListEnd::Tail, 3));
+        let pop = LMPop::try_from(array(&["BLMPOP", "0.5", "1", "a", "LEFT"]))?;
+        assert_eq!((pop.keys.len(), pop.end, pop.count), (1, ListEnd::Head, 1));
+
+        let err = |args: &[&str]| LMPop::try_from(array(args)).unwrap_err().to_string();
+        assert_eq!(
+            err(&["blmpop", "0", "1", "a"]),
+            "ERR wrong number of arguments for 'blmpop' command"
+        );
+        assert_eq!(
+            err(&["lmpop", "-1", "a", "LEFT"]),
+            "ERR numkeys should be greater than 0"
+        );
+        assert_eq!(
+            err(&["lmpop", "1", "a", "LEFT", "COUNT"]),
+            "ERR syntax error"
+        );
+        assert_eq!(
+            err(&["blmpop", "soon", "1", "a", "LEFT"]),
+            "ERR timeout is not a float or out of range"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_commands() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let push = LPush::try_from(array(&["rpush", "l", "a", "b", "a"]))?;
+        assert_eq!(push.execute(&backend), RespFrame::Integer(3));
+        let lrem = LRem::try_from(array(&["lrem", "l", "-1", "a"]))?;
+        assert_eq!(lrem.execute(&backend), RespFrame::Integer(1));
+        let range = LRange::try_from(array(&["lrange", "l", "0", "-1"]))?;
+        assert_eq!(
+            range.execute(&backend),
+            bulk_array(vec![BulkString::new("a"), BulkString::new("b")])
+        );
+
+        let pop = LPop::try_from(array(&["rpop", "l"]))?;
+        assert_eq!(pop.execute(&backend), BulkString::new("b").into());
+        let pop = LPop::try_from(array(&["lpop", "missing", "2"]))?;
+        assert_eq!(pop.execute(&backend), RespArray::null().into());
+
+        let pop = LMPop::try_from(array(&["lmpop", "2", "missing", "l", "LEFT"]))?;
+        assert_eq!(
+            pop.execute(&backend),
+            RespArray::new(vec![
+                BulkString::new("l").into(),
+                bulk_array(vec![BulkString::new("a")])
+            ])
+            .into()
+        );
+        let pop = LMPop::try_from(array(&["lmpop", "1", "l", "LEFT"]))?;
+        assert_eq!(pop.execute(&backend), RespArray::null().into());
+        Ok(())
+    }
+}
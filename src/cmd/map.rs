@@ -1,12 +1,15 @@
-use crate::{Backend, BulkString, RespArray, RespFrame, RespNull};
+use crate::{backend::grow_string, Backend, BulkString, RespArray, RespFrame, RespNull};
 
-use super::{extract_args, validate_command, CommandError, CommandExecutor, Get, Set, RESP_OK};
+use super::{
+    extract_args, extract_integer, extract_string, validate_command, Append, Cas, CommandError,
+    CommandExecutor, Get, GetDel, GetSet, Incr, Set, SetRange, RESP_OK,
+};
 
 impl CommandExecutor for Get {
     fn execute(self, backend: &Backend) -> RespFrame {
         let res = backend.get(&self.key);
         match res {
-            Some(value) => value,
+            Some(value) => BulkString::from(value).into(),
             None => RespFrame::Null(RespNull),
         }
     }
@@ -14,11 +17,133 @@ impl CommandExecutor for Get {
 
 impl CommandExecutor for Set {
     fn execute(self, backend: &Backend) -> RespFrame {
+        if let Err(e) = check_string_size(backend, self.value.len()) {
+            return e.into();
+        }
         backend.set(self.key, self.value);
         RESP_OK.clone()
     }
 }
 
+impl CommandExecutor for GetSet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if let Err(e) = check_string_size(backend, self.value.len()) {
+            return e.into();
+        }
+        match backend.update(&self.key, |value| value.replace(self.value)) {
+            Ok(Some(old)) => BulkString::new(old).into(),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for GetDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.update(&self.key, Option::take) {
+            Ok(Some(old)) => BulkString::new(old).into(),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for Incr {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let res = backend.update(&self.key, |value| {
+            let current = match value {
+                Some(bytes) => bytes_to_integer(bytes).ok_or_else(|| {
+                    CommandError::InvalidArgument(
+                        "value is not an integer or out of range".to_string(),
+                    )
+                })?,
+                None => 0,
+            };
+            let new = current.checked_add(1).ok_or_else(|| {
+                CommandError::InvalidArgument("increment or decrement would overflow".to_string())
+            })?;
+            *value = Some(new.to_string().into_bytes());
+            Ok::<_, CommandError>(new)
+        });
+        let res = res.unwrap_or_else(|e| Err(e.into()));
+        match res {
+            Ok(new) => RespFrame::Integer(new),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for Append {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let res = backend.update(&self.key, |value| {
+            // checked before creating the key, which a refused command must not.
+            let len = value.as_ref().map_or(0, Vec::len) + self.value.len();
+            check_string_size(backend, len)?;
+            let bytes = value.get_or_insert_with(Vec::new);
+            grow_string(bytes, len);
+            bytes.extend_from_slice(&self.value);
+            Ok::<_, CommandError>(bytes.len())
+        });
+        let res = res.unwrap_or_else(|e| Err(e.into()));
+        match res {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SetRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let res = backend.update(&self.key, |value| {
+            // an empty value never creates the key.
+            if value.is_none() && self.value.is_empty() {
+                return Ok(0);
+            }
+            if self.value.is_empty() {
+                return Ok(value.as_ref().map_or(0, Vec::len));
+            }
+            // checked before creating the key, which a refused command must not.
+            let end = self.offset.saturating_add(self.value.len());
+            check_string_size(backend, end)?;
+            let bytes = value.get_or_insert_with(Vec::new);
+            if bytes.len() < end {
+                grow_string(bytes, end);
+                bytes.resize(end, 0);
+            }
+            bytes[self.offset..end].copy_from_slice(&self.value);
+            Ok::<_, CommandError>(bytes.len())
+        });
+        let res = res.unwrap_or_else(|e| Err(e.into()));
+        match res {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for Cas {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.compare_and_set(&self.key, Some(&self.expected), self.new) {
+            Ok(swapped) => RespFrame::Integer(swapped as i64),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+/// Integer held by a string value.
+pub(super) fn bytes_to_integer(bytes: &[u8]) -> Option<i64> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+fn check_string_size(backend: &Backend, size: usize) -> Result<(), CommandError> {
+    if size > backend.encoding.max_string_size {
+        return Err(CommandError::InvalidArgument(
+            "string exceeds maximum allowed size (proto-max-bulk-len)".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 impl TryFrom<RespArray> for Get {
     type Error = CommandError;
 
@@ -37,6 +162,40 @@ impl TryFrom<RespArray> for Get {
     }
 }
 
+impl TryFrom<RespArray> for GetSet {
+    type Error = CommandError;
+
+    // getset key value
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "getset", 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(key), Some(RespFrame::BulkString(BulkString(Some(value))))) => Ok(GetSet {
+                key: extract_string(key)?,
+                value: value.into(),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or value".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for GetDel {
+    type Error = CommandError;
+
+    // getdel key
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "getdel", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(GetDel {
+            key: extract_string(args.next().unwrap())?,
+        })
+    }
+}
+
 impl TryFrom<RespArray> for Set {
     type Error = CommandError;
 
@@ -48,9 +207,46 @@ impl TryFrom<RespArray> for Set {
         match (args.next(), args.next()) {
             (
                 Some(RespFrame::BulkString(BulkString(Some(key)))),
-                Some(RespFrame::BulkString(value)),
+                Some(RespFrame::BulkString(BulkString(Some(value)))),
             ) => Ok(Set {
-                key: String::from_utf8(key).map_err(CommandError::Utf8Error)?,
+                key: String::from_utf8(key.into()).map_err(CommandError::Utf8Error)?,
+                value: value.into(),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or value".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Incr {
+    type Error = CommandError;
+
+    // incr key
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "incr", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(Incr {
+                key: extract_string(key)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Append {
+    type Error = CommandError;
+
+    // append key value
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "append", 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(key), Some(RespFrame::BulkString(BulkString(Some(value))))) => Ok(Append {
+                key: extract_string(key)?,
                 value: value.into(),
             }),
             _ => Err(CommandError::InvalidArgument(
@@ -60,12 +256,68 @@ impl TryFrom<RespArray> for Set {
     }
 }
 
+impl TryFrom<RespArray> for SetRange {
+    type Error = CommandError;
+
+    // setrange key offset value
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "setrange", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(offset), Some(RespFrame::BulkString(BulkString(Some(value))))) => {
+                let offset = extract_integer(offset)?;
+                if offset < 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "offset is out of range".to_string(),
+                    ));
+                }
+                Ok(SetRange {
+                    key: extract_string(key)?,
+                    offset: offset as usize,
+                    value: value.into(),
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, offset or value".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Cas {
+    type Error = CommandError;
+
+    // cas key expected new
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "cas", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (
+                Some(key),
+                Some(RespFrame::BulkString(BulkString(Some(expected)))),
+                Some(RespFrame::BulkString(BulkString(Some(new)))),
+            ) => Ok(Cas {
+                key: extract_string(key)?,
+                expected: expected.into(),
+                new: new.into(),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, expected or new value".to_string(),
+            )),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bytes::BytesMut;
 
+    use crate::{EncodingConfig, SimpleError};
+
     use super::*;
-    use crate::{RespArray, RespDecode};
+    use crate::{cmd::array, RespArray, RespDecode};
 
     #[test]
     fn test_get_from_resp_array() -> anyhow::Result<()> {
@@ -90,7 +342,7 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err().to_string(),
-            "Invalid argument: Invalid command: expected: get, got: xget",
+            "ERR expected command get, got: xget",
         );
 
         // invalid argument
@@ -100,7 +352,7 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err().to_string(),
-            "Invalid argument: length of get command arguments must be 1",
+            "ERR wrong number of arguments for 'get' command",
         );
         Ok(())
     }
@@ -115,7 +367,7 @@ mod tests {
         ]);
         let result = Set::try_from(resp_array)?;
         assert_eq!(result.key, "key".to_string());
-        assert_eq!(result.value, RespFrame::BulkString("value".into()));
+        assert_eq!(result.value, b"value".to_vec());
 
         // invalid case - cmd error
         let resp_array = RespArray::new(vec![
@@ -128,7 +380,7 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err().to_string(),
-            "Invalid argument: Invalid command: expected: set, got: setx"
+            "ERR expected command set, got: setx"
         );
 
         // invalid case - invalid argument error
@@ -142,7 +394,7 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err().to_string(),
-            "Invalid argument: length of set command arguments must be 2".to_string()
+            "ERR wrong number of arguments for 'set' command".to_string()
         );
         Ok(())
     }
@@ -151,4 +403,150 @@ mod tests {
     fn test_execute_get() -> anyhow::Result<()> {
         Ok(())
     }
+
+    #[test]
+    fn test_execute_incr() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let res = Incr::try_from(array(&["incr", "n"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(1));
+        let res = Incr::try_from(array(&["incr", "n"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(2));
+        assert_eq!(backend.get("n"), Some("2".into()));
+
+        backend.set("s".to_string(), "abc".into());
+        let res = Incr::try_from(array(&["incr", "s"]))?.execute(&backend);
+        assert_eq!(
+            res,
+            SimpleError::new("ERR value is not an integer or out of range").into()
+        );
+        assert_eq!(backend.get("s"), Some("abc".into()));
+
+        backend.set("max".to_string(), i64::MAX.to_string().into());
+        let res = Incr::try_from(array(&["incr", "max"]))?.execute(&backend);
+        assert_eq!(
+            res,
+            SimpleError::new("ERR increment or decrement would overflow").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_incr_is_atomic() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let backend = backend.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        Incr::try_from(array(&["incr", "n"]))
+                            .unwrap()
+                            .execute(&backend);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(backend.get("n"), Some("8000".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_append_and_setrange() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let res = Append::try_from(array(&["append", "k", "Hello"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(5));
+        let res = Append::try_from(array(&["append", "k", " World"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(11));
+        // the room reserved for the next appends is accounted for.
+        assert_eq!(backend.memory_usage("k", 0), Some("k".len() + 22));
+
+        let res = SetRange::try_from(array(&["setrange", "k", "6", "Redis"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(11));
+        assert_eq!(backend.get("k"), Some("Hello Redis".into()));
+
+        let res = SetRange::try_from(array(&["setrange", "pad", "3", "x"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(4));
+        assert_eq!(backend.get("pad"), Some("\0\0\0x".into()));
+
+        let res = SetRange::try_from(array(&["setrange", "missing", "3", ""]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(0));
+        assert_eq!(backend.get("missing"), None);
+
+        assert!(SetRange::try_from(array(&["setrange", "k", "-1", "x"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_size_is_capped() -> anyhow::Result<()> {
+        let backend = Backend::with_encoding(EncodingConfig {
+            max_string_size: 8,
+            ..Default::default()
+        });
+        let too_large = RespFrame::Error(SimpleError::new(
+            "ERR string exceeds maximum allowed size (proto-max-bulk-len)".to_string(),
+        ));
+
+        let res = Set::try_from(array(&["set", "k", "123456789"]))?.execute(&backend);
+        assert_eq!(res, too_large);
+        assert_eq!(backend.get("k"), None);
+        let res = GetSet::try_from(array(&["getset", "k", "123456789"]))?.execute(&backend);
+        assert_eq!(res, too_large);
+
+        let res = Set::try_from(array(&["set", "k", "12345678"]))?.execute(&backend);
+        assert_eq!(res, RESP_OK.clone());
+        let res = Append::try_from(array(&["append", "k", "9"]))?.execute(&backend);
+        assert_eq!(res, too_large);
+        let res = SetRange::try_from(array(&["setrange", "k", "8", "9"]))?.execute(&backend);
+        assert_eq!(res, too_large);
+        let res = SetRange::try_from(array(&["setrange", "k", "7", "9"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(8));
+        assert_eq!(backend.get("k"), Some("12345679".into()));
+
+        // a refused command doesn't create the key.
+        let res =
+            SetRange::try_from(array(&["setrange", "nk1", "600000000", "x"]))?.execute(&backend);
+        assert_eq!(res, too_large);
+        assert!(!backend.exists("nk1"));
+        let res = Append::try_from(array(&["append", "nk2", "123456789"]))?.execute(&backend);
+        assert_eq!(res, too_large);
+        assert!(!backend.exists("nk2"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_cas() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let res = Cas::try_from(array(&["cas", "k", "a", "b"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(0));
+        assert_eq!(backend.get("k"), None);
+
+        backend.set("k".to_string(), "a".into());
+        let res = Cas::try_from(array(&["cas", "k", "x", "b"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(0));
+        let res = Cas::try_from(array(&["cas", "k", "a", "b"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(1));
+        assert_eq!(backend.get("k"), Some("b".into()));
+
+        assert_eq!(backend.compare_and_set("new", None, "v".into()), Ok(true));
+        assert_eq!(backend.compare_and_set("new", None, "w".into()), Ok(false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_getset_and_getdel() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let res = GetSet::try_from(array(&["getset", "k", "a"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Null(RespNull));
+        let res = GetSet::try_from(array(&["getset", "k", "b"]))?.execute(&backend);
+        assert_eq!(res, BulkString::new("a").into());
+
+        let res = GetDel::try_from(array(&["getdel", "k"]))?.execute(&backend);
+        assert_eq!(res, BulkString::new("b").into());
+        assert_eq!(backend.get("k"), None);
+        let res = GetDel::try_from(array(&["getdel", "k"]))?.execute(&backend);
+        assert_eq!(res, RespFrame::Null(RespNull));
+        Ok(())
+    }
 }
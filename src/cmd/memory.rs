@@ -0,0 +1,142 @@
+use crate::{Backend, BulkString, RespArray, RespFrame};
+
+use super::{
+    err::CommandError, extract_args, extract_integer, extract_string, CommandExecutor, Memory,
+    RESP_OK,
+};
+
+/// Number of container elements measured by MEMORY USAGE by default.
+const DEFAULT_SAMPLES: usize = 5;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MemorySubcommand {
+    Doctor,
+    Purge,
+    Usage { key: String, samples: usize },
+}
+
+impl CommandExecutor for Memory {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.subcommand {
+            MemorySubcommand::Doctor => BulkString::new(backend.memory_doctor()).into(),
+            MemorySubcommand::Purge => {
+                backend.optimize_memory();
+                RESP_OK.clone()
+            }
+            MemorySubcommand::Usage { key, samples } => match backend.memory_usage(&key, samples) {
+                Some(size) => RespFrame::Integer(size as i64),
+                None => BulkString::null().into(),
+            },
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Memory {
+    type Error = CommandError;
+
+    // memory doctor
+    // memory purge
+    // memory usage key [SAMPLES count]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(arg) => extract_string(arg)?,
+            None => return Err(CommandError::WrongArity("memory".to_string())),
+        };
+        match subcommand.to_ascii_lowercase().as_str() {
+            "doctor" if args.next().is_none() => Ok(Memory {
+                subcommand: MemorySubcommand::Doctor,
+            }),
+            "doctor" => Err(CommandError::WrongArity("memory|doctor".to_string())),
+            "purge" if args.next().is_none() => Ok(Memory {
+                subcommand: MemorySubcommand::Purge,
+            }),
+            "purge" => Err(CommandError::WrongArity("memory|purge".to_string())),
+            "usage" => parse_usage(args),
+            _ => Err(CommandError::InvalidArgument(format!(
+                "unknown subcommand '{}'",
+                subcommand
+            ))),
+        }
+    }
+}
+
+fn parse_usage(mut args: impl Iterator<Item = RespFrame>) -> Result<Memory, CommandError> {
+    let key = match args.next() {
+        Some(key) => extract_string(key)?,
+        None => return Err(CommandError::WrongArity("memory|usage".to_string())),
+    };
+    let mut samples = DEFAULT_SAMPLES;
+    let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+    while let Some(arg) = args.next() {
+        match extract_string(arg)?.to_ascii_lowercase().as_str() {
+            "samples" => {
+                let count = extract_integer(args.next().ok_or_else(syntax_error)?)?;
+                // a negative count is treated like 0: measure everything.
+                samples = count.max(0) as usize;
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok(Memory {
+        subcommand: MemorySubcommand::Usage { key, samples },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::array;
+
+    fn memory(args: &[&str]) -> Result<Memory, CommandError> {
+        Memory::try_from(array(args))
+    }
+
+    #[test]
+    fn test_memory_from_resp_array() -> anyhow::Result<()> {
+        assert_eq!(
+            memory(&["memory", "DOCTOR"])?.subcommand,
+            MemorySubcommand::Doctor
+        );
+        assert_eq!(
+            memory(&["memory", "usage", "k"])?.subcommand,
+            MemorySubcommand::Usage {
+                key: "k".to_string(),
+                samples: DEFAULT_SAMPLES
+            }
+        );
+        assert_eq!(
+            memory(&["memory", "usage", "k", "SAMPLES", "0"])?.subcommand,
+            MemorySubcommand::Usage {
+                key: "k".to_string(),
+                samples: 0
+            }
+        );
+        assert!(memory(&["memory", "usage"]).is_err());
+        assert!(memory(&["memory", "usage", "k", "samples"]).is_err());
+        assert!(memory(&["memory", "usage", "k", "samples", "x"]).is_err());
+        assert!(memory(&["memory"]).is_err());
+        assert!(memory(&["memory", "doctor", "extra"]).is_err());
+        assert_eq!(
+            memory(&["memory", "Purge"])?.subcommand,
+            MemorySubcommand::Purge
+        );
+        assert!(memory(&["memory", "purge", "extra"]).is_err());
+        assert!(memory(&["memory", "unknown"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_doctor_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let res = memory(&["memory", "doctor"])?.execute(&backend);
+        assert_eq!(res, BulkString::new(backend.memory_doctor()).into());
+
+        let res = memory(&["memory", "usage", "k"])?.execute(&backend);
+        assert_eq!(res, BulkString::null().into());
+        backend.set("k".to_string(), "v".into());
+        let res = memory(&["memory", "usage", "k"])?.execute(&backend);
+        assert!(matches!(res, RespFrame::Integer(size) if size > 0));
+        Ok(())
+    }
+}
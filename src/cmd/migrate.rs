@@ -0,0 +1,430 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use bytes::BytesMut;
+use tokio::{
+    runtime::{Handle, RuntimeFlavor},
+    task::block_in_place,
+};
+
+use crate::{
+    err::RespError, Backend, BulkString, RespArray, RespDecodeV2, RespEncode, RespFrame, RespNull,
+    SimpleString,
+};
+
+use super::{
+    err::CommandError, extract_args, extract_integer, extract_string, validate_command,
+    CommandExecutor, Dump, Migrate, Restore, RESP_OK,
+};
+
+/// Timeout of MIGRATE when given 0, like Redis.
+const DEFAULT_MIGRATE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+impl CommandExecutor for Dump {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.dump(&self.key) {
+            Some(payload) => BulkString::new(payload).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for Restore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.restore(&self.key, &self.payload, self.replace) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for Migrate {
+    /// The transfer blocks the calling thread until the target replied or timed out,
+    /// like it blocks the server in Redis.
+    fn execute(self, backend: &Backend) -> RespFrame {
+        // the keys can't be modified between their dump and their removal.
+        let _guard = backend.lock_keys(self.keys.iter().map(String::as_str));
+        let payloads: Vec<(&str, Vec<u8>)> = self
+            .keys
+            .iter()
+            .filter_map(|key| Some((key.as_str(), backend.dump(key)?)))
+            .collect();
+        if payloads.is_empty() {
+            return SimpleString::new("NOKEY").into();
+        }
+        let (restored, res) = blocking(|| self.transfer(&payloads));
+        if !self.copy {
+            for key in restored {
+                backend.remove_key(key);
+            }
+        }
+        match res {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl Migrate {
+    /// Restore the keys on the target, returns the keys restored and the first error.
+    fn transfer<'a>(
+        &self,
+        payloads: &[(&'a str, Vec<u8>)],
+    ) -> (Vec<&'a str>, Result<(), CommandError>) {
+        let mut stream = match self.connect() {
+            Ok(stream) => stream,
+            Err(_) => {
+                let e = CommandError::Io("error or timeout connecting to the client".to_string());
+                return (vec![], Err(e));
+            }
+        };
+        // the slot is importing on the target, which only serves it after ASKING.
+        let mut request = Vec::new();
+        for (key, payload) in payloads {
+            request.extend_from_slice(&command(&[b"asking"]).encode());
+            let mut restore = vec![b"restore".as_slice(), key.as_bytes(), b"0", payload];
+            if self.replace {
+                restore.push(b"replace");
+            }
+            request.extend_from_slice(&command(&restore).encode());
+        }
+        if stream.write_all(&request).is_err() {
+            let e = CommandError::Io("error or timeout writing to target instance".to_string());
+            return (vec![], Err(e));
+        }
+
+        let mut replies = RepliesReader::new(stream);
+        let mut restored = Vec::new();
+        let mut res = Ok(());
+        for (key, _) in payloads {
+            let (asking, restore) = match (replies.next(), replies.next()) {
+                (Some(asking), Some(restore)) => (asking, restore),
+                _ => {
+                    let e =
+                        CommandError::Io("error or timeout reading to target instance".to_string());
+                    return (restored, Err(e));
+                }
+            };
+            match (asking, restore) {
+                (RespFrame::Error(e), _) | (_, RespFrame::Error(e)) => {
+                    if res.is_ok() {
+                        res = Err(CommandError::InvalidArgument(format!(
+                            "Target instance replied with error: {}",
+                            e.0
+                        )));
+                    }
+                }
+                _ => restored.push(*key),
+            }
+        }
+        (restored, res)
+    }
+
+    fn connect(&self) -> std::io::Result<TcpStream> {
+        let mut last_err = None;
+        for addr in (self.host.as_str(), self.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout))?;
+                    stream.set_write_timeout(Some(self.timeout))?;
+                    return Ok(stream);
+                }
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| std::io::ErrorKind::AddrNotAvailable.into()))
+    }
+}
+
+/// Run `f`, which blocks on I/O. On a worker of the multi-threaded runtime, its other
+/// tasks are handed over first, they could be the ones the target needs to reply.
+fn blocking<T>(f: impl FnOnce() -> T) -> T {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => block_in_place(f),
+        _ => f(),
+    }
+}
+
+/// Reads the replies of the target one frame at a time.
+struct RepliesReader {
+    stream: TcpStream,
+    buf: BytesMut,
+}
+
+impl RepliesReader {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            buf: BytesMut::new(),
+        }
+    }
+
+    /// The next reply, `None` on error or timeout.
+    fn next(&mut self) -> Option<RespFrame> {
+        let mut chunk = [0; 4096];
+        loop {
+            match RespFrame::decode(&mut self.buf) {
+                Ok(frame) => return Some(frame),
+                Err(RespError::NotCompleted(_)) => {}
+                Err(_) => return None,
+            }
+            match self.stream.read(&mut chunk) {
+                Ok(0) | Err(_) => return None,
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
+fn command(args: &[&[u8]]) -> RespArray {
+    RespArray::new(
+        args.iter()
+            .map(|arg| BulkString::new(*arg).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+}
+
+impl TryFrom<RespArray> for Dump {
+    type Error = CommandError;
+
+    // dump key
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "dump", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Dump {
+            key: extract_string(args.next().unwrap())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Restore {
+    type Error = CommandError;
+
+    // restore key ttl serialized-value [REPLACE]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (Some(key), Some(ttl), Some(payload)) = (args.next(), args.next(), args.next()) else {
+            return Err(CommandError::WrongArity("restore".to_string()));
+        };
+        let key = extract_string(key)?;
+        // keys never expire as a whole, only hash fields do: a dumped hash keeps them.
+        match extract_integer(ttl)? {
+            0 => {}
+            ttl if ttl < 0 => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid TTL value, must be >= 0".to_string(),
+                ))
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "keys can't expire, the TTL must be 0".to_string(),
+                ))
+            }
+        }
+        let RespFrame::BulkString(BulkString(Some(payload))) = payload else {
+            return Err(CommandError::InvalidArgument(
+                "Argument must be a bulk string".to_string(),
+            ));
+        };
+        let mut replace = false;
+        for arg in args {
+            match extract_string(arg)?.to_ascii_lowercase().as_str() {
+                "replace" => replace = true,
+                // ABSTTL, IDLETIME and FREQ are not supported.
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(Restore {
+            key,
+            payload: payload.into(),
+            replace,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Migrate {
+    type Error = CommandError;
+
+    // migrate host port key|"" destination-db timeout [COPY] [REPLACE] [KEYS key [key ...]]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (Some(host), Some(port), Some(key), Some(db), Some(timeout)) = (
+            args.next(),
+            args.next(),
+            args.next(),
+            args.next(),
+            args.next(),
+        ) else {
+            return Err(CommandError::WrongArity("migrate".to_string()));
+        };
+        let host = extract_string(host)?;
+        let port = u16::try_from(extract_integer(port)?)
+            .map_err(|_| CommandError::InvalidArgument("Invalid TCP port".to_string()))?;
+        let key = extract_string(key)?;
+        // there is a single database.
+        if extract_integer(db)? != 0 {
+            return Err(CommandError::InvalidArgument(
+                "DB index is out of range".to_string(),
+            ));
+        }
+        let timeout = match extract_integer(timeout)? {
+            ms if ms <= 0 => DEFAULT_MIGRATE_TIMEOUT,
+            ms => Duration::from_millis(ms as u64),
+        };
+
+        let mut migrate = Migrate {
+            host,
+            port,
+            keys: vec![],
+            timeout,
+            copy: false,
+            replace: false,
+        };
+        let mut keys_option = false;
+        while let Some(arg) = args.next() {
+            match extract_string(arg)?.to_ascii_lowercase().as_str() {
+                "copy" => migrate.copy = true,
+                "replace" => migrate.replace = true,
+                "keys" => {
+                    if !key.is_empty() {
+                        return Err(CommandError::InvalidArgument(
+                            "When using MIGRATE KEYS option, the key argument must be set to the empty string".to_string(),
+                        ));
+                    }
+                    keys_option = true;
+                    migrate.keys = args
+                        .by_ref()
+                        .map(extract_string)
+                        .collect::<Result<_, _>>()?;
+                }
+                // AUTH and AUTH2 are not supported.
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        if !keys_option {
+            migrate.keys.push(key);
+        }
+        if migrate.keys.is_empty() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(migrate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use crate::{cmd::array, SimpleError};
+
+    use super::*;
+
+    #[test]
+    fn test_migrate_from_resp_array() -> anyhow::Result<()> {
+        let migrate = Migrate::try_from(array(&[
+            "migrate",
+            "127.0.0.1",
+            "7001",
+            "",
+            "0",
+            "0",
+            "copy",
+            "keys",
+            "a",
+            "b",
+        ]))?;
+        assert_eq!(migrate.keys, vec!["a", "b"]);
+        assert!(migrate.copy && !migrate.replace);
+        assert_eq!(migrate.timeout, DEFAULT_MIGRATE_TIMEOUT);
+
+        let migrate = Migrate::try_from(array(&[
+            "migrate",
+            "127.0.0.1",
+            "7001",
+            "k",
+            "0",
+            "50",
+            "replace",
+        ]))?;
+        assert_eq!(migrate.keys, vec!["k"]);
+        assert_eq!(migrate.timeout, Duration::from_millis(50));
+
+        for args in [
+            &["migrate", "127.0.0.1", "7001", "k", "0"][..],
+            &["migrate", "127.0.0.1", "7001", "k", "1", "0"],
+            &["migrate", "127.0.0.1", "7001", "k", "0", "0", "keys", "a"],
+            &["migrate", "127.0.0.1", "7001", "k", "0", "0", "auth", "pw"],
+        ] {
+            assert!(Migrate::try_from(array(args)).is_err(), "{:?}", args);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_from_resp_array() -> anyhow::Result<()> {
+        let restore = Restore::try_from(array(&["restore", "k", "0", "payload", "REPLACE"]))?;
+        assert!(restore.replace);
+        assert!(Restore::try_from(array(&["restore", "k", "100", "payload"])).is_err());
+        assert!(Restore::try_from(array(&["restore", "k", "0", "payload", "absttl"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_restore_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("k".to_string(), b"v\r\n".to_vec());
+        let RespFrame::BulkString(payload) =
+            Dump::try_from(array(&["dump", "k"]))?.execute(&backend)
+        else {
+            panic!("expected a bulk string");
+        };
+        let restore = Restore {
+            key: "k".to_string(),
+            payload: payload.as_ref().to_vec(),
+            replace: false,
+        };
+        assert_eq!(
+            restore.execute(&backend),
+            SimpleError::new("BUSYKEY Target key name already exists.").into()
+        );
+        let restore = Restore {
+            key: "copy".to_string(),
+            payload: payload.as_ref().to_vec(),
+            replace: false,
+        };
+        assert_eq!(restore.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.get("copy"), backend.get("k"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_errors() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        // nothing listens on the port once the listener is dropped.
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let migrate = |key: &str| Migrate {
+            host: "127.0.0.1".to_string(),
+            port,
+            keys: vec![key.to_string()],
+            timeout: Duration::from_millis(100),
+            copy: false,
+            replace: false,
+        };
+        assert_eq!(
+            migrate("missing").execute(&backend),
+            SimpleString::new("NOKEY").into()
+        );
+        backend.set("k".to_string(), b"v".to_vec());
+        assert_eq!(
+            migrate("k").execute(&backend),
+            SimpleError::new("IOERR error or timeout connecting to the client").into()
+        );
+        // the key stays where it is when it couldn't be moved.
+        assert!(backend.exists("k"));
+        Ok(())
+    }
+}
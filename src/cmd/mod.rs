@@ -1,19 +1,56 @@
+pub mod blocking;
+pub mod bloom;
+pub mod client;
+pub mod cluster;
+pub mod command;
+pub mod debug;
 pub mod echo;
 pub mod err;
+pub mod help;
 pub mod hmap;
+pub mod hook;
+pub mod import;
+pub mod info;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod keyspace;
+pub mod list;
 pub mod map;
+pub mod memory;
+pub mod migrate;
+pub mod multi;
+pub mod plugin;
+pub mod pubsub;
+pub mod registry;
+pub mod search;
 pub mod set;
+pub mod slowlog;
+pub mod snapshot;
+pub mod sort;
+pub mod timeseries;
+pub mod zset;
 
-use std::collections::HashSet;
+use std::{collections::HashSet, time::Duration};
 
 use enum_dispatch::enum_dispatch;
 
-use crate::{backend, BulkString, RespArray, RespFrame, SimpleString};
+use crate::{
+    backend, config::RequestLimits, Aggregation, BulkString, ExpireCondition, FieldType, ListEnd,
+    Query, RespArray, RespFrame, SimpleString, ZAddFlags, ZRangeSpec,
+};
 
-use self::err::CommandError;
+use self::{
+    cluster::ClusterSubcommand,
+    command::CommandSubcommand,
+    debug::DebugSubcommand,
+    err::CommandError,
+    memory::MemorySubcommand,
+    registry::{lookup_command, CommandSpec},
+    slowlog::SlowlogSubcommand,
+};
 
 lazy_static::lazy_static! {
-    static ref RESP_OK:RespFrame = SimpleString::new("OK").into();
+    pub(crate) static ref RESP_OK:RespFrame = SimpleString::new("OK").into();
 }
 
 #[enum_dispatch]
@@ -25,13 +62,98 @@ pub trait CommandExecutor {
 pub enum Command {
     Get(Get),
     Set(Set),
+    GetSet(GetSet),
+    GetDel(GetDel),
+    Rename(Rename),
+    Dump(Dump),
+    Restore(Restore),
+    Migrate(Migrate),
+    Scan(Scan),
+    ObjectEncoding(ObjectEncoding),
     HGet(HGet),
     HSet(HSet),
     HGetAll(HGetAll),
     HMGet(HMGet),
+    HIncrBy(HIncrBy),
+    HExpire(HExpire),
+    HTtl(HTtl),
+    HPersist(HPersist),
+    HRandField(HRandField),
+    #[cfg(feature = "json")]
+    JsonSet(JsonSet),
+    #[cfg(feature = "json")]
+    JsonGet(JsonGet),
+    #[cfg(feature = "json")]
+    JsonDel(JsonDel),
+    Incr(Incr),
+    Append(Append),
+    SetRange(SetRange),
+    Cas(Cas),
     Echo(Echo),
     SAdd(SAdd),
+    BfReserve(BfReserve),
+    BfAdd(BfAdd),
+    BfExists(BfExists),
+    BfScanDump(BfScanDump),
+    BfLoadChunk(BfLoadChunk),
+    TsCreate(TsCreate),
+    TsAdd(TsAdd),
+    TsRange(TsRange),
+    TsCreateRule(TsCreateRule),
+    FtCreate(FtCreate),
+    FtSearch(FtSearch),
+    FtDropIndex(FtDropIndex),
     SIsMember(SIsMember),
+    SMove(SMove),
+    SInterCard(SInterCard),
+    SRandMember(SRandMember),
+    LPush(LPush),
+    LPop(LPop),
+    LMPop(LMPop),
+    LLen(LLen),
+    LRange(LRange),
+    LIndex(LIndex),
+    LInsert(LInsert),
+    LRem(LRem),
+    ZAdd(ZAdd),
+    ZRem(ZRem),
+    ZMPop(ZMPop),
+    ZScore(ZScore),
+    ZCard(ZCard),
+    ZRank(ZRank),
+    ZRange(ZRange),
+    ZRangeByScore(ZRangeByScore),
+    ZRangeStore(ZRangeStore),
+    Sort(Sort),
+    SPublish(SPublish),
+    Memory(Memory),
+    Info(Info),
+    DebugCommand(DebugCommand),
+    SlowlogCommand(SlowlogCommand),
+    ClusterCommand(ClusterCommand),
+    CommandCommand(CommandCommand),
+    Help(Help),
+}
+
+/// Commands that change the state of the calling connection rather than the keyspace.
+/// They are handled by the network layer, which owns the connection's state.
+#[derive(Debug)]
+pub enum ConnectionCommand {
+    SSubscribe(SSubscribe),
+    SUnsubscribe(SUnsubscribe),
+    ClientTracking(ClientTracking),
+    ClientInfo,
+    /// Whether replies to reads carry the freshness of the value as an attribute.
+    ClientAttributes(bool),
+    /// Whether the raw frames of the connection are logged.
+    ClientTrace(bool),
+    /// Let the next command access a slot this cluster node is importing.
+    Asking,
+    Hello(Hello),
+    /// Delay every write of a reply, disabled by zero.
+    DebugReplyDelay(Duration),
+    /// Write replies in fragments of at most this many bytes, disabled by zero.
+    DebugReplyFragment(usize),
 }
 
 #[derive(Debug)]
@@ -42,7 +164,87 @@ pub struct Get {
 #[derive(Debug)]
 pub struct Set {
     key: String,
-    value: RespFrame,
+    value: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct GetSet {
+    key: String,
+    value: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct GetDel {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct Rename {
+    src: String,
+    dst: String,
+}
+
+#[derive(Debug)]
+pub struct Dump {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct Restore {
+    key: String,
+    payload: Vec<u8>,
+    replace: bool,
+}
+
+/// Move keys to another server with DUMP and RESTORE, see [`Backend::dump`](crate::Backend::dump).
+#[derive(Debug)]
+pub struct Migrate {
+    host: String,
+    port: u16,
+    keys: Vec<String>,
+    timeout: Duration,
+    /// Keep the keys on this server.
+    copy: bool,
+    /// Replace the keys existing on the target.
+    replace: bool,
+}
+
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    pattern: Option<String>,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct ObjectEncoding {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct Incr {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct Append {
+    key: String,
+    value: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SetRange {
+    key: String,
+    offset: usize,
+    value: Vec<u8>,
+}
+
+/// Compare-and-swap, an extension command: `CAS key expected new`.
+#[derive(Debug)]
+pub struct Cas {
+    key: String,
+    expected: Vec<u8>,
+    new: Vec<u8>,
 }
 
 #[derive(Debug)]
@@ -69,6 +271,66 @@ pub struct HMGet {
     fields: Vec<String>,
 }
 
+#[derive(Debug)]
+pub struct HIncrBy {
+    key: String,
+    field: String,
+    increment: i64,
+}
+
+/// HEXPIRE and HPEXPIRE, the time to live is in milliseconds.
+#[derive(Debug)]
+pub struct HExpire {
+    key: String,
+    ttl_ms: i64,
+    condition: ExpireCondition,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HTtl {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HPersist {
+    key: String,
+    fields: Vec<String>,
+}
+
+/// HRANDFIELD, without a count a single field is replied rather than an array.
+#[derive(Debug)]
+pub struct HRandField {
+    key: String,
+    count: Option<i64>,
+    withvalues: bool,
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub struct JsonSet {
+    key: String,
+    path: json::JsonPath,
+    value: serde_json::Value,
+    condition: json::JsonSetCondition,
+}
+
+/// JSON.GET with its paths, kept with their text which keys the reply to several paths.
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub struct JsonGet {
+    key: String,
+    paths: Vec<(String, json::JsonPath)>,
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub struct JsonDel {
+    key: String,
+    path: json::JsonPath,
+}
+
 #[derive(Debug)]
 pub struct Echo {
     message: String,
@@ -86,6 +348,311 @@ pub struct SIsMember {
     member: BulkString,
 }
 
+#[derive(Debug)]
+pub struct SMove {
+    src: String,
+    dst: String,
+    member: BulkString,
+}
+
+#[derive(Debug)]
+pub struct SInterCard {
+    keys: Vec<String>,
+    limit: usize,
+}
+
+/// SRANDMEMBER, without a count a single member is replied rather than an array.
+#[derive(Debug)]
+pub struct SRandMember {
+    key: String,
+    count: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct BfReserve {
+    key: String,
+    error_rate: f64,
+    capacity: u64,
+    /// `None` for a non-scaling filter.
+    expansion: Option<u32>,
+}
+
+/// BF.ADD and BF.MADD, which replies with an array.
+#[derive(Debug)]
+pub struct BfAdd {
+    key: String,
+    items: Vec<Vec<u8>>,
+    multi: bool,
+}
+
+#[derive(Debug)]
+pub struct BfExists {
+    key: String,
+    item: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct BfScanDump {
+    key: String,
+    iterator: u64,
+}
+
+#[derive(Debug)]
+pub struct BfLoadChunk {
+    key: String,
+    iterator: u64,
+    data: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct TsCreate {
+    key: String,
+    retention_ms: u64,
+    labels: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+pub struct TsAdd {
+    key: String,
+    /// `None` for `*`, the current time.
+    timestamp: Option<u64>,
+    value: f64,
+    /// Retention of the series if it is created.
+    retention_ms: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct TsRange {
+    key: String,
+    from: u64,
+    to: u64,
+    count: Option<usize>,
+    aggregation: Option<(Aggregation, u64)>,
+}
+
+#[derive(Debug)]
+pub struct TsCreateRule {
+    src: String,
+    dest: String,
+    aggregation: Aggregation,
+    bucket_ms: u64,
+}
+
+/// FT.CREATE, a subset of RediSearch: TAG and NUMERIC fields of hashes.
+#[derive(Debug)]
+pub struct FtCreate {
+    index: String,
+    prefixes: Vec<String>,
+    schema: Vec<(String, FieldType)>,
+}
+
+#[derive(Debug)]
+pub struct FtSearch {
+    index: String,
+    query: Query,
+}
+
+#[derive(Debug)]
+pub struct FtDropIndex {
+    index: String,
+}
+
+#[derive(Debug)]
+pub struct Sort {
+    key: String,
+    by: Option<String>,
+    limit: Option<(i64, i64)>,
+    get: Vec<String>,
+    desc: bool,
+    alpha: bool,
+}
+
+#[derive(Debug)]
+pub struct SSubscribe {
+    pub(crate) channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SUnsubscribe {
+    pub(crate) channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct LPush {
+    key: String,
+    values: Vec<BulkString>,
+    end: ListEnd,
+}
+
+#[derive(Debug)]
+pub struct LPop {
+    key: String,
+    /// Without a count a single element is replied instead of an array.
+    count: Option<usize>,
+    end: ListEnd,
+}
+
+/// LMPOP, and BLMPOP which runs as LMPOP until the connection waits for a list.
+#[derive(Debug)]
+pub struct LMPop {
+    keys: Vec<String>,
+    end: ListEnd,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct LLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct LRange {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LIndex {
+    key: String,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct LInsert {
+    key: String,
+    before: bool,
+    pivot: BulkString,
+    value: BulkString,
+}
+
+#[derive(Debug)]
+pub struct LRem {
+    key: String,
+    count: i64,
+    value: BulkString,
+}
+
+#[derive(Debug)]
+pub struct ZAdd {
+    key: String,
+    flags: ZAddFlags,
+    /// INCR: the single score is an increment, as ZINCRBY.
+    incr: bool,
+    members: Vec<(f64, BulkString)>,
+}
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: String,
+    members: Vec<BulkString>,
+}
+
+/// ZMPOP, and BZMPOP which runs as ZMPOP until the connection waits for a sorted set.
+#[derive(Debug)]
+pub struct ZMPop {
+    keys: Vec<String>,
+    /// MAX: the highest scored members are popped first.
+    max: bool,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: String,
+    member: BulkString,
+}
+
+#[derive(Debug)]
+pub struct ZCard {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct ZRank {
+    key: String,
+    member: BulkString,
+}
+
+#[derive(Debug)]
+pub struct ZRange {
+    key: String,
+    spec: ZRangeSpec,
+    withscores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRangeByScore {
+    key: String,
+    spec: ZRangeSpec,
+    withscores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRangeStore {
+    dst: String,
+    src: String,
+    spec: ZRangeSpec,
+}
+
+#[derive(Debug)]
+pub struct SPublish {
+    channel: String,
+    message: BulkString,
+}
+
+#[derive(Debug)]
+pub struct Memory {
+    subcommand: MemorySubcommand,
+}
+
+#[derive(Debug)]
+pub struct DebugCommand {
+    subcommand: DebugSubcommand,
+}
+
+#[derive(Debug)]
+pub struct SlowlogCommand {
+    subcommand: SlowlogSubcommand,
+}
+
+#[derive(Debug)]
+pub struct ClusterCommand {
+    subcommand: ClusterSubcommand,
+}
+
+#[derive(Debug)]
+pub struct CommandCommand {
+    subcommand: CommandSubcommand,
+}
+
+#[derive(Debug)]
+pub struct Info {
+    /// Lowercased section names, all sections if empty.
+    sections: Vec<String>,
+}
+
+/// `<container> HELP`, available for every command with subcommands.
+#[derive(Debug)]
+pub struct Help {
+    spec: &'static CommandSpec,
+}
+
+/// Switch the protocol of the connection, and tell about the server.
+#[derive(Debug)]
+pub struct Hello {
+    /// The protocol version to switch to, 2 or 3, `None` to keep the current one.
+    pub(crate) protover: Option<u8>,
+    pub(crate) setname: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ClientTracking {
+    pub(crate) on: bool,
+    pub(crate) bcast: bool,
+    pub(crate) prefixes: Vec<String>,
+}
+
 impl TryFrom<RespFrame> for Command {
     type Error = CommandError;
 
@@ -104,21 +671,181 @@ impl TryFrom<RespArray> for Command {
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
         match value.first() {
-            Some(RespFrame::BulkString(ref c)) => match c.as_ref() {
-                b"get" => Ok(Get::try_from(value)?.into()),
-                b"set" => Ok(Set::try_from(value)?.into()),
-                b"hget" => Ok(HGet::try_from(value)?.into()),
-                b"hset" => Ok(HSet::try_from(value)?.into()),
-                b"hgetall" => Ok(HGetAll::try_from(value)?.into()),
-                b"hmget" => Ok(HMGet::try_from(value)?.into()),
-                b"echo" => Ok(Echo::try_from(value)?.into()),
-                b"sadd" => Ok(SAdd::try_from(value)?.into()),
-                b"sismember" => Ok(SIsMember::try_from(value)?.into()),
-                _ => Err(CommandError::InvalidCommand(format!(
-                    "Invalid command: {}",
-                    String::from_utf8_lossy(c.as_ref())
-                ))),
-            },
+            Some(RespFrame::BulkString(ref c)) => {
+                let spec = lookup_command(c.as_ref())
+                    .ok_or_else(|| CommandError::unknown_command(&value))?;
+                if let Some(help) = Help::parse(spec, &value) {
+                    return Ok(help.into());
+                }
+                match spec.name {
+                    "get" => Ok(Get::try_from(value)?.into()),
+                    "set" => Ok(Set::try_from(value)?.into()),
+                    "getset" => Ok(GetSet::try_from(value)?.into()),
+                    "getdel" => Ok(GetDel::try_from(value)?.into()),
+                    "rename" => Ok(Rename::try_from(value)?.into()),
+                    "dump" => Ok(Dump::try_from(value)?.into()),
+                    "restore" => Ok(Restore::try_from(value)?.into()),
+                    "migrate" => Ok(Migrate::try_from(value)?.into()),
+                    "scan" => Ok(Scan::try_from(value)?.into()),
+                    "object" => Ok(ObjectEncoding::try_from(value)?.into()),
+                    "hget" => Ok(HGet::try_from(value)?.into()),
+                    "hset" => Ok(HSet::try_from(value)?.into()),
+                    "hgetall" => Ok(HGetAll::try_from(value)?.into()),
+                    "hmget" => Ok(HMGet::try_from(value)?.into()),
+                    "hincrby" => Ok(HIncrBy::try_from(value)?.into()),
+                    "hexpire" | "hpexpire" => Ok(HExpire::try_from(value)?.into()),
+                    "httl" => Ok(HTtl::try_from(value)?.into()),
+                    "hpersist" => Ok(HPersist::try_from(value)?.into()),
+                    "hrandfield" => Ok(HRandField::try_from(value)?.into()),
+                    #[cfg(feature = "json")]
+                    "json.set" => Ok(JsonSet::try_from(value)?.into()),
+                    #[cfg(feature = "json")]
+                    "json.get" => Ok(JsonGet::try_from(value)?.into()),
+                    #[cfg(feature = "json")]
+                    "json.del" => Ok(JsonDel::try_from(value)?.into()),
+                    "incr" => Ok(Incr::try_from(value)?.into()),
+                    "append" => Ok(Append::try_from(value)?.into()),
+                    "setrange" => Ok(SetRange::try_from(value)?.into()),
+                    "cas" => Ok(Cas::try_from(value)?.into()),
+                    "echo" => Ok(Echo::try_from(value)?.into()),
+                    "sadd" => Ok(SAdd::try_from(value)?.into()),
+                    "sismember" => Ok(SIsMember::try_from(value)?.into()),
+                    "smove" => Ok(SMove::try_from(value)?.into()),
+                    "sintercard" => Ok(SInterCard::try_from(value)?.into()),
+                    "srandmember" => Ok(SRandMember::try_from(value)?.into()),
+                    "lpush" | "rpush" => Ok(LPush::try_from(value)?.into()),
+                    "lpop" | "rpop" => Ok(LPop::try_from(value)?.into()),
+                    "lmpop" | "blmpop" => Ok(LMPop::try_from(value)?.into()),
+                    "llen" => Ok(LLen::try_from(value)?.into()),
+                    "lrange" => Ok(LRange::try_from(value)?.into()),
+                    "lindex" => Ok(LIndex::try_from(value)?.into()),
+                    "linsert" => Ok(LInsert::try_from(value)?.into()),
+                    "lrem" => Ok(LRem::try_from(value)?.into()),
+                    "zadd" => Ok(ZAdd::try_from(value)?.into()),
+                    "zrem" => Ok(ZRem::try_from(value)?.into()),
+                    "zmpop" | "bzmpop" => Ok(ZMPop::try_from(value)?.into()),
+                    "zscore" => Ok(ZScore::try_from(value)?.into()),
+                    "zcard" => Ok(ZCard::try_from(value)?.into()),
+                    "zrank" => Ok(ZRank::try_from(value)?.into()),
+                    "zrange" => Ok(ZRange::try_from(value)?.into()),
+                    "zrangebyscore" => Ok(ZRangeByScore::try_from(value)?.into()),
+                    "zrangestore" => Ok(ZRangeStore::try_from(value)?.into()),
+                    "bf.reserve" => Ok(BfReserve::try_from(value)?.into()),
+                    "bf.add" | "bf.madd" => Ok(BfAdd::try_from(value)?.into()),
+                    "bf.exists" => Ok(BfExists::try_from(value)?.into()),
+                    "bf.scandump" => Ok(BfScanDump::try_from(value)?.into()),
+                    "bf.loadchunk" => Ok(BfLoadChunk::try_from(value)?.into()),
+                    "ts.create" => Ok(TsCreate::try_from(value)?.into()),
+                    "ts.add" => Ok(TsAdd::try_from(value)?.into()),
+                    "ts.range" => Ok(TsRange::try_from(value)?.into()),
+                    "ts.createrule" => Ok(TsCreateRule::try_from(value)?.into()),
+                    "ft.create" => Ok(FtCreate::try_from(value)?.into()),
+                    "ft.search" => Ok(FtSearch::try_from(value)?.into()),
+                    "ft.dropindex" => Ok(FtDropIndex::try_from(value)?.into()),
+                    "sort" | "sort_ro" => Ok(Sort::try_from(value)?.into()),
+                    "spublish" => Ok(SPublish::try_from(value)?.into()),
+                    "memory" => Ok(Memory::try_from(value)?.into()),
+                    "info" => Ok(Info::try_from(value)?.into()),
+                    "debug" => Ok(DebugCommand::try_from(value)?.into()),
+                    "slowlog" => Ok(SlowlogCommand::try_from(value)?.into()),
+                    "cluster" => Ok(ClusterCommand::try_from(value)?.into()),
+                    "command" => Ok(CommandCommand::try_from(value)?.into()),
+                    "ssubscribe" | "sunsubscribe" | "client" | "multi" | "exec" | "discard"
+                    | "watch" | "unwatch" | "hello" | "asking" => {
+                        Err(CommandError::InvalidCommand(format!(
+                            "{} is only allowed on a client connection",
+                            spec.name
+                        )))
+                    }
+                    // a registered command missing above is unknown to the server.
+                    _ => Err(CommandError::unknown_command(&value)),
+                }
+            }
+            _ => Err(CommandError::InvalidCommand(
+                "Command must have a BulkString as the first argument".to_string(),
+            )),
+        }
+    }
+}
+
+const CONNECTION_COMMANDS: &[&str] = &["ssubscribe", "sunsubscribe", "client", "asking", "hello"];
+const SUBSCRIPTION_COMMANDS: &[&str] = &["ssubscribe", "sunsubscribe"];
+
+impl ConnectionCommand {
+    /// Whether the frame is a command which must be handled by the connection.
+    /// Their HELP doesn't touch the connection and is left to [`Command`].
+    pub fn matches(frame: &RespFrame) -> bool {
+        (command_name_in(frame, CONNECTION_COMMANDS) && !is_help(frame))
+            || is_connection_debug(frame)
+    }
+
+    /// Whether the frame is a command allowed while the connection is subscribed.
+    pub fn is_subscription(frame: &RespFrame) -> bool {
+        command_name_in(frame, SUBSCRIPTION_COMMANDS)
+    }
+}
+
+fn is_connection_debug(frame: &RespFrame) -> bool {
+    match frame {
+        RespFrame::Array(array) if command_name_in(frame, &["debug"]) => match array.get(1) {
+            Some(RespFrame::BulkString(sub)) => debug::CONNECTION_SUBCOMMANDS
+                .iter()
+                .any(|name| name.as_bytes().eq_ignore_ascii_case(sub.as_ref())),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn is_help(frame: &RespFrame) -> bool {
+    match frame {
+        RespFrame::Array(array) if array.len() == 2 => match array[1] {
+            RespFrame::BulkString(ref sub) => sub.as_ref().eq_ignore_ascii_case(b"help"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+pub(crate) fn command_name_in(frame: &RespFrame, names: &[&str]) -> bool {
+    match frame {
+        RespFrame::Array(array) => match array.first() {
+            Some(RespFrame::BulkString(c)) => names
+                .iter()
+                .any(|name| name.as_bytes().eq_ignore_ascii_case(c.as_ref())),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+impl TryFrom<RespFrame> for ConnectionCommand {
+    type Error = CommandError;
+
+    fn try_from(value: RespFrame) -> Result<Self, Self::Error> {
+        let value = match value {
+            RespFrame::Array(v) => v,
+            _ => {
+                return Err(CommandError::InvalidCommand(
+                    "Command must be an Array".to_string(),
+                ))
+            }
+        };
+        match value.first() {
+            Some(RespFrame::BulkString(ref c)) => {
+                match c.as_ref().to_ascii_lowercase().as_slice() {
+                    b"ssubscribe" => Ok(ConnectionCommand::SSubscribe(value.try_into()?)),
+                    b"sunsubscribe" => Ok(ConnectionCommand::SUnsubscribe(value.try_into()?)),
+                    b"client" => client::parse_client_command(value),
+                    b"hello" => Ok(ConnectionCommand::Hello(value.try_into()?)),
+                    b"debug" => debug::parse_connection_debug(value),
+                    b"asking" => {
+                        validate_command(&value, "asking", 0)?;
+                        Ok(ConnectionCommand::Asking)
+                    }
+                    _ => Err(CommandError::unknown_command(&value)),
+                }
+            }
             _ => Err(CommandError::InvalidCommand(
                 "Command must have a BulkString as the first argument".to_string(),
             )),
@@ -126,23 +853,52 @@ impl TryFrom<RespArray> for Command {
     }
 }
 
+/// Reject requests exceeding the configured argument count or total size.
+pub(crate) fn check_request_limits(
+    frame: &RespFrame,
+    limits: &RequestLimits,
+) -> anyhow::Result<(), CommandError> {
+    let RespFrame::Array(args) = frame else {
+        return Ok(());
+    };
+    if args.len() > limits.max_args {
+        return Err(CommandError::InvalidArgument(format!(
+            "too many arguments: {}, max allowed is {}",
+            args.len(),
+            limits.max_args
+        )));
+    }
+    let size: usize = args
+        .iter()
+        .map(|arg| match arg {
+            RespFrame::BulkString(s) => s.as_ref().len(),
+            RespFrame::SimpleString(s) => s.as_ref().len(),
+            _ => 0,
+        })
+        .sum();
+    if size > limits.max_request_size {
+        return Err(CommandError::InvalidArgument(format!(
+            "request too large: {} bytes, max allowed is {} bytes",
+            size, limits.max_request_size
+        )));
+    }
+    Ok(())
+}
+
 fn validate_command(
     value: &RespArray,
     cmd: &str,
     n_arg: usize,
 ) -> anyhow::Result<(), CommandError> {
     if value.len() != n_arg + 1 {
-        return Err(CommandError::InvalidArgument(format!(
-            "length of {} command arguments must be {}",
-            cmd, n_arg
-        )));
+        return Err(CommandError::WrongArity(cmd.to_string()));
     }
 
     match value[0] {
         RespFrame::BulkString(BulkString(Some(ref c))) => {
             if c.to_ascii_lowercase() != cmd.as_bytes() {
-                return Err(CommandError::InvalidArgument(format!(
-                    "Invalid command: expected: {}, got: {}",
+                return Err(CommandError::InvalidCommand(format!(
+                    "expected command {}, got: {}",
                     cmd,
                     String::from_utf8_lossy(c)
                 )));
@@ -167,6 +923,110 @@ fn extract_args(value: RespArray, start: usize) -> anyhow::Result<Vec<RespFrame>
     }
 }
 
+fn extract_string(frame: RespFrame) -> anyhow::Result<String, CommandError> {
+    match frame {
+        RespFrame::BulkString(BulkString(Some(s))) => {
+            String::from_utf8(s.into()).map_err(CommandError::Utf8Error)
+        }
+        _ => Err(CommandError::InvalidArgument(
+            "Argument must be a bulk string".to_string(),
+        )),
+    }
+}
+
+fn extract_integer(frame: RespFrame) -> anyhow::Result<i64, CommandError> {
+    extract_string(frame)?.parse().map_err(|_| {
+        CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+    })
+}
+
+/// The count of SRANDMEMBER and HRANDFIELD. Like Redis, it is at most
+/// `i64::MAX / 2` either way, so that a reply with values can't overflow.
+fn extract_random_count(frame: RespFrame) -> Result<i64, CommandError> {
+    let count = extract_integer(frame)?;
+    if !(-(i64::MAX / 2)..=i64::MAX / 2).contains(&count) {
+        return Err(CommandError::InvalidArgument(
+            "value is out of range".to_string(),
+        ));
+    }
+    Ok(count)
+}
+
+/// The timeout of a blocking command in seconds, `None` for 0 which waits forever.
+fn extract_timeout(frame: RespFrame) -> Result<Option<Duration>, CommandError> {
+    let timeout = extract_string(frame)?
+        .parse::<f64>()
+        .ok()
+        .filter(|timeout| timeout.is_finite())
+        .ok_or_else(|| {
+            CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+        })?;
+    if timeout < 0.0 {
+        return Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        ));
+    }
+    match Duration::try_from_secs_f64(timeout) {
+        Ok(Duration::ZERO) => Ok(None),
+        Ok(timeout) => Ok(Some(timeout)),
+        Err(_) => Err(CommandError::InvalidArgument(
+            "timeout is out of range".to_string(),
+        )),
+    }
+}
+
+/// The arguments of LMPOP and ZMPOP: `numkeys key [key ...] <where> [COUNT count]`.
+/// Returns the keys, where to pop from, and the count, 1 by default.
+fn extract_multi_pop(args: Vec<RespFrame>) -> Result<(Vec<String>, String, usize), CommandError> {
+    let mut args = args.into_iter();
+    let numkeys = match args.next() {
+        Some(frame) => extract_integer(frame)?,
+        None => unreachable!("the arity leaves numkeys"),
+    };
+    if numkeys <= 0 {
+        return Err(CommandError::InvalidArgument(
+            "numkeys should be greater than 0".to_string(),
+        ));
+    }
+    // the keys must be followed by where to pop from.
+    if numkeys as usize >= args.len() {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+    let keys = args
+        .by_ref()
+        .take(numkeys as usize)
+        .map(extract_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    let from = extract_string(args.next().expect("where was counted"))?.to_ascii_uppercase();
+    let count = match (args.next(), args.next(), args.next()) {
+        (None, _, _) => 1,
+        (Some(RespFrame::BulkString(ref opt)), Some(count), None)
+            if opt.as_ref().eq_ignore_ascii_case(b"count") =>
+        {
+            match extract_integer(count)? {
+                count if count > 0 => count as usize,
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "count should be greater than 0".to_string(),
+                    ))
+                }
+            }
+        }
+        _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+    };
+    Ok((keys, from, count))
+}
+
+/// Builds the request array a client sends for `args`.
+#[cfg(test)]
+pub(crate) fn array(args: &[&str]) -> RespArray {
+    RespArray::new(
+        args.iter()
+            .map(|a| BulkString::new(*a).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,8 +1050,48 @@ mod tests {
         assert!(res.is_err());
         assert_eq!(
             res.unwrap_err().to_string(),
-            "Invalid argument: length of get command arguments must be 1".to_string()
+            "ERR wrong number of arguments for 'get' command".to_string()
         );
         Ok(())
     }
+
+    #[test]
+    fn test_check_request_limits() -> anyhow::Result<()> {
+        let limits = RequestLimits {
+            max_args: 3,
+            max_request_size: 10,
+        };
+        let frame = |args: &[&str]| -> RespFrame { array(args).into() };
+
+        check_request_limits(&frame(&["set", "k", "v"]), &limits)?;
+
+        let res = check_request_limits(&frame(&["sadd", "k", "a", "b"]), &limits);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "ERR too many arguments: 4, max allowed is 3"
+        );
+
+        let res = check_request_limits(&frame(&["set", "k", "0123456789"]), &limits);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "ERR request too large: 14 bytes, max allowed is 10 bytes"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_every_registered_command_converts() {
+        for spec in registry::COMMAND_TABLE {
+            let _ = Command::try_from(array(&[spec.name]));
+            let _ = Command::try_from(array(&[spec.name, "help"]));
+        }
+
+        let Err(err) = Command::try_from(array(&["asking", "help"])) else {
+            panic!("asking must be refused outside a connection");
+        };
+        assert_eq!(
+            err.to_string(),
+            "ERR asking is only allowed on a client connection"
+        );
+    }
 }
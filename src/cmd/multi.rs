@@ -0,0 +1,226 @@
+use crate::{RespArray, RespFrame, SimpleString};
+
+use super::{
+    err::CommandError,
+    extract_args, extract_string,
+    plugin::Plugins,
+    registry::{arity_matches, lookup_command},
+    validate_command,
+};
+
+/// MULTI, EXEC, DISCARD and WATCH, which the connection handles rather than queuing
+/// them, and UNWATCH.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TransactionCommand {
+    Multi,
+    Exec,
+    Discard,
+    /// Fail the next EXEC if one of the keys is modified before it.
+    Watch(Vec<String>),
+    /// Forget the watched keys. Queued in a transaction, like other commands.
+    Unwatch,
+}
+
+/// The commands a connection queued since MULTI.
+#[derive(Debug, Default)]
+pub(crate) struct Transaction {
+    queued: Vec<RespFrame>,
+    /// The first key of the queued commands, whose slot the keys of the other
+    /// commands must share in cluster mode.
+    first_key: Option<String>,
+    /// Whether a command was refused while queuing, EXEC then discards the transaction.
+    aborted: bool,
+}
+
+impl TransactionCommand {
+    pub fn matches(frame: &RespFrame) -> bool {
+        super::command_name_in(frame, &["multi", "exec", "discard", "watch", "unwatch"])
+    }
+}
+
+impl TryFrom<RespFrame> for TransactionCommand {
+    type Error = CommandError;
+
+    fn try_from(value: RespFrame) -> Result<Self, Self::Error> {
+        let RespFrame::Array(value) = value else {
+            return Err(CommandError::InvalidCommand(
+                "Command must be an Array".to_string(),
+            ));
+        };
+        let name = match value.first() {
+            Some(RespFrame::BulkString(c)) => c.as_ref().to_ascii_lowercase(),
+            _ => vec![],
+        };
+        let (name, cmd) = match name.as_slice() {
+            b"multi" => ("multi", Self::Multi),
+            b"exec" => ("exec", Self::Exec),
+            b"discard" => ("discard", Self::Discard),
+            b"unwatch" => ("unwatch", Self::Unwatch),
+            b"watch" => {
+                if value.len() < 2 {
+                    return Err(CommandError::WrongArity("watch".to_string()));
+                }
+                let keys = extract_args(value, 1)?
+                    .into_iter()
+                    .map(extract_string)
+                    .collect::<Result<_, _>>()?;
+                return Ok(Self::Watch(keys));
+            }
+            _ => {
+                return Err(CommandError::InvalidCommand(
+                    "expected a transaction command".to_string(),
+                ))
+            }
+        };
+        validate_command(&value, name, 0)?;
+        Ok(cmd)
+    }
+}
+
+impl Transaction {
+    /// Queue the command of the frame, replying QUEUED. A command which is unknown
+    /// or has the wrong number of arguments is refused and aborts the transaction,
+    /// like Redis does; other errors are only found when EXEC runs the command.
+    ///
+    /// The keys of the command, after the first key queued, are checked by `route`:
+    /// in cluster mode, a command redirected to another node or with keys in another
+    /// slot than those queued before aborts the transaction too.
+    pub fn queue(
+        &mut self,
+        frame: RespFrame,
+        plugins: &Plugins,
+        route: impl FnOnce(&[String]) -> Result<(), CommandError>,
+    ) -> RespFrame {
+        // the request could not be decoded, reply with the protocol error.
+        if let RespFrame::Error(_) = frame {
+            self.aborted = true;
+            return frame;
+        }
+        let res = check_queued(&frame, plugins).and_then(|keys| {
+            let keys: Vec<String> = self.first_key.iter().cloned().chain(keys).collect();
+            route(&keys)?;
+            self.first_key = keys.into_iter().next();
+            Ok(())
+        });
+        match res {
+            Ok(()) => {
+                self.queued.push(frame);
+                SimpleString::new("QUEUED").into()
+            }
+            Err(e) => {
+                self.aborted = true;
+                e.into()
+            }
+        }
+    }
+
+    /// Make EXEC discard the transaction, after a command was refused before queuing.
+    pub fn abort(&mut self) {
+        self.aborted = true;
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    pub fn into_queued(self) -> Vec<RespFrame> {
+        self.queued
+    }
+}
+
+/// The keys of the command which can be queued.
+fn check_queued(frame: &RespFrame, plugins: &Plugins) -> Result<Vec<String>, CommandError> {
+    let args = match frame {
+        RespFrame::Array(RespArray(Some(args))) => args,
+        _ => {
+            return Err(CommandError::InvalidCommand(
+                "Command must be an Array".to_string(),
+            ))
+        }
+    };
+    let Some(RespFrame::BulkString(name)) = args.first() else {
+        return Err(CommandError::InvalidCommand(
+            "Command must have a BulkString as the first argument".to_string(),
+        ));
+    };
+    let (name, arity, keys) = match (lookup_command(name.as_ref()), plugins.get(name.as_ref())) {
+        (_, Some(plugin)) => (plugin.name().to_string(), plugin.arity(), vec![]),
+        (Some(spec), None) => (spec.name.to_string(), spec.arity, spec.keys(args)),
+        (None, None) => return Err(CommandError::unknown_command(args)),
+    };
+    if !arity_matches(arity, args.len()) {
+        return Err(CommandError::WrongArity(name));
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::array;
+
+    use super::*;
+
+    fn frame(args: &[&str]) -> RespFrame {
+        array(args).into()
+    }
+
+    #[test]
+    fn test_transaction_command_from_frame() -> anyhow::Result<()> {
+        assert!(TransactionCommand::matches(&frame(&["Multi"])));
+        assert!(!TransactionCommand::matches(&frame(&["get", "multi"])));
+        assert_eq!(
+            TransactionCommand::try_from(frame(&["EXEC"]))?,
+            TransactionCommand::Exec
+        );
+        assert!(TransactionCommand::try_from(frame(&["discard", "now"])).is_err());
+        assert_eq!(
+            TransactionCommand::try_from(frame(&["watch", "a", "b"]))?,
+            TransactionCommand::Watch(vec!["a".to_string(), "b".to_string()])
+        );
+        assert!(TransactionCommand::try_from(frame(&["watch"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_queue() {
+        let plugins = Plugins::default();
+        let mut transaction = Transaction::default();
+        assert_eq!(
+            transaction.queue(frame(&["set", "key", "value"]), &plugins, |_| Ok(())),
+            SimpleString::new("QUEUED").into()
+        );
+        assert!(!transaction.is_aborted());
+        // errors found when running the command don't abort the transaction.
+        transaction.queue(frame(&["incr", "key"]), &plugins, |_| Ok(()));
+        assert!(!transaction.is_aborted());
+        assert_eq!(transaction.into_queued().len(), 2);
+
+        let mut transaction = Transaction::default();
+        let reply = transaction.queue(frame(&["get"]), &plugins, |_| Ok(()));
+        assert_eq!(reply, CommandError::WrongArity("get".to_string()).into());
+        assert!(transaction.is_aborted());
+
+        let mut transaction = Transaction::default();
+        transaction.queue(frame(&["nosuchcommand", "key"]), &plugins, |_| Ok(()));
+        assert!(transaction.is_aborted());
+        assert!(transaction.into_queued().is_empty());
+
+        // the keys of later commands are routed with the first key queued.
+        let mut transaction = Transaction::default();
+        transaction.queue(frame(&["echo", "hi"]), &plugins, |keys| {
+            assert!(keys.is_empty());
+            Ok(())
+        });
+        transaction.queue(frame(&["sintercard", "2", "a", "b"]), &plugins, |keys| {
+            assert_eq!(keys, ["a", "b"]);
+            Ok(())
+        });
+        let reply = transaction.queue(frame(&["get", "c"]), &plugins, |keys| {
+            assert_eq!(keys, ["a", "c"]);
+            Err(CommandError::CrossSlot)
+        });
+        assert_eq!(reply, CommandError::CrossSlot.into());
+        assert!(transaction.is_aborted());
+        assert_eq!(transaction.into_queued().len(), 2);
+    }
+}
@@ -0,0 +1,104 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{Backend, RespFrame};
+
+use super::{err::CommandError, registry::arity_matches};
+
+/// A custom command provided by an embedder.
+///
+/// Plugins are registered on the [`ServerBuilder`](crate::ServerBuilder) and
+/// dispatched by name like the builtin commands. Any state the plugin needs
+/// can live in the plugin itself, it is shared by all connections.
+pub trait CommandPlugin: Send + Sync + 'static {
+    /// Name of the command, matched case-insensitively.
+    fn name(&self) -> &str;
+
+    /// Arity of the command, following the Redis convention used by
+    /// [`CommandSpec`](crate::CommandSpec): the command name is included,
+    /// and a negative value means at least `-arity` arguments.
+    fn arity(&self) -> i64;
+
+    /// Execute the command. `args` does not contain the command name.
+    fn execute(&self, args: Vec<RespFrame>, backend: &Backend) -> RespFrame;
+}
+
+/// Registered plugins, keyed by lowercase command name.
+#[derive(Clone, Default)]
+pub struct Plugins(Arc<HashMap<String, Arc<dyn CommandPlugin>>>);
+
+impl Plugins {
+    pub(crate) fn new(plugins: HashMap<String, Arc<dyn CommandPlugin>>) -> Self {
+        Self(Arc::new(plugins))
+    }
+
+    pub fn get(&self, name: &[u8]) -> Option<&Arc<dyn CommandPlugin>> {
+        let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+        self.0.get(&name)
+    }
+
+    /// Run the plugin with the frame's arguments after checking the arity.
+    pub(crate) fn execute(
+        plugin: &dyn CommandPlugin,
+        mut args: Vec<RespFrame>,
+        backend: &Backend,
+    ) -> RespFrame {
+        if !arity_matches(plugin.arity(), args.len()) {
+            return CommandError::WrongArity(plugin.name().to_string()).into();
+        }
+        args.remove(0);
+        plugin.execute(args, backend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    use crate::{BulkString, SimpleError};
+
+    use super::*;
+
+    struct Counter(AtomicI64);
+
+    impl CommandPlugin for Counter {
+        fn name(&self) -> &str {
+            "counter.incr"
+        }
+
+        fn arity(&self) -> i64 {
+            1
+        }
+
+        fn execute(&self, _args: Vec<RespFrame>, _backend: &Backend) -> RespFrame {
+            (self.0.fetch_add(1, Ordering::Relaxed) + 1).into()
+        }
+    }
+
+    #[test]
+    fn test_plugins_execute() {
+        let counter: Arc<dyn CommandPlugin> = Arc::new(Counter(AtomicI64::new(0)));
+        let plugins = Plugins::new([("counter.incr".to_string(), counter)].into());
+        let backend = Backend::new();
+
+        let plugin = plugins.get(b"COUNTER.INCR").unwrap();
+        let args = vec![BulkString::new("counter.incr").into()];
+        assert_eq!(
+            Plugins::execute(plugin.as_ref(), args.clone(), &backend),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            Plugins::execute(plugin.as_ref(), args, &backend),
+            RespFrame::Integer(2)
+        );
+
+        let args = vec![
+            BulkString::new("counter.incr").into(),
+            BulkString::new("extra").into(),
+        ];
+        assert_eq!(
+            Plugins::execute(plugin.as_ref(), args, &backend),
+            SimpleError::new("ERR wrong number of arguments for 'counter.incr' command").into()
+        );
+        assert!(plugins.get(b"unknown").is_none());
+    }
+}
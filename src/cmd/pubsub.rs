@@ -0,0 +1,118 @@
+use crate::{Backend, BulkString, RespArray, RespFrame};
+
+use super::{
+    err::CommandError, extract_args, extract_string, validate_command, CommandExecutor, SPublish,
+    SSubscribe, SUnsubscribe,
+};
+
+impl CommandExecutor for SPublish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.spublish(&self.channel, self.message).into()
+    }
+}
+
+impl TryFrom<RespArray> for SSubscribe {
+    type Error = CommandError;
+
+    // ssubscribe shardchannel [shardchannel ...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 2 {
+            return Err(CommandError::WrongArity("ssubscribe".to_string()));
+        }
+        validate_command(&value, "ssubscribe", value.len() - 1)?;
+        let channels = extract_args(value, 1)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SSubscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for SUnsubscribe {
+    type Error = CommandError;
+
+    // sunsubscribe [shardchannel [shardchannel ...]]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "sunsubscribe", value.len() - 1)?;
+        let channels = extract_args(value, 1)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SUnsubscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for SPublish {
+    type Error = CommandError;
+
+    // spublish shardchannel message
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "spublish", 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(channel), Some(RespFrame::BulkString(message))) => Ok(SPublish {
+                channel: extract_string(channel)?,
+                message,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid arguments for spublish".into(),
+            )),
+        }
+    }
+}
+
+/// Build the confirmation reply of a (un)subscription, e.g. `["ssubscribe", channel, count]`.
+pub fn subscription_reply(kind: &str, channel: Option<&str>, count: usize) -> RespFrame {
+    let channel = channel.map(BulkString::new).unwrap_or(BulkString::null());
+    RespArray::new(vec![
+        BulkString::new(kind).into(),
+        channel.into(),
+        (count as i64).into(),
+    ])
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::{array, ConnectionCommand};
+
+    use super::*;
+
+    fn frame(args: &[&str]) -> RespFrame {
+        array(args).into()
+    }
+
+    #[test]
+    fn test_subscribe_command_from_resp_frame() -> anyhow::Result<()> {
+        let f = frame(&["SSUBSCRIBE", "a", "b"]);
+        assert!(ConnectionCommand::matches(&f));
+        assert!(ConnectionCommand::is_subscription(&f));
+        match ConnectionCommand::try_from(f)? {
+            ConnectionCommand::SSubscribe(cmd) => assert_eq!(cmd.channels, vec!["a", "b"]),
+            cmd => panic!("unexpected command: {:?}", cmd),
+        }
+
+        let f = frame(&["sunsubscribe"]);
+        assert!(ConnectionCommand::matches(&f));
+        match ConnectionCommand::try_from(f)? {
+            ConnectionCommand::SUnsubscribe(cmd) => assert!(cmd.channels.is_empty()),
+            cmd => panic!("unexpected command: {:?}", cmd),
+        }
+
+        assert!(!ConnectionCommand::matches(&frame(&["get", "a"])));
+        assert!(ConnectionCommand::try_from(frame(&["ssubscribe"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_spublish_from_resp_array() -> anyhow::Result<()> {
+        let cmd = SPublish::try_from(RespArray::new(vec![
+            BulkString::new("spublish").into(),
+            BulkString::new("ch").into(),
+            BulkString::new("msg").into(),
+        ]))?;
+        assert_eq!(cmd.channel, "ch");
+        assert_eq!(cmd.message, BulkString::new("msg"));
+        Ok(())
+    }
+}
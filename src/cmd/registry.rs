@@ -0,0 +1,1122 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::BitOr,
+};
+
+use bytes::Bytes;
+
+use crate::{BulkString, RespFrame};
+
+use super::err::CommandError;
+
+/// Flags describing how a command interacts with the keyspace.
+/// They mirror the flags used by Redis in its command table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CommandFlags(u8);
+
+impl CommandFlags {
+    /// The command may modify the keyspace.
+    pub const WRITE: Self = Self(1);
+    /// The command never modifies the keyspace.
+    pub const READONLY: Self = Self(1 << 1);
+    /// The command may increase memory usage, so it should be rejected when out of memory.
+    pub const DENYOOM: Self = Self(1 << 2);
+    /// The command is not allowed inside scripts.
+    pub const NOSCRIPT: Self = Self(1 << 3);
+    /// The command is related to pub/sub.
+    pub const PUBSUB: Self = Self(1 << 4);
+    /// The command may wait for one of its keys to be written.
+    pub const BLOCKING: Self = Self(1 << 5);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Names of the flags, as COMMAND INFO replies them.
+    pub fn names(self) -> Vec<&'static str> {
+        [
+            (Self::WRITE, "write"),
+            (Self::READONLY, "readonly"),
+            (Self::DENYOOM, "denyoom"),
+            (Self::NOSCRIPT, "noscript"),
+            (Self::PUBSUB, "pubsub"),
+            (Self::BLOCKING, "blocking"),
+        ]
+        .into_iter()
+        .filter_map(|(flag, name)| self.contains(flag).then_some(name))
+        .collect()
+    }
+}
+
+impl BitOr for CommandFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.union(rhs)
+    }
+}
+
+/// Static metadata of a subcommand of a container command, shown by its HELP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubcommandSpec {
+    pub name: &'static str,
+    /// Arguments synopsis, e.g. `<key> [SAMPLES <count>]`.
+    pub args: &'static str,
+    pub summary: &'static str,
+}
+
+impl SubcommandSpec {
+    const fn new(name: &'static str, args: &'static str, summary: &'static str) -> Self {
+        Self {
+            name,
+            args,
+            summary,
+        }
+    }
+}
+
+/// Documentation of a command, served by COMMAND DOCS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CommandDocs {
+    /// Group of the command, e.g. `string` or `hash`.
+    pub group: &'static str,
+    /// Version of Redis, or of the module for module commands, which introduced the
+    /// command. Commands of R-Redis only have the version of R-Redis introducing them.
+    pub since: &'static str,
+    /// Arguments synopsis, e.g. `<key> [NX|XX] [<path> ...]`, which COMMAND DOCS
+    /// details argument by argument. Subcommands of containers are documented apart.
+    pub args: &'static str,
+    pub summary: &'static str,
+}
+
+/// How a command with keys at positions depending on its arguments locates them,
+/// like the commands flagged `movablekeys` by Redis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovableKeys {
+    /// The number of keys at this index, followed by the keys, like SINTERCARD.
+    NumKeys(usize),
+    /// The key of MIGRATE, or the keys following its KEYS option when the key is empty.
+    Migrate,
+}
+
+/// Static metadata of a command.
+///
+/// `arity` follows the Redis convention: a positive value means the exact
+/// number of arguments (command name included), a negative value means
+/// at least `-arity` arguments.
+///
+/// `first_key`, `last_key` and `key_step` locate the keys among the arguments
+/// like the legacy Redis key specs do: a `last_key` of -1 means the last argument,
+/// and a `first_key` of 0 means the command takes no keys (or not at fixed positions).
+/// The commands taking keys at other positions have `movable_keys`.
+///
+/// Container commands list their `subcommands`, which also gives them a HELP subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub arity: i64,
+    pub flags: CommandFlags,
+    pub first_key: i64,
+    pub last_key: i64,
+    pub key_step: i64,
+    pub movable_keys: Option<MovableKeys>,
+    pub subcommands: &'static [SubcommandSpec],
+    pub docs: CommandDocs,
+}
+
+impl CommandSpec {
+    const fn new(
+        name: &'static str,
+        arity: i64,
+        flags: CommandFlags,
+        first_key: i64,
+        last_key: i64,
+        key_step: i64,
+    ) -> Self {
+        Self {
+            name,
+            arity,
+            flags,
+            first_key,
+            last_key,
+            key_step,
+            movable_keys: None,
+            subcommands: &[],
+            docs: CommandDocs {
+                group: "",
+                since: "",
+                args: "",
+                summary: "",
+            },
+        }
+    }
+
+    const fn with_subcommands(self, subcommands: &'static [SubcommandSpec]) -> Self {
+        Self {
+            subcommands,
+            ..self
+        }
+    }
+
+    const fn with_movable_keys(self, movable_keys: MovableKeys) -> Self {
+        Self {
+            movable_keys: Some(movable_keys),
+            ..self
+        }
+    }
+
+    const fn with_docs(
+        self,
+        group: &'static str,
+        since: &'static str,
+        args: &'static str,
+        summary: &'static str,
+    ) -> Self {
+        Self {
+            docs: CommandDocs {
+                group,
+                since,
+                args,
+                summary,
+            },
+            ..self
+        }
+    }
+
+    pub fn is_container(&self) -> bool {
+        !self.subcommands.is_empty()
+    }
+
+    /// Indexes of the key arguments of a command invocation with `argc` arguments,
+    /// the command name included.
+    pub fn key_indexes(&self, argc: usize) -> Vec<usize> {
+        if self.first_key <= 0 || self.key_step <= 0 {
+            return vec![];
+        }
+        let last = if self.last_key < 0 {
+            argc as i64 + self.last_key
+        } else {
+            self.last_key.min(argc as i64 - 1)
+        };
+        (self.first_key..=last)
+            .step_by(self.key_step as usize)
+            .map(|i| i as usize)
+            .collect()
+    }
+
+    /// Indexes of the key arguments of a command invocation, the command name included,
+    /// whether the command takes its keys at fixed positions or not.
+    pub fn key_indexes_in(&self, args: &[RespFrame]) -> Vec<usize> {
+        let arg = |i: usize| match args.get(i) {
+            Some(RespFrame::BulkString(arg)) => Some(arg.as_ref()),
+            _ => None,
+        };
+        match self.movable_keys {
+            None => self.key_indexes(args.len()),
+            Some(MovableKeys::NumKeys(i)) => {
+                let numkeys = arg(i)
+                    .and_then(|n| std::str::from_utf8(n).ok()?.parse::<usize>().ok())
+                    .unwrap_or(0);
+                (i + 1..args.len().min((i + 1).saturating_add(numkeys))).collect()
+            }
+            Some(MovableKeys::Migrate) => match arg(3) {
+                Some(b"") => (6..args.len())
+                    .find(|i| arg(*i).is_some_and(|a| a.eq_ignore_ascii_case(b"keys")))
+                    .map_or(vec![], |i| (i + 1..args.len()).collect()),
+                Some(_) => vec![3],
+                None => vec![],
+            },
+        }
+    }
+
+    /// The keys among the arguments of a command invocation.
+    pub fn keys(&self, args: &[RespFrame]) -> Vec<String> {
+        self.key_indexes_in(args)
+            .into_iter()
+            .filter_map(|i| match args.get(i) {
+                Some(RespFrame::BulkString(key)) => {
+                    Some(String::from_utf8_lossy(key.as_ref()).into_owned())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn is_write(&self) -> bool {
+        self.flags.contains(CommandFlags::WRITE)
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.flags.contains(CommandFlags::READONLY)
+    }
+
+    pub fn is_denyoom(&self) -> bool {
+        self.flags.contains(CommandFlags::DENYOOM)
+    }
+
+    pub fn is_noscript(&self) -> bool {
+        self.flags.contains(CommandFlags::NOSCRIPT)
+    }
+
+    pub fn is_blocking(&self) -> bool {
+        self.flags.contains(CommandFlags::BLOCKING)
+    }
+}
+
+const WRITE_DENYOOM: CommandFlags = CommandFlags::WRITE.union(CommandFlags::DENYOOM);
+const PUBSUB_NOSCRIPT: CommandFlags = CommandFlags::PUBSUB.union(CommandFlags::NOSCRIPT);
+const WRITE_BLOCKING: CommandFlags = CommandFlags::WRITE.union(CommandFlags::BLOCKING);
+
+const CLIENT_SUBCOMMANDS: &[SubcommandSpec] = &[
+    SubcommandSpec::new(
+        "attributes",
+        "(ON|OFF)",
+        "Attach the remaining time to live of the value to the replies of GET and HGET.",
+    ),
+    SubcommandSpec::new(
+        "info",
+        "",
+        "Return information about the current client connection.",
+    ),
+    SubcommandSpec::new(
+        "trace",
+        "(ON|OFF)",
+        "Log the raw frames read and written by the current connection.",
+    ),
+    SubcommandSpec::new(
+        "tracking",
+        "(ON|OFF) [BCAST] [PREFIX <prefix> ...]",
+        "Control server assisted client side caching.",
+    ),
+];
+
+const MEMORY_SUBCOMMANDS: &[SubcommandSpec] = &[
+    SubcommandSpec::new("doctor", "", "Return memory problems reports."),
+    SubcommandSpec::new(
+        "purge",
+        "",
+        "Re-encode values which shrank into their most compact representation.",
+    ),
+    SubcommandSpec::new(
+        "usage",
+        "<key> [SAMPLES <count>]",
+        "Return memory in bytes used by <key> and its value.",
+    ),
+];
+
+const DEBUG_SUBCOMMANDS: &[SubcommandSpec] = &[
+    SubcommandSpec::new(
+        "object",
+        "<key>",
+        "Show low level info about the key and associated value.",
+    ),
+    SubcommandSpec::new(
+        "reload",
+        "",
+        "Save the keyspace as a snapshot and load it back in place.",
+    ),
+    SubcommandSpec::new(
+        "reply-delay",
+        "<milliseconds>",
+        "Delay every write of the replies of this connection, 0 to disable.",
+    ),
+    SubcommandSpec::new(
+        "reply-fragment",
+        "<bytes>",
+        "Write the replies of this connection in fragments of at most <bytes>, 0 to disable.",
+    ),
+    SubcommandSpec::new(
+        "stringmatch-len",
+        "<pattern> <string>",
+        "Return 1 if the glob-style pattern matches the string, 0 otherwise.",
+    ),
+];
+
+const SLOWLOG_SUBCOMMANDS: &[SubcommandSpec] = &[
+    SubcommandSpec::new(
+        "get",
+        "[<count>]",
+        "Return top <count> entries from the slowlog (default: 10, -1 mean all).",
+    ),
+    SubcommandSpec::new("len", "", "Return the length of the slowlog."),
+    SubcommandSpec::new("reset", "", "Reset the slowlog."),
+];
+
+const CLUSTER_SUBCOMMANDS: &[SubcommandSpec] = &[
+    SubcommandSpec::new(
+        "addslots",
+        "<slot> [<slot> ...]",
+        "Assign slots to current node.",
+    ),
+    SubcommandSpec::new(
+        "countkeysinslot",
+        "<slot>",
+        "Return the number of keys in <slot>.",
+    ),
+    SubcommandSpec::new("forget", "<node-id>", "Remove a node from the cluster."),
+    SubcommandSpec::new(
+        "getkeysinslot",
+        "<slot> <count>",
+        "Return key names stored by current node in a slot.",
+    ),
+    SubcommandSpec::new("info", "", "Return information about the cluster."),
+    SubcommandSpec::new("keyslot", "<key>", "Return the hash slot for <key>."),
+    SubcommandSpec::new(
+        "meet",
+        "<ip> <port> [<bus-port>]",
+        "Connect nodes into a working cluster.",
+    ),
+    SubcommandSpec::new("myid", "", "Return the node id."),
+    SubcommandSpec::new("nodes", "", "Return cluster configuration seen by node."),
+    SubcommandSpec::new(
+        "setslot",
+        "<slot> (IMPORTING <node-id>|MIGRATING <node-id>|STABLE|NODE <node-id>)",
+        "Set slot state.",
+    ),
+];
+
+const COMMAND_SUBCOMMANDS: &[SubcommandSpec] = &[
+    SubcommandSpec::new(
+        "count",
+        "",
+        "Return the total number of commands in this server.",
+    ),
+    SubcommandSpec::new(
+        "docs",
+        "[<command-name> [<command-name> ...]]",
+        "Return documentary information about commands, all of them by default.",
+    ),
+    SubcommandSpec::new(
+        "info",
+        "[<command-name> [<command-name> ...]]",
+        "Return details about commands, all of them by default.",
+    ),
+    SubcommandSpec::new("list", "", "Return a list of all commands in this server."),
+];
+
+const OBJECT_SUBCOMMANDS: &[SubcommandSpec] = &[SubcommandSpec::new(
+    "encoding",
+    "<key>",
+    "Return the kind of internal representation used in order to store the value associated with a <key>.",
+)];
+
+/// All commands supported by the server.
+pub static COMMAND_TABLE: &[CommandSpec] = &[
+    CommandSpec::new("get", 2, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "string",
+        "1.0.0",
+        "<key>",
+        "Returns the string value of a key.",
+    ),
+    CommandSpec::new("set", 3, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "string",
+        "1.0.0",
+        "<key> <value>",
+        "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist.",
+    ),
+    CommandSpec::new("getset", 3, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "string",
+        "1.0.0",
+        "<key> <value>",
+        "Returns the previous string value of a key after setting it to a new value.",
+    ),
+    CommandSpec::new("getdel", 2, CommandFlags::WRITE, 1, 1, 1).with_docs(
+        "string",
+        "6.2.0",
+        "<key>",
+        "Returns the string value of a key after deleting the key.",
+    ),
+    CommandSpec::new("rename", 3, CommandFlags::WRITE, 1, 2, 1).with_docs(
+        "generic",
+        "1.0.0",
+        "<key> <newkey>",
+        "Renames a key and overwrites the destination.",
+    ),
+    CommandSpec::new("dump", 2, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "generic",
+        "2.6.0",
+        "<key>",
+        "Returns a serialized representation of the value stored at a key.",
+    ),
+    CommandSpec::new("restore", -4, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "generic",
+        "2.6.0",
+        "<key> <ttl> <serialized-value> [REPLACE]",
+        "Creates a key from the serialized representation of a value.",
+    ),
+    // the keys of MIGRATE may follow KEYS, it locks them itself.
+    CommandSpec::new("migrate", -6, CommandFlags::WRITE, 0, 0, 0)
+        .with_movable_keys(MovableKeys::Migrate)
+        .with_docs(
+        "generic",
+        "2.6.0",
+        "<host> <port> (<key>|\"\") <destination-db> <timeout> [COPY] [REPLACE] [KEYS <key> [<key> ...]]",
+        "Atomically transfers a key from one Redis instance to another.",
+    ),
+    CommandSpec::new("scan", -2, CommandFlags::READONLY, 0, 0, 0).with_docs(
+        "generic",
+        "2.8.0",
+        "<cursor> [MATCH <pattern>] [COUNT <count>]",
+        "Iterates over the key names in the database.",
+    ),
+    CommandSpec::new("object", -2, CommandFlags::READONLY, 2, 2, 1)
+        .with_subcommands(OBJECT_SUBCOMMANDS)
+        .with_docs(
+            "generic",
+            "2.2.3",
+            "",
+            "A container for object introspection commands.",
+        ),
+    CommandSpec::new("hget", 3, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "hash",
+        "2.0.0",
+        "<key> <field>",
+        "Returns the value of a field in a hash.",
+    ),
+    CommandSpec::new("hset", 4, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "hash",
+        "2.0.0",
+        "<key> <field> <value>",
+        "Creates or modifies the value of a field in a hash.",
+    ),
+    CommandSpec::new("hincrby", 4, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "hash",
+        "2.0.0",
+        "<key> <field> <increment>",
+        "Increments the integer value of a field in a hash by a number. Uses 0 as initial value if the field doesn't exist.",
+    ),
+    CommandSpec::new("hexpire", -6, CommandFlags::WRITE, 1, 1, 1).with_docs(
+        "hash",
+        "7.4.0",
+        "<key> <seconds> [NX|XX|GT|LT] FIELDS <numfields> <field> [<field> ...]",
+        "Set expiry for hash fields using relative time to expire (seconds).",
+    ),
+    CommandSpec::new("hpexpire", -6, CommandFlags::WRITE, 1, 1, 1).with_docs(
+        "hash",
+        "7.4.0",
+        "<key> <milliseconds> [NX|XX|GT|LT] FIELDS <numfields> <field> [<field> ...]",
+        "Set expiry for hash fields using relative time to expire (milliseconds).",
+    ),
+    CommandSpec::new("httl", -5, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "hash",
+        "7.4.0",
+        "<key> FIELDS <numfields> <field> [<field> ...]",
+        "Returns the TTL in seconds of hash fields.",
+    ),
+    CommandSpec::new("hpersist", -5, CommandFlags::WRITE, 1, 1, 1).with_docs(
+        "hash",
+        "7.4.0",
+        "<key> FIELDS <numfields> <field> [<field> ...]",
+        "Removes the expiration time of hash fields.",
+    ),
+    #[cfg(feature = "json")]
+    CommandSpec::new("json.set", -4, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "json",
+        "1.0.0",
+        "<key> <path> <value> [NX|XX]",
+        "Sets or updates the JSON value at a path.",
+    ),
+    #[cfg(feature = "json")]
+    CommandSpec::new("json.get", -2, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "json",
+        "1.0.0",
+        "<key> [<path> ...]",
+        "Gets the value at one or more paths in JSON serialized form.",
+    ),
+    #[cfg(feature = "json")]
+    CommandSpec::new("json.del", -2, CommandFlags::WRITE, 1, 1, 1).with_docs(
+        "json",
+        "1.0.0",
+        "<key> [<path>]",
+        "Deletes a value.",
+    ),
+    CommandSpec::new("incr", 2, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "string",
+        "1.0.0",
+        "<key>",
+        "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+    ),
+    CommandSpec::new("append", 3, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "string",
+        "2.0.0",
+        "<key> <value>",
+        "Appends a string to the value of a key. Creates the key if it doesn't exist.",
+    ),
+    CommandSpec::new("setrange", 4, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "string",
+        "2.2.0",
+        "<key> <offset> <value>",
+        "Overwrites a part of a string value with another by an offset. Creates the key if it doesn't exist.",
+    ),
+    CommandSpec::new("cas", 4, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "string",
+        "0.1.0",
+        "<key> <expected> <new>",
+        "Sets the string value of a key to a new value if it holds the expected one.",
+    ),
+    CommandSpec::new("hgetall", 2, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "hash",
+        "2.0.0",
+        "<key>",
+        "Returns all fields and values in a hash.",
+    ),
+    CommandSpec::new("hmget", -3, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "hash",
+        "2.0.0",
+        "<key> <field> [<field> ...]",
+        "Returns the values of all fields in a hash.",
+    ),
+    CommandSpec::new("hrandfield", -2, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "hash",
+        "6.2.0",
+        "<key> [<count> [WITHVALUES]]",
+        "Returns one or more random fields from a hash.",
+    ),
+    CommandSpec::new("echo", 2, CommandFlags::empty(), 0, 0, 0).with_docs(
+        "connection",
+        "1.0.0",
+        "<message>",
+        "Returns the given string.",
+    ),
+    CommandSpec::new("sadd", -3, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "set",
+        "1.0.0",
+        "<key> <member> [<member> ...]",
+        "Adds one or more members to a set. Creates the key if it doesn't exist.",
+    ),
+    CommandSpec::new("sismember", 3, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "set",
+        "1.0.0",
+        "<key> <member>",
+        "Determines whether a member belongs to a set.",
+    ),
+    CommandSpec::new("smove", 4, CommandFlags::WRITE, 1, 2, 1).with_docs(
+        "set",
+        "1.0.0",
+        "<source> <destination> <member>",
+        "Moves a member from one set to another.",
+    ),
+    CommandSpec::new("sintercard", -3, CommandFlags::READONLY, 0, 0, 0)
+        .with_movable_keys(MovableKeys::NumKeys(1))
+        .with_docs(
+        "set",
+        "7.0.0",
+        "<numkeys> <key> [<key> ...] [LIMIT <limit>]",
+        "Returns the number of members of the intersect of multiple sets.",
+    ),
+    CommandSpec::new("srandmember", -2, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "set",
+        "1.0.0",
+        "<key> [<count>]",
+        "Get one or multiple random members from a set.",
+    ),
+    CommandSpec::new("lpush", -3, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "list",
+        "1.0.0",
+        "<key> <element> [<element> ...]",
+        "Prepends one or more elements to a list. Creates the key if it doesn't exist.",
+    ),
+    CommandSpec::new("rpush", -3, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "list",
+        "1.0.0",
+        "<key> <element> [<element> ...]",
+        "Appends one or more elements to a list. Creates the key if it doesn't exist.",
+    ),
+    CommandSpec::new("lpop", -2, CommandFlags::WRITE, 1, 1, 1).with_docs(
+        "list",
+        "1.0.0",
+        "<key> [<count>]",
+        "Returns the first elements in a list after removing them. Deletes the list if the last element was popped.",
+    ),
+    CommandSpec::new("rpop", -2, CommandFlags::WRITE, 1, 1, 1).with_docs(
+        "list",
+        "1.0.0",
+        "<key> [<count>]",
+        "Returns and removes the last elements of a list. Deletes the list if the last element was popped.",
+    ),
+    CommandSpec::new("lmpop", -4, CommandFlags::WRITE, 0, 0, 0)
+        .with_movable_keys(MovableKeys::NumKeys(1))
+        .with_docs(
+        "list",
+        "7.0.0",
+        "<numkeys> <key> [<key> ...] (LEFT|RIGHT) [COUNT <count>]",
+        "Returns multiple elements from a list after removing them. Deletes the list if the last element was popped.",
+    ),
+    CommandSpec::new("blmpop", -5, WRITE_BLOCKING, 0, 0, 0)
+        .with_movable_keys(MovableKeys::NumKeys(2))
+        .with_docs(
+        "list",
+        "7.0.0",
+        "<timeout> <numkeys> <key> [<key> ...] (LEFT|RIGHT) [COUNT <count>]",
+        "Pops the first element from one of multiple lists. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+    ),
+    CommandSpec::new("llen", 2, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "list",
+        "1.0.0",
+        "<key>",
+        "Returns the length of a list.",
+    ),
+    CommandSpec::new("lrange", 4, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "list",
+        "1.0.0",
+        "<key> <start> <stop>",
+        "Returns a range of elements from a list.",
+    ),
+    CommandSpec::new("lindex", 3, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "list",
+        "1.0.0",
+        "<key> <index>",
+        "Returns an element from a list by its index.",
+    ),
+    CommandSpec::new("linsert", 5, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "list",
+        "2.2.0",
+        "<key> (BEFORE|AFTER) <pivot> <element>",
+        "Inserts an element before or after another element in a list.",
+    ),
+    CommandSpec::new("lrem", 4, CommandFlags::WRITE, 1, 1, 1).with_docs(
+        "list",
+        "1.0.0",
+        "<key> <count> <element>",
+        "Removes elements from a list. Deletes the list if the last element was removed.",
+    ),
+    CommandSpec::new("zadd", -4, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "sorted-set",
+        "1.2.0",
+        "<key> [NX|XX] [GT|LT] [CH] [INCR] <score> <member> [<score> <member> ...]",
+        "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist.",
+    ),
+    CommandSpec::new("zrem", -3, CommandFlags::WRITE, 1, 1, 1).with_docs(
+        "sorted-set",
+        "1.2.0",
+        "<key> <member> [<member> ...]",
+        "Removes one or more members from a sorted set. Deletes the sorted set if all members were removed.",
+    ),
+    CommandSpec::new("zmpop", -4, CommandFlags::WRITE, 0, 0, 0)
+        .with_movable_keys(MovableKeys::NumKeys(1))
+        .with_docs(
+        "sorted-set",
+        "7.0.0",
+        "<numkeys> <key> [<key> ...] (MIN|MAX) [COUNT <count>]",
+        "Returns the highest- or lowest-scoring members from one or more sorted sets after removing them. Deletes the sorted set if the last member was popped.",
+    ),
+    CommandSpec::new("bzmpop", -5, WRITE_BLOCKING, 0, 0, 0)
+        .with_movable_keys(MovableKeys::NumKeys(2))
+        .with_docs(
+        "sorted-set",
+        "7.0.0",
+        "<timeout> <numkeys> <key> [<key> ...] (MIN|MAX) [COUNT <count>]",
+        "Removes and returns a member by score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last element was popped.",
+    ),
+    CommandSpec::new("zscore", 3, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "sorted-set",
+        "1.2.0",
+        "<key> <member>",
+        "Returns the score of a member in a sorted set.",
+    ),
+    CommandSpec::new("zcard", 2, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "sorted-set",
+        "1.2.0",
+        "<key>",
+        "Returns the number of members in a sorted set.",
+    ),
+    CommandSpec::new("zrank", 3, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "sorted-set",
+        "2.0.0",
+        "<key> <member>",
+        "Returns the index of a member in a sorted set ordered by ascending scores.",
+    ),
+    CommandSpec::new("zrange", -4, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "sorted-set",
+        "1.2.0",
+        "<key> <start> <stop> [BYSCORE | BYLEX] [REV] [LIMIT <offset> <count>] [WITHSCORES]",
+        "Returns members in a sorted set within a range of indexes.",
+    ),
+    CommandSpec::new("zrangebyscore", -4, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "sorted-set",
+        "1.0.5",
+        "<key> <min> <max> [WITHSCORES] [LIMIT <offset> <count>]",
+        "Returns members in a sorted set within a range of scores.",
+    ),
+    CommandSpec::new("zrangestore", -5, WRITE_DENYOOM, 1, 2, 1).with_docs(
+        "sorted-set",
+        "6.2.0",
+        "<dst> <src> <min> <max> [BYSCORE | BYLEX] [REV] [LIMIT <offset> <count>]",
+        "Stores a range of members from sorted set in a key.",
+    ),
+    CommandSpec::new("bf.reserve", -4, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "bf",
+        "1.0.0",
+        "<key> <error_rate> <capacity> [EXPANSION <expansion>] [NONSCALING]",
+        "Creates a new Bloom filter.",
+    ),
+    CommandSpec::new("bf.add", 3, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "bf",
+        "1.0.0",
+        "<key> <item>",
+        "Adds an item to a Bloom filter.",
+    ),
+    CommandSpec::new("bf.madd", -3, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "bf",
+        "1.0.0",
+        "<key> <item> [<item> ...]",
+        "Adds one or more items to a Bloom filter. The filter is created if it does not exist.",
+    ),
+    CommandSpec::new("bf.exists", 3, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "bf",
+        "1.0.0",
+        "<key> <item>",
+        "Checks whether an item exists in a Bloom filter.",
+    ),
+    CommandSpec::new("bf.scandump", 3, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "bf",
+        "1.0.0",
+        "<key> <iterator>",
+        "Begins an incremental save of the bloom filter.",
+    ),
+    CommandSpec::new("bf.loadchunk", 4, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "bf",
+        "1.0.0",
+        "<key> <iterator> <data>",
+        "Restores a filter previously saved using SCANDUMP.",
+    ),
+    CommandSpec::new("ts.create", -2, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "timeseries",
+        "1.0.0",
+        "<key> [RETENTION <retentionPeriod>] [LABELS <label> <value> [<label> <value> ...]]",
+        "Creates a new time series.",
+    ),
+    CommandSpec::new("ts.add", -4, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "timeseries",
+        "1.0.0",
+        "<key> (<timestamp>|*) <value> [RETENTION <retentionPeriod>]",
+        "Appends a sample to a time series.",
+    ),
+    CommandSpec::new("ts.range", -4, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "timeseries",
+        "1.0.0",
+        "<key> <fromTimestamp> <toTimestamp> [COUNT <count>] [AGGREGATION <aggregator> <bucketDuration>]",
+        "Queries a range in forward direction.",
+    ),
+    CommandSpec::new("ts.createrule", 6, CommandFlags::WRITE, 1, 2, 1).with_docs(
+        "timeseries",
+        "1.0.0",
+        "<sourceKey> <destKey> AGGREGATION <aggregator> <bucketDuration>",
+        "Creates a compaction rule.",
+    ),
+    CommandSpec::new("ft.create", -5, CommandFlags::WRITE, 0, 0, 0).with_docs(
+        "search",
+        "1.0.0",
+        "<index> [ON HASH] [PREFIX <count> <prefix> [<prefix> ...]] SCHEMA <field> (TAG|NUMERIC) [<field> (TAG|NUMERIC) ...]",
+        "Creates an index with the given spec.",
+    ),
+    CommandSpec::new("ft.search", 3, CommandFlags::READONLY, 0, 0, 0).with_docs(
+        "search",
+        "1.0.0",
+        "<index> <query>",
+        "Searches the index with a query, returning the matching keys.",
+    ),
+    CommandSpec::new("ft.dropindex", 2, CommandFlags::WRITE, 0, 0, 0).with_docs(
+        "search",
+        "2.0.0",
+        "<index>",
+        "Deletes the index.",
+    ),
+    CommandSpec::new("sort", -2, WRITE_DENYOOM, 1, 1, 1).with_docs(
+        "generic",
+        "1.0.0",
+        "<key> [BY <pattern>] [LIMIT <offset> <count>] [GET <pattern> [GET <pattern> ...]] [ASC|DESC] [ALPHA] [STORE <destination>]",
+        "Sorts the elements in a list, a set, or a sorted set, optionally storing the result.",
+    ),
+    CommandSpec::new("sort_ro", -2, CommandFlags::READONLY, 1, 1, 1).with_docs(
+        "generic",
+        "7.0.0",
+        "<key> [BY <pattern>] [LIMIT <offset> <count>] [GET <pattern> [GET <pattern> ...]] [ASC|DESC] [ALPHA]",
+        "Returns the sorted elements of a list, a set, or a sorted set.",
+    ),
+    CommandSpec::new("ssubscribe", -2, PUBSUB_NOSCRIPT, 0, 0, 0).with_docs(
+        "pubsub",
+        "7.0.0",
+        "<shardchannel> [<shardchannel> ...]",
+        "Listens for messages published to shard channels.",
+    ),
+    CommandSpec::new("sunsubscribe", -1, PUBSUB_NOSCRIPT, 0, 0, 0).with_docs(
+        "pubsub",
+        "7.0.0",
+        "[<shardchannel> [<shardchannel> ...]]",
+        "Stops listening to messages posted to shard channels.",
+    ),
+    CommandSpec::new("spublish", 3, CommandFlags::PUBSUB, 0, 0, 0).with_docs(
+        "pubsub",
+        "7.0.0",
+        "<shardchannel> <message>",
+        "Posts a message to a shard channel.",
+    ),
+    CommandSpec::new("client", -2, CommandFlags::NOSCRIPT, 0, 0, 0)
+        .with_subcommands(CLIENT_SUBCOMMANDS)
+        .with_docs(
+            "connection",
+            "2.4.0",
+            "",
+            "A container for client connection commands.",
+        ),
+    CommandSpec::new("hello", -1, CommandFlags::NOSCRIPT, 0, 0, 0).with_docs(
+        "connection",
+        "6.0.0",
+        "[<protover> [SETNAME <clientname>]]",
+        "Handshakes with the Redis server.",
+    ),
+    CommandSpec::new("memory", -2, CommandFlags::READONLY, 0, 0, 0)
+        .with_subcommands(MEMORY_SUBCOMMANDS)
+        .with_docs(
+            "server",
+            "4.0.0",
+            "",
+            "A container for memory diagnostics commands.",
+        ),
+    CommandSpec::new("info", -1, CommandFlags::empty(), 0, 0, 0).with_docs(
+        "server",
+        "1.0.0",
+        "[<section> [<section> ...]]",
+        "Returns information and statistics about the server.",
+    ),
+    CommandSpec::new("debug", -2, CommandFlags::NOSCRIPT, 0, 0, 0)
+        .with_subcommands(DEBUG_SUBCOMMANDS)
+        .with_docs(
+            "server",
+            "1.0.0",
+            "",
+            "A container for debugging commands.",
+        ),
+    CommandSpec::new("slowlog", -2, CommandFlags::empty(), 0, 0, 0)
+        .with_subcommands(SLOWLOG_SUBCOMMANDS)
+        .with_docs(
+            "server",
+            "2.2.12",
+            "",
+            "A container for slow log commands.",
+        ),
+    CommandSpec::new("asking", 1, CommandFlags::empty(), 0, 0, 0).with_docs(
+        "cluster",
+        "3.0.0",
+        "",
+        "Signals that a cluster client is following an -ASK redirect.",
+    ),
+    CommandSpec::new("multi", 1, CommandFlags::NOSCRIPT, 0, 0, 0).with_docs(
+        "transactions",
+        "1.2.0",
+        "",
+        "Starts a transaction.",
+    ),
+    CommandSpec::new("exec", 1, CommandFlags::NOSCRIPT, 0, 0, 0).with_docs(
+        "transactions",
+        "1.2.0",
+        "",
+        "Executes all commands in a transaction.",
+    ),
+    CommandSpec::new("discard", 1, CommandFlags::NOSCRIPT, 0, 0, 0).with_docs(
+        "transactions",
+        "2.0.0",
+        "",
+        "Discards a transaction.",
+    ),
+    CommandSpec::new("watch", -2, CommandFlags::NOSCRIPT, 1, -1, 1).with_docs(
+        "transactions",
+        "2.2.0",
+        "<key> [<key> ...]",
+        "Monitors changes to keys to determine the execution of a transaction.",
+    ),
+    CommandSpec::new("unwatch", 1, CommandFlags::NOSCRIPT, 0, 0, 0).with_docs(
+        "transactions",
+        "2.2.0",
+        "",
+        "Forgets about watched keys of a transaction.",
+    ),
+    CommandSpec::new("cluster", -2, CommandFlags::empty(), 0, 0, 0)
+        .with_subcommands(CLUSTER_SUBCOMMANDS)
+        .with_docs(
+            "cluster",
+            "3.0.0",
+            "",
+            "A container for Redis Cluster commands.",
+        ),
+    CommandSpec::new("command", -1, CommandFlags::empty(), 0, 0, 0)
+        .with_subcommands(COMMAND_SUBCOMMANDS)
+        .with_docs(
+            "server",
+            "2.8.13",
+            "",
+            "Returns detailed information about all commands.",
+        ),
+];
+
+/// Whether `argc` arguments, the command name included, satisfy `arity`: exactly
+/// `arity` if it is positive, at least `-arity` otherwise.
+pub(crate) fn arity_matches(arity: i64, argc: usize) -> bool {
+    let argc = argc as i64;
+    (arity >= 0 && argc == arity) || (arity < 0 && argc >= -arity)
+}
+
+/// Look up a command by name, case-insensitively.
+pub fn lookup_command(name: &[u8]) -> Option<&'static CommandSpec> {
+    COMMAND_TABLE
+        .iter()
+        .find(|spec| spec.name.as_bytes().eq_ignore_ascii_case(name))
+}
+
+/// Builtin commands renamed or disabled by a server, like the rename-command
+/// directive of Redis, e.g. to keep FLUSHALL away from clients in production.
+#[derive(Debug, Default)]
+pub(crate) struct CommandRenames {
+    /// The builtin command of each new name.
+    aliases: HashMap<String, &'static CommandSpec>,
+    /// The names clients can't use anymore.
+    hidden: HashSet<&'static str>,
+}
+
+impl CommandRenames {
+    /// Accept the builtin `spec` as `new_name` rather than its own name, or not at all
+    /// if `new_name` is empty.
+    pub fn rename(&mut self, spec: &'static CommandSpec, new_name: &str) {
+        self.hidden.insert(spec.name);
+        if !new_name.is_empty() {
+            self.aliases.insert(new_name.to_ascii_lowercase(), spec);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hidden.is_empty()
+    }
+
+    /// Give the command of the request its builtin name, which the rest of the
+    /// server knows it by. A renamed or disabled command is unknown.
+    pub fn resolve(&self, frame: &mut RespFrame) -> Result<(), CommandError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let RespFrame::Array(args) = frame else {
+            return Ok(());
+        };
+        let Some(RespFrame::BulkString(name)) = args.first() else {
+            return Ok(());
+        };
+        let alias = String::from_utf8_lossy(name.as_ref()).to_ascii_lowercase();
+        if let Some(spec) = self.aliases.get(&alias) {
+            if let Some(first) = args.0.as_mut().and_then(|args| args.first_mut()) {
+                *first = BulkString::from(Bytes::from_static(spec.name.as_bytes())).into();
+            }
+            return Ok(());
+        }
+        if lookup_command(name.as_ref()).is_some_and(|spec| self.hidden.contains(spec.name)) {
+            return Err(CommandError::unknown_command(args));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RespArray;
+
+    use super::*;
+
+    #[test]
+    fn test_command_flags() {
+        let flags = CommandFlags::WRITE | CommandFlags::DENYOOM;
+        assert!(flags.contains(CommandFlags::WRITE));
+        assert!(flags.contains(CommandFlags::DENYOOM));
+        assert!(!flags.contains(CommandFlags::READONLY));
+        assert!(CommandFlags::empty().contains(CommandFlags::empty()));
+    }
+
+    #[test]
+    fn test_lookup_command() {
+        let spec = lookup_command(b"GET").unwrap();
+        assert_eq!(spec.name, "get");
+        assert!(spec.is_readonly());
+        assert!(!spec.is_write());
+
+        let spec = lookup_command(b"sadd").unwrap();
+        assert!(spec.is_write());
+        assert!(spec.is_denyoom());
+
+        assert!(lookup_command(b"unknown").is_none());
+    }
+
+    #[test]
+    fn test_no_command_is_both_write_and_readonly() {
+        for spec in COMMAND_TABLE {
+            assert!(!(spec.is_write() && spec.is_readonly()), "{}", spec.name);
+        }
+    }
+
+    #[test]
+    fn test_key_indexes() {
+        assert_eq!(lookup_command(b"get").unwrap().key_indexes(2), vec![1]);
+        assert_eq!(lookup_command(b"hset").unwrap().key_indexes(4), vec![1]);
+        assert!(lookup_command(b"echo").unwrap().key_indexes(2).is_empty());
+
+        let spec = CommandSpec::new("mset", -3, CommandFlags::WRITE, 1, -1, 2);
+        assert_eq!(spec.key_indexes(5), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_movable_keys() {
+        let keys = |args: &[&str]| {
+            let spec = lookup_command(args[0].as_bytes()).unwrap();
+            let args: Vec<RespFrame> = args.iter().map(|a| BulkString::new(*a).into()).collect();
+            spec.keys(&args)
+        };
+        assert_eq!(
+            keys(&["sintercard", "2", "a", "b", "LIMIT", "1"]),
+            vec!["a", "b"]
+        );
+        // a count larger than the arguments is refused by the command.
+        assert_eq!(keys(&["sintercard", "3", "a"]), vec!["a"]);
+        assert!(keys(&["sintercard", "x", "a"]).is_empty());
+        assert_eq!(keys(&["lmpop", "2", "a", "b", "LEFT"]), vec!["a", "b"]);
+        assert_eq!(keys(&["bzmpop", "0", "1", "z", "MIN"]), vec!["z"]);
+        assert_eq!(keys(&["migrate", "h", "1", "k", "0", "5"]), vec!["k"]);
+        assert_eq!(
+            keys(&["migrate", "h", "1", "", "0", "5", "REPLACE", "KEYS", "a", "b"]),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn test_command_renames() {
+        let mut renames = CommandRenames::default();
+        assert!(renames.is_empty());
+        renames.rename(lookup_command(b"debug").unwrap(), "");
+        renames.rename(lookup_command(b"get").unwrap(), "Secret-Get");
+
+        let request = |args: &[&str]| -> RespFrame {
+            RespArray::new(
+                args.iter()
+                    .map(|a| BulkString::new(*a).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into()
+        };
+        let mut frame = request(&["SECRET-GET", "k"]);
+        assert!(renames.resolve(&mut frame).is_ok());
+        assert_eq!(frame, request(&["get", "k"]));
+        let mut frame = request(&["set", "k", "v"]);
+        assert!(renames.resolve(&mut frame).is_ok());
+        assert_eq!(frame, request(&["set", "k", "v"]));
+        for name in ["get", "Debug"] {
+            let err = renames.resolve(&mut request(&[name])).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("ERR unknown command '{}', with args beginning with: ", name)
+            );
+        }
+    }
+}
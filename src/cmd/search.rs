@@ -0,0 +1,223 @@
+use crate::{
+    Backend, BulkString, FieldType, Query, RespArray, RespFrame, SearchError, SearchIndex,
+};
+
+use super::{
+    extract_args, extract_integer, extract_string, CommandError, CommandExecutor, FtCreate,
+    FtDropIndex, FtSearch, RESP_OK,
+};
+
+impl From<SearchError> for CommandError {
+    fn from(e: SearchError) -> Self {
+        CommandError::InvalidArgument(e.to_string())
+    }
+}
+
+impl CommandExecutor for FtCreate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let index = SearchIndex::new(self.prefixes, self.schema);
+        match backend.ft_create(&self.index, index) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for FtSearch {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ft_search(&self.index, &self.query) {
+            // like FT.SEARCH with NOCONTENT: the number of matches, then the keys.
+            Ok(keys) => {
+                let mut res = vec![RespFrame::Integer(keys.len() as i64)];
+                res.extend(keys.into_iter().map(|key| BulkString::new(key).into()));
+                RespArray::new(res).into()
+            }
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for FtDropIndex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ft_dropindex(&self.index) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for FtCreate {
+    type Error = CommandError;
+
+    // ft.create index [ON HASH] [PREFIX count prefix [prefix ...]] SCHEMA field TAG|NUMERIC [...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 5 {
+            return Err(CommandError::WrongArity("ft.create".to_string()));
+        }
+        let mut args = extract_args(value, 1)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .peekable();
+        let index = args.next().unwrap();
+        let syntax = || CommandError::InvalidArgument("syntax error".to_string());
+
+        if args.next_if(|arg| arg.eq_ignore_ascii_case("on")).is_some() {
+            match args.next() {
+                Some(ty) if ty.eq_ignore_ascii_case("hash") => {}
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "only HASH indexes are supported".to_string(),
+                    ))
+                }
+            }
+        }
+        let mut prefixes = Vec::new();
+        if args
+            .next_if(|arg| arg.eq_ignore_ascii_case("prefix"))
+            .is_some()
+        {
+            let count = args
+                .next()
+                .and_then(|n| extract_integer(BulkString::new(n).into()).ok())
+                .filter(|n| *n > 0)
+                .ok_or_else(syntax)?;
+            for _ in 0..count {
+                prefixes.push(args.next().ok_or_else(syntax)?);
+            }
+        }
+        match args.next() {
+            Some(arg) if arg.eq_ignore_ascii_case("schema") => {}
+            _ => return Err(syntax()),
+        }
+        let mut schema = Vec::new();
+        while let Some(field) = args.next() {
+            let ty = match args.next() {
+                Some(ty) if ty.eq_ignore_ascii_case("tag") => FieldType::Tag,
+                Some(ty) if ty.eq_ignore_ascii_case("numeric") => FieldType::Numeric,
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Invalid field type for field `{}`",
+                        field
+                    )))
+                }
+            };
+            schema.push((field, ty));
+        }
+        if schema.is_empty() {
+            return Err(syntax());
+        }
+        Ok(FtCreate {
+            index,
+            prefixes,
+            schema,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FtSearch {
+    type Error = CommandError;
+
+    // ft.search index query
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() != 3 {
+            return Err(CommandError::WrongArity("ft.search".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(FtSearch {
+            index: extract_string(args.next().unwrap())?,
+            query: Query::parse(&extract_string(args.next().unwrap())?)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FtDropIndex {
+    type Error = CommandError;
+
+    // ft.dropindex index
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() != 2 {
+            return Err(CommandError::WrongArity("ft.dropindex".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(FtDropIndex {
+            index: extract_string(args.next().unwrap())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SimpleError;
+
+    use super::*;
+    use crate::cmd::array;
+
+    #[test]
+    fn test_ft_create_from_resp_array() -> anyhow::Result<()> {
+        let create = FtCreate::try_from(array(&[
+            "ft.create",
+            "idx",
+            "ON",
+            "HASH",
+            "PREFIX",
+            "2",
+            "a:",
+            "b:",
+            "SCHEMA",
+            "city",
+            "TAG",
+            "age",
+            "NUMERIC",
+        ]))?;
+        assert_eq!(create.prefixes, vec!["a:", "b:"]);
+        assert_eq!(
+            create.schema,
+            vec![
+                ("city".to_string(), FieldType::Tag),
+                ("age".to_string(), FieldType::Numeric),
+            ]
+        );
+
+        let res = FtCreate::try_from(array(&["ft.create", "idx", "SCHEMA", "title", "TEXT"]));
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "ERR Invalid field type for field `title`"
+        );
+        let res = FtCreate::try_from(array(&["ft.create", "idx", "PREFIX", "1", "a:", "city"]));
+        assert!(res.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ft_search() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend
+            .hset(
+                "user:1".to_string(),
+                "city".to_string(),
+                BulkString::new("Paris").into(),
+            )
+            .unwrap();
+        let create = FtCreate::try_from(array(&["ft.create", "idx", "SCHEMA", "city", "TAG"]))?;
+        assert_eq!(create.execute(&backend), RESP_OK.clone());
+
+        let search = FtSearch::try_from(array(&["ft.search", "idx", "@city:{paris}"]))?;
+        assert_eq!(
+            search.execute(&backend),
+            RespArray::new(vec![
+                RespFrame::Integer(1),
+                BulkString::new("user:1").into()
+            ])
+            .into()
+        );
+        let search = FtSearch::try_from(array(&["ft.search", "missing", "*"]))?;
+        assert_eq!(
+            search.execute(&backend),
+            SimpleError::new("ERR Unknown Index name").into()
+        );
+        assert!(FtSearch::try_from(array(&["ft.search", "idx", "paris"])).is_err());
+        Ok(())
+    }
+}
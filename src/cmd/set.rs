@@ -1,18 +1,58 @@
 use std::collections::HashSet;
 
-use crate::{BulkString, RespArray, RespFrame};
+use crate::{BulkString, RespArray, RespFrame, RespNull};
 
-use super::{err::CommandError, extract_args, validate_command, CommandExecutor, SAdd, SIsMember};
+use super::{
+    err::CommandError, extract_args, extract_integer, extract_random_count, extract_string,
+    validate_command, CommandExecutor, SAdd, SInterCard, SIsMember, SMove, SRandMember,
+};
 
 impl CommandExecutor for SAdd {
     fn execute(self, backend: &crate::backend::Backend) -> crate::RespFrame {
-        backend.sadd(self.key, self.member).into()
+        match backend.sadd(self.key, self.member) {
+            Ok(added) => RespFrame::Integer(added),
+            Err(e) => CommandError::from(e).into(),
+        }
     }
 }
 
 impl CommandExecutor for SIsMember {
     fn execute(self, backend: &crate::backend::Backend) -> RespFrame {
-        backend.is_member(self.key, self.member).into()
+        RespFrame::Boolean(backend.is_member(self.key, self.member))
+    }
+}
+
+impl CommandExecutor for SInterCard {
+    fn execute(self, backend: &crate::backend::Backend) -> RespFrame {
+        backend.sintercard(&self.keys, self.limit).into()
+    }
+}
+
+impl CommandExecutor for SMove {
+    fn execute(self, backend: &crate::backend::Backend) -> RespFrame {
+        match backend.smove(&self.src, &self.dst, self.member) {
+            Ok(moved) => RespFrame::Integer(moved as i64),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for SRandMember {
+    fn execute(self, backend: &crate::backend::Backend) -> RespFrame {
+        match self.count {
+            Some(count) => RespArray::new(
+                backend
+                    .srandmember(&self.key, count)
+                    .into_iter()
+                    .map(RespFrame::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            None => match backend.srandmember(&self.key, 1).pop() {
+                Some(member) => member.into(),
+                None => RespFrame::Null(RespNull),
+            },
+        }
     }
 }
 
@@ -23,7 +63,7 @@ impl TryFrom<RespArray> for SAdd {
         let mut args = extract_args(value, 1)?.into_iter();
         let key = match args.next() {
             Some(RespFrame::BulkString(BulkString(Some(key)))) => {
-                String::from_utf8(key).map_err(CommandError::Utf8Error)?
+                String::from_utf8(key.into()).map_err(CommandError::Utf8Error)?
             }
             _ => {
                 return Err(CommandError::InvalidArgument(
@@ -58,7 +98,7 @@ impl TryFrom<RespArray> for SIsMember {
                 Some(RespFrame::BulkString(BulkString(Some(key)))),
                 Some(RespFrame::BulkString(member)),
             ) => Ok(SIsMember {
-                key: String::from_utf8(key).map_err(CommandError::Utf8Error)?,
+                key: String::from_utf8(key.into()).map_err(CommandError::Utf8Error)?,
                 member,
             }),
             _ => Err(CommandError::InvalidArgument(
@@ -67,3 +107,213 @@ impl TryFrom<RespArray> for SIsMember {
         }
     }
 }
+
+impl TryFrom<RespArray> for SMove {
+    type Error = CommandError;
+
+    // smove source destination member
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "smove", 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(src), Some(dst), Some(RespFrame::BulkString(member))) => Ok(SMove {
+                src: extract_string(src)?,
+                dst: extract_string(dst)?,
+                member,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid arguments for smove".into(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SInterCard {
+    type Error = CommandError;
+
+    // sintercard numkeys key [key ...] [LIMIT limit]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 3 {
+            return Err(CommandError::WrongArity("sintercard".to_string()));
+        }
+        validate_command(&value, "sintercard", value.len() - 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let numkeys = match args.next() {
+            Some(frame) => extract_integer(frame)?,
+            None => unreachable!(),
+        };
+        if numkeys <= 0 {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ));
+        }
+        if numkeys as usize > args.len() {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+
+        let keys = args
+            .by_ref()
+            .take(numkeys as usize)
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let limit = match (args.next(), args.next(), args.next()) {
+            (None, _, _) => 0,
+            (Some(RespFrame::BulkString(ref opt)), Some(limit), None)
+                if opt.as_ref().eq_ignore_ascii_case(b"limit") =>
+            {
+                let limit = extract_integer(limit)?;
+                if limit < 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "LIMIT can't be negative".to_string(),
+                    ));
+                }
+                limit as usize
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid arguments for sintercard".into(),
+                ))
+            }
+        };
+
+        Ok(SInterCard { keys, limit })
+    }
+}
+
+impl TryFrom<RespArray> for SRandMember {
+    type Error = CommandError;
+
+    // srandmember key [count]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if !(2..=3).contains(&value.len()) {
+            return Err(CommandError::WrongArity("srandmember".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SRandMember {
+            key: extract_string(args.next().unwrap())?,
+            count: args.next().map(extract_random_count).transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Backend;
+
+    use super::*;
+
+    fn sintercard(args: &[&str]) -> Result<SInterCard, CommandError> {
+        let mut frames = vec![BulkString::new("sintercard").into()];
+        frames.extend(args.iter().map(|a| BulkString::new(*a).into()));
+        SInterCard::try_from(RespArray::new(frames))
+    }
+
+    #[test]
+    fn test_sintercard_from_resp_array() -> anyhow::Result<()> {
+        let cmd = sintercard(&["2", "s1", "s2"])?;
+        assert_eq!(cmd.keys, vec!["s1".to_string(), "s2".to_string()]);
+        assert_eq!(cmd.limit, 0);
+
+        let cmd = sintercard(&["1", "s1", "LIMIT", "5"])?;
+        assert_eq!(cmd.keys, vec!["s1".to_string()]);
+        assert_eq!(cmd.limit, 5);
+
+        assert!(sintercard(&["0", "s1"]).is_err());
+        assert!(sintercard(&["3", "s1", "s2"]).is_err());
+        assert!(sintercard(&["1", "s1", "LIMIT", "-1"]).is_err());
+        assert!(sintercard(&["1", "s1", "LIMIT"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sintercard_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let members = |m: &[&str]| m.iter().map(|m| BulkString::new(*m)).collect();
+        backend
+            .sadd("s1".to_string(), members(&["a", "b", "c", "d"]))
+            .unwrap();
+        backend
+            .sadd("s2".to_string(), members(&["b", "c", "d", "e"]))
+            .unwrap();
+        backend
+            .sadd("s3".to_string(), members(&["c", "d"]))
+            .unwrap();
+
+        let res = sintercard(&["2", "s1", "s2"])?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(3));
+
+        let res = sintercard(&["3", "s1", "s2", "s3"])?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(2));
+
+        let res = sintercard(&["2", "s1", "s2", "limit", "1"])?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(1));
+
+        let res = sintercard(&["2", "s1", "missing"])?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_srandmember_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend
+            .sadd("s".to_string(), HashSet::from([BulkString::new("a")]))
+            .unwrap();
+        let srandmember = |args: &[&str]| -> Result<RespFrame, CommandError> {
+            let mut frames = vec![BulkString::new("srandmember").into()];
+            frames.extend(args.iter().map(|a| BulkString::new(*a).into()));
+            Ok(SRandMember::try_from(RespArray::new(frames))?.execute(&backend))
+        };
+        let array =
+            |n: usize| -> RespFrame { RespArray::new(vec![BulkString::new("a").into(); n]).into() };
+
+        assert_eq!(srandmember(&["s"])?, BulkString::new("a").into());
+        assert_eq!(srandmember(&["missing"])?, RespFrame::Null(RespNull));
+        assert_eq!(srandmember(&["s", "3"])?, array(1));
+        assert_eq!(srandmember(&["s", "-3"])?, array(3));
+        assert_eq!(srandmember(&["s", "0"])?, array(0));
+        assert!(srandmember(&["s", "x"]).is_err());
+        assert!(srandmember(&["s", "1", "2"]).is_err());
+
+        let out_of_range = |count: i64| {
+            srandmember(&["s", &count.to_string()])
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(out_of_range(i64::MIN), "ERR value is out of range");
+        assert_eq!(
+            out_of_range(-(i64::MAX / 2) - 1),
+            "ERR value is out of range"
+        );
+        assert_eq!(out_of_range(i64::MAX), "ERR value is out of range");
+        // nothing is allocated for the picks of an empty reply.
+        assert_eq!(srandmember(&["missing", "-999999999999"])?, array(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_smove_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend
+            .sadd("src".to_string(), HashSet::from([BulkString::new("a")]))
+            .unwrap();
+        let smove = |member: &str| -> anyhow::Result<RespFrame> {
+            let frames: Vec<RespFrame> = ["smove", "src", "dst", member]
+                .iter()
+                .map(|a| BulkString::new(*a).into())
+                .collect();
+            Ok(SMove::try_from(RespArray::new(frames))?.execute(&backend))
+        };
+
+        assert_eq!(smove("a")?, RespFrame::Integer(1));
+        assert_eq!(smove("a")?, RespFrame::Integer(0));
+        // the source is removed with its last member.
+        assert!(backend.smembers("src").is_none());
+        assert_eq!(backend.smembers("dst"), Some(vec![BulkString::new("a")]));
+        Ok(())
+    }
+}
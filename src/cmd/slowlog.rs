@@ -0,0 +1,180 @@
+use crate::{Backend, BulkString, RespArray, RespFrame, SlowlogEntry};
+
+use super::{
+    err::CommandError, extract_args, extract_integer, extract_string, CommandExecutor,
+    SlowlogCommand, RESP_OK,
+};
+
+/// Entries replied by SLOWLOG GET without a count.
+const DEFAULT_GET_COUNT: usize = 10;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SlowlogSubcommand {
+    /// The most recent entries, all of them if `None`.
+    Get(Option<usize>),
+    Len,
+    Reset,
+}
+
+impl CommandExecutor for SlowlogCommand {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.subcommand {
+            SlowlogSubcommand::Get(count) => RespArray::new(
+                backend
+                    .slowlog_get(count)
+                    .into_iter()
+                    .map(entry_frame)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            SlowlogSubcommand::Len => RespFrame::Integer(backend.slowlog_len() as i64),
+            SlowlogSubcommand::Reset => {
+                backend.slowlog_reset();
+                RESP_OK.clone()
+            }
+        }
+    }
+}
+
+/// An entry like Redis replies it, followed by an `over-budget` flag
+/// if the command overran the execution budget.
+fn entry_frame(entry: SlowlogEntry) -> RespFrame {
+    let args: Vec<RespFrame> = entry.args.into_iter().map(RespFrame::from).collect();
+    let mut frame = vec![
+        RespFrame::Integer(entry.id as i64),
+        RespFrame::Integer(entry.timestamp as i64),
+        RespFrame::Integer(entry.duration.as_micros() as i64),
+        RespArray::new(args).into(),
+        BulkString::new(entry.client_addr).into(),
+        // connections have no name.
+        BulkString::new("").into(),
+    ];
+    if entry.over_budget {
+        frame.push(BulkString::new("over-budget").into());
+    }
+    RespArray::new(frame).into()
+}
+
+impl TryFrom<RespArray> for SlowlogCommand {
+    type Error = CommandError;
+
+    // slowlog get [count]
+    // slowlog len
+    // slowlog reset
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(arg) => extract_string(arg)?,
+            None => return Err(CommandError::WrongArity("slowlog".to_string())),
+        };
+        let subcommand = match subcommand.to_ascii_lowercase().as_str() {
+            "get" => {
+                let count = match args.next() {
+                    None => Some(DEFAULT_GET_COUNT),
+                    Some(count) => match extract_integer(count)? {
+                        -1 => None,
+                        count if count >= 0 => Some(count as usize),
+                        _ => {
+                            return Err(CommandError::InvalidArgument(
+                                "count should be greater than or equal to -1".to_string(),
+                            ))
+                        }
+                    },
+                };
+                SlowlogSubcommand::Get(count)
+            }
+            "len" => SlowlogSubcommand::Len,
+            "reset" => SlowlogSubcommand::Reset,
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'",
+                    subcommand
+                )))
+            }
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument(format!(
+                "unknown subcommand or wrong number of arguments for '{}'",
+                subcommand_name(&subcommand)
+            )));
+        }
+        Ok(SlowlogCommand { subcommand })
+    }
+}
+
+fn subcommand_name(subcommand: &SlowlogSubcommand) -> &'static str {
+    match subcommand {
+        SlowlogSubcommand::Get(_) => "get",
+        SlowlogSubcommand::Len => "len",
+        SlowlogSubcommand::Reset => "reset",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::cmd::array;
+
+    fn slowlog(args: &[&str]) -> Result<SlowlogCommand, CommandError> {
+        SlowlogCommand::try_from(array(args))
+    }
+
+    #[test]
+    fn test_slowlog_from_resp_array() -> anyhow::Result<()> {
+        assert_eq!(
+            slowlog(&["slowlog", "GET"])?.subcommand,
+            SlowlogSubcommand::Get(Some(DEFAULT_GET_COUNT))
+        );
+        assert_eq!(
+            slowlog(&["slowlog", "get", "-1"])?.subcommand,
+            SlowlogSubcommand::Get(None)
+        );
+        assert_eq!(
+            slowlog(&["slowlog", "get", "2"])?.subcommand,
+            SlowlogSubcommand::Get(Some(2))
+        );
+        assert_eq!(
+            slowlog(&["slowlog", "len"])?.subcommand,
+            SlowlogSubcommand::Len
+        );
+        assert!(slowlog(&["slowlog", "get", "-2"]).is_err());
+        assert!(slowlog(&["slowlog", "get", "1", "2"]).is_err());
+        assert!(slowlog(&["slowlog", "len", "1"]).is_err());
+        assert!(slowlog(&["slowlog", "unknown"]).is_err());
+        assert!(slowlog(&["slowlog"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_slowlog_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let command = vec![BulkString::new("get").into()];
+        backend.slowlog_push(
+            &command,
+            Duration::from_micros(1500),
+            "addr".into(),
+            true,
+            8,
+        );
+
+        let res = slowlog(&["slowlog", "len"])?.execute(&backend);
+        assert_eq!(res, RespFrame::Integer(1));
+        let RespFrame::Array(entries) = slowlog(&["slowlog", "get"])?.execute(&backend) else {
+            panic!("SLOWLOG GET must reply with an array");
+        };
+        let RespFrame::Array(entry) = &entries[0] else {
+            panic!("entries must be arrays");
+        };
+        assert_eq!(entry.len(), 7);
+        assert_eq!(entry[2], RespFrame::Integer(1500));
+        assert_eq!(entry[3], RespArray::new(command).into());
+        assert_eq!(entry[6], BulkString::new("over-budget").into());
+
+        let res = slowlog(&["slowlog", "reset"])?.execute(&backend);
+        assert_eq!(res, RESP_OK.clone());
+        assert_eq!(backend.slowlog_len(), 0);
+        Ok(())
+    }
+}
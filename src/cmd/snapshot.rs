@@ -0,0 +1,154 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read},
+    path::Path,
+};
+
+use crate::{backend::BlockWriter, Backend, ExportStats};
+
+use super::import::ImportError;
+
+/// Snapshots of the keyspace for applications embedding the backend, in the
+/// format of [`Backend::export_resp`]. Bloom filters can't be exported: they are
+/// left out of snapshots, and kept as is when a snapshot is loaded.
+impl Backend {
+    /// Write a snapshot to a file. It is written next to it first and renamed
+    /// once complete, so a crash never leaves a truncated snapshot behind.
+    ///
+    /// The commands are split in blocks of 64KB, each followed by its CRC64 as a
+    /// `+CRC64 <hex>` simple string, so that a file damaged later is detected
+    /// when it is loaded.
+    pub fn save_to(&self, path: impl AsRef<Path>) -> io::Result<ExportStats> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut writer = BlockWriter::new(BufWriter::new(File::create(&tmp)?))?;
+        let stats = self.export_commands(|command| writer.write_command(command))?;
+        writer.finish()?.into_inner()?.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(stats)
+    }
+
+    /// Replace the keyspace with the snapshot saved in a file, which fails if
+    /// the file is truncated or its checksums don't match.
+    pub fn load_from(&self, path: impl AsRef<Path>) -> Result<usize, ImportError> {
+        self.replace_with(BufReader::new(File::open(path)?))
+    }
+
+    /// The snapshot of the keyspace, in memory.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut snapshot = Vec::new();
+        self.export_resp(&mut snapshot)
+            .expect("writing to a Vec can't fail");
+        snapshot
+    }
+
+    /// Replace the keyspace with a snapshot returned by [`Backend::serialize`].
+    pub fn deserialize(&self, snapshot: &[u8]) -> Result<usize, ImportError> {
+        self.replace_with(snapshot)
+    }
+
+    /// Serialize the keyspace and load it back in place, like DEBUG RELOAD.
+    pub fn reload(&self) -> Result<usize, ImportError> {
+        let _guard = self.lock_all();
+        let snapshot = self.serialize();
+        self.clear_exported();
+        self.import_resp(&snapshot[..])
+    }
+
+    /// Every key is locked meanwhile, the keyspace is never seen half loaded.
+    /// If the snapshot is invalid, the keys loaded before the error are kept.
+    fn replace_with(&self, reader: impl Read) -> Result<usize, ImportError> {
+        let _guard = self.lock_all();
+        self.clear_exported();
+        self.import_resp(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, ListEnd};
+
+    use super::*;
+
+    #[test]
+    fn test_reload() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("s".to_string(), b"v".to_vec());
+        backend
+            .push("l", vec![BulkString::new("x")], ListEnd::Head)
+            .unwrap();
+        backend.bf_add("bf", &[b"item".to_vec()]).unwrap();
+        let before = backend.serialize();
+
+        // the filter is a chunk of parameters and one of bits.
+        assert_eq!(backend.reload()?, 4);
+        assert_eq!(backend.serialize(), before);
+        assert_eq!(backend.bf_exists("bf", &[b"item".to_vec()]), vec![true]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_and_load() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("s".to_string(), b"v".to_vec());
+        backend
+            .sadd("set".to_string(), [BulkString::new("m")].into())
+            .unwrap();
+        backend.bf_add("bf", &[b"item".to_vec()]).unwrap();
+        let path = std::env::temp_dir().join(format!("rredis-snapshot-{}", std::process::id()));
+        assert_eq!(backend.save_to(&path)?.keys, 3);
+
+        let restored = Backend::new();
+        restored.set("stale".to_string(), b"v".to_vec());
+        restored.bf_add("stale-bf", &[b"item".to_vec()]).unwrap();
+        restored.load_from(&path)?;
+        assert_eq!(restored.bf_exists("bf", &[b"item".to_vec()]), vec![true]);
+        assert!(!restored.exists("stale-bf"));
+        fs::remove_file(&path)?;
+        assert_eq!(restored.get("stale"), None);
+        assert_eq!(restored.serialize(), backend.serialize());
+
+        let copy = Backend::new();
+        copy.deserialize(&backend.serialize())?;
+        assert_eq!(copy.get("s").as_deref(), Some(&b"v"[..]));
+        assert!(copy.deserialize(b"*1\r\n$4\r\nnope\r\n").is_err());
+        assert!(matches!(
+            copy.load_from("/nonexistent/snapshot"),
+            Err(ImportError::Io(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_damaged_snapshot() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("s".to_string(), b"value".to_vec());
+        let path = std::env::temp_dir().join(format!("rredis-damaged-{}", std::process::id()));
+        backend.save_to(&path)?;
+        let snapshot = fs::read(&path)?;
+        // the checksum of the empty block, the SET, then its checksum.
+        assert!(snapshot.starts_with(b"+CRC64 0000000000000000\r\n*3\r\n"));
+        let set_end = snapshot.len() - b"+CRC64 0123456789abcdef\r\n".len();
+
+        let mut corrupt = snapshot.clone();
+        let at = corrupt.windows(5).position(|w| w == b"value").unwrap();
+        corrupt[at] = b'V';
+        fs::write(&path, &corrupt)?;
+        let restored = Backend::new();
+        assert!(matches!(
+            restored.load_from(&path),
+            Err(ImportError::Checksum { offset, .. }) if offset == set_end as u64
+        ));
+
+        for len in [set_end - 3, set_end] {
+            fs::write(&path, &snapshot[..len])?;
+            assert!(matches!(
+                restored.load_from(&path),
+                Err(ImportError::Truncated { .. })
+            ));
+        }
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
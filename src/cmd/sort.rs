@@ -0,0 +1,320 @@
+use std::cmp::Ordering;
+
+use crate::{Backend, BulkString, RespArray, RespFrame};
+
+use super::{
+    err::CommandError, extract_args, extract_integer, extract_string, CommandExecutor, Sort,
+};
+
+impl CommandExecutor for Sort {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.sort(backend) {
+            Ok(frame) => frame,
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl Sort {
+    fn sort(&self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let members = backend.smembers(&self.key).unwrap_or_default();
+
+        // a BY pattern without '*' means "do not sort".
+        let dont_sort = matches!(self.by, Some(ref by) if !by.contains('*'));
+
+        let mut items = Vec::with_capacity(members.len());
+        for member in members {
+            let elem = member.as_ref().to_vec();
+            let weight = match self.by {
+                Some(ref by) if !dont_sort => lookup_by_pattern(backend, by, &elem),
+                Some(_) => None,
+                None => Some(elem.clone()),
+            };
+            let score = if self.alpha || dont_sort {
+                0.0
+            } else {
+                match weight {
+                    Some(ref w) => parse_score(w)?,
+                    None => 0.0,
+                }
+            };
+            items.push(SortItem {
+                elem,
+                weight,
+                score,
+            });
+        }
+
+        if !dont_sort {
+            items.sort_by(|a, b| {
+                let ord = if self.alpha {
+                    a.weight.cmp(&b.weight)
+                } else {
+                    a.score.partial_cmp(&b.score).unwrap_or(Ordering::Equal)
+                };
+                // fall back to the element itself so the output is deterministic.
+                let ord = ord.then_with(|| a.elem.cmp(&b.elem));
+                if self.desc {
+                    ord.reverse()
+                } else {
+                    ord
+                }
+            });
+        }
+
+        let (offset, count) = self.limit.unwrap_or((0, -1));
+        let offset = offset.max(0) as usize;
+        let count = if count < 0 {
+            usize::MAX
+        } else {
+            count as usize
+        };
+
+        let mut res = Vec::new();
+        for item in items.into_iter().skip(offset).take(count) {
+            if self.get.is_empty() {
+                res.push(BulkString::new(item.elem).into());
+                continue;
+            }
+            for pattern in &self.get {
+                let value = lookup_by_pattern(backend, pattern, &item.elem);
+                res.push(
+                    value
+                        .map(BulkString::new)
+                        .unwrap_or(BulkString::null())
+                        .into(),
+                );
+            }
+        }
+        Ok(RespArray::new(res).into())
+    }
+}
+
+struct SortItem {
+    elem: Vec<u8>,
+    weight: Option<Vec<u8>>,
+    score: f64,
+}
+
+fn parse_score(value: &[u8]) -> Result<f64, CommandError> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| !v.is_nan())
+        .ok_or_else(|| {
+            CommandError::InvalidArgument(
+                "One or more scores can't be converted into double".to_string(),
+            )
+        })
+}
+
+/// Resolve a BY/GET pattern for an element.
+///
+/// The first `*` in the pattern is replaced by the element to build a key,
+/// and a `->field` suffix dereferences a hash field instead of a string key.
+/// `#` resolves to the element itself.
+fn lookup_by_pattern(backend: &Backend, pattern: &str, elem: &[u8]) -> Option<Vec<u8>> {
+    if pattern == "#" {
+        return Some(elem.to_vec());
+    }
+    let star = pattern.find('*')?;
+    let (key_pattern, field) = match pattern[star..].find("->") {
+        Some(pos) if star + pos + 2 < pattern.len() => {
+            (&pattern[..star + pos], Some(&pattern[star + pos + 2..]))
+        }
+        _ => (pattern, None),
+    };
+
+    let elem = String::from_utf8_lossy(elem);
+    let key = format!(
+        "{}{}{}",
+        &key_pattern[..star],
+        elem,
+        &key_pattern[star + 1..]
+    );
+    let value = match field {
+        Some(field) => backend.hget(&key, field)?,
+        None => return backend.get(&key).map(Vec::from),
+    };
+
+    match value {
+        RespFrame::BulkString(BulkString(v)) => v.map(Vec::from),
+        RespFrame::SimpleString(s) => Some(s.0.into_bytes()),
+        RespFrame::Integer(i) => Some(i.to_string().into_bytes()),
+        RespFrame::Double(d) => Some(d.to_string().into_bytes()),
+        _ => None,
+    }
+}
+
+impl Sort {
+    fn parse(value: RespArray, name: &str, readonly: bool) -> Result<Self, CommandError> {
+        if value.len() < 2 {
+            return Err(CommandError::WrongArity(name.to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let mut sort = Sort {
+            key,
+            by: None,
+            limit: None,
+            get: Vec::new(),
+            desc: false,
+            alpha: false,
+        };
+
+        let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+        while let Some(arg) = args.next() {
+            let arg = extract_string(arg)?;
+            match arg.to_ascii_lowercase().as_str() {
+                "asc" => sort.desc = false,
+                "desc" => sort.desc = true,
+                "alpha" => sort.alpha = true,
+                "by" => sort.by = Some(extract_string(args.next().ok_or_else(syntax_error)?)?),
+                "get" => sort
+                    .get
+                    .push(extract_string(args.next().ok_or_else(syntax_error)?)?),
+                "limit" => {
+                    let offset = extract_integer(args.next().ok_or_else(syntax_error)?)?;
+                    let count = extract_integer(args.next().ok_or_else(syntax_error)?)?;
+                    sort.limit = Some((offset, count));
+                }
+                "store" if !readonly => {
+                    return Err(CommandError::InvalidArgument(
+                        "STORE is not supported yet".to_string(),
+                    ))
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(sort)
+    }
+}
+
+impl TryFrom<RespArray> for Sort {
+    type Error = CommandError;
+
+    // sort key [BY pattern] [LIMIT offset count] [GET pattern [GET pattern ...]]
+    //     [ASC | DESC] [ALPHA] [STORE destination]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        match value.first() {
+            Some(RespFrame::BulkString(ref c)) if c.as_ref().eq_ignore_ascii_case(b"sort_ro") => {
+                Sort::parse(value, "sort_ro", true)
+            }
+            _ => Sort::parse(value, "sort", false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SimpleError;
+
+    use super::*;
+    use crate::cmd::array;
+
+    fn sort(args: &[&str]) -> Result<Sort, CommandError> {
+        Sort::try_from(array(args))
+    }
+
+    fn bulks(items: &[&str]) -> RespFrame {
+        RespArray::new(
+            items
+                .iter()
+                .map(|i| BulkString::new(*i).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+
+    fn setup() -> Backend {
+        let backend = Backend::new();
+        let members = ["3", "1", "2"]
+            .iter()
+            .map(|m| BulkString::new(*m))
+            .collect();
+        backend.sadd("nums".to_string(), members).unwrap();
+        for (i, w) in [("1", "30"), ("2", "10"), ("3", "20")] {
+            backend.set(format!("weight_{}", i), w.into());
+            backend
+                .hset(
+                    format!("obj_{}", i),
+                    "name".to_string(),
+                    BulkString::new(format!("n{}", i)).into(),
+                )
+                .unwrap();
+        }
+        backend
+    }
+
+    #[test]
+    fn test_sort_from_resp_array() -> anyhow::Result<()> {
+        let cmd = sort(&[
+            "sort", "key", "BY", "w_*", "LIMIT", "0", "2", "GET", "#", "GET", "o_*", "DESC",
+            "ALPHA",
+        ])?;
+        assert_eq!(cmd.key, "key");
+        assert_eq!(cmd.by, Some("w_*".to_string()));
+        assert_eq!(cmd.limit, Some((0, 2)));
+        assert_eq!(cmd.get, vec!["#".to_string(), "o_*".to_string()]);
+        assert!(cmd.desc);
+        assert!(cmd.alpha);
+
+        assert!(sort(&["sort"]).is_err());
+        assert!(sort(&["sort", "key", "BY"]).is_err());
+        assert!(sort(&["sort", "key", "unknown"]).is_err());
+        assert!(sort(&["sort_ro", "key", "STORE", "dst"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_execute() -> anyhow::Result<()> {
+        let backend = setup();
+
+        let res = sort(&["sort", "nums"])?.execute(&backend);
+        assert_eq!(res, bulks(&["1", "2", "3"]));
+
+        let res = sort(&["sort", "nums", "DESC", "LIMIT", "0", "2"])?.execute(&backend);
+        assert_eq!(res, bulks(&["3", "2"]));
+
+        let res = sort(&["sort_ro", "nums", "BY", "weight_*"])?.execute(&backend);
+        assert_eq!(res, bulks(&["2", "3", "1"]));
+
+        let res = sort(&[
+            "sort",
+            "nums",
+            "BY",
+            "weight_*",
+            "GET",
+            "#",
+            "GET",
+            "obj_*->name",
+        ])?
+        .execute(&backend);
+        assert_eq!(res, bulks(&["2", "n2", "3", "n3", "1", "n1"]));
+
+        let res = sort(&["sort", "missing"])?.execute(&backend);
+        assert_eq!(res, bulks(&[]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_alpha() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let members = ["b", "c", "a"]
+            .iter()
+            .map(|m| BulkString::new(*m))
+            .collect();
+        backend.sadd("letters".to_string(), members).unwrap();
+
+        let res = sort(&["sort", "letters", "ALPHA"])?.execute(&backend);
+        assert_eq!(res, bulks(&["a", "b", "c"]));
+
+        let res = sort(&["sort", "letters"])?.execute(&backend);
+        assert_eq!(
+            res,
+            SimpleError::new("ERR One or more scores can't be converted into double").into()
+        );
+        Ok(())
+    }
+}
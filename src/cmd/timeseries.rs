@@ -0,0 +1,332 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{Aggregation, Backend, RespArray, RespFrame, TimeSeries, TimeSeriesError};
+
+use super::{
+    extract_args, extract_integer, extract_string, CommandError, CommandExecutor, TsAdd, TsCreate,
+    TsCreateRule, TsRange, RESP_OK,
+};
+
+impl From<TimeSeriesError> for CommandError {
+    fn from(e: TimeSeriesError) -> Self {
+        match e {
+            TimeSeriesError::WrongType(e) => e.into(),
+            e => CommandError::InvalidArgument(e.to_string()),
+        }
+    }
+}
+
+impl CommandExecutor for TsCreate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let series = TimeSeries::new(self.retention_ms, self.labels);
+        match backend.ts_create(&self.key, series) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for TsAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let timestamp = self.timestamp.unwrap_or_else(now_ms);
+        let retention_ms = self.retention_ms.unwrap_or_default();
+        let create = || TimeSeries::new(retention_ms, vec![]);
+        match backend.ts_add(&self.key, timestamp, self.value, create) {
+            Ok(()) => RespFrame::Integer(timestamp as i64),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for TsRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let samples = match backend.ts_range(&self.key, self.from, self.to, self.aggregation) {
+            Ok(samples) => samples,
+            Err(e) => return CommandError::from(e).into(),
+        };
+        let samples = samples
+            .into_iter()
+            .take(self.count.unwrap_or(usize::MAX))
+            .map(|(timestamp, value)| {
+                RespArray::new(vec![
+                    RespFrame::Integer(timestamp as i64),
+                    RespFrame::Double(value),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(samples).into()
+    }
+}
+
+impl CommandExecutor for TsCreateRule {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ts_create_rule(&self.src, &self.dest, self.aggregation, self.bucket_ms) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+impl TryFrom<RespArray> for TsCreate {
+    type Error = CommandError;
+
+    // ts.create key [RETENTION retentionPeriod] [LABELS label value ...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 2 {
+            return Err(CommandError::WrongArity("ts.create".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let mut res = TsCreate {
+            key,
+            retention_ms: 0,
+            labels: Vec::new(),
+        };
+        while let Some(arg) = args.next() {
+            let arg = extract_string(arg)?;
+            if arg.eq_ignore_ascii_case("retention") {
+                res.retention_ms = parse_u64(args.next(), "invalid RETENTION")?;
+            } else if arg.eq_ignore_ascii_case("labels") {
+                let rest = args
+                    .by_ref()
+                    .map(extract_string)
+                    .collect::<Result<Vec<_>, _>>()?;
+                if rest.is_empty() || rest.len() % 2 != 0 {
+                    return Err(ts_error("invalid LABELS"));
+                }
+                res.labels = rest
+                    .chunks(2)
+                    .map(|pair| (pair[0].clone(), pair[1].clone()))
+                    .collect();
+            } else {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+        }
+        Ok(res)
+    }
+}
+
+impl TryFrom<RespArray> for TsAdd {
+    type Error = CommandError;
+
+    // ts.add key timestamp|* value [RETENTION retentionPeriod]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() != 4 && value.len() != 6 {
+            return Err(CommandError::WrongArity("ts.add".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let timestamp = match extract_string(args.next().unwrap())?.as_str() {
+            "*" => None,
+            ts => Some(ts.parse().map_err(|_| ts_error("invalid timestamp"))?),
+        };
+        let value = extract_string(args.next().unwrap())?
+            .parse::<f64>()
+            .ok()
+            .filter(|v| !v.is_nan())
+            .ok_or_else(|| ts_error("invalid value"))?;
+        let retention_ms = match args.next().map(extract_string).transpose()? {
+            Some(arg) if arg.eq_ignore_ascii_case("retention") => {
+                Some(parse_u64(args.next(), "invalid RETENTION")?)
+            }
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            None => None,
+        };
+        Ok(TsAdd {
+            key,
+            timestamp,
+            value,
+            retention_ms,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsRange {
+    type Error = CommandError;
+
+    // ts.range key fromTimestamp toTimestamp [COUNT count] [AGGREGATION aggregator bucketDuration]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 4 {
+            return Err(CommandError::WrongArity("ts.range".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let from = match extract_string(args.next().unwrap())?.as_str() {
+            "-" => 0,
+            ts => ts.parse().map_err(|_| ts_error("wrong fromTimestamp"))?,
+        };
+        let to = match extract_string(args.next().unwrap())?.as_str() {
+            "+" => u64::MAX,
+            ts => ts.parse().map_err(|_| ts_error("wrong toTimestamp"))?,
+        };
+        let mut res = TsRange {
+            key,
+            from,
+            to,
+            count: None,
+            aggregation: None,
+        };
+        while let Some(arg) = args.next() {
+            let arg = extract_string(arg)?;
+            if arg.eq_ignore_ascii_case("count") {
+                res.count = Some(parse_u64(args.next(), "Couldn't parse COUNT")? as usize);
+            } else if arg.eq_ignore_ascii_case("aggregation") {
+                res.aggregation = Some(parse_aggregation(&mut args)?);
+            } else {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+        }
+        Ok(res)
+    }
+}
+
+impl TryFrom<RespArray> for TsCreateRule {
+    type Error = CommandError;
+
+    // ts.createrule sourceKey destKey AGGREGATION aggregator bucketDuration
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() != 6 {
+            return Err(CommandError::WrongArity("ts.createrule".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let src = extract_string(args.next().unwrap())?;
+        let dest = extract_string(args.next().unwrap())?;
+        if src == dest {
+            return Err(ts_error(
+                "the source key and destination key should be different",
+            ));
+        }
+        if !extract_string(args.next().unwrap())?.eq_ignore_ascii_case("aggregation") {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let (aggregation, bucket_ms) = parse_aggregation(&mut args)?;
+        Ok(TsCreateRule {
+            src,
+            dest,
+            aggregation,
+            bucket_ms,
+        })
+    }
+}
+
+/// Parse the `aggregator bucketDuration` following AGGREGATION.
+fn parse_aggregation(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<(Aggregation, u64), CommandError> {
+    let aggregation = args
+        .next()
+        .map(extract_string)
+        .transpose()?
+        .and_then(|name| Aggregation::parse(&name))
+        .ok_or_else(|| ts_error("Unknown aggregation type"))?;
+    let bucket_ms = parse_u64(args.next(), "bucketDuration must be greater than zero")?;
+    if bucket_ms == 0 {
+        return Err(ts_error("bucketDuration must be greater than zero"));
+    }
+    Ok((aggregation, bucket_ms))
+}
+
+fn parse_u64(arg: Option<RespFrame>, error: &str) -> Result<u64, CommandError> {
+    match arg.map(extract_integer) {
+        Some(Ok(n)) if n >= 0 => Ok(n as u64),
+        _ => Err(ts_error(error)),
+    }
+}
+
+fn ts_error(message: &str) -> CommandError {
+    CommandError::InvalidArgument(format!("TSDB: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cmd::array, SimpleError};
+
+    use super::*;
+
+    #[test]
+    fn test_ts_create_from_resp_array() -> anyhow::Result<()> {
+        let create = TsCreate::try_from(array(&[
+            "ts.create",
+            "temp",
+            "RETENTION",
+            "60000",
+            "LABELS",
+            "room",
+            "kitchen",
+        ]))?;
+        assert_eq!(create.retention_ms, 60000);
+        assert_eq!(
+            create.labels,
+            vec![("room".to_string(), "kitchen".to_string())]
+        );
+        let res = TsCreate::try_from(array(&["ts.create", "temp", "LABELS", "room"]));
+        assert_eq!(res.unwrap_err().to_string(), "ERR TSDB: invalid LABELS");
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_commands() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let sample = |ts: i64, v: f64| -> RespFrame {
+            RespArray::new(vec![RespFrame::Integer(ts), RespFrame::Double(v)]).into()
+        };
+
+        assert_eq!(
+            TsCreate::try_from(array(&["ts.create", "t"]))?.execute(&backend),
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            TsCreate::try_from(array(&["ts.create", "t"]))?.execute(&backend),
+            SimpleError::new("ERR TSDB: key already exists").into()
+        );
+        for (ts, v) in [("1000", "1.5"), ("2000", "2"), ("3000", "4")] {
+            let add = TsAdd::try_from(array(&["ts.add", "t", ts, v]))?;
+            assert_eq!(add.execute(&backend), RespFrame::Integer(ts.parse()?));
+        }
+        let add = TsAdd::try_from(array(&["ts.add", "auto", "*", "1"]))?;
+        assert!(matches!(add.execute(&backend), RespFrame::Integer(ts) if ts > 0));
+
+        let range = TsRange::try_from(array(&["ts.range", "t", "-", "+", "COUNT", "2"]))?;
+        assert_eq!(
+            range.execute(&backend),
+            RespArray::new(vec![sample(1000, 1.5), sample(2000, 2.0)]).into()
+        );
+        let range = TsRange::try_from(array(&[
+            "ts.range",
+            "t",
+            "1500",
+            "+",
+            "AGGREGATION",
+            "max",
+            "5000",
+        ]))?;
+        assert_eq!(
+            range.execute(&backend),
+            RespArray::new(vec![sample(0, 4.0)]).into()
+        );
+        let range = TsRange::try_from(array(&["ts.range", "missing", "-", "+"]))?;
+        assert_eq!(
+            range.execute(&backend),
+            SimpleError::new("ERR TSDB: the key does not exist").into()
+        );
+        assert!(TsRange::try_from(array(&[
+            "ts.range",
+            "t",
+            "-",
+            "+",
+            "AGGREGATION",
+            "median",
+            "1"
+        ]))
+        .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,683 @@
+use crate::{
+    parse_score, Backend, BulkString, LexBound, RespArray, RespFrame, RespNull, ScoreBound,
+    ZAddFlags, ZIncrError, ZRangeBy, ZRangeSpec,
+};
+
+use super::{
+    extract_args, extract_integer, extract_multi_pop, extract_string, extract_timeout,
+    validate_command, CommandError, CommandExecutor, ZAdd, ZCard, ZMPop, ZRange, ZRangeByScore,
+    ZRangeStore, ZRank, ZRem, ZScore,
+};
+
+impl CommandExecutor for ZAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if self.incr {
+            let (incr, member) = self.members.into_iter().next().expect("INCR has one pair");
+            return match backend.zadd_incr(&self.key, incr, member, self.flags) {
+                Ok(Some(score)) => RespFrame::Double(score),
+                Ok(None) => RespFrame::Null(RespNull),
+                Err(ZIncrError::WrongType(e)) => CommandError::from(e).into(),
+                Err(e) => CommandError::InvalidArgument(e.to_string()).into(),
+            };
+        }
+        match backend.zadd_with(&self.key, self.members, self.flags) {
+            Ok(added) => RespFrame::Integer(added),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.zrem(&self.key, &self.members))
+    }
+}
+
+impl CommandExecutor for ZScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zscore(&self.key, &self.member) {
+            Some(score) => RespFrame::Double(score),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for ZCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.zcard(&self.key) as i64)
+    }
+}
+
+impl CommandExecutor for ZRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zrank(&self.key, &self.member) {
+            Some(rank) => RespFrame::Integer(rank as i64),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for ZRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        elements_reply(backend.zrange(&self.key, &self.spec), self.withscores)
+    }
+}
+
+impl CommandExecutor for ZRangeByScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        elements_reply(backend.zrange(&self.key, &self.spec), self.withscores)
+    }
+}
+
+impl CommandExecutor for ZRangeStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.zrangestore(&self.dst, &self.src, &self.spec) as i64)
+    }
+}
+
+impl CommandExecutor for ZMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zmpop(&self.keys, self.count, self.max) {
+            Ok(Some((key, elements))) => {
+                let elements = elements
+                    .into_iter()
+                    .map(|(member, score)| {
+                        RespArray::new(vec![member.into(), RespFrame::Double(score)]).into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(vec![
+                    BulkString::new(key).into(),
+                    RespArray::new(elements).into(),
+                ])
+                .into()
+            }
+            Ok(None) => RespArray::null().into(),
+            Err(e) => CommandError::from(e).into(),
+        }
+    }
+}
+
+/// The members, each followed by its score with WITHSCORES.
+fn elements_reply(elements: Vec<(BulkString, f64)>, withscores: bool) -> RespFrame {
+    let mut res = Vec::with_capacity(elements.len() * (1 + withscores as usize));
+    for (member, score) in elements {
+        res.push(member.into());
+        if withscores {
+            res.push(RespFrame::Double(score));
+        }
+    }
+    RespArray::new(res).into()
+}
+
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+
+    // zadd key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 4 {
+            return Err(CommandError::WrongArity("zadd".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next().unwrap())?;
+        let mut flags = ZAddFlags::default();
+        let mut incr = false;
+        while let Some(RespFrame::BulkString(arg)) = args.peek() {
+            match arg.as_ref().to_ascii_lowercase().as_slice() {
+                b"nx" => flags.nx = true,
+                b"xx" => flags.xx = true,
+                b"gt" => flags.gt = true,
+                b"lt" => flags.lt = true,
+                b"ch" => flags.ch = true,
+                b"incr" => incr = true,
+                _ => break,
+            }
+            args.next();
+        }
+        let args: Vec<RespFrame> = args.collect();
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        if flags.nx && flags.xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if [flags.nx, flags.gt, flags.lt]
+            .iter()
+            .filter(|f| **f)
+            .count()
+            > 1
+        {
+            return Err(CommandError::InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if incr && args.len() > 2 {
+            return Err(CommandError::InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+        let mut args = args.into_iter();
+        let mut members = Vec::new();
+        while let (Some(score), Some(member)) = (args.next(), args.next()) {
+            members.push((score_arg(score)?, bulk_arg(member)?));
+        }
+        Ok(ZAdd {
+            key,
+            flags,
+            incr,
+            members,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+
+    // zrem key member [member ...]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 3 {
+            return Err(CommandError::WrongArity("zrem".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let members = args.map(bulk_arg).collect::<Result<_, _>>()?;
+        Ok(ZRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+
+    // zscore key member
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "zscore", 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZScore {
+            key: extract_string(args.next().unwrap())?,
+            member: bulk_arg(args.next().unwrap())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+
+    // zcard key
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "zcard", 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZCard {
+            key: extract_string(args.next().unwrap())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRank {
+    type Error = CommandError;
+
+    // zrank key member
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, "zrank", 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZRank {
+            key: extract_string(args.next().unwrap())?,
+            member: bulk_arg(args.next().unwrap())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+
+    // zrange key start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 4 {
+            return Err(CommandError::WrongArity("zrange".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let (spec, withscores) = parse_range(args, true)?;
+        Ok(ZRange {
+            key,
+            spec,
+            withscores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZMPop {
+    type Error = CommandError;
+
+    // zmpop numkeys key [key ...] MIN | MAX [COUNT count]
+    // bzmpop timeout numkeys key [key ...] MIN | MAX [COUNT count]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(name)) if name.as_ref().eq_ignore_ascii_case(b"bzmpop") => {
+                "bzmpop"
+            }
+            _ => "zmpop",
+        };
+        let blocking = name == "bzmpop";
+        if value.len() < 4 + blocking as usize {
+            return Err(CommandError::WrongArity(name.to_string()));
+        }
+        let mut args = extract_args(value, 1)?;
+        if blocking {
+            // the connection waits, the command itself never does.
+            extract_timeout(args.remove(0))?;
+        }
+        let (keys, from, count) = extract_multi_pop(args)?;
+        let max = match from.as_str() {
+            "MIN" => false,
+            "MAX" => true,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(ZMPop { keys, max, count })
+    }
+}
+
+impl TryFrom<RespArray> for ZRangeStore {
+    type Error = CommandError;
+
+    // zrangestore dst src min max [BYSCORE | BYLEX] [REV] [LIMIT offset count]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 5 {
+            return Err(CommandError::WrongArity("zrangestore".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let dst = extract_string(args.next().unwrap())?;
+        let src = extract_string(args.next().unwrap())?;
+        let (spec, _) = parse_range(args, false)?;
+        Ok(ZRangeStore { dst, src, spec })
+    }
+}
+
+impl TryFrom<RespArray> for ZRangeByScore {
+    type Error = CommandError;
+
+    // zrangebyscore key min max [WITHSCORES] [LIMIT offset count]
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 4 {
+            return Err(CommandError::WrongArity("zrangebyscore".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next().unwrap())?;
+        let min = score_bound(&bulk_arg(args.next().unwrap())?)?;
+        let max = score_bound(&bulk_arg(args.next().unwrap())?)?;
+        let mut spec = ZRangeSpec {
+            by: ZRangeBy::Score { min, max },
+            rev: false,
+            offset: 0,
+            count: None,
+        };
+        let mut withscores = false;
+        let syntax = || CommandError::InvalidArgument("syntax error".to_string());
+        while let Some(arg) = args.next() {
+            let arg = extract_string(arg)?;
+            if arg.eq_ignore_ascii_case("withscores") {
+                withscores = true;
+            } else if arg.eq_ignore_ascii_case("limit") {
+                (spec.offset, spec.count) = parse_limit(&mut args)?;
+            } else {
+                return Err(syntax());
+            }
+        }
+        Ok(ZRangeByScore {
+            key,
+            spec,
+            withscores,
+        })
+    }
+}
+
+/// Parse the unified range of ZRANGE and ZRANGESTORE, the arguments following the keys:
+/// `start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count]`, then `[WITHSCORES]`
+/// if allowed. Returns the range and whether WITHSCORES was given.
+fn parse_range(
+    mut args: impl Iterator<Item = RespFrame>,
+    allow_withscores: bool,
+) -> Result<(ZRangeSpec, bool), CommandError> {
+    let start = bulk_arg(args.next().unwrap())?;
+    let stop = bulk_arg(args.next().unwrap())?;
+    let syntax = |msg: &str| CommandError::InvalidArgument(format!("syntax error{}", msg));
+    let (mut by_score, mut by_lex, mut rev, mut withscores) = (false, false, false, false);
+    let mut limit = None;
+    while let Some(arg) = args.next() {
+        match extract_string(arg)?.to_ascii_lowercase().as_str() {
+            "byscore" => by_score = true,
+            "bylex" => by_lex = true,
+            "rev" => rev = true,
+            "limit" => limit = Some(parse_limit(&mut args)?),
+            "withscores" if allow_withscores => withscores = true,
+            _ => return Err(syntax("")),
+        }
+    }
+    if by_score && by_lex {
+        return Err(syntax(""));
+    }
+    if limit.is_some() && !by_score && !by_lex {
+        return Err(syntax(
+            ", LIMIT is only supported in combination with either BYSCORE or BYLEX",
+        ));
+    }
+    if withscores && by_lex {
+        return Err(syntax(
+            ", WITHSCORES not supported in combination with BYLEX",
+        ));
+    }
+    // reversed ranges of scores or members are given from their end, like ZREVRANGEBYSCORE.
+    let (min, max) = match rev {
+        true => (&stop, &start),
+        false => (&start, &stop),
+    };
+    let by = if by_score {
+        ZRangeBy::Score {
+            min: score_bound(min)?,
+            max: score_bound(max)?,
+        }
+    } else if by_lex {
+        let bound = |arg: &BulkString| {
+            LexBound::parse(arg.as_ref()).ok_or_else(|| {
+                CommandError::InvalidArgument("min or max not valid string range item".to_string())
+            })
+        };
+        ZRangeBy::Lex {
+            min: bound(min)?,
+            max: bound(max)?,
+        }
+    } else {
+        ZRangeBy::Rank {
+            start: extract_integer(start.into())?,
+            stop: extract_integer(stop.into())?,
+        }
+    };
+    let (offset, count) = limit.unwrap_or((0, None));
+    let spec = ZRangeSpec {
+        by,
+        rev,
+        offset,
+        count,
+    };
+    Ok((spec, withscores))
+}
+
+/// Parse the `offset count` of LIMIT.
+fn parse_limit(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<(usize, Option<usize>), CommandError> {
+    let mut next = || {
+        let arg = args
+            .next()
+            .ok_or_else(|| CommandError::InvalidArgument("syntax error".to_string()))?;
+        extract_integer(arg)
+    };
+    let offset = next()?;
+    let count = next()?;
+    // a negative offset returns nothing, a negative count everything.
+    Ok((
+        usize::try_from(offset).unwrap_or(usize::MAX),
+        usize::try_from(count).ok(),
+    ))
+}
+
+fn score_bound(arg: &BulkString) -> Result<ScoreBound, CommandError> {
+    std::str::from_utf8(arg.as_ref())
+        .ok()
+        .and_then(ScoreBound::parse)
+        .ok_or_else(|| CommandError::InvalidArgument("min or max is not a float".to_string()))
+}
+
+fn score_arg(frame: RespFrame) -> Result<f64, CommandError> {
+    parse_score(&extract_string(frame)?)
+        .ok_or_else(|| CommandError::InvalidArgument("value is not a valid float".to_string()))
+}
+
+fn bulk_arg(frame: RespFrame) -> Result<BulkString, CommandError> {
+    match frame {
+        RespFrame::BulkString(member) => Ok(member),
+        _ => Err(CommandError::InvalidArgument(
+            "Argument must be a bulk string".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::array;
+
+    #[test]
+    fn test_zadd_from_resp_array() -> anyhow::Result<()> {
+        let zadd = ZAdd::try_from(array(&["zadd", "z", "1.5", "a", "-inf", "b"]))?;
+        assert_eq!(
+            zadd.members,
+            vec![
+                (1.5, BulkString::new("a")),
+                (f64::NEG_INFINITY, BulkString::new("b"))
+            ]
+        );
+        let res = ZAdd::try_from(array(&["zadd", "z", "x", "a"]));
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "ERR value is not a valid float"
+        );
+        assert!(ZAdd::try_from(array(&["zadd", "z", "1"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zadd_flags_from_resp_array() -> anyhow::Result<()> {
+        let zadd = ZAdd::try_from(array(&["zadd", "z", "NX", "5", "d"]))?;
+        assert!(zadd.flags.nx);
+        assert_eq!(zadd.members, vec![(5.0, BulkString::new("d"))]);
+
+        let zadd = ZAdd::try_from(array(&["zadd", "z", "xx", "gt", "ch", "1", "a", "2", "b"]))?;
+        assert_eq!(
+            zadd.flags,
+            ZAddFlags {
+                xx: true,
+                gt: true,
+                ch: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(zadd.members.len(), 2);
+        assert!(ZAdd::try_from(array(&["zadd", "z", "INCR", "1", "a"]))?.incr);
+
+        let error = |args: &[&str]| ZAdd::try_from(array(args)).unwrap_err().to_string();
+        assert_eq!(error(&["zadd", "z", "NX", "5"]), "ERR syntax error");
+        assert_eq!(
+            error(&["zadd", "z", "CH", "NX", "INCR"]),
+            "ERR syntax error"
+        );
+        assert_eq!(
+            error(&["zadd", "z", "NX", "XX", "1", "a"]),
+            "ERR XX and NX options at the same time are not compatible"
+        );
+        assert_eq!(
+            error(&["zadd", "z", "GT", "LT", "1", "a"]),
+            "ERR GT, LT, and/or NX options at the same time are not compatible"
+        );
+        assert_eq!(
+            error(&["zadd", "z", "INCR", "1", "a", "2", "b"]),
+            "ERR INCR option supports a single increment-element pair"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_zadd_flags() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let zadd = |args: &[&str]| -> anyhow::Result<RespFrame> {
+            Ok(ZAdd::try_from(array(args))?.execute(&backend))
+        };
+
+        assert_eq!(zadd(&["zadd", "z", "XX", "1", "a"])?, RespFrame::Integer(0));
+        assert!(!backend.zset.contains_key("z"));
+        assert_eq!(
+            zadd(&["zadd", "z", "1", "a", "5", "b"])?,
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            zadd(&["zadd", "z", "NX", "9", "a", "2", "c"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(backend.zscore("z", &BulkString::new("a")), Some(1.0));
+
+        // GT and LT only guard updates, new members are still added.
+        assert_eq!(
+            zadd(&["zadd", "z", "GT", "CH", "0", "a", "6", "b", "1", "d"])?,
+            RespFrame::Integer(2)
+        );
+        assert_eq!(backend.zscore("z", &BulkString::new("a")), Some(1.0));
+        assert_eq!(backend.zscore("z", &BulkString::new("b")), Some(6.0));
+        assert_eq!(
+            zadd(&["zadd", "z", "LT", "CH", "3", "b", "3", "c"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            zadd(&["zadd", "z", "XX", "CH", "7", "a", "1", "e"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(backend.zscore("z", &BulkString::new("e")), None);
+
+        assert_eq!(
+            zadd(&["zadd", "z", "INCR", "2", "a"])?,
+            RespFrame::Double(9.0)
+        );
+        assert_eq!(
+            zadd(&["zadd", "z", "INCR", "NX", "2", "a"])?,
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            zadd(&["zadd", "z", "INCR", "LT", "1", "a"])?,
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            zadd(&["zadd", "z", "INCR", "XX", "1", "new"])?,
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            zadd(&["zadd", "z", "INCR", "1.5", "new"])?,
+            RespFrame::Double(1.5)
+        );
+        zadd(&["zadd", "z", "+inf", "a"])?;
+        assert_eq!(
+            zadd(&["zadd", "z", "INCR", "-inf", "a"])?,
+            CommandError::InvalidArgument("resulting score is not a number (NaN)".to_string())
+                .into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_syntax_errors() {
+        let error = |args: &[&str]| ZRange::try_from(array(args)).unwrap_err().to_string();
+        assert_eq!(
+            error(&["zrange", "z", "0", "1", "LIMIT", "0", "1"]),
+            "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+        );
+        assert_eq!(
+            error(&["zrange", "z", "-", "+", "BYLEX", "WITHSCORES"]),
+            "ERR syntax error, WITHSCORES not supported in combination with BYLEX"
+        );
+        assert_eq!(
+            error(&["zrange", "z", "a", "+", "BYLEX"]),
+            "ERR min or max not valid string range item"
+        );
+        assert_eq!(
+            error(&["zrange", "z", "a", "1", "BYSCORE"]),
+            "ERR min or max is not a float"
+        );
+        assert_eq!(
+            error(&["zrange", "z", "0", "1", "BYSCORE", "BYLEX"]),
+            "ERR syntax error"
+        );
+        let store =
+            ZRangeStore::try_from(array(&["zrangestore", "d", "z", "0", "1", "WITHSCORES"]));
+        assert_eq!(store.unwrap_err().to_string(), "ERR syntax error");
+    }
+
+    #[test]
+    fn test_zset_commands() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let zadd = ZAdd::try_from(array(&["zadd", "z", "1", "a", "2", "b", "3", "c"]))?;
+        assert_eq!(zadd.execute(&backend), RespFrame::Integer(3));
+
+        let rank = ZRank::try_from(array(&["zrank", "z", "b"]))?;
+        assert_eq!(rank.execute(&backend), RespFrame::Integer(1));
+        let score = ZScore::try_from(array(&["zscore", "z", "c"]))?;
+        assert_eq!(score.execute(&backend), RespFrame::Double(3.0));
+
+        let range = ZRange::try_from(array(&["zrange", "z", "1", "-1", "WITHSCORES"]))?;
+        assert_eq!(
+            range.execute(&backend),
+            RespArray::new(vec![
+                BulkString::new("b").into(),
+                RespFrame::Double(2.0),
+                BulkString::new("c").into(),
+                RespFrame::Double(3.0),
+            ])
+            .into()
+        );
+        let range = ZRangeByScore::try_from(array(&[
+            "zrangebyscore",
+            "z",
+            "(1",
+            "+inf",
+            "LIMIT",
+            "1",
+            "1",
+        ]))?;
+        assert_eq!(
+            range.execute(&backend),
+            RespArray::new(vec![BulkString::new("c").into()]).into()
+        );
+
+        let range = ZRange::try_from(array(&[
+            "zrange",
+            "z",
+            "+inf",
+            "(1",
+            "BYSCORE",
+            "REV",
+            "LIMIT",
+            "0",
+            "1",
+            "WITHSCORES",
+        ]))?;
+        assert_eq!(
+            range.execute(&backend),
+            RespArray::new(vec![BulkString::new("c").into(), RespFrame::Double(3.0)]).into()
+        );
+        let store = ZRangeStore::try_from(array(&["zrangestore", "dst", "z", "0", "0", "REV"]))?;
+        assert_eq!(store.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.zscore("dst", &BulkString::new("c")), Some(3.0));
+
+        let zrem = ZRem::try_from(array(&["zrem", "z", "a", "missing"]))?;
+        assert_eq!(zrem.execute(&backend), RespFrame::Integer(1));
+        let card = ZCard::try_from(array(&["zcard", "z"]))?;
+        assert_eq!(card.execute(&backend), RespFrame::Integer(2));
+
+        let pop = ZMPop::try_from(array(&["zmpop", "2", "missing", "z", "MAX"]))?;
+        assert_eq!(
+            pop.execute(&backend),
+            RespArray::new(vec![
+                BulkString::new("z").into(),
+                RespArray::new(vec![RespArray::new(vec![
+                    BulkString::new("c").into(),
+                    RespFrame::Double(3.0)
+                ])
+                .into()])
+                .into(),
+            ])
+            .into()
+        );
+        let pop = ZMPop::try_from(array(&["bzmpop", "1", "1", "missing", "min", "COUNT", "2"]))?;
+        assert_eq!((pop.max, pop.count), (false, 2));
+        assert_eq!(pop.execute(&backend), RespArray::null().into());
+        assert!(ZMPop::try_from(array(&["zmpop", "1", "z", "MAX", "COUNT", "-1"])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+use std::{path::PathBuf, time::Duration};
+
+/// Settings of a [`Server`](crate::Server).
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Limit the rate of commands accepted from clients, disabled by default.
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Sanity limits applied to every request before it is executed.
+    pub request_limits: RequestLimits,
+    /// How to deal with clients which can't keep up with pushed messages.
+    pub slow_consumer: SlowConsumerConfig,
+    /// Sizing of the per-connection read and write buffers.
+    pub buffers: BufferConfig,
+    /// Which commands are recorded as slow, and how long a command may run.
+    pub slowlog: SlowlogConfig,
+    /// How often the memory optimizer of MEMORY PURGE runs in the background,
+    /// disabled by default.
+    pub optimize_interval: Option<Duration>,
+    /// Execute the commands on this many worker threads, picked by the hash slot of
+    /// their first key, rather than on the task of their connection. The commands on
+    /// a key keep their order while CPU-bound commands spread across cores.
+    /// Commands without keys still run on their connection. Disabled by default.
+    pub workers: Option<usize>,
+    /// Run as a node of a Redis Cluster, disabled by default.
+    pub cluster: Option<ClusterConfig>,
+    /// Log the raw frames read and written by every connection, like CLIENT TRACE ON
+    /// does for a single one. Disabled by default.
+    pub trace_protocol: bool,
+    /// Refuse new connections while this many clients are connected, like maxclients.
+    /// Unlimited by default.
+    pub max_clients: Option<usize>,
+    /// Refuse the commands flagged [`CommandFlags::DENYOOM`](crate::CommandFlags::DENYOOM)
+    /// with an OOM error while the server uses more bytes than this, see
+    /// [`Backend::used_memory`](crate::Backend::used_memory), like maxmemory with the
+    /// noeviction policy. Unlimited by default.
+    pub max_memory: Option<usize>,
+    /// Only serve clients connecting from the loopback interface, unless an address
+    /// is set with [`ServerBuilder::addr`](crate::ServerBuilder::addr), like the
+    /// protected-mode of Redis. Enabled by default.
+    pub protected_mode: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            rate_limit: None,
+            request_limits: RequestLimits::default(),
+            slow_consumer: SlowConsumerConfig::default(),
+            buffers: BufferConfig::default(),
+            slowlog: SlowlogConfig::default(),
+            optimize_interval: None,
+            workers: None,
+            cluster: None,
+            trace_protocol: false,
+            max_clients: None,
+            max_memory: None,
+            protected_mode: true,
+        }
+    }
+}
+
+/// Cluster mode: the hash slots of the keyspace are split among nodes, which
+/// redirect clients to the node serving the slot of their keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterConfig {
+    /// Address other nodes and clients reach this node at, `ip:port`.
+    pub announce_addr: String,
+    /// Port of the cluster bus, the node-to-node link, on the host of `announce_addr`.
+    pub bus_port: u16,
+    /// Where the cluster state is saved, like nodes.conf.
+    pub config_file: PathBuf,
+    /// How often each node is pinged on the bus.
+    pub ping_interval: Duration,
+    /// A node which didn't answer pings for this long is reported disconnected.
+    pub node_timeout: Duration,
+}
+
+impl ClusterConfig {
+    /// A node announced at `announce_addr`, with the bus on its port + 10000 like Redis.
+    pub fn new(announce_addr: impl Into<String>, config_file: impl Into<PathBuf>) -> Self {
+        let announce_addr = announce_addr.into();
+        let port = announce_addr
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse::<u16>().ok())
+            .unwrap_or(6379);
+        Self {
+            announce_addr,
+            bus_port: port.wrapping_add(10000),
+            config_file: config_file.into(),
+            ping_interval: Duration::from_millis(100),
+            node_timeout: Duration::from_secs(15),
+        }
+    }
+}
+
+/// Recording of slow commands in the SLOWLOG, and the execution budget of commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowlogConfig {
+    /// Commands running longer than this are recorded, like slowlog-log-slower-than.
+    pub log_slower_than: Duration,
+    /// Entries kept, the oldest ones are dropped, like slowlog-max-len.
+    pub max_len: usize,
+    /// Commands running longer than this are recorded flagged as over budget,
+    /// even below `log_slower_than`. Disabled by default.
+    pub budget: Option<Duration>,
+    /// Let the other connections run once a command overran its budget,
+    /// before the next command of its connection.
+    pub yield_over_budget: bool,
+}
+
+impl Default for SlowlogConfig {
+    fn default() -> Self {
+        Self {
+            log_slower_than: Duration::from_millis(10),
+            max_len: 128,
+            budget: None,
+            yield_over_budget: true,
+        }
+    }
+}
+
+/// Connection buffers grow to fit large frames and shrink back once drained,
+/// so thousands of mostly idle clients hold little memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferConfig {
+    /// Capacity allocated for a new connection, and restored when shrinking.
+    pub initial_size: usize,
+    /// Capacity a drained buffer may keep, larger buffers are shrunk.
+    pub max_idle_size: usize,
+    /// Bulk strings of requests at least this large are read into a buffer of their
+    /// own as they arrive, rather than with the rest of their request.
+    pub large_bulk_size: usize,
+}
+
+impl Default for BufferConfig {
+    fn default() -> Self {
+        Self {
+            initial_size: 4 * 1024,
+            max_idle_size: 64 * 1024,
+            large_bulk_size: 32 * 1024,
+        }
+    }
+}
+
+/// Upper bounds of a single request, so one pathological command
+/// cannot stall the executor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestLimits {
+    /// Maximum number of arguments, the command name included.
+    pub max_args: usize,
+    /// Maximum total size of the arguments in bytes.
+    pub max_request_size: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_args: 1024 * 1024,
+            max_request_size: 512 * 1024 * 1024,
+        }
+    }
+}
+
+/// Detection of connections which don't read pushed messages (pub/sub,
+/// invalidations) fast enough, so the server keeps queueing data for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowConsumerConfig {
+    /// How long writing a single pushed message may stay blocked on a full socket buffer.
+    pub max_write_stall: Duration,
+    pub action: SlowConsumerAction,
+    /// Messages queued for a connection before `overflow` applies.
+    pub max_queued: usize,
+    pub overflow: PushOverflow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowConsumerAction {
+    /// Log a warning and keep waiting for the client.
+    Log,
+    /// Close the connection.
+    Disconnect,
+}
+
+/// What to do with a message pushed to a connection whose queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOverflow {
+    /// Drop the oldest queued message, the client misses it.
+    DropOldest,
+    /// Close the connection.
+    Disconnect,
+    /// Delay SPUBLISH until every subscriber of the channel has room.
+    BlockPublisher,
+}
+
+impl Default for SlowConsumerConfig {
+    fn default() -> Self {
+        Self {
+            max_write_stall: Duration::from_secs(60),
+            action: SlowConsumerAction::Disconnect,
+            max_queued: 10_000,
+            overflow: PushOverflow::Disconnect,
+        }
+    }
+}
+
+/// Token bucket rate limiting of incoming commands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitConfig {
+    /// Whether each connection or each source IP gets its own bucket.
+    pub key: RateLimitKey,
+    /// Sustained number of commands allowed per second.
+    pub commands_per_sec: f64,
+    /// Number of commands which may be sent in a burst above the sustained rate.
+    pub burst: u32,
+    /// What to do with a command exceeding the limit.
+    pub action: ThrottleAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    Client,
+    Ip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleAction {
+    /// Reply with an error without executing the command.
+    Reject,
+    /// Delay reading from the connection until the command is allowed.
+    Delay,
+}
+
+/// Spilling of cold string values to disk, so the dataset can outgrow memory.
+/// Keys stay in memory, and a spilled value is read back into memory when accessed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TieringConfig {
+    /// Directory of the spilled values, owned by the backend: values left
+    /// there by a previous process are removed.
+    pub dir: PathBuf,
+    /// Bytes of string values kept in memory, the least recently used values
+    /// above it are spilled.
+    pub max_memory: usize,
+    /// Values shorter than this stay in memory, their key outweighs them anyway.
+    pub min_value_len: usize,
+}
+
+impl TieringConfig {
+    pub fn new(dir: impl Into<PathBuf>, max_memory: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            max_memory,
+            min_value_len: 64,
+        }
+    }
+}
+
+/// Thresholds below which collections use a compact encoding, like the
+/// `*-max-listpack-*` and `set-max-intset-entries` settings of Redis.
+/// A collection outgrowing them is converted to a hash table, and never converted back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodingConfig {
+    /// Maximum number of fields of a listpack encoded hash.
+    pub hash_max_listpack_entries: usize,
+    /// Maximum length of a field or value of a listpack encoded hash.
+    pub hash_max_listpack_value: usize,
+    /// Maximum number of members of an intset encoded set.
+    pub set_max_intset_entries: usize,
+    /// Maximum number of members of a listpack encoded set.
+    pub set_max_listpack_entries: usize,
+    /// Maximum length of a member of a listpack encoded set.
+    pub set_max_listpack_value: usize,
+    /// String values at least this long are stored LZ4 compressed and decompressed
+    /// on reads, trading CPU for memory. Disabled if `None`, the default.
+    pub string_compress_threshold: Option<usize>,
+    /// Maximum size of a string value, SET, APPEND or SETRANGE making one larger fail,
+    /// like proto-max-bulk-len. 512MB by default.
+    pub max_string_size: usize,
+}
+
+impl Default for EncodingConfig {
+    fn default() -> Self {
+        Self {
+            hash_max_listpack_entries: 128,
+            hash_max_listpack_value: 64,
+            set_max_intset_entries: 512,
+            set_max_listpack_entries: 128,
+            set_max_listpack_value: 64,
+            string_compress_threshold: None,
+            max_string_size: 512 * 1024 * 1024,
+        }
+    }
+}
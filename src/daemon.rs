@@ -0,0 +1,103 @@
+//! Running the server binary in the background, like the `daemonize` and `pidfile`
+//! options of redis-server.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Where the pid of a daemonized server is written when no pidfile is given.
+pub const DEFAULT_PIDFILE: &str = "/var/run/rredis.pid";
+
+/// A file holding the pid of the process, removed when dropped.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn create(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        fs::write(&path, format!("{}\n", std::process::id()))?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // the file may have been removed already, there is nothing left to do then.
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Detach the process from its terminal: fork, start a new session and fork again,
+/// so that the daemon can't acquire a terminal, then point the standard streams
+/// to `/dev/null`. The intermediate processes exit, only the daemon returns.
+///
+/// Only the calling thread survives a fork: this must be called before any other
+/// thread is started, the tokio runtime included.
+#[cfg(unix)]
+pub fn daemonize() -> io::Result<()> {
+    fork_and_exit_parent()?;
+    // SAFETY: setsid has no preconditions.
+    if unsafe { libc::setsid() } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    fork_and_exit_parent()?;
+    // SAFETY: the path is NUL terminated, and dup2 and close are given open descriptors.
+    unsafe {
+        let fd = libc::open(c"/dev/null".as_ptr(), libc::O_RDWR);
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        for stdio in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+            libc::dup2(fd, stdio);
+        }
+        if fd > libc::STDERR_FILENO {
+            libc::close(fd);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn fork_and_exit_parent() -> io::Result<()> {
+    // SAFETY: the process is single threaded, see `daemonize`. The parent exits
+    // without running destructors or flushing buffers, which belong to the child now.
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(()),
+        _ => unsafe { libc::_exit(0) },
+    }
+}
+
+#[cfg(not(unix))]
+pub fn daemonize() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "daemonize is only supported on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pidfile() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("rredis-{}.pid", std::process::id()));
+        let pidfile = PidFile::create(&path)?;
+        assert_eq!(pidfile.path(), path);
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            format!("{}\n", std::process::id())
+        );
+        drop(pidfile);
+        assert!(!path.exists());
+        Ok(())
+    }
+}
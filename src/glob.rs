@@ -0,0 +1,164 @@
+//! Glob-style patterns, with the syntax of Redis' KEYS, SCAN MATCH and PSUBSCRIBE.
+//!
+//! Unlike the recursive matcher of Redis, which needs a nesting limit against
+//! patterns like `a*a*a*a*b`, only the last star is ever backtracked: matching takes
+//! at most `O(pattern.len() * text.len())` steps and no stack.
+
+/// Whether `text` matches `pattern`: `*` matches any sequence of bytes, `?` any byte,
+/// `[abc]`, `[a-z]` and `[^abc]` a class of bytes, and `\` escapes the next byte.
+pub fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // after a mismatch, the last star extends by one byte: the pattern resumes
+    // after the star, and the text after what the star covers.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if pattern.get(p) == Some(&b'*') {
+            p += 1;
+            star = Some((p, t));
+            continue;
+        }
+        if p < pattern.len() {
+            let (matched, next) = match_token(pattern, p, text[t]);
+            if matched {
+                p = next;
+                t += 1;
+                continue;
+            }
+        }
+        match star {
+            Some((after_star, covered)) => {
+                p = after_star;
+                t = covered + 1;
+                star = Some((after_star, t));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Match a byte against the token of the pattern starting at `p`, which is not a star.
+/// Returns whether it matched and where the next token starts.
+fn match_token(pattern: &[u8], p: usize, c: u8) -> (bool, usize) {
+    match pattern[p] {
+        b'?' => (true, p + 1),
+        b'\\' if p + 1 < pattern.len() => (pattern[p + 1] == c, p + 2),
+        b'[' => match_class(pattern, p + 1, c),
+        literal => (literal == c, p + 1),
+    }
+}
+
+/// Like Redis, an unterminated class ends with the pattern.
+fn match_class(pattern: &[u8], mut p: usize, c: u8) -> (bool, usize) {
+    let negated = pattern.get(p) == Some(&b'^');
+    if negated {
+        p += 1;
+    }
+    let mut matched = false;
+    while let Some(&b) = pattern.get(p) {
+        match b {
+            b']' => return (matched != negated, p + 1),
+            b'\\' if p + 1 < pattern.len() => {
+                matched |= pattern[p + 1] == c;
+                p += 2;
+            }
+            start if pattern.get(p + 1) == Some(&b'-') && p + 2 < pattern.len() => {
+                let end = pattern[p + 2];
+                matched |= (start.min(end)..=start.max(end)).contains(&c);
+                p += 3;
+            }
+            b => {
+                matched |= b == c;
+                p += 1;
+            }
+        }
+    }
+    (matched != negated, p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        glob_match(pattern.as_bytes(), text.as_bytes())
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(matches("*", ""));
+        assert!(matches("*", "anything"));
+        assert!(matches("user:*", "user:1"));
+        assert!(!matches("user:*", "session:1"));
+        assert!(matches("h?llo", "hello"));
+        assert!(!matches("h?llo", "hllo"));
+        assert!(matches("*a*b", "xaxxb"));
+        assert!(!matches("*a*b", "xaxxbc"));
+        assert!(matches("a**b", "ab"));
+
+        assert!(matches("h[ae]llo", "hallo"));
+        assert!(!matches("h[ae]llo", "hillo"));
+        assert!(matches("h[^e]llo", "hallo"));
+        assert!(!matches("h[^e]llo", "hello"));
+        assert!(matches("[a-c]", "b"));
+        assert!(matches("[c-a]", "b"));
+        assert!(!matches("[a-c]", "d"));
+        assert!(matches("[\\]]", "]"));
+        // an unterminated class ends with the pattern.
+        assert!(matches("[ab", "a"));
+
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+        assert!(matches("a\\", "a\\"));
+    }
+
+    /// The obvious exponential matcher, trying every length for each star.
+    fn reference_match(pattern: &[u8], text: &[u8]) -> bool {
+        match (pattern.first(), text.first()) {
+            (None, _) => text.is_empty(),
+            (Some(b'*'), _) => {
+                reference_match(&pattern[1..], text)
+                    || (!text.is_empty() && reference_match(pattern, &text[1..]))
+            }
+            (Some(_), None) => false,
+            (Some(_), Some(&c)) => {
+                let (matched, next) = match_token(pattern, 0, c);
+                matched && reference_match(&pattern[next..], &text[1..])
+            }
+        }
+    }
+
+    #[test]
+    fn test_glob_match_agrees_with_reference() {
+        const ALPHABET: &[u8] = b"ab*?[]^-\\";
+        // xorshift, so that failures are reproducible.
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+        for _ in 0..20_000 {
+            let pattern: Vec<u8> = (0..next(8))
+                .map(|_| ALPHABET[next(ALPHABET.len())])
+                .collect();
+            let text: Vec<u8> = (0..next(8)).map(|_| ALPHABET[next(4)]).collect();
+            assert_eq!(
+                glob_match(&pattern, &text),
+                reference_match(&pattern, &text),
+                "pattern {:?}, text {:?}",
+                String::from_utf8_lossy(&pattern),
+                String::from_utf8_lossy(&text)
+            );
+        }
+    }
+
+    #[test]
+    fn test_glob_match_many_stars() {
+        let pattern = "a*".repeat(50) + "b";
+        let text = "a".repeat(10_000);
+        assert!(!matches(&pattern, &text));
+        assert!(matches(&pattern, &(text + "b")));
+    }
+}
@@ -1,9 +1,38 @@
+// the code generated by the derive macros refers to the crate as `rredis`.
+extern crate self as rredis;
+
 mod backend;
+mod cluster;
 mod cmd;
+mod config;
+pub mod daemon;
+pub mod glob;
 pub mod network;
+mod ratelimit;
 mod resp;
 mod respv2;
+mod server;
+pub mod version;
+mod workers;
 
 pub use backend::*;
+pub use cluster::{key_slot, Cluster, ClusterNode, CLUSTER_SLOTS};
+pub use cmd::{
+    err::CommandError,
+    hook::{WriteCommand, WriteHook},
+    import::{
+        check_resp, inspect_resp, repair_resp_file, ImportError, ImportOptions, ImportStats,
+        RestoreError,
+    },
+    plugin::CommandPlugin,
+    registry::{lookup_command, CommandFlags, CommandSpec, SubcommandSpec, COMMAND_TABLE},
+    Command, CommandExecutor,
+};
+pub use config::{
+    BufferConfig, ClusterConfig, EncodingConfig, PushOverflow, RateLimitConfig, RateLimitKey,
+    RequestLimits, ServerConfig, SlowConsumerAction, SlowConsumerConfig, SlowlogConfig,
+    ThrottleAction, TieringConfig,
+};
 pub use resp::*;
 pub use respv2::*;
+pub use server::{Server, ServerBuilder};
@@ -1,29 +1,220 @@
-use rredis::{network, Backend};
-use tokio::net::TcpListener;
-use tracing::info;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    net::{IpAddr, SocketAddr},
+};
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
+use anyhow::{anyhow, bail, Context};
+use rredis::{
+    check_resp,
+    daemon::{daemonize, PidFile, DEFAULT_PIDFILE},
+    version, Backend, ImportOptions, Server, ServerConfig,
+};
+use tracing::{info, warn};
+
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+/// The port listened on unless another one is given with --port.
+const DEFAULT_PORT: u16 = 6379;
+
+/// A file to load the keys of, in the order given.
+enum Source {
+    Rdb(String),
+    Import(String),
+}
 
-    let addr = "0.0.0.0:6379";
-    info!("R-Redis is running on {}", addr);
-    let listener = TcpListener::bind(addr).await?;
+fn main() -> anyhow::Result<()> {
+    let mut sources = Vec::new();
+    let mut export = None;
+    let mut daemon = false;
+    let mut pidfile = None;
+    let mut renames = Vec::new();
+    let mut binds = Vec::new();
+    let mut port = DEFAULT_PORT;
+    let mut protected_mode = true;
+    let mut load_truncated = true;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| args.next().ok_or_else(|| anyhow!("{} needs {}", arg, what));
+        match arg.as_str() {
+            // load the keys of a dump.rdb written by Redis.
+            "--rdb" => sources.push(Source::Rdb(value("a path")?)),
+            // replay the commands written by --export.
+            "--import" => sources.push(Source::Import(value("a path")?)),
+            // replay the commands of an --import file before a truncated last command
+            // rather than failing, like aof-load-truncated.
+            "--load-truncated" => load_truncated = yes_no(&arg, &value("yes or no")?)?,
+            // validate a file of --rdb or --import and exit.
+            "--check" => return check_file(&value("a path")?),
+            // write the loaded keys as RESP commands and exit rather than serving them.
+            "--export" => export = Some(value("a path")?),
+            // run in the background, like `daemonize yes` of redis-server.
+            "--daemonize" => daemon = yes_no(&arg, &value("yes or no")?)?,
+            "--pidfile" => pidfile = Some(value("a path")?),
+            // an address to listen on, `ip` on --port or `ip:port`, may be repeated e.g.
+            // for a port only reachable from an internal network. Without it, the server
+            // listens on every interface but only serves loopback clients, see
+            // --protected-mode.
+            "--bind" => binds.push(value("an address")?),
+            "--port" => port = value("a port")?.parse()?,
+            "--protected-mode" => protected_mode = yes_no(&arg, &value("yes or no")?)?,
+            // like `rename-command NAME NEW-NAME`, an empty new name disables the command.
+            "--rename-command" => renames.push((value("a command")?, value("a new name")?)),
+            "-v" | "--version" => {
+                println!("{}", version::version_line());
+                return Ok(());
+            }
+            _ => bail!("unknown argument: {}", arg),
+        }
+    }
+    // before the runtime starts any thread, see `daemonize`.
+    if daemon && export.is_none() {
+        daemonize()?;
+    }
+    tracing_subscriber::fmt::init();
+    // like redis-server, a daemon writes its pid even if no pidfile is given,
+    // and not being able to write it doesn't prevent the server from running.
+    let pidfile = match pidfile.or_else(|| daemon.then(|| DEFAULT_PIDFILE.to_string())) {
+        Some(path) if export.is_none() => PidFile::create(&path)
+            .inspect_err(|e| warn!("Failed to write PID file {}: {}", path, e))
+            .ok(),
+        _ => None,
+    };
 
     let backend = Backend::new();
-    loop {
-        let (stream, socket_addr) = listener.accept().await?;
-        info!("Accepted connection from {}", socket_addr);
-        let cloned_backend = backend.clone();
-        tokio::spawn(async move {
-            match network::handle_stream(stream, cloned_backend).await {
-                Ok(_) => {
-                    info!("Connection from {} exited", socket_addr);
-                }
-                Err(e) => {
-                    info!("Error handling connection from {}: {}", socket_addr, e);
+    for source in sources {
+        match source {
+            Source::Rdb(path) => {
+                let stats = backend.load_rdb_file(&path)?;
+                info!("Loaded {} keys from {}", stats.keys, path);
+            }
+            Source::Import(path) => {
+                let options = ImportOptions { load_truncated };
+                let stats =
+                    backend.import_resp_with(BufReader::new(File::open(&path)?), options)?;
+                if let Some(offset) = stats.truncated_at {
+                    warn!(
+                        "{} is truncated, replayed the commands before byte {}",
+                        path, offset
+                    );
                 }
+                info!("Replayed {} commands from {}", stats.commands, path);
             }
-        });
+        }
+    }
+    if let Some(path) = export {
+        let stats = backend.save_to(&path)?;
+        info!("Exported {} keys to {}", stats.keys, path);
+        return Ok(());
+    }
+
+    let res = tokio::runtime::Runtime::new()?.block_on(serve(
+        backend,
+        renames,
+        binds,
+        port,
+        protected_mode,
+    ));
+    drop(pidfile);
+    res
+}
+
+/// Serve until the server fails or is asked to shut down.
+async fn serve(
+    backend: Backend,
+    renames: Vec<(String, String)>,
+    binds: Vec<String>,
+    port: u16,
+    protected_mode: bool,
+) -> anyhow::Result<()> {
+    let config = ServerConfig {
+        protected_mode,
+        ..Default::default()
+    };
+    let mut builder = Server::builder().port(port).backend(backend).config(config);
+    let addrs: Vec<_> = binds.iter().map(|bind| bind_addr(bind, port)).collect();
+    for (i, addr) in addrs.iter().enumerate() {
+        builder = match i {
+            0 => builder.addr(addr.clone()),
+            _ => builder.listen(addr.clone()),
+        };
+    }
+    for (name, new_name) in renames {
+        builder = builder.rename_command(name, new_name);
     }
+    let server = builder.build()?;
+    let mode = match server.backend().cluster() {
+        Some(_) => "cluster",
+        None => "standalone",
+    };
+    let addrs = match addrs.is_empty() {
+        true => format!("0.0.0.0:{}", port),
+        false => addrs.join(", "),
+    };
+    println!("{}", version::banner(&addrs, mode));
+    server
+        .run_until(async {
+            match shutdown_signal().await {
+                Ok(()) => info!("Received a shutdown signal, exiting"),
+                Err(e) => warn!("Failed to wait for a shutdown signal, exiting: {}", e),
+            }
+        })
+        .await
+}
+
+fn yes_no(arg: &str, value: &str) -> anyhow::Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        v => bail!("{} needs yes or no, not {}", arg, v),
+    }
+}
+
+/// Validate a dump.rdb, recognized by its magic, or a file of RESP commands,
+/// without executing them.
+fn check_file(path: &str) -> anyhow::Result<()> {
+    let mut file = BufReader::new(File::open(path)?);
+    if file.fill_buf()?.starts_with(b"REDIS") {
+        let stats = Backend::new()
+            .load_rdb_file(path)
+            .with_context(|| format!("{} is invalid", path))?;
+        println!("{}: valid RDB file, {} keys", path, stats.keys);
+    } else {
+        let stats = check_resp(file, ImportOptions::default())
+            .with_context(|| format!("{} is invalid", path))?;
+        println!(
+            "{}: valid commands file, {} commands, {} checksummed blocks",
+            path, stats.commands, stats.verified_blocks
+        );
+    }
+    Ok(())
+}
+
+/// The address of a --bind value, which is an `ip:port` or an `ip` on `port`.
+fn bind_addr(bind: &str, port: u16) -> String {
+    match bind.parse::<SocketAddr>() {
+        Ok(addr) => addr.to_string(),
+        Err(_) => match bind.parse::<IpAddr>() {
+            Ok(IpAddr::V6(ip)) => format!("[{}]:{}", ip, port),
+            _ => format!("{}:{}", bind, port),
+        },
+    }
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() -> anyhow::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => Ok(res?),
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> anyhow::Result<()> {
+    Ok(tokio::signal::ctrl_c().await?)
 }
@@ -1,32 +1,124 @@
+use std::{
+    collections::HashSet,
+    future::Future,
+    io::{self, IoSlice},
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
 use anyhow::anyhow;
-use bytes::BytesMut;
+use bytes::{Buf, BytesMut};
 use futures::SinkExt;
-use tokio::net::TcpStream;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::TcpStream,
+    sync::broadcast::error::RecvError,
+    time::{sleep, timeout, Sleep},
+};
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
+use tracing::{debug, info, warn};
 
 use crate::{
-    cmd::{Command, CommandExecutor},
+    cluster::SlotRoute,
+    cmd::{
+        blocking::BlockingCommand,
+        check_request_limits, command_name_in,
+        err::CommandError,
+        hook::WriteHooks,
+        multi::{Transaction, TransactionCommand},
+        plugin::Plugins,
+        pubsub::subscription_reply,
+        registry::CommandRenames,
+        Command, CommandExecutor, ConnectionCommand, RESP_OK,
+    },
+    config::{BufferConfig, PushOverflow, ServerConfig, SlowConsumerAction, SlowConsumerConfig},
     err::RespError,
-    Backend, RespDecodeV2, RespEncode, RespFrame,
+    key_slot, lookup_command, parse_frame_length, push_queue,
+    ratelimit::Throttle,
+    respv2::{LargeBulks, PartialArray},
+    server::ServerState,
+    version, Backend, BulkString, ChangeKind, ConnectedClient, EncodedFrames, PushSender,
+    RespArray, RespAttribute, RespDecodeV2, RespEncode, RespFrame, RespMap, SimpleString,
+    WriteCommand,
 };
 
-struct RespFrameCodec;
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+const RATE_LIMIT_ERROR: &str = "ERR max command rate exceeded for this client, try again later";
+
+#[derive(Debug, Default)]
+pub(crate) struct RespFrameCodec {
+    /// Id of the connection if its raw frames are logged, see CLIENT TRACE.
+    trace: Option<u64>,
+    /// Whether frames are written for a RESP2 client, see [`RespFrame::into_resp2`].
+    resp2: bool,
+    /// Read the large bulk strings of requests as they arrive, `None` buffers whole frames.
+    large_bulks: Option<LargeBulks>,
+    /// The request being read, once one of its bulk strings is large.
+    partial: Option<PartialArray>,
+}
+
+/// State of a single client connection.
+struct Session {
+    id: u64,
+    backend: Backend,
+    push_tx: PushSender,
+    shard_channels: HashSet<String>,
+    tracking: bool,
+    /// Whether replies to reads are preceded by the freshness of the value.
+    attributes: bool,
+    /// Whether the next command may access a slot being imported, see ASKING.
+    asking: bool,
+    /// Whether the raw frames of the connection are logged.
+    trace: bool,
+    /// Whether the client switched to RESP3 with HELLO, RESP2 is spoken until then.
+    resp3: bool,
+    /// The commands queued since MULTI, `None` outside of a transaction.
+    transaction: Option<Transaction>,
+    /// The keys of WATCH, forgotten by the next EXEC or DISCARD.
+    watched: Vec<String>,
+    /// How replies are written, see DEBUG REPLY-DELAY and REPLY-FRAGMENT.
+    reply_delay: Duration,
+    reply_fragment: usize,
+}
+
+/// A stream whose writes are delayed and split on request of the client, so that
+/// its handling of slow and fragmented replies can be tested deterministically.
+struct ReplyFaults<T> {
+    inner: T,
+    /// Every write waits this long first.
+    delay: Duration,
+    /// Writes are at most this long, unlimited if zero.
+    fragment: usize,
+    /// The delay of the pending write.
+    sleep: Option<Pin<Box<Sleep>>>,
+}
 
+#[derive(Clone)]
 struct RedisRequest {
     frame: RespFrame,
-    backend: Backend,
+    state: ServerState,
+    client_addr: SocketAddr,
+    asking: bool,
 }
 
 struct RedisResponse {
     frame: RespFrame,
+    /// Whether the command overran the execution budget.
+    over_budget: bool,
 }
 
 impl Encoder<RespFrame> for RespFrameCodec {
     type Error = anyhow::Error;
     fn encode(&mut self, item: RespFrame, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        let bs = item.encode();
-        dst.extend_from_slice(bs.as_slice());
+        let item = if self.resp2 { item.into_resp2() } else { item };
+        let start = dst.len();
+        item.write_to(dst);
+        self.trace_out(&dst[start..]);
         Ok(())
     }
 }
@@ -35,43 +127,1146 @@ impl Decoder for RespFrameCodec {
     type Error = anyhow::Error;
     type Item = RespFrame;
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<RespFrame>, Self::Error> {
-        let res = RespFrame::decode(src);
-        match res {
-            Err(RespError::NotCompleted) => Ok(None),
+        // decoding consumes the bytes of the frame, a traced connection keeps a copy.
+        let raw = self.trace.map(|_| src.clone());
+        let res = self.decode_frame(src);
+        if let Some(raw) = raw {
+            let consumed = raw.len() - src.len();
+            if consumed > 0 {
+                self.trace("in", &raw[..consumed]);
+            }
+        }
+        res
+    }
+}
+
+impl RespFrameCodec {
+    fn decode_frame(&mut self, src: &mut BytesMut) -> anyhow::Result<Option<RespFrame>> {
+        if let Some(partial) = self.partial.as_mut() {
+            // part of the frame was consumed, the connection can't recover from an error.
+            let array = partial
+                .decode(src)
+                .map_err(|e| anyhow!("Protocol error: {}", e))?;
+            if array.is_some() {
+                self.partial = None;
+            }
+            return Ok(array.map(RespFrame::Array));
+        }
+        match RespFrame::decode(src) {
+            Err(RespError::NotCompleted(needed)) => {
+                if let Some(limits) = self.large_bulks {
+                    let partial = PartialArray::start(src, limits)
+                        .map_err(|e| anyhow!("Protocol error: {}", e))?;
+                    if let Some(partial) = partial {
+                        self.partial = Some(partial);
+                        return self.decode_frame(src);
+                    }
+                }
+                // make room for the rest of the frame at once.
+                if let Some(needed) = needed {
+                    src.reserve(needed);
+                }
+                Ok(None)
+            }
             Ok(frame) => Ok(Some(frame)),
-            Err(e) => Ok(Some(RespFrame::Error(e.to_string().into()))),
+            Err(e) => Ok(Some(CommandError::from(e).into())),
         }
     }
+
+    /// Log bytes written to the traced connection.
+    fn trace_out(&self, bytes: &[u8]) {
+        self.trace("out", bytes);
+    }
+
+    /// The subscriber timestamps the lines. Bytes are escaped, so that a frame
+    /// stays on one line and binary data is readable.
+    fn trace(&self, direction: &str, bytes: &[u8]) {
+        if let Some(id) = self.trace {
+            info!(target: "rredis::trace", "{}", trace_line(id, direction, bytes));
+        }
+    }
+}
+
+fn trace_line(id: u64, direction: &str, bytes: &[u8]) -> String {
+    format!("client {} {} \"{}\"", id, direction, bytes.escape_ascii())
 }
 
 pub async fn handle_stream(stream: TcpStream, backend: Backend) -> anyhow::Result<()> {
-    let mut framed = Framed::new(stream, RespFrameCodec);
+    let state = ServerState::new(
+        backend,
+        Plugins::default(),
+        WriteHooks::default(),
+        CommandRenames::default(),
+        ServerConfig::default(),
+    );
+    serve_stream(stream, state).await
+}
+
+pub(crate) async fn serve_stream(stream: TcpStream, state: ServerState) -> anyhow::Result<()> {
+    let peer_addr = stream.peer_addr()?;
+    let peer_ip = peer_addr.ip();
+    if state.protected && !is_loopback(peer_ip) {
+        let mut stream = stream;
+        let denied = format!("-{}\r\n", CommandError::ProtectedMode);
+        stream.write_all(denied.as_bytes()).await?;
+        return Err(anyhow!("refused in protected mode"));
+    }
+    let backend = state.backend.clone();
+    let slow_consumer = &state.config.slow_consumer;
+    let (push_tx, mut push_rx) = push_queue(slow_consumer.max_queued, slow_consumer.overflow);
+    let mut session = Session::new(backend.clone(), push_tx, peer_addr);
+    session.trace = state.config.trace_protocol;
+    let codec = RespFrameCodec {
+        trace: session.trace_id(),
+        resp2: !session.resp3,
+        large_bulks: Some(LargeBulks {
+            min_size: state.config.buffers.large_bulk_size,
+            max_size: state.config.request_limits.max_request_size,
+        }),
+        partial: None,
+    };
+    let stream = ReplyFaults::new(stream);
+    let mut framed = Framed::with_capacity(stream, codec, state.config.buffers.initial_size);
+    let mut rate_bucket = state.limiter.client_bucket();
 
     loop {
-        match framed.next().await {
-            None => return Err(anyhow!("connection closed")),
-            Some(Err(e)) => return Err(anyhow!(e.to_string())),
-            Some(Ok(frame)) => {
-                let req = RedisRequest {
-                    frame,
-                    backend: backend.clone(),
+        shrink_idle_buffers(&mut framed, &state.config.buffers);
+        tokio::select! {
+            frame = framed.next() => match frame {
+                None => return Err(anyhow!("connection closed")),
+                Some(Err(e)) => return Err(anyhow!(e.to_string())),
+                Some(Ok(mut frame)) => {
+                    session.touch();
+                    match state.limiter.check(rate_bucket.as_mut(), peer_ip) {
+                        Throttle::Allow => {}
+                        Throttle::Reject => {
+                            framed.send(RespFrame::Error(RATE_LIMIT_ERROR.into())).await?;
+                            continue;
+                        }
+                        Throttle::Delay(wait) => tokio::time::sleep(wait).await,
+                    }
+                    let checked = state
+                        .renames
+                        .resolve(&mut frame)
+                        .and_then(|_| check_request_limits(&frame, &state.config.request_limits));
+                    if let Err(e) = checked {
+                        if let Some(transaction) = session.transaction.as_mut() {
+                            transaction.abort();
+                        }
+                        framed.send(e.into()).await?;
+                        continue;
+                    }
+                    if session.is_subscribed() && !ConnectionCommand::is_subscription(&frame) {
+                        framed.send(subscribed_context_error(&frame)).await?;
+                        continue;
+                    }
+                    if TransactionCommand::matches(&frame) {
+                        let reply = session.handle_transaction_command(frame, &state, peer_addr);
+                        write_reply(&mut framed, reply, &state.config.buffers).await?;
+                        continue;
+                    }
+                    if let Some(transaction) = session.transaction.as_mut() {
+                        let asking = std::mem::take(&mut session.asking);
+                        let reply = transaction.queue(frame, &state.plugins, |keys| {
+                            route_request(&backend, keys, asking)
+                        });
+                        write_reply(&mut framed, reply, &state.config.buffers).await?;
+                        continue;
+                    }
+                    if ConnectionCommand::matches(&frame) {
+                        let replies = session.handle_connection_command(frame);
+                        // the reply to CLIENT TRACE ON is traced, the one to OFF isn't,
+                        // and the reply to HELLO is in the protocol it switched to.
+                        framed.codec_mut().trace = session.trace_id();
+                        framed.codec_mut().resp2 = !session.resp3;
+                        framed.get_mut().delay = session.reply_delay;
+                        framed.get_mut().fragment = session.reply_fragment;
+                        for resp in replies {
+                            framed.feed(resp).await?;
+                        }
+                        framed.flush().await?;
+                        continue;
+                    }
+                    debug!("request from {}:\n{}", peer_ip, frame);
+                    if slow_consumer.overflow == PushOverflow::BlockPublisher {
+                        if let Some(channel) = published_channel(&frame) {
+                            backend.wait_for_subscribers(&channel).await;
+                        }
+                    }
+                    let read_keys = session.read_keys(&frame);
+                    let attribute = session.freshness(&frame);
+                    let req = RedisRequest {
+                        frame,
+                        state: state.clone(),
+                        client_addr: peer_addr,
+                        // ASKING only applies to the command which follows it.
+                        asking: std::mem::take(&mut session.asking),
+                    };
+                    let resp = match BlockingCommand::parse(&req.frame) {
+                        Some(blocking) => handle_blocking_request(req, blocking).await?,
+                        None => handle_request(req).await?,
+                    };
+                    backend.track_keys(session.id, read_keys.iter().map(String::as_str));
+                    if let Some(attribute) = attribute {
+                        if !matches!(resp.frame, RespFrame::Null(_) | RespFrame::Error(_)) {
+                            // written with the reply, an attribute is never sent alone.
+                            let start = framed.write_buffer().len();
+                            attribute.write_to(framed.write_buffer_mut());
+                            framed.codec().trace_out(&framed.write_buffer()[start..]);
+                        }
+                    }
+                    write_reply(&mut framed, resp.frame, &state.config.buffers).await?;
+                    if resp.over_budget && state.config.slowlog.yield_over_budget {
+                        tokio::task::yield_now().await;
+                    }
+                }
+            },
+            push = push_rx.recv() => match push {
+                Some(push) => send_push(&mut framed, push, slow_consumer).await?,
+                None => return Err(anyhow!(
+                    "slow consumer: more than {} pushed messages queued, disconnecting",
+                    slow_consumer.max_queued
+                )),
+            }
+        }
+    }
+}
+
+/// Whether the client connected from this host, over IPv4 or IPv6.
+fn is_loopback(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback(),
+        IpAddr::V6(ip) => ip
+            .to_ipv4_mapped()
+            .map_or(ip.is_loopback(), |ip| ip.is_loopback()),
+    }
+}
+
+/// Release the memory of buffers which grew for a large frame and are now drained.
+fn shrink_idle_buffers<T>(framed: &mut Framed<T, RespFrameCodec>, config: &BufferConfig) {
+    let read_buf = framed.read_buffer_mut();
+    if read_buf.is_empty() && read_buf.capacity() > config.max_idle_size {
+        *read_buf = BytesMut::with_capacity(config.initial_size);
+    }
+    let write_buf = framed.write_buffer_mut();
+    if write_buf.is_empty() && write_buf.capacity() > config.max_idle_size {
+        *write_buf = BytesMut::with_capacity(config.initial_size);
+    }
+}
+
+/// Write the reply to a request. The replies to pipelined requests are written
+/// together, the write is only flushed once no other request was received entirely.
+///
+/// A reply holding large bulk strings is written at once with a vectored write,
+/// which references them rather than copying them to the write buffer.
+async fn write_reply<T>(
+    framed: &mut Framed<T, RespFrameCodec>,
+    reply: RespFrame,
+    config: &BufferConfig,
+) -> anyhow::Result<()>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    if !holds_large_bulk(&reply, config.large_bulk_size) {
+        framed.feed(reply).await?;
+        if !request_pending(framed) {
+            framed.flush().await?;
+        }
+        return Ok(());
+    }
+    let reply = if framed.codec().resp2 {
+        reply.into_resp2()
+    } else {
+        reply
+    };
+    let mut encoded = EncodedFrames::new(config.large_bulk_size);
+    encoded.push(reply);
+    if framed.codec().trace.is_some() {
+        let bytes = encoded.clone().copy_to_bytes(encoded.remaining());
+        framed.codec().trace_out(&bytes);
+    }
+    // the replies before it go first.
+    framed.flush().await?;
+    let stream = framed.get_mut();
+    write_all_vectored(stream, &mut encoded).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Write all of `buf`, passing several of its chunks to every write.
+async fn write_all_vectored<W>(writer: &mut W, buf: &mut impl Buf) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while buf.has_remaining() {
+        let n = {
+            let mut slices = [IoSlice::new(&[]); 64];
+            let cnt = buf.chunks_vectored(&mut slices);
+            writer.write_vectored(&slices[..cnt]).await?
+        };
+        if n == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        buf.advance(n);
+    }
+    Ok(())
+}
+
+impl<T> ReplyFaults<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            delay: Duration::ZERO,
+            fragment: 0,
+            sleep: None,
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for ReplyFaults<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for ReplyFaults<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.delay.is_zero() {
+            let delay = self.delay;
+            let pending = self.sleep.get_or_insert_with(|| Box::pin(sleep(delay)));
+            ready!(pending.as_mut().poll(cx));
+        }
+        let len = match self.fragment {
+            0 => buf.len(),
+            fragment => buf.len().min(fragment),
+        };
+        let res = ready!(Pin::new(&mut self.inner).poll_write(cx, &buf[..len]));
+        // the next write waits again.
+        self.sleep = None;
+        Poll::Ready(res)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+fn holds_large_bulk(frame: &RespFrame, min_size: usize) -> bool {
+    match frame {
+        RespFrame::BulkString(BulkString(Some(v))) => v.len() >= min_size,
+        RespFrame::Array(array) => array.iter().any(|f| holds_large_bulk(f, min_size)),
+        RespFrame::Push(push) => push.iter().any(|f| holds_large_bulk(f, min_size)),
+        RespFrame::Set(set) => set.iter().any(|f| holds_large_bulk(f, min_size)),
+        RespFrame::Map(map) => map
+            .iter()
+            .any(|(k, v)| holds_large_bulk(k, min_size) || holds_large_bulk(v, min_size)),
+        _ => false,
+    }
+}
+
+/// The shard channel of a SPUBLISH, whose publisher may wait for its subscribers.
+fn published_channel(frame: &RespFrame) -> Option<String> {
+    match frame {
+        RespFrame::Array(array) if command_name_in(frame, &["spublish"]) => match array.get(1) {
+            Some(RespFrame::BulkString(channel)) => {
+                Some(String::from_utf8_lossy(channel.as_ref()).into_owned())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether the next request was already received entirely.
+fn request_pending<T>(framed: &Framed<T, RespFrameCodec>) -> bool {
+    framed.codec().partial.is_none() && parse_frame_length(framed.read_buffer()).is_ok()
+}
+
+/// Write a pushed message, detecting clients which stopped reading them.
+async fn send_push<T>(
+    framed: &mut Framed<T, RespFrameCodec>,
+    push: RespFrame,
+    config: &SlowConsumerConfig,
+) -> anyhow::Result<()>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    match timeout(config.max_write_stall, framed.send(push)).await {
+        Ok(res) => res,
+        Err(_) => match config.action {
+            SlowConsumerAction::Log => {
+                warn!(
+                    "slow consumer: pushed message blocked for more than {:?}",
+                    config.max_write_stall
+                );
+                // the frame is already buffered, wait for it to be written.
+                framed.flush().await
+            }
+            SlowConsumerAction::Disconnect => Err(anyhow!(
+                "slow consumer: pushed message blocked for more than {:?}, disconnecting",
+                config.max_write_stall
+            )),
+        },
+    }
+}
+
+impl Session {
+    fn new(backend: Backend, push_tx: PushSender, addr: SocketAddr) -> Self {
+        let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+        backend.register_client(ConnectedClient::new(id, addr.to_string()));
+        Self {
+            id,
+            backend,
+            push_tx,
+            shard_channels: HashSet::new(),
+            tracking: false,
+            attributes: false,
+            asking: false,
+            trace: false,
+            resp3: false,
+            transaction: None,
+            watched: Vec::new(),
+            reply_delay: Duration::ZERO,
+            reply_fragment: 0,
+        }
+    }
+
+    fn trace_id(&self) -> Option<u64> {
+        self.trace.then_some(self.id)
+    }
+
+    /// Record that the client just sent a command.
+    fn touch(&self) {
+        self.backend
+            .update_client(self.id, |client| client.last_interaction = Instant::now());
+    }
+
+    fn is_subscribed(&self) -> bool {
+        !self.shard_channels.is_empty()
+    }
+
+    fn sync_subscriptions(&self) {
+        let count = self.shard_channels.len();
+        self.backend
+            .update_client(self.id, |client| client.shard_subscriptions = count);
+    }
+
+    /// The remaining time to live of the value read by GET or HGET, if the client
+    /// asked for it. Other reads don't get an attribute.
+    fn freshness(&self, frame: &RespFrame) -> Option<RespAttribute> {
+        let RespFrame::Array(args) = frame else {
+            return None;
+        };
+        if !self.attributes {
+            return None;
+        }
+        let arg = |i: usize| match args.get(i) {
+            Some(RespFrame::BulkString(arg)) => {
+                Some(String::from_utf8_lossy(arg.as_ref()).into_owned())
+            }
+            _ => None,
+        };
+        let pttl = match (arg(0)?.to_ascii_lowercase().as_str(), args.len()) {
+            ("get", 2) => self.backend.pttl(&arg(1)?),
+            ("hget", 3) => self.backend.hpttl(&arg(1)?, &[arg(2)?])[0],
+            _ => return None,
+        };
+        let attribute = [(SimpleString::new("pttl"), RespFrame::Integer(pttl))];
+        Some(RespAttribute::new(attribute.into_iter().collect()))
+    }
+
+    /// Keys read by the frame's command which should be tracked for client-side caching.
+    fn read_keys(&self, frame: &RespFrame) -> Vec<String> {
+        let RespFrame::Array(args) = frame else {
+            return vec![];
+        };
+        let spec = match args.first() {
+            Some(RespFrame::BulkString(name)) if self.tracking => lookup_command(name.as_ref()),
+            _ => None,
+        };
+        match spec {
+            Some(spec) if spec.is_readonly() => spec.keys(args),
+            _ => vec![],
+        }
+    }
+
+    /// Move the transaction of the connection: MULTI starts queuing commands, which
+    /// EXEC runs and DISCARD drops, both also forgetting the keys of WATCH.
+    fn handle_transaction_command(
+        &mut self,
+        frame: RespFrame,
+        state: &ServerState,
+        addr: SocketAddr,
+    ) -> RespFrame {
+        // UNWATCH is queued in a transaction, which needs the frame.
+        let cmd = match TransactionCommand::try_from(frame.clone()) {
+            Ok(cmd) => cmd,
+            Err(e) => {
+                if let Some(transaction) = self.transaction.as_mut() {
+                    transaction.abort();
+                }
+                return e.into();
+            }
+        };
+        match (cmd, self.transaction.take()) {
+            (TransactionCommand::Multi, None) => {
+                self.transaction = Some(Transaction::default());
+                RESP_OK.clone()
+            }
+            // the nesting error, like WATCH's, doesn't abort the transaction.
+            (TransactionCommand::Multi, Some(transaction)) => {
+                self.transaction = Some(transaction);
+                CommandError::InvalidCommand("MULTI calls can not be nested".to_string()).into()
+            }
+            (TransactionCommand::Watch(_), Some(transaction)) => {
+                self.transaction = Some(transaction);
+                CommandError::InvalidCommand("WATCH inside MULTI is not allowed".to_string()).into()
+            }
+            (TransactionCommand::Watch(keys), None) => {
+                self.backend
+                    .watch_keys(self.id, keys.iter().map(String::as_str));
+                self.watched.extend(keys);
+                RESP_OK.clone()
+            }
+            (TransactionCommand::Unwatch, Some(mut transaction)) => {
+                let reply = transaction.queue(frame, &state.plugins, |_| Ok(()));
+                self.transaction = Some(transaction);
+                reply
+            }
+            (TransactionCommand::Unwatch, None) => {
+                self.unwatch();
+                RESP_OK.clone()
+            }
+            (TransactionCommand::Discard, Some(_)) => {
+                self.unwatch();
+                RESP_OK.clone()
+            }
+            (TransactionCommand::Exec, Some(transaction)) if transaction.is_aborted() => {
+                self.unwatch();
+                CommandError::ExecAbort.into()
+            }
+            (TransactionCommand::Exec, Some(transaction)) => {
+                self.exec(transaction.into_queued(), state, addr)
+            }
+            (TransactionCommand::Exec, None) => {
+                CommandError::InvalidCommand("EXEC without MULTI".to_string()).into()
+            }
+            (TransactionCommand::Discard, None) => {
+                CommandError::InvalidCommand("DISCARD without MULTI".to_string()).into()
+            }
+        }
+    }
+
+    /// Forget the keys the connection watches.
+    fn unwatch(&mut self) {
+        let keys = std::mem::take(&mut self.watched);
+        self.backend
+            .unwatch_keys(self.id, keys.iter().map(String::as_str));
+    }
+
+    /// Run the commands of a transaction, unless a watched key was modified.
+    ///
+    /// The watched keys and those of the commands are locked exclusively meanwhile,
+    /// so no command of another connection runs between the check and the commands,
+    /// nor between the commands. A command whose keys the command table doesn't
+    /// locate, like FLUSHALL or a plugin, locks every key.
+    fn exec(&mut self, queued: Vec<RespFrame>, state: &ServerState, addr: SocketAddr) -> RespFrame {
+        let mut keys = self.watched.clone();
+        let mut all = false;
+        for frame in &queued {
+            let RespFrame::Array(args) = frame else {
+                continue;
+            };
+            let spec = match args.first() {
+                Some(RespFrame::BulkString(name)) if state.plugins.get(name.as_ref()).is_none() => {
+                    lookup_command(name.as_ref())
+                }
+                _ => None,
+            };
+            match spec {
+                Some(spec) => {
+                    let command = spec.keys(args);
+                    all |= command.is_empty() && spec.is_write();
+                    keys.extend(command);
+                }
+                None => all = true,
+            }
+        }
+        let backend = self.backend.clone();
+        let _guard = backend.lock_transaction(keys.iter().map(String::as_str), all);
+        let dirty = self.backend.is_watch_dirty(self.id);
+        self.unwatch();
+        if dirty {
+            return RespArray::null().into();
+        }
+        let replies: Vec<_> = queued
+            .into_iter()
+            .map(|frame| self.execute_queued(frame, state, addr))
+            .collect();
+        RespArray::new(replies).into()
+    }
+
+    /// Run a command queued by a transaction, on the thread holding the locks of
+    /// its keys.
+    fn execute_queued(
+        &mut self,
+        frame: RespFrame,
+        state: &ServerState,
+        addr: SocketAddr,
+    ) -> RespFrame {
+        // UNWATCH, the keys were forgotten when EXEC started.
+        if TransactionCommand::matches(&frame) {
+            return RESP_OK.clone();
+        }
+        if ConnectionCommand::matches(&frame) {
+            // a subscription replies a frame per channel.
+            let mut replies = self.handle_connection_command(frame);
+            return match replies.len() {
+                1 => replies.remove(0),
+                _ => RespArray::new(replies).into(),
+            };
+        }
+        let args = request_args(&frame);
+        let asking = std::mem::take(&mut self.asking);
+        let (frame, elapsed) = execute_request(frame, &args, state, asking);
+        finish_request(state, &args, elapsed, addr, frame).frame
+    }
+
+    fn handle_connection_command(&mut self, frame: RespFrame) -> Vec<RespFrame> {
+        let cmd = match ConnectionCommand::try_from(frame) {
+            Ok(cmd) => cmd,
+            Err(e) => return vec![e.into()],
+        };
+        match cmd {
+            ConnectionCommand::ClientTracking(cmd) => {
+                if cmd.on {
+                    self.backend.enable_tracking(
+                        self.id,
+                        self.push_tx.clone(),
+                        cmd.bcast,
+                        cmd.prefixes,
+                    );
+                } else {
+                    self.backend.disable_tracking(self.id);
+                }
+                self.tracking = cmd.on;
+                self.backend
+                    .update_client(self.id, |client| client.tracking = cmd.on);
+                vec![RESP_OK.clone()]
+            }
+            ConnectionCommand::ClientAttributes(on) => {
+                self.attributes = on;
+                vec![RESP_OK.clone()]
+            }
+            ConnectionCommand::ClientTrace(on) => {
+                self.trace = on;
+                vec![RESP_OK.clone()]
+            }
+            ConnectionCommand::DebugReplyDelay(delay) => {
+                self.reply_delay = delay;
+                vec![RESP_OK.clone()]
+            }
+            ConnectionCommand::DebugReplyFragment(len) => {
+                self.reply_fragment = len;
+                vec![RESP_OK.clone()]
+            }
+            ConnectionCommand::Asking => {
+                if self.backend.cluster().is_none() {
+                    return vec![CommandError::InvalidCommand(
+                        "This instance has cluster support disabled".to_string(),
+                    )
+                    .into()];
+                }
+                self.asking = true;
+                vec![RESP_OK.clone()]
+            }
+            ConnectionCommand::Hello(hello) => {
+                if let Some(protover) = hello.protover {
+                    self.resp3 = protover == 3;
+                }
+                let resp = if self.resp3 { 3 } else { 2 };
+                self.backend.update_client(self.id, |client| {
+                    client.resp = resp;
+                    if let Some(name) = &hello.setname {
+                        client.name.clone_from(name);
+                    }
+                });
+                let mode = match self.backend.cluster() {
+                    Some(_) => "cluster",
+                    None => "standalone",
+                };
+                let mut reply = RespMap::new();
+                reply.insert(BulkString::new("server"), BulkString::new("redis").into());
+                reply.insert(
+                    BulkString::new("version"),
+                    BulkString::new(version::VERSION).into(),
+                );
+                reply.insert(BulkString::new("proto"), RespFrame::Integer(resp as i64));
+                reply.insert(BulkString::new("id"), RespFrame::Integer(self.id as i64));
+                reply.insert(BulkString::new("mode"), BulkString::new(mode).into());
+                reply.insert(BulkString::new("role"), BulkString::new("master").into());
+                reply.insert(BulkString::new("modules"), RespArray::new(vec![]).into());
+                vec![reply.into()]
+            }
+            ConnectionCommand::ClientInfo => {
+                let line = self
+                    .backend
+                    .client_info(self.id)
+                    .map(|client| client.describe())
+                    .unwrap_or_default();
+                vec![BulkString::new(line).into()]
+            }
+            ConnectionCommand::SSubscribe(cmd) => {
+                let replies = cmd
+                    .channels
+                    .into_iter()
+                    .map(|channel| {
+                        if self.shard_channels.insert(channel.clone()) {
+                            self.backend
+                                .ssubscribe(channel.clone(), (self.id, self.push_tx.clone()));
+                        }
+                        subscription_reply("ssubscribe", Some(&channel), self.shard_channels.len())
+                    })
+                    .collect();
+                self.sync_subscriptions();
+                replies
+            }
+            ConnectionCommand::SUnsubscribe(cmd) => {
+                let channels = if cmd.channels.is_empty() {
+                    self.shard_channels.iter().cloned().collect()
+                } else {
+                    cmd.channels
                 };
-                let resp = handle_request(req).await?;
-                framed.send(resp.frame).await?;
+                if channels.is_empty() {
+                    return vec![subscription_reply("sunsubscribe", None, 0)];
+                }
+                let replies = channels
+                    .into_iter()
+                    .map(|channel| {
+                        if self.shard_channels.remove(&channel) {
+                            self.backend.sunsubscribe(&channel, self.id);
+                        }
+                        subscription_reply(
+                            "sunsubscribe",
+                            Some(&channel),
+                            self.shard_channels.len(),
+                        )
+                    })
+                    .collect();
+                self.sync_subscriptions();
+                replies
             }
         }
     }
 }
 
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.backend.unregister_client(self.id);
+        self.unwatch();
+        for channel in &self.shard_channels {
+            self.backend.sunsubscribe(channel, self.id);
+        }
+        if self.tracking {
+            self.backend.disable_tracking(self.id);
+        }
+    }
+}
+
+fn subscribed_context_error(frame: &RespFrame) -> RespFrame {
+    let name = match frame {
+        RespFrame::Array(array) => match array.first() {
+            Some(RespFrame::BulkString(c)) => String::from_utf8_lossy(c.as_ref()).to_lowercase(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    };
+    RespFrame::Error(
+        format!(
+            "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+            name
+        )
+        .into(),
+    )
+}
+
 async fn handle_request(req: RedisRequest) -> anyhow::Result<RedisResponse> {
-    let (frame, backend) = (req.frame, req.backend);
+    let RedisRequest {
+        frame,
+        state,
+        client_addr,
+        asking,
+    } = req;
+    // the request could not be decoded, reply with the protocol error.
+    if let RespFrame::Error(_) = frame {
+        return Ok(RedisResponse {
+            frame,
+            over_budget: false,
+        });
+    }
+    let args = request_args(&frame);
+    let (frame, elapsed, args) = match (&state.workers, request_slot(&args)) {
+        (Some(workers), Some(slot)) => {
+            let job_state = state.clone();
+            workers
+                .run(slot, move || {
+                    let (frame, elapsed) = execute_request(frame, &args, &job_state, asking);
+                    (frame, elapsed, args)
+                })
+                .await?
+        }
+        _ => {
+            let (frame, elapsed) = execute_request(frame, &args, &state, asking);
+            (frame, elapsed, args)
+        }
+    };
+    Ok(finish_request(&state, &args, elapsed, client_addr, frame))
+}
+
+/// Run a blocking command again after each write to one of its keys, until it replies
+/// something else than null, or reply its null once its timeout elapsed.
+async fn handle_blocking_request(
+    req: RedisRequest,
+    blocking: BlockingCommand,
+) -> anyhow::Result<RedisResponse> {
+    let deadline = blocking
+        .timeout
+        .map(|timeout| tokio::time::Instant::now() + timeout);
+    // subscribed before the first run, so that no write after it is missed.
+    let mut changes = req.state.backend.watch_changes();
+    loop {
+        let resp = handle_request(req.clone()).await?;
+        if !matches!(
+            resp.frame,
+            RespFrame::Null(_) | RespFrame::Array(RespArray(None))
+        ) {
+            return Ok(resp);
+        }
+        loop {
+            let change = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, changes.recv()).await {
+                    Ok(change) => change,
+                    Err(_) => return Ok(resp),
+                },
+                None => changes.recv().await,
+            };
+            match change {
+                Ok(change)
+                    if change.kind == ChangeKind::Set && blocking.keys.contains(&change.key) =>
+                {
+                    break
+                }
+                Ok(_) => {}
+                // the changes missed may have written one of the keys.
+                Err(RecvError::Lagged(_)) => break,
+                Err(RecvError::Closed) => return Ok(resp),
+            }
+        }
+    }
+}
+
+/// The slowlog and the hooks need the arguments, which parsing the command consumes.
+fn request_args(frame: &RespFrame) -> Vec<RespFrame> {
+    match frame {
+        RespFrame::Array(RespArray(Some(args))) => args.clone(),
+        _ => vec![],
+    }
+}
+
+/// Log the command if it was slow, and build the response of its reply.
+fn finish_request(
+    state: &ServerState,
+    args: &[RespFrame],
+    elapsed: Option<Duration>,
+    client_addr: SocketAddr,
+    frame: RespFrame,
+) -> RedisResponse {
+    let config = &state.config.slowlog;
+    let over_budget = elapsed
+        .zip(config.budget)
+        .is_some_and(|(elapsed, budget)| elapsed > budget);
+    if let Some(elapsed) = elapsed {
+        if over_budget || elapsed > config.log_slower_than {
+            state.backend.slowlog_push(
+                args,
+                elapsed,
+                client_addr.to_string(),
+                over_budget,
+                config.max_len,
+            );
+        }
+    }
+    RedisResponse { frame, over_budget }
+}
+
+/// The hash slot of the first key of the command, `None` for commands without keys.
+/// Commands on keys sharing a hash tag are thus run by the same worker.
+fn request_slot(args: &[RespFrame]) -> Option<u16> {
+    let spec = match args.first() {
+        Some(RespFrame::BulkString(name)) => lookup_command(name.as_ref())?,
+        _ => return None,
+    };
+    let first = *spec.key_indexes_in(args).first()?;
+    match args.get(first) {
+        Some(RespFrame::BulkString(key)) => Some(key_slot(key.as_ref())),
+        _ => None,
+    }
+}
+
+/// Check that the keys are served by this node in cluster mode, else the redirect
+/// to the node serving them. The keys of a command, or of a transaction, must all be
+/// in the same slot.
+fn route_request(backend: &Backend, keys: &[String], asking: bool) -> Result<(), CommandError> {
+    let (Some(cluster), Some(first)) = (backend.cluster(), keys.first()) else {
+        return Ok(());
+    };
+    let slot = key_slot(first.as_bytes());
+    if keys[1..].iter().any(|key| key_slot(key.as_bytes()) != slot) {
+        return Err(CommandError::CrossSlot);
+    }
+    match cluster.route(slot, asking) {
+        SlotRoute::Myself => Ok(()),
+        // the missing keys may have been moved already, or are created on the target.
+        SlotRoute::Migrating(addr) if keys.iter().any(|key| !backend.exists(key)) => {
+            Err(CommandError::Ask { slot, addr })
+        }
+        SlotRoute::Migrating(_) => Ok(()),
+        SlotRoute::Node(addr) => Err(CommandError::Moved { slot, addr }),
+        SlotRoute::Unassigned => Err(CommandError::ClusterDown),
+    }
+}
+
+/// Run the command of the frame, returns its reply and how long it ran,
+/// `None` if it didn't run. Waiting for the locks of its keys is not counted.
+fn execute_request(
+    frame: RespFrame,
+    args: &[RespFrame],
+    state: &ServerState,
+    asking: bool,
+) -> (RespFrame, Option<Duration>) {
+    let backend = &state.backend;
+    if let Some(RespFrame::BulkString(name)) = args.first() {
+        if let Some(plugin) = state.plugins.get(name.as_ref()) {
+            let start = Instant::now();
+            let res = Plugins::execute(plugin.as_ref(), args.to_vec(), backend);
+            return (res, Some(start.elapsed()));
+        }
+    }
+    let spec = match args.first() {
+        Some(RespFrame::BulkString(name)) => lookup_command(name.as_ref()),
+        _ => None,
+    };
+    let keys = spec.map(|spec| spec.keys(args)).unwrap_or_default();
+    if let Err(e) = route_request(backend, &keys, asking) {
+        return (e.into(), None);
+    }
+    if let Some(max) = state.config.max_memory {
+        if spec.is_some_and(|spec| spec.is_denyoom()) && backend.used_memory() > max {
+            return (CommandError::OutOfMemory.into(), None);
+        }
+    }
     match TryInto::<Command>::try_into(frame) {
         Ok(cmd) => {
-            let res = cmd.execute(&backend);
-            Ok(RedisResponse { frame: res })
+            // multi-key commands must not be observed half-done.
+            let _guard = backend.lock_keys(keys.iter().map(String::as_str));
+            let start = Instant::now();
+            let res = match spec {
+                Some(spec) if spec.is_write() && !state.hooks.is_empty() => {
+                    let write = WriteCommand {
+                        spec,
+                        keys: &keys,
+                        args,
+                    };
+                    state.hooks.run(&write, backend, || cmd.execute(backend))
+                }
+                _ => cmd.execute(backend),
+            };
+            (res, Some(start.elapsed()))
         }
-        Err(e) => Ok(RedisResponse {
-            frame: RespFrame::Error(e.to_string().into()),
-        }),
+        Err(e) => (e.into(), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{duplex, AsyncReadExt};
+
+    use crate::BulkString;
+
+    use super::*;
+
+    #[test]
+    fn test_is_loopback() -> anyhow::Result<()> {
+        for ip in ["127.0.0.1", "127.1.2.3", "::1", "::ffff:127.0.0.1"] {
+            assert!(is_loopback(ip.parse()?), "{}", ip);
+        }
+        for ip in ["10.0.0.1", "0.0.0.0", "::ffff:10.0.0.1", "fe80::1"] {
+            assert!(!is_loopback(ip.parse()?), "{}", ip);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_shrink_idle_buffers() {
+        let (client, _peer) = duplex(64);
+        let mut framed = Framed::with_capacity(client, RespFrameCodec::default(), 16);
+        let config = BufferConfig {
+            initial_size: 16,
+            max_idle_size: 1024,
+            ..Default::default()
+        };
+
+        framed.read_buffer_mut().extend_from_slice(&[b'x'; 4096]);
+        shrink_idle_buffers(&mut framed, &config);
+        // buffers holding data are kept.
+        assert!(framed.read_buffer().capacity() >= 4096);
+
+        framed.read_buffer_mut().clear();
+        framed.write_buffer_mut().reserve(4096);
+        shrink_idle_buffers(&mut framed, &config);
+        assert!(framed.read_buffer().capacity() < 1024);
+        assert!(framed.write_buffer().capacity() < 1024);
+    }
+
+    #[tokio::test]
+    async fn test_reply_faults() -> anyhow::Result<()> {
+        let (client, mut peer) = duplex(64);
+        let mut stream = ReplyFaults::new(client);
+        assert_eq!(stream.write(b"+hello\r\n").await?, 8);
+
+        stream.fragment = 3;
+        stream.delay = Duration::from_millis(20);
+        let start = Instant::now();
+        assert_eq!(stream.write(b"+hello\r\n").await?, 3);
+        stream.write_all(b"llo\r\n").await?;
+        assert!(start.elapsed() >= Duration::from_millis(60));
+
+        let mut buf = [0; 14];
+        peer.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"+hello\r\n+hello");
+        Ok(())
+    }
+
+    #[test]
+    fn test_traced_codec() -> anyhow::Result<()> {
+        let mut codec = RespFrameCodec {
+            trace: Some(7),
+            ..Default::default()
+        };
+        let mut buf = BytesMut::from(&b"+OK\r\n$3\r\nab"[..]);
+        assert_eq!(
+            codec.decode(&mut buf)?,
+            Some(SimpleString::new("OK").into())
+        );
+        assert_eq!(codec.decode(&mut buf)?, None);
+        buf.extend_from_slice(b"c\r\n");
+        assert_eq!(codec.decode(&mut buf)?, Some(BulkString::new("abc").into()));
+        assert!(buf.is_empty());
+
+        assert_eq!(
+            trace_line(7, "in", b"$3\r\n\x00b\"\r\n"),
+            r#"client 7 in "$3\r\n\x00b\"\r\n""#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_reads_large_bulk_strings_as_they_arrive() -> anyhow::Result<()> {
+        let mut codec = RespFrameCodec {
+            large_bulks: Some(LargeBulks {
+                min_size: 8,
+                max_size: 1024,
+            }),
+            ..Default::default()
+        };
+        let mut buf = BytesMut::from(&b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$100\r\n"[..]);
+        for _ in 0..10 {
+            assert_eq!(codec.decode(&mut buf)?, None);
+            assert!(buf.is_empty());
+            buf.extend_from_slice(&[b'x'; 10]);
+        }
+        buf.extend_from_slice(b"\r\n+OK\r\n");
+        let frame = RespArray::new(vec![
+            BulkString::new("set").into(),
+            BulkString::new("k").into(),
+            BulkString::new(vec![b'x'; 100]).into(),
+        ]);
+        assert_eq!(codec.decode(&mut buf)?, Some(frame.into()));
+        assert_eq!(
+            codec.decode(&mut buf)?,
+            Some(SimpleString::new("OK").into())
+        );
+
+        // a bulk string larger than any request closes the connection.
+        let mut buf = BytesMut::from(&b"*2\r\n$3\r\nget\r\n$9223372036854775807\r\n"[..]);
+        assert!(codec.decode(&mut buf).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_reply() -> anyhow::Result<()> {
+        let (client, mut peer) = duplex(1024);
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        let config = BufferConfig {
+            large_bulk_size: 16,
+            ..Default::default()
+        };
+
+        // not flushed while the next request was already received.
+        framed
+            .read_buffer_mut()
+            .extend_from_slice(b"*1\r\n$4\r\nPING\r\n");
+        write_reply(&mut framed, SimpleString::new("PONG").into(), &config).await?;
+        assert_eq!(framed.write_buffer(), "+PONG\r\n");
+
+        framed.read_buffer_mut().clear();
+        let large: RespFrame = RespArray::new(vec![
+            BulkString::new(vec![b'x'; 100]).into(),
+            RespFrame::Integer(1),
+        ])
+        .into();
+        write_reply(&mut framed, large.clone(), &config).await?;
+        assert!(framed.write_buffer().is_empty());
+
+        let mut expected = b"+PONG\r\n".to_vec();
+        expected.extend_from_slice(&large.encode());
+        let mut received = vec![0; expected.len()];
+        peer.read_exact(&mut received).await?;
+        assert_eq!(received, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_push_detects_slow_consumer() -> anyhow::Result<()> {
+        // the peer never reads, so the small pipe fills up quickly.
+        let (client, _peer) = duplex(64);
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        let config = SlowConsumerConfig {
+            max_write_stall: Duration::from_millis(50),
+            action: SlowConsumerAction::Disconnect,
+            ..Default::default()
+        };
+
+        let push: RespFrame = BulkString::new(vec![b'x'; 16]).into();
+        send_push(&mut framed, push.clone(), &config).await?;
+
+        let push: RespFrame = BulkString::new(vec![b'x'; 128]).into();
+        let res = send_push(&mut framed, push, &config).await;
+        assert!(res.unwrap_err().to_string().starts_with("slow consumer"));
+        Ok(())
     }
 }
@@ -0,0 +1,221 @@
+use std::{
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+
+use crate::config::{RateLimitConfig, RateLimitKey, ThrottleAction};
+
+/// Buckets of idle IPs are dropped once there are more than this many.
+const MAX_IDLE_IP_BUCKETS: usize = 1024;
+
+/// A token bucket refilled at `rate` tokens per second, holding at most `burst` tokens.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, burst: u32, now: Instant) -> Self {
+        let burst = burst.max(1) as f64;
+        Self {
+            rate,
+            burst,
+            tokens: burst,
+            last: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last = now;
+    }
+
+    /// Take a token if one is available, otherwise return how long until one is.
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(self.wait_time())
+        }
+    }
+
+    /// Take a token, borrowing from the future if needed,
+    /// and return how long the caller should wait before using it.
+    pub fn acquire(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+
+    fn wait_time(&self) -> Duration {
+        Duration::from_secs_f64((1.0 - self.tokens).max(0.0) / self.rate)
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.burst
+    }
+}
+
+/// Outcome of checking a command against the rate limit.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Throttle {
+    Allow,
+    Reject,
+    Delay(Duration),
+}
+
+/// Rate limiter shared by all the connections of a server.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    config: Option<RateLimitConfig>,
+    ip_buckets: DashMap<IpAddr, Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: Option<RateLimitConfig>) -> Self {
+        Self {
+            config,
+            ip_buckets: DashMap::new(),
+        }
+    }
+
+    /// The per-connection bucket, if the limit is applied per client.
+    pub fn client_bucket(&self) -> Option<TokenBucket> {
+        match self.config {
+            Some(ref config) if config.key == RateLimitKey::Client => Some(TokenBucket::new(
+                config.commands_per_sec,
+                config.burst,
+                Instant::now(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Check a command received from `ip` against the limit.
+    pub fn check(&self, client_bucket: Option<&mut TokenBucket>, ip: IpAddr) -> Throttle {
+        let Some(ref config) = self.config else {
+            return Throttle::Allow;
+        };
+        let now = Instant::now();
+        let take = |bucket: &mut TokenBucket| match config.action {
+            ThrottleAction::Reject => match bucket.try_acquire(now) {
+                Ok(_) => Throttle::Allow,
+                Err(_) => Throttle::Reject,
+            },
+            ThrottleAction::Delay => match bucket.acquire(now) {
+                Duration::ZERO => Throttle::Allow,
+                wait => Throttle::Delay(wait),
+            },
+        };
+
+        match (config.key, client_bucket) {
+            (RateLimitKey::Client, Some(bucket)) => take(bucket),
+            (RateLimitKey::Client, None) => Throttle::Allow,
+            (RateLimitKey::Ip, _) => {
+                if self.ip_buckets.len() > MAX_IDLE_IP_BUCKETS {
+                    self.ip_buckets
+                        .retain(|_, bucket| !bucket.get_mut().unwrap().is_full(now));
+                }
+                let bucket = self.ip_buckets.entry(ip).or_insert_with(|| {
+                    Mutex::new(TokenBucket::new(config.commands_per_sec, config.burst, now))
+                });
+                let mut bucket = bucket.lock().unwrap();
+                take(&mut bucket)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(10.0, 2, now);
+        assert!(bucket.try_acquire(now).is_ok());
+        assert!(bucket.try_acquire(now).is_ok());
+        assert_eq!(bucket.try_acquire(now), Err(Duration::from_millis(100)));
+
+        let later = now + Duration::from_millis(100);
+        assert!(bucket.try_acquire(later).is_ok());
+        assert!(bucket.try_acquire(later).is_err());
+
+        // refill never exceeds the burst.
+        let much_later = later + Duration::from_secs(60);
+        assert!(bucket.try_acquire(much_later).is_ok());
+        assert!(bucket.try_acquire(much_later).is_ok());
+        assert!(bucket.try_acquire(much_later).is_err());
+    }
+
+    #[test]
+    fn test_token_bucket_acquire_borrows() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(10.0, 1, now);
+        assert_eq!(bucket.acquire(now), Duration::ZERO);
+        assert_eq!(bucket.acquire(now), Duration::from_millis(100));
+        assert_eq!(bucket.acquire(now), Duration::from_millis(200));
+        assert!(bucket.try_acquire(now).is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter_per_ip() {
+        let limiter = RateLimiter::new(Some(RateLimitConfig {
+            key: RateLimitKey::Ip,
+            commands_per_sec: 1.0,
+            burst: 1,
+            action: ThrottleAction::Reject,
+        }));
+        let ip1 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let ip2 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        assert!(limiter.client_bucket().is_none());
+        assert_eq!(limiter.check(None, ip1), Throttle::Allow);
+        assert_eq!(limiter.check(None, ip1), Throttle::Reject);
+        assert_eq!(limiter.check(None, ip2), Throttle::Allow);
+    }
+
+    #[test]
+    fn test_rate_limiter_per_client() {
+        let limiter = RateLimiter::new(Some(RateLimitConfig {
+            key: RateLimitKey::Client,
+            commands_per_sec: 1.0,
+            burst: 1,
+            action: ThrottleAction::Delay,
+        }));
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let mut bucket1 = limiter.client_bucket().unwrap();
+        let mut bucket2 = limiter.client_bucket().unwrap();
+        assert_eq!(limiter.check(Some(&mut bucket1), ip), Throttle::Allow);
+        assert!(matches!(
+            limiter.check(Some(&mut bucket1), ip),
+            Throttle::Delay(_)
+        ));
+        assert_eq!(limiter.check(Some(&mut bucket2), ip), Throttle::Allow);
+    }
+
+    #[test]
+    fn test_rate_limiter_disabled() {
+        let limiter = RateLimiter::default();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        for _ in 0..100 {
+            assert_eq!(limiter.check(None, ip), Throttle::Allow);
+        }
+    }
+}
@@ -4,12 +4,12 @@ use bytes::BytesMut;
 
 use crate::{
     cal_total_length, err::RespError, parse_length, parse_length_and_move, resp_frame::RespFrame,
-    RespDecode, RespEncode, BUF_CAP,
+    EncodedFrames, RespDecode, RespEncode, BUF_CAP,
 };
 
 pub const NULL_ARRAY: &[u8] = b"*-1\r\n";
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RespArray(pub(crate) Option<Vec<RespFrame>>);
 
 impl RespDecode for RespArray {
@@ -17,7 +17,7 @@ impl RespDecode for RespArray {
 
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         if buf.len() < Self::expect_length(buf)? {
-            return Err(RespError::NotCompleted);
+            return Err(RespError::NotCompleted(None));
         }
         let length = parse_length_and_move(Self::PREFIX, buf)?;
         if length == -1 {
@@ -65,6 +65,30 @@ impl RespEncode for RespArray {
             }
         }
     }
+    fn write_to(self, buf: &mut BytesMut) {
+        match self.0 {
+            None => buf.extend_from_slice(NULL_ARRAY),
+            Some(v) => {
+                buf.extend_from_slice(format!("*{}\r\n", v.len()).as_bytes());
+                for frame in v {
+                    frame.write_to(buf);
+                }
+            }
+        }
+    }
+
+    fn write_chunks(self, frames: &mut EncodedFrames) {
+        match self.0 {
+            None => frames.buf_mut().extend_from_slice(NULL_ARRAY),
+            Some(v) => {
+                let header = format!("*{}\r\n", v.len());
+                frames.buf_mut().extend_from_slice(header.as_bytes());
+                for frame in v {
+                    frame.write_chunks(frames);
+                }
+            }
+        }
+    }
 }
 
 impl RespArray {
@@ -133,7 +157,7 @@ mod tests {
         // not completed
         let mut buf = BytesMut::from("*2\r\n+foo\r\n");
         let result = RespArray::decode(&mut buf);
-        assert_eq!(result.unwrap_err(), RespError::NotCompleted);
+        assert_eq!(result.unwrap_err(), RespError::NotCompleted(None));
 
         // add bytes to buf to make it completed
         buf.extend_from_slice(b"+bar\r\n");
@@ -0,0 +1,104 @@
+use std::ops::Deref;
+
+use bytes::BytesMut;
+
+use crate::{
+    cal_total_length, err::RespError, parse_length, parse_length_and_move, resp_frame::RespFrame,
+    RespDecode, RespEncode, RespMap, BUF_CAP,
+};
+
+/// Auxiliary data about a reply, sent right before it, which clients not
+/// interested in it can skip.
+/// It isn't a [`RespFrame`]: it only ever precedes one.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RespAttribute(RespMap);
+
+/// The RESP attribute is encoded like a map but with a different first byte.
+/// Format:
+///     |<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>
+///
+/// - A pipe character (|) as the first byte.
+/// - One or more decimal digits (0..9) as the number of entries as an unsigned, base-10 value.
+/// - The CRLF terminator.
+/// - Two additional RESP types for every key and value of the attribute.
+impl RespEncode for RespAttribute {
+    fn encode(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(BUF_CAP);
+        buf.extend_from_slice(&format!("|{}\r\n", self.len()).into_bytes());
+        for (key, value) in self.0 {
+            buf.extend(key.encode());
+            buf.extend(value.encode());
+        }
+        buf
+    }
+}
+
+impl RespDecode for RespAttribute {
+    const PREFIX: &'static str = "|";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        if buf.len() < Self::expect_length(buf)? {
+            return Err(RespError::NotCompleted(None));
+        }
+        let length = parse_length_and_move(Self::PREFIX, buf)?;
+        let mut map = RespMap::new();
+        for _ in 0..length {
+            let key = RespFrame::decode(buf)?;
+            let value = RespFrame::decode(buf)?;
+            map.insert(key, value);
+        }
+        Ok(RespAttribute(map))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(Self::PREFIX, buf)?;
+        cal_total_length(buf, end, len as usize, Self::PREFIX)
+    }
+}
+
+impl RespAttribute {
+    pub fn new(map: RespMap) -> Self {
+        RespAttribute(map)
+    }
+}
+
+impl Deref for RespAttribute {
+    type Target = RespMap;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SimpleString;
+
+    use super::*;
+
+    #[test]
+    fn test_attribute_encode() {
+        let attribute = RespAttribute::new(
+            [(SimpleString::new("pttl"), RespFrame::Integer(-1))]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(attribute.encode(), b"|1\r\n+pttl\r\n:-1\r\n");
+    }
+
+    #[test]
+    fn test_attribute_decode() -> anyhow::Result<()> {
+        let mut buf = BytesMut::from("|1\r\n+pttl\r\n:1500\r\n$1\r\nv\r\n");
+        let attribute = RespAttribute::decode(&mut buf)?;
+        assert_eq!(
+            attribute.get(&SimpleString::new("pttl").into()),
+            Some(&RespFrame::Integer(1500))
+        );
+        // the reply it precedes is left in the buffer.
+        assert_eq!(buf, BytesMut::from("$1\r\nv\r\n"));
+
+        let mut buf = BytesMut::from("|1\r\n+pttl\r\n");
+        let result = RespAttribute::decode(&mut buf);
+        assert_eq!(result.unwrap_err(), RespError::NotCompleted(None));
+        Ok(())
+    }
+}
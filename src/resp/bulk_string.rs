@@ -1,15 +1,16 @@
 use std::ops::Deref;
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 
 use crate::{
-    err::RespError, parse_length, parse_length_and_move, RespDecode, RespEncode, CRLF, CRLF_LEN,
+    err::RespError, parse_length, parse_length_and_move, EncodedFrames, RespDecode, RespEncode,
+    CRLF, CRLF_LEN,
 };
 
 pub const NULL_BULK_STRING: &[u8] = b"$-1\r\n";
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
-pub struct BulkString(pub(crate) Option<Vec<u8>>);
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BulkString(pub(crate) Option<Bytes>);
 
 /// A bulk string represents a single binary string.
 /// The string can be of any size, but by default,
@@ -36,6 +37,31 @@ impl RespEncode for BulkString {
             }
         }
     }
+
+    fn write_to(self, buf: &mut BytesMut) {
+        match self.0 {
+            None => buf.extend_from_slice(NULL_BULK_STRING),
+            Some(v) => {
+                buf.reserve(v.len() + 16);
+                buf.extend_from_slice(format!("${}\r\n", v.len()).as_bytes());
+                buf.extend_from_slice(&v);
+                buf.extend_from_slice(CRLF);
+            }
+        }
+    }
+
+    fn write_chunks(self, frames: &mut EncodedFrames) {
+        match self.0 {
+            None => frames.buf_mut().extend_from_slice(NULL_BULK_STRING),
+            Some(v) => {
+                frames
+                    .buf_mut()
+                    .extend_from_slice(format!("${}\r\n", v.len()).as_bytes());
+                frames.push_payload(v);
+                frames.buf_mut().extend_from_slice(CRLF);
+            }
+        }
+    }
 }
 
 impl RespDecode for BulkString {
@@ -45,9 +71,11 @@ impl RespDecode for BulkString {
         let length = parse_length_and_move(Self::PREFIX, buf)?;
         if length == -1 {
             return Ok(BulkString::null());
+        } else if length < -1 {
+            return Err(RespError::InvalidFrameLength(length));
         }
         if buf.len() < length as usize + CRLF_LEN {
-            return Err(RespError::NotCompleted);
+            return Err(RespError::NotCompleted(None));
         }
         let content: BytesMut = buf.split_to(length as usize);
         if !buf.starts_with(CRLF) {
@@ -57,13 +85,15 @@ impl RespDecode for BulkString {
             )));
         }
         buf.advance(CRLF_LEN);
-        Ok(BulkString::new(content))
+        Ok(BulkString(Some(content.freeze())))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
         let (end, length) = parse_length(Self::PREFIX, buf)?;
         if length == -1 {
             Ok(NULL_BULK_STRING.len())
+        } else if length < -1 {
+            Err(RespError::InvalidFrameLength(length))
         } else {
             Ok(end + CRLF_LEN + length as usize + CRLF_LEN)
         }
@@ -72,7 +102,7 @@ impl RespDecode for BulkString {
 
 impl BulkString {
     pub fn new(s: impl Into<Vec<u8>>) -> Self {
-        BulkString(Some(s.into()))
+        BulkString(Some(Bytes::from(s.into())))
     }
 
     pub fn null() -> Self {
@@ -81,7 +111,7 @@ impl BulkString {
 }
 
 impl Deref for BulkString {
-    type Target = Option<Vec<u8>>;
+    type Target = Option<Bytes>;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
@@ -89,19 +119,26 @@ impl Deref for BulkString {
 
 impl From<&str> for BulkString {
     fn from(value: &str) -> Self {
-        BulkString(Some(value.as_bytes().to_vec()))
+        BulkString::new(value)
     }
 }
 
 impl From<&[u8]> for BulkString {
     fn from(value: &[u8]) -> Self {
-        BulkString(Some(value.to_vec()))
+        BulkString::new(value)
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for BulkString {
     fn from(value: &[u8; N]) -> Self {
-        BulkString(Some(value.to_vec()))
+        BulkString::new(value)
+    }
+}
+
+/// Wraps a shared value without copying it.
+impl From<Bytes> for BulkString {
+    fn from(value: Bytes) -> Self {
+        BulkString(Some(value))
     }
 }
 
@@ -131,11 +168,20 @@ mod tests {
         assert_eq!(frame.encode(), b"$5\r\nhello\r\n");
     }
 
+    #[test]
+    fn test_bulk_string_write_to() {
+        let mut buf = BytesMut::new();
+        let frame: RespFrame = BulkString::from(Bytes::from_static(b"hello")).into();
+        frame.write_to(&mut buf);
+        BulkString::null().write_to(&mut buf);
+        assert_eq!(&buf[..], b"$5\r\nhello\r\n$-1\r\n");
+    }
+
     #[test]
     fn test_bulk_string_decode() -> anyhow::Result<()> {
         let mut buf = BytesMut::from("$5\r\nhello\r\n");
         let result = BulkString::decode(&mut buf)?;
-        assert_eq!(result.0.unwrap(), b"hello");
+        assert_eq!(result.as_ref(), b"hello");
 
         let mut buf = BytesMut::from("$5\r\nhell\r\n");
         let result = BulkString::decode(&mut buf);
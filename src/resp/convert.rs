@@ -0,0 +1,475 @@
+use bytes::Bytes;
+
+use crate::{err::RespError, BulkString, RespArray, RespFrame, RespNull};
+
+/// Conversion of a value to a frame, e.g. to reply it from a plugin command.
+///
+/// Derive it for structs with `#[derive(ToResp)]`:
+/// - Structs with named fields become a map of the field names to their values,
+///   a field can be renamed with `#[resp(rename = "key")]`.
+/// - Tuple structs become an array, except structs of a single field which become
+///   that field, like serde newtypes.
+/// - Fields marked `#[resp(json)]` are converted through serde and JSON with
+///   [`RespFrame::from_json`], so types implementing `Serialize` need no impl of
+///   their own. Requires the `json` feature.
+pub trait ToResp {
+    fn to_resp(&self) -> RespFrame;
+}
+
+/// Conversion of a frame to a value, e.g. a reply read by an embedder.
+///
+/// Derive it for structs with `#[derive(FromResp)]`, mirroring [`ToResp`]. Maps are
+/// also read from RESP2 arrays of key value pairs, like HGETALL replies. Keys of no
+/// field are ignored, and missing fields are an error unless they are an `Option`.
+pub trait FromResp: Sized {
+    fn from_resp(frame: RespFrame) -> Result<Self, RespError>;
+
+    /// The value of a field missing from a map.
+    #[doc(hidden)]
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+impl ToResp for RespFrame {
+    fn to_resp(&self) -> RespFrame {
+        self.clone()
+    }
+}
+
+impl FromResp for RespFrame {
+    fn from_resp(frame: RespFrame) -> Result<Self, RespError> {
+        Ok(frame)
+    }
+}
+
+impl ToResp for BulkString {
+    fn to_resp(&self) -> RespFrame {
+        self.clone().into()
+    }
+}
+
+impl FromResp for BulkString {
+    fn from_resp(frame: RespFrame) -> Result<Self, RespError> {
+        match frame {
+            RespFrame::BulkString(s) => Ok(s),
+            RespFrame::SimpleString(s) => Ok(BulkString::new(s.0)),
+            frame => Err(unexpected("a string", &frame)),
+        }
+    }
+}
+
+impl ToResp for Bytes {
+    fn to_resp(&self) -> RespFrame {
+        BulkString::from(self.clone()).into()
+    }
+}
+
+impl FromResp for Bytes {
+    fn from_resp(frame: RespFrame) -> Result<Self, RespError> {
+        match BulkString::from_resp(frame)?.0 {
+            Some(data) => Ok(data),
+            None => Err(RespError::InvalidFrameType(
+                "expected a string, got nil".into(),
+            )),
+        }
+    }
+}
+
+impl ToResp for str {
+    fn to_resp(&self) -> RespFrame {
+        BulkString::new(self).into()
+    }
+}
+
+impl ToResp for String {
+    fn to_resp(&self) -> RespFrame {
+        self.as_str().to_resp()
+    }
+}
+
+impl FromResp for String {
+    fn from_resp(frame: RespFrame) -> Result<Self, RespError> {
+        match frame {
+            RespFrame::SimpleString(s) => Ok(s.0),
+            frame => String::from_utf8(Bytes::from_resp(frame)?.into())
+                .map_err(|_| RespError::InvalidFrame("string is not valid UTF-8".into())),
+        }
+    }
+}
+
+/// Integers are also read from strings, as RESP2 replies often hold them.
+macro_rules! impl_integer {
+    ($($ty:ty),*) => {$(
+        impl ToResp for $ty {
+            fn to_resp(&self) -> RespFrame {
+                RespFrame::Integer(*self as i64)
+            }
+        }
+
+        impl FromResp for $ty {
+            fn from_resp(frame: RespFrame) -> Result<Self, RespError> {
+                let i = match frame {
+                    RespFrame::Integer(i) => i,
+                    frame => String::from_resp(frame)?.parse()?,
+                };
+                <$ty>::try_from(i).map_err(|_| {
+                    RespError::InvalidFrame(format!("integer out of range: {}", i))
+                })
+            }
+        }
+    )*};
+}
+
+impl_integer!(i8, i16, i32, i64, u8, u16, u32);
+
+impl ToResp for f64 {
+    fn to_resp(&self) -> RespFrame {
+        RespFrame::Double(*self)
+    }
+}
+
+impl FromResp for f64 {
+    fn from_resp(frame: RespFrame) -> Result<Self, RespError> {
+        match frame {
+            RespFrame::Double(d) => Ok(d),
+            RespFrame::Integer(i) => Ok(i as f64),
+            frame => Ok(String::from_resp(frame)?.parse()?),
+        }
+    }
+}
+
+impl ToResp for bool {
+    fn to_resp(&self) -> RespFrame {
+        RespFrame::Boolean(*self)
+    }
+}
+
+/// Booleans are also read from the integers 0 and 1 of RESP2.
+impl FromResp for bool {
+    fn from_resp(frame: RespFrame) -> Result<Self, RespError> {
+        match frame {
+            RespFrame::Boolean(b) => Ok(b),
+            RespFrame::Integer(0) => Ok(false),
+            RespFrame::Integer(1) => Ok(true),
+            frame => Err(unexpected("a boolean", &frame)),
+        }
+    }
+}
+
+impl<T: ToResp> ToResp for Option<T> {
+    fn to_resp(&self) -> RespFrame {
+        match self {
+            Some(value) => value.to_resp(),
+            None => RespNull.into(),
+        }
+    }
+}
+
+/// Nulls of every kind are read as `None`.
+impl<T: FromResp> FromResp for Option<T> {
+    fn from_resp(frame: RespFrame) -> Result<Self, RespError> {
+        match frame {
+            RespFrame::Null(_)
+            | RespFrame::BulkString(BulkString(None))
+            | RespFrame::Array(RespArray(None)) => Ok(None),
+            frame => T::from_resp(frame).map(Some),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: ToResp> ToResp for [T] {
+    fn to_resp(&self) -> RespFrame {
+        RespArray::new(self.iter().map(T::to_resp).collect::<Vec<_>>()).into()
+    }
+}
+
+impl<T: ToResp> ToResp for Vec<T> {
+    fn to_resp(&self) -> RespFrame {
+        self.as_slice().to_resp()
+    }
+}
+
+/// Vectors are read from arrays, sets and pushes.
+impl<T: FromResp> FromResp for Vec<T> {
+    fn from_resp(frame: RespFrame) -> Result<Self, RespError> {
+        items(frame)?.into_iter().map(T::from_resp).collect()
+    }
+}
+
+impl<T: ToResp + ?Sized> ToResp for &T {
+    fn to_resp(&self) -> RespFrame {
+        (**self).to_resp()
+    }
+}
+
+#[cfg(feature = "json")]
+impl ToResp for serde_json::Value {
+    fn to_resp(&self) -> RespFrame {
+        RespFrame::from_json(self)
+    }
+}
+
+#[cfg(feature = "json")]
+impl FromResp for serde_json::Value {
+    fn from_resp(frame: RespFrame) -> Result<Self, RespError> {
+        Ok(frame.to_json())
+    }
+}
+
+/// The field of a derived [`FromResp`], `None` if it is missing from the map.
+#[doc(hidden)]
+pub fn field<T: FromResp>(frame: Option<RespFrame>, name: &str) -> Result<T, RespError> {
+    match frame {
+        Some(frame) => T::from_resp(frame),
+        None => T::missing().ok_or_else(|| missing(name)),
+    }
+}
+
+/// The entries of a map, or of an array of key value pairs.
+#[doc(hidden)]
+pub fn entries(frame: RespFrame) -> Result<Vec<(RespFrame, RespFrame)>, RespError> {
+    let items = match frame {
+        RespFrame::Map(map) => return Ok(map.into_iter().collect()),
+        frame => items(frame)?,
+    };
+    if items.len() % 2 != 0 {
+        return Err(RespError::InvalidFrameLength(items.len() as isize));
+    }
+    let mut items = items.into_iter();
+    let mut entries = Vec::with_capacity(items.len() / 2);
+    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+        entries.push((key, value));
+    }
+    Ok(entries)
+}
+
+/// The bytes of a map key, to match it with the name of a field.
+#[doc(hidden)]
+pub fn key(frame: &RespFrame) -> Option<&[u8]> {
+    match frame {
+        RespFrame::BulkString(BulkString(Some(data))) => Some(data),
+        RespFrame::SimpleString(s) => Some(s.0.as_bytes()),
+        _ => None,
+    }
+}
+
+/// The items of an array of exactly `len` items, for tuple structs.
+#[doc(hidden)]
+pub fn tuple(frame: RespFrame, len: usize) -> Result<Vec<RespFrame>, RespError> {
+    let items = items(frame)?;
+    if items.len() != len {
+        return Err(RespError::InvalidFrameLength(items.len() as isize));
+    }
+    Ok(items)
+}
+
+/// A field converted through serde, see `#[resp(json)]`. A value which can't be
+/// represented as JSON, e.g. a map with non-string keys, becomes an error frame.
+#[cfg(feature = "json")]
+#[doc(hidden)]
+pub fn json_to_resp<T: serde::Serialize + ?Sized>(value: &T) -> RespFrame {
+    match serde_json::to_value(value) {
+        Ok(value) => RespFrame::from_json(&value),
+        Err(e) => crate::SimpleError::new(format!("ERR {}", e)).into(),
+    }
+}
+
+/// A field read through serde, a missing one is read from `null`.
+#[cfg(feature = "json")]
+#[doc(hidden)]
+pub fn json_field<T: serde::de::DeserializeOwned>(
+    frame: Option<RespFrame>,
+    name: &str,
+) -> Result<T, RespError> {
+    match frame {
+        Some(frame) => serde_json::from_value(frame.to_json())
+            .map_err(|e| RespError::InvalidFrame(format!("field {}: {}", name, e))),
+        None => serde_json::from_value(serde_json::Value::Null).map_err(|_| missing(name)),
+    }
+}
+
+fn items(frame: RespFrame) -> Result<Vec<RespFrame>, RespError> {
+    match frame {
+        RespFrame::Array(RespArray(Some(items))) => Ok(items),
+        RespFrame::Set(set) => Ok(set.into_iter().collect()),
+        RespFrame::Push(push) => Ok(push.into_iter().collect()),
+        frame => Err(unexpected("an array", &frame)),
+    }
+}
+
+fn missing(name: &str) -> RespError {
+    RespError::InvalidFrame(format!("missing field: {}", name))
+}
+
+fn unexpected(expected: &str, frame: &RespFrame) -> RespError {
+    let kind = match frame {
+        RespFrame::SimpleString(_) => "a simple string",
+        RespFrame::Error(_) => "an error",
+        RespFrame::Null(_) | RespFrame::BulkString(BulkString(None)) => "nil",
+        RespFrame::Array(RespArray(None)) => "nil",
+        RespFrame::Integer(_) => "an integer",
+        RespFrame::BulkString(_) => "a bulk string",
+        RespFrame::Array(_) => "an array",
+        RespFrame::Boolean(_) => "a boolean",
+        RespFrame::Double(_) => "a double",
+        RespFrame::Map(_) => "a map",
+        RespFrame::Set(_) => "a set",
+        RespFrame::Push(_) => "a push",
+    };
+    RespError::InvalidFrameType(format!("expected {}, got {}", expected, kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FromResp, RespMap, SimpleString, ToResp};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, ToResp, FromResp)]
+    struct Member {
+        name: String,
+        #[resp(rename = "score")]
+        rank: f64,
+        tags: Vec<String>,
+        note: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, ToResp, FromResp)]
+    struct Pair(i64, bool);
+
+    #[derive(Debug, PartialEq, ToResp, FromResp)]
+    struct Key(String);
+
+    #[derive(Debug, PartialEq, ToResp, FromResp)]
+    struct Wrapper<T> {
+        inner: T,
+    }
+
+    fn member() -> Member {
+        Member {
+            name: "a".to_string(),
+            rank: 1.5,
+            tags: vec!["x".to_string()],
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_derive_to_resp() {
+        let mut map = RespMap::new();
+        map.insert(BulkString::new("name"), b"a".into());
+        map.insert(BulkString::new("score"), 1.5.into());
+        map.insert(
+            BulkString::new("tags"),
+            RespArray::new(vec![b"x".into()]).into(),
+        );
+        map.insert(BulkString::new("note"), RespNull.into());
+        assert_eq!(member().to_resp(), map.into());
+
+        let pair: RespFrame = RespArray::new(vec![1.into(), true.into()]).into();
+        assert_eq!(Pair(1, true).to_resp(), pair);
+        assert_eq!(Key("k".to_string()).to_resp(), b"k".into());
+        let wrapper = Wrapper {
+            inner: Key("k".to_string()),
+        };
+        assert_eq!(Wrapper::from_resp(wrapper.to_resp()), Ok(wrapper));
+    }
+
+    #[test]
+    fn test_derive_from_resp() {
+        assert_eq!(Member::from_resp(member().to_resp()), Ok(member()));
+
+        // a RESP2 reply, with strings for the numbers, in another order.
+        let frame: RespFrame = RespArray::new(vec![
+            SimpleString::new("score").into(),
+            b"1.5".into(),
+            b"unknown".into(),
+            1.into(),
+            b"tags".into(),
+            RespArray::new(vec![b"x".into()]).into(),
+            b"name".into(),
+            b"a".into(),
+            b"note".into(),
+            b"n".into(),
+        ])
+        .into();
+        let expected = Member {
+            note: Some("n".to_string()),
+            ..member()
+        };
+        assert_eq!(Member::from_resp(frame), Ok(expected));
+
+        let frame: RespFrame = RespArray::new(vec![b"1".into(), 0.into()]).into();
+        assert_eq!(Pair::from_resp(frame), Ok(Pair(1, false)));
+        assert_eq!(Key::from_resp(b"k".into()), Ok(Key("k".to_string())));
+    }
+
+    #[test]
+    fn test_derive_from_invalid_frames() {
+        let frame: RespFrame = RespArray::new(vec![b"name".into(), b"a".into()]).into();
+        assert_eq!(
+            Member::from_resp(frame),
+            Err(RespError::InvalidFrame("missing field: score".into()))
+        );
+        let frame: RespFrame = RespArray::new(vec![b"name".into()]).into();
+        assert_eq!(
+            Member::from_resp(frame),
+            Err(RespError::InvalidFrameLength(1))
+        );
+        let frame: RespFrame = RespArray::new(vec![1.into()]).into();
+        assert_eq!(
+            Pair::from_resp(frame),
+            Err(RespError::InvalidFrameLength(1))
+        );
+        assert_eq!(
+            Pair::from_resp(1.into()),
+            Err(RespError::InvalidFrameType(
+                "expected an array, got an integer".into()
+            ))
+        );
+        assert!(u8::from_resp(256.into()).is_err());
+        assert!(String::from_resp(b"\xff".into()).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_derive_json_fields() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, PartialEq, ToResp, FromResp)]
+        struct Document {
+            id: i64,
+            #[resp(json)]
+            attrs: BTreeMap<String, Vec<u32>>,
+            #[resp(json)]
+            parent: Option<i64>,
+        }
+
+        let document = Document {
+            id: 1,
+            attrs: BTreeMap::from([("a".to_string(), vec![1, 2])]),
+            parent: None,
+        };
+        let frame = document.to_resp();
+        let mut map = RespMap::new();
+        map.insert(BulkString::new("id"), 1.into());
+        map.insert(
+            BulkString::new("attrs"),
+            RespFrame::from_json(&serde_json::json!({"a": [1, 2]})),
+        );
+        map.insert(BulkString::new("parent"), RespNull.into());
+        assert_eq!(frame, map.into());
+        assert_eq!(Document::from_resp(frame), Ok(document));
+
+        let frame: RespFrame = RespArray::new(vec![b"id".into(), 1.into()]).into();
+        assert_eq!(
+            Document::from_resp(frame),
+            Err(RespError::InvalidFrame("missing field: attrs".into()))
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt::{self, Display, Formatter, Write};
+
+use crate::{resp_frame::RespFrame, RespArray};
+
+/// Frames are rendered the way redis-cli shows replies:
+///     1) "foo"
+///     2) 1) (integer) 1
+///        2) "\x00\xff"
+///
+/// Bulk strings are quoted with binary data hex-escaped,
+/// nested aggregates are indented under their index.
+impl Display for RespFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RespFrame::SimpleString(s) => f.write_str(&s.0),
+            RespFrame::Error(e) => write!(f, "(error) {}", e.0),
+            RespFrame::Null(_) => f.write_str("(nil)"),
+            RespFrame::Integer(i) => write!(f, "(integer) {}", i),
+            RespFrame::BulkString(s) => match s.0 {
+                Some(ref data) => write_escaped(f, data),
+                None => f.write_str("(nil)"),
+            },
+            RespFrame::Array(RespArray(None)) => f.write_str("(nil)"),
+            RespFrame::Array(array) => write_items(f, array.iter(), ")", "(empty array)"),
+            RespFrame::Boolean(b) => write!(f, "({})", b),
+            RespFrame::Double(d) => write!(f, "(double) {}", d),
+            RespFrame::Map(map) => {
+                if map.is_empty() {
+                    return f.write_str("(empty hash)");
+                }
+                let width = map.len().to_string().len();
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char('\n')?;
+                    }
+                    let prefix = format!("{:>width$}# ", i + 1);
+                    let entry = format!("{} => {}", key, value);
+                    write_indented(f, &prefix, &entry)?;
+                }
+                Ok(())
+            }
+            RespFrame::Set(set) => write_items(f, set.iter(), "~", "(empty set)"),
+            RespFrame::Push(push) => write_items(f, push.iter(), ")", "(empty array)"),
+        }
+    }
+}
+
+fn write_items<'a>(
+    f: &mut Formatter<'_>,
+    items: impl ExactSizeIterator<Item = &'a RespFrame>,
+    marker: &str,
+    empty: &str,
+) -> fmt::Result {
+    if items.len() == 0 {
+        return f.write_str(empty);
+    }
+    let width = items.len().to_string().len();
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            f.write_char('\n')?;
+        }
+        let prefix = format!("{:>width$}{} ", i + 1, marker);
+        write_indented(f, &prefix, &item.to_string())?;
+    }
+    Ok(())
+}
+
+/// Write the first line after the prefix, and align the other lines with it.
+fn write_indented(f: &mut Formatter<'_>, prefix: &str, text: &str) -> fmt::Result {
+    for (i, line) in text.lines().enumerate() {
+        if i == 0 {
+            f.write_str(prefix)?;
+        } else {
+            write!(f, "\n{:width$}", "", width = prefix.len())?;
+        }
+        f.write_str(line)?;
+    }
+    Ok(())
+}
+
+/// Quote the data, escaping anything which is not printable ASCII.
+fn write_escaped(f: &mut Formatter<'_>, data: &[u8]) -> fmt::Result {
+    f.write_char('"')?;
+    for &b in data {
+        match b {
+            b'\\' => f.write_str("\\\\")?,
+            b'"' => f.write_str("\\\"")?,
+            b'\n' => f.write_str("\\n")?,
+            b'\r' => f.write_str("\\r")?,
+            b'\t' => f.write_str("\\t")?,
+            0x07 => f.write_str("\\a")?,
+            0x08 => f.write_str("\\b")?,
+            b if b.is_ascii_graphic() || b == b' ' => f.write_char(b as char)?,
+            b => write!(f, "\\x{:02x}", b)?,
+        }
+    }
+    f.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, RespMap, RespNull, RespSet, SimpleError, SimpleString};
+
+    use super::*;
+
+    #[test]
+    fn test_display_scalars() {
+        assert_eq!(RespFrame::from(SimpleString::new("OK")).to_string(), "OK");
+        assert_eq!(
+            RespFrame::from(SimpleError::new("ERR oops")).to_string(),
+            "(error) ERR oops"
+        );
+        assert_eq!(RespFrame::from(RespNull).to_string(), "(nil)");
+        assert_eq!(RespFrame::from(BulkString::null()).to_string(), "(nil)");
+        assert_eq!(RespFrame::from(RespArray::null()).to_string(), "(nil)");
+        assert_eq!(RespFrame::Integer(-3).to_string(), "(integer) -3");
+        assert_eq!(RespFrame::Boolean(true).to_string(), "(true)");
+        assert_eq!(RespFrame::Double(1.5).to_string(), "(double) 1.5");
+    }
+
+    #[test]
+    fn test_display_escapes_binary_data() {
+        let frame: RespFrame = b"a \"b\"\r\n\x00\xff".into();
+        assert_eq!(frame.to_string(), r#""a \"b\"\r\n\x00\xff""#);
+    }
+
+    #[test]
+    fn test_display_nested_aggregates() {
+        let inner = RespArray::new(vec![1.into(), b"x".into()]);
+        let frame: RespFrame = RespArray::new(vec![b"foo".into(), inner.into()]).into();
+        assert_eq!(
+            frame.to_string(),
+            "1) \"foo\"\n2) 1) (integer) 1\n   2) \"x\""
+        );
+
+        let frame: RespFrame =
+            RespArray::new((0..10).map(RespFrame::from).collect::<Vec<_>>()).into();
+        let rendered = frame.to_string();
+        assert!(rendered.starts_with(" 1) (integer) 0\n"));
+        assert!(rendered.ends_with("\n10) (integer) 9"));
+
+        let mut map = RespMap::new();
+        map.insert(
+            BulkString::new("k"),
+            RespArray::new(vec![1.into(), 2.into()]).into(),
+        );
+        assert_eq!(
+            RespFrame::from(map).to_string(),
+            "1# \"k\" => 1) (integer) 1\n   2) (integer) 2"
+        );
+
+        let set = RespSet::new(vec![b"a".into()]);
+        assert_eq!(RespFrame::from(set).to_string(), "1~ \"a\"");
+        assert_eq!(
+            RespFrame::from(RespArray::new(vec![])).to_string(),
+            "(empty array)"
+        );
+        assert_eq!(RespFrame::from(RespMap::new()).to_string(), "(empty hash)");
+    }
+}
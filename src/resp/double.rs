@@ -11,8 +11,8 @@ use crate::{err::RespError, extract_simple_frame_data, RespDecode, RespEncode, C
 /// - One or more decimal digits (0..9) as an unsigned, base-10 integral value.
 /// - An optional dot (.), followed by one or more decimal digits (0..9) as an unsigned, base-10 fractional value.
 /// - An optional capital or lowercase letter E (E or e),
-///     followed by an optional plus (+) or minus (-) as the exponent's sign,
-///     ending with one or more decimal digits (0..9) as an unsigned, base-10 exponent value.
+///   followed by an optional plus (+) or minus (-) as the exponent's sign,
+///   ending with one or more decimal digits (0..9) as an unsigned, base-10 exponent value.
 /// - The CRLF terminator.
 ///
 /// Example:
@@ -8,8 +8,10 @@ pub enum RespError {
     InvalidFrameType(String),
     #[error("Invalid frame length: {0}")]
     InvalidFrameLength(isize),
+    /// More data is needed to decode the frame,
+    /// with at least how many more bytes when it is known.
     #[error("Frame is not completed")]
-    NotCompleted,
+    NotCompleted(Option<usize>),
     #[error("Parse int error: {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
     #[error("Parse float error: {0}")]
@@ -1,7 +1,13 @@
+use std::fmt::Write;
+
 use bytes::BytesMut;
 
 use crate::{err::RespError, extract_simple_frame_data, RespDecode, RespEncode, CRLF_LEN};
 
+/// The encodings of the most frequent integer replies, e.g. of EXISTS or SADD.
+pub const INTEGER_ZERO: &[u8] = b":0\r\n";
+pub const INTEGER_ONE: &[u8] = b":1\r\n";
+
 /// This type is a CRLF-terminated string that represents a signed, base-10, 64-bit integer.
 ///
 /// Format:
@@ -13,7 +19,21 @@ use crate::{err::RespError, extract_simple_frame_data, RespDecode, RespEncode, C
 /// - The CRLF terminator.
 impl RespEncode for i64 {
     fn encode(self) -> Vec<u8> {
-        format!(":{}\r\n", self).into_bytes()
+        match self {
+            0 => INTEGER_ZERO.to_vec(),
+            1 => INTEGER_ONE.to_vec(),
+            _ => format!(":{}\r\n", self).into_bytes(),
+        }
+    }
+
+    fn write_to(self, buf: &mut BytesMut) {
+        match self {
+            0 => buf.extend_from_slice(INTEGER_ZERO),
+            1 => buf.extend_from_slice(INTEGER_ONE),
+            _ => {
+                let _ = write!(buf, ":{}\r\n", self);
+            }
+        }
     }
 }
 
@@ -51,6 +71,15 @@ mod tests {
         assert_eq!(frame.encode(), b":123\r\n");
     }
 
+    #[test]
+    fn test_integer_write_to() {
+        let mut buf = BytesMut::new();
+        for i in [0, 1, -1, i64::MIN] {
+            RespFrame::from(i).write_to(&mut buf);
+        }
+        assert_eq!(buf, ":0\r\n:1\r\n:-1\r\n:-9223372036854775808\r\n");
+    }
+
     #[test]
     fn test_integer_decode() -> anyhow::Result<()> {
         let mut buf = BytesMut::from(":10\r\n");
@@ -67,7 +96,7 @@ mod tests {
 
         buf.extend_from_slice(b":100\r");
         let result = i64::decode(&mut buf);
-        assert_eq!(result.unwrap_err(), RespError::NotCompleted);
+        assert_eq!(result.unwrap_err(), RespError::NotCompleted(None));
 
         buf.put_u8(b'\n');
         let result = i64::decode(&mut buf)?;
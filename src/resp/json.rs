@@ -0,0 +1,151 @@
+use serde_json::{Map, Number, Value};
+
+use crate::{resp_frame::RespFrame, BulkString, RespArray, RespMap, RespNull, SimpleError};
+
+const ERROR_KEY: &str = "error";
+
+/// Conversion between frames and JSON values.
+///
+/// The conversion is lossy, JSON has fewer types than RESP:
+/// - Bulk strings which are not valid UTF-8 have their invalid bytes replaced by U+FFFD.
+/// - Simple strings and bulk strings both become strings, and are read back as bulk strings.
+/// - Nulls of every kind become `null`, read back as a RESP3 null.
+/// - Sets and pushes become arrays.
+/// - Errors become `{"error": "<message>"}`, and such an object is read back as an error.
+/// - Doubles which are not finite become the strings `"inf"`, `"-inf"` and `"nan"`.
+/// - Map keys which are not strings become their JSON text, e.g. `1` becomes `"1"`.
+impl RespFrame {
+    pub fn to_json(&self) -> Value {
+        match self {
+            RespFrame::SimpleString(s) => Value::String(s.0.clone()),
+            RespFrame::Error(e) => {
+                let mut obj = Map::new();
+                obj.insert(ERROR_KEY.to_string(), Value::String(e.0.clone()));
+                Value::Object(obj)
+            }
+            RespFrame::Null(_) => Value::Null,
+            RespFrame::Integer(i) => Value::Number((*i).into()),
+            RespFrame::BulkString(s) => match s.0 {
+                Some(ref data) => Value::String(String::from_utf8_lossy(data).into_owned()),
+                None => Value::Null,
+            },
+            RespFrame::Array(RespArray(None)) => Value::Null,
+            RespFrame::Array(array) => array_to_json(array.iter()),
+            RespFrame::Boolean(b) => Value::Bool(*b),
+            RespFrame::Double(d) => match Number::from_f64(*d) {
+                Some(n) => Value::Number(n),
+                None => Value::String(d.to_string().to_lowercase()),
+            },
+            RespFrame::Map(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| (json_key(key), value.to_json()))
+                    .collect(),
+            ),
+            RespFrame::Set(set) => array_to_json(set.iter()),
+            RespFrame::Push(push) => array_to_json(push.iter()),
+        }
+    }
+
+    pub fn from_json(value: &Value) -> RespFrame {
+        match value {
+            Value::Null => RespNull.into(),
+            Value::Bool(b) => (*b).into(),
+            Value::Number(n) => match n.as_i64() {
+                Some(i) => i.into(),
+                // integers out of the i64 range are kept as doubles.
+                None => n.as_f64().unwrap_or(f64::NAN).into(),
+            },
+            Value::String(s) => BulkString::new(s.as_str()).into(),
+            Value::Array(values) => {
+                RespArray::new(values.iter().map(RespFrame::from_json).collect::<Vec<_>>()).into()
+            }
+            Value::Object(obj) => match (obj.len(), obj.get(ERROR_KEY)) {
+                (1, Some(Value::String(message))) => SimpleError::new(message.as_str()).into(),
+                _ => {
+                    let mut map = RespMap::new();
+                    for (key, value) in obj {
+                        map.insert(BulkString::new(key.as_str()), RespFrame::from_json(value));
+                    }
+                    map.into()
+                }
+            },
+        }
+    }
+}
+
+fn array_to_json<'a>(items: impl Iterator<Item = &'a RespFrame>) -> Value {
+    Value::Array(items.map(RespFrame::to_json).collect())
+}
+
+fn json_key(key: &RespFrame) -> String {
+    match key.to_json() {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{RespSet, SimpleString};
+
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let mut map = RespMap::new();
+        map.insert(BulkString::new("name"), SimpleString::new("r-redis").into());
+        map.insert(
+            RespFrame::Integer(1),
+            RespArray::new(vec![1.into(), 1.5.into()]).into(),
+        );
+        map.insert(BulkString::new("null"), BulkString::null().into());
+        map.insert(
+            BulkString::new("set"),
+            RespSet::new(vec![true.into()]).into(),
+        );
+        let frame: RespFrame = map.into();
+        assert_eq!(
+            frame.to_json(),
+            json!({"name": "r-redis", "1": [1, 1.5], "null": null, "set": [true]})
+        );
+
+        let frame: RespFrame = SimpleError::new("ERR oops").into();
+        assert_eq!(frame.to_json(), json!({"error": "ERR oops"}));
+        assert_eq!(
+            RespFrame::Double(f64::NEG_INFINITY).to_json(),
+            json!("-inf")
+        );
+    }
+
+    #[test]
+    fn test_to_json_is_lossy_for_binary_data() {
+        let frame: RespFrame = b"a\xffb".into();
+        assert_eq!(frame.to_json(), json!("a\u{fffd}b"));
+    }
+
+    #[test]
+    fn test_from_json() {
+        let value = json!({"k": [1, 2.5, "v", null, false], "error": "not alone"});
+        // object keys are sorted by serde_json.
+        let mut map = RespMap::new();
+        map.insert(BulkString::new("error"), b"not alone".into());
+        map.insert(
+            BulkString::new("k"),
+            RespArray::new(vec![
+                1.into(),
+                2.5.into(),
+                b"v".into(),
+                RespNull.into(),
+                false.into(),
+            ])
+            .into(),
+        );
+        assert_eq!(RespFrame::from_json(&value), map.into());
+
+        let frame = RespFrame::from_json(&json!({"error": "ERR oops"}));
+        assert_eq!(frame, SimpleError::new("ERR oops").into());
+        assert_eq!(RespFrame::from_json(&frame.to_json()), frame);
+    }
+}
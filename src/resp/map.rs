@@ -1,17 +1,23 @@
 use std::{
-    collections::BTreeMap,
-    ops::{Deref, DerefMut},
+    cmp::Ordering,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::Deref,
 };
 
 use bytes::BytesMut;
+use indexmap::IndexMap;
 
 use crate::{
     cal_total_length, err::RespError, parse_length, parse_length_and_move, resp_frame::RespFrame,
-    simple_string::SimpleString, RespDecode, RespEncode, BUF_CAP,
+    EncodedFrames, RespDecode, RespEncode, BUF_CAP,
 };
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
-pub struct RespMap(BTreeMap<String, RespFrame>);
+/// Keys can be any frame, as allowed by RESP3.
+/// Entries are kept in insertion order, which is also the encoding order.
+/// Equality, ordering and hashing ignore the order of the entries.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RespMap(IndexMap<RespFrame, RespFrame>);
 
 /// The RESP map encodes a collection of key-value tuples, i.e., a dictionary or a hash.
 /// Format:
@@ -39,11 +45,20 @@ impl RespEncode for RespMap {
         let mut buf = Vec::with_capacity(BUF_CAP);
         buf.extend_from_slice(&format!("%{}\r\n", self.len()).into_bytes());
         for (key, value) in self.0 {
-            buf.extend(SimpleString::new(key).encode());
-            buf.extend(&value.encode());
+            buf.extend(key.encode());
+            buf.extend(value.encode());
         }
         buf
     }
+
+    fn write_chunks(self, frames: &mut EncodedFrames) {
+        let header = format!("%{}\r\n", self.len());
+        frames.buf_mut().extend_from_slice(header.as_bytes());
+        for (key, value) in self.0 {
+            key.write_chunks(frames);
+            value.write_chunks(frames);
+        }
+    }
 }
 
 impl RespDecode for RespMap {
@@ -51,14 +66,14 @@ impl RespDecode for RespMap {
 
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         if buf.len() < Self::expect_length(buf)? {
-            return Err(RespError::NotCompleted);
+            return Err(RespError::NotCompleted(None));
         }
         let length = parse_length_and_move(Self::PREFIX, buf)?;
         let mut map = RespMap::new();
         for _ in 0..length {
-            let key = SimpleString::decode(buf)?;
+            let key = RespFrame::decode(buf)?;
             let value = RespFrame::decode(buf)?;
-            map.insert(key.0, value);
+            map.insert(key, value);
         }
         Ok(map)
     }
@@ -71,48 +86,100 @@ impl RespDecode for RespMap {
 
 impl RespMap {
     pub fn new() -> Self {
-        RespMap(BTreeMap::new())
+        RespMap(IndexMap::new())
+    }
+
+    /// Insert an entry, replacing the value of an equal key in place.
+    /// Returns the replaced value.
+    pub fn insert(&mut self, key: impl Into<RespFrame>, value: RespFrame) -> Option<RespFrame> {
+        self.0.insert(key.into(), value)
+    }
+
+    pub fn get(&self, key: &RespFrame) -> Option<&RespFrame> {
+        self.0.get(key)
+    }
+
+    fn sorted(&self) -> Vec<(&RespFrame, &RespFrame)> {
+        let mut entries: Vec<_> = self.0.iter().collect();
+        entries.sort();
+        entries
     }
 }
 
-impl Default for RespMap {
-    fn default() -> Self {
-        RespMap::new()
+impl PartialOrd for RespMap {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RespMap {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sorted().cmp(&other.sorted())
+    }
+}
+
+impl Hash for RespMap {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // like sets, equal maps hash the same whatever the order of their entries.
+        let combined = self.0.iter().fold(0u64, |acc, entry| {
+            let mut hasher = DefaultHasher::new();
+            entry.hash(&mut hasher);
+            acc.wrapping_add(hasher.finish())
+        });
+        self.0.len().hash(state);
+        combined.hash(state);
     }
 }
 
 impl Deref for RespMap {
-    type Target = BTreeMap<String, RespFrame>;
+    type Target = IndexMap<RespFrame, RespFrame>;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl DerefMut for RespMap {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl<K: Into<RespFrame>> FromIterator<(K, RespFrame)> for RespMap {
+    fn from_iter<T: IntoIterator<Item = (K, RespFrame)>>(iter: T) -> Self {
+        let mut map = RespMap::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
     }
 }
 
-impl From<BTreeMap<String, RespFrame>> for RespMap {
-    fn from(value: BTreeMap<String, RespFrame>) -> Self {
-        RespMap(value)
+impl IntoIterator for RespMap {
+    type Item = (RespFrame, RespFrame);
+    type IntoIter = indexmap::map::IntoIter<RespFrame, RespFrame>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::{BulkString, SimpleString};
+
     use super::*;
 
     #[test]
     fn test_map_encode() {
         let mut map = RespMap::new();
-        map.insert("first".to_string(), 1.into());
-        map.insert("second".to_string(), 2.into());
+        map.insert(SimpleString::new("first"), 1.into());
+        map.insert(SimpleString::new("second"), 2.into());
         let frame: RespFrame = map.into();
         assert_eq!(frame.encode(), b"%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n");
     }
 
+    #[test]
+    fn test_map_encode_keeps_insertion_order() {
+        let mut map = RespMap::new();
+        map.insert(SimpleString::new("second"), 2.into());
+        map.insert(SimpleString::new("first"), 1.into());
+        let frame: RespFrame = map.into();
+        assert_eq!(frame.encode(), b"%2\r\n+second\r\n:2\r\n+first\r\n:1\r\n");
+    }
+
     #[test]
     fn test_map_decode() -> anyhow::Result<()> {
         // empty map
@@ -124,37 +191,87 @@ mod tests {
         let mut buf = BytesMut::from("%1\r\n+foo\r\n+bar\r\n");
         let result = RespMap::decode(&mut buf)?;
         let mut expected = RespMap::new();
-        expected.insert("foo".to_string(), SimpleString::new("bar").into());
+        expected.insert(SimpleString::new("foo"), SimpleString::new("bar").into());
         assert_eq!(result, expected);
 
         // many items in one type
         let mut buf = BytesMut::from("%2\r\n+foo\r\n+bar\r\n+baz\r\n+qux\r\n");
         let result = RespMap::decode(&mut buf)?;
         let mut expected = RespMap::new();
-        expected.insert("foo".to_string(), SimpleString::new("bar").into());
-        expected.insert("baz".to_string(), SimpleString::new("qux").into());
+        expected.insert(SimpleString::new("foo"), SimpleString::new("bar").into());
+        expected.insert(SimpleString::new("baz"), SimpleString::new("qux").into());
         assert_eq!(result, expected);
 
         // many items in different types
         let mut buf = BytesMut::from("%2\r\n+foo\r\n+bar\r\n+baz\r\n:2\r\n");
         let result = RespMap::decode(&mut buf)?;
         let mut expected = RespMap::new();
-        expected.insert("foo".to_string(), SimpleString::new("bar").into());
-        expected.insert("baz".to_string(), (2).into());
+        expected.insert(SimpleString::new("foo"), SimpleString::new("bar").into());
+        expected.insert(SimpleString::new("baz"), (2).into());
         assert_eq!(result, expected);
 
         // not completed
         let mut buf = BytesMut::from("%2\r\n+foo\r\n+bar\r\n");
         let result = RespMap::decode(&mut buf);
-        assert_eq!(result.unwrap_err(), RespError::NotCompleted);
+        assert_eq!(result.unwrap_err(), RespError::NotCompleted(None));
 
         // add bytes to buf to make it completed
         buf.extend_from_slice(b"+baz\r\n+qux\r\n");
         let result = RespMap::decode(&mut buf)?;
         let mut expected = RespMap::new();
-        expected.insert("foo".to_string(), SimpleString::new("bar").into());
-        expected.insert("baz".to_string(), SimpleString::new("qux").into());
+        expected.insert(SimpleString::new("foo"), SimpleString::new("bar").into());
+        expected.insert(SimpleString::new("baz"), SimpleString::new("qux").into());
         assert_eq!(result, expected);
         Ok(())
     }
+
+    #[test]
+    fn test_map_with_frame_keys() -> anyhow::Result<()> {
+        let mut buf = BytesMut::from("%3\r\n$3\r\nfoo\r\n:1\r\n:2\r\n#t\r\n+foo\r\n,1.5\r\n");
+        let map = RespMap::decode(&mut buf)?;
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&BulkString::new("foo").into()), Some(&1.into()));
+        assert_eq!(map.get(&2.into()), Some(&true.into()));
+        assert_eq!(map.get(&SimpleString::new("foo").into()), Some(&1.5.into()));
+
+        // encoding keeps the key types and the order.
+        let frame: RespFrame = map.into();
+        assert_eq!(
+            frame.encode(),
+            b"%3\r\n$3\r\nfoo\r\n:1\r\n:2\r\n#t\r\n+foo\r\n,+1.5\r\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_insert_replaces_equal_key() {
+        let mut map = RespMap::new();
+        assert_eq!(map.insert(SimpleString::new("a"), 1.into()), None);
+        map.insert(SimpleString::new("b"), 2.into());
+        assert_eq!(map.insert(SimpleString::new("a"), 3.into()), Some(1.into()));
+        let keys: Vec<_> = map.keys().cloned().collect();
+        assert_eq!(
+            keys,
+            vec![SimpleString::new("a").into(), SimpleString::new("b").into()]
+        );
+        assert_eq!(map.get(&SimpleString::new("a").into()), Some(&3.into()));
+    }
+
+    #[test]
+    fn test_map_eq_ignores_order() {
+        use std::collections::HashSet;
+
+        let a: RespMap = (0..1000)
+            .map(|i| (BulkString::new(i.to_string()), i.into()))
+            .collect();
+        let b: RespMap = (0..1000)
+            .rev()
+            .map(|i| (BulkString::new(i.to_string()), i.into()))
+            .collect();
+        assert_eq!(a.len(), 1000);
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        let frames: HashSet<RespFrame> = [a.into(), b.into()].into_iter().collect();
+        assert_eq!(frames.len(), 1);
+    }
 }
@@ -3,27 +3,58 @@ use enum_dispatch::enum_dispatch;
 
 use self::err::RespError;
 
+pub use rredis_derive::{FromResp, ToResp};
+
 pub use self::{
-    array::RespArray, bulk_string::BulkString, map::RespMap, null::RespNull, resp_frame::RespFrame,
-    set::RespSet, simple_error::SimpleError, simple_string::SimpleString,
+    array::RespArray,
+    attribute::RespAttribute,
+    bulk_string::BulkString,
+    convert::{FromResp, ToResp},
+    map::RespMap,
+    null::RespNull,
+    push::RespPush,
+    resp_frame::RespFrame,
+    set::RespSet,
+    simple_error::SimpleError,
+    simple_string::SimpleString,
+    vectored::EncodedFrames,
 };
 
 pub mod array;
+pub mod attribute;
 pub mod boolean;
 pub mod bulk_string;
+pub mod convert;
+mod display;
 pub mod double;
 pub mod err;
 pub mod integer;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod map;
 pub mod null;
+pub mod push;
 pub mod resp_frame;
 pub mod set;
 pub mod simple_error;
 pub mod simple_string;
+pub mod vectored;
 
 #[enum_dispatch]
-pub trait RespEncode {
+pub trait RespEncode: Sized {
     fn encode(self) -> Vec<u8>;
+
+    /// Append the encoded frame to `buf`, which frames holding large payloads
+    /// override to write them in place rather than through an intermediate buffer.
+    fn write_to(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(&self.encode());
+    }
+
+    /// Append the encoded frame to `frames`, which frames holding large payloads
+    /// override to reference them rather than copy them.
+    fn write_chunks(self, frames: &mut EncodedFrames) {
+        self.write_to(frames.buf_mut());
+    }
 }
 
 pub trait RespDecode: Sized {
@@ -35,6 +66,9 @@ pub trait RespDecode: Sized {
 pub const BUF_CAP: usize = 4096;
 pub const CRLF: &[u8] = b"\r\n";
 pub const CRLF_LEN: usize = CRLF.len();
+/// How deeply aggregate frames may nest, a deeper frame is refused
+/// rather than decoded recursively until the stack overflows.
+pub const MAX_NESTING_DEPTH: usize = 128;
 
 pub fn extract_fixed_data(
     buf: &mut BytesMut,
@@ -42,7 +76,7 @@ pub fn extract_fixed_data(
     expect_type: &str,
 ) -> Result<(), RespError> {
     if buf.len() < expect.len() {
-        return Err(RespError::NotCompleted);
+        return Err(RespError::NotCompleted(None));
     }
     if !buf.starts_with(expect) {
         return Err(RespError::InvalidFrameType(format!(
@@ -56,8 +90,8 @@ pub fn extract_fixed_data(
 }
 
 pub fn extract_simple_frame_data(buf: &[u8], prefix: &str) -> Result<usize, RespError> {
-    if buf.len() <= 3 {
-        return Err(RespError::NotCompleted);
+    if buf.len() < 3 {
+        return Err(RespError::NotCompleted(None));
     }
 
     if !buf.starts_with(prefix.as_bytes()) {
@@ -70,7 +104,7 @@ pub fn extract_simple_frame_data(buf: &[u8], prefix: &str) -> Result<usize, Resp
     if let Some(end) = find_crlf(buf, 1) {
         Ok(end)
     } else {
-        Err(RespError::NotCompleted)
+        Err(RespError::NotCompleted(None))
     }
 }
 
@@ -105,32 +139,53 @@ pub fn cal_total_length(
     end: usize,
     len: usize,
     prefix: &str,
+) -> Result<usize, RespError> {
+    cal_nested_length(buf, end, len, prefix, 0)
+}
+
+/// The length of an aggregate frame nested in `depth` other aggregates.
+fn cal_nested_length(
+    buf: &[u8],
+    end: usize,
+    len: usize,
+    prefix: &str,
+    depth: usize,
 ) -> Result<usize, RespError> {
     let mut total: usize = end + CRLF_LEN;
     let mut data = &buf[total..];
-    match prefix {
-        "*" | "~" => {
-            for _ in 0..len {
-                let item_len = RespFrame::expect_length(data)?;
-                data = &data[item_len..];
-                total += item_len;
-            }
-            Ok(total)
-        }
-        "%" => {
-            for _ in 0..len {
-                let key_len = SimpleString::expect_length(data)?;
-                data = &data[key_len..];
-                total += key_len;
-
-                let value_len = RespFrame::expect_length(data)?;
-                data = &data[value_len..];
-                total += value_len;
-            }
-            Ok(total)
-        }
-        _ => Ok(len + CRLF_LEN),
+    let items = match prefix {
+        "*" | "~" | ">" => len,
+        "%" | "|" => len.saturating_mul(2),
+        _ => return Ok(len + CRLF_LEN),
+    };
+    for _ in 0..items {
+        let item_len = nested_frame_length(data, depth + 1)?;
+        // a bulk string may be longer than what was received of it.
+        data = data.get(item_len..).ok_or(RespError::NotCompleted(None))?;
+        total += item_len;
+    }
+    Ok(total)
+}
+
+fn nested_frame_length(buf: &[u8], depth: usize) -> Result<usize, RespError> {
+    let prefix = match buf.first() {
+        Some(b'*') => RespArray::PREFIX,
+        Some(b'~') => RespSet::PREFIX,
+        Some(b'>') => RespPush::PREFIX,
+        Some(b'%') => RespMap::PREFIX,
+        _ => return RespFrame::expect_length(buf),
+    };
+    if depth >= MAX_NESTING_DEPTH {
+        return Err(RespError::InvalidFrame(format!(
+            "aggregate frames nested deeper than {} levels",
+            MAX_NESTING_DEPTH
+        )));
+    }
+    let (end, len) = parse_length(prefix, buf)?;
+    if prefix == RespArray::PREFIX && len == -1 {
+        return Ok(array::NULL_ARRAY.len());
     }
+    cal_nested_length(buf, end, len as usize, prefix, depth)
 }
 
 #[cfg(test)]
@@ -150,4 +205,27 @@ mod tests {
         assert_eq!(find_crlf(buf, 2), Some(6));
         assert_eq!(find_crlf(buf, 3), Some(11));
     }
+
+    #[test]
+    fn test_nesting_beyond_the_limit() {
+        let mut buf = b"*1\r\n".repeat(MAX_NESTING_DEPTH);
+        buf.extend_from_slice(b":1\r\n");
+        assert_eq!(RespFrame::expect_length(&buf), Ok(buf.len()));
+
+        let buf = b"*1\r\n".repeat(100_000);
+        assert!(matches!(
+            RespFrame::expect_length(&buf),
+            Err(RespError::InvalidFrame(_))
+        ));
+        assert!(RespFrame::decode(&mut BytesMut::from(&buf[..])).is_err());
+    }
+
+    #[test]
+    fn test_truncated_bulk_string_in_array() {
+        let buf = b"*2\r\n$5\r\nab\r\n";
+        assert_eq!(
+            RespFrame::expect_length(buf),
+            Err(RespError::NotCompleted(None))
+        );
+    }
 }
@@ -4,7 +4,7 @@ use crate::{err::RespError, extract_fixed_data, RespDecode, RespEncode};
 
 pub const NULL: &[u8] = b"_\r\n";
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RespNull;
 
 impl RespDecode for RespNull {
@@ -47,7 +47,7 @@ mod tests {
         // not completed case
         buf.extend_from_slice(b"_\r");
         let result = RespNull::decode(&mut buf);
-        assert_eq!(result.unwrap_err(), RespError::NotCompleted);
+        assert_eq!(result.unwrap_err(), RespError::NotCompleted(None));
 
         // put \n to complete the string.
         buf.put_u8(b'\n');
@@ -0,0 +1,119 @@
+use std::ops::Deref;
+
+use bytes::BytesMut;
+
+use crate::{
+    cal_total_length, err::RespError, parse_length, parse_length_and_move, resp_frame::RespFrame,
+    EncodedFrames, RespDecode, RespEncode, BUF_CAP,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RespPush(Vec<RespFrame>);
+
+/// Pushes are out-of-band data sent by the server, like pub/sub messages
+/// or client-side caching invalidations.
+/// They are encoded like arrays but with a different first byte, so clients
+/// can tell them apart from replies to their commands.
+/// Format:
+///     ><number-of-elements>\r\n<element-1>...<element-n>
+///
+/// - A greater-than sign (>) as the first byte.
+/// - One or more decimal digits (0..9) as the number of elements in the message as an unsigned, base-10 value.
+/// - The CRLF terminator.
+/// - An additional RESP type for every element of the push data.
+impl RespEncode for RespPush {
+    fn encode(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(BUF_CAP);
+        buf.extend_from_slice(&format!(">{}\r\n", self.len()).into_bytes());
+        for frame in self.0 {
+            buf.extend_from_slice(&frame.encode());
+        }
+        buf
+    }
+
+    fn write_chunks(self, frames: &mut EncodedFrames) {
+        let header = format!(">{}\r\n", self.len());
+        frames.buf_mut().extend_from_slice(header.as_bytes());
+        for frame in self.0 {
+            frame.write_chunks(frames);
+        }
+    }
+}
+
+impl RespDecode for RespPush {
+    const PREFIX: &'static str = ">";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        if buf.len() < Self::expect_length(buf)? {
+            return Err(RespError::NotCompleted(None));
+        }
+        let length = parse_length_and_move(Self::PREFIX, buf)?;
+        let mut data = Vec::with_capacity(length as usize);
+        for _ in 0..length {
+            data.push(RespFrame::decode(buf)?);
+        }
+        Ok(RespPush::new(data))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(Self::PREFIX, buf)?;
+        cal_total_length(buf, end, len as usize, Self::PREFIX)
+    }
+}
+
+impl RespPush {
+    pub fn new(s: impl Into<Vec<RespFrame>>) -> Self {
+        RespPush(s.into())
+    }
+}
+
+impl Deref for RespPush {
+    type Target = Vec<RespFrame>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl IntoIterator for RespPush {
+    type Item = RespFrame;
+    type IntoIter = std::vec::IntoIter<RespFrame>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, RespArray};
+
+    use super::*;
+
+    #[test]
+    fn test_push_encode() {
+        let push = RespPush::new(vec![
+            BulkString::new("invalidate").into(),
+            RespArray::new(vec![BulkString::new("foo").into()]).into(),
+        ]);
+        let frame: RespFrame = push.into();
+        assert_eq!(
+            frame.encode(),
+            b">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nfoo\r\n"
+        );
+    }
+
+    #[test]
+    fn test_push_decode() -> anyhow::Result<()> {
+        let mut buf = BytesMut::from(">2\r\n+message\r\n:1\r\n");
+        let result = RespPush::decode(&mut buf)?;
+        assert_eq!(
+            result,
+            RespPush::new(vec![crate::SimpleString::new("message").into(), (1).into()])
+        );
+
+        // not completed
+        let mut buf = BytesMut::from(">2\r\n+message\r\n");
+        let result = RespPush::decode(&mut buf);
+        assert_eq!(result.unwrap_err(), RespError::NotCompleted(None));
+        Ok(())
+    }
+}
@@ -1,17 +1,27 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
 use bytes::BytesMut;
 use enum_dispatch::enum_dispatch;
 
 use crate::{
-    array::RespArray, bulk_string::BulkString, err::RespError, null::RespNull, set::RespSet,
-    simple_error::SimpleError, simple_string::SimpleString, RespDecode,
+    array::RespArray, bulk_string::BulkString, err::RespError, null::RespNull, push::RespPush,
+    set::RespSet, simple_error::SimpleError, simple_string::SimpleString, RespDecode,
 };
 
 use super::map::RespMap;
 
 /// RESP(Redis serialization protocol specification).
 /// According to https://redis.io/docs/latest/develop/reference/protocol-spec/.
+///
+/// Frames are totally ordered and hashable so they can be used as keys.
+/// Doubles follow the `ordered-float` semantics: all NaNs are equal and
+/// greater than any other number, and `-0.0` equals `0.0`.
+/// Frames of different types are ordered by type.
 #[enum_dispatch(RespEncode)]
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone)]
 pub enum RespFrame {
     SimpleString(SimpleString),
     Error(SimpleError),
@@ -23,13 +33,14 @@ pub enum RespFrame {
     Double(f64),
     Map(RespMap),
     Set(RespSet),
+    Push(RespPush),
 }
 
 impl RespDecode for RespFrame {
     const PREFIX: &'static str = "";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         if buf.len() < 3 {
-            return Err(RespError::NotCompleted);
+            return Err(RespError::NotCompleted(None));
         }
         let first = buf[0];
         let res: RespFrame = match first {
@@ -43,6 +54,7 @@ impl RespDecode for RespFrame {
             b'*' => RespArray::decode(buf)?.into(),
             b'%' => RespMap::decode(buf)?.into(),
             b'~' => RespSet::decode(buf)?.into(),
+            b'>' => RespPush::decode(buf)?.into(),
             _ => {
                 return Err(RespError::InvalidFrameType(format!(
                     "unknown type: {}",
@@ -58,6 +70,7 @@ impl RespDecode for RespFrame {
         match iter.peek() {
             Some(b'*') => RespArray::expect_length(buf),
             Some(b'~') => RespSet::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
             Some(b'%') => RespMap::expect_length(buf),
             Some(b'#') => bool::expect_length(buf),
             Some(b':') => i64::expect_length(buf),
@@ -66,11 +79,133 @@ impl RespDecode for RespFrame {
             Some(b'-') => SimpleError::expect_length(buf),
             Some(b'_') => RespNull::expect_length(buf),
             Some(b'$') => BulkString::expect_length(buf),
-            _ => Err(RespError::NotCompleted),
+            _ => Err(RespError::NotCompleted(None)),
+        }
+    }
+}
+
+impl RespFrame {
+    /// Rank of the frame type, used to order frames of different types.
+    fn type_rank(&self) -> u8 {
+        match self {
+            RespFrame::SimpleString(_) => 0,
+            RespFrame::Error(_) => 1,
+            RespFrame::Null(_) => 2,
+            RespFrame::Integer(_) => 3,
+            RespFrame::BulkString(_) => 4,
+            RespFrame::Array(_) => 5,
+            RespFrame::Boolean(_) => 6,
+            RespFrame::Double(_) => 7,
+            RespFrame::Map(_) => 8,
+            RespFrame::Set(_) => 9,
+            RespFrame::Push(_) => 10,
+        }
+    }
+}
+
+fn cmp_f64(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        // comparing non-NaN values never fails.
+        (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+    }
+}
+
+fn hash_f64<H: Hasher>(v: f64, state: &mut H) {
+    // equal values must hash the same: canonicalize NaNs and zeros.
+    let bits = if v.is_nan() {
+        f64::NAN.to_bits()
+    } else if v == 0.0 {
+        0
+    } else {
+        v.to_bits()
+    };
+    bits.hash(state);
+}
+
+impl PartialEq for RespFrame {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RespFrame {}
+
+impl PartialOrd for RespFrame {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RespFrame {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (RespFrame::SimpleString(a), RespFrame::SimpleString(b)) => a.cmp(b),
+            (RespFrame::Error(a), RespFrame::Error(b)) => a.cmp(b),
+            (RespFrame::Null(a), RespFrame::Null(b)) => a.cmp(b),
+            (RespFrame::Integer(a), RespFrame::Integer(b)) => a.cmp(b),
+            (RespFrame::BulkString(a), RespFrame::BulkString(b)) => a.cmp(b),
+            (RespFrame::Array(a), RespFrame::Array(b)) => a.cmp(b),
+            (RespFrame::Boolean(a), RespFrame::Boolean(b)) => a.cmp(b),
+            (RespFrame::Double(a), RespFrame::Double(b)) => cmp_f64(*a, *b),
+            (RespFrame::Map(a), RespFrame::Map(b)) => a.cmp(b),
+            (RespFrame::Set(a), RespFrame::Set(b)) => a.cmp(b),
+            (RespFrame::Push(a), RespFrame::Push(b)) => a.cmp(b),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
+
+impl Hash for RespFrame {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_rank().hash(state);
+        match self {
+            RespFrame::SimpleString(v) => v.hash(state),
+            RespFrame::Error(v) => v.hash(state),
+            RespFrame::Null(v) => v.hash(state),
+            RespFrame::Integer(v) => v.hash(state),
+            RespFrame::BulkString(v) => v.hash(state),
+            RespFrame::Array(v) => v.hash(state),
+            RespFrame::Boolean(v) => v.hash(state),
+            RespFrame::Double(v) => hash_f64(*v, state),
+            RespFrame::Map(v) => v.hash(state),
+            RespFrame::Set(v) => v.hash(state),
+            RespFrame::Push(v) => v.hash(state),
+        }
+    }
+}
+
+impl RespFrame {
+    /// The frame as a RESP2 client understands it, the way Redis replies to them:
+    /// nulls become null bulk strings, booleans integers, doubles bulk strings,
+    /// and maps, sets and pushes arrays, maps flattened into keys and values.
+    pub fn into_resp2(self) -> RespFrame {
+        match self {
+            RespFrame::Null(_) => BulkString::null().into(),
+            RespFrame::Boolean(b) => RespFrame::Integer(b as i64),
+            RespFrame::Double(d) if d.is_nan() => BulkString::new("nan").into(),
+            RespFrame::Double(d) => BulkString::new(d.to_string()).into(),
+            RespFrame::Array(RespArray(Some(items))) => resp2_array(items),
+            RespFrame::Map(map) => resp2_array(map.into_iter().flat_map(|(k, v)| [k, v])),
+            RespFrame::Set(set) => resp2_array(set),
+            RespFrame::Push(push) => resp2_array(push),
+            frame => frame,
         }
     }
 }
 
+fn resp2_array(items: impl IntoIterator<Item = RespFrame>) -> RespFrame {
+    RespArray::new(
+        items
+            .into_iter()
+            .map(RespFrame::into_resp2)
+            .collect::<Vec<_>>(),
+    )
+    .into()
+}
+
 impl From<&[u8]> for RespFrame {
     fn from(value: &[u8]) -> Self {
         BulkString::new(value).into()
@@ -85,8 +220,44 @@ impl<const N: usize> From<&[u8; N]> for RespFrame {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::{BTreeMap, HashSet};
+
     use super::*;
 
+    #[test]
+    fn test_into_resp2() {
+        assert_eq!(
+            RespFrame::Null(RespNull).into_resp2(),
+            BulkString::null().into()
+        );
+        assert_eq!(RespFrame::Boolean(true).into_resp2(), RespFrame::Integer(1));
+        assert_eq!(
+            RespFrame::Double(1.5).into_resp2(),
+            BulkString::new("1.5").into()
+        );
+        assert_eq!(
+            RespFrame::Double(f64::NEG_INFINITY).into_resp2(),
+            BulkString::new("-inf").into()
+        );
+
+        let mut map = RespMap::new();
+        map.insert(BulkString::new("score"), RespFrame::Double(2.0));
+        map.insert(BulkString::new("missing"), RespFrame::Null(RespNull));
+        let frame: RespFrame = RespArray::new(vec![map.into()]).into();
+        let expected: RespFrame = RespArray::new(vec![RespArray::new(vec![
+            BulkString::new("score").into(),
+            BulkString::new("2").into(),
+            BulkString::new("missing").into(),
+            BulkString::null().into(),
+        ])
+        .into()])
+        .into();
+        assert_eq!(frame.into_resp2(), expected);
+
+        let error: RespFrame = SimpleError::new("ERR no".to_string()).into();
+        assert_eq!(error.clone().into_resp2(), error);
+    }
+
     #[test]
     fn test_resp_frame_decode() -> anyhow::Result<()> {
         let mut buf = BytesMut::from("$-1\r\n");
@@ -100,4 +271,46 @@ mod tests {
         assert_eq!(result, RespFrame::BulkString(b"hello".into()));
         Ok(())
     }
+
+    #[test]
+    fn test_resp_frame_double_semantics() {
+        let nan: RespFrame = f64::NAN.into();
+        assert_eq!(nan, (-f64::NAN).into());
+        assert!(nan > f64::INFINITY.into());
+        assert_eq!(RespFrame::Double(-0.0), RespFrame::Double(0.0));
+
+        let mut set = HashSet::new();
+        set.insert(RespFrame::Double(0.0));
+        assert!(set.contains(&RespFrame::Double(-0.0)));
+        set.insert(f64::NAN.into());
+        assert!(set.contains(&RespFrame::Double(f64::NAN)));
+    }
+
+    #[test]
+    fn test_resp_frame_total_order() {
+        let mut frames: Vec<RespFrame> = vec![
+            2.5.into(),
+            BulkString::new("b").into(),
+            1.into(),
+            BulkString::new("a").into(),
+            SimpleString::new("ok").into(),
+            (-1.0).into(),
+        ];
+        frames.sort();
+        assert_eq!(
+            frames,
+            vec![
+                SimpleString::new("ok").into(),
+                1.into(),
+                BulkString::new("a").into(),
+                BulkString::new("b").into(),
+                (-1.0).into(),
+                2.5.into(),
+            ]
+        );
+
+        // frames can be used as BTreeMap keys.
+        let map: BTreeMap<RespFrame, i64> = frames.into_iter().zip(0..).collect();
+        assert_eq!(map.get(&BulkString::new("a").into()), Some(&2));
+    }
 }
@@ -1,14 +1,22 @@
-use std::ops::Deref;
+use std::{
+    cmp::Ordering,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
 
 use bytes::BytesMut;
+use indexmap::IndexSet;
 
 use crate::{
     cal_total_length, err::RespError, parse_length, parse_length_and_move, resp_frame::RespFrame,
-    RespDecode, RespEncode, BUF_CAP,
+    EncodedFrames, RespDecode, RespEncode, BUF_CAP,
 };
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
-pub struct RespSet(Vec<RespFrame>);
+/// Elements are unique and kept in insertion order, which is also the encoding order.
+/// Equality, ordering and hashing ignore the order of the elements.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RespSet(IndexSet<RespFrame>);
 
 /// Sets are somewhat like Arrays but are unordered and should only contain unique elements.
 /// Format:
@@ -27,6 +35,14 @@ impl RespEncode for RespSet {
         }
         buf
     }
+
+    fn write_chunks(self, frames: &mut EncodedFrames) {
+        let header = format!("~{}\r\n", self.len());
+        frames.buf_mut().extend_from_slice(header.as_bytes());
+        for frame in self.0 {
+            frame.write_chunks(frames);
+        }
+    }
 }
 
 impl RespDecode for RespSet {
@@ -34,18 +50,14 @@ impl RespDecode for RespSet {
 
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         if buf.len() < Self::expect_length(buf)? {
-            return Err(RespError::NotCompleted);
+            return Err(RespError::NotCompleted(None));
         }
         let length = parse_length_and_move(Self::PREFIX, buf)?;
-        let mut data = Vec::with_capacity(length as usize);
+        let mut set = IndexSet::with_capacity(length as usize);
         for _ in 0..length {
-            let key = RespFrame::decode(buf)?;
-            if data.contains(&key) {
-                continue;
-            }
-            data.push(key);
+            set.insert(RespFrame::decode(buf)?);
         }
-        Ok(RespSet::new(data))
+        Ok(RespSet(set))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
@@ -55,18 +67,80 @@ impl RespDecode for RespSet {
 }
 
 impl RespSet {
+    /// Build a set from the frames, dropping duplicates.
     pub fn new(s: impl Into<Vec<RespFrame>>) -> Self {
-        RespSet(s.into())
+        s.into().into_iter().collect()
+    }
+
+    /// Add the frame, returns false if it was already present.
+    pub fn insert(&mut self, frame: RespFrame) -> bool {
+        self.0.insert(frame)
+    }
+
+    /// Elements of either set, those of `self` first.
+    pub fn union(&self, other: &RespSet) -> RespSet {
+        RespSet(self.0.union(&other.0).cloned().collect())
+    }
+
+    /// Elements of both sets, in the order of `self`.
+    pub fn intersection(&self, other: &RespSet) -> RespSet {
+        RespSet(self.0.intersection(&other.0).cloned().collect())
+    }
+
+    fn sorted(&self) -> Vec<&RespFrame> {
+        let mut elems: Vec<_> = self.0.iter().collect();
+        elems.sort();
+        elems
+    }
+}
+
+impl PartialOrd for RespSet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RespSet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sorted().cmp(&other.sorted())
+    }
+}
+
+impl Hash for RespSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // combine the element hashes with a commutative operation
+        // so that equal sets hash the same whatever their order.
+        let combined = self.0.iter().fold(0u64, |acc, frame| {
+            let mut hasher = DefaultHasher::new();
+            frame.hash(&mut hasher);
+            acc.wrapping_add(hasher.finish())
+        });
+        self.0.len().hash(state);
+        combined.hash(state);
     }
 }
 
 impl Deref for RespSet {
-    type Target = Vec<RespFrame>;
+    type Target = IndexSet<RespFrame>;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
+impl FromIterator<RespFrame> for RespSet {
+    fn from_iter<T: IntoIterator<Item = RespFrame>>(iter: T) -> Self {
+        RespSet(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for RespSet {
+    type Item = RespFrame;
+    type IntoIter = indexmap::set::IntoIter<RespFrame>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::simple_string::SimpleString;
@@ -119,7 +193,7 @@ mod tests {
         // not completed
         let mut buf = BytesMut::from("~2\r\n+foo\r\n");
         let result = RespSet::decode(&mut buf);
-        assert_eq!(result.unwrap_err(), RespError::NotCompleted);
+        assert_eq!(result.unwrap_err(), RespError::NotCompleted(None));
 
         // add bytes to buf to make it completed
         buf.extend_from_slice(b"+baz\r\n");
@@ -131,4 +205,28 @@ mod tests {
         assert_eq!(result, expected);
         Ok(())
     }
+
+    #[test]
+    fn test_set_semantics() {
+        let a = RespSet::new(vec![1.into(), 2.into(), 1.into()]);
+        assert_eq!(a.len(), 2);
+        assert!(a.contains(&RespFrame::Integer(2)));
+
+        // order doesn't matter for equality and hashing.
+        let b = RespSet::new(vec![2.into(), 1.into()]);
+        assert_eq!(a, b);
+        let hash = |s: &RespSet| {
+            let mut hasher = DefaultHasher::new();
+            s.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&a), hash(&b));
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+
+        let c = RespSet::new(vec![2.into(), 3.into()]);
+        let union: Vec<_> = a.union(&c).into_iter().collect();
+        assert_eq!(union, vec![1.into(), 2.into(), 3.into()]);
+        let inter: Vec<_> = a.intersection(&c).into_iter().collect();
+        assert_eq!(inter, vec![2.into()]);
+    }
 }
@@ -2,7 +2,7 @@ use bytes::BytesMut;
 
 use crate::{err::RespError, extract_simple_frame_data, RespDecode, RespEncode, CRLF_LEN};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SimpleError(pub(crate) String);
 
 impl RespDecode for SimpleError {
@@ -29,9 +29,12 @@ impl RespDecode for SimpleError {
 /// whereas the string encoded in the error type is the error message itself.
 ///
 /// Examples: -Error message\r\n
+///
+/// Like Redis, CR and LF characters in the message are replaced by spaces
+/// so that the message can't break the framing.
 impl RespEncode for SimpleError {
     fn encode(self) -> Vec<u8> {
-        format!("-{}\r\n", self.0).into_bytes()
+        format!("-{}\r\n", self.0.replace(['\r', '\n'], " ")).into_bytes()
     }
 }
 
@@ -71,7 +74,7 @@ mod tests {
         // not completed case
         buf.extend_from_slice(b"-Hi\r");
         let result = SimpleError::decode(&mut buf);
-        assert_eq!(result.unwrap_err(), RespError::NotCompleted);
+        assert_eq!(result.unwrap_err(), RespError::NotCompleted(None));
 
         // put \n to complete the string.
         buf.put_u8(b'\n');
@@ -86,4 +89,17 @@ mod tests {
         let frame: RespFrame = SimpleError::new("Error Message".to_string()).into();
         assert_eq!(frame.encode(), b"-Error Message\r\n");
     }
+
+    #[test]
+    fn test_simple_error_with_crlf_is_sanitized() -> anyhow::Result<()> {
+        let frame: RespFrame = SimpleError::new("ERR bad\r\n+OK").into();
+        let encoded = frame.encode();
+        assert_eq!(encoded, b"-ERR bad  +OK\r\n");
+
+        let mut buf = BytesMut::from(encoded.as_slice());
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(frame, SimpleError::new("ERR bad  +OK").into());
+        assert!(buf.is_empty());
+        Ok(())
+    }
 }
@@ -1,8 +1,13 @@
 use bytes::BytesMut;
 
-use crate::{err::RespError, extract_simple_frame_data, RespDecode, RespEncode, CRLF_LEN};
+use crate::{
+    err::RespError, extract_simple_frame_data, BulkString, RespDecode, RespEncode, CRLF, CRLF_LEN,
+};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+/// The encoding of the most frequent reply.
+pub const SIMPLE_STRING_OK: &[u8] = b"+OK\r\n";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SimpleString(pub(crate) String);
 
 impl RespDecode for SimpleString {
@@ -25,10 +30,28 @@ impl RespDecode for SimpleString {
 /// The string mustn't contain a CR (\r) or LF (\n) character and is terminated by CRLF (i.e., \r\n).
 ///
 /// Examples: +OK\r\n
+///
+/// A string containing CR or LF is promoted to a bulk string instead,
+/// otherwise the peer would parse its remainder as another frame.
 impl RespEncode for SimpleString {
     fn encode(self) -> Vec<u8> {
+        if self.0.contains(['\r', '\n']) {
+            return BulkString::new(self.0).encode();
+        }
         format!("+{}\r\n", self.0).into_bytes()
     }
+
+    fn write_to(self, buf: &mut BytesMut) {
+        if self.0 == "OK" {
+            buf.extend_from_slice(SIMPLE_STRING_OK);
+        } else if self.0.contains(['\r', '\n']) {
+            BulkString::new(self.0).write_to(buf);
+        } else {
+            buf.extend_from_slice(b"+");
+            buf.extend_from_slice(self.0.as_bytes());
+            buf.extend_from_slice(CRLF);
+        }
+    }
 }
 
 impl SimpleString {
@@ -67,7 +90,7 @@ mod tests {
         // not completed case
         buf.extend_from_slice(b"+Hi\r");
         let result = SimpleString::decode(&mut buf);
-        assert_eq!(result.unwrap_err(), RespError::NotCompleted);
+        assert_eq!(result.unwrap_err(), RespError::NotCompleted(None));
 
         // put \n to complete the string.
         buf.put_u8(b'\n');
@@ -85,6 +108,29 @@ mod tests {
         assert_eq!(frame.encode(), b"+hello\r\n");
     }
 
+    #[test]
+    fn test_simple_string_write_to() {
+        let mut buf = BytesMut::new();
+        for s in ["OK", "hello", "a\nb"] {
+            RespFrame::from(SimpleString::new(s)).write_to(&mut buf);
+        }
+        assert_eq!(buf, "+OK\r\n+hello\r\n$3\r\na\nb\r\n");
+    }
+
+    #[test]
+    fn test_simple_string_with_crlf_is_promoted() -> anyhow::Result<()> {
+        // e.g. ECHO "hi\r\n+INJECTED" must not reply with two frames.
+        let frame: RespFrame = SimpleString::new("hi\r\n+INJECTED").into();
+        let encoded = frame.encode();
+        assert_eq!(encoded, b"$13\r\nhi\r\n+INJECTED\r\n");
+
+        let mut buf = BytesMut::from(encoded.as_slice());
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(frame, BulkString::new("hi\r\n+INJECTED").into());
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn test_bulk_string_expect_length() -> anyhow::Result<()> {
         // TODO: deal with null string with simple string.
@@ -0,0 +1,117 @@
+use std::{collections::VecDeque, io::IoSlice};
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use crate::RespEncode;
+
+/// Frames encoded for a vectored write, see [`RespEncode::write_chunks`].
+///
+/// The encoding is a list of chunks, which reference the payloads of large bulk
+/// strings rather than copy them, e.g. to reply a value of 100MB.
+#[derive(Debug, Clone)]
+pub struct EncodedFrames {
+    chunks: VecDeque<Bytes>,
+    /// The encoding written since the last referenced payload, the last chunk.
+    buf: BytesMut,
+    /// Payloads at least this large are referenced.
+    min_shared: usize,
+}
+
+impl EncodedFrames {
+    pub fn new(min_shared: usize) -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            buf: BytesMut::new(),
+            min_shared,
+        }
+    }
+
+    pub fn push(&mut self, frame: impl RespEncode) {
+        frame.write_chunks(self);
+    }
+
+    /// The buffer the encoding is appended to.
+    pub fn buf_mut(&mut self) -> &mut BytesMut {
+        &mut self.buf
+    }
+
+    /// Append a payload, which is referenced if it is large.
+    pub fn push_payload(&mut self, payload: Bytes) {
+        if payload.len() < self.min_shared {
+            self.buf.extend_from_slice(&payload);
+            return;
+        }
+        if !self.buf.is_empty() {
+            self.chunks.push_back(self.buf.split().freeze());
+        }
+        self.chunks.push_back(payload);
+    }
+}
+
+impl Buf for EncodedFrames {
+    fn remaining(&self) -> usize {
+        self.chunks.iter().map(Bytes::len).sum::<usize>() + self.buf.len()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        match self.chunks.front() {
+            Some(chunk) => chunk,
+            None => &self.buf,
+        }
+    }
+
+    fn advance(&mut self, mut cnt: usize) {
+        while let Some(chunk) = self.chunks.front_mut() {
+            if cnt < chunk.len() {
+                chunk.advance(cnt);
+                return;
+            }
+            cnt -= chunk.len();
+            self.chunks.pop_front();
+        }
+        self.buf.advance(cnt);
+    }
+
+    fn chunks_vectored<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
+        let chunks = self.chunks.iter().map(|c| &c[..]);
+        let chunks = chunks.chain(Some(&self.buf[..]).filter(|b| !b.is_empty()));
+        dst.iter_mut()
+            .zip(chunks)
+            .map(|(slice, chunk)| *slice = IoSlice::new(chunk))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, RespArray, RespFrame};
+
+    use super::*;
+
+    #[test]
+    fn test_encoded_frames() {
+        let large = Bytes::from(vec![b'x'; 16]);
+        let frame: RespFrame = RespArray::new(vec![
+            BulkString::new("small").into(),
+            BulkString::from(large.clone()).into(),
+            RespFrame::Integer(1),
+        ])
+        .into();
+
+        let mut encoded = EncodedFrames::new(8);
+        encoded.push(frame.clone());
+        encoded.push(RespFrame::Integer(2));
+        // the payload is referenced, not copied.
+        assert_eq!(encoded.chunks.len(), 2);
+        assert_eq!(encoded.chunks[1].as_ptr(), large.as_ptr());
+
+        let mut slices = [IoSlice::new(&[]); 8];
+        assert_eq!(encoded.chunks_vectored(&mut slices), 3);
+
+        let mut expected = frame.encode();
+        expected.extend_from_slice(b":2\r\n");
+        assert_eq!(encoded.remaining(), expected.len());
+        encoded.advance(3);
+        assert_eq!(encoded.copy_to_bytes(encoded.remaining()), expected[3..]);
+    }
+}
@@ -0,0 +1,190 @@
+use bytes::{Buf, BytesMut};
+
+use crate::{err::RespError, BulkString, RespArray, RespDecodeV2, RespFrame, CRLF};
+
+use super::parse_frame_length;
+
+/// Which bulk strings of a request are read as they arrive, see [`PartialArray`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LargeBulks {
+    /// Bulk strings at least this large are read into a buffer of their own.
+    pub min_size: usize,
+    /// Larger bulk strings are refused.
+    pub max_size: usize,
+}
+
+/// A request array decoded element by element as its bytes arrive, once one of
+/// its bulk strings is large.
+///
+/// The large bulk string is read into a buffer of its own, allocated once for its
+/// length, which then holds the value without being copied: the whole frame is
+/// never buffered, e.g. for a SET of 100MB.
+#[derive(Debug)]
+pub(crate) struct PartialArray {
+    items: Vec<RespFrame>,
+    /// Elements not decoded yet, the large bulk string being read included.
+    remaining: usize,
+    /// The large bulk string being read, and its length.
+    bulk: Option<(BytesMut, usize)>,
+    limits: LargeBulks,
+}
+
+impl PartialArray {
+    /// Start decoding the array at the start of `buf` element by element, if the
+    /// first of its elements which didn't arrive entirely is a large bulk string.
+    /// The elements before it are consumed.
+    pub fn start(buf: &mut BytesMut, limits: LargeBulks) -> Result<Option<Self>, RespError> {
+        let Some((header_len, len)) = header(buf, b'*') else {
+            return Ok(None);
+        };
+        let Ok(len) = usize::try_from(len) else {
+            return Ok(None);
+        };
+        let mut offset = header_len;
+        let mut complete = 0;
+        while complete < len {
+            match parse_frame_length(&buf[offset..]) {
+                Ok(item_len) => offset += item_len,
+                Err(RespError::NotCompleted(_)) => break,
+                Err(e) => return Err(e),
+            }
+            complete += 1;
+        }
+        match header(&buf[offset..], b'$') {
+            Some((_, bulk_len)) if complete < len && bulk_len >= limits.min_size as i64 => {}
+            _ => return Ok(None),
+        }
+
+        buf.advance(header_len);
+        let mut array = PartialArray {
+            // every element takes some bytes, the length may be made up.
+            items: Vec::with_capacity(len.min(buf.len())),
+            remaining: len,
+            bulk: None,
+            limits,
+        };
+        for _ in 0..complete {
+            array.push(RespFrame::decode(buf)?);
+        }
+        Ok(Some(array))
+    }
+
+    /// Decode the elements which arrived, returning the array once all of them did.
+    pub fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<RespArray>, RespError> {
+        while self.remaining > 0 {
+            if let Some((body, len)) = self.bulk.as_mut() {
+                let n = (*len - body.len()).min(buf.len());
+                body.extend_from_slice(&buf[..n]);
+                buf.advance(n);
+                if body.len() < *len || buf.len() < CRLF.len() {
+                    return Ok(None);
+                }
+                if !buf.starts_with(CRLF) {
+                    return Err(RespError::InvalidFrame(
+                        "expected CRLF after the bulk string".to_string(),
+                    ));
+                }
+                buf.advance(CRLF.len());
+                if let Some((body, _)) = self.bulk.take() {
+                    self.push(BulkString::from(body.freeze()).into());
+                }
+                continue;
+            }
+            match header(buf, b'$') {
+                Some((header_len, len)) if len >= self.limits.min_size as i64 => {
+                    if len > self.limits.max_size as i64 {
+                        return Err(RespError::InvalidFrameLength(len as isize));
+                    }
+                    buf.advance(header_len);
+                    self.bulk = Some((BytesMut::with_capacity(len as usize), len as usize));
+                }
+                _ => match RespFrame::decode(buf) {
+                    Ok(item) => self.push(item),
+                    Err(RespError::NotCompleted(_)) => return Ok(None),
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+        Ok(Some(RespArray::new(std::mem::take(&mut self.items))))
+    }
+
+    fn push(&mut self, item: RespFrame) {
+        self.items.push(item);
+        self.remaining -= 1;
+    }
+}
+
+/// The length of the `<prefix><length>\r\n` header at the start of `buf`, and the
+/// length it holds. `None` when the header didn't arrive entirely, or isn't one.
+fn header(buf: &[u8], prefix: u8) -> Option<(usize, i64)> {
+    if buf.first() != Some(&prefix) {
+        return None;
+    }
+    let end = buf.windows(CRLF.len()).position(|w| w == CRLF)?;
+    let len = std::str::from_utf8(&buf[1..end]).ok()?.parse().ok()?;
+    Some((end + CRLF.len(), len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: LargeBulks = LargeBulks {
+        min_size: 8,
+        max_size: 64,
+    };
+
+    #[test]
+    fn test_partial_array() -> anyhow::Result<()> {
+        let mut buf = BytesMut::from("*4\r\n$3\r\nset\r\n$3\r\nkey\r\n$10\r\n0123");
+        let mut array = PartialArray::start(&mut buf, LIMITS)?.expect("a large bulk string");
+        assert_eq!(buf, "$10\r\n0123");
+
+        assert_eq!(array.decode(&mut buf)?, None);
+        assert!(buf.is_empty());
+        buf.extend_from_slice(b"456789\r");
+        assert_eq!(array.decode(&mut buf)?, None);
+        assert_eq!(buf, "\r");
+        buf.extend_from_slice(b"\n$2\r\nEX\r\n+next\r\n");
+        assert_eq!(
+            array.decode(&mut buf)?,
+            Some(RespArray::new(vec![
+                BulkString::new("set").into(),
+                BulkString::new("key").into(),
+                BulkString::new("0123456789").into(),
+                BulkString::new("EX").into(),
+            ]))
+        );
+        assert_eq!(buf, "+next\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_array_only_for_large_bulk_strings() -> anyhow::Result<()> {
+        // the bulk string is small, or its header didn't arrive yet.
+        let mut buf = BytesMut::from("*2\r\n$3\r\nget\r\n$7\r\nabc");
+        assert!(PartialArray::start(&mut buf, LIMITS)?.is_none());
+        let mut buf = BytesMut::from("*2\r\n$3\r\nget\r\n$10");
+        assert!(PartialArray::start(&mut buf, LIMITS)?.is_none());
+        let mut buf = BytesMut::from("$10\r\n0123");
+        assert!(PartialArray::start(&mut buf, LIMITS)?.is_none());
+        assert_eq!(buf, "$10\r\n0123");
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_array_errors() -> anyhow::Result<()> {
+        let mut buf = BytesMut::from("*2\r\n$3\r\nset\r\n$100\r\n");
+        let mut array = PartialArray::start(&mut buf, LIMITS)?.expect("a large bulk string");
+        assert_eq!(
+            array.decode(&mut buf),
+            Err(RespError::InvalidFrameLength(100))
+        );
+
+        let mut buf = BytesMut::from("*2\r\n$3\r\nset\r\n$8\r\n0123");
+        let mut array = PartialArray::start(&mut buf, LIMITS)?.expect("a large bulk string");
+        buf.extend_from_slice(b"4567xx");
+        assert!(array.decode(&mut buf).is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
+mod incremental;
 mod parser;
 mod parser_len;
 
+pub(crate) use self::incremental::{LargeBulks, PartialArray};
 pub use self::parser::parse_frame;
 pub use self::parser_len::parse_frame_length;
 use crate::{err::RespError, RespFrame};
@@ -25,9 +27,7 @@ impl RespDecodeV2 for RespFrame {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeMap;
-
-    use crate::{BulkString, RespArray};
+    use crate::{BulkString, RespArray, RespMap, RespPush, RespSet, SimpleString};
 
     use super::*;
 
@@ -42,7 +42,7 @@ mod tests {
     fn respv2_simple_string_bad_len_should_fail() {
         let buf = b"+OK\r";
         let err = RespFrame::expect_length(buf).unwrap_err();
-        assert_eq!(err, RespError::NotCompleted)
+        assert_eq!(err, RespError::NotCompleted(None))
     }
 
     #[test]
@@ -87,6 +87,35 @@ mod tests {
         assert_eq!(len, buf.len());
     }
 
+    #[test]
+    fn respv2_empty_bulk_string_should_work() {
+        let buf = b"*3\r\n$1\r\na\r\n$0\r\n\r\n$1\r\nb\r\n";
+        assert_eq!(RespFrame::expect_length(buf).unwrap(), buf.len());
+        let mut buf = BytesMut::from(&buf[..]);
+        let frame = RespFrame::decode(&mut buf).unwrap();
+        assert_eq!(
+            frame,
+            RespArray::new(vec![
+                BulkString::new("a").into(),
+                BulkString::new("").into(),
+                BulkString::new("b").into(),
+            ])
+            .into()
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn respv2_incomplete_bulk_string_length_should_hint_needed_bytes() {
+        let buf = b"$6\r\nfoo";
+        let err = RespFrame::expect_length(buf).unwrap_err();
+        assert_eq!(err, RespError::NotCompleted(Some(5)));
+
+        let buf = b"*2\r\n$3\r\nfoo\r\n$100\r\n";
+        let err = RespFrame::expect_length(buf).unwrap_err();
+        assert_eq!(err, RespError::NotCompleted(Some(102)));
+    }
+
     #[test]
     fn respv2_bulk_string_should_work() {
         let mut buf = BytesMut::from("$6\r\nfoobar\r\n");
@@ -147,19 +176,80 @@ mod tests {
 
     #[test]
     fn respv2_map_length_should_work() {
-        let buf = b"%2\r\n+OK\r\n-ERR\r\n";
+        let buf = b"%2\r\n+OK\r\n-ERR\r\n:1\r\n$3\r\nfoo\r\n";
+        let len = RespFrame::expect_length(buf).unwrap();
+        assert_eq!(len, buf.len());
+
+        let buf = b"%0\r\n";
         let len = RespFrame::expect_length(buf).unwrap();
         assert_eq!(len, buf.len());
     }
 
     #[test]
     fn respv2_map_should_work() {
-        let mut buf = BytesMut::from("%2\r\n+OK\r\n-ERR\r\n");
+        let mut buf = BytesMut::from("%2\r\n+OK\r\n-ERR\r\n:1\r\n$3\r\nfoo\r\n");
         let frame = RespFrame::decode(&mut buf).unwrap();
-        let items: BTreeMap<String, RespFrame> =
-            [("OK".to_string(), RespFrame::Error("ERR".into()))]
-                .into_iter()
-                .collect();
-        assert_eq!(frame, RespFrame::Map(items.into()));
+        let items: RespMap = [
+            (
+                RespFrame::from(SimpleString::new("OK")),
+                RespFrame::Error("ERR".into()),
+            ),
+            (RespFrame::Integer(1), BulkString::new("foo").into()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(frame, RespFrame::Map(items));
+    }
+
+    #[test]
+    fn respv2_set_should_work() {
+        let buf = b"~3\r\n+a\r\n:1\r\n+a\r\n";
+        let len = RespFrame::expect_length(buf).unwrap();
+        assert_eq!(len, buf.len());
+
+        let mut buf = BytesMut::from(&buf[..]);
+        let frame = RespFrame::decode(&mut buf).unwrap();
+        let set = RespSet::new(vec![SimpleString::new("a").into(), 1.into()]);
+        assert_eq!(frame, RespFrame::Set(set));
+    }
+
+    #[test]
+    fn respv2_push_should_work() {
+        let buf = b">2\r\n+message\r\n:1\r\n";
+        let len = RespFrame::expect_length(buf).unwrap();
+        assert_eq!(len, buf.len());
+
+        let mut buf = BytesMut::from(&buf[..]);
+        let frame = RespFrame::decode(&mut buf).unwrap();
+        assert_eq!(
+            frame,
+            RespFrame::Push(RespPush::new(vec![
+                RespFrame::SimpleString("message".into()),
+                RespFrame::Integer(1)
+            ]))
+        );
+    }
+
+    #[test]
+    fn respv2_integer_min_should_work() {
+        let mut buf = BytesMut::from(":-9223372036854775808\r\n");
+        let frame = RespFrame::decode(&mut buf).unwrap();
+        assert_eq!(frame, RespFrame::Integer(i64::MIN));
+
+        let mut buf = BytesMut::from(":9223372036854775808\r\n");
+        assert!(RespFrame::decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn respv2_nesting_beyond_the_limit_should_fail() {
+        let mut buf = b"*1\r\n".repeat(crate::MAX_NESTING_DEPTH);
+        buf.extend_from_slice(b":1\r\n");
+        assert_eq!(RespFrame::expect_length(&buf).unwrap(), buf.len());
+        assert!(parse_frame(&mut &buf[..]).is_ok());
+
+        // deep enough to overflow the stack if parsed recursively.
+        let buf = b"*1\r\n".repeat(100_000);
+        assert!(RespFrame::expect_length(&buf).is_err());
+        assert!(parse_frame(&mut &buf[..]).is_err());
     }
 }
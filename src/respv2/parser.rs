@@ -1,26 +1,36 @@
 use winnow::{
     ascii::{digit1, float},
-    combinator::{alt, dispatch, fail, opt, preceded, terminated},
+    combinator::{alt, dispatch, fail, opt, terminated},
     error::{ContextError, ErrMode},
     token::{any, take, take_until},
     PResult, Parser,
 };
 
-use crate::{BulkString, RespArray, RespFrame, RespMap, RespNull, SimpleError, SimpleString};
+use crate::{
+    BulkString, RespArray, RespFrame, RespMap, RespNull, RespPush, RespSet, SimpleError,
+    SimpleString, MAX_NESTING_DEPTH,
+};
 
 const CRLF: &[u8] = b"\r\n";
 
 pub fn parse_frame(input: &mut &[u8]) -> PResult<RespFrame> {
+    nested_frame(input, 0)
+}
+
+// a frame nested in `depth` aggregate frames.
+fn nested_frame(input: &mut &[u8], depth: usize) -> PResult<RespFrame> {
     dispatch!(any;
         b'+' => simple_string.map(RespFrame::SimpleString),
         b'-' => error.map(RespFrame::Error),
         b':' => integer.map(RespFrame::Integer),
         b'$' => alt((null_bulk_string.map(RespFrame::BulkString), bulk_string.map(RespFrame::BulkString))),
-        b'*' => alt((null_array.map(RespFrame::Array), array.map(RespFrame::Array))),
+        b'*' => (|i: &mut &[u8]| array(i, depth)).map(RespFrame::Array),
         b'_' => null.map(RespFrame::Null),
         b'#' => boolean.map(RespFrame::Boolean),
         b',' => double.map(RespFrame::Double),
-        b'%' => map.map(RespFrame::Map),
+        b'%' => (|i: &mut &[u8]| map(i, depth)).map(RespFrame::Map),
+        b'~' => (|i: &mut &[u8]| set(i, depth)).map(RespFrame::Set),
+        b'>' => (|i: &mut &[u8]| push(i, depth)).map(RespFrame::Push),
         _v => fail::<_,_,_>
     )
     .parse_next(input)
@@ -39,9 +49,14 @@ fn error(input: &mut &[u8]) -> PResult<SimpleError> {
 // :[<+|->]<value>\r\n
 pub(crate) fn integer(input: &mut &[u8]) -> PResult<i64> {
     let sign = opt(alt(('+', '-'))).parse_next(input)?.unwrap_or('+');
-    let sign = if sign == '+' { 1 } else { -1 };
-    let v: i64 = terminated(digit1.parse_to(), CRLF).parse_next(input)?;
-    Ok(sign * v)
+    let v: u64 = terminated(digit1.parse_to(), CRLF).parse_next(input)?;
+    // parse the magnitude unsigned, so that i64::MIN is in range.
+    let v = if sign == '+' {
+        i64::try_from(v).ok()
+    } else {
+        0i64.checked_sub_unsigned(v)
+    };
+    v.ok_or_else(|| cut_err("integer out of range"))
 }
 
 // $-1\r\n null bulk string
@@ -49,42 +64,64 @@ fn null_bulk_string(input: &mut &[u8]) -> PResult<BulkString> {
     "-1\r\n".value(BulkString(None)).parse_next(input)
 }
 
-// $<length>\r\n<data>\r\n
-#[allow(clippy::comparison_chain)]
+// $<length>\r\n<data>\r\n, the data of an empty string is still followed by \r\n
 fn bulk_string(input: &mut &[u8]) -> PResult<BulkString> {
     let len = integer.parse_next(input)?;
-    if len == 0 {
-        return Ok(BulkString(Some(vec![])));
-    } else if len < 0 {
+    if len < 0 {
         return Err(cut_err("bulk string len < 0 is invalid"));
     }
     let data = terminated(take(len as usize), CRLF)
         .map(|s: &[u8]| s.to_vec())
         .parse_next(input)?;
-    Ok(BulkString(Some(data)))
-}
-
-// *-1\r\n
-fn null_array(input: &mut &[u8]) -> PResult<RespArray> {
-    "-1\r\n".value(RespArray::null()).parse_next(input)
+    Ok(BulkString::new(data))
 }
 
-// *<number-of-elements>\r\n<element-1>...<element-n>
-#[allow(clippy::comparison_chain)]
-fn array(input: &mut &[u8]) -> PResult<RespArray> {
+// *<number-of-elements>\r\n<element-1>...<element-n>, or *-1\r\n for the null array
+fn array(input: &mut &[u8], depth: usize) -> PResult<RespArray> {
+    check_depth(depth)?;
     let len = integer.parse_next(input)?;
-    if len == 0 {
-        return Ok(RespArray::new(vec![]));
+    if len == -1 {
+        return Ok(RespArray::null());
     } else if len < 0 {
         return Err(cut_err("array len < 0 is invalid"));
     }
-    let mut arr = Vec::with_capacity(len as usize);
+    // every element takes some bytes, a length beyond the input fails anyway.
+    let mut arr = Vec::with_capacity((len as usize).min(input.len()));
     for _ in 0..len {
-        arr.push(parse_frame(input)?);
+        arr.push(nested_frame(input, depth + 1)?);
     }
     Ok(RespArray::new(arr))
 }
 
+// ><number-of-elements>\r\n<element-1>...<element-n>
+fn push(input: &mut &[u8], depth: usize) -> PResult<RespPush> {
+    check_depth(depth)?;
+    let len = integer.parse_next(input)?;
+    if len < 0 {
+        return Err(cut_err("push len < 0 is invalid"));
+    }
+    // every element takes some bytes, a length beyond the input fails anyway.
+    let mut arr = Vec::with_capacity((len as usize).min(input.len()));
+    for _ in 0..len {
+        arr.push(nested_frame(input, depth + 1)?);
+    }
+    Ok(RespPush::new(arr))
+}
+
+// ~<number-of-elements>\r\n<element-1>...<element-n>
+fn set(input: &mut &[u8], depth: usize) -> PResult<RespSet> {
+    check_depth(depth)?;
+    let len = integer.parse_next(input)?;
+    if len < 0 {
+        return Err(cut_err("set len < 0 is invalid"));
+    }
+    let mut set = RespSet::default();
+    for _ in 0..len {
+        set.insert(nested_frame(input, depth + 1)?);
+    }
+    Ok(set)
+}
+
 // _\r\n
 fn null(input: &mut &[u8]) -> PResult<RespNull> {
     CRLF.value(RespNull).parse_next(input)
@@ -100,16 +137,17 @@ fn double(input: &mut &[u8]) -> PResult<f64> {
     terminated(float, CRLF).parse_next(input)
 }
 
-fn map(input: &mut &[u8]) -> PResult<RespMap> {
+// %<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>
+fn map(input: &mut &[u8], depth: usize) -> PResult<RespMap> {
+    check_depth(depth)?;
     let len: i64 = integer.parse_next(input)?;
-    if len <= 0 {
-        return Err(cut_err("map len <= 0 is invalid"));
+    if len < 0 {
+        return Err(cut_err("map len < 0 is invalid"));
     }
     let mut res = RespMap::new();
-    let count = len as usize / 2;
-    for _ in 0..count {
-        let key = preceded('+', parse_string).parse_next(input)?;
-        let value = parse_frame(input)?;
+    for _ in 0..len {
+        let key = nested_frame(input, depth + 1)?;
+        let value = nested_frame(input, depth + 1)?;
         res.insert(key, value);
     }
     Ok(res)
@@ -121,6 +159,13 @@ fn parse_string(input: &mut &[u8]) -> PResult<String> {
         .parse_next(input)
 }
 
+pub(crate) fn check_depth(depth: usize) -> PResult<()> {
+    if depth >= MAX_NESTING_DEPTH {
+        return Err(cut_err("aggregate frames nested too deeply"));
+    }
+    Ok(())
+}
+
 pub(crate) fn cut_err(_s: impl Into<String>) -> ErrMode<ContextError> {
     ErrMode::Cut(ContextError::default())
 }
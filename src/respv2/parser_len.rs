@@ -9,7 +9,7 @@ use winnow::{
 
 use crate::{
     err::RespError,
-    respv2::parser::{cut_err, integer},
+    respv2::parser::{check_depth, cut_err, integer},
     CRLF,
 };
 
@@ -22,11 +22,19 @@ pub fn parse_frame_length(input: &[u8]) -> Result<usize, RespError> {
             let end = target.as_ptr() as usize;
             Ok(end - start)
         }
-        Err(_) => Err(RespError::NotCompleted),
+        Err(ErrMode::Incomplete(Needed::Size(size))) => {
+            Err(RespError::NotCompleted(Some(size.get())))
+        }
+        Err(_) => Err(RespError::NotCompleted(None)),
     }
 }
 
 pub fn parse_frame_len(input: &mut &[u8]) -> PResult<()> {
+    nested_frame_len(input, 0)
+}
+
+// the length of a frame nested in `depth` aggregate frames.
+fn nested_frame_len(input: &mut &[u8], depth: usize) -> PResult<()> {
     // parse simple frame like {}...\r\n
     let mut simple_parser = terminated(take_until(0.., CRLF), CRLF).value(());
     dispatch!(any;
@@ -34,17 +42,20 @@ pub fn parse_frame_len(input: &mut &[u8]) -> PResult<()> {
         b'-' => simple_parser,
         b':' => simple_parser,
         b'$' => bulk_string_len,
-        b'*' => array_len,
+        b'*' => |i: &mut &[u8]| array_len(i, depth),
         b'_' => simple_parser,
         b'#' => simple_parser,
         b',' => simple_parser,
-        b'%' => map_len,
+        b'%' => |i: &mut &[u8]| map_len(i, depth),
+        b'~' => |i: &mut &[u8]| array_len(i, depth),
+        b'>' => |i: &mut &[u8]| array_len(i, depth),
         _v => fail::<_,_,_>
     )
     .parse_next(input)
 }
 
-fn array_len(input: &mut &[u8]) -> PResult<()> {
+fn array_len(input: &mut &[u8], depth: usize) -> PResult<()> {
+    check_depth(depth)?;
     let len: i64 = integer.parse_next(input)?;
     if len == 0 || len == -1 {
         return Ok(());
@@ -52,14 +63,14 @@ fn array_len(input: &mut &[u8]) -> PResult<()> {
         return Err(cut_err("array length must >= -1"));
     }
     for _ in 0..len {
-        parse_frame_len(input)?;
+        nested_frame_len(input, depth + 1)?;
     }
     Ok(())
 }
 
 fn bulk_string_len(input: &mut &[u8]) -> PResult<()> {
     let len = integer.parse_next(input)?;
-    if len == -1 || len == 0 {
+    if len == -1 {
         return Ok(());
     } else if len < -1 {
         return Err(cut_err("bulk string length must >= -1"));
@@ -79,19 +90,17 @@ fn bulk_string_len(input: &mut &[u8]) -> PResult<()> {
     Ok(())
 }
 
-fn map_len(input: &mut &[u8]) -> PResult<()> {
+fn map_len(input: &mut &[u8], depth: usize) -> PResult<()> {
+    check_depth(depth)?;
     let len = integer.parse_next(input)?;
-    if len <= 0 {
-        return Err(cut_err("map length must > 0"));
+    if len < 0 {
+        return Err(cut_err("map length must >= 0"));
     }
-    let count = len as usize / 2;
-    for _ in 0..count {
+    for _ in 0..len {
         // key
-        terminated(take_until(0.., CRLF), CRLF)
-            .value(())
-            .parse_next(input)?;
+        nested_frame_len(input, depth + 1)?;
         // value
-        parse_frame_len(input)?;
+        nested_frame_len(input, depth + 1)?;
     }
     Ok(())
 }
@@ -0,0 +1,927 @@
+use std::{collections::HashMap, future::Future, sync::Arc, time::Instant};
+
+use anyhow::bail;
+use tokio::{io::AsyncWriteExt, net::TcpListener, task::JoinSet};
+use tracing::info;
+
+use crate::{
+    cluster::{bus, Cluster},
+    cmd::{hook::WriteHooks, plugin::Plugins, registry::CommandRenames},
+    config::ServerConfig,
+    lookup_command, network,
+    ratelimit::RateLimiter,
+    workers::WorkerPool,
+    Backend, CommandPlugin, WriteHook, SPILL_INTERVAL,
+};
+
+/// The port bound on every interface unless an address is set with [`ServerBuilder::addr`].
+const DEFAULT_PORT: u16 = 6379;
+
+const MAX_CLIENTS_ERROR: &[u8] = b"-ERR max number of clients reached\r\n";
+
+/// An embeddable R-Redis server.
+pub struct Server {
+    listeners: Vec<Listen>,
+    state: ServerState,
+}
+
+/// An address the server listens on.
+struct Listen {
+    addr: String,
+    /// Whether only the clients connecting from the loopback interface are served.
+    protected: bool,
+}
+
+pub struct ServerBuilder {
+    /// The address to bind, `None` until set, for [`ServerConfig::protected_mode`].
+    addr: Option<String>,
+    /// Addresses listened on besides `addr`.
+    extra_addrs: Vec<String>,
+    /// The port bound on every interface if `addr` isn't set.
+    port: u16,
+    backend: Backend,
+    config: ServerConfig,
+    plugins: Vec<Arc<dyn CommandPlugin>>,
+    hooks: Vec<Arc<dyn WriteHook>>,
+    renames: Vec<(String, String)>,
+}
+
+/// State shared by all the connections of a server.
+#[derive(Clone)]
+pub(crate) struct ServerState {
+    pub(crate) backend: Backend,
+    pub(crate) plugins: Plugins,
+    pub(crate) hooks: WriteHooks,
+    pub(crate) config: Arc<ServerConfig>,
+    pub(crate) renames: Arc<CommandRenames>,
+    pub(crate) limiter: Arc<RateLimiter>,
+    /// Whether only the clients connecting from the loopback interface are served.
+    pub(crate) protected: bool,
+    pub(crate) workers: Option<Arc<WorkerPool>>,
+}
+
+impl ServerState {
+    pub(crate) fn new(
+        backend: Backend,
+        plugins: Plugins,
+        hooks: WriteHooks,
+        renames: CommandRenames,
+        config: ServerConfig,
+    ) -> Self {
+        let limiter = RateLimiter::new(config.rate_limit.clone());
+        let workers = config.workers.map(|n| Arc::new(WorkerPool::new(n)));
+        Self {
+            backend,
+            plugins,
+            hooks,
+            renames: Arc::new(renames),
+            config: Arc::new(config),
+            limiter: Arc::new(limiter),
+            protected: false,
+            workers,
+        }
+    }
+}
+
+impl Server {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::default()
+    }
+
+    pub fn backend(&self) -> &Backend {
+        &self.state.backend
+    }
+
+    /// Bind the configured addresses and serve connections until an error occurs.
+    pub async fn run(self) -> anyhow::Result<()> {
+        self.run_until(std::future::pending()).await
+    }
+
+    /// Bind the configured addresses and serve connections until `shutdown`
+    /// completes, or an error occurs. Every listener stops accepting connections
+    /// once one of them fails.
+    pub async fn run_until(self, shutdown: impl Future<Output = ()>) -> anyhow::Result<()> {
+        let mut listeners = Vec::with_capacity(self.listeners.len());
+        for listen in &self.listeners {
+            let listener = TcpListener::bind(&listen.addr).await?;
+            info!("R-Redis is running on {}", listen.addr);
+            listeners.push((listener, listen.protected));
+        }
+        self.serve_listeners(listeners, shutdown).await
+    }
+
+    /// Serve connections accepted from an already bound listener.
+    pub async fn serve(self, listener: TcpListener) -> anyhow::Result<()> {
+        let protected = self.state.protected;
+        self.serve_listeners(vec![(listener, protected)], std::future::pending())
+            .await
+    }
+
+    /// Run the background tasks, and an accept loop for each listener sharing them.
+    async fn serve_listeners(
+        self,
+        listeners: Vec<(TcpListener, bool)>,
+        shutdown: impl Future<Output = ()>,
+    ) -> anyhow::Result<()> {
+        let backend = self.state.backend.clone();
+        let expire = tokio::spawn(async move {
+            loop {
+                backend.active_expire(Instant::now());
+                match backend.active_expire_delay(Instant::now()) {
+                    // more is due, let the clients run before the next batch.
+                    delay if delay.is_zero() => tokio::task::yield_now().await,
+                    delay => tokio::time::sleep(delay).await,
+                }
+            }
+        });
+        let backend = self.state.backend.clone();
+        let spill = backend.is_tiered().then(|| {
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(SPILL_INTERVAL).await;
+                    let backend = backend.clone();
+                    let _ = tokio::task::spawn_blocking(move || backend.spill_cold()).await;
+                }
+            })
+        });
+        let backend = self.state.backend.clone();
+        let optimize = self.state.config.optimize_interval.map(|interval| {
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(interval).await;
+                    let backend = backend.clone();
+                    let _ = tokio::task::spawn_blocking(move || backend.optimize_memory()).await;
+                }
+            })
+        });
+        let cluster_bus = match self.state.backend.cluster() {
+            Some(cluster) => {
+                let bus_listener =
+                    TcpListener::bind(("0.0.0.0", cluster.config().bus_port)).await?;
+                Some(tokio::spawn(bus::run(cluster.clone(), bus_listener)))
+            }
+            None => None,
+        };
+        let mut accept_loops = JoinSet::new();
+        for (listener, protected) in listeners {
+            let mut state = self.state.clone();
+            state.protected = protected;
+            accept_loops.spawn(accept_loop(listener, state));
+        }
+        let res = tokio::select! {
+            Some(res) = accept_loops.join_next() => res.map_err(anyhow::Error::from).and_then(|res| res),
+            _ = shutdown => Ok(()),
+        };
+        // the listeners are closed once this returns.
+        accept_loops.shutdown().await;
+        expire.abort();
+        for task in [spill, optimize].into_iter().flatten() {
+            task.abort();
+        }
+        if let Some(task) = cluster_bus {
+            task.abort();
+        }
+        res
+    }
+}
+
+async fn accept_loop(listener: TcpListener, state: ServerState) -> anyhow::Result<()> {
+    loop {
+        let (mut stream, socket_addr) = listener.accept().await?;
+        if let Some(max) = state.config.max_clients {
+            if state.backend.clients_len() >= max {
+                info!("Refused connection from {}: too many clients", socket_addr);
+                state.backend.stats.record_rejected_connection();
+                // the client is told why, without delaying the accept loop.
+                tokio::spawn(async move { stream.write_all(MAX_CLIENTS_ERROR).await });
+                continue;
+            }
+        }
+        info!("Accepted connection from {}", socket_addr);
+        let state = state.clone();
+        tokio::spawn(async move {
+            match network::serve_stream(stream, state).await {
+                Ok(_) => {
+                    info!("Connection from {} exited", socket_addr);
+                }
+                Err(e) => {
+                    info!("Error handling connection from {}: {}", socket_addr, e);
+                }
+            }
+        });
+    }
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        Self {
+            addr: None,
+            extra_addrs: Vec::new(),
+            port: DEFAULT_PORT,
+            backend: Backend::new(),
+            config: ServerConfig::default(),
+            plugins: Vec::new(),
+            hooks: Vec::new(),
+            renames: Vec::new(),
+        }
+    }
+}
+
+impl ServerBuilder {
+    pub fn addr(mut self, addr: impl Into<String>) -> Self {
+        self.addr = Some(addr.into());
+        self
+    }
+
+    /// Listen on every interface on `port` rather than 6379, unless an address is set.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Also listen on `addr`, e.g. on a port only reachable from an internal network.
+    /// Connections of every listener share the backend and the settings.
+    pub fn listen(mut self, addr: impl Into<String>) -> Self {
+        self.extra_addrs.push(addr.into());
+        self
+    }
+
+    /// Serve an existing backend, e.g. one shared with the embedding application.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn config(mut self, config: ServerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Register a custom command.
+    pub fn plugin(mut self, plugin: impl CommandPlugin) -> Self {
+        self.plugins.push(Arc::new(plugin));
+        self
+    }
+
+    /// Register a hook run around every mutating command, see [`WriteHook`].
+    pub fn write_hook(mut self, hook: impl WriteHook) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Accept the builtin command `name` as `new_name` only, or not at all if
+    /// `new_name` is empty, like the rename-command directive of Redis.
+    pub fn rename_command(mut self, name: impl Into<String>, new_name: impl Into<String>) -> Self {
+        self.renames.push((name.into(), new_name.into()));
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<Server> {
+        let mut plugins = HashMap::new();
+        for plugin in self.plugins {
+            let name = plugin.name().to_ascii_lowercase();
+            if lookup_command(name.as_bytes()).is_some() {
+                bail!("plugin command '{}' conflicts with a builtin command", name);
+            }
+            if plugins.insert(name.clone(), plugin).is_some() {
+                bail!("plugin command '{}' is registered twice", name);
+            }
+        }
+        let mut renames = CommandRenames::default();
+        let mut new_names = HashMap::new();
+        for (name, new_name) in self.renames {
+            let Some(spec) = lookup_command(name.as_bytes()) else {
+                bail!("cannot rename '{}', it is not a builtin command", name);
+            };
+            let new_name = new_name.to_ascii_lowercase();
+            if !new_name.is_empty() {
+                if lookup_command(new_name.as_bytes()).is_some() || plugins.contains_key(&new_name)
+                {
+                    bail!(
+                        "cannot rename '{}' to '{}', which is already a command",
+                        name,
+                        new_name
+                    );
+                }
+                if new_names.insert(new_name.clone(), spec.name).is_some() {
+                    bail!("two commands are renamed to '{}'", new_name);
+                }
+            }
+            renames.rename(spec, &new_name);
+        }
+        if let Some(config) = &self.config.cluster {
+            let cluster = Cluster::open(config.clone())?;
+            if !self.backend.enable_cluster(Arc::new(cluster)) {
+                bail!("the backend is already served in cluster mode");
+            }
+        }
+        // like Redis without an explicit bind directive.
+        let mut listeners = vec![match self.addr {
+            Some(addr) => Listen {
+                addr,
+                protected: false,
+            },
+            None => Listen {
+                addr: format!("0.0.0.0:{}", self.port),
+                protected: self.config.protected_mode,
+            },
+        }];
+        listeners.extend(self.extra_addrs.into_iter().map(|addr| Listen {
+            addr,
+            protected: false,
+        }));
+        let mut state = ServerState::new(
+            self.backend,
+            Plugins::new(plugins),
+            WriteHooks::new(self.hooks),
+            renames,
+            self.config,
+        );
+        state.protected = listeners[0].protected;
+        Ok(Server { listeners, state })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        time::timeout,
+    };
+
+    use std::time::Duration;
+
+    use crate::{BulkString, ClusterConfig, CommandError, RespFrame, SlowlogConfig, WriteCommand};
+
+    use super::*;
+
+    struct Hello;
+
+    impl CommandPlugin for Hello {
+        fn name(&self) -> &str {
+            "HELLO.WORLD"
+        }
+
+        fn arity(&self) -> i64 {
+            -1
+        }
+
+        fn execute(&self, args: Vec<RespFrame>, _backend: &Backend) -> RespFrame {
+            BulkString::new(format!("hello {} args", args.len())).into()
+        }
+    }
+
+    #[test]
+    fn test_protected_without_bind_address() -> anyhow::Result<()> {
+        assert!(Server::builder().build()?.state.protected);
+        assert!(
+            !Server::builder()
+                .addr("127.0.0.1:6379")
+                .build()?
+                .state
+                .protected
+        );
+        let config = ServerConfig {
+            protected_mode: false,
+            ..Default::default()
+        };
+        assert!(!Server::builder().config(config).build()?.state.protected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_listeners_share_the_backend() -> anyhow::Result<()> {
+        let public = TcpListener::bind("127.0.0.1:0").await?;
+        let internal = TcpListener::bind("127.0.0.1:0").await?;
+        let addrs = [public.local_addr()?, internal.local_addr()?];
+        let server = Server::builder().build()?;
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let listeners = vec![(public, true), (internal, false)];
+        let running = tokio::spawn(server.serve_listeners(listeners, async {
+            let _ = stopped.await;
+        }));
+
+        let mut stream = TcpStream::connect(addrs[0]).await?;
+        assert_eq!(
+            request(&mut stream, &["set", "foo", "bar"]).await?,
+            "+OK\r\n"
+        );
+        let mut stream = TcpStream::connect(addrs[1]).await?;
+        assert_eq!(
+            request(&mut stream, &["get", "foo"]).await?,
+            "$3\r\nbar\r\n"
+        );
+
+        // both listeners stop accepting connections on shutdown.
+        let _ = stop.send(());
+        timeout(Duration::from_secs(1), running).await???;
+        for addr in addrs {
+            assert!(TcpStream::connect(addr).await.is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_rejects_conflicting_renames() {
+        let build = |renames: &[(&str, &str)]| {
+            let mut builder = Server::builder().plugin(Hello);
+            for (name, new_name) in renames {
+                builder = builder.rename_command(*name, *new_name);
+            }
+            builder.build()
+        };
+        assert!(build(&[("debug", ""), ("get", "fetch")]).is_ok());
+        assert!(build(&[("unknown", "")]).is_err());
+        assert!(build(&[("get", "set")]).is_err());
+        assert!(build(&[("get", "hello.world")]).is_err());
+        assert!(build(&[("get", "fetch"), ("set", "FETCH")]).is_err());
+    }
+
+    #[test]
+    fn test_build_rejects_conflicting_plugins() {
+        struct Get;
+        impl CommandPlugin for Get {
+            fn name(&self) -> &str {
+                "get"
+            }
+            fn arity(&self) -> i64 {
+                2
+            }
+            fn execute(&self, _args: Vec<RespFrame>, _backend: &Backend) -> RespFrame {
+                unreachable!()
+            }
+        }
+
+        assert!(Server::builder().plugin(Get).build().is_err());
+        assert!(Server::builder()
+            .plugin(Hello)
+            .plugin(Hello)
+            .build()
+            .is_err());
+        assert!(Server::builder().plugin(Hello).build().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_server_dispatches_plugins() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = Server::builder().plugin(Hello).build()?;
+        tokio::spawn(server.serve(listener));
+
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"*2\r\n$11\r\nhello.world\r\n$1\r\na\r\n")
+            .await?;
+        let mut buf = [0; 64];
+        let n = stream.read(&mut buf).await?;
+        assert_eq!(&buf[..n], b"$12\r\nhello 1 args\r\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_server_runs_write_hooks() -> anyhow::Result<()> {
+        struct DenyAll;
+
+        impl WriteHook for DenyAll {
+            fn before_write(
+                &self,
+                cmd: &WriteCommand,
+                _backend: &Backend,
+            ) -> Result<(), CommandError> {
+                Err(CommandError::InvalidCommand(format!(
+                    "{} is denied",
+                    cmd.spec.name
+                )))
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = Server::builder().write_hook(DenyAll).build()?;
+        let backend = server.backend().clone();
+        tokio::spawn(server.serve(listener));
+
+        let mut stream = TcpStream::connect(addr).await?;
+        let mut buf = [0; 64];
+        stream
+            .write_all(b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n")
+            .await?;
+        let n = stream.read(&mut buf).await?;
+        assert_eq!(&buf[..n], b"-ERR set is denied\r\n");
+        assert_eq!(backend.get("k"), None);
+
+        // reads are not hooked.
+        stream.write_all(b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n").await?;
+        let n = stream.read(&mut buf).await?;
+        assert_eq!(&buf[..n], b"$-1\r\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commands_over_budget_are_logged() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let config = ServerConfig {
+            slowlog: SlowlogConfig {
+                log_slower_than: Duration::from_secs(60),
+                budget: Some(Duration::ZERO),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let server = Server::builder().config(config).build()?;
+        let backend = server.backend().clone();
+        tokio::spawn(server.serve(listener));
+
+        let mut stream = TcpStream::connect(addr).await?;
+        let mut buf = [0; 5];
+        stream.write_all(b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n").await?;
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"$-1\r\n");
+        let entries = backend.slowlog_get(None);
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].over_budget);
+        assert_eq!(
+            entries[0].args,
+            vec![BulkString::new("get"), BulkString::new("k")]
+        );
+        assert_eq!(entries[0].client_addr, stream.local_addr()?.to_string());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commands_run_on_workers() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let config = ServerConfig {
+            workers: Some(4),
+            ..Default::default()
+        };
+        let server = Server::builder().config(config).build()?;
+        tokio::spawn(server.serve(listener));
+
+        let mut stream = TcpStream::connect(addr).await?;
+        // pipelined commands on several keys, replied to in order.
+        stream
+            .write_all(
+                b"*3\r\n$3\r\nset\r\n$3\r\nfoo\r\n$1\r\n1\r\n\
+                  *3\r\n$3\r\nset\r\n$3\r\nbar\r\n$1\r\n2\r\n\
+                  *2\r\n$3\r\nget\r\n$3\r\nfoo\r\n\
+                  *2\r\n$3\r\nget\r\n$3\r\nbar\r\n\
+                  *2\r\n$4\r\necho\r\n$2\r\nhi\r\n",
+            )
+            .await?;
+        let expected = b"+OK\r\n+OK\r\n$1\r\n1\r\n$1\r\n2\r\n+hi\r\n";
+        let mut buf = vec![0; expected.len()];
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(buf, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transactions_are_isolated() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let config = ServerConfig {
+            workers: Some(4),
+            ..Default::default()
+        };
+        let server = Server::builder().config(config).build()?;
+        tokio::spawn(server.serve(listener));
+
+        let writer = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await?;
+            for _ in 0..200 {
+                stream
+                    .write_all(
+                        b"*1\r\n$5\r\nmulti\r\n\
+                          *4\r\n$7\r\nhincrby\r\n$1\r\nh\r\n$1\r\na\r\n$1\r\n1\r\n\
+                          *4\r\n$7\r\nhincrby\r\n$1\r\nh\r\n$1\r\nb\r\n$1\r\n1\r\n\
+                          *1\r\n$4\r\nexec\r\n",
+                    )
+                    .await?;
+                // +OK, two +QUEUED and the array of the two counters.
+                let mut reply = Vec::new();
+                while reply.windows(2).filter(|w| w == b"\r\n").count() < 6 {
+                    let mut buf = [0; 64];
+                    let n = stream.read(&mut buf).await?;
+                    anyhow::ensure!(n > 0, "connection closed");
+                    reply.extend_from_slice(&buf[..n]);
+                }
+            }
+            anyhow::Ok(())
+        });
+
+        let mut stream = TcpStream::connect(addr).await?;
+        while !writer.is_finished() {
+            // the other connection's commands never run between those of its transaction.
+            let reply = request(&mut stream, &["hmget", "h", "a", "b"]).await?;
+            let values: Vec<_> = reply
+                .split("\r\n")
+                .filter(|line| !line.starts_with(['*', '$']) || *line == "$-1")
+                .collect();
+            // the fields and their values.
+            assert_eq!(values.get(1), values.get(3), "{:?}", reply);
+        }
+        writer.await??;
+        assert_eq!(
+            request(&mut stream, &["hmget", "h", "a", "b"]).await?,
+            "*4\r\n$1\r\na\r\n$3\r\n200\r\n$1\r\nb\r\n$3\r\n200\r\n"
+        );
+        Ok(())
+    }
+
+    async fn cluster_node(name: &str) -> anyhow::Result<(std::net::SocketAddr, u16, Backend)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let bus_port = TcpListener::bind("127.0.0.1:0").await?.local_addr()?.port();
+        let config_file =
+            std::env::temp_dir().join(format!("rredis-nodes-{}-{}.conf", name, std::process::id()));
+        let _ = std::fs::remove_file(&config_file);
+        let config = ServerConfig {
+            cluster: Some(ClusterConfig {
+                bus_port,
+                ping_interval: Duration::from_millis(20),
+                ..ClusterConfig::new(addr.to_string(), config_file)
+            }),
+            ..Default::default()
+        };
+        let server = Server::builder().config(config).build()?;
+        let backend = server.backend().clone();
+        tokio::spawn(server.serve(listener));
+        Ok((addr, bus_port, backend))
+    }
+
+    async fn request(stream: &mut TcpStream, args: &[&str]) -> anyhow::Result<String> {
+        let mut req = format!("*{}\r\n", args.len());
+        for arg in args {
+            req.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+        stream.write_all(req.as_bytes()).await?;
+        let mut buf = vec![0; 256];
+        let n = stream.read(&mut buf).await?;
+        Ok(String::from_utf8_lossy(&buf[..n]).into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_cluster_nodes_converge() -> anyhow::Result<()> {
+        let (addr1, _, backend1) = cluster_node("one").await?;
+        let (addr2, bus_port2, backend2) = cluster_node("two").await?;
+        let mut client1 = TcpStream::connect(addr1).await?;
+        let mut client2 = TcpStream::connect(addr2).await?;
+
+        let port2 = addr2.port().to_string();
+        let bus_port2 = bus_port2.to_string();
+        let reply = request(
+            &mut client1,
+            &["cluster", "meet", "127.0.0.1", &port2, &bus_port2],
+        )
+        .await?;
+        assert_eq!(reply, "+OK\r\n");
+        // the slot of bar.
+        let reply = request(&mut client2, &["cluster", "addslots", "5061"]).await?;
+        assert_eq!(reply, "+OK\r\n");
+
+        let cluster1 = backend1.cluster().unwrap().clone();
+        let cluster2 = backend2.cluster().unwrap().clone();
+        let converged = async {
+            loop {
+                let known = |nodes: Vec<crate::ClusterNode>| {
+                    nodes.len() == 2 && nodes.iter().all(|node| !node.handshake)
+                };
+                if known(cluster1.nodes())
+                    && known(cluster2.nodes())
+                    && cluster1.slot_owner(5061).as_deref() == Some(cluster2.myid())
+                {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), converged).await?;
+
+        let reply = request(&mut client1, &["get", "bar"]).await?;
+        assert_eq!(reply, format!("-MOVED 5061 {}\r\n", addr2));
+        let reply = request(&mut client2, &["set", "bar", "1"]).await?;
+        assert_eq!(reply, "+OK\r\n");
+        let reply = request(&mut client1, &["get", "foo"]).await?;
+        assert_eq!(reply, "-CLUSTERDOWN Hash slot not served\r\n");
+        let reply = request(&mut client1, &["cluster", "keyslot", "foo"]).await?;
+        assert_eq!(reply, ":12182\r\n");
+
+        let reply = request(&mut client1, &["cluster", "forget", cluster2.myid()]).await?;
+        assert_eq!(reply, "+OK\r\n");
+        assert_eq!(cluster1.nodes().len(), 1);
+        assert_eq!(cluster1.slot_owner(5061), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cluster_cross_slot() -> anyhow::Result<()> {
+        let (addr, _, _backend) = cluster_node("cross").await?;
+        let mut client = TcpStream::connect(addr).await?;
+        let slots: Vec<String> = (0..crate::CLUSTER_SLOTS).map(|s| s.to_string()).collect();
+        let mut args = vec!["cluster", "addslots"];
+        args.extend(slots.iter().map(String::as_str));
+        assert_eq!(request(&mut client, &args).await?, "+OK\r\n");
+
+        request(&mut client, &["set", "foo", "1"]).await?;
+        let reply = request(&mut client, &["rename", "foo", "bar"]).await?;
+        assert!(reply.starts_with("-CROSSSLOT "), "{}", reply);
+
+        // key:41928 is in the slot of foo.
+        let reply = request(&mut client, &["rename", "foo", "key:41928"]).await?;
+        assert_eq!(reply, "+OK\r\n");
+
+        request(&mut client, &["set", "{user}.a", "1"]).await?;
+        let reply = request(&mut client, &["rename", "{user}.a", "{user}.b"]).await?;
+        assert_eq!(reply, "+OK\r\n");
+
+        // the keys found in the arguments, rather than at fixed positions.
+        let reply = request(&mut client, &["sintercard", "2", "foo", "bar"]).await?;
+        assert!(reply.starts_with("-CROSSSLOT "), "{}", reply);
+
+        // the keys of a transaction must share a slot too.
+        assert_eq!(request(&mut client, &["multi"]).await?, "+OK\r\n");
+        let reply = request(&mut client, &["set", "{user}.c", "2"]).await?;
+        assert_eq!(reply, "+QUEUED\r\n");
+        let reply = request(&mut client, &["get", "foo"]).await?;
+        assert!(reply.starts_with("-CROSSSLOT "), "{}", reply);
+        let reply = request(&mut client, &["exec"]).await?;
+        assert!(reply.starts_with("-EXECABORT "), "{}", reply);
+        assert_eq!(request(&mut client, &["get", "{user}.c"]).await?, "$-1\r\n");
+        Ok(())
+    }
+
+    // MIGRATE blocks its thread until the target replied.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_slot_migration() -> anyhow::Result<()> {
+        let (addr1, _, backend1) = cluster_node("source").await?;
+        let (addr2, bus_port2, backend2) = cluster_node("target").await?;
+        let cluster1 = backend1.cluster().unwrap().clone();
+        let cluster2 = backend2.cluster().unwrap().clone();
+        let (id1, id2) = (cluster1.myid(), cluster2.myid());
+        let mut client1 = TcpStream::connect(addr1).await?;
+        let mut client2 = TcpStream::connect(addr2).await?;
+
+        let port2 = addr2.port().to_string();
+        request(
+            &mut client1,
+            &[
+                "cluster",
+                "meet",
+                "127.0.0.1",
+                &port2,
+                &bus_port2.to_string(),
+            ],
+        )
+        .await?;
+        request(&mut client1, &["cluster", "addslots", "5061"]).await?;
+        let known = async {
+            while cluster2.slot_owner(5061).as_deref() != Some(id1)
+                || cluster1.nodes().iter().any(|node| node.handshake)
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), known).await?;
+        assert_eq!(
+            request(&mut client1, &["set", "bar", "1"]).await?,
+            "+OK\r\n"
+        );
+
+        let reply = request(
+            &mut client2,
+            &["cluster", "setslot", "5061", "importing", id1],
+        )
+        .await?;
+        assert_eq!(reply, "+OK\r\n");
+        let reply = request(
+            &mut client1,
+            &["cluster", "setslot", "5061", "migrating", id2],
+        )
+        .await?;
+        assert_eq!(reply, "+OK\r\n");
+        // keys not moved yet are still served by the source.
+        assert_eq!(request(&mut client1, &["get", "bar"]).await?, "$1\r\n1\r\n");
+
+        let reply = request(
+            &mut client1,
+            &["migrate", "127.0.0.1", &port2, "bar", "0", "1000"],
+        )
+        .await?;
+        assert_eq!(reply, "+OK\r\n");
+        assert!(!backend1.exists("bar"));
+        assert_eq!(
+            request(&mut client1, &["get", "bar"]).await?,
+            format!("-ASK 5061 {}\r\n", addr2)
+        );
+        assert_eq!(
+            request(&mut client2, &["get", "bar"]).await?,
+            format!("-MOVED 5061 {}\r\n", addr1)
+        );
+        assert_eq!(request(&mut client2, &["asking"]).await?, "+OK\r\n");
+        assert_eq!(request(&mut client2, &["get", "bar"]).await?, "$1\r\n1\r\n");
+
+        request(&mut client2, &["cluster", "setslot", "5061", "node", id2]).await?;
+        request(&mut client1, &["cluster", "setslot", "5061", "node", id2]).await?;
+        assert_eq!(request(&mut client2, &["get", "bar"]).await?, "$1\r\n1\r\n");
+        assert_eq!(
+            request(&mut client1, &["get", "bar"]).await?,
+            format!("-MOVED 5061 {}\r\n", addr2)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_info_describes_the_connection() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = Server::builder().build()?;
+        let backend = server.backend().clone();
+        tokio::spawn(server.serve(listener));
+
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"*2\r\n$6\r\nclient\r\n$4\r\ninfo\r\n")
+            .await?;
+        let mut buf = vec![0; 512];
+        let n = stream.read(&mut buf).await?;
+        let reply = String::from_utf8_lossy(&buf[..n]);
+        let local = stream.local_addr()?.to_string();
+        assert!(reply.starts_with('$'));
+        assert!(reply.contains(&format!(" addr={} name= ", local)));
+        assert!(reply.contains(" flags=N db=0 sub=0 psub=0 ssub=0 resp=2 lib-name=\n"));
+        assert_eq!(backend.clients_len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reads_carry_freshness_attributes() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = Server::builder().build()?;
+        let backend = server.backend().clone();
+        tokio::spawn(server.serve(listener));
+        backend.set("k".to_string(), b"v".to_vec());
+
+        let mut stream = TcpStream::connect(addr).await?;
+        // not asked for yet.
+        stream.write_all(b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n").await?;
+        let mut buf = [0; 7];
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"$1\r\nv\r\n");
+
+        stream
+            .write_all(
+                b"*3\r\n$6\r\nclient\r\n$10\r\nattributes\r\n$2\r\non\r\n\
+                  *2\r\n$3\r\nget\r\n$1\r\nk\r\n\
+                  *2\r\n$3\r\nget\r\n$7\r\nmissing\r\n",
+            )
+            .await?;
+        let expected = b"+OK\r\n|1\r\n+pttl\r\n:-1\r\n$1\r\nv\r\n$-1\r\n";
+        let mut buf = vec![0; expected.len()];
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(buf, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_switches_to_resp3() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = Server::builder().build()?;
+        let backend = server.backend().clone();
+        tokio::spawn(server.serve(listener));
+        backend
+            .zadd("z", vec![(1.5, BulkString::new("m"))])
+            .unwrap();
+
+        let mut stream = TcpStream::connect(addr).await?;
+        let queries = b"*3\r\n$6\r\nzscore\r\n$1\r\nz\r\n$1\r\nm\r\n\
+                        *3\r\n$6\r\nzscore\r\n$1\r\nz\r\n$1\r\nx\r\n\
+                        *3\r\n$9\r\nsismember\r\n$1\r\ns\r\n$1\r\nm\r\n";
+        stream.write_all(queries).await?;
+        let expected = b"$3\r\n1.5\r\n$-1\r\n:0\r\n";
+        let mut buf = vec![0; expected.len()];
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(buf, expected);
+
+        // the length of the reply to HELLO varies, read until the replies which follow it.
+        stream
+            .write_all(b"*2\r\n$5\r\nhello\r\n$1\r\n3\r\n")
+            .await?;
+        stream.write_all(queries).await?;
+        let expected = b",+1.5\r\n_\r\n#f\r\n";
+        let mut buf = Vec::new();
+        while !buf.ends_with(expected) {
+            let mut chunk = [0; 256];
+            let n = timeout(Duration::from_secs(5), stream.read(&mut chunk)).await??;
+            anyhow::ensure!(n > 0, "connection closed");
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        assert!(buf.starts_with(b"%7\r\n$6\r\nserver\r\n$5\r\nredis\r\n"));
+        Ok(())
+    }
+}
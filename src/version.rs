@@ -0,0 +1,77 @@
+//! What the running server was built from.
+
+/// Version of the crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Abbreviated hash of the commit built, `00000000` outside of a git checkout.
+pub const GIT_SHA: &str = env!("RREDIS_GIT_SHA");
+/// Whether tracked files had uncommitted changes when built.
+pub const GIT_DIRTY: bool = matches!(env!("RREDIS_GIT_DIRTY").as_bytes(), b"1");
+
+/// The optional features the server was built with.
+pub fn features() -> Vec<&'static str> {
+    let features = [
+        ("json", cfg!(feature = "json")),
+        ("jemalloc", cfg!(feature = "jemalloc")),
+    ];
+    features
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect()
+}
+
+/// The allocator serving the memory of the server.
+pub fn allocator() -> &'static str {
+    if cfg!(feature = "jemalloc") {
+        "jemalloc"
+    } else {
+        "libc"
+    }
+}
+
+/// One line describing the build, like `redis-server --version`.
+pub fn version_line() -> String {
+    format!(
+        "R-Redis server v={} sha={}:{} malloc={} bits={} features={}",
+        VERSION,
+        GIT_SHA,
+        u8::from(GIT_DIRTY),
+        allocator(),
+        usize::BITS,
+        features().join(",")
+    )
+}
+
+/// The banner printed when the server starts.
+pub fn banner(addr: &str, mode: &str) -> String {
+    format!(
+        r"
+     ____       ____           _ _
+    |  _ \     |  _ \ ___  __| (_)___     R-Redis {} ({}/{}) {} bit
+    | |_) |____| |_) / _ \/ _` | / __|
+    |  _ <_____|  _ <  __/ (_| | \__ \    Running in {} mode
+    |_| \_\    |_| \_\___|\__,_|_|___/    Address: {}
+                                          PID: {}
+",
+        VERSION,
+        GIT_SHA,
+        u8::from(GIT_DIRTY),
+        usize::BITS,
+        mode,
+        addr,
+        std::process::id()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_line() {
+        let line = version_line();
+        assert!(line.starts_with(&format!("R-Redis server v={} sha=", VERSION)));
+        assert_eq!(GIT_SHA.len(), 8);
+        assert_eq!(features().contains(&"json"), cfg!(feature = "json"));
+        assert!(banner("0.0.0.0:6379", "standalone").contains("Running in standalone mode"));
+    }
+}
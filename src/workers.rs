@@ -0,0 +1,105 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc::{self, Sender},
+    thread,
+};
+
+use anyhow::anyhow;
+use tokio::sync::oneshot;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Threads executing commands on behalf of the connections, see
+/// [`ServerConfig::workers`](crate::ServerConfig::workers).
+///
+/// A command runs on the worker of the slot of its first key: the commands on a key
+/// run in the order they were queued, while commands on other keys run in parallel.
+/// Workers exit once the pool is dropped and their queue is drained.
+pub(crate) struct WorkerPool {
+    queues: Vec<Sender<Job>>,
+}
+
+impl WorkerPool {
+    pub(crate) fn new(workers: usize) -> Self {
+        let queues = (0..workers.max(1))
+            .map(|i| {
+                let (tx, rx) = mpsc::channel::<Job>();
+                thread::Builder::new()
+                    .name(format!("rredis-worker-{}", i))
+                    .spawn(move || {
+                        for job in rx {
+                            // a panicking command fails alone, its worker keeps serving.
+                            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                        }
+                    })
+                    .expect("failed to spawn a worker thread");
+                tx
+            })
+            .collect();
+        Self { queues }
+    }
+
+    /// Run `f` on the worker of `slot`, once the jobs queued on it before have run.
+    pub(crate) async fn run<T: Send + 'static>(
+        &self,
+        slot: u16,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> anyhow::Result<T> {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = tx.send(f());
+        });
+        self.queues[slot as usize % self.queues.len()]
+            .send(job)
+            .map_err(|_| anyhow!("the worker exited"))?;
+        rx.await.map_err(|_| anyhow!("the command panicked"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_jobs_of_a_slot_run_in_order() -> anyhow::Result<()> {
+        let pool = Arc::new(WorkerPool::new(4));
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut replies = Vec::new();
+        for i in 0..10 {
+            let order = order.clone();
+            let pool = pool.clone();
+            // queued in order, the first jobs being the slowest.
+            let job = move || {
+                thread::sleep(Duration::from_millis(10 - i));
+                order.lock().unwrap().push(i);
+                i
+            };
+            let (tx, rx) = oneshot::channel();
+            pool.queues[7 % 4]
+                .send(Box::new(move || {
+                    let _ = tx.send(job());
+                }))
+                .unwrap();
+            replies.push(rx);
+        }
+        for (i, reply) in replies.into_iter().enumerate() {
+            assert_eq!(reply.await?, i as u64);
+        }
+        assert_eq!(*order.lock().unwrap(), (0..10).collect::<Vec<_>>());
+        assert_eq!(pool.run(7, || "done").await?, "done");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_worker_survives_panics() -> anyhow::Result<()> {
+        let pool = WorkerPool::new(1);
+        assert!(pool.run::<()>(0, || panic!("boom")).await.is_err());
+        assert_eq!(pool.run(0, || 1).await?, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+// each test crate only uses some of the helpers.
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+
+use redis::{aio::MultiplexedConnection, Client};
+use rredis::{Server, ServerBuilder, ServerConfig};
+use tokio::net::TcpListener;
+
+/// A server running inside the test process on an ephemeral port.
+pub struct TestServer {
+    addr: SocketAddr,
+    client: Client,
+}
+
+impl TestServer {
+    pub async fn start() -> anyhow::Result<Self> {
+        Self::start_with(ServerConfig::default()).await
+    }
+
+    pub async fn start_with(config: ServerConfig) -> anyhow::Result<Self> {
+        Self::build(Server::builder().config(config)).await
+    }
+
+    pub async fn build(builder: ServerBuilder) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = builder.build()?;
+        tokio::spawn(server.serve(listener));
+        let client = Client::open(format!("redis://{}/", addr))?;
+        Ok(Self { addr, client })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Open a new client connection.
+    pub async fn connect(&self) -> anyhow::Result<MultiplexedConnection> {
+        Ok(self.client.get_multiplexed_async_connection().await?)
+    }
+}
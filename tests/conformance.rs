@@ -0,0 +1,179 @@
+//! Compatibility with real Redis, checked against the transcripts in `tests/conformance/`.
+//!
+//! A transcript is a sequence of commands, each prefixed by `> `, followed by
+//! the reply real Redis gives, rendered like redis-cli does. Arguments are
+//! separated by spaces and may be double-quoted, with the escapes redis-cli prints.
+//! Lines starting with `#` are comments. Every transcript runs on a fresh server.
+//!
+//! Known differences are marked by a `# todo: <reason>` comment before the command:
+//! they don't fail the suite, but must be unmarked once they match Redis.
+//!
+//!     > SET key value
+//!     OK
+//!     > GET key
+//!     "value"
+
+mod common;
+
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail};
+use bytes::BytesMut;
+use rredis::{err::RespError, BulkString, RespArray, RespDecodeV2, RespEncode, RespFrame};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use common::TestServer;
+
+struct Case {
+    line: usize,
+    command: String,
+    args: Vec<Vec<u8>>,
+    expected: String,
+    todo: bool,
+}
+
+#[tokio::test]
+async fn test_conformance() -> anyhow::Result<()> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    paths.sort();
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let cases = parse_transcript(&fs::read_to_string(&path)?)
+            .map_err(|e| anyhow!("{}: {}", name, e))?;
+        let server = TestServer::start().await?;
+        let mut stream = TcpStream::connect(server.addr()).await?;
+        let mut buf = BytesMut::new();
+        for case in cases {
+            let reply = query(&mut stream, &mut buf, case.args).await?;
+            let actual = reply.to_string();
+            if case.todo && actual == case.expected {
+                failures.push(format!(
+                    "{}:{}: > {}\nmatches Redis now, remove its todo marker",
+                    name, case.line, case.command
+                ));
+            } else if !case.todo && actual != case.expected {
+                failures.push(format!(
+                    "{}:{}: > {}\nexpected:\n{}\nactual:\n{}",
+                    name, case.line, case.command, case.expected, actual
+                ));
+            }
+        }
+    }
+    if !failures.is_empty() {
+        bail!(
+            "{} conformance cases failed:\n\n{}",
+            failures.len(),
+            failures.join("\n\n")
+        );
+    }
+    Ok(())
+}
+
+async fn query(
+    stream: &mut TcpStream,
+    buf: &mut BytesMut,
+    args: Vec<Vec<u8>>,
+) -> anyhow::Result<RespFrame> {
+    let args: Vec<RespFrame> = args
+        .into_iter()
+        .map(|a| BulkString::new(a).into())
+        .collect();
+    stream
+        .write_all(&RespFrame::from(RespArray::new(args)).encode())
+        .await?;
+    loop {
+        match RespFrame::decode(buf) {
+            Ok(frame) => return Ok(frame),
+            Err(RespError::NotCompleted(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+        if stream.read_buf(buf).await? == 0 {
+            bail!("connection closed");
+        }
+    }
+}
+
+fn parse_transcript(text: &str) -> anyhow::Result<Vec<Case>> {
+    let mut cases: Vec<Case> = Vec::new();
+    let mut todo = false;
+    for (i, line) in text.lines().enumerate() {
+        if line.starts_with("# todo:") {
+            todo = true;
+            continue;
+        }
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        if let Some(command) = line.strip_prefix("> ") {
+            let args = split_args(command).map_err(|e| anyhow!("line {}: {}", i + 1, e))?;
+            cases.push(Case {
+                line: i + 1,
+                command: command.to_string(),
+                args,
+                expected: String::new(),
+                todo,
+            });
+            todo = false;
+            continue;
+        }
+        let Some(case) = cases.last_mut() else {
+            bail!("line {}: reply without a command", i + 1);
+        };
+        if !case.expected.is_empty() {
+            case.expected.push('\n');
+        }
+        case.expected.push_str(line);
+    }
+    if let Some(case) = cases.iter().find(|case| case.expected.is_empty()) {
+        bail!("line {}: command without a reply", case.line);
+    }
+    Ok(cases)
+}
+
+/// Split a command line into arguments, handling double-quoted ones.
+fn split_args(line: &str) -> anyhow::Result<Vec<Vec<u8>>> {
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(first) = chars.next() else {
+            return Ok(args);
+        };
+        let mut arg = Vec::new();
+        if first != '"' {
+            arg.extend_from_slice(first.encode_utf8(&mut [0; 4]).as_bytes());
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                arg.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            args.push(arg);
+            continue;
+        }
+        loop {
+            match chars.next() {
+                None => bail!("unterminated quoted argument"),
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('n') => arg.push(b'\n'),
+                    Some('r') => arg.push(b'\r'),
+                    Some('t') => arg.push(b'\t'),
+                    Some('x') => {
+                        let hex: String = chars.by_ref().take(2).collect();
+                        arg.push(u8::from_str_radix(&hex, 16)?);
+                    }
+                    Some(c) => arg.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                    None => bail!("unterminated escape"),
+                },
+                Some(c) => arg.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        args.push(arg);
+    }
+}
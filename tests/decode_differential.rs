@@ -0,0 +1,112 @@
+//! Differential tests of the v1 decoder, [`RespDecode`], against the v2 one, [`RespDecodeV2`].
+//!
+//! Valid frames are generated as bytes, in the forms clients and servers send
+//! rather than only the ones this crate encodes, e.g. `:+1` or `,1e10`. Both
+//! decoders must decode them to the same frame, consuming the same length.
+
+use bytes::BytesMut;
+use proptest::{collection::vec, prelude::*};
+use rredis::{err::RespError, RespDecode, RespDecodeV2, RespFrame};
+
+const MAX_DEPTH: u32 = 4;
+
+fn line(prefix: char, content: impl AsRef<[u8]>) -> Vec<u8> {
+    let mut buf = vec![prefix as u8];
+    buf.extend_from_slice(content.as_ref());
+    buf.extend_from_slice(b"\r\n");
+    buf
+}
+
+fn aggregate(prefix: char, len: usize, items: Vec<Vec<u8>>) -> Vec<u8> {
+    let mut buf = line(prefix, len.to_string());
+    buf.extend(items.into_iter().flatten());
+    buf
+}
+
+fn simple_frame() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        "[^\r\n]{0,16}".prop_map(|s| line('+', s)),
+        "[^\r\n]{0,16}".prop_map(|s| line('-', format!("ERR {}", s))),
+        any::<i64>().prop_map(|i| line(':', i.to_string())),
+        (0..1000i64).prop_map(|i| line(':', format!("+{}", i))),
+        vec(any::<u8>(), 0..32).prop_map(|data| {
+            let mut buf = line('$', data.len().to_string());
+            buf.extend_from_slice(&data);
+            buf.extend_from_slice(b"\r\n");
+            buf
+        }),
+        Just(b"$-1\r\n".to_vec()),
+        Just(b"*-1\r\n".to_vec()),
+        Just(b"_\r\n".to_vec()),
+        any::<bool>().prop_map(|b| line('#', if b { "t" } else { "f" })),
+        double(),
+    ]
+}
+
+fn double() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        any::<f64>().prop_map(|v| line(',', v.to_string())),
+        any::<f64>().prop_map(|v| line(',', format!("{:e}", v))),
+        any::<i32>().prop_map(|v| line(',', v.to_string())),
+        prop_oneof![Just("inf"), Just("-inf"), Just("nan")].prop_map(|v| line(',', v)),
+    ]
+}
+
+fn frame() -> impl Strategy<Value = Vec<u8>> {
+    simple_frame().prop_recursive(MAX_DEPTH, 64, 8, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..8).prop_map(|items| aggregate('*', items.len(), items)),
+            vec(inner.clone(), 0..8).prop_map(|items| aggregate('~', items.len(), items)),
+            vec(inner.clone(), 0..8).prop_map(|items| aggregate('>', items.len(), items)),
+            vec((inner.clone(), inner), 0..4).prop_map(|entries| {
+                let len = entries.len();
+                let items = entries.into_iter().flat_map(|(k, v)| [k, v]).collect();
+                aggregate('%', len, items)
+            }),
+        ]
+    })
+}
+
+/// Decode with each decoder, with the bytes each consumed.
+fn decode_v1(data: &[u8]) -> Result<(RespFrame, usize), RespError> {
+    let mut buf = BytesMut::from(data);
+    <RespFrame as RespDecode>::decode(&mut buf).map(|frame| (frame, data.len() - buf.len()))
+}
+
+fn decode_v2(data: &[u8]) -> Result<(RespFrame, usize), RespError> {
+    let mut buf = BytesMut::from(data);
+    <RespFrame as RespDecodeV2>::decode(&mut buf).map(|frame| (frame, data.len() - buf.len()))
+}
+
+proptest! {
+    #[test]
+    fn test_decoders_agree(data in frame()) {
+        let v1 = decode_v1(&data);
+        prop_assert!(v1.is_ok(), "v1 fails on {:?}: {:?}", data.escape_ascii().to_string(), v1);
+        prop_assert_eq!(&v1, &decode_v2(&data), "on {:?}", data.escape_ascii().to_string());
+        prop_assert_eq!(v1.unwrap().1, data.len());
+    }
+
+    #[test]
+    fn test_decoders_agree_on_consumed_length(data in frame(), next in frame()) {
+        let mut buf = data.clone();
+        buf.extend_from_slice(&next);
+        let v2 = decode_v2(&buf);
+        prop_assert_eq!(&decode_v1(&buf), &v2, "on {:?}", buf.escape_ascii().to_string());
+        prop_assert_eq!(v2.unwrap().1, data.len());
+    }
+
+    #[test]
+    fn test_decoders_wait_for_truncated_frames(data in frame(), cut in any::<prop::sample::Index>()) {
+        let data = &data[..cut.index(data.len())];
+        let escaped = data.escape_ascii().to_string();
+        prop_assert!(
+            matches!(decode_v1(data), Err(RespError::NotCompleted(_))),
+            "v1 on {:?}: {:?}", escaped, decode_v1(data)
+        );
+        prop_assert!(
+            matches!(decode_v2(data), Err(RespError::NotCompleted(_))),
+            "v2 on {:?}: {:?}", escaped, decode_v2(data)
+        );
+    }
+}
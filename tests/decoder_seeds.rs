@@ -0,0 +1,83 @@
+//! The seeds of the decoder fuzz targets in `fuzz/seeds/`, checked against both decoders.
+//!
+//! Every seed in `malformed/` must be refused by the v1 and v2 decoders, without
+//! panicking, and every seed in `valid/` must decode to the same frame with both,
+//! consuming the whole seed.
+
+use std::{fs, path::Path};
+
+use bytes::BytesMut;
+use rredis::{
+    err::RespError, parse_frame, parse_frame_length, RespDecode, RespDecodeV2, RespEncode,
+    RespFrame,
+};
+
+fn seeds(dir: &str) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/seeds")
+        .join(dir);
+    let mut seeds = fs::read_dir(dir)?
+        .map(|entry| {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            Ok((name.into_owned(), fs::read(&path)?))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    seeds.sort();
+    Ok(seeds)
+}
+
+/// Decode the seed with each decoder, with the bytes each consumed.
+fn decode_v1(data: &[u8]) -> Result<(RespFrame, usize), RespError> {
+    let mut buf = BytesMut::from(data);
+    <RespFrame as RespDecode>::decode(&mut buf).map(|frame| (frame, data.len() - buf.len()))
+}
+
+fn decode_v2(data: &[u8]) -> Result<(RespFrame, usize), RespError> {
+    let mut buf = BytesMut::from(data);
+    <RespFrame as RespDecodeV2>::decode(&mut buf).map(|frame| (frame, data.len() - buf.len()))
+}
+
+#[test]
+fn test_malformed_seeds_are_refused() -> anyhow::Result<()> {
+    let seeds = seeds("malformed")?;
+    assert!(!seeds.is_empty());
+    for (name, data) in seeds {
+        assert!(decode_v1(&data).is_err(), "v1 decodes {}", name);
+        assert!(decode_v2(&data).is_err(), "v2 decodes {}", name);
+        assert!(
+            parse_frame(&mut &data[..]).is_err(),
+            "parse_frame parses {}",
+            name
+        );
+        // the length parser doesn't check the content of the frame, only its bounds.
+        if let Ok(len) = parse_frame_length(&data) {
+            assert!(
+                len <= data.len(),
+                "{}: length {} beyond the seed",
+                name,
+                len
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_valid_seeds_decode_alike() -> anyhow::Result<()> {
+    let seeds = seeds("valid")?;
+    assert!(!seeds.is_empty());
+    for (name, data) in seeds {
+        let (v1, consumed) = decode_v1(&data).map_err(|e| anyhow::anyhow!("v1 {}: {}", name, e))?;
+        assert_eq!(consumed, data.len(), "v1 {}", name);
+        let (v2, consumed) = decode_v2(&data).map_err(|e| anyhow::anyhow!("v2 {}: {}", name, e))?;
+        assert_eq!(consumed, data.len(), "v2 {}", name);
+        assert_eq!(parse_frame_length(&data), Ok(data.len()), "{}", name);
+        // compare the encodings, NaN doubles aren't equal to themselves.
+        let encoded = v1.encode();
+        assert_eq!(encoded, v2.encode(), "{}", name);
+        let (again, _) = decode_v2(&encoded)?;
+        assert_eq!(again.encode(), encoded, "{}", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,323 @@
+mod common;
+
+use std::time::Duration;
+
+use redis::{AsyncCommands, ErrorKind};
+use rredis::{Server, ServerConfig};
+use tokio::{io::AsyncReadExt, net::TcpStream};
+
+use common::TestServer;
+
+#[tokio::test]
+async fn test_set_and_get() -> anyhow::Result<()> {
+    let server = TestServer::start().await?;
+    let mut conn = server.connect().await?;
+
+    let _: () = conn.set("key", "value").await?;
+    let value: String = conn.get("key").await?;
+    assert_eq!(value, "value");
+    let missing: Option<String> = conn.get("missing").await?;
+    assert_eq!(missing, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_large_values() -> anyhow::Result<()> {
+    let server = TestServer::start().await?;
+    let mut conn = server.connect().await?;
+
+    // read as they arrive, followed by other commands in the same pipeline.
+    let value: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let (_, _, small): ((), (), String) = redis::pipe()
+        .set("large", &value)
+        .set("small", "value")
+        .get("small")
+        .query_async(&mut conn)
+        .await?;
+    assert_eq!(small, "value");
+    let stored: Vec<u8> = conn.get("large").await?;
+    assert_eq!(stored, value);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fragmented_replies() -> anyhow::Result<()> {
+    let server = TestServer::start().await?;
+    let mut conn = server.connect().await?;
+
+    let _: () = redis::cmd("DEBUG")
+        .arg("REPLY-FRAGMENT")
+        .arg(2)
+        .query_async(&mut conn)
+        .await?;
+    let _: () = redis::cmd("DEBUG")
+        .arg("REPLY-DELAY")
+        .arg(1)
+        .query_async(&mut conn)
+        .await?;
+    let _: () = conn.set("key", "a value split across writes").await?;
+    let value: String = conn.get("key").await?;
+    assert_eq!(value, "a value split across writes");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_renamed_commands() -> anyhow::Result<()> {
+    let builder = Server::builder()
+        .rename_command("set", "store")
+        .rename_command("debug", "");
+    let server = TestServer::build(builder).await?;
+    let mut conn = server.connect().await?;
+
+    let _: () = redis::cmd("STORE")
+        .arg("key")
+        .arg("value")
+        .query_async(&mut conn)
+        .await?;
+    let value: String = conn.get("key").await?;
+    assert_eq!(value, "value");
+
+    let err = conn.set::<_, _, ()>("key", "other").await.unwrap_err();
+    assert_eq!(err.code(), Some("ERR"));
+    assert!(err.to_string().contains("unknown command 'SET'"));
+    let err = redis::cmd("DEBUG")
+        .arg("RELOAD")
+        .query_async::<()>(&mut conn)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("unknown command 'DEBUG'"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_clients() -> anyhow::Result<()> {
+    let config = ServerConfig {
+        max_clients: Some(1),
+        ..Default::default()
+    };
+    let server = TestServer::start_with(config).await?;
+    let mut conn = server.connect().await?;
+    let _: () = conn.set("key", "value").await?;
+
+    let mut refused = TcpStream::connect(server.addr()).await?;
+    let mut reply = String::new();
+    refused.read_to_string(&mut reply).await?;
+    assert_eq!(reply, "-ERR max number of clients reached\r\n");
+
+    let info: String = redis::cmd("INFO")
+        .arg("stats")
+        .query_async(&mut conn)
+        .await?;
+    assert!(info.contains("\r\nrejected_connections:1\r\n"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_memory() -> anyhow::Result<()> {
+    let config = ServerConfig {
+        max_memory: Some(1024),
+        ..Default::default()
+    };
+    let server = TestServer::start_with(config).await?;
+    let mut conn = server.connect().await?;
+    let _: () = conn.set("key", "x".repeat(4096)).await?;
+    // the used memory is measured again once the last measure is stale.
+    tokio::time::sleep(Duration::from_millis(150)).await;
+
+    let err = conn.set::<_, _, ()>("other", "value").await.unwrap_err();
+    assert_eq!(err.code(), Some("OOM"));
+    // commands which can't grow the dataset still run.
+    let value: String = conn.get_del("key").await?;
+    assert_eq!(value.len(), 4096);
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    let _: () = conn.set("other", "value").await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connections_share_the_keyspace() -> anyhow::Result<()> {
+    let server = TestServer::start().await?;
+
+    let mut tasks = Vec::new();
+    for _ in 0..8 {
+        let mut conn = server.connect().await?;
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..50 {
+                let _: i64 = redis::cmd("INCR")
+                    .arg("counter")
+                    .query_async(&mut conn)
+                    .await?;
+            }
+            Ok::<_, redis::RedisError>(())
+        }));
+    }
+    for task in tasks {
+        task.await??;
+    }
+
+    let mut conn = server.connect().await?;
+    let counter: i64 = conn.get("counter").await?;
+    assert_eq!(counter, 400);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pipelining() -> anyhow::Result<()> {
+    let server = TestServer::start().await?;
+    let mut conn = server.connect().await?;
+
+    let (a, b, counter, field): (String, String, i64, String) = redis::pipe()
+        .set("a", "1")
+        .ignore()
+        .set("b", "2")
+        .ignore()
+        .get("a")
+        .get("b")
+        .cmd("INCR")
+        .arg("counter")
+        .hset("hash", "field", "value")
+        .ignore()
+        .hget("hash", "field")
+        .query_async(&mut conn)
+        .await?;
+    assert_eq!((a.as_str(), b.as_str(), counter), ("1", "2", 1));
+    assert_eq!(field, "value");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_errors_do_not_break_the_connection() -> anyhow::Result<()> {
+    let server = TestServer::start().await?;
+    let mut conn = server.connect().await?;
+
+    let err = redis::cmd("NOSUCHCOMMAND")
+        .query_async::<()>(&mut conn)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ResponseError);
+    assert!(err.to_string().contains("unknown command"));
+
+    let err = redis::cmd("GET")
+        .query_async::<()>(&mut conn)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("wrong number of arguments"));
+
+    let _: () = conn.set("key", "not a number").await?;
+    let err = redis::cmd("INCR")
+        .arg("key")
+        .query_async::<i64>(&mut conn)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ResponseError);
+
+    // the connection is still usable.
+    let _: () = conn.set("key", "value").await?;
+    let value: String = conn.get("key").await?;
+    assert_eq!(value, "value");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transactions() -> anyhow::Result<()> {
+    let server = TestServer::start().await?;
+    let mut conn = server.connect().await?;
+    let mut other = server.connect().await?;
+
+    let (a, b): (String, i64) = redis::pipe()
+        .atomic()
+        .set("a", "1")
+        .ignore()
+        .get("a")
+        .cmd("INCR")
+        .arg("b")
+        .query_async(&mut conn)
+        .await?;
+    assert_eq!((a.as_str(), b), ("1", 1));
+
+    // a watched key modified by another connection fails the transaction.
+    redis::cmd("WATCH")
+        .arg("a")
+        .query_async::<()>(&mut conn)
+        .await?;
+    let _: () = other.set("a", "2").await?;
+    let res: Option<(String,)> = redis::pipe()
+        .atomic()
+        .set("a", "3")
+        .ignore()
+        .get("a")
+        .query_async(&mut conn)
+        .await?;
+    assert_eq!(res, None);
+    let a: String = conn.get("a").await?;
+    assert_eq!(a, "2");
+
+    // EXEC forgot the watched key.
+    let _: () = other.set("a", "4").await?;
+    let res: Option<(String,)> = redis::pipe()
+        .atomic()
+        .get("a")
+        .query_async(&mut conn)
+        .await?;
+    assert_eq!(res, Some(("4".to_string(),)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_blocking_pops() -> anyhow::Result<()> {
+    let server = TestServer::start().await?;
+    let mut conn = server.connect().await?;
+    let mut other = server.connect().await?;
+
+    // a write to another key doesn't wake the client up.
+    let blocked = tokio::spawn(async move {
+        redis::cmd("BLMPOP")
+            .arg(0)
+            .arg(2)
+            .arg("a")
+            .arg("b")
+            .arg("LEFT")
+            .query_async::<Option<(String, Vec<String>)>>(&mut conn)
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let _: () = other.set("c", "v").await?;
+    let _: i64 = other.rpush("b", "x").await?;
+    let popped = blocked.await??;
+    assert_eq!(popped, Some(("b".to_string(), vec!["x".to_string()])));
+
+    // the null is replied once the timeout elapsed.
+    let popped: Option<(String, Vec<(String, f64)>)> = redis::cmd("BZMPOP")
+        .arg(0.05)
+        .arg(1)
+        .arg("z")
+        .arg("MIN")
+        .query_async(&mut other)
+        .await?;
+    assert_eq!(popped, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_big_keys() -> anyhow::Result<()> {
+    let server = TestServer::start().await?;
+    let mut conn = server.connect().await?;
+    let _: () = conn.set("small", "value").await?;
+    let _: () = conn.set("large", "x".repeat(64 * 1024)).await?;
+
+    let addr = server.addr().to_string();
+    let output = tokio::task::spawn_blocking(move || {
+        std::process::Command::new(env!("CARGO_BIN_EXE_rredis-check"))
+            .args(["--bigkeys", &addr])
+            .output()
+    })
+    .await??;
+    assert!(output.status.success());
+    let report = String::from_utf8(output.stdout)?;
+    assert!(report.contains(": 2 keys"));
+    // the largest key comes first.
+    let large = report.find("large").unwrap();
+    let small = report.find("small").unwrap();
+    assert!(large < small);
+    Ok(())
+}